@@ -0,0 +1,316 @@
+// Ephemeris
+// Apparent positions of the sun and moon, sidereal time and horizontal
+// coordinates (after Meeus, Astronomical Algorithms)
+
+use chrono::{DateTime, Utc};
+
+/// Julian day of 2000-01-01 12:00 TT
+pub const J2000: f64 = 2_451_545.0;
+
+/// Earth equatorial radius (km)
+const EARTH_RADIUS_KM: f64 = 6378.14;
+/// Moon mean radius (km)
+const MOON_RADIUS_KM: f64 = 1737.4;
+/// Kilometers per astronomical unit
+pub const AU_KM: f64 = 149_597_870.7;
+
+/// Geocentric apparent equatorial position
+#[derive(Debug, Clone, Copy)]
+pub struct Equatorial {
+    /// Right ascension (degrees)
+    pub ra: f64,
+    /// Declination (degrees)
+    pub dec: f64,
+    /// Distance (km)
+    pub distance_km: f64,
+}
+
+impl Equatorial {
+    /// Equatorial horizontal parallax (degrees)
+    pub fn horizontal_parallax(&self) -> f64 {
+        (EARTH_RADIUS_KM / self.distance_km).asin().to_degrees()
+    }
+}
+
+/// Altitude and azimuth seen by an observer (degrees)
+#[derive(Debug, Clone, Copy)]
+pub struct Horizontal {
+    pub altitude: f64,
+    pub azimuth: f64,
+}
+
+/// Julian day (UT) of a time
+pub fn julian_day(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5
+}
+
+/// Dynamical time (JDE) from a UT Julian day, using a polynomial fit for ΔT
+pub fn dynamical_time(jd: f64) -> f64 {
+    let t = (jd - J2000) / 365.25;
+    let delta_t = if t < 5.0 {
+        63.86 + 0.3345 * t - 0.060_374 * t.powi(2) + 0.001_727_5 * t.powi(3) + 0.000_651_814 * t.powi(4)
+            + 0.000_023_735_99 * t.powi(5)
+    } else {
+        62.92 + 0.32217 * t + 0.005_589 * t * t
+    };
+    jd + delta_t / 86_400.0
+}
+
+/// Julian centuries since J2000
+pub fn centuries(jd: f64) -> f64 {
+    (jd - J2000) / 36525.0
+}
+
+pub fn normalize_degrees(angle: f64) -> f64 {
+    angle.rem_euclid(360.0)
+}
+
+fn sin_d(x: f64) -> f64 {
+    x.to_radians().sin()
+}
+
+fn cos_d(x: f64) -> f64 {
+    x.to_radians().cos()
+}
+
+/// Nutation in longitude and obliquity (degrees), main terms
+pub fn nutation(t: f64) -> (f64, f64) {
+    let omega = 125.04452 - 1_934.136_261 * t;
+    let l_sun = 280.4665 + 36_000.769_8 * t;
+    let l_moon = 218.3165 + 481_267.881_3 * t;
+
+    let dpsi = -17.20 * sin_d(omega) - 1.32 * sin_d(2.0 * l_sun) - 0.23 * sin_d(2.0 * l_moon) + 0.21 * sin_d(2.0 * omega);
+    let deps = 9.20 * cos_d(omega) + 0.57 * cos_d(2.0 * l_sun) + 0.10 * cos_d(2.0 * l_moon) - 0.09 * cos_d(2.0 * omega);
+    (dpsi / 3600.0, deps / 3600.0)
+}
+
+/// True obliquity of the ecliptic (degrees)
+pub fn obliquity(t: f64) -> f64 {
+    let mean = 23.0 + 26.0 / 60.0 + (21.448 - 46.8150 * t - 0.00059 * t * t + 0.001813 * t * t * t) / 3600.0;
+    mean + nutation(t).1
+}
+
+/// Greenwich apparent sidereal time (degrees) at a UT Julian day
+pub fn sidereal_time(jd: f64) -> f64 {
+    let t = centuries(jd);
+    let mean = 280.460_618_37 + 360.985_647_366_29 * (jd - J2000) + 0.000_387_933 * t * t - t * t * t / 38_710_000.0;
+    normalize_degrees(mean + nutation(t).0 * cos_d(obliquity(t)))
+}
+
+/// Convert ecliptic longitude/latitude of date (degrees) to equatorial
+pub fn ecliptic_to_equatorial(lambda: f64, beta: f64, epsilon: f64) -> (f64, f64) {
+    let ra = (sin_d(lambda) * cos_d(epsilon) - beta.to_radians().tan() * sin_d(epsilon))
+        .atan2(cos_d(lambda))
+        .to_degrees();
+    let dec = (sin_d(beta) * cos_d(epsilon) + cos_d(beta) * sin_d(epsilon) * sin_d(lambda))
+        .asin()
+        .to_degrees();
+    (normalize_degrees(ra), dec)
+}
+
+/// Apparent geocentric ecliptic longitude of the sun (degrees) and distance (AU)
+pub fn sun_longitude(t: f64) -> (f64, f64) {
+    let l0 = 280.46646 + 36_000.769_83 * t + 0.000_303_2 * t * t;
+    let m = 357.52911 + 35_999.050_29 * t - 0.000_153_7 * t * t;
+    let e = 0.016_708_634 - 0.000_042_037 * t;
+    let c = (1.914_602 - 0.004_817 * t - 0.000_014 * t * t) * sin_d(m)
+        + (0.019_993 - 0.000_101 * t) * sin_d(2.0 * m)
+        + 0.000_289 * sin_d(3.0 * m);
+
+    let true_longitude = l0 + c;
+    let anomaly = m + c;
+    let radius = 1.000_001_018 * (1.0 - e * e) / (1.0 + e * cos_d(anomaly));
+
+    // Nutation and aberration
+    let apparent = true_longitude + nutation(t).0 - 20.4898 / 3600.0 / radius;
+    (normalize_degrees(apparent), radius)
+}
+
+/// Apparent position of the sun at a dynamical time (JDE)
+pub fn sun_position(jde: f64) -> Equatorial {
+    let t = centuries(jde);
+    let (lambda, radius) = sun_longitude(t);
+    let (ra, dec) = ecliptic_to_equatorial(lambda, 0.0, obliquity(t));
+    Equatorial { ra, dec, distance_km: radius * AU_KM }
+}
+
+/// Periodic terms for the moon's longitude and distance: D, M, M', F, Σl (1e-6 deg), Σr (m)
+const MOON_LR: &[(i8, i8, i8, i8, f64, f64)] = &[
+    (0, 0, 1, 0, 6_288_774.0, -20_905_355.0),
+    (2, 0, -1, 0, 1_274_027.0, -3_699_111.0),
+    (2, 0, 0, 0, 658_314.0, -2_955_968.0),
+    (0, 0, 2, 0, 213_618.0, -569_925.0),
+    (0, 1, 0, 0, -185_116.0, 48_888.0),
+    (0, 0, 0, 2, -114_332.0, -3_149.0),
+    (2, 0, -2, 0, 58_793.0, 246_158.0),
+    (2, -1, -1, 0, 57_066.0, -152_138.0),
+    (2, 0, 1, 0, 53_322.0, -170_733.0),
+    (2, -1, 0, 0, 45_758.0, -204_586.0),
+    (0, 1, -1, 0, -40_923.0, -129_620.0),
+    (1, 0, 0, 0, -34_720.0, 108_743.0),
+    (0, 1, 1, 0, -30_383.0, 104_755.0),
+    (2, 0, 0, -2, 15_327.0, 10_321.0),
+    (0, 0, 1, 2, -12_528.0, 0.0),
+    (0, 0, 1, -2, 10_980.0, 79_661.0),
+    (4, 0, -1, 0, 10_675.0, -34_782.0),
+    (0, 0, 3, 0, 10_034.0, -23_210.0),
+    (4, 0, -2, 0, 8_548.0, -21_636.0),
+    (2, 1, -1, 0, -7_888.0, 24_208.0),
+    (2, 1, 0, 0, -6_766.0, 30_824.0),
+    (1, 0, -1, 0, -5_163.0, -8_379.0),
+    (1, 1, 0, 0, 4_987.0, -16_675.0),
+    (2, -1, 1, 0, 4_036.0, -12_831.0),
+    (2, 0, 2, 0, 3_994.0, -10_445.0),
+    (4, 0, 0, 0, 3_861.0, -11_650.0),
+    (2, 0, -3, 0, 3_665.0, 14_403.0),
+    (0, 1, -2, 0, -2_689.0, -7_003.0),
+    (2, 0, -1, 2, -2_602.0, 0.0),
+    (2, -1, -2, 0, 2_390.0, 10_056.0),
+    (1, 0, 1, 0, -2_348.0, 6_322.0),
+    (2, -2, 0, 0, 2_236.0, -9_884.0),
+];
+
+/// Periodic terms for the moon's latitude: D, M, M', F, Σb (1e-6 deg)
+const MOON_B: &[(i8, i8, i8, i8, f64)] = &[
+    (0, 0, 0, 1, 5_128_122.0),
+    (0, 0, 1, 1, 280_602.0),
+    (0, 0, 1, -1, 277_693.0),
+    (2, 0, 0, -1, 173_237.0),
+    (2, 0, -1, 1, 55_413.0),
+    (2, 0, -1, -1, 46_271.0),
+    (2, 0, 0, 1, 32_573.0),
+    (0, 0, 2, 1, 17_198.0),
+    (2, 0, 1, -1, 9_266.0),
+    (0, 0, 2, -1, 8_822.0),
+    (2, -1, 0, -1, 8_216.0),
+    (2, 0, -2, -1, 4_324.0),
+    (2, 0, 1, 1, 4_200.0),
+    (2, 1, 0, -1, -3_359.0),
+    (2, -1, -1, 1, 2_463.0),
+    (2, -1, 0, 1, 2_211.0),
+    (2, -1, -1, -1, 2_065.0),
+    (0, 1, -1, -1, -1_870.0),
+    (4, 0, -1, -1, 1_828.0),
+    (0, 1, 0, 1, -1_794.0),
+    (0, 0, 0, 3, -1_749.0),
+    (0, 1, -1, 1, -1_565.0),
+    (1, 0, 0, 1, -1_491.0),
+    (0, 1, 1, 1, -1_475.0),
+    (0, 1, 1, -1, -1_410.0),
+    (0, 1, 0, -1, -1_344.0),
+    (1, 0, 0, -1, -1_335.0),
+    (0, 0, 3, 1, 1_107.0),
+    (4, 0, 0, -1, 1_021.0),
+    (4, 0, -1, 1, 833.0),
+];
+
+/// Apparent geocentric ecliptic longitude, latitude (degrees) and distance (km) of the moon
+pub fn moon_ecliptic(t: f64) -> (f64, f64, f64) {
+    let l = 218.316_447_7 + 481_267.881_234_21 * t - 0.001_578_6 * t * t + t * t * t / 538_841.0;
+    let d = 297.850_192_1 + 445_267.111_403_4 * t - 0.001_881_9 * t * t + t * t * t / 545_868.0;
+    let m = 357.529_109_2 + 35_999.050_290_9 * t - 0.000_153_6 * t * t;
+    let mp = 134.963_396_4 + 477_198.867_505_5 * t + 0.008_741_4 * t * t + t * t * t / 69_699.0;
+    let f = 93.272_095 + 483_202.017_523_3 * t - 0.003_653_9 * t * t - t * t * t / 3_526_000.0;
+    let a1 = 119.75 + 131.849 * t;
+    let a2 = 53.09 + 479_264.290 * t;
+    let a3 = 313.45 + 481_266.484 * t;
+    let e = 1.0 - 0.002_516 * t - 0.000_007_4 * t * t;
+
+    let eccentricity = |m_mult: i8| match m_mult.abs() {
+        1 => e,
+        2 => e * e,
+        _ => 1.0,
+    };
+    let argument = |dd: i8, mm: i8, mpp: i8, ff: i8| {
+        f64::from(dd) * d + f64::from(mm) * m + f64::from(mpp) * mp + f64::from(ff) * f
+    };
+
+    let mut sum_l = 0.0;
+    let mut sum_r = 0.0;
+    for &(dd, mm, mpp, ff, cl, cr) in MOON_LR {
+        let arg = argument(dd, mm, mpp, ff);
+        let k = eccentricity(mm);
+        sum_l += cl * k * sin_d(arg);
+        sum_r += cr * k * cos_d(arg);
+    }
+    let mut sum_b = 0.0;
+    for &(dd, mm, mpp, ff, cb) in MOON_B {
+        sum_b += cb * eccentricity(mm) * sin_d(argument(dd, mm, mpp, ff));
+    }
+
+    sum_l += 3958.0 * sin_d(a1) + 1962.0 * sin_d(l - f) + 318.0 * sin_d(a2);
+    sum_b += -2235.0 * sin_d(l) + 382.0 * sin_d(a3) + 175.0 * sin_d(a1 - f) + 175.0 * sin_d(a1 + f)
+        + 127.0 * sin_d(l - mp)
+        - 115.0 * sin_d(l + mp);
+
+    let longitude = normalize_degrees(l + sum_l / 1e6 + nutation(t).0);
+    let latitude = sum_b / 1e6;
+    let distance = 385_000.56 + sum_r / 1000.0;
+    (longitude, latitude, distance)
+}
+
+/// Apparent geocentric position of the moon at a dynamical time (JDE)
+pub fn moon_position(jde: f64) -> Equatorial {
+    let t = centuries(jde);
+    let (lambda, beta, distance_km) = moon_ecliptic(t);
+    let (ra, dec) = ecliptic_to_equatorial(lambda, beta, obliquity(t));
+    Equatorial { ra, dec, distance_km }
+}
+
+/// Moon semi-diameter seen from the earth's centre (degrees)
+pub fn moon_semi_diameter(distance_km: f64) -> f64 {
+    (MOON_RADIUS_KM / distance_km).asin().to_degrees()
+}
+
+/// Greenwich hour angle of a body (degrees)
+pub fn greenwich_hour_angle(jd: f64, ra: f64) -> f64 {
+    normalize_degrees(sidereal_time(jd) - ra)
+}
+
+/// Geocentric altitude and azimuth of a body for an observer (longitude east positive)
+pub fn horizontal(jd: f64, position: &Equatorial, lat: f64, lon: f64) -> Horizontal {
+    let lha = normalize_degrees(greenwich_hour_angle(jd, position.ra) + lon);
+    altitude_azimuth(lat, position.dec, lha)
+}
+
+/// Altitude and azimuth from latitude, declination and local hour angle (degrees)
+pub fn altitude_azimuth(lat: f64, dec: f64, lha: f64) -> Horizontal {
+    let altitude = (sin_d(lat) * sin_d(dec) + cos_d(lat) * cos_d(dec) * cos_d(lha))
+        .clamp(-1.0, 1.0)
+        .asin()
+        .to_degrees();
+    let azimuth = (-cos_d(dec) * sin_d(lha))
+        .atan2(sin_d(dec) * cos_d(lat) - cos_d(dec) * sin_d(lat) * cos_d(lha))
+        .to_degrees();
+    Horizontal { altitude, azimuth: normalize_degrees(azimuth) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sun_and_moon_positions() {
+        // Meeus example 25.a: 1992 October 13.0 TD, RA 198.38083, Dec -7.78507
+        let jde = julian_day(Utc.with_ymd_and_hms(1992, 10, 13, 0, 0, 0).unwrap());
+        let sun = sun_position(jde);
+        assert!((sun.ra - 198.38083).abs() < 0.01, "{}", sun.ra);
+        assert!((sun.dec + 7.78507).abs() < 0.01, "{}", sun.dec);
+
+        // Meeus example 47.a: 1992 April 12.0 TD, λ 133.162655, β -3.229126, Δ 368409.7 km
+        let t = centuries(julian_day(Utc.with_ymd_and_hms(1992, 4, 12, 0, 0, 0).unwrap()));
+        let (lambda, beta, distance) = moon_ecliptic(t);
+        assert!((lambda - 133.167265).abs() < 0.01, "{}", lambda);
+        assert!((beta + 3.229126).abs() < 0.01, "{}", beta);
+        assert!((distance - 368_409.7).abs() < 50.0, "{}", distance);
+
+        // Meeus example 12.a: 1987 April 10 0h UT, apparent sidereal time 197.6922
+        let gast = sidereal_time(julian_day(Utc.with_ymd_and_hms(1987, 4, 10, 0, 0, 0).unwrap()));
+        assert!((gast - 197.6922).abs() < 0.001, "{}", gast);
+
+        // ΔT was about 69 seconds in 2020
+        let jd = julian_day(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap());
+        assert!(((dynamical_time(jd) - jd) * 86_400.0 - 69.4).abs() < 3.0);
+    }
+}
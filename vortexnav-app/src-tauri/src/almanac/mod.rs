@@ -0,0 +1,345 @@
+// Almanac Module
+// Sunrise/sunset, twilight, moonrise/moonset and moon phase for any position and date
+
+pub mod ephemeris;
+mod route;
+
+pub use route::*;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use ephemeris::{dynamical_time, horizontal, julian_day, moon_ecliptic, moon_position, sun_longitude, sun_position, Horizontal};
+
+/// Sun altitude at rise/set: refraction (34') plus semi-diameter (16')
+const SUNRISE_ALTITUDE: f64 = -0.8333;
+const CIVIL_TWILIGHT: f64 = -6.0;
+const NAUTICAL_TWILIGHT: f64 = -12.0;
+const ASTRONOMICAL_TWILIGHT: f64 = -18.0;
+/// Mean synodic month (days)
+const SYNODIC_MONTH: f64 = 29.530_588;
+/// Sampling step when searching for rise/set times (minutes)
+const SEARCH_STEP_MINUTES: i64 = 10;
+
+/// Natural light by sun altitude
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightCondition {
+    Day,
+    CivilTwilight,
+    NauticalTwilight,
+    AstronomicalTwilight,
+    Night,
+}
+
+impl LightCondition {
+    pub fn from_sun_altitude(altitude: f64) -> Self {
+        if altitude >= SUNRISE_ALTITUDE {
+            LightCondition::Day
+        } else if altitude >= CIVIL_TWILIGHT {
+            LightCondition::CivilTwilight
+        } else if altitude >= NAUTICAL_TWILIGHT {
+            LightCondition::NauticalTwilight
+        } else if altitude >= ASTRONOMICAL_TWILIGHT {
+            LightCondition::AstronomicalTwilight
+        } else {
+            LightCondition::Night
+        }
+    }
+
+    /// Dark for navigation purposes: the sun is below civil twilight
+    pub fn is_dark(&self) -> bool {
+        !matches!(self, LightCondition::Day | LightCondition::CivilTwilight)
+    }
+}
+
+/// Moon phase at a time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoonPhase {
+    /// Illuminated fraction of the disc (0-1)
+    pub illumination: f64,
+    /// Days since new moon
+    pub age_days: f64,
+    pub waxing: bool,
+    pub name: String,
+}
+
+/// Sun and moon events for one local day at a position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlmanacDay {
+    pub date: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub utc_offset_minutes: i32,
+    pub astronomical_dawn: Option<String>,
+    pub nautical_dawn: Option<String>,
+    pub civil_dawn: Option<String>,
+    pub sunrise: Option<String>,
+    pub solar_noon: Option<String>,
+    pub sunset: Option<String>,
+    pub civil_dusk: Option<String>,
+    pub nautical_dusk: Option<String>,
+    pub astronomical_dusk: Option<String>,
+    pub day_length_hours: f64,
+    pub sun_always_up: bool,
+    pub sun_always_down: bool,
+    pub moonrise: Option<String>,
+    pub moonset: Option<String>,
+    /// Phase at local noon
+    pub moon_phase: MoonPhase,
+}
+
+/// Sun and moon at a position and time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkyConditions {
+    pub time: String,
+    pub sun_altitude: f64,
+    pub sun_azimuth: f64,
+    pub moon_altitude: f64,
+    pub moon_azimuth: f64,
+    pub moon_phase: MoonPhase,
+    pub light: LightCondition,
+}
+
+/// Altitude and azimuth of the sun's centre
+pub fn sun_horizontal(lat: f64, lon: f64, time: DateTime<Utc>) -> Horizontal {
+    let jd = julian_day(time);
+    horizontal(jd, &sun_position(dynamical_time(jd)), lat, lon)
+}
+
+/// Geocentric altitude and azimuth of the moon's centre, with the altitude of the
+/// moon's centre at rise/set (allowing for parallax, semi-diameter and refraction)
+fn moon_horizontal(lat: f64, lon: f64, time: DateTime<Utc>) -> (Horizontal, f64) {
+    let jd = julian_day(time);
+    let moon = moon_position(dynamical_time(jd));
+    let rise_altitude = 0.7275 * moon.horizontal_parallax() - 0.5667;
+    (horizontal(jd, &moon, lat, lon), rise_altitude)
+}
+
+pub fn light_condition(lat: f64, lon: f64, time: DateTime<Utc>) -> LightCondition {
+    LightCondition::from_sun_altitude(sun_horizontal(lat, lon, time).altitude)
+}
+
+/// Moon phase from the elongation of the moon from the sun
+pub fn moon_phase(time: DateTime<Utc>) -> MoonPhase {
+    let t = ephemeris::centuries(dynamical_time(julian_day(time)));
+    let (moon_lon, moon_lat, _) = moon_ecliptic(t);
+    let (sun_lon, _) = sun_longitude(t);
+
+    let elongation_lon = (moon_lon - sun_lon).rem_euclid(360.0);
+    let elongation = (moon_lat.to_radians().cos() * elongation_lon.to_radians().cos()).acos();
+    let illumination = (1.0 - elongation.cos()) / 2.0;
+
+    let name = match elongation_lon {
+        a if !(22.5..337.5).contains(&a) => "New Moon",
+        a if a < 67.5 => "Waxing Crescent",
+        a if a < 112.5 => "First Quarter",
+        a if a < 157.5 => "Waxing Gibbous",
+        a if a < 202.5 => "Full Moon",
+        a if a < 247.5 => "Waning Gibbous",
+        a if a < 292.5 => "Last Quarter",
+        _ => "Waning Crescent",
+    };
+
+    MoonPhase {
+        illumination,
+        age_days: elongation_lon / 360.0 * SYNODIC_MONTH,
+        waxing: elongation_lon < 180.0,
+        name: name.to_string(),
+    }
+}
+
+/// Times between start and end when `f` crosses zero, with whether it was rising
+fn zero_crossings(f: impl Fn(DateTime<Utc>) -> f64, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(DateTime<Utc>, bool)> {
+    let step = Duration::minutes(SEARCH_STEP_MINUTES);
+    let mut crossings = Vec::new();
+
+    let mut t0 = start;
+    let mut v0 = f(t0);
+    while t0 < end {
+        let t1 = (t0 + step).min(end);
+        let v1 = f(t1);
+        if (v0 < 0.0) != (v1 < 0.0) {
+            // Bisect to the second
+            let (mut lo, mut hi) = (t0, t1);
+            while hi - lo > Duration::seconds(1) {
+                let mid = lo + (hi - lo) / 2;
+                if (f(mid) < 0.0) == (v0 < 0.0) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            crossings.push((lo, v1 > v0));
+        }
+        t0 = t1;
+        v0 = v1;
+    }
+
+    crossings
+}
+
+fn first_crossing(crossings: &[(DateTime<Utc>, bool)], rising: bool) -> Option<String> {
+    crossings.iter().find(|(_, r)| *r == rising).map(|(t, _)| t.to_rfc3339())
+}
+
+/// Hours between start and end with `f` above zero
+fn hours_above(f: impl Fn(DateTime<Utc>) -> f64, crossings: &[(DateTime<Utc>, bool)], start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    let mut above = f(start) >= 0.0;
+    let mut since = start;
+    let mut total = Duration::zero();
+    for (time, rising) in crossings {
+        if *rising {
+            since = *time;
+        } else if above {
+            total += *time - since;
+        }
+        above = *rising;
+    }
+    if above {
+        total += end - since;
+    }
+    total.num_seconds() as f64 / 3600.0
+}
+
+/// Almanac for a local day. The day runs midnight to midnight at `utc_offset_minutes`.
+pub fn almanac_day(lat: f64, lon: f64, date: NaiveDate, utc_offset_minutes: i32) -> AlmanacDay {
+    let start = date.and_hms_opt(0, 0, 0).unwrap().and_utc() - Duration::minutes(utc_offset_minutes as i64);
+    let end = start + Duration::days(1);
+
+    let sun_events = |altitude: f64| zero_crossings(|t| sun_horizontal(lat, lon, t).altitude - altitude, start, end);
+    let sun = sun_events(SUNRISE_ALTITUDE);
+    let civil = sun_events(CIVIL_TWILIGHT);
+    let nautical = sun_events(NAUTICAL_TWILIGHT);
+    let astronomical = sun_events(ASTRONOMICAL_TWILIGHT);
+
+    // Solar noon: highest sun of the day, refined with a parabola through the samples
+    let step = Duration::minutes(SEARCH_STEP_MINUTES);
+    let samples: Vec<(DateTime<Utc>, f64)> = std::iter::successors(Some(start), |t| Some(*t + step))
+        .take_while(|t| *t <= end)
+        .map(|t| (t, sun_horizontal(lat, lon, t).altitude))
+        .collect();
+    let peak = samples
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let solar_noon = if peak > 0 && peak + 1 < samples.len() {
+        let (h0, h1, h2) = (samples[peak - 1].1, samples[peak].1, samples[peak + 1].1);
+        let denom = h0 - 2.0 * h1 + h2;
+        let offset = if denom.abs() > 1e-12 { 0.5 * (h0 - h2) / denom } else { 0.0 };
+        Some((samples[peak].0 + Duration::seconds((offset * SEARCH_STEP_MINUTES as f64 * 60.0) as i64)).to_rfc3339())
+    } else {
+        None
+    };
+
+    let day_length_hours = hours_above(|t| sun_horizontal(lat, lon, t).altitude - SUNRISE_ALTITUDE, &sun, start, end);
+
+    let moon = zero_crossings(
+        |t| {
+            let (position, rise_altitude) = moon_horizontal(lat, lon, t);
+            position.altitude - rise_altitude
+        },
+        start,
+        end,
+    );
+
+    AlmanacDay {
+        date: date.format("%Y-%m-%d").to_string(),
+        lat,
+        lon,
+        utc_offset_minutes,
+        astronomical_dawn: first_crossing(&astronomical, true),
+        nautical_dawn: first_crossing(&nautical, true),
+        civil_dawn: first_crossing(&civil, true),
+        sunrise: first_crossing(&sun, true),
+        solar_noon,
+        sunset: first_crossing(&sun, false),
+        civil_dusk: first_crossing(&civil, false),
+        nautical_dusk: first_crossing(&nautical, false),
+        astronomical_dusk: first_crossing(&astronomical, false),
+        day_length_hours,
+        sun_always_up: sun.is_empty() && day_length_hours > 12.0,
+        sun_always_down: sun.is_empty() && day_length_hours < 12.0,
+        moonrise: first_crossing(&moon, true),
+        moonset: first_crossing(&moon, false),
+        moon_phase: moon_phase(start + Duration::hours(12)),
+    }
+}
+
+/// Default UTC offset for a longitude (nautical time zone)
+pub fn nautical_zone_offset_minutes(lon: f64) -> i32 {
+    (lon / 15.0).round() as i32 * 60
+}
+
+/// Sun, moon and light at a position and time
+pub fn sky_conditions(lat: f64, lon: f64, time: DateTime<Utc>) -> SkyConditions {
+    let sun = sun_horizontal(lat, lon, time);
+    let (moon, _) = moon_horizontal(lat, lon, time);
+    SkyConditions {
+        time: time.to_rfc3339(),
+        sun_altitude: sun.altitude,
+        sun_azimuth: sun.azimuth,
+        moon_altitude: moon.altitude,
+        moon_azimuth: moon.azimuth,
+        moon_phase: moon_phase(time),
+        light: LightCondition::from_sun_altitude(sun.altitude),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn parse(time: &Option<String>) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time.as_ref().unwrap()).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_auckland_almanac() {
+        // Auckland, 2024-12-21 (NZDT, UTC+13): sunrise about 05:58, sunset about 20:40
+        let date = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        let day = almanac_day(-36.85, 174.76, date, 13 * 60);
+
+        let sunrise = parse(&day.sunrise);
+        let sunset = parse(&day.sunset);
+        let expected_rise = Utc.with_ymd_and_hms(2024, 12, 20, 16, 58, 0).unwrap();
+        let expected_set = Utc.with_ymd_and_hms(2024, 12, 21, 7, 40, 0).unwrap();
+        assert!((sunrise - expected_rise).num_minutes().abs() <= 3, "{}", sunrise);
+        assert!((sunset - expected_set).num_minutes().abs() <= 3, "{}", sunset);
+        assert!((day.day_length_hours - 14.7).abs() < 0.1, "{}", day.day_length_hours);
+
+        // Twilight brackets sunrise in order
+        assert!(parse(&day.nautical_dawn) < parse(&day.civil_dawn));
+        assert!(parse(&day.civil_dawn) < sunrise);
+        assert!(parse(&day.civil_dusk) > sunset);
+        assert!(!day.sun_always_up && !day.sun_always_down);
+
+        assert_eq!(light_condition(-36.85, 174.76, expected_rise + Duration::hours(6)), LightCondition::Day);
+        assert!(light_condition(-36.85, 174.76, expected_set + Duration::hours(3)).is_dark());
+    }
+
+    #[test]
+    fn test_moon_phase_and_polar_day() {
+        // Full moon 2024-12-15 09:02 UTC, new moon 2024-12-30 22:27 UTC
+        let full = moon_phase(Utc.with_ymd_and_hms(2024, 12, 15, 9, 0, 0).unwrap());
+        assert_eq!(full.name, "Full Moon");
+        assert!(full.illumination > 0.99);
+        let new = moon_phase(Utc.with_ymd_and_hms(2024, 12, 30, 22, 0, 0).unwrap());
+        assert_eq!(new.name, "New Moon");
+        assert!(new.illumination < 0.01);
+        assert!(new.age_days < 0.5 || new.age_days > 29.0);
+
+        // Midsummer in Antarctica: the sun never sets
+        let day = almanac_day(-75.0, 0.0, NaiveDate::from_ymd_opt(2024, 12, 21).unwrap(), 0);
+        assert!(day.sun_always_up);
+        assert!(day.sunrise.is_none());
+        assert!((day.day_length_hours - 24.0).abs() < 1e-9);
+
+        // Moonrise and moonset are roughly half a day apart
+        let day = almanac_day(-36.85, 174.76, NaiveDate::from_ymd_opt(2024, 12, 10).unwrap(), 13 * 60);
+        assert!(day.moonrise.is_some() || day.moonset.is_some());
+    }
+}
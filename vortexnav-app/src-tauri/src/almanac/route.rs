@@ -0,0 +1,120 @@
+// Daylight Along a Route
+// Marks legs and arrivals of a route's ETA table that fall in darkness
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{light_condition, moon_phase, LightCondition};
+use crate::navigation::{time_after, RouteEtaPoint};
+
+/// Sampling step along each leg (hours)
+const SAMPLE_HOURS: f64 = 0.25;
+
+/// Light on one leg of a passage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLegDaylight {
+    pub leg_index: usize,
+    pub from_name: Option<String>,
+    pub to_name: Option<String>,
+    pub departure_eta: String,
+    pub arrival_eta: String,
+    /// Hours of the leg sailed with the sun below civil twilight
+    pub dark_hours: f64,
+    pub darkness_fraction: f64,
+    pub in_darkness: bool,
+    pub arrival_light: LightCondition,
+    pub arrival_in_darkness: bool,
+    /// Moon illumination in the middle of the leg (0-1)
+    pub moon_illumination: f64,
+}
+
+/// Position at a time along a run of ETA points, interpolated linearly
+fn position_at(points: &[RouteEtaPoint], hours: f64) -> (f64, f64) {
+    for pair in points.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if hours <= b.hours_from_start {
+            let span = b.hours_from_start - a.hours_from_start;
+            let t = if span > 0.0 { ((hours - a.hours_from_start) / span).clamp(0.0, 1.0) } else { 0.0 };
+            return (a.lat + (b.lat - a.lat) * t, a.lon + (b.lon - a.lon) * t);
+        }
+    }
+    points.last().map(|p| (p.lat, p.lon)).unwrap_or((0.0, 0.0))
+}
+
+/// Darkness on each leg of an ETA table. Legs run between waypoint rows
+/// (rows with a name); intermediate rows refine the track.
+pub fn route_daylight(points: &[RouteEtaPoint], departure: DateTime<Utc>) -> Vec<RouteLegDaylight> {
+    let waypoint_rows: Vec<usize> = points
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.name.is_some())
+        .map(|(i, _)| i)
+        .collect();
+
+    waypoint_rows
+        .windows(2)
+        .enumerate()
+        .map(|(leg_index, pair)| {
+            let leg = &points[pair[0]..=pair[1]];
+            let (from, to) = (&leg[0], &leg[leg.len() - 1]);
+            let duration = to.hours_from_start - from.hours_from_start;
+
+            let steps = (duration / SAMPLE_HOURS).ceil().max(1.0) as usize;
+            let step = duration / steps as f64;
+            let mut dark_hours = 0.0;
+            for k in 0..steps {
+                // Midpoint of each sample interval
+                let hours = from.hours_from_start + (k as f64 + 0.5) * step;
+                let (lat, lon) = position_at(leg, hours);
+                if light_condition(lat, lon, time_after(departure, hours)).is_dark() {
+                    dark_hours += step;
+                }
+            }
+
+            let arrival_time = time_after(departure, to.hours_from_start);
+            let arrival_light = light_condition(to.lat, to.lon, arrival_time);
+            let middle = time_after(departure, from.hours_from_start + duration / 2.0);
+
+            RouteLegDaylight {
+                leg_index,
+                from_name: from.name.clone(),
+                to_name: to.name.clone(),
+                departure_eta: from.eta.clone(),
+                arrival_eta: to.eta.clone(),
+                dark_hours,
+                darkness_fraction: if duration > 0.0 { dark_hours / duration } else { 0.0 },
+                in_darkness: dark_hours > 0.0,
+                arrival_light,
+                arrival_in_darkness: arrival_light.is_dark(),
+                moon_illumination: moon_phase(middle).illumination,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Waypoint;
+    use crate::navigation::{build_eta_table, calculate_statistics};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_overnight_leg_marked_dark() {
+        // Auckland to Russell, leaving 12:30 NZDT on the longest day at 6 knots
+        let waypoints = vec![Waypoint::at("Westhaven", -36.84, 174.77), Waypoint::at("Kawau", -36.2, 174.9), Waypoint::at("Russell", -35.26, 174.12)];
+        let departure = Utc.with_ymd_and_hms(2024, 12, 20, 23, 30, 0).unwrap();
+        let stats = calculate_statistics(&waypoints, 6.0);
+        let table = build_eta_table(&waypoints, &stats, 6.0, departure, Some(1.0));
+
+        let legs = route_daylight(&table, departure);
+        assert_eq!(legs.len(), 2);
+        // First leg (39nm) finishes around 19:00, before sunset
+        assert!(!legs[0].in_darkness);
+        assert!(!legs[0].arrival_in_darkness);
+        // Second leg (68nm) sails through the night and arrives after dawn
+        assert!(legs[1].dark_hours > 7.0, "{}", legs[1].dark_hours);
+        assert!(legs[1].darkness_fraction > 0.6);
+        assert!(!legs[1].arrival_in_darkness);
+    }
+}
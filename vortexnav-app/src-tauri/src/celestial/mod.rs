@@ -0,0 +1,163 @@
+// Celestial Navigation Module
+// Almanac for the sun, moon, planets and navigational stars, and sight reduction
+
+mod planets;
+mod sight;
+mod stars;
+
+pub use planets::Planet;
+pub use sight::*;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::almanac::ephemeris::{
+    dynamical_time, greenwich_hour_angle, julian_day, moon_position, moon_semi_diameter, normalize_degrees,
+    sidereal_time, sun_position, Equatorial, AU_KM,
+};
+use stars::{apparent_place, find_star, NavStar, NAV_STARS};
+
+/// Sun semi-diameter at one astronomical unit (arcseconds)
+const SUN_SEMI_DIAMETER_ARCSEC: f64 = 959.63;
+
+#[derive(Error, Debug)]
+pub enum CelestialError {
+    #[error("Unknown celestial body '{0}'")]
+    UnknownBody(String),
+    #[error("Invalid sight time '{0}'")]
+    InvalidTime(String),
+    #[error("A fix needs at least two sights, got {0}")]
+    NotEnoughSights(usize),
+    #[error("Lines of position cross at too fine an angle for a fix")]
+    PoorGeometry,
+}
+
+/// A body that can be observed with a sextant
+#[derive(Debug, Clone, Copy)]
+pub enum Body {
+    Sun,
+    Moon,
+    Planet(Planet),
+    Star(&'static NavStar),
+}
+
+impl Body {
+    /// Look up a body by name ("Sun", "Moon", a planet or a navigational star)
+    pub fn from_name(name: &str) -> Result<Body, CelestialError> {
+        let trimmed = name.trim();
+        if trimmed.eq_ignore_ascii_case("sun") {
+            return Ok(Body::Sun);
+        }
+        if trimmed.eq_ignore_ascii_case("moon") {
+            return Ok(Body::Moon);
+        }
+        if let Some(planet) = Planet::ALL.iter().find(|p| p.name().eq_ignore_ascii_case(trimmed)) {
+            return Ok(Body::Planet(*planet));
+        }
+        find_star(trimmed).map(Body::Star).ok_or_else(|| CelestialError::UnknownBody(name.to_string()))
+    }
+
+    /// Every body in the almanac
+    pub fn all() -> Vec<Body> {
+        let mut bodies = vec![Body::Sun, Body::Moon];
+        bodies.extend(Planet::ALL.iter().map(|p| Body::Planet(*p)));
+        bodies.extend(NAV_STARS.iter().map(Body::Star));
+        bodies
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Body::Sun => "Sun",
+            Body::Moon => "Moon",
+            Body::Planet(planet) => planet.name(),
+            Body::Star(star) => star.name,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Body::Sun => "sun",
+            Body::Moon => "moon",
+            Body::Planet(_) => "planet",
+            Body::Star(_) => "star",
+        }
+    }
+
+    /// Apparent geocentric position at a dynamical time (JDE). Stars are at infinity.
+    pub fn position(&self, jde: f64) -> Equatorial {
+        match self {
+            Body::Sun => sun_position(jde),
+            Body::Moon => moon_position(jde),
+            Body::Planet(planet) => planets::planet_position(*planet, jde),
+            Body::Star(star) => {
+                let (ra, dec) = apparent_place(star.ra, star.dec, star.pm_ra, star.pm_dec, jde);
+                Equatorial { ra, dec, distance_km: f64::INFINITY }
+            }
+        }
+    }
+
+    /// Geocentric semi-diameter (arcminutes); planets and stars are treated as points
+    pub fn semi_diameter_arcmin(&self, position: &Equatorial) -> f64 {
+        match self {
+            Body::Sun => SUN_SEMI_DIAMETER_ARCSEC / 60.0 / (position.distance_km / AU_KM),
+            Body::Moon => moon_semi_diameter(position.distance_km) * 60.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A body listed in the almanac
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CelestialBodyInfo {
+    pub name: String,
+    pub kind: String,
+    pub magnitude: Option<f64>,
+}
+
+/// Almanac data for a body at an instant (angles in degrees)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyAlmanac {
+    pub body: String,
+    pub kind: String,
+    pub time: String,
+    pub gha: f64,
+    /// Sidereal hour angle (360 - right ascension)
+    pub sha: f64,
+    pub dec: f64,
+    pub gha_aries: f64,
+    pub semi_diameter_arcmin: f64,
+    pub horizontal_parallax_arcmin: f64,
+}
+
+pub fn celestial_bodies() -> Vec<CelestialBodyInfo> {
+    Body::all()
+        .into_iter()
+        .map(|body| CelestialBodyInfo {
+            name: body.name().to_string(),
+            kind: body.kind().to_string(),
+            magnitude: match body {
+                Body::Star(star) => Some(star.magnitude),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+/// GHA, SHA and declination of a body, as tabulated in a nautical almanac
+pub fn body_almanac(body: Body, time: DateTime<Utc>) -> BodyAlmanac {
+    let jd = julian_day(time);
+    let position = body.position(dynamical_time(jd));
+
+    BodyAlmanac {
+        body: body.name().to_string(),
+        kind: body.kind().to_string(),
+        time: time.to_rfc3339(),
+        gha: greenwich_hour_angle(jd, position.ra),
+        sha: normalize_degrees(360.0 - position.ra),
+        dec: position.dec,
+        gha_aries: sidereal_time(jd),
+        semi_diameter_arcmin: body.semi_diameter_arcmin(&position),
+        horizontal_parallax_arcmin: position.horizontal_parallax() * 60.0,
+    }
+}
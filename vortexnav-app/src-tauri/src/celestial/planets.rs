@@ -0,0 +1,146 @@
+// Navigational Planets
+// Venus, Mars, Jupiter and Saturn from mean Keplerian elements (Standish,
+// valid 1800-2050), good to about an arcminute
+
+use serde::{Deserialize, Serialize};
+
+use super::stars::apparent_place;
+use crate::almanac::ephemeris::{centuries, Equatorial, AU_KM};
+
+/// Obliquity of the ecliptic at J2000 (degrees)
+const OBLIQUITY_J2000: f64 = 23.439_291_1;
+/// Light time for one astronomical unit (days)
+const LIGHT_DAYS_PER_AU: f64 = 0.005_775_518;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Planet {
+    Venus,
+    Mars,
+    Jupiter,
+    Saturn,
+}
+
+impl Planet {
+    pub const ALL: [Planet; 4] = [Planet::Venus, Planet::Mars, Planet::Jupiter, Planet::Saturn];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Planet::Venus => "Venus",
+            Planet::Mars => "Mars",
+            Planet::Jupiter => "Jupiter",
+            Planet::Saturn => "Saturn",
+        }
+    }
+
+    fn elements(&self) -> &'static Elements {
+        match self {
+            Planet::Venus => &VENUS,
+            Planet::Mars => &MARS,
+            Planet::Jupiter => &JUPITER,
+            Planet::Saturn => &SATURN,
+        }
+    }
+}
+
+/// Orbital elements at J2000 and their rates per century:
+/// a (AU), e, I, L, longitude of perihelion, longitude of node (degrees)
+struct Elements {
+    base: [f64; 6],
+    rate: [f64; 6],
+}
+
+const VENUS: Elements = Elements {
+    base: [0.723_335_66, 0.006_776_72, 3.394_676_05, 181.979_099_5, 131.602_467_18, 76.679_842_55],
+    rate: [0.000_003_90, -0.000_041_07, -0.000_788_90, 58_517.815_387_29, 0.002_683_29, -0.277_694_18],
+};
+
+const EARTH_MOON: Elements = Elements {
+    base: [1.000_002_61, 0.016_711_23, -0.000_015_31, 100.464_571_66, 102.937_681_93, 0.0],
+    rate: [0.000_005_62, -0.000_043_92, -0.012_946_68, 35_999.372_449_81, 0.323_273_64, 0.0],
+};
+
+const MARS: Elements = Elements {
+    base: [1.523_710_34, 0.093_394_10, 1.849_691_42, -4.553_432_05, -23.943_629_59, 49.559_538_91],
+    rate: [0.000_018_47, 0.000_078_82, -0.008_131_31, 19_140.302_684_99, 0.444_410_88, -0.292_573_43],
+};
+
+const JUPITER: Elements = Elements {
+    base: [5.202_887_00, 0.048_386_24, 1.304_396_95, 34.396_440_51, 14.728_479_83, 100.473_909_09],
+    rate: [-0.000_116_07, -0.000_132_53, -0.001_837_14, 3_034.746_127_75, 0.212_526_68, 0.204_691_06],
+};
+
+const SATURN: Elements = Elements {
+    base: [9.536_675_94, 0.053_861_79, 2.485_991_87, 49.954_244_23, 92.598_878_31, 113.662_424_48],
+    rate: [-0.001_250_60, -0.000_509_91, 0.001_936_09, 1_222.493_622_01, -0.418_972_16, -0.288_677_94],
+};
+
+/// Heliocentric position in the J2000 ecliptic frame (AU)
+fn heliocentric(elements: &Elements, t: f64) -> [f64; 3] {
+    let el: Vec<f64> = (0..6).map(|i| elements.base[i] + elements.rate[i] * t).collect();
+    let (a, e, incl, l, perihelion, node) = (el[0], el[1], el[2].to_radians(), el[3], el[4], el[5]);
+
+    let omega = (perihelion - node).to_radians();
+    let node = node.to_radians();
+    let mean_anomaly = (l - perihelion).rem_euclid(360.0).to_radians();
+
+    // Kepler's equation by Newton iteration
+    let mut ecc_anomaly = mean_anomaly + e * mean_anomaly.sin();
+    for _ in 0..10 {
+        let delta = (ecc_anomaly - e * ecc_anomaly.sin() - mean_anomaly) / (1.0 - e * ecc_anomaly.cos());
+        ecc_anomaly -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+
+    let x = a * (ecc_anomaly.cos() - e);
+    let y = a * (1.0 - e * e).sqrt() * ecc_anomaly.sin();
+
+    let (sin_w, cos_w) = omega.sin_cos();
+    let (sin_n, cos_n) = node.sin_cos();
+    let (sin_i, cos_i) = incl.sin_cos();
+    [
+        (cos_w * cos_n - sin_w * sin_n * cos_i) * x + (-sin_w * cos_n - cos_w * sin_n * cos_i) * y,
+        (cos_w * sin_n + sin_w * cos_n * cos_i) * x + (-sin_w * sin_n + cos_w * cos_n * cos_i) * y,
+        (sin_w * sin_i) * x + (cos_w * sin_i) * y,
+    ]
+}
+
+/// Apparent geocentric position of a planet at a dynamical time (JDE)
+pub fn planet_position(planet: Planet, jde: f64) -> Equatorial {
+    let earth = heliocentric(&EARTH_MOON, centuries(jde));
+
+    // Correct for light time
+    let mut geocentric = [0.0; 3];
+    let mut distance = 0.0;
+    for _ in 0..3 {
+        let body = heliocentric(planet.elements(), centuries(jde - distance * LIGHT_DAYS_PER_AU));
+        geocentric = [body[0] - earth[0], body[1] - earth[1], body[2] - earth[2]];
+        distance = (geocentric[0].powi(2) + geocentric[1].powi(2) + geocentric[2].powi(2)).sqrt();
+    }
+
+    // J2000 ecliptic to J2000 equatorial
+    let (sin_e, cos_e) = OBLIQUITY_J2000.to_radians().sin_cos();
+    let x = geocentric[0];
+    let y = geocentric[1] * cos_e - geocentric[2] * sin_e;
+    let z = geocentric[1] * sin_e + geocentric[2] * cos_e;
+    let ra0 = y.atan2(x).to_degrees();
+    let dec0 = (z / distance).asin().to_degrees();
+
+    let (ra, dec) = apparent_place(ra0, dec0, 0.0, 0.0, jde);
+    Equatorial { ra, dec, distance_km: distance * AU_KM }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_venus_position() {
+        // Meeus example 33.a: 1992 December 20.0 TD, RA 21h04m41.454s, Dec -18°53'16.84"
+        let venus = planet_position(Planet::Venus, 2_448_976.5);
+        assert!((venus.ra - 316.172_73).abs() < 0.02, "{}", venus.ra);
+        assert!((venus.dec + 18.888_01).abs() < 0.02, "{}", venus.dec);
+        assert!((venus.distance_km / AU_KM - 0.910_947).abs() < 0.001);
+    }
+}
@@ -0,0 +1,369 @@
+// Sight Reduction
+// Sextant altitude corrections, intercept and azimuth, and fixes from lines of position
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Body, CelestialError};
+use crate::almanac::ephemeris::{
+    altitude_azimuth, dynamical_time, greenwich_hour_angle, julian_day, normalize_degrees, Equatorial,
+};
+use crate::navigation::{calculate_bearing, destination_point, haversine_distance};
+
+/// Dip of the sea horizon per square root of meter of height of eye (arcminutes)
+const DIP_FACTOR: f64 = 1.76;
+/// Standard atmosphere assumed by the refraction formula
+const STANDARD_PRESSURE_MB: f64 = 1010.0;
+const STANDARD_TEMPERATURE_C: f64 = 10.0;
+/// Iterations of the least squares fix
+const MAX_FIX_ITERATIONS: usize = 20;
+/// Smallest angle between lines of position accepted for a fix (degrees)
+const MIN_CUT_ANGLE: f64 = 10.0;
+
+/// Which part of the sun or moon was brought down to the horizon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Limb {
+    #[default]
+    Lower,
+    Upper,
+    Center,
+}
+
+/// A sextant observation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sight {
+    pub body: String,
+    /// UTC time of the sight (RFC3339)
+    pub time: String,
+    /// Sextant altitude Hs (degrees)
+    pub sextant_altitude: f64,
+    #[serde(default)]
+    pub limb: Limb,
+    /// Added to Hs; negative when the index error is on the arc (arcminutes)
+    #[serde(default)]
+    pub index_correction_arcmin: f64,
+    #[serde(default)]
+    pub height_of_eye_m: f64,
+    pub temperature_c: Option<f64>,
+    pub pressure_mb: Option<f64>,
+}
+
+/// Corrections taking Hs to observed altitude Ho
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SightCorrections {
+    pub dip_arcmin: f64,
+    /// Apparent altitude Ha (degrees)
+    pub apparent_altitude: f64,
+    pub refraction_arcmin: f64,
+    /// Signed for the limb observed
+    pub semi_diameter_arcmin: f64,
+    pub parallax_arcmin: f64,
+    /// Observed altitude Ho (degrees)
+    pub observed_altitude: f64,
+}
+
+/// A reduced sight: the line of position against an assumed position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SightReduction {
+    pub body: String,
+    pub time: String,
+    pub corrections: SightCorrections,
+    pub gha: f64,
+    pub dec: f64,
+    pub lha: f64,
+    pub assumed_lat: f64,
+    pub assumed_lon: f64,
+    /// Computed altitude Hc (degrees)
+    pub computed_altitude: f64,
+    /// True azimuth Zn (degrees)
+    pub azimuth: f64,
+    /// Ho - Hc (nautical miles), positive toward the body
+    pub intercept_nm: f64,
+    pub toward: bool,
+}
+
+/// Sights and the dead reckoning needed to cross them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CelestialFixRequest {
+    pub sights: Vec<Sight>,
+    /// DR position at the fix time
+    pub dr_lat: f64,
+    pub dr_lon: f64,
+    /// Time of the fix (RFC3339), defaulting to the last sight
+    pub fix_time: Option<String>,
+    /// Course and speed made good, used to advance or retire lines of position
+    #[serde(default)]
+    pub course: f64,
+    #[serde(default)]
+    pub speed_kn: f64,
+}
+
+/// Position found from two or more lines of position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CelestialFix {
+    pub lat: f64,
+    pub lon: f64,
+    pub time: String,
+    pub iterations: usize,
+    /// RMS distance of the fix from the lines of position (nautical miles)
+    pub residual_nm: f64,
+    pub distance_from_dr_nm: f64,
+    pub bearing_from_dr: f64,
+    /// Each sight reduced at the fix, advanced along the DR track
+    pub reductions: Vec<SightReduction>,
+    pub waypoint_id: Option<i64>,
+}
+
+pub fn parse_sight_time(time: &str) -> Result<DateTime<Utc>, CelestialError> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| CelestialError::InvalidTime(time.to_string()))
+}
+
+/// Refraction for an apparent altitude (Bennett), scaled for temperature and pressure (arcminutes)
+pub fn refraction_arcmin(apparent_altitude: f64, temperature_c: f64, pressure_mb: f64) -> f64 {
+    let h = apparent_altitude.max(-1.0);
+    let standard = 1.0 / (h + 7.31 / (h + 4.4)).to_radians().tan();
+    standard * (pressure_mb / STANDARD_PRESSURE_MB) * (283.0 / (273.0 + temperature_c))
+}
+
+/// Correct a sextant altitude for a body at a known geocentric position
+pub fn correct_altitude(sight: &Sight, body: Body, position: &Equatorial) -> SightCorrections {
+    let dip_arcmin = DIP_FACTOR * sight.height_of_eye_m.max(0.0).sqrt();
+    let apparent_altitude = sight.sextant_altitude + (sight.index_correction_arcmin - dip_arcmin) / 60.0;
+
+    let refraction = refraction_arcmin(
+        apparent_altitude,
+        sight.temperature_c.unwrap_or(STANDARD_TEMPERATURE_C),
+        sight.pressure_mb.unwrap_or(STANDARD_PRESSURE_MB),
+    );
+    let altitude = apparent_altitude - refraction / 60.0;
+
+    let parallax = position.horizontal_parallax() * 60.0 * altitude.to_radians().cos();
+
+    let mut semi_diameter = body.semi_diameter_arcmin(position);
+    if matches!(body, Body::Moon) {
+        // Augmentation: the moon is nearer the observer than the earth's centre
+        semi_diameter *= 1.0 + altitude.to_radians().sin() * position.horizontal_parallax().to_radians().sin();
+    }
+    let semi_diameter_arcmin = match sight.limb {
+        Limb::Lower => semi_diameter,
+        Limb::Upper => -semi_diameter,
+        Limb::Center => 0.0,
+    };
+
+    SightCorrections {
+        dip_arcmin,
+        apparent_altitude,
+        refraction_arcmin: refraction,
+        semi_diameter_arcmin,
+        parallax_arcmin: parallax,
+        observed_altitude: altitude + (parallax + semi_diameter_arcmin) / 60.0,
+    }
+}
+
+/// Reduce a sight against an assumed position: Hc, Zn and intercept
+pub fn reduce_sight(sight: &Sight, lat: f64, lon: f64) -> Result<SightReduction, CelestialError> {
+    let body = Body::from_name(&sight.body)?;
+    let time = parse_sight_time(&sight.time)?;
+
+    let jd = julian_day(time);
+    let position = body.position(dynamical_time(jd));
+    let corrections = correct_altitude(sight, body, &position);
+
+    let gha = greenwich_hour_angle(jd, position.ra);
+    let lha = normalize_degrees(gha + lon);
+    let computed = altitude_azimuth(lat, position.dec, lha);
+    let intercept_nm = (corrections.observed_altitude - computed.altitude) * 60.0;
+
+    Ok(SightReduction {
+        body: body.name().to_string(),
+        time: time.to_rfc3339(),
+        corrections,
+        gha,
+        dec: position.dec,
+        lha,
+        assumed_lat: lat,
+        assumed_lon: lon,
+        computed_altitude: computed.altitude,
+        azimuth: computed.azimuth,
+        intercept_nm,
+        toward: intercept_nm >= 0.0,
+    })
+}
+
+fn wrap_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Reduce every sight from where the vessel was at its time, given a position at the fix time
+fn reduce_along_track(
+    request: &CelestialFixRequest,
+    times: &[DateTime<Utc>],
+    fix_time: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+) -> Result<Vec<SightReduction>, CelestialError> {
+    request
+        .sights
+        .iter()
+        .zip(times)
+        .map(|(sight, time)| {
+            let hours = (*time - fix_time).num_milliseconds() as f64 / 3_600_000.0;
+            let (sight_lat, sight_lon) = destination_point(lat, lon, request.course, request.speed_kn * hours);
+            reduce_sight(sight, sight_lat, sight_lon)
+        })
+        .collect()
+}
+
+/// Least squares fix from two or more sights, iterated from the DR position.
+/// Earlier sights are advanced to the fix time along the DR course and speed.
+pub fn compute_fix(request: &CelestialFixRequest) -> Result<CelestialFix, CelestialError> {
+    let count = request.sights.len();
+    if count < 2 {
+        return Err(CelestialError::NotEnoughSights(count));
+    }
+
+    let times = request
+        .sights
+        .iter()
+        .map(|s| parse_sight_time(&s.time))
+        .collect::<Result<Vec<_>, _>>()?;
+    let fix_time = match &request.fix_time {
+        Some(time) => parse_sight_time(time)?,
+        None => *times.iter().max().unwrap(),
+    };
+
+    let min_determinant = MIN_CUT_ANGLE.to_radians().sin().powi(2) * (count * count) as f64 / 4.0;
+    let (mut lat, mut lon) = (request.dr_lat, request.dr_lon);
+    let mut iterations = 0;
+
+    while iterations < MAX_FIX_ITERATIONS {
+        iterations += 1;
+        let reductions = reduce_along_track(request, &times, fix_time, lat, lon)?;
+
+        // Normal equations for a shift (north, east) in miles
+        let (mut a, mut b, mut c, mut d, mut e) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for r in &reductions {
+            let (sin_z, cos_z) = r.azimuth.to_radians().sin_cos();
+            a += cos_z * cos_z;
+            b += cos_z * sin_z;
+            c += sin_z * sin_z;
+            d += r.intercept_nm * cos_z;
+            e += r.intercept_nm * sin_z;
+        }
+        let determinant = a * c - b * b;
+        if determinant < min_determinant {
+            return Err(CelestialError::PoorGeometry);
+        }
+
+        let north = (d * c - e * b) / determinant;
+        let east = (e * a - d * b) / determinant;
+        lat = (lat + north / 60.0).clamp(-89.9, 89.9);
+        lon = wrap_longitude(lon + east / (60.0 * lat.to_radians().cos()));
+
+        if north.hypot(east) < 0.01 {
+            break;
+        }
+    }
+
+    let reductions = reduce_along_track(request, &times, fix_time, lat, lon)?;
+    let residual_nm =
+        (reductions.iter().map(|r| r.intercept_nm * r.intercept_nm).sum::<f64>() / count as f64).sqrt();
+
+    Ok(CelestialFix {
+        lat,
+        lon,
+        time: fix_time.to_rfc3339(),
+        iterations,
+        residual_nm,
+        distance_from_dr_nm: haversine_distance(request.dr_lat, request.dr_lon, lat, lon),
+        bearing_from_dr: calculate_bearing(request.dr_lat, request.dr_lon, lat, lon),
+        reductions,
+        waypoint_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn sight(body: &str, time: DateTime<Utc>, hs: f64) -> Sight {
+        Sight {
+            body: body.to_string(),
+            time: time.to_rfc3339(),
+            sextant_altitude: hs,
+            limb: Limb::Lower,
+            index_correction_arcmin: -1.5,
+            height_of_eye_m: 2.5,
+            temperature_c: None,
+            pressure_mb: None,
+        }
+    }
+
+    /// The sextant reading that a perfect observer at a position would get
+    fn perfect_sight(body: &str, time: DateTime<Utc>, lat: f64, lon: f64) -> Sight {
+        let mut s = sight(body, time, 0.0);
+        let hc = reduce_sight(&s, lat, lon).unwrap().computed_altitude;
+        s.sextant_altitude = hc;
+        for _ in 0..5 {
+            let ho = reduce_sight(&s, lat, lon).unwrap().corrections.observed_altitude;
+            s.sextant_altitude += hc - ho;
+        }
+        s
+    }
+
+    #[test]
+    fn test_altitude_corrections() {
+        let time = Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+        let reduction = reduce_sight(&sight("sun", time, 30.0), -36.8, 174.8).unwrap();
+        let c = &reduction.corrections;
+
+        // Dip for 2.5 m is 2.8', refraction at 30° about 1.7', sun near perihelion 16.3'
+        assert!((c.dip_arcmin - 2.78).abs() < 0.01);
+        assert!((c.refraction_arcmin - 1.7).abs() < 0.1, "{}", c.refraction_arcmin);
+        assert!((c.semi_diameter_arcmin - 16.3).abs() < 0.1, "{}", c.semi_diameter_arcmin);
+        assert!((c.parallax_arcmin - 0.13).abs() < 0.01);
+        let expected = 30.0 + (-1.5 - c.dip_arcmin - c.refraction_arcmin + c.parallax_arcmin + 16.3) / 60.0;
+        assert!((c.observed_altitude - expected).abs() < 0.002);
+
+        assert!(Body::from_name("Rigil kentaurus").is_ok());
+        assert!(reduce_sight(&sight("Pluto", time, 30.0), 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_running_fix_from_three_sights() {
+        // Evening twilight off Great Barrier Island, making 060° at 6 knots
+        let fix_time = Utc.with_ymd_and_hms(2024, 6, 1, 6, 30, 0).unwrap();
+        let (true_lat, true_lon) = (-36.2, 175.6);
+        let (course, speed_kn) = (60.0, 6.0);
+
+        let mut sights = Vec::new();
+        for (body, minutes_before) in [("Arcturus", 40), ("Acrux", 20), ("Canopus", 0)] {
+            let time = fix_time - Duration::minutes(minutes_before);
+            let run = -speed_kn * minutes_before as f64 / 60.0;
+            let (lat, lon) = destination_point(true_lat, true_lon, course, run);
+            sights.push(perfect_sight(body, time, lat, lon));
+        }
+
+        let request = CelestialFixRequest {
+            sights,
+            dr_lat: -36.5,
+            dr_lon: 175.3,
+            fix_time: None,
+            course,
+            speed_kn,
+        };
+        let fix = compute_fix(&request).unwrap();
+        let error_nm = haversine_distance(fix.lat, fix.lon, true_lat, true_lon);
+        assert!(error_nm < 0.05, "fix {} {} off by {} nm", fix.lat, fix.lon, error_nm);
+        assert!(fix.residual_nm < 0.05);
+        assert!(fix.distance_from_dr_nm > 20.0);
+        assert!(fix.reductions.iter().all(|r| r.computed_altitude > 10.0));
+
+        let one = CelestialFixRequest { sights: request.sights[..1].to_vec(), ..request };
+        assert!(matches!(compute_fix(&one), Err(CelestialError::NotEnoughSights(1))));
+    }
+}
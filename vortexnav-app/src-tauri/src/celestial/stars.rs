@@ -0,0 +1,155 @@
+// Navigational Stars
+// The 57 almanac stars plus Polaris (J2000, Hipparcos) and their apparent places
+
+use crate::almanac::ephemeris::{centuries, normalize_degrees, nutation, obliquity, sun_longitude, J2000};
+
+/// Constant of aberration (arcseconds)
+const ABERRATION_ARCSEC: f64 = 20.495_52;
+
+/// A catalogue star at epoch J2000
+#[derive(Debug, Clone, Copy)]
+pub struct NavStar {
+    pub name: &'static str,
+    /// Right ascension (degrees)
+    pub ra: f64,
+    /// Declination (degrees)
+    pub dec: f64,
+    /// Proper motion in right ascension, times cos(dec) (mas/year)
+    pub pm_ra: f64,
+    /// Proper motion in declination (mas/year)
+    pub pm_dec: f64,
+    pub magnitude: f64,
+}
+
+pub const NAV_STARS: &[NavStar] = &[
+    NavStar { name: "Alpheratz", ra: 2.09692, dec: 29.09044, pm_ra: 135.7, pm_dec: -162.9, magnitude: 2.06 },
+    NavStar { name: "Ankaa", ra: 6.57104, dec: -42.30600, pm_ra: 232.8, pm_dec: -353.6, magnitude: 2.40 },
+    NavStar { name: "Schedar", ra: 10.12683, dec: 56.53733, pm_ra: 50.9, pm_dec: -32.1, magnitude: 2.24 },
+    NavStar { name: "Diphda", ra: 10.89738, dec: -17.98661, pm_ra: 232.8, pm_dec: 31.9, magnitude: 2.04 },
+    NavStar { name: "Achernar", ra: 24.42854, dec: -57.23675, pm_ra: 88.0, pm_dec: -40.1, magnitude: 0.46 },
+    NavStar { name: "Hamal", ra: 31.79338, dec: 23.46242, pm_ra: 188.6, pm_dec: -148.1, magnitude: 2.01 },
+    NavStar { name: "Polaris", ra: 37.95454, dec: 89.26411, pm_ra: 44.5, pm_dec: -11.9, magnitude: 1.97 },
+    NavStar { name: "Acamar", ra: 44.56533, dec: -40.30472, pm_ra: -52.9, pm_dec: 21.9, magnitude: 2.88 },
+    NavStar { name: "Menkar", ra: 45.56987, dec: 4.08975, pm_ra: -10.4, pm_dec: -76.9, magnitude: 2.54 },
+    NavStar { name: "Mirfak", ra: 51.08071, dec: 49.86117, pm_ra: 24.1, pm_dec: -26.1, magnitude: 1.79 },
+    NavStar { name: "Aldebaran", ra: 68.98017, dec: 16.50931, pm_ra: 62.8, pm_dec: -189.4, magnitude: 0.86 },
+    NavStar { name: "Rigel", ra: 78.63446, dec: -8.20164, pm_ra: 1.3, pm_dec: 0.5, magnitude: 0.13 },
+    NavStar { name: "Capella", ra: 79.17233, dec: 45.99800, pm_ra: 75.5, pm_dec: -427.1, magnitude: 0.08 },
+    NavStar { name: "Bellatrix", ra: 81.28275, dec: 6.34969, pm_ra: -8.8, pm_dec: -13.3, magnitude: 1.64 },
+    NavStar { name: "Elnath", ra: 81.57296, dec: 28.60744, pm_ra: 23.3, pm_dec: -174.2, magnitude: 1.65 },
+    NavStar { name: "Alnilam", ra: 84.05337, dec: -1.20192, pm_ra: 1.5, pm_dec: -1.1, magnitude: 1.69 },
+    NavStar { name: "Betelgeuse", ra: 88.79296, dec: 7.40706, pm_ra: 27.3, pm_dec: 10.9, magnitude: 0.50 },
+    NavStar { name: "Canopus", ra: 95.98796, dec: -52.69567, pm_ra: 19.9, pm_dec: 23.2, magnitude: -0.74 },
+    NavStar { name: "Sirius", ra: 101.28717, dec: -16.71611, pm_ra: -546.0, pm_dec: -1223.1, magnitude: -1.46 },
+    NavStar { name: "Adhara", ra: 104.65646, dec: -28.97208, pm_ra: 3.2, pm_dec: 1.3, magnitude: 1.50 },
+    NavStar { name: "Procyon", ra: 114.82550, dec: 5.22500, pm_ra: -714.6, pm_dec: -1036.8, magnitude: 0.34 },
+    NavStar { name: "Pollux", ra: 116.32896, dec: 28.02619, pm_ra: -626.6, pm_dec: -45.8, magnitude: 1.14 },
+    NavStar { name: "Avior", ra: 125.62850, dec: -59.50947, pm_ra: -25.5, pm_dec: 22.7, magnitude: 1.86 },
+    NavStar { name: "Suhail", ra: 136.99900, dec: -43.43258, pm_ra: -23.2, pm_dec: 14.3, magnitude: 2.23 },
+    NavStar { name: "Miaplacidus", ra: 138.30000, dec: -69.71719, pm_ra: -156.5, pm_dec: 108.9, magnitude: 1.67 },
+    NavStar { name: "Alphard", ra: 141.89683, dec: -8.65861, pm_ra: -15.2, pm_dec: 34.4, magnitude: 1.98 },
+    NavStar { name: "Regulus", ra: 152.09296, dec: 11.96722, pm_ra: -248.7, pm_dec: 5.6, magnitude: 1.40 },
+    NavStar { name: "Dubhe", ra: 165.93196, dec: 61.75103, pm_ra: -134.1, pm_dec: -34.7, magnitude: 1.81 },
+    NavStar { name: "Denebola", ra: 177.26492, dec: 14.57206, pm_ra: -497.7, pm_dec: -114.7, magnitude: 2.13 },
+    NavStar { name: "Gienah", ra: 183.95154, dec: -17.54192, pm_ra: -159.6, pm_dec: 22.3, magnitude: 2.58 },
+    NavStar { name: "Acrux", ra: 186.64958, dec: -63.09908, pm_ra: -35.8, pm_dec: -14.9, magnitude: 0.77 },
+    NavStar { name: "Gacrux", ra: 187.79150, dec: -57.11322, pm_ra: 28.2, pm_dec: -265.1, magnitude: 1.63 },
+    NavStar { name: "Alioth", ra: 193.50729, dec: 55.95983, pm_ra: 111.7, pm_dec: -8.2, magnitude: 1.76 },
+    NavStar { name: "Spica", ra: 201.29825, dec: -11.16133, pm_ra: -42.4, pm_dec: -31.7, magnitude: 0.97 },
+    NavStar { name: "Alkaid", ra: 206.88517, dec: 49.31328, pm_ra: -121.2, pm_dec: -15.6, magnitude: 1.85 },
+    NavStar { name: "Hadar", ra: 210.95588, dec: -60.37303, pm_ra: -33.3, pm_dec: -23.2, magnitude: 0.61 },
+    NavStar { name: "Menkent", ra: 211.67062, dec: -36.36994, pm_ra: -519.3, pm_dec: -518.0, magnitude: 2.06 },
+    NavStar { name: "Arcturus", ra: 213.91529, dec: 19.18242, pm_ra: -1093.4, pm_dec: -2000.1, magnitude: -0.05 },
+    NavStar { name: "Rigil Kentaurus", ra: 219.90204, dec: -60.83400, pm_ra: -3679.3, pm_dec: 473.7, magnitude: -0.27 },
+    NavStar { name: "Zubenelgenubi", ra: 222.71963, dec: -16.04178, pm_ra: -105.7, pm_dec: -68.4, magnitude: 2.75 },
+    NavStar { name: "Kochab", ra: 222.67638, dec: 74.15550, pm_ra: -32.6, pm_dec: 11.4, magnitude: 2.07 },
+    NavStar { name: "Alphecca", ra: 233.67196, dec: 26.71469, pm_ra: 120.3, pm_dec: -89.6, magnitude: 2.22 },
+    NavStar { name: "Antares", ra: 247.35192, dec: -26.43200, pm_ra: -12.1, pm_dec: -23.3, magnitude: 1.06 },
+    NavStar { name: "Atria", ra: 252.16625, dec: -69.02772, pm_ra: 17.9, pm_dec: -32.9, magnitude: 1.91 },
+    NavStar { name: "Sabik", ra: 257.59454, dec: -15.72492, pm_ra: 41.2, pm_dec: 97.7, magnitude: 2.43 },
+    NavStar { name: "Shaula", ra: 263.40217, dec: -37.10383, pm_ra: -8.5, pm_dec: -30.8, magnitude: 1.62 },
+    NavStar { name: "Rasalhague", ra: 263.73362, dec: 12.56003, pm_ra: 108.1, pm_dec: -221.6, magnitude: 2.08 },
+    NavStar { name: "Eltanin", ra: 269.15154, dec: 51.48889, pm_ra: -8.5, pm_dec: -23.1, magnitude: 2.23 },
+    NavStar { name: "Kaus Australis", ra: 276.04300, dec: -34.38461, pm_ra: -39.4, pm_dec: -124.2, magnitude: 1.79 },
+    NavStar { name: "Vega", ra: 279.23475, dec: 38.78369, pm_ra: 200.9, pm_dec: 286.2, magnitude: 0.03 },
+    NavStar { name: "Nunki", ra: 283.81638, dec: -26.29672, pm_ra: 15.1, pm_dec: -53.4, magnitude: 2.05 },
+    NavStar { name: "Altair", ra: 297.69583, dec: 8.86833, pm_ra: 536.2, pm_dec: 385.3, magnitude: 0.76 },
+    NavStar { name: "Peacock", ra: 306.41192, dec: -56.73508, pm_ra: 6.9, pm_dec: -86.0, magnitude: 1.94 },
+    NavStar { name: "Deneb", ra: 310.35800, dec: 45.28033, pm_ra: 2.0, pm_dec: 1.9, magnitude: 1.25 },
+    NavStar { name: "Enif", ra: 326.04650, dec: 9.87500, pm_ra: 26.9, pm_dec: 0.4, magnitude: 2.39 },
+    NavStar { name: "Al Na'ir", ra: 332.05825, dec: -46.96097, pm_ra: 126.7, pm_dec: -147.2, magnitude: 1.73 },
+    NavStar { name: "Fomalhaut", ra: 344.41271, dec: -29.62225, pm_ra: 328.9, pm_dec: -164.7, magnitude: 1.16 },
+    NavStar { name: "Markab", ra: 346.19021, dec: 15.20528, pm_ra: 60.4, pm_dec: -41.3, magnitude: 2.48 },
+];
+
+/// Look up a star by name, ignoring case, spaces and apostrophes
+pub fn find_star(name: &str) -> Option<&'static NavStar> {
+    let key = simplify(name);
+    NAV_STARS.iter().find(|s| simplify(s.name) == key)
+}
+
+fn simplify(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Apparent right ascension and declination (degrees) at a dynamical time of a
+/// J2000 position, applying proper motion, precession, nutation and annual aberration
+pub fn apparent_place(ra0: f64, dec0: f64, pm_ra: f64, pm_dec: f64, jde: f64) -> (f64, f64) {
+    let t = centuries(jde);
+    let years = (jde - J2000) / 365.25;
+
+    // Proper motion
+    let ra0 = ra0 + pm_ra * years / 3_600_000.0 / dec0.to_radians().cos().max(1e-6);
+    let dec0 = dec0 + pm_dec * years / 3_600_000.0;
+
+    // Precession from J2000 (Meeus 21.4)
+    let zeta = (2_306.218_1 * t + 0.301_88 * t * t + 0.017_998 * t.powi(3)) / 3600.0;
+    let z = (2_306.218_1 * t + 1.094_68 * t * t + 0.018_203 * t.powi(3)) / 3600.0;
+    let theta = (2_004.310_9 * t - 0.426_65 * t * t - 0.041_833 * t.powi(3)) / 3600.0;
+
+    let (sin_d0, cos_d0) = dec0.to_radians().sin_cos();
+    let (sin_a, cos_a) = (ra0 + zeta).to_radians().sin_cos();
+    let (sin_th, cos_th) = theta.to_radians().sin_cos();
+    let a = cos_d0 * sin_a;
+    let b = cos_th * cos_d0 * cos_a - sin_th * sin_d0;
+    let c = sin_th * cos_d0 * cos_a + cos_th * sin_d0;
+    let ra = a.atan2(b).to_degrees() + z;
+    let dec = c.clamp(-1.0, 1.0).asin().to_degrees();
+
+    // Nutation and aberration (Meeus 23.1, 23.3)
+    let (dpsi, deps) = nutation(t);
+    let epsilon = obliquity(t).to_radians();
+    let (sun, _) = sun_longitude(t);
+    let kappa = ABERRATION_ARCSEC / 3600.0;
+
+    let (sin_ra, cos_ra) = ra.to_radians().sin_cos();
+    let (sin_dec, cos_dec) = dec.to_radians().sin_cos();
+    let tan_dec = sin_dec / cos_dec.max(1e-9);
+    let (sin_sun, cos_sun) = sun.to_radians().sin_cos();
+
+    let d_ra = (epsilon.cos() + epsilon.sin() * sin_ra * tan_dec) * dpsi - cos_ra * tan_dec * deps
+        - kappa * (cos_ra * cos_sun * epsilon.cos() + sin_ra * sin_sun) / cos_dec.max(1e-9);
+    let d_dec = epsilon.sin() * cos_ra * dpsi + sin_ra * deps
+        - kappa * (cos_sun * epsilon.cos() * (epsilon.tan() * cos_dec - sin_ra * sin_dec) + cos_ra * sin_dec * sin_sun);
+
+    (normalize_degrees(ra + d_ra), dec + d_dec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apparent_place() {
+        // Meeus examples 21.b and 23.a: theta Persei at 2028 November 13.19 TD
+        let jde = 2_462_088.69;
+        let pm_ra = 0.034_25 * 15.0 * 1000.0 * 49.228_467_f64.to_radians().cos();
+        let (ra, dec) = apparent_place(41.049_942, 49.228_467, pm_ra, -89.5, jde);
+        assert!((ra - 41.559_965).abs() < 0.001, "{}", ra);
+        assert!((dec - 49.352_069).abs() < 0.001, "{}", dec);
+
+        assert_eq!(NAV_STARS.len(), 58);
+        assert_eq!(find_star("al nair").unwrap().name, "Al Na'ir");
+        assert!(find_star("Betelgeuze").is_none());
+    }
+}
@@ -7,7 +7,7 @@ mod cell;
 mod geometry;
 mod dictionary;
 mod reader;
-mod renderer;
+pub mod renderer;
 mod server;
 
 pub use decode::*;
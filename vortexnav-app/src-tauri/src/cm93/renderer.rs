@@ -246,7 +246,7 @@ impl TileRasterizer {
     }
 
     /// Set a pixel with bounds checking
-    fn set_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: [u8; 4]) {
         let size = self.config.tile_size as i32;
         if x >= 0 && x < size && y >= 0 && y < size {
             let idx = ((y as u32 * self.config.tile_size + x as u32) * 4) as usize;
@@ -1,15 +1,31 @@
 // Tauri commands for frontend communication
 
+use crate::almanac::{almanac_day, nautical_zone_offset_minutes, route_daylight, sky_conditions, AlmanacDay, RouteLegDaylight, SkyConditions};
+use crate::celestial::{
+    body_almanac, celestial_bodies, compute_fix, parse_sight_time, reduce_sight, Body, BodyAlmanac, CelestialBodyInfo,
+    CelestialFix, CelestialFixRequest, Sight, SightReduction,
+};
 use crate::catalog_parser::{parse_catalog_file, parse_catalog_xml};
 use crate::chart_converter::{check_gdal_available, convert_to_mbtiles, get_mbtiles_output_path, write_mbtiles_metadata, GdalInfo};
 use crate::cm93::{Cm93Server, GeoJsonTile};
-use crate::database::{AppSettings, BaseNauticalSettings, CatalogChart, ChartCatalog, ChartCustomMetadata, ChartLayerState, Cm93Settings, ConfigDatabase, GebcoSettings, GpsSourceRecord, MBTilesMetadata, MBTilesReader, Route, RouteStatistics, RouteTag, RouteWithWaypoints, Track, TrackPoint, TrackWithPoints, Waypoint};
+use crate::data_server::{DataServer, DataServerStatus, DataSnapshot};
+use crate::database::{self, AppSettings, BaseNauticalSettings, BoatPolarRecord, CatalogChart, ChartCatalog, ChartCustomMetadata, ChartLayerState, Cm93Settings, ConfigDatabase, DataServerSettings, GebcoSettings, GpsSourceRecord, MBTilesMetadata, MBTilesReader, PositionFilterSettings, Route, RouteStatistics, RouteTag, RouteWithWaypoints, SearchKind, SyncPeer, SyncSettings, TidalCurrentStationRecord, TidalStreamGridRecord, TideStationRecord, Track, TrackPoint, TrackWithPoints, Waypoint, WaypointGroup};
 use crate::download_manager::{download_file, extract_zip, categorize_extracted_files, fetch_catalog_url, filename_from_url, DownloadState};
+use crate::grib::{compare_departures, grib_vector_features, render_grib_tile, route_weather, DepartureWindow, GribDataset, GribLayer, GribParameter, RouteWeatherReport};
 use crate::gps::{DetectedPort, GpsManager, GpsSourceConfig, GpsSourceStatus, GpsSourceType};
+use crate::instruments::{AisTarget, InstrumentData};
+use crate::navigation::{build_eta_table, calculate_statistics, calculate_statistics_with_currents, course_to_steer, ActiveNavigation, CourseToSteer, CurrentProvider, RouteEtaPoint};
 use crate::nmea::GpsData;
+use crate::opencpn;
+use crate::search::{self, BoundingBox, SearchResult};
+use crate::signalk::{self, SignalKServer};
+use crate::sync::{pair_with, sync_with_peer, PairingCode, SyncReport, SyncServer, SyncStatus};
+use crate::tides::{parse_current_station_file, parse_station_file, parse_stream_grid_file, parse_tide_time, route_tides, RouteTidePoint, TidalCurrentModel, TidalCurrentPrediction, TidalCurrentStation, TidalStreamGrid, TidalStreamSample, TidePrediction, TideStation};
+use crate::weather_routing::{build_proposed_route, run_isochrones, LandMask, Polar, RoutingOptions, WeatherRoutingProgress, WeatherRoutingRequest, WeatherRoutingResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager, State};
 use tokio::sync::Mutex as TokioMutex;
@@ -21,6 +37,11 @@ pub struct AppState {
     pub mbtiles_readers: Mutex<HashMap<String, MBTilesReader>>,
     pub charts_dir: PathBuf,
     pub cm93_server: Mutex<Option<Cm93Server>>,
+    pub grib_data: Mutex<Option<Arc<GribDataset>>>,
+    /// Cancel token of the latest weather routing run
+    pub weather_routing_cancel: Mutex<Option<Arc<AtomicBool>>>,
+    pub data_server: Mutex<Option<DataServer>>,
+    pub sync_server: Mutex<Option<SyncServer>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -399,6 +420,984 @@ pub fn save_cm93_settings(settings: Cm93Settings, state: State<AppState>) -> Com
     }
 }
 
+// ============ GRIB Weather Commands ============
+
+/// Summary of the loaded GRIB dataset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GribStatus {
+    pub loaded: bool,
+    pub path: Option<String>,
+    pub parameters: Vec<GribParameter>,
+    pub forecast_times: Vec<String>,
+    pub bounds: Option<[f64; 4]>,
+    pub field_count: usize,
+}
+
+fn grib_status(dataset: Option<&GribDataset>) -> GribStatus {
+    match dataset {
+        Some(d) => GribStatus {
+            loaded: true,
+            path: d.source_path.clone(),
+            parameters: d.parameters(),
+            forecast_times: d.forecast_times().iter().map(|t| t.to_rfc3339()).collect(),
+            bounds: d.bounds(),
+            field_count: d.fields.len(),
+        },
+        None => GribStatus {
+            loaded: false,
+            path: None,
+            parameters: Vec::new(),
+            forecast_times: Vec::new(),
+            bounds: None,
+            field_count: 0,
+        },
+    }
+}
+
+/// Parse an RFC 3339 forecast time, defaulting to the first forecast step
+fn parse_grib_time(dataset: &GribDataset, time: Option<String>) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    match time {
+        Some(t) => chrono::DateTime::parse_from_rfc3339(&t)
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|e| format!("Invalid time '{}': {}", t, e)),
+        None => dataset
+            .forecast_times()
+            .first()
+            .copied()
+            .ok_or_else(|| "GRIB data has no forecast times".to_string()),
+    }
+}
+
+/// Load a GRIB1/GRIB2 file, replacing any previously loaded data
+#[tauri::command]
+pub fn load_grib_file(file_path: String, state: State<AppState>) -> CommandResult<GribStatus> {
+    match GribDataset::open(&file_path) {
+        Ok(dataset) => {
+            log::info!(
+                "Loaded GRIB file {} ({} fields, parameters {:?})",
+                file_path,
+                dataset.fields.len(),
+                dataset.parameters()
+            );
+            let status = grib_status(Some(&dataset));
+            *state.grib_data.lock().unwrap() = Some(Arc::new(dataset));
+            CommandResult::ok(status)
+        }
+        Err(e) => {
+            log::error!("Failed to load GRIB file {}: {}", file_path, e);
+            CommandResult::err(&e.to_string())
+        }
+    }
+}
+
+/// Get the status of the loaded GRIB data
+#[tauri::command]
+pub fn get_grib_status(state: State<AppState>) -> CommandResult<GribStatus> {
+    let grib_lock = state.grib_data.lock().unwrap();
+    CommandResult::ok(grib_status(grib_lock.as_deref()))
+}
+
+/// Unload GRIB data
+#[tauri::command]
+pub fn clear_grib_data(state: State<AppState>) -> CommandResult<()> {
+    *state.grib_data.lock().unwrap() = None;
+    CommandResult::ok(())
+}
+
+/// Get a colour-ramped PNG overlay tile for a GRIB layer
+/// (wind, gust, pressure, waves, current) at a forecast time
+#[tauri::command]
+pub fn get_grib_tile(
+    layer: String,
+    time: Option<String>,
+    z: u8,
+    x: u32,
+    y: u32,
+    state: State<AppState>,
+) -> Result<Vec<u8>, String> {
+    let layer = GribLayer::from_name(&layer).ok_or_else(|| format!("Unknown GRIB layer: {}", layer))?;
+
+    let grib_lock = state.grib_data.lock().unwrap();
+    let dataset = grib_lock.as_ref().ok_or("No GRIB data loaded")?;
+    let time = parse_grib_time(dataset, time)?;
+
+    render_grib_tile(dataset, layer, time, z, x, y).map_err(|e| e.to_string())
+}
+
+/// Get wind barbs, current arrows and isobars as GeoJSON for a bounding box
+#[tauri::command]
+pub fn get_grib_vectors(
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+    zoom: u8,
+    time: Option<String>,
+    state: State<AppState>,
+) -> CommandResult<GeoJsonTile> {
+    let grib_lock = state.grib_data.lock().unwrap();
+    let Some(dataset) = grib_lock.as_ref() else {
+        return CommandResult::err("No GRIB data loaded");
+    };
+
+    match parse_grib_time(dataset, time) {
+        Ok(time) => CommandResult::ok(grib_vector_features(
+            dataset, time, min_lat, min_lon, max_lat, max_lon, zoom,
+        )),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Get the forecast (TWS/TWD, gusts, waves, pressure) at each point of a
+/// route's ETA table for a departure time
+#[tauri::command]
+pub fn get_route_weather(
+    route_id: i64,
+    departure_time: String,
+    interval_hours: Option<f64>,
+    state: State<AppState>,
+) -> CommandResult<RouteWeatherReport> {
+    let departure = match parse_departure_time(&departure_time) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&e),
+    };
+
+    let route = match state.config_db.get_route(route_id) {
+        Ok(Some(route)) => route,
+        Ok(None) => return CommandResult::err(&format!("Route with id {} not found", route_id)),
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let grib_lock = state.grib_data.lock().unwrap();
+    let Some(dataset) = grib_lock.as_ref() else {
+        return CommandResult::err("No GRIB data loaded");
+    };
+
+    let speed = route.route.estimated_speed_kn;
+    let stats = calculate_statistics(&route.waypoints, speed);
+    let table = build_eta_table(&route.waypoints, &stats, speed, departure, interval_hours);
+
+    CommandResult::ok(route_weather(dataset, &table, departure))
+}
+
+/// Compare route weather for `count` departures spaced `step_hours` apart,
+/// marking the best weather window
+#[tauri::command]
+pub fn compare_route_departures(
+    route_id: i64,
+    first_departure: String,
+    step_hours: f64,
+    count: usize,
+    interval_hours: Option<f64>,
+    state: State<AppState>,
+) -> CommandResult<Vec<DepartureWindow>> {
+    let first = match parse_departure_time(&first_departure) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&e),
+    };
+    if step_hours <= 0.0 || count == 0 {
+        return CommandResult::err("Departure step and count must be positive");
+    }
+
+    let route = match state.config_db.get_route(route_id) {
+        Ok(Some(route)) => route,
+        Ok(None) => return CommandResult::err(&format!("Route with id {} not found", route_id)),
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let grib_lock = state.grib_data.lock().unwrap();
+    let Some(dataset) = grib_lock.as_ref() else {
+        return CommandResult::err("No GRIB data loaded");
+    };
+
+    let departures: Vec<_> = (0..count)
+        .map(|i| crate::navigation::time_after(first, step_hours * i as f64))
+        .collect();
+
+    CommandResult::ok(compare_departures(
+        dataset,
+        &route.waypoints,
+        route.route.estimated_speed_kn,
+        &departures,
+        interval_hours,
+    ))
+}
+
+// ============ Weather Routing Commands ============
+
+/// Import a boat polar file (TWA x TWS table, tab/CSV separated)
+#[tauri::command]
+pub fn import_boat_polar(
+    file_path: String,
+    name: Option<String>,
+    state: State<AppState>,
+) -> CommandResult<BoatPolarRecord> {
+    let text = match std::fs::read_to_string(&file_path) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&format!("Failed to read polar file: {}", e)),
+    };
+
+    let name = name.unwrap_or_else(|| {
+        std::path::Path::new(&file_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Polar".to_string())
+    });
+
+    let polar = match Polar::parse(&name, &text) {
+        Ok(p) => p,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let polar_json = match serde_json::to_string(&polar) {
+        Ok(j) => j,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    match state.config_db.create_boat_polar(&name, &polar_json) {
+        Ok(id) => CommandResult::ok(BoatPolarRecord {
+            id: Some(id),
+            name,
+            created_at: None,
+        }),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// List imported boat polars
+#[tauri::command]
+pub fn get_boat_polars(state: State<AppState>) -> CommandResult<Vec<BoatPolarRecord>> {
+    match state.config_db.get_boat_polars() {
+        Ok(polars) => CommandResult::ok(polars),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Get a boat polar table
+#[tauri::command]
+pub fn get_boat_polar(id: i64, state: State<AppState>) -> CommandResult<Polar> {
+    match state.config_db.get_boat_polar_data(id) {
+        Ok(Some(json)) => match serde_json::from_str(&json) {
+            Ok(polar) => CommandResult::ok(polar),
+            Err(e) => CommandResult::err(&e.to_string()),
+        },
+        Ok(None) => CommandResult::err(&format!("Polar with id {} not found", id)),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_boat_polar(id: i64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_boat_polar(id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Load CM93 land areas covering the passage, if the CM93 server is running
+fn load_land_mask(state: &AppState, request: &WeatherRoutingRequest) -> Option<LandMask> {
+    let cm93_lock = state.cm93_server.lock().unwrap();
+    let server = cm93_lock.as_ref()?;
+
+    let margin = 1.0 + (request.end_lat - request.start_lat).abs().max((request.end_lon - request.start_lon).abs()) * 0.25;
+    let min_lat = request.start_lat.min(request.end_lat) - margin;
+    let max_lat = request.start_lat.max(request.end_lat) + margin;
+    let min_lon = request.start_lon.min(request.end_lon) - margin;
+    let max_lon = request.start_lon.max(request.end_lon) + margin;
+
+    // Coarser CM93 scales for longer passages
+    let span = (max_lat - min_lat).max(max_lon - min_lon);
+    let zoom = if span > 20.0 { 3 } else if span > 6.0 { 5 } else if span > 2.0 { 7 } else { 9 };
+
+    match server.get_features_in_bounds(min_lat, min_lon, max_lat, max_lon, zoom) {
+        Ok(tile) => Some(LandMask::from_cm93_features(&tile)),
+        Err(e) => {
+            log::warn!("Weather routing without land data: {}", e);
+            None
+        }
+    }
+}
+
+/// Run isochrone weather routing from start to destination over the loaded GRIB data.
+/// Emits "weather-routing-progress" events and can be stopped with cancel_weather_routing.
+#[tauri::command]
+pub async fn run_weather_routing(
+    request: WeatherRoutingRequest,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<WeatherRoutingResult>, ()> {
+    let departure = match parse_departure_time(&request.departure_time) {
+        Ok(t) => t,
+        Err(e) => return Ok(CommandResult::err(&e)),
+    };
+
+    let polar: Polar = match state.config_db.get_boat_polar_data(request.polar_id) {
+        Ok(Some(json)) => match serde_json::from_str(&json) {
+            Ok(p) => p,
+            Err(e) => return Ok(CommandResult::err(&e.to_string())),
+        },
+        Ok(None) => return Ok(CommandResult::err(&format!("Polar with id {} not found", request.polar_id))),
+        Err(e) => return Ok(CommandResult::err(&e.to_string())),
+    };
+
+    let Some(dataset) = state.grib_data.lock().unwrap().clone() else {
+        return Ok(CommandResult::err("No GRIB data loaded"));
+    };
+
+    let _ = app_handle.emit("weather-routing-progress", WeatherRoutingProgress {
+        phase: "preparing".to_string(),
+        current: 0,
+        total: 0,
+        elapsed_hours: 0.0,
+        distance_to_go_nm: 0.0,
+    });
+
+    let land = load_land_mask(&state, &request);
+    let land_checked = land.as_ref().is_some_and(|l| !l.is_empty());
+
+    let forecast_hours = dataset
+        .forecast_times()
+        .last()
+        .map(|end| (*end - departure).num_minutes() as f64 / 60.0)
+        .unwrap_or(0.0);
+    let options = RoutingOptions {
+        start: (request.start_lat, request.start_lon),
+        end: (request.end_lat, request.end_lon),
+        departure,
+        time_step_hours: request.time_step_hours.filter(|h| *h > 0.0).unwrap_or(1.0),
+        heading_step_deg: request.heading_step_deg.filter(|d| *d > 0.0).unwrap_or(5.0),
+        max_hours: request.max_hours.unwrap_or(forecast_hours).min(forecast_hours.max(0.0)),
+        use_currents: request.use_currents.unwrap_or(true),
+    };
+    let name = request
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Weather route {}", departure.format("%Y-%m-%d %H:%M")));
+
+    // Each run has its own token; a new run stops the one it replaces
+    let cancel = Arc::new(AtomicBool::new(false));
+    if let Some(previous) = state.weather_routing_cancel.lock().unwrap().replace(cancel.clone()) {
+        previous.store(true, Ordering::SeqCst);
+    }
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mut progress = |p: WeatherRoutingProgress| {
+            let _ = app_handle.emit("weather-routing-progress", p);
+        };
+        run_isochrones(&dataset, &polar, land.as_ref(), &options, &cancel, &mut progress)
+    })
+    .await
+    .unwrap();
+
+    match result {
+        Ok(points) => Ok(CommandResult::ok(build_proposed_route(&name, points, land_checked))),
+        Err(e) => {
+            log::info!("Weather routing stopped: {}", e);
+            Ok(CommandResult::err(&e.to_string()))
+        }
+    }
+}
+
+/// Cancel a running weather routing job
+#[tauri::command]
+pub fn cancel_weather_routing(state: State<AppState>) -> CommandResult<()> {
+    if let Some(cancel) = state.weather_routing_cancel.lock().unwrap().as_ref() {
+        cancel.store(true, Ordering::SeqCst);
+    }
+    CommandResult::ok(())
+}
+
+// ============ Tide Commands ============
+
+/// Default search radius for the nearest tide station on a route
+const TIDE_STATION_RANGE_NM: f64 = 30.0;
+
+/// Nearest tide station to a position
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NearestTideStation {
+    pub station: TideStationRecord,
+    pub distance_nm: f64,
+}
+
+fn load_tide_stations(state: &AppState) -> Result<Vec<TideStation>, String> {
+    let rows = state.config_db.get_tide_stations_with_constituents().map_err(|e| e.to_string())?;
+    rows.into_iter()
+        .map(|(record, json)| {
+            Ok(TideStation {
+                id: record.id,
+                name: record.name,
+                lat: record.lat,
+                lon: record.lon,
+                mean_level_m: record.mean_level_m,
+                source: record.source,
+                constituents: serde_json::from_str(&json).map_err(|e| e.to_string())?,
+            })
+        })
+        .collect()
+}
+
+/// Import tide stations (harmonic constants) from a station file
+#[tauri::command]
+pub fn import_tide_stations(file_path: String, state: State<AppState>) -> CommandResult<Vec<TideStationRecord>> {
+    let text = match std::fs::read_to_string(&file_path) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&format!("Failed to read tide station file: {}", e)),
+    };
+    let stations = match parse_station_file(&text) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let mut records = Vec::new();
+    for station in stations {
+        let constituents = match serde_json::to_string(&station.constituents) {
+            Ok(j) => j,
+            Err(e) => return CommandResult::err(&e.to_string()),
+        };
+        let record = TideStationRecord {
+            id: None,
+            name: station.name,
+            lat: station.lat,
+            lon: station.lon,
+            mean_level_m: station.mean_level_m,
+            source: station.source,
+            created_at: None,
+        };
+        records.push((record, constituents));
+    }
+
+    // All or nothing, so a failure part way through leaves no half-imported file
+    let db = &state.config_db;
+    let imported = db.transaction(|tx| {
+        records
+            .into_iter()
+            .map(|(mut record, constituents)| {
+                record.id = Some(database::upsert_tide_station(tx, &record, &constituents)?);
+                Ok(record)
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match imported {
+        Ok(imported) => {
+            log::info!("Imported {} tide stations from {}", imported.len(), file_path);
+            CommandResult::ok(imported)
+        }
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_tide_stations(state: State<AppState>) -> CommandResult<Vec<TideStationRecord>> {
+    match state.config_db.get_tide_stations() {
+        Ok(stations) => CommandResult::ok(stations),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_tide_station(id: i64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_tide_station(id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Find the tide station nearest to a position
+#[tauri::command]
+pub fn find_nearest_tide_station(lat: f64, lon: f64, state: State<AppState>) -> CommandResult<Option<NearestTideStation>> {
+    let stations = match state.config_db.get_tide_stations() {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let nearest = stations
+        .into_iter()
+        .map(|s| {
+            let distance_nm = crate::navigation::haversine_distance(lat, lon, s.lat, s.lon);
+            NearestTideStation { station: s, distance_nm }
+        })
+        .min_by(|a, b| a.distance_nm.total_cmp(&b.distance_nm));
+
+    CommandResult::ok(nearest)
+}
+
+/// Predict heights (every `interval_minutes`, default 30) and high/low waters for a station
+#[tauri::command]
+pub fn predict_tides(
+    station_id: i64,
+    start_time: String,
+    end_time: String,
+    interval_minutes: Option<i64>,
+    state: State<AppState>,
+) -> CommandResult<TidePrediction> {
+    let (Some(start), Some(end)) = (parse_tide_time(&start_time), parse_tide_time(&end_time)) else {
+        return CommandResult::err("Invalid start or end time");
+    };
+
+    let stations = match load_tide_stations(&state) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e),
+    };
+    let Some(station) = stations.iter().find(|s| s.id == Some(station_id)) else {
+        return CommandResult::err(&format!("Tide station with id {} not found", station_id));
+    };
+
+    match station.predict(start, end, interval_minutes.unwrap_or(30)) {
+        Ok(prediction) => CommandResult::ok(prediction),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Tide at each point of a route's ETA table, from the nearest station
+/// (within `max_distance_nm`, default 30nm) or a fixed station
+#[tauri::command]
+pub fn get_route_tides(
+    route_id: i64,
+    departure_time: String,
+    interval_hours: Option<f64>,
+    station_id: Option<i64>,
+    max_distance_nm: Option<f64>,
+    state: State<AppState>,
+) -> CommandResult<Vec<RouteTidePoint>> {
+    let departure = match parse_departure_time(&departure_time) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&e),
+    };
+
+    let route = match state.config_db.get_route(route_id) {
+        Ok(Some(route)) => route,
+        Ok(None) => return CommandResult::err(&format!("Route with id {} not found", route_id)),
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let mut stations = match load_tide_stations(&state) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e),
+    };
+    let max_distance = match station_id {
+        Some(id) => {
+            stations.retain(|s| s.id == Some(id));
+            if stations.is_empty() {
+                return CommandResult::err(&format!("Tide station with id {} not found", id));
+            }
+            f64::MAX
+        }
+        None => max_distance_nm.unwrap_or(TIDE_STATION_RANGE_NM),
+    };
+
+    let speed = route.route.estimated_speed_kn;
+    let stats = match route_statistics_at(&state, &route.waypoints, speed, departure, true) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e),
+    };
+    let table = build_eta_table(&route.waypoints, &stats, speed, departure, interval_hours);
+
+    CommandResult::ok(route_tides(&stations, &table, departure, max_distance))
+}
+
+// ============ Tidal Stream Commands ============
+
+fn load_tidal_current_model(state: &AppState) -> Result<TidalCurrentModel, String> {
+    let stations = state
+        .config_db
+        .get_tidal_current_stations_with_constituents()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(record, json)| {
+            Ok(TidalCurrentStation {
+                id: record.id,
+                name: record.name,
+                lat: record.lat,
+                lon: record.lon,
+                flood_direction: record.flood_direction,
+                ebb_direction: record.ebb_direction,
+                mean_speed_kn: record.mean_speed_kn,
+                source: record.source,
+                constituents: serde_json::from_str(&json).map_err(|e| e.to_string())?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let grids = state
+        .config_db
+        .get_tidal_stream_grids_with_data()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(record, json)| {
+            let mut grid: TidalStreamGrid = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+            grid.id = record.id;
+            Ok(grid)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if stations.is_empty() && grids.is_empty() {
+        return Ok(TidalCurrentModel::default());
+    }
+    let tide_stations = if grids.is_empty() { Vec::new() } else { load_tide_stations(state)? };
+    Ok(TidalCurrentModel::new(stations, grids, &tide_stations))
+}
+
+/// Route statistics for a departure, worked through the tidal streams when any are loaded
+fn route_statistics_at(
+    state: &AppState,
+    waypoints: &[Waypoint],
+    speed_kn: f64,
+    departure: chrono::DateTime<chrono::Utc>,
+    use_tidal_streams: bool,
+) -> Result<RouteStatistics, String> {
+    if use_tidal_streams {
+        let model = load_tidal_current_model(state)?;
+        if !model.is_empty() {
+            return Ok(calculate_statistics_with_currents(waypoints, speed_kn, departure, &model));
+        }
+    }
+    Ok(calculate_statistics(waypoints, speed_kn))
+}
+
+/// Import tidal current stations (harmonic constants in knots) from a station file
+#[tauri::command]
+pub fn import_tidal_current_stations(file_path: String, state: State<AppState>) -> CommandResult<Vec<TidalCurrentStationRecord>> {
+    let text = match std::fs::read_to_string(&file_path) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&format!("Failed to read current station file: {}", e)),
+    };
+    let stations = match parse_current_station_file(&text) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let mut records = Vec::new();
+    for station in stations {
+        let constituents = match serde_json::to_string(&station.constituents) {
+            Ok(j) => j,
+            Err(e) => return CommandResult::err(&e.to_string()),
+        };
+        let record = TidalCurrentStationRecord {
+            id: None,
+            name: station.name,
+            lat: station.lat,
+            lon: station.lon,
+            flood_direction: station.flood_direction,
+            ebb_direction: station.ebb_direction,
+            mean_speed_kn: station.mean_speed_kn,
+            source: station.source,
+            created_at: None,
+        };
+        records.push((record, constituents));
+    }
+
+    let db = &state.config_db;
+    let imported = db.transaction(|tx| {
+        records
+            .into_iter()
+            .map(|(mut record, constituents)| {
+                record.id = Some(database::upsert_tidal_current_station(tx, &record, &constituents)?);
+                Ok(record)
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match imported {
+        Ok(imported) => {
+            log::info!("Imported {} tidal current stations from {}", imported.len(), file_path);
+            CommandResult::ok(imported)
+        }
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_tidal_current_stations(state: State<AppState>) -> CommandResult<Vec<TidalCurrentStationRecord>> {
+    match state.config_db.get_tidal_current_stations() {
+        Ok(stations) => CommandResult::ok(stations),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_tidal_current_station(id: i64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_tidal_current_station(id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Import a gridded tidal stream table referenced to HW at a tide station
+#[tauri::command]
+pub fn import_tidal_stream_grid(file_path: String, state: State<AppState>) -> CommandResult<TidalStreamGridRecord> {
+    let text = match std::fs::read_to_string(&file_path) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&format!("Failed to read tidal stream table: {}", e)),
+    };
+    let grid = match parse_stream_grid_file(&text) {
+        Ok(g) => g,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let grid_data = match serde_json::to_string(&grid) {
+        Ok(j) => j,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let mut record = TidalStreamGridRecord {
+        id: None,
+        name: grid.name,
+        reference_station: grid.reference_station,
+        point_count: grid.points.len() as i64,
+        created_at: None,
+    };
+    let db = &state.config_db;
+    match db.transaction(|tx| database::upsert_tidal_stream_grid(tx, &record, &grid_data)) {
+        Ok(id) => {
+            record.id = Some(id);
+            CommandResult::ok(record)
+        }
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_tidal_stream_grids(state: State<AppState>) -> CommandResult<Vec<TidalStreamGridRecord>> {
+    match state.config_db.get_tidal_stream_grids() {
+        Ok(grids) => CommandResult::ok(grids),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_tidal_stream_grid(id: i64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_tidal_stream_grid(id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Predict the stream (every `interval_minutes`, default 30), slack water and
+/// maximum flood/ebb for a current station
+#[tauri::command]
+pub fn predict_tidal_currents(
+    station_id: i64,
+    start_time: String,
+    end_time: String,
+    interval_minutes: Option<i64>,
+    state: State<AppState>,
+) -> CommandResult<TidalCurrentPrediction> {
+    let (Some(start), Some(end)) = (parse_tide_time(&start_time), parse_tide_time(&end_time)) else {
+        return CommandResult::err("Invalid start or end time");
+    };
+
+    let records = match state.config_db.get_tidal_current_stations_with_constituents() {
+        Ok(r) => r,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let Some((record, json)) = records.into_iter().find(|(r, _)| r.id == Some(station_id)) else {
+        return CommandResult::err(&format!("Tidal current station with id {} not found", station_id));
+    };
+    let constituents = match serde_json::from_str(&json) {
+        Ok(c) => c,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let station = TidalCurrentStation {
+        id: record.id,
+        name: record.name,
+        lat: record.lat,
+        lon: record.lon,
+        flood_direction: record.flood_direction,
+        ebb_direction: record.ebb_direction,
+        mean_speed_kn: record.mean_speed_kn,
+        source: record.source,
+        constituents,
+    };
+
+    match station.predict(start, end, interval_minutes.unwrap_or(30)) {
+        Ok(prediction) => CommandResult::ok(prediction),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Tidal stream at a position and time from the loaded tables and stations
+#[tauri::command]
+pub fn get_tidal_stream_at(lat: f64, lon: f64, time: String, state: State<AppState>) -> CommandResult<Option<TidalStreamSample>> {
+    let Some(time) = parse_tide_time(&time) else {
+        return CommandResult::err("Invalid time");
+    };
+    match load_tidal_current_model(&state) {
+        Ok(model) => CommandResult::ok(model.sample(lat, lon, time)),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Course to steer from one position to another, allowing for set and drift.
+/// Uses the given set/drift, or the predicted tidal stream at the start at `time`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn get_course_to_steer(
+    from_lat: f64,
+    from_lon: f64,
+    to_lat: f64,
+    to_lon: f64,
+    boat_speed_kn: f64,
+    time: Option<String>,
+    set: Option<f64>,
+    drift_kn: Option<f64>,
+    state: State<AppState>,
+) -> CommandResult<CourseToSteer> {
+    let track = crate::navigation::calculate_bearing(from_lat, from_lon, to_lat, to_lon);
+
+    let (set, drift) = match (set, drift_kn) {
+        (Some(set), Some(drift)) => (set, drift),
+        _ => {
+            let time = match time.as_deref().map(parse_tide_time) {
+                Some(Some(t)) => t,
+                Some(None) => return CommandResult::err("Invalid time"),
+                None => chrono::Utc::now(),
+            };
+            match load_tidal_current_model(&state) {
+                Ok(model) => model.current_at(from_lat, from_lon, time).unwrap_or((0.0, 0.0)),
+                Err(e) => return CommandResult::err(&e),
+            }
+        }
+    };
+
+    match course_to_steer(track, boat_speed_kn, set, drift) {
+        Some(cts) => CommandResult::ok(cts),
+        None => CommandResult::err("Cannot make good this track against the stream at this boat speed"),
+    }
+}
+
+// ============ Almanac Commands ============
+
+/// Sun, moon and twilight times for `days` local days (default 1) from a date.
+/// Days run midnight to midnight at `utc_offset_minutes`, defaulting to the
+/// nautical time zone of the longitude.
+#[tauri::command]
+pub fn get_almanac(
+    lat: f64,
+    lon: f64,
+    date: String,
+    days: Option<u32>,
+    utc_offset_minutes: Option<i32>,
+) -> CommandResult<Vec<AlmanacDay>> {
+    let Ok(first) = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d") else {
+        return CommandResult::err(&format!("Invalid date '{}', expected YYYY-MM-DD", date));
+    };
+    let offset = utc_offset_minutes.unwrap_or_else(|| nautical_zone_offset_minutes(lon));
+
+    let days = (0..days.unwrap_or(1).clamp(1, 366))
+        .map(|i| almanac_day(lat, lon, first + chrono::Duration::days(i as i64), offset))
+        .collect();
+    CommandResult::ok(days)
+}
+
+/// Sun and moon position, moon phase and light at a position (default now)
+#[tauri::command]
+pub fn get_sky_conditions(lat: f64, lon: f64, time: Option<String>) -> CommandResult<SkyConditions> {
+    let time = match time.as_deref().map(parse_tide_time) {
+        Some(Some(t)) => t,
+        Some(None) => return CommandResult::err("Invalid time"),
+        None => chrono::Utc::now(),
+    };
+    CommandResult::ok(sky_conditions(lat, lon, time))
+}
+
+/// Darkness on each leg of a route and at each arrival for a departure time
+#[tauri::command]
+pub fn get_route_daylight(
+    route_id: i64,
+    departure_time: String,
+    use_tidal_streams: Option<bool>,
+    state: State<AppState>,
+) -> CommandResult<Vec<RouteLegDaylight>> {
+    let departure = match parse_departure_time(&departure_time) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&e),
+    };
+
+    let route = match state.config_db.get_route(route_id) {
+        Ok(Some(route)) => route,
+        Ok(None) => return CommandResult::err(&format!("Route with id {} not found", route_id)),
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    let speed = route.route.estimated_speed_kn;
+    let stats = match route_statistics_at(&state, &route.waypoints, speed, departure, use_tidal_streams.unwrap_or(true)) {
+        Ok(s) => s,
+        Err(e) => return CommandResult::err(&e),
+    };
+    let table = build_eta_table(&route.waypoints, &stats, speed, departure, Some(1.0));
+
+    CommandResult::ok(route_daylight(&table, departure))
+}
+
+// ============ Celestial Commands ============
+
+/// Sun, moon, planets and navigational stars available for sights
+#[tauri::command]
+pub fn get_celestial_bodies() -> CommandResult<Vec<CelestialBodyInfo>> {
+    CommandResult::ok(celestial_bodies())
+}
+
+/// GHA, SHA and declination of a body at a time (default now)
+#[tauri::command]
+pub fn get_celestial_almanac(body: String, time: Option<String>) -> CommandResult<BodyAlmanac> {
+    let body = match Body::from_name(&body) {
+        Ok(b) => b,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let time = match time.as_deref().map(parse_sight_time) {
+        Some(Ok(t)) => t,
+        Some(Err(e)) => return CommandResult::err(&e.to_string()),
+        None => chrono::Utc::now(),
+    };
+    CommandResult::ok(body_almanac(body, time))
+}
+
+/// Reduce a single sight against an assumed or DR position
+#[tauri::command]
+pub fn reduce_celestial_sight(sight: Sight, lat: f64, lon: f64) -> CommandResult<SightReduction> {
+    match reduce_sight(&sight, lat, lon) {
+        Ok(reduction) => CommandResult::ok(reduction),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Fix from two or more sights, optionally saved as a waypoint
+#[tauri::command]
+pub fn compute_celestial_fix(
+    request: CelestialFixRequest,
+    save_as_waypoint: Option<String>,
+    state: State<AppState>,
+) -> CommandResult<CelestialFix> {
+    let mut fix = match compute_fix(&request) {
+        Ok(fix) => fix,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    if let Some(name) = save_as_waypoint {
+        let bodies: Vec<&str> = fix.reductions.iter().map(|r| r.body.as_str()).collect();
+        let waypoint = Waypoint {
+            id: None,
+            name,
+            lat: fix.lat,
+            lon: fix.lon,
+            description: Some(format!("Celestial fix at {} from {}", fix.time, bodies.join(", "))),
+            symbol: Some("fix".to_string()),
+            show_label: true,
+            hidden: false,
+            created_at: None,
+            uuid: None,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: None,
+            depth_m: None,
+            temperature_c: None,
+        };
+        match state.config_db.create_waypoint(&waypoint) {
+            Ok(id) => fix.waypoint_id = Some(id),
+            Err(e) => return CommandResult::err(&e.to_string()),
+        }
+    }
+
+    CommandResult::ok(fix)
+}
+
 // ============ GPS Commands ============
 
 #[tauri::command]
@@ -427,6 +1426,15 @@ pub fn test_gps_port(port_name: String, baud_rate: u32) -> CommandResult<bool> {
     }
 }
 
+/// Look for a Signal K server at "host[:port]"
+#[tauri::command]
+pub fn discover_signalk_server(address: String) -> CommandResult<SignalKServer> {
+    match signalk::discover(&address) {
+        Ok(server) => CommandResult::ok(server),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
 #[tauri::command]
 pub fn get_gps_sources(state: State<AppState>) -> CommandResult<Vec<GpsSourceConfig>> {
     // Load from database and convert to config
@@ -437,16 +1445,12 @@ pub fn get_gps_sources(state: State<AppState>) -> CommandResult<Vec<GpsSourceCon
                 .map(|r| GpsSourceConfig {
                     id: r.id,
                     name: r.name,
-                    source_type: match r.source_type.as_str() {
-                        "serial_port" => GpsSourceType::SerialPort,
-                        "tcp_stream" => GpsSourceType::TcpStream,
-                        "simulated" => GpsSourceType::Simulated,
-                        _ => GpsSourceType::SerialPort,
-                    },
+                    source_type: GpsSourceType::from_name(&r.source_type),
                     port_name: r.port_name,
                     baud_rate: r.baud_rate,
                     enabled: r.enabled,
                     priority: r.priority,
+                    raw_nmea: r.raw_nmea,
                 })
                 .collect();
             CommandResult::ok(configs)
@@ -460,15 +1464,12 @@ pub fn save_gps_source(source: GpsSourceConfig, state: State<AppState>) -> Comma
     let record = GpsSourceRecord {
         id: source.id,
         name: source.name,
-        source_type: match source.source_type {
-            GpsSourceType::SerialPort => "serial_port".to_string(),
-            GpsSourceType::TcpStream => "tcp_stream".to_string(),
-            GpsSourceType::Simulated => "simulated".to_string(),
-        },
+        source_type: source.source_type.as_str().to_string(),
         port_name: source.port_name,
         baud_rate: source.baud_rate,
         enabled: source.enabled,
         priority: source.priority,
+        raw_nmea: source.raw_nmea,
     };
 
     match state.config_db.save_gps_source(&record) {
@@ -496,103 +1497,437 @@ pub fn update_gps_priorities(priorities: Vec<(String, i32)>, state: State<AppSta
 }
 
 #[tauri::command]
-pub fn start_gps(state: State<AppState>) -> CommandResult<()> {
-    // Load sources from database
-    let sources = match state.config_db.get_gps_sources() {
-        Ok(records) => records
-            .into_iter()
-            .map(|r| GpsSourceConfig {
-                id: r.id,
-                name: r.name,
-                source_type: match r.source_type.as_str() {
-                    "serial_port" => GpsSourceType::SerialPort,
-                    "tcp_stream" => GpsSourceType::TcpStream,
-                    "simulated" => GpsSourceType::Simulated,
-                    _ => GpsSourceType::SerialPort,
-                },
-                port_name: r.port_name,
-                baud_rate: r.baud_rate,
-                enabled: r.enabled,
-                priority: r.priority,
-            })
-            .collect(),
-        Err(e) => return CommandResult::err(&e.to_string()),
-    };
-
-    // Set sources and start
-    state.gps_manager.set_sources(sources);
-
-    match state.gps_manager.start() {
+pub fn start_gps(state: State<AppState>) -> CommandResult<()> {
+    // Load sources from database
+    let sources = match state.config_db.get_gps_sources() {
+        Ok(records) => records
+            .into_iter()
+            .map(|r| GpsSourceConfig {
+                id: r.id,
+                name: r.name,
+                source_type: GpsSourceType::from_name(&r.source_type),
+                port_name: r.port_name,
+                baud_rate: r.baud_rate,
+                enabled: r.enabled,
+                priority: r.priority,
+                raw_nmea: r.raw_nmea,
+            })
+            .collect(),
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+
+    // Set sources and start
+    state.gps_manager.set_sources(sources);
+
+    match state.gps_manager.start() {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn stop_gps(state: State<AppState>) -> CommandResult<()> {
+    state.gps_manager.stop();
+    CommandResult::ok(())
+}
+
+#[tauri::command]
+pub fn get_nmea_buffer(state: State<AppState>) -> CommandResult<Vec<String>> {
+    CommandResult::ok(state.gps_manager.get_nmea_buffer())
+}
+
+#[tauri::command]
+pub fn clear_nmea_buffer(state: State<AppState>) -> CommandResult<()> {
+    state.gps_manager.clear_nmea_buffer();
+    CommandResult::ok(())
+}
+
+/// Depth, wind and environment readings from Signal K
+#[tauri::command]
+pub fn get_instrument_data(state: State<AppState>) -> CommandResult<InstrumentData> {
+    CommandResult::ok(state.gps_manager.get_instruments())
+}
+
+/// Vessels received over AIS
+#[tauri::command]
+pub fn get_ais_targets(state: State<AppState>) -> CommandResult<Vec<AisTarget>> {
+    CommandResult::ok(state.gps_manager.get_ais_targets())
+}
+
+/// Get GPS position filter and integrity settings
+#[tauri::command]
+pub fn get_position_filter_settings(state: State<AppState>) -> CommandResult<PositionFilterSettings> {
+    match state.config_db.get_position_filter_settings() {
+        Ok(settings) => CommandResult::ok(settings),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Save GPS position filter settings and apply them to the running GPS
+#[tauri::command]
+pub fn save_position_filter_settings(settings: PositionFilterSettings, state: State<AppState>) -> CommandResult<()> {
+    let gain = 0.0..=1.0;
+    if !gain.contains(&settings.position_alpha) || !gain.contains(&settings.velocity_beta) {
+        return CommandResult::err("Position and velocity gains must be between 0 and 1");
+    }
+    if !(settings.low_speed_kn.is_finite() && settings.low_speed_kn > 0.0) {
+        return CommandResult::err("Low speed threshold must be above 0 knots");
+    }
+    match state.config_db.save_position_filter_settings(&settings) {
+        Ok(_) => {
+            state.gps_manager.set_filter_settings(settings);
+            CommandResult::ok(())
+        }
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+// ============ Data Server Commands ============
+
+/// Start the NMEA/Signal K server, publishing whatever the app state holds at each broadcast
+pub fn start_data_server(app: &tauri::AppHandle, settings: &DataServerSettings) -> std::io::Result<DataServer> {
+    let app = app.clone();
+    DataServer::start(
+        settings,
+        Arc::new(move || {
+            let gps_manager = &app.state::<AppState>().gps_manager;
+            DataSnapshot {
+                gps: gps_manager.get_data(),
+                instruments: gps_manager.get_instruments(),
+                ais_targets: gps_manager.get_ais_targets(),
+                active_route: gps_manager.get_navigation(),
+            }
+        }),
+    )
+}
+
+#[tauri::command]
+pub fn get_data_server_settings(state: State<AppState>) -> CommandResult<DataServerSettings> {
+    match state.config_db.get_data_server_settings() {
+        Ok(settings) => CommandResult::ok(settings),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Save data server settings and restart (or stop) the server to match
+#[tauri::command]
+pub fn save_data_server_settings(
+    settings: DataServerSettings,
+    app: tauri::AppHandle,
+    state: State<AppState>,
+) -> CommandResult<DataServerStatus> {
+    if let Err(e) = state.config_db.save_data_server_settings(&settings) {
+        return CommandResult::err(&e.to_string());
+    }
+
+    let mut server = state.data_server.lock().unwrap();
+    // Release the ports before binding them again
+    *server = None;
+    if !settings.enabled {
+        return CommandResult::ok(DataServerStatus::default());
+    }
+    match start_data_server(&app, &settings) {
+        Ok(started) => {
+            let status = started.status();
+            *server = Some(started);
+            CommandResult::ok(status)
+        }
+        Err(e) => CommandResult::err(&format!("Failed to start data server: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn get_data_server_status(state: State<AppState>) -> CommandResult<DataServerStatus> {
+    let server = state.data_server.lock().unwrap();
+    CommandResult::ok(server.as_ref().map(DataServer::status).unwrap_or_default())
+}
+
+// ============ Sync Commands ============
+
+/// Start accepting pairing and sync requests from other devices
+pub fn start_sync_server(app: &tauri::AppHandle, settings: &SyncSettings) -> std::io::Result<SyncServer> {
+    let app = app.clone();
+    SyncServer::start(settings, Arc::new(move |f| f(&app.state::<AppState>().config_db)))
+}
+
+#[tauri::command]
+pub fn get_sync_settings(state: State<AppState>) -> CommandResult<SyncSettings> {
+    match state.config_db.get_sync_settings() {
+        Ok(settings) => CommandResult::ok(settings),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Save sync settings and restart (or stop) the sync server to match
+#[tauri::command]
+pub fn save_sync_settings(settings: SyncSettings, app: tauri::AppHandle, state: State<AppState>) -> CommandResult<SyncStatus> {
+    if let Err(e) = state.config_db.save_sync_settings(&settings) {
+        return CommandResult::err(&e.to_string());
+    }
+
+    let mut server = state.sync_server.lock().unwrap();
+    // Release the port before binding it again
+    *server = None;
+    if !settings.enabled {
+        return CommandResult::ok(SyncStatus::default());
+    }
+    match start_sync_server(&app, &settings) {
+        Ok(started) => {
+            let status = started.status();
+            *server = Some(started);
+            CommandResult::ok(status)
+        }
+        Err(e) => CommandResult::err(&format!("Failed to start sync server: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn get_sync_status(state: State<AppState>) -> CommandResult<SyncStatus> {
+    let server = state.sync_server.lock().unwrap();
+    CommandResult::ok(server.as_ref().map(SyncServer::status).unwrap_or_default())
+}
+
+/// Show a code for another device to pair with this one
+#[tauri::command]
+pub fn start_sync_pairing(state: State<AppState>) -> CommandResult<PairingCode> {
+    match state.sync_server.lock().unwrap().as_ref() {
+        Some(server) => CommandResult::ok(server.start_pairing()),
+        None => CommandResult::err("Turn on sync to pair with another device"),
+    }
+}
+
+/// Pair with the device at `address` (host:port) using the code it shows
+#[tauri::command]
+pub async fn pair_sync_device(
+    address: String,
+    code: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<SyncPeer>, ()> {
+    let own_port = state.sync_server.lock().unwrap().as_ref().map(SyncServer::port);
+    let result = tokio::task::spawn_blocking(move || {
+        pair_with(&app.state::<AppState>().config_db, &address, &code, own_port)
+    })
+    .await
+    .unwrap();
+
+    match result {
+        Ok(peer) => Ok(CommandResult::ok(peer)),
+        Err(e) => Ok(CommandResult::err(&e.to_string())),
+    }
+}
+
+#[tauri::command]
+pub fn get_sync_peers(state: State<AppState>) -> CommandResult<Vec<SyncPeer>> {
+    match state.config_db.get_sync_peers() {
+        Ok(peers) => CommandResult::ok(peers),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn remove_sync_peer(device_id: String, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_sync_peer(&device_id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Exchange changes with a paired device and report any conflicts
+#[tauri::command]
+pub async fn sync_with_device(
+    device_id: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CommandResult<SyncReport>, ()> {
+    let peer = match state.config_db.get_sync_peer(&device_id) {
+        Ok(Some(peer)) => peer,
+        Ok(None) => return Ok(CommandResult::err("Device is not paired")),
+        Err(e) => return Ok(CommandResult::err(&e.to_string())),
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        sync_with_peer(&app.state::<AppState>().config_db, &peer)
+    })
+    .await
+    .unwrap();
+
+    match result {
+        Ok(report) => Ok(CommandResult::ok(report)),
+        Err(e) => Ok(CommandResult::err(&e.to_string())),
+    }
+}
+
+/// Route and waypoint being navigated to, published by the data server and
+/// navigation progress events. None clears the target.
+#[tauri::command]
+pub fn set_navigation_target(
+    route_id: Option<i64>,
+    waypoint_index: usize,
+    state: State<AppState>,
+) -> CommandResult<Option<ActiveNavigation>> {
+    let navigation = match route_id {
+        Some(id) => match state.config_db.get_route(id) {
+            Ok(Some(route)) if waypoint_index < route.waypoints.len() => Some(ActiveNavigation {
+                route_id: route.route.id,
+                route_name: route.route.name,
+                waypoints: route.waypoints,
+                next_index: waypoint_index,
+            }),
+            Ok(Some(_)) => return CommandResult::err(&format!("Route {} has no waypoint {}", id, waypoint_index)),
+            Ok(None) => return CommandResult::err(&format!("Route with id {} not found", id)),
+            Err(e) => return CommandResult::err(&e.to_string()),
+        },
+        None => None,
+    };
+    state.gps_manager.set_navigation(navigation.clone());
+    CommandResult::ok(navigation)
+}
+
+// ============ Waypoint Commands ============
+
+#[tauri::command]
+pub fn get_waypoints(state: State<AppState>) -> CommandResult<Vec<Waypoint>> {
+    match state.config_db.get_waypoints() {
+        Ok(waypoints) => CommandResult::ok(waypoints),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn create_waypoint(waypoint: Waypoint, state: State<AppState>) -> CommandResult<i64> {
+    match state.config_db.create_waypoint(&waypoint) {
+        Ok(id) => CommandResult::ok(id),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn update_waypoint(waypoint: Waypoint, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.update_waypoint(&waypoint) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Update only the position (lat/lon) of a waypoint.
+/// This is safe for drag operations - it won't overwrite name/symbol/description.
+#[tauri::command]
+pub fn update_waypoint_position(id: i64, lat: f64, lon: f64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.update_waypoint_position(id, lat, lon) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn delete_waypoint(id: i64, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_waypoint(id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn toggle_waypoint_hidden(id: i64, hidden: bool, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.toggle_waypoint_hidden(id, hidden) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn move_waypoints_to_group(ids: Vec<i64>, group_id: Option<i64>, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.move_waypoints_to_group(&ids, group_id) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn set_waypoints_hidden(ids: Vec<i64>, hidden: bool, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.set_waypoints_hidden(&ids, hidden) {
         Ok(_) => CommandResult::ok(()),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
 #[tauri::command]
-pub fn stop_gps(state: State<AppState>) -> CommandResult<()> {
-    state.gps_manager.stop();
-    CommandResult::ok(())
+pub fn set_waypoints_show_label(ids: Vec<i64>, show_label: bool, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.set_waypoints_show_label(&ids, show_label) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
 }
 
 #[tauri::command]
-pub fn get_nmea_buffer(state: State<AppState>) -> CommandResult<Vec<String>> {
-    CommandResult::ok(state.gps_manager.get_nmea_buffer())
+pub fn set_waypoints_symbol(ids: Vec<i64>, symbol: Option<String>, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.set_waypoints_symbol(&ids, symbol.as_deref()) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
 }
 
 #[tauri::command]
-pub fn clear_nmea_buffer(state: State<AppState>) -> CommandResult<()> {
-    state.gps_manager.clear_nmea_buffer();
-    CommandResult::ok(())
+pub fn delete_waypoints(ids: Vec<i64>, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.delete_waypoints(&ids) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
 }
 
-// ============ Waypoint Commands ============
+// ============ Waypoint Group Commands ============
 
 #[tauri::command]
-pub fn get_waypoints(state: State<AppState>) -> CommandResult<Vec<Waypoint>> {
-    match state.config_db.get_waypoints() {
-        Ok(waypoints) => CommandResult::ok(waypoints),
+pub fn get_waypoint_groups(state: State<AppState>) -> CommandResult<Vec<WaypointGroup>> {
+    match state.config_db.get_waypoint_groups() {
+        Ok(groups) => CommandResult::ok(groups),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
 #[tauri::command]
-pub fn create_waypoint(waypoint: Waypoint, state: State<AppState>) -> CommandResult<i64> {
-    match state.config_db.create_waypoint(&waypoint) {
+pub fn create_waypoint_group(group: WaypointGroup, state: State<AppState>) -> CommandResult<i64> {
+    match state.config_db.create_waypoint_group(&group) {
         Ok(id) => CommandResult::ok(id),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
+/// Rename a group or move it under another group
 #[tauri::command]
-pub fn update_waypoint(waypoint: Waypoint, state: State<AppState>) -> CommandResult<()> {
-    match state.config_db.update_waypoint(&waypoint) {
+pub fn update_waypoint_group(group: WaypointGroup, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.update_waypoint_group(&group) {
         Ok(_) => CommandResult::ok(()),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
-/// Update only the position (lat/lon) of a waypoint.
-/// This is safe for drag operations - it won't overwrite name/symbol/description.
 #[tauri::command]
-pub fn update_waypoint_position(id: i64, lat: f64, lon: f64, state: State<AppState>) -> CommandResult<()> {
-    match state.config_db.update_waypoint_position(id, lat, lon) {
+pub fn toggle_waypoint_group_hidden(id: i64, hidden: bool, state: State<AppState>) -> CommandResult<()> {
+    match state.config_db.toggle_waypoint_group_hidden(id, hidden) {
         Ok(_) => CommandResult::ok(()),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
+/// Count the waypoints deleting a group would remove: those in it or its
+/// subgroups that no route uses
 #[tauri::command]
-pub fn delete_waypoint(id: i64, state: State<AppState>) -> CommandResult<()> {
-    match state.config_db.delete_waypoint(id) {
-        Ok(_) => CommandResult::ok(()),
+pub fn get_waypoint_group_exclusive_waypoint_count(id: i64, state: State<AppState>) -> CommandResult<usize> {
+    match state.config_db.get_exclusive_group_waypoints(id) {
+        Ok(waypoint_ids) => CommandResult::ok(waypoint_ids.len()),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
 
+/// Delete a group and its subgroups, optionally with their waypoints. Waypoints
+/// used by a route are kept and move to the parent group.
+/// Returns the IDs of waypoints that were deleted.
 #[tauri::command]
-pub fn toggle_waypoint_hidden(id: i64, hidden: bool, state: State<AppState>) -> CommandResult<()> {
-    match state.config_db.toggle_waypoint_hidden(id, hidden) {
-        Ok(_) => CommandResult::ok(()),
+pub fn delete_waypoint_group(id: i64, delete_waypoints: bool, state: State<AppState>) -> CommandResult<Vec<i64>> {
+    match state.config_db.delete_waypoint_group(id, delete_waypoints) {
+        Ok(deleted_waypoint_ids) => CommandResult::ok(deleted_waypoint_ids),
         Err(e) => CommandResult::err(&e.to_string()),
     }
 }
@@ -746,6 +2081,7 @@ pub fn delete_route_tag(id: i64, state: State<AppState>) -> CommandResult<()> {
 pub fn calculate_route_statistics(
     waypoint_ids: Vec<i64>,
     speed_kn: f64,
+    departure_time: Option<String>,
     state: State<AppState>,
 ) -> CommandResult<RouteStatistics> {
     // Get waypoints by their IDs in order
@@ -758,74 +2094,57 @@ pub fn calculate_route_statistics(
         }
     }
 
-    // Calculate statistics
-    let stats = calculate_statistics(&waypoints, speed_kn);
-    CommandResult::ok(stats)
-}
-
-/// Calculate route statistics from a list of waypoints
-fn calculate_statistics(waypoints: &[Waypoint], speed_kn: f64) -> RouteStatistics {
-    let mut total_distance_nm = 0.0;
-    let mut leg_distances = Vec::new();
-    let mut leg_bearings = Vec::new();
-
-    for i in 0..waypoints.len().saturating_sub(1) {
-        let from = &waypoints[i];
-        let to = &waypoints[i + 1];
-
-        let distance = haversine_distance(from.lat, from.lon, to.lat, to.lon);
-        let bearing = calculate_bearing(from.lat, from.lon, to.lat, to.lon);
-
-        total_distance_nm += distance;
-        leg_distances.push(distance);
-        leg_bearings.push(bearing);
-    }
-
-    let estimated_time_hours = if speed_kn > 0.0 {
-        total_distance_nm / speed_kn
-    } else {
-        0.0
-    };
-
-    RouteStatistics {
-        total_distance_nm,
-        waypoint_count: waypoints.len(),
-        estimated_time_hours,
-        leg_distances,
-        leg_bearings,
+    // With a departure time, leg times allow for fair or foul tide
+    match departure_time {
+        Some(time) => {
+            let departure = match parse_departure_time(&time) {
+                Ok(t) => t,
+                Err(e) => return CommandResult::err(&e),
+            };
+            match route_statistics_at(&state, &waypoints, speed_kn, departure, true) {
+                Ok(stats) => CommandResult::ok(stats),
+                Err(e) => CommandResult::err(&e),
+            }
+        }
+        None => CommandResult::ok(calculate_statistics(&waypoints, speed_kn)),
     }
 }
 
-/// Calculate distance between two points using Haversine formula
-fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    const EARTH_RADIUS_NM: f64 = 3440.065; // Earth radius in nautical miles
-
-    let lat1_rad = lat1.to_radians();
-    let lat2_rad = lat2.to_radians();
-    let delta_lat = (lat2 - lat1).to_radians();
-    let delta_lon = (lon2 - lon1).to_radians();
-
-    let a = (delta_lat / 2.0).sin().powi(2)
-        + lat1_rad.cos() * lat2_rad.cos() * (delta_lon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().asin();
-
-    EARTH_RADIUS_NM * c
+/// Parse an RFC 3339 departure time
+fn parse_departure_time(time: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    chrono::DateTime::parse_from_rfc3339(time)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .map_err(|e| format!("Invalid departure time '{}': {}", time, e))
 }
 
-/// Calculate initial bearing from point 1 to point 2
-fn calculate_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let lat1_rad = lat1.to_radians();
-    let lat2_rad = lat2.to_radians();
-    let delta_lon = (lon2 - lon1).to_radians();
-
-    let y = delta_lon.sin() * lat2_rad.cos();
-    let x = lat1_rad.cos() * lat2_rad.sin() - lat1_rad.sin() * lat2_rad.cos() * delta_lon.cos();
-
-    let bearing_rad = y.atan2(x);
-    let bearing_deg = bearing_rad.to_degrees();
+/// Get the ETA table for a route: every waypoint plus optional intermediate
+/// points every `interval_hours`, with the time we expect to be there.
+/// Tidal streams are allowed for unless `use_tidal_streams` is false.
+#[tauri::command]
+pub fn get_route_eta_table(
+    route_id: i64,
+    departure_time: String,
+    interval_hours: Option<f64>,
+    use_tidal_streams: Option<bool>,
+    state: State<AppState>,
+) -> CommandResult<Vec<RouteEtaPoint>> {
+    let departure = match parse_departure_time(&departure_time) {
+        Ok(t) => t,
+        Err(e) => return CommandResult::err(&e),
+    };
 
-    // Normalize to 0-360
-    (bearing_deg + 360.0) % 360.0
+    match state.config_db.get_route(route_id) {
+        Ok(Some(route)) => {
+            let speed = route.route.estimated_speed_kn;
+            let stats = match route_statistics_at(&state, &route.waypoints, speed, departure, use_tidal_streams.unwrap_or(true)) {
+                Ok(s) => s,
+                Err(e) => return CommandResult::err(&e),
+            };
+            CommandResult::ok(build_eta_table(&route.waypoints, &stats, speed, departure, interval_hours))
+        }
+        Ok(None) => CommandResult::err(&format!("Route with id {} not found", route_id)),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
 }
 
 // ============ Track Commands ============
@@ -1055,6 +2374,12 @@ pub fn convert_track_to_route(
             show_label: true,
             hidden: false,
             created_at: None,
+            uuid: None,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: None,
+            depth_m: None,
+            temperature_c: None,
         };
 
         match state.config_db.create_waypoint(&waypoint) {
@@ -1102,9 +2427,64 @@ fn simplify_track_points(points: &[TrackPoint], max_waypoints: usize) -> Vec<Tra
     result
 }
 
+// ============ Search Commands ============
+
+/// The `count` waypoints, routes and tracks nearest a position, with distance
+/// and bearing from it. Omitting `kinds` searches all of them.
+#[tauri::command]
+pub fn search_nearest(lat: f64, lon: f64, count: usize, kinds: Option<Vec<SearchKind>>, state: State<AppState>) -> CommandResult<Vec<SearchResult>> {
+    match search::nearest(&state.config_db, lat, lon, count, &kinds.unwrap_or_default()) {
+        Ok(results) => CommandResult::ok(results),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn search_within_radius(lat: f64, lon: f64, radius_nm: f64, kinds: Option<Vec<SearchKind>>, state: State<AppState>) -> CommandResult<Vec<SearchResult>> {
+    match search::within_radius(&state.config_db, lat, lon, radius_nm, &kinds.unwrap_or_default()) {
+        Ok(results) => CommandResult::ok(results),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Objects inside a box, measured from `origin_lat`/`origin_lon` when given
+#[tauri::command]
+pub fn search_within_bbox(
+    bbox: BoundingBox,
+    origin_lat: Option<f64>,
+    origin_lon: Option<f64>,
+    kinds: Option<Vec<SearchKind>>,
+    state: State<AppState>,
+) -> CommandResult<Vec<SearchResult>> {
+    let origin = origin_lat.zip(origin_lon);
+    match search::within_bbox(&state.config_db, &bbox, origin, &kinds.unwrap_or_default()) {
+        Ok(results) => CommandResult::ok(results),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
+/// Name and description search; each word matches as a prefix
+#[tauri::command]
+pub fn search_text(
+    query: String,
+    origin_lat: Option<f64>,
+    origin_lon: Option<f64>,
+    kinds: Option<Vec<SearchKind>>,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> CommandResult<Vec<SearchResult>> {
+    let origin = origin_lat.zip(origin_lon);
+    match search::text(&state.config_db, &query, origin, &kinds.unwrap_or_default(), limit.unwrap_or(50)) {
+        Ok(results) => CommandResult::ok(results),
+        Err(e) => CommandResult::err(&e.to_string()),
+    }
+}
+
 // ============ GPX Import/Export Commands ============
 
-use crate::gpx::{self, GpxImportResult, GpxRoute, GpxRoutePoint};
+use crate::geojson;
+use crate::gpx::{self, GpxDisplayMode, GpxExtensions, GpxImportResult, GpxRoute, GpxRoutePoint, GpxWaypoint, ParsedGpx};
+use crate::kml;
 
 /// Import a GPX file, creating routes and waypoints
 #[tauri::command]
@@ -1117,154 +2497,227 @@ pub fn import_gpx(file_path: String, state: State<AppState>) -> CommandResult<Gp
         Err(e) => return CommandResult::err(&format!("Failed to parse GPX file: {}", e)),
     };
 
-    let mut result = GpxImportResult {
-        routes_imported: 0,
-        waypoints_imported: 0,
-        tracks_imported: 0,
-        errors: Vec::new(),
-    };
+    CommandResult::ok(import_parsed_gpx(&state.config_db, parsed))
+}
+
+/// Store parsed GPX content. Anything carrying a uuid already known here is
+/// updated in place, so re-importing an exported file doesn't duplicate it.
+pub(crate) fn import_parsed_gpx(db: &ConfigDatabase, parsed: ParsedGpx) -> GpxImportResult {
+    let mut result = GpxImportResult::default();
 
     // Import standalone waypoints
     for wpt in parsed.waypoints {
         let waypoint = Waypoint {
             id: None,
-            name: wpt.name.unwrap_or_else(|| format!("Waypoint {}", result.waypoints_imported + 1)),
+            name: wpt.name.unwrap_or_else(|| format!("Waypoint {}", result.waypoints_imported + result.waypoints_updated + 1)),
             lat: wpt.lat,
             lon: wpt.lon,
             description: wpt.desc,
             symbol: wpt.sym,
-            show_label: true,
+            show_label: wpt.extensions.display_mode.is_none_or(GpxDisplayMode::shows_label),
             hidden: false,
             created_at: None,
+            uuid: wpt.uuid,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: wpt.extensions.arrival_radius_nm,
+            depth_m: wpt.extensions.depth_m,
+            temperature_c: wpt.extensions.temperature_c,
         };
 
-        match state.config_db.create_waypoint(&waypoint) {
-            Ok(_) => result.waypoints_imported += 1,
+        match save_gpx_waypoint(db, waypoint) {
+            Ok((_, true)) => result.waypoints_updated += 1,
+            Ok((_, false)) => result.waypoints_imported += 1,
             Err(e) => result.errors.push(format!("Failed to import waypoint: {}", e)),
         }
     }
 
     // Import routes
     for rte in parsed.routes {
-        let route_name = rte.name.unwrap_or_else(|| format!("Imported Route {}", result.routes_imported + 1));
-
-        // First, create waypoints for route points
-        let mut waypoint_ids = Vec::new();
-        for (idx, pt) in rte.points.iter().enumerate() {
-            let waypoint = Waypoint {
-                id: None,
-                name: pt.name.clone().unwrap_or_else(|| format!("{} - WP{}", route_name, idx + 1)),
-                lat: pt.lat,
-                lon: pt.lon,
-                description: pt.desc.clone(),
-                symbol: pt.sym.clone(),
-                show_label: true,
-                hidden: false,
-                created_at: None,
-            };
+        let route_name = rte.name.unwrap_or_else(|| format!("Imported Route {}", result.routes_imported + result.routes_updated + 1));
 
-            match state.config_db.create_waypoint(&waypoint) {
-                Ok(id) => {
-                    waypoint_ids.push(id);
-                    result.waypoints_imported += 1;
-                }
-                Err(e) => {
-                    result.errors.push(format!("Failed to create waypoint for route: {}", e));
+        let waypoints = rte.points.iter().enumerate().map(|(idx, pt)| Waypoint {
+            id: None,
+            name: pt.name.clone().unwrap_or_else(|| format!("{} - WP{}", route_name, idx + 1)),
+            lat: pt.lat,
+            lon: pt.lon,
+            description: pt.desc.clone(),
+            symbol: pt.sym.clone(),
+            show_label: pt.extensions.display_mode.is_none_or(GpxDisplayMode::shows_label),
+            hidden: false,
+            created_at: None,
+            uuid: pt.uuid.clone(),
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: pt.extensions.arrival_radius_nm,
+            depth_m: pt.extensions.depth_m,
+            temperature_c: pt.extensions.temperature_c,
+        }).collect();
+
+        let route = Route {
+            id: None,
+            name: route_name.clone(),
+            description: rte.desc,
+            color: rte.color.or_else(|| Some("#c026d3".to_string())), // Default magenta
+            is_active: false,
+            hidden: false,
+            total_distance_nm: None, // Will be calculated
+            estimated_speed_kn: 5.0,
+            created_at: None,
+            updated_at: None,
+            uuid: rte.uuid,
+        };
+
+        match save_gpx_route(db, route, waypoints, &mut result) {
+            Ok(Some(true)) => result.routes_updated += 1,
+            Ok(Some(false)) => result.routes_imported += 1,
+            Ok(None) => {}
+            Err(e) => result.errors.push(format!("Failed to create route '{}': {}", route_name, e)),
+        }
+    }
+
+    // Import tracks, keeping their segments
+    for trk in parsed.tracks {
+        let track_name = trk.name.clone().unwrap_or_else(|| format!("Imported Track {}", result.tracks_imported + result.tracks_updated + 1));
+
+        // A track exported from this device only needs its details refreshed
+        match find_gpx_track(db, trk.uuid.as_deref()) {
+            Ok(Some(track)) => {
+                let updated = Track { name: track_name, description: trk.desc, ..track };
+                match db.update_track(&updated) {
+                    Ok(_) => result.tracks_updated += 1,
+                    Err(e) => result.errors.push(format!("Failed to update track '{}': {}", updated.name, e)),
                 }
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                result.errors.push(format!("Failed to look up track '{}': {}", track_name, e));
+                continue;
             }
         }
 
-        // Create the route
-        if !waypoint_ids.is_empty() {
-            let route = Route {
-                id: None,
-                name: route_name.clone(),
-                description: rte.desc,
-                color: Some("#c026d3".to_string()), // Default magenta
-                is_active: false,
-                hidden: false,
-                total_distance_nm: None, // Will be calculated
-                estimated_speed_kn: 5.0,
-                created_at: None,
-                updated_at: None,
-            };
+        let points = gpx::track_points_from_gpx(&trk);
+        if points.is_empty() {
+            continue;
+        }
+        let (started_at, ended_at) = gpx::track_time_span(&points);
+        let track = Track {
+            name: track_name.clone(),
+            description: trk.desc,
+            color: trk.color.or(Track::default().color),
+            started_at,
+            ended_at,
+            total_distance_nm: Some(gpx::track_distance_nm(&points)),
+            point_count: points.len() as i64,
+            uuid: trk.uuid,
+            ..Track::default()
+        };
 
-            match state.config_db.create_route(&route, &waypoint_ids, &[]) {
-                Ok(_) => result.routes_imported += 1,
-                Err(e) => result.errors.push(format!("Failed to create route '{}': {}", route_name, e)),
-            }
+        match db.import_track(&track, &points) {
+            Ok(_) => result.tracks_imported += 1,
+            Err(e) => result.errors.push(format!("Failed to import track '{}': {}", track_name, e)),
         }
     }
 
-    // Import tracks (convert to routes)
-    for trk in parsed.tracks {
-        let track_name = trk.name.unwrap_or_else(|| format!("Imported Track {}", result.tracks_imported + 1));
-
-        // Combine all segments into one route
-        let mut all_points: Vec<&GpxRoutePoint> = Vec::new();
-        for seg in &trk.segments {
-            all_points.extend(seg.iter());
-        }
+    result
+}
 
-        if all_points.is_empty() {
-            continue;
+/// Create a waypoint, or update the existing one with the same uuid.
+/// Returns the id and whether it already existed.
+fn save_gpx_waypoint(db: &ConfigDatabase, waypoint: Waypoint) -> rusqlite::Result<(i64, bool)> {
+    let existing = match waypoint.uuid.as_deref() {
+        Some(uuid) => db.find_waypoint_id(uuid)?,
+        None => None,
+    };
+    match existing.map(|id| db.get_waypoint(id)).transpose()?.flatten() {
+        Some(current) => {
+            // Display preferences are local to this device
+            let updated = Waypoint { id: current.id, show_label: current.show_label, hidden: current.hidden, ..waypoint };
+            db.update_waypoint(&updated)?;
+            Ok((current.id.unwrap_or_default(), true))
         }
+        None => Ok((db.create_waypoint(&waypoint)?, false)),
+    }
+}
 
-        // Create waypoints for track points (may be many, so sample if needed)
-        let mut waypoint_ids = Vec::new();
-        let sample_rate = if all_points.len() > 50 { all_points.len() / 50 } else { 1 };
+/// Create a route, or update the existing one with the same uuid.
+/// Returns whether it already existed, or None when none of its points could be saved.
+fn save_gpx_route(
+    db: &ConfigDatabase,
+    route: Route,
+    mut waypoints: Vec<Waypoint>,
+    result: &mut GpxImportResult,
+) -> rusqlite::Result<Option<bool>> {
+    let existing = match route.uuid.as_deref() {
+        Some(uuid) => db.find_route_id(uuid)?.map(|id| db.get_route(id)).transpose()?.flatten(),
+        None => None,
+    };
 
-        for (idx, pt) in all_points.iter().enumerate() {
-            if idx % sample_rate != 0 && idx != all_points.len() - 1 {
-                continue; // Sample points for large tracks, but always include last point
+    // Points without a uuid of their own replace the local point in the same position
+    if let Some(current) = &existing {
+        for (waypoint, local) in waypoints.iter_mut().zip(&current.waypoints) {
+            if waypoint.uuid.is_none() {
+                waypoint.uuid = local.uuid.clone();
             }
+        }
+    }
 
-            let waypoint = Waypoint {
-                id: None,
-                name: pt.name.clone().unwrap_or_else(|| format!("{} - PT{}", track_name, waypoint_ids.len() + 1)),
-                lat: pt.lat,
-                lon: pt.lon,
-                description: pt.desc.clone(),
-                symbol: pt.sym.clone(),
-                show_label: false, // Tracks often have many points
-                hidden: false,
-                created_at: None,
-            };
-
-            match state.config_db.create_waypoint(&waypoint) {
-                Ok(id) => {
-                    waypoint_ids.push(id);
+    let mut waypoint_ids = Vec::new();
+    for waypoint in waypoints {
+        match save_gpx_waypoint(db, waypoint) {
+            Ok((id, updated)) => {
+                waypoint_ids.push(id);
+                if updated {
+                    result.waypoints_updated += 1;
+                } else {
                     result.waypoints_imported += 1;
                 }
-                Err(e) => {
-                    result.errors.push(format!("Failed to create waypoint for track: {}", e));
-                }
             }
+            Err(e) => result.errors.push(format!("Failed to create waypoint for route: {}", e)),
         }
+    }
+    if waypoint_ids.is_empty() {
+        return Ok(None);
+    }
 
-        // Create the route from track
-        if !waypoint_ids.is_empty() {
-            let route = Route {
-                id: None,
-                name: track_name.clone(),
-                description: trk.desc,
-                color: Some("#3b82f6".to_string()), // Blue for tracks
-                is_active: false,
-                hidden: false,
+    match existing {
+        Some(current) => {
+            // Keep this device's styling, tags and active state
+            let updated = Route {
+                id: current.route.id,
+                name: route.name,
+                description: route.description,
                 total_distance_nm: None,
-                estimated_speed_kn: 5.0,
-                created_at: None,
-                updated_at: None,
+                ..current.route
             };
-
-            match state.config_db.create_route(&route, &waypoint_ids, &[]) {
-                Ok(_) => result.tracks_imported += 1,
-                Err(e) => result.errors.push(format!("Failed to create route from track '{}': {}", track_name, e)),
-            }
+            let tag_ids: Vec<i64> = current.tags.iter().filter_map(|t| t.id).collect();
+            db.update_route(&updated, &waypoint_ids, &tag_ids)?;
+            Ok(Some(true))
+        }
+        None => {
+            db.create_route(&route, &waypoint_ids, &[])?;
+            Ok(Some(false))
         }
     }
+}
 
-    CommandResult::ok(result)
+/// GPX extensions of a stored waypoint: display mode, arrival radius, depth and temperature
+fn waypoint_extensions(wp: &Waypoint) -> GpxExtensions {
+    GpxExtensions {
+        display_mode: Some(if wp.show_label { GpxDisplayMode::Name } else { GpxDisplayMode::Symbol }),
+        arrival_radius_nm: wp.arrival_radius_nm,
+        depth_m: wp.depth_m,
+        temperature_c: wp.temperature_c,
+        ..Default::default()
+    }
+}
+
+fn find_gpx_track(db: &ConfigDatabase, uuid: Option<&str>) -> rusqlite::Result<Option<Track>> {
+    match uuid {
+        Some(uuid) => Ok(db.find_track_id(uuid)?.map(|id| db.get_track(id)).transpose()?.flatten()),
+        None => Ok(None),
+    }
 }
 
 /// Export a single route to a GPX file
@@ -1281,6 +2734,8 @@ pub fn export_route_gpx(route_id: i64, file_path: String, state: State<AppState>
     let gpx_route = GpxRoute {
         name: Some(route_with_waypoints.route.name.clone()),
         desc: route_with_waypoints.route.description.clone(),
+        uuid: route_with_waypoints.route.uuid.clone(),
+        color: route_with_waypoints.route.color.clone(),
         points: route_with_waypoints.waypoints.iter().map(|wp| GpxRoutePoint {
             name: Some(wp.name.clone()),
             lat: wp.lat,
@@ -1289,6 +2744,8 @@ pub fn export_route_gpx(route_id: i64, file_path: String, state: State<AppState>
             time: None,
             desc: wp.description.clone(),
             sym: wp.symbol.clone(),
+            uuid: wp.uuid.clone(),
+            extensions: waypoint_extensions(wp),
         }).collect(),
     };
 
@@ -1321,6 +2778,8 @@ pub fn export_routes_gpx(route_ids: Vec<i64>, file_path: String, state: State<Ap
         gpx_routes.push(GpxRoute {
             name: Some(route_with_waypoints.route.name.clone()),
             desc: route_with_waypoints.route.description.clone(),
+            uuid: route_with_waypoints.route.uuid.clone(),
+            color: route_with_waypoints.route.color.clone(),
             points: route_with_waypoints.waypoints.iter().map(|wp| GpxRoutePoint {
                 name: Some(wp.name.clone()),
                 lat: wp.lat,
@@ -1329,6 +2788,8 @@ pub fn export_routes_gpx(route_ids: Vec<i64>, file_path: String, state: State<Ap
                 time: None,
                 desc: wp.description.clone(),
                 sym: wp.symbol.clone(),
+                uuid: wp.uuid.clone(),
+                extensions: waypoint_extensions(wp),
             }).collect(),
         });
     }
@@ -1360,6 +2821,8 @@ pub fn get_route_gpx_string(route_id: i64, state: State<AppState>) -> CommandRes
     let gpx_route = GpxRoute {
         name: Some(route_with_waypoints.route.name.clone()),
         desc: route_with_waypoints.route.description.clone(),
+        uuid: route_with_waypoints.route.uuid.clone(),
+        color: route_with_waypoints.route.color.clone(),
         points: route_with_waypoints.waypoints.iter().map(|wp| GpxRoutePoint {
             name: Some(wp.name.clone()),
             lat: wp.lat,
@@ -1368,6 +2831,8 @@ pub fn get_route_gpx_string(route_id: i64, state: State<AppState>) -> CommandRes
             time: None,
             desc: wp.description.clone(),
             sym: wp.symbol.clone(),
+            uuid: wp.uuid.clone(),
+            extensions: waypoint_extensions(wp),
         }).collect(),
     };
 
@@ -1410,6 +2875,197 @@ pub fn get_route_summary_text(route_id: i64, state: State<AppState>) -> CommandR
     CommandResult::ok(summary)
 }
 
+// ============ KML and GeoJSON Commands ============
+
+/// Import a KML or KMZ file the same way as a GPX file
+#[tauri::command]
+pub fn import_kml(file_path: String, state: State<AppState>) -> CommandResult<GpxImportResult> {
+    match kml::parse_kml_file(std::path::Path::new(&file_path)) {
+        Ok(parsed) => CommandResult::ok(import_parsed_gpx(&state.config_db, parsed)),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Import a GeoJSON file the same way as a GPX file
+#[tauri::command]
+pub fn import_geojson(file_path: String, state: State<AppState>) -> CommandResult<GpxImportResult> {
+    match geojson::parse_geojson_file(std::path::Path::new(&file_path)) {
+        Ok(parsed) => CommandResult::ok(import_parsed_gpx(&state.config_db, parsed)),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Export routes, waypoints and tracks to KML, zipped as KMZ when the path ends in .kmz
+#[tauri::command]
+pub fn export_kml(
+    route_ids: Vec<i64>,
+    waypoint_ids: Vec<i64>,
+    track_ids: Vec<i64>,
+    file_path: String,
+    state: State<AppState>,
+) -> CommandResult<()> {
+    let data = match collect_export(&state.config_db, &route_ids, &waypoint_ids, &track_ids) {
+        Ok(data) => data,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    match kml::write_kml_file(std::path::Path::new(&file_path), &data, "VortexNav Export") {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Export routes, waypoints and tracks to a GeoJSON FeatureCollection
+#[tauri::command]
+pub fn export_geojson(
+    route_ids: Vec<i64>,
+    waypoint_ids: Vec<i64>,
+    track_ids: Vec<i64>,
+    file_path: String,
+    state: State<AppState>,
+) -> CommandResult<()> {
+    let data = match collect_export(&state.config_db, &route_ids, &waypoint_ids, &track_ids) {
+        Ok(data) => data,
+        Err(e) => return CommandResult::err(&e.to_string()),
+    };
+    let json = match geojson::generate_geojson(&data) {
+        Ok(json) => json,
+        Err(e) => return CommandResult::err(&e),
+    };
+    match std::fs::write(&file_path, json) {
+        Ok(_) => CommandResult::ok(()),
+        Err(e) => CommandResult::err(&format!("Failed to write GeoJSON file: {}", e)),
+    }
+}
+
+/// Gather routes, waypoints and tracks into the shared export structure.
+/// IDs that no longer exist are skipped.
+fn collect_export(db: &ConfigDatabase, route_ids: &[i64], waypoint_ids: &[i64], track_ids: &[i64]) -> rusqlite::Result<ParsedGpx> {
+    let mut data = ParsedGpx { waypoints: vec![], routes: vec![], tracks: vec![] };
+
+    for id in waypoint_ids {
+        if let Some(wp) = db.get_waypoint(*id)? {
+            data.waypoints.push(GpxWaypoint {
+                extensions: waypoint_extensions(&wp),
+                name: Some(wp.name),
+                lat: wp.lat,
+                lon: wp.lon,
+                ele: None,
+                time: None,
+                desc: wp.description,
+                sym: wp.symbol,
+                uuid: wp.uuid,
+            });
+        }
+    }
+
+    for id in route_ids {
+        if let Some(route) = db.get_route(*id)? {
+            data.routes.push(GpxRoute {
+                name: Some(route.route.name),
+                desc: route.route.description,
+                uuid: route.route.uuid,
+                color: route.route.color,
+                points: route.waypoints.into_iter().map(|wp| GpxRoutePoint {
+                    extensions: waypoint_extensions(&wp),
+                    name: Some(wp.name),
+                    lat: wp.lat,
+                    lon: wp.lon,
+                    ele: None,
+                    time: None,
+                    desc: wp.description,
+                    sym: wp.symbol,
+                    uuid: wp.uuid,
+                }).collect(),
+            });
+        }
+    }
+
+    for id in track_ids {
+        if let Some(track) = db.get_track_with_points(*id)? {
+            data.tracks.push(gpx::track_to_gpx(&track));
+        }
+    }
+
+    Ok(data)
+}
+
+// ============ OpenCPN Import Commands ============
+
+/// Import OpenCPN's navobj.xml, or a GPX file exported from OpenCPN, keeping
+/// its GUIDs, visibility, arrival radii, planned speeds, colours and real tracks
+#[tauri::command]
+pub fn import_opencpn(file_path: String, state: State<AppState>) -> CommandResult<GpxImportResult> {
+    match opencpn::parse_navobj_file(std::path::Path::new(&file_path)) {
+        Ok(navobj) => CommandResult::ok(opencpn::import_navobj(&state.config_db, navobj)),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+// ============ CSV Import Commands ============
+
+use crate::csv_import::{self, CsvImportOptions, CsvPreview};
+
+/// Read a CSV, TSV or spreadsheet waypoint list without importing it, showing
+/// the detected layout and each row's position or the reason it can't be read
+#[tauri::command]
+pub fn preview_csv_import(file_path: String, options: CsvImportOptions) -> CommandResult<CsvPreview> {
+    match csv_import::preview_file(std::path::Path::new(&file_path), &options) {
+        Ok(preview) => CommandResult::ok(preview),
+        Err(e) => CommandResult::err(&e),
+    }
+}
+
+/// Import a CSV, TSV or spreadsheet waypoint list as waypoints, or as a route
+/// through the rows in order. Rows that can't be read are reported as errors.
+#[tauri::command]
+pub fn import_csv(file_path: String, options: CsvImportOptions, state: State<AppState>) -> CommandResult<GpxImportResult> {
+    let path = std::path::Path::new(&file_path);
+    let preview = match csv_import::preview_file(path, &options) {
+        Ok(preview) => preview,
+        Err(e) => return CommandResult::err(&e),
+    };
+
+    let route_name = options
+        .route_name
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "Imported Route".to_string());
+    let (parsed, row_errors) = csv_import::to_parsed_gpx(&preview, options.build_route, &route_name);
+
+    let mut result = import_parsed_gpx(&state.config_db, parsed);
+    result.errors.splice(0..0, row_errors);
+    CommandResult::ok(result)
+}
+
+// ============ User Data Backup Commands ============
+
+use crate::user_data::{self, ArchiveManifest, ImportMode, ImportReport};
+
+/// Export all user data (waypoints, routes, tracks, settings, sources, charts) to one archive
+#[tauri::command]
+pub fn export_user_data(file_path: String, state: State<AppState>) -> CommandResult<ArchiveManifest> {
+    match user_data::export_archive(&state.config_db, std::path::Path::new(&file_path)) {
+        Ok(manifest) => CommandResult::ok(manifest),
+        Err(e) => CommandResult::err(&format!("Failed to export user data: {}", e)),
+    }
+}
+
+/// Restore user data from an archive. With dry_run nothing is written and the
+/// report shows what the import would change.
+#[tauri::command]
+pub fn import_user_data(
+    file_path: String,
+    mode: ImportMode,
+    dry_run: bool,
+    state: State<AppState>,
+) -> CommandResult<ImportReport> {
+    match user_data::import_archive(&state.config_db, std::path::Path::new(&file_path), mode, dry_run) {
+        Ok(report) => CommandResult::ok(report),
+        Err(e) => CommandResult::err(&format!("Failed to import user data: {}", e)),
+    }
+}
+
 // ============ MBTiles Commands ============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,566 @@
+// CSV, TSV and spreadsheet waypoint list importer
+// Reads tables of marks with coordinates in decimal, DM or DMS notation and previews them before import
+
+use calamine::{open_workbook_auto, Reader};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::datum::Datum;
+use crate::gpx::{GpxRoute, GpxRoutePoint, GpxWaypoint, ParsedGpx};
+
+const SPREADSHEET_EXTENSIONS: [&str; 5] = ["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Rows looked at when detecting the delimiter and columns
+const SAMPLE_ROWS: usize = 20;
+
+/// Which column holds what; indexes are zero-based
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub name: Option<usize>,
+    pub lat: Option<usize>,
+    pub lon: Option<usize>,
+    /// Latitude and longitude together, e.g. `36°50.5'S 174°45.2'E`
+    pub position: Option<usize>,
+    pub description: Option<usize>,
+    pub symbol: Option<usize>,
+}
+
+impl ColumnMapping {
+    fn has_coordinates(&self) -> bool {
+        self.position.is_some() || (self.lat.is_some() && self.lon.is_some())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvImportOptions {
+    /// Field separator for text files; detected when not given
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// Whether the first row names the columns; detected when not given
+    #[serde(default)]
+    pub has_header: Option<bool>,
+    /// Detected from the header or the contents when not given
+    #[serde(default)]
+    pub mapping: Option<ColumnMapping>,
+    /// Datum the coordinates are given on
+    #[serde(default)]
+    pub datum: Datum,
+    /// Also build a route through the rows in file order
+    #[serde(default)]
+    pub build_route: bool,
+    #[serde(default)]
+    pub route_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvPreviewRow {
+    /// Row number in the file, counting from 1
+    pub row: usize,
+    pub name: Option<String>,
+    /// WGS84, after any datum shift
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub description: Option<String>,
+    pub symbol: Option<String>,
+    /// Why the row can't be imported
+    pub error: Option<String>,
+}
+
+/// How a file was read and what it would import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvPreview {
+    /// None for spreadsheets
+    pub delimiter: Option<char>,
+    pub has_header: bool,
+    /// Header names, or "Column N" without a header
+    pub columns: Vec<String>,
+    pub mapping: ColumnMapping,
+    pub rows: Vec<CsvPreviewRow>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Lat,
+    Lon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    /// N, S, E or W
+    Hemisphere(char),
+}
+
+/// Split coordinate text into numbers and hemisphere letters. Degree, minute and
+/// second marks and separators are dropped; any other word means it isn't a coordinate.
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next_is_digit = chars.get(i + 1).is_some_and(|n| n.is_ascii_digit() || *n == '.');
+        // A dash straight after a digit separates degrees and minutes rather than negating
+        let is_sign = matches!(c, '-' | '+' | '\u{2212}') && next_is_digit && (i == 0 || !chars[i - 1].is_ascii_digit());
+        if c.is_ascii_digit() || (c == '.' && next_is_digit) || is_sign {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect::<String>().replace('\u{2212}', "-");
+            tokens.push(Token::Number(number.parse().ok()?));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_alphabetic() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().to_ascii_uppercase();
+            match word.as_str() {
+                "N" | "S" | "E" | "W" => tokens.push(Token::Hemisphere(word.chars().next()?)),
+                "DEG" => {}
+                _ => return None,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    Some(tokens)
+}
+
+/// Degrees from up to three numbers (degrees, minutes, seconds) and a hemisphere
+fn to_degrees(numbers: &[f64], hemisphere: Option<char>, axis: Axis) -> Option<f64> {
+    let (&degrees, rest) = numbers.split_first()?;
+    if rest.len() > 2 || rest.iter().any(|n| *n < 0.0 || *n >= 60.0) {
+        return None;
+    }
+    // Only the last number may have a fraction
+    if numbers[..numbers.len() - 1].iter().any(|n| n.fract() != 0.0) {
+        return None;
+    }
+    let magnitude = degrees.abs() + rest.first().unwrap_or(&0.0) / 60.0 + rest.get(1).unwrap_or(&0.0) / 3600.0;
+    let negative = match hemisphere {
+        Some('S') | Some('W') if degrees.is_sign_negative() => return None,
+        Some('S') | Some('W') => true,
+        Some(_) if degrees.is_sign_negative() => return None,
+        _ => degrees.is_sign_negative(),
+    };
+    let limit = if axis == Axis::Lat { 90.0 } else { 180.0 };
+    (magnitude <= limit).then_some(if negative { -magnitude } else { magnitude })
+}
+
+fn hemisphere_axis(hemisphere: char) -> Axis {
+    if matches!(hemisphere, 'N' | 'S') { Axis::Lat } else { Axis::Lon }
+}
+
+fn numbers(tokens: &[Token]) -> Vec<f64> {
+    tokens.iter().filter_map(|t| if let Token::Number(n) = t { Some(*n) } else { None }).collect()
+}
+
+/// Read one latitude or longitude, e.g. `-36.8417`, `36°50.5'S`, `S 36 50 30`
+pub fn parse_coordinate(text: &str, axis: Axis) -> Option<f64> {
+    // A lone latitude or longitude can only use a comma as a decimal comma
+    let text = if text.contains('.') { text.to_string() } else { text.replace(',', ".") };
+    let tokens = tokenize(&text)?;
+    let hemispheres: Vec<char> = tokens.iter().filter_map(|t| if let Token::Hemisphere(h) = t { Some(*h) } else { None }).collect();
+    match hemispheres[..] {
+        [] => to_degrees(&numbers(&tokens), None, axis),
+        [h] if hemisphere_axis(h) == axis => to_degrees(&numbers(&tokens), Some(h), axis),
+        _ => None,
+    }
+}
+
+/// Read latitude and longitude from one field, e.g. `-36.8417,174.7533` or
+/// `36°50.5'S 174°45.2'E`. With hemisphere letters either order is accepted.
+pub fn parse_position(text: &str) -> Option<(f64, f64)> {
+    let tokens = tokenize(text)?;
+    let marks: Vec<usize> = tokens.iter().enumerate().filter(|(_, t)| matches!(t, Token::Hemisphere(_))).map(|(i, _)| i).collect();
+    match marks[..] {
+        [] => {
+            let numbers = numbers(&tokens);
+            if numbers.is_empty() || !numbers.len().is_multiple_of(2) {
+                return None;
+            }
+            let (lat, lon) = numbers.split_at(numbers.len() / 2);
+            Some((to_degrees(lat, None, Axis::Lat)?, to_degrees(lon, None, Axis::Lon)?))
+        }
+        [first, second] => {
+            // Letters lead each half (`S36 50.5 E174 45.2`) or follow it (`36 50.5S 174 45.2E`)
+            let (a, b) = if first == 0 {
+                (&tokens[..second], &tokens[second..])
+            } else if second == tokens.len() - 1 {
+                (&tokens[..=first], &tokens[first + 1..])
+            } else {
+                return None;
+            };
+            let half = |part: &[Token]| -> Option<(Axis, f64)> {
+                let hemisphere = part.iter().find_map(|t| if let Token::Hemisphere(h) = t { Some(*h) } else { None })?;
+                let axis = hemisphere_axis(hemisphere);
+                Some((axis, to_degrees(&numbers(part), Some(hemisphere), axis)?))
+            };
+            match (half(a)?, half(b)?) {
+                ((Axis::Lat, lat), (Axis::Lon, lon)) | ((Axis::Lon, lon), (Axis::Lat, lat)) => Some((lat, lon)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether a cell reads as a coordinate rather than, say, a mark number
+fn looks_like_coordinate(text: &str) -> bool {
+    tokenize(text).is_some_and(|tokens| {
+        tokens.iter().any(|t| matches!(t, Token::Hemisphere(_)))
+            || numbers(&tokens).iter().any(|n| n.fract() != 0.0)
+            || text.contains('°')
+    })
+}
+
+fn read_delimited(content: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    if !delimiter.is_ascii() {
+        return Err(format!("Separator '{}' must be an ASCII character", delimiter));
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut rows = Vec::new();
+    let (mut line, mut counted) = (1, 0);
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read CSV: {}", e))?;
+        // The reader skips blank lines and positions records before them; keep them
+        // as empty rows so row numbers match the file
+        let bytes = content.as_bytes();
+        let mut start = record.position().map_or(0, |p| p.byte() as usize).min(bytes.len());
+        while start < bytes.len() && matches!(bytes[start], b'\r' | b'\n') {
+            start += 1;
+        }
+        line += bytes[counted..start].iter().filter(|&&b| b == b'\n').count();
+        counted = start;
+        rows.resize(line.max(rows.len() + 1) - 1, Vec::new());
+        rows.push(record.iter().map(|field| field.trim().to_string()).collect());
+    }
+    Ok(rows)
+}
+
+/// The separator that splits the opening rows into the most columns. On a tie
+/// tab beats semicolon beats comma, as semicolons usually mean decimal commas.
+fn detect_delimiter(content: &str) -> char {
+    let sample: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).take(SAMPLE_ROWS).collect();
+    let sample = sample.join("\n");
+    let columns = |delimiter: char| {
+        read_delimited(&sample, delimiter)
+            .ok()
+            .and_then(|rows| rows.iter().map(Vec::len).min())
+            .unwrap_or(0)
+    };
+    // max_by_key keeps the last of equal candidates
+    [',', ';', '\t'].into_iter().max_by_key(|d| columns(*d)).unwrap_or(',')
+}
+
+fn read_spreadsheet(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook = open_workbook_auto(path).map_err(|e| format!("Failed to open spreadsheet: {}", e))?;
+    let sheet = workbook.sheet_names().first().cloned().ok_or("Spreadsheet has no sheets")?;
+    let range = workbook.worksheet_range(&sheet).map_err(|e| format!("Failed to read sheet '{}': {}", sheet, e))?;
+    // The range starts at the first used cell; pad above it so row numbers match the sheet
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    let mut rows = vec![Vec::new(); first_row];
+    rows.extend(range.rows().map(|row| row.iter().map(|cell| cell.to_string().trim().to_string()).collect()));
+    Ok(rows)
+}
+
+fn normalize_header(header: &str) -> String {
+    header.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+fn header_mapping(headers: &[String]) -> ColumnMapping {
+    let find = |names: &[&str]| {
+        headers.iter().position(|h| {
+            let h = normalize_header(h);
+            names.iter().any(|name| h == *name)
+        })
+    };
+    // Headers like "Latitude (WGS84)" still count
+    let find_prefixed = |names: &[&str]| {
+        headers.iter().position(|h| {
+            let h = normalize_header(h);
+            names.iter().any(|name| h.starts_with(name))
+        })
+    };
+    ColumnMapping {
+        name: find(&["name", "mark", "waypoint", "wpt", "title", "label", "point"]),
+        lat: find(&["lat"]).or_else(|| find_prefixed(&["latitude"])),
+        lon: find(&["lon", "long", "lng"]).or_else(|| find_prefixed(&["longitude"])),
+        position: find(&["position", "pos", "coordinates", "coords", "location", "latlon", "latlong"]),
+        description: find(&["description", "desc", "notes", "note", "comment", "comments", "remarks"]),
+        symbol: find(&["symbol", "sym", "icon"]),
+    }
+}
+
+/// Guess the columns from what the rows hold
+fn content_mapping(rows: &[Vec<String>]) -> ColumnMapping {
+    let sample = &rows[..rows.len().min(SAMPLE_ROWS)];
+    let width = sample.iter().map(Vec::len).max().unwrap_or(0);
+    let share = |column: usize, test: &dyn Fn(&str) -> bool| {
+        let matching = sample.iter().filter(|row| row.get(column).is_some_and(|cell| test(cell))).count();
+        matching as f64 / sample.len().max(1) as f64
+    };
+    // Most rows rather than all, so a few typos don't hide a column
+    let mostly = 0.6;
+
+    let mut mapping = ColumnMapping {
+        position: (0..width).find(|&c| share(c, &|cell| looks_like_coordinate(cell) && parse_position(cell).is_some()) >= mostly),
+        ..Default::default()
+    };
+    if mapping.position.is_none() {
+        let lat = (0..width.saturating_sub(1)).find(|&c| {
+            share(c, &|cell| looks_like_coordinate(cell) && parse_coordinate(cell, Axis::Lat).is_some()) >= mostly
+                && share(c + 1, &|cell| looks_like_coordinate(cell) && parse_coordinate(cell, Axis::Lon).is_some()) >= mostly
+        });
+        mapping.lat = lat;
+        mapping.lon = lat.map(|c| c + 1);
+    }
+
+    let used = [mapping.position, mapping.lat, mapping.lon];
+    let free: Vec<usize> = (0..width).filter(|c| !used.contains(&Some(*c))).collect();
+    // Prefer words for the name, falling back to mark numbers
+    let text_columns: Vec<usize> = free.iter().copied().filter(|&c| share(c, &|cell| cell.chars().any(char::is_alphabetic)) >= 0.5).collect();
+    mapping.name = text_columns.first().copied().or_else(|| free.iter().copied().find(|&c| share(c, &|cell| !cell.is_empty()) >= 0.5));
+    mapping.description = text_columns.iter().copied().find(|c| Some(*c) != mapping.name);
+    mapping
+}
+
+fn preview_row(row: usize, cells: &[String], mapping: &ColumnMapping, datum: Datum) -> CsvPreviewRow {
+    let cell = |column: Option<usize>| column.and_then(|c| cells.get(c)).map(|s| s.trim()).filter(|s| !s.is_empty());
+    let position = if mapping.position.is_some() {
+        match cell(mapping.position) {
+            Some(text) => parse_position(text).ok_or_else(|| format!("Can't read position '{}'", text)),
+            None => Err("No position".to_string()),
+        }
+    } else if mapping.has_coordinates() {
+        let read = |column, axis, label| match cell(column) {
+            Some(text) => parse_coordinate(text, axis).ok_or_else(|| format!("Can't read {} '{}'", label, text)),
+            None => Err(format!("No {}", label)),
+        };
+        read(mapping.lat, Axis::Lat, "latitude").and_then(|lat| Ok((lat, read(mapping.lon, Axis::Lon, "longitude")?)))
+    } else {
+        Err("No latitude and longitude columns chosen".to_string())
+    };
+    let position = position.map(|(lat, lon)| datum.to_wgs84(lat, lon));
+
+    CsvPreviewRow {
+        row,
+        name: cell(mapping.name).map(str::to_string),
+        lat: position.as_ref().ok().map(|p| p.0),
+        lon: position.as_ref().ok().map(|p| p.1),
+        description: cell(mapping.description).map(str::to_string),
+        symbol: cell(mapping.symbol).map(str::to_string),
+        error: position.err(),
+    }
+}
+
+/// Work out the layout of a table and read every row
+pub fn preview_rows(rows: Vec<Vec<String>>, delimiter: Option<char>, options: &CsvImportOptions) -> CsvPreview {
+    // Row numbers refer to the file, so blank rows are counted before being dropped
+    let rows: Vec<(usize, Vec<String>)> = rows
+        .into_iter()
+        .enumerate()
+        .filter(|(_, cells)| cells.iter().any(|c| !c.trim().is_empty()))
+        .map(|(i, cells)| (i + 1, cells))
+        .collect();
+    let first = rows.first().map(|(_, cells)| cells.clone()).unwrap_or_default();
+
+    let by_header = header_mapping(&first);
+    let has_header = options
+        .has_header
+        .unwrap_or_else(|| by_header.has_coordinates() || !first.iter().any(|cell| looks_like_coordinate(cell)));
+    let data = if has_header { &rows[rows.len().min(1)..] } else { &rows[..] };
+    let cells: Vec<Vec<String>> = data.iter().map(|(_, cells)| cells.clone()).collect();
+    let mapping = match &options.mapping {
+        Some(mapping) => mapping.clone(),
+        None if has_header && by_header.has_coordinates() => by_header,
+        None => content_mapping(&cells),
+    };
+
+    let width = rows.iter().map(|(_, cells)| cells.len()).max().unwrap_or(0);
+    let columns = (0..width)
+        .map(|c| match first.get(c) {
+            Some(header) if has_header && !header.is_empty() => header.clone(),
+            _ => format!("Column {}", c + 1),
+        })
+        .collect();
+
+    CsvPreview {
+        delimiter,
+        has_header,
+        columns,
+        rows: data.iter().map(|(row, cells)| preview_row(*row, cells, &mapping, options.datum)).collect(),
+        mapping,
+    }
+}
+
+/// Characters for bytes 0x80-0x9F in Windows-1252, where it differs from
+/// Latin-1. The five bytes it leaves undefined pass through as controls.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// Read file bytes as UTF-8, falling back to Windows-1252, which spreadsheet
+/// exports often use instead
+fn decode_text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| {
+        e.into_bytes()
+            .into_iter()
+            .map(|b| match b {
+                0x80..=0x9f => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                _ => b as char,
+            })
+            .collect()
+    })
+}
+
+/// Preview delimited text
+pub fn preview_text(content: &str, options: &CsvImportOptions) -> Result<CsvPreview, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(content));
+    Ok(preview_rows(read_delimited(content, delimiter)?, Some(delimiter), options))
+}
+
+/// Preview a CSV/TSV file, or the first sheet of a spreadsheet
+pub fn preview_file(path: &Path, options: &CsvImportOptions) -> Result<CsvPreview, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    if SPREADSHEET_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(preview_rows(read_spreadsheet(path)?, None, options));
+    }
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    preview_text(&decode_text(bytes), options)
+}
+
+/// Turn the readable rows into waypoints, or a route through them in order.
+/// Returns a message for each row left out.
+pub fn to_parsed_gpx(preview: &CsvPreview, build_route: bool, route_name: &str) -> (ParsedGpx, Vec<String>) {
+    let mut points = Vec::new();
+    let mut errors = Vec::new();
+    for row in &preview.rows {
+        match (row.lat, row.lon, &row.error) {
+            (Some(lat), Some(lon), None) => points.push(GpxRoutePoint {
+                name: Some(row.name.clone().unwrap_or_else(|| format!("Mark {}", points.len() + 1))),
+                lat,
+                lon,
+                ele: None,
+                time: None,
+                desc: row.description.clone(),
+                sym: row.symbol.clone(),
+                uuid: None,
+                extensions: Default::default(),
+            }),
+            (_, _, error) => errors.push(format!("Row {}: {}", row.row, error.as_deref().unwrap_or("No position"))),
+        }
+    }
+
+    let mut parsed = ParsedGpx { waypoints: vec![], routes: vec![], tracks: vec![] };
+    if build_route {
+        if !points.is_empty() {
+            parsed.routes.push(GpxRoute { name: Some(route_name.to_string()), desc: None, uuid: None, color: None, points });
+        }
+    } else {
+        parsed.waypoints = points
+            .into_iter()
+            .map(|p| GpxWaypoint {
+                name: p.name,
+                lat: p.lat,
+                lon: p.lon,
+                ele: None,
+                time: None,
+                desc: p.desc,
+                sym: p.sym,
+                uuid: None,
+                extensions: Default::default(),
+            })
+            .collect();
+    }
+    (parsed, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: Option<f64>, expected: f64) -> bool {
+        actual.is_some_and(|a| (a - expected).abs() < 1e-6)
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        assert!(close(parse_coordinate("-36.8417", Axis::Lat), -36.8417));
+        assert!(close(parse_coordinate("36°50.5'S", Axis::Lat), -(36.0 + 50.5 / 60.0)));
+        assert!(close(parse_coordinate("S 36 50 30", Axis::Lat), -(36.0 + 50.5 / 60.0)));
+        assert!(close(parse_coordinate("174° 45′ 12″ E", Axis::Lon), 174.0 + 45.2 / 60.0));
+        assert!(close(parse_coordinate("036-50.5S", Axis::Lat), -(36.0 + 50.5 / 60.0)));
+        assert!(close(parse_coordinate("W 0 30", Axis::Lon), -0.5));
+        assert!(parse_coordinate("36°50.5'E", Axis::Lat).is_none());
+        assert!(parse_coordinate("36°65'S", Axis::Lat).is_none());
+        assert!(parse_coordinate("95.0", Axis::Lat).is_none());
+        assert!(parse_coordinate("Bean Rock", Axis::Lat).is_none());
+
+        let expected = (-(36.0 + 50.5 / 60.0), 174.0 + 45.2 / 60.0);
+        for text in ["36°50.5'S 174°45.2'E", "S36 50.5 E174 45.2", "174°45.2'E, 36°50.5'S", "-36.841667, 174.753333"] {
+            let (lat, lon) = parse_position(text).unwrap_or_else(|| panic!("{}", text));
+            assert!((lat - expected.0).abs() < 1e-5 && (lon - expected.1).abs() < 1e-5, "{}", text);
+        }
+        assert!(parse_position("36°50.5'S 36°50.5'S").is_none());
+        assert!(parse_position("-36.8417").is_none());
+    }
+
+    #[test]
+    fn test_preview_and_build_route() {
+        // Race instructions with mark numbers, no header and a combined position column
+        let content = "1\tBean Rock\t36°49.9'S 174°49.8'E\n2\tRangitoto Light\t36°47.4'S 174°49.8'E\n\n3\tNorth Head\tsomewhere\n";
+        let preview = preview_text(content, &CsvImportOptions::default()).unwrap();
+        assert_eq!(preview.delimiter, Some('\t'));
+        assert!(!preview.has_header);
+        assert_eq!((preview.mapping.name, preview.mapping.position), (Some(1), Some(2)));
+        assert_eq!(preview.rows.len(), 3);
+        assert!(close(preview.rows[0].lat, -(36.0 + 49.9 / 60.0)));
+        assert_eq!(preview.rows[2].row, 4);
+        assert!(preview.rows[2].error.as_deref().unwrap().contains("somewhere"));
+
+        let (parsed, errors) = to_parsed_gpx(&preview, true, "Race 1");
+        assert_eq!(parsed.routes[0].points.len(), 2);
+        assert_eq!(parsed.routes[0].points[1].name.as_deref(), Some("Rangitoto Light"));
+        assert_eq!(errors, ["Row 4: Can't read position 'somewhere'"]);
+
+        // A header names the columns, with decimal commas behind semicolons
+        let content = "Name;Lat;Lon;Notes\nKawau;36°25,2'S;174°49,8'E;Anchorage\n";
+        let preview = preview_text(content, &CsvImportOptions::default()).unwrap();
+        assert_eq!(preview.delimiter, Some(';'));
+        assert!(preview.has_header);
+        assert_eq!(preview.columns, ["Name", "Lat", "Lon", "Notes"]);
+        assert_eq!(preview.mapping.description, Some(3));
+        let (parsed, _) = to_parsed_gpx(&preview, false, "");
+        assert_eq!(parsed.waypoints[0].desc.as_deref(), Some("Anchorage"));
+        assert!((parsed.waypoints[0].lat + 36.0 + 25.2 / 60.0).abs() < 1e-6);
+
+        // An older datum is shifted to WGS84
+        let content = "name,lat,lon\nWesthaven,-36.84,174.76\n";
+        let options = CsvImportOptions { datum: Datum::Nzgd49, ..Default::default() };
+        let preview = preview_text(content, &options).unwrap();
+        assert!(!close(preview.rows[0].lat, -36.84));
+        assert!((preview.rows[0].lat.unwrap() + 36.84).abs() < 0.01);
+
+        // The csv reader only splits on single bytes
+        let options = CsvImportOptions { delimiter: Some('§'), ..Default::default() };
+        assert!(preview_text(content, &options).is_err());
+    }
+
+    #[test]
+    fn test_decode_windows_1252() {
+        assert_eq!(decode_text("Motuihe – “north” 36°".as_bytes().to_vec()), "Motuihe – “north” 36°");
+        // Not valid UTF-8, so read as Windows-1252, undefined bytes included
+        let bytes = vec![b'M', 0x96, b' ', 0x93, b'x', 0x94, b' ', 0xb0, 0x80, 0x81, 0x9d, 0x9f];
+        assert_eq!(decode_text(bytes), "M\u{2013} \u{201c}x\u{201d} °€\u{81}\u{9d}Ÿ");
+    }
+}
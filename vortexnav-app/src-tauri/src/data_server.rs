@@ -0,0 +1,862 @@
+// Data server
+// Re-broadcasts our position, instruments, AIS and active route as NMEA 0183 over TCP
+// and as a minimal Signal K REST + WebSocket server
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::database::{DataServerSettings, Waypoint};
+use crate::instruments::{AisTarget, InstrumentData, METERS_PER_SECOND_TO_KNOTS};
+use crate::navigation::{haversine_distance, ActiveNavigation, RouteProgress};
+use crate::nmea::GpsData;
+use crate::signalk::METERS_PER_NM;
+
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(1);
+/// AIS targets are re-sent every this many broadcasts
+const AIS_BROADCAST_EVERY: u64 = 10;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Within this distance of a waypoint without its own arrival radius, RMB reports arrival (nm)
+const ARRIVAL_RADIUS_NM: f64 = 0.1;
+const SIGNALK_VERSION: &str = "1.7.0";
+/// Connections each listener serves at once; more are turned away
+const MAX_CLIENTS: usize = 32;
+
+/// Everything the server publishes, taken fresh for each broadcast
+#[derive(Debug, Clone, Default)]
+pub struct DataSnapshot {
+    pub gps: GpsData,
+    pub instruments: InstrumentData,
+    pub ais_targets: Vec<AisTarget>,
+    pub active_route: Option<ActiveNavigation>,
+}
+
+impl DataSnapshot {
+    fn progress(&self) -> Option<RouteProgress> {
+        let (lat, lon) = (self.gps.latitude?, self.gps.longitude?);
+        self.active_route.as_ref()?.progress(lat, lon, self.gps.speed_knots, self.gps.course)
+    }
+}
+
+pub type SnapshotProvider = Arc<dyn Fn() -> DataSnapshot + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DataServerStatus {
+    pub running: bool,
+    pub nmea_address: Option<String>,
+    pub signalk_address: Option<String>,
+    pub nmea_clients: usize,
+    pub signalk_clients: usize,
+    pub last_error: Option<String>,
+}
+
+/// Running NMEA and Signal K listeners
+pub struct DataServer {
+    stop_flag: Arc<AtomicBool>,
+    nmea_address: SocketAddr,
+    signalk_address: SocketAddr,
+    nmea_clients: Arc<AtomicUsize>,
+    signalk_clients: Arc<AtomicUsize>,
+    listeners: Vec<JoinHandle<()>>,
+}
+
+impl DataServer {
+    /// Bind both ports and start serving. Port 0 picks a free port.
+    pub fn start(settings: &DataServerSettings, provider: SnapshotProvider) -> io::Result<Self> {
+        let nmea_listener = TcpListener::bind((settings.bind_address.as_str(), settings.nmea_port))?;
+        let signalk_listener = TcpListener::bind((settings.bind_address.as_str(), settings.signalk_port))?;
+        nmea_listener.set_nonblocking(true)?;
+        signalk_listener.set_nonblocking(true)?;
+
+        let mut server = Self {
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            nmea_address: nmea_listener.local_addr()?,
+            signalk_address: signalk_listener.local_addr()?,
+            nmea_clients: Arc::new(AtomicUsize::new(0)),
+            signalk_clients: Arc::new(AtomicUsize::new(0)),
+            listeners: Vec::new(),
+        };
+
+        let (stop_flag, clients, nmea_provider) =
+            (Arc::clone(&server.stop_flag), Arc::clone(&server.nmea_clients), Arc::clone(&provider));
+        server.listeners.push(thread::spawn(move || run_nmea_server(nmea_listener, nmea_provider, stop_flag, clients)));
+
+        let (stop_flag, clients) = (Arc::clone(&server.stop_flag), Arc::clone(&server.signalk_clients));
+        let self_urn = format!("vessels.urn:mrn:signalk:uuid:{}", uuid::Uuid::new_v4());
+        server
+            .listeners
+            .push(thread::spawn(move || run_signalk_server(signalk_listener, provider, stop_flag, clients, self_urn)));
+
+        log::info!("Data server listening: NMEA on {}, Signal K on {}", server.nmea_address, server.signalk_address);
+        Ok(server)
+    }
+
+    pub fn status(&self) -> DataServerStatus {
+        DataServerStatus {
+            running: !self.stop_flag.load(Ordering::SeqCst),
+            nmea_address: Some(self.nmea_address.to_string()),
+            signalk_address: Some(self.signalk_address.to_string()),
+            nmea_clients: self.nmea_clients.load(Ordering::SeqCst),
+            signalk_clients: self.signalk_clients.load(Ordering::SeqCst),
+            last_error: None,
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for DataServer {
+    /// Stops and waits for both listeners, so their ports can be bound again at once
+    fn drop(&mut self) {
+        self.stop();
+        for listener in self.listeners.drain(..) {
+            let _ = listener.join();
+        }
+    }
+}
+
+// ============ NMEA 0183 ============
+
+fn with_checksum(start: char, body: &str) -> String {
+    format!("{}{}*{:02X}", start, body, body.bytes().fold(0u8, |acc, b| acc ^ b))
+}
+
+/// ddmm.mmmm,N / dddmm.mmmm,E
+fn nmea_coordinate(value: f64, is_lat: bool) -> String {
+    let hemisphere = match (is_lat, value >= 0.0) {
+        (true, true) => 'N',
+        (true, false) => 'S',
+        (false, true) => 'E',
+        (false, false) => 'W',
+    };
+    let abs = value.abs();
+    let degrees = abs.trunc();
+    let minutes = (abs - degrees) * 60.0;
+    if is_lat {
+        format!("{:02}{:07.4},{}", degrees as u32, minutes, hemisphere)
+    } else {
+        format!("{:03}{:07.4},{}", degrees as u32, minutes, hemisphere)
+    }
+}
+
+fn field(value: Option<f64>, decimals: usize) -> String {
+    value.map(|v| format!("{:.*}", decimals, v)).unwrap_or_default()
+}
+
+/// Waypoint identifier for RMB: name without commas, truncated
+fn waypoint_ident(waypoint: &Waypoint) -> String {
+    waypoint.name.replace([',', '*', '$'], " ").chars().take(16).collect()
+}
+
+/// Sentences describing our own vessel and route at `now`
+pub fn nmea_sentences(snapshot: &DataSnapshot, now: DateTime<Utc>) -> Vec<String> {
+    let gps = &snapshot.gps;
+    let instruments = &snapshot.instruments;
+    let time = now.format("%H%M%S.00").to_string();
+    let mut sentences = Vec::new();
+
+    if let (Some(lat), Some(lon)) = (gps.latitude, gps.longitude) {
+        let (lat, lon) = (nmea_coordinate(lat, true), nmea_coordinate(lon, false));
+        let mode = if gps.is_estimated { 'E' } else { 'A' };
+        sentences.push(with_checksum(
+            '$',
+            &format!(
+                "GPRMC,{},A,{},{},{},{},{},,,{}",
+                time,
+                lat,
+                lon,
+                field(gps.speed_knots, 1),
+                field(gps.course, 1),
+                now.format("%d%m%y"),
+                mode
+            ),
+        ));
+        let quality = if gps.is_estimated { 6 } else { gps.fix_quality.unwrap_or(1) };
+        sentences.push(with_checksum(
+            '$',
+            &format!(
+                "GPGGA,{},{},{},{},{:02},{},{},M,,M,,",
+                time,
+                lat,
+                lon,
+                quality,
+                gps.satellites.unwrap_or(0),
+                field(gps.hdop.map(f64::from), 1),
+                field(gps.altitude, 1)
+            ),
+        ));
+    }
+    if gps.course.is_some() || gps.speed_knots.is_some() {
+        sentences.push(with_checksum(
+            '$',
+            &format!(
+                "GPVTG,{},T,,M,{},N,{},K,A",
+                field(gps.course, 1),
+                field(gps.speed_knots, 1),
+                field(gps.speed_knots.map(|kn| kn * 1.852), 1)
+            ),
+        ));
+    }
+    if let Some(heading) = gps.heading {
+        sentences.push(with_checksum('$', &format!("IIHDT,{:.1},T", heading)));
+    }
+    if let Some(depth) = instruments.depth_m {
+        sentences.push(with_checksum('$', &format!("IIDPT,{:.1},0.0", depth)));
+    }
+    if let Some(temp) = instruments.water_temp_c {
+        sentences.push(with_checksum('$', &format!("IIMTW,{:.1},C", temp)));
+    }
+    if let (Some(angle), Some(speed)) = (instruments.apparent_wind_angle, instruments.apparent_wind_speed_kn) {
+        sentences.push(with_checksum('$', &format!("IIMWV,{:.1},R,{:.1},N,A", angle.rem_euclid(360.0), speed)));
+    }
+    if let (Some(angle), Some(speed)) = (instruments.true_wind_angle, instruments.true_wind_speed_kn) {
+        sentences.push(with_checksum('$', &format!("IIMWV,{:.1},T,{:.1},N,A", angle.rem_euclid(360.0), speed)));
+    }
+
+    if let Some(progress) = snapshot.progress() {
+        let next = &progress.next_waypoint;
+        let xte = progress.cross_track_nm.unwrap_or(0.0);
+        // Direction to steer: right of track steers left
+        let steer = if xte > 0.0 { 'L' } else { 'R' };
+        let arrived = if progress.distance_nm <= next.arrival_radius_nm.unwrap_or(ARRIVAL_RADIUS_NM) { 'A' } else { 'V' };
+        sentences.push(with_checksum(
+            '$',
+            &format!(
+                "ECRMB,A,{:.2},{},{},{},{},{},{:.1},{:.1},{},{},A",
+                xte.abs().min(9.99),
+                steer,
+                progress.previous_waypoint.as_ref().map(waypoint_ident).unwrap_or_default(),
+                waypoint_ident(next),
+                nmea_coordinate(next.lat, true),
+                nmea_coordinate(next.lon, false),
+                progress.distance_nm.min(999.9),
+                progress.bearing,
+                field(progress.vmg_kn, 1),
+                arrived
+            ),
+        ));
+    }
+    sentences
+}
+
+/// Packs fields MSB first into AIS 6-bit armoring
+#[derive(Default)]
+struct AisBits {
+    bits: Vec<bool>,
+}
+
+impl AisBits {
+    fn push(&mut self, value: i64, width: usize) {
+        for i in (0..width).rev() {
+            self.bits.push((value >> i) & 1 == 1);
+        }
+    }
+
+    fn armored(&self) -> String {
+        self.bits
+            .chunks(6)
+            .map(|chunk| {
+                let v = chunk.iter().enumerate().fold(0u8, |acc, (i, bit)| acc | ((*bit as u8) << (5 - i)));
+                (if v < 40 { v + 48 } else { v + 56 }) as char
+            })
+            .collect()
+    }
+}
+
+fn ais_nav_status(state: Option<&str>) -> i64 {
+    match state {
+        Some("motoring") => 0,
+        Some("anchored") => 1,
+        Some("not under command") => 2,
+        Some("restricted manouverability") => 3,
+        Some("moored") => 5,
+        Some("aground") => 6,
+        Some("fishing") => 7,
+        Some("sailing") => 8,
+        _ => 15,
+    }
+}
+
+/// AIVDM type 1 position report for a target with an MMSI and position
+pub fn ais_position_report(target: &AisTarget) -> Option<String> {
+    let mmsi: i64 = target.mmsi.as_deref()?.parse().ok()?;
+    let (lat, lon) = (target.latitude?, target.longitude?);
+
+    let mut bits = AisBits::default();
+    bits.push(1, 6); // Message type
+    bits.push(0, 2); // Repeat indicator
+    bits.push(mmsi, 30);
+    bits.push(ais_nav_status(target.nav_state.as_deref()), 4);
+    bits.push(-128, 8); // Rate of turn not available
+    bits.push(target.sog_knots.map(|s| ((s * 10.0).round() as i64).min(1022)).unwrap_or(1023), 10);
+    bits.push(0, 1); // Position accuracy
+    bits.push((lon * 600_000.0).round() as i64, 28);
+    bits.push((lat * 600_000.0).round() as i64, 27);
+    bits.push(target.cog.map(|c| (c * 10.0).round() as i64 % 3600).unwrap_or(3600), 12);
+    bits.push(target.heading.map(|h| h.round() as i64 % 360).unwrap_or(511), 9);
+    bits.push(60, 6); // Time stamp not available
+    bits.push(0, 2 + 3 + 1 + 19); // Manoeuvre, spare, RAIM, radio status
+
+    Some(with_checksum('!', &format!("AIVDM,1,1,,A,{},0", bits.armored())))
+}
+
+fn run_nmea_server(listener: TcpListener, provider: SnapshotProvider, stop_flag: Arc<AtomicBool>, client_count: Arc<AtomicUsize>) {
+    let mut clients: Vec<TcpStream> = Vec::new();
+    let mut last_broadcast: Option<Instant> = None;
+    let mut broadcasts: u64 = 0;
+
+    while !stop_flag.load(Ordering::SeqCst) {
+        while let Ok((stream, peer)) = listener.accept() {
+            if clients.len() >= MAX_CLIENTS {
+                log::warn!("Turning away NMEA client {}: {} already connected", peer, MAX_CLIENTS);
+                continue;
+            }
+            log::info!("NMEA client connected: {}", peer);
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            let _ = stream.set_nodelay(true);
+            clients.push(stream);
+        }
+
+        if !clients.is_empty() && last_broadcast.is_none_or(|at| at.elapsed() >= BROADCAST_INTERVAL) {
+            let snapshot = provider();
+            let mut sentences = nmea_sentences(&snapshot, Utc::now());
+            if broadcasts.is_multiple_of(AIS_BROADCAST_EVERY) {
+                sentences.extend(snapshot.ais_targets.iter().filter_map(ais_position_report));
+            }
+            let mut payload = sentences.join("\r\n");
+            payload.push_str("\r\n");
+            clients.retain_mut(|client| client.write_all(payload.as_bytes()).is_ok());
+            last_broadcast = Some(Instant::now());
+            broadcasts += 1;
+        }
+        client_count.store(clients.len(), Ordering::SeqCst);
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// ============ Signal K ============
+
+/// Own-vessel values as Signal K paths in SI units
+fn own_values(snapshot: &DataSnapshot) -> Vec<(&'static str, Value)> {
+    let gps = &snapshot.gps;
+    let instruments = &snapshot.instruments;
+    let ms = |kn: Option<f64>| kn.map(|v| v / METERS_PER_SECOND_TO_KNOTS);
+    let rad = |deg: Option<f64>| deg.map(f64::to_radians);
+    let kelvin = |c: Option<f64>| c.map(|v| v + 273.15);
+
+    let mut values: Vec<(&'static str, Option<Value>)> = vec![
+        (
+            "navigation.position",
+            gps.latitude.zip(gps.longitude).map(|(lat, lon)| json!({"latitude": lat, "longitude": lon})),
+        ),
+        ("navigation.speedOverGround", ms(gps.speed_knots).map(Value::from)),
+        ("navigation.courseOverGroundTrue", rad(gps.course).map(Value::from)),
+        ("navigation.headingTrue", rad(gps.heading).map(Value::from)),
+        ("navigation.headingMagnetic", rad(instruments.heading_magnetic).map(Value::from)),
+        ("navigation.magneticVariation", rad(instruments.magnetic_variation).map(Value::from)),
+        ("navigation.speedThroughWater", ms(instruments.speed_through_water_kn).map(Value::from)),
+        ("navigation.gnss.satellites", gps.satellites.map(Value::from)),
+        ("navigation.gnss.horizontalDilution", gps.hdop.map(|h| Value::from(f64::from(h)))),
+        ("environment.depth.belowTransducer", instruments.depth_m.map(Value::from)),
+        ("environment.depth.belowKeel", instruments.depth_below_keel_m.map(Value::from)),
+        ("environment.water.temperature", kelvin(instruments.water_temp_c).map(Value::from)),
+        ("environment.outside.temperature", kelvin(instruments.air_temp_c).map(Value::from)),
+        ("environment.outside.pressure", instruments.pressure_mb.map(|mb| Value::from(mb * 100.0))),
+        ("environment.wind.angleApparent", rad(instruments.apparent_wind_angle).map(Value::from)),
+        ("environment.wind.speedApparent", ms(instruments.apparent_wind_speed_kn).map(Value::from)),
+        ("environment.wind.angleTrueWater", rad(instruments.true_wind_angle).map(Value::from)),
+        ("environment.wind.directionTrue", rad(instruments.true_wind_direction).map(Value::from)),
+        ("environment.wind.speedTrue", ms(instruments.true_wind_speed_kn).map(Value::from)),
+    ];
+
+    if let Some(progress) = snapshot.progress() {
+        let next = &progress.next_waypoint;
+        values.extend([
+            (
+                "navigation.courseGreatCircle.nextPoint.position",
+                Some(json!({"latitude": next.lat, "longitude": next.lon})),
+            ),
+            ("navigation.courseGreatCircle.nextPoint.distance", Some(Value::from(progress.distance_nm * METERS_PER_NM))),
+            ("navigation.courseGreatCircle.nextPoint.bearingTrue", Some(Value::from(progress.bearing.to_radians()))),
+            ("navigation.courseGreatCircle.nextPoint.velocityMadeGood", ms(progress.vmg_kn).map(Value::from)),
+            (
+                "navigation.courseGreatCircle.crossTrackError",
+                progress.cross_track_nm.map(|xte| Value::from(xte * METERS_PER_NM)),
+            ),
+            (
+                "navigation.courseGreatCircle.previousPoint.position",
+                progress.previous_waypoint.as_ref().map(|p| json!({"latitude": p.lat, "longitude": p.lon})),
+            ),
+        ]);
+    }
+    if let Some(route) = &snapshot.active_route {
+        values.push((
+            "navigation.courseGreatCircle.activeRoute.href",
+            Some(Value::from(format!("/resources/routes/{}", route_key(route)))),
+        ));
+    }
+
+    values.into_iter().filter_map(|(path, value)| value.map(|v| (path, v))).collect()
+}
+
+fn ais_context(target: &AisTarget) -> String {
+    match &target.mmsi {
+        Some(mmsi) => format!("vessels.urn:mrn:imo:mmsi:{}", mmsi),
+        None if target.id.starts_with("vessels.") => target.id.clone(),
+        None => format!("vessels.{}", target.id),
+    }
+}
+
+fn ais_values(target: &AisTarget) -> Vec<(&'static str, Value)> {
+    let mut values: Vec<(&'static str, Option<Value>)> = vec![
+        (
+            "navigation.position",
+            target.latitude.zip(target.longitude).map(|(lat, lon)| json!({"latitude": lat, "longitude": lon})),
+        ),
+        ("navigation.speedOverGround", target.sog_knots.map(|kn| Value::from(kn / METERS_PER_SECOND_TO_KNOTS))),
+        ("navigation.courseOverGroundTrue", target.cog.map(|c| Value::from(c.to_radians()))),
+        ("navigation.headingTrue", target.heading.map(|h| Value::from(h.to_radians()))),
+        ("communication.callsignVhf", target.call_sign.clone().map(Value::from)),
+    ];
+    let mut root = Map::new();
+    if let Some(name) = &target.name {
+        root.insert("name".to_string(), Value::from(name.clone()));
+    }
+    if let Some(mmsi) = &target.mmsi {
+        root.insert("mmsi".to_string(), Value::from(mmsi.clone()));
+    }
+    if !root.is_empty() {
+        values.push(("", Some(Value::Object(root))));
+    }
+    values.into_iter().filter_map(|(path, value)| value.map(|v| (path, v))).collect()
+}
+
+fn delta(context: &str, values: Vec<(&'static str, Value)>, timestamp: &str) -> String {
+    let values: Vec<Value> = values.into_iter().map(|(path, value)| json!({"path": path, "value": value})).collect();
+    json!({
+        "context": context,
+        "updates": [{"source": {"label": "vortexnav"}, "timestamp": timestamp, "values": values}],
+    })
+    .to_string()
+}
+
+/// Nest dotted paths into the Signal K full data model
+fn full_model(values: Vec<(&'static str, Value)>, timestamp: &str) -> Value {
+    let mut root = Map::new();
+    for (path, value) in values {
+        if path.is_empty() {
+            if let Value::Object(properties) = value {
+                root.extend(properties);
+            }
+            continue;
+        }
+        let mut node = &mut root;
+        let mut parts = path.split('.').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                node.insert(part.to_string(), json!({"value": value, "timestamp": timestamp}));
+                break;
+            }
+            node = node
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .expect("Signal K path nodes are objects");
+        }
+    }
+    Value::Object(root)
+}
+
+fn route_key(route: &ActiveNavigation) -> String {
+    match route.route_id {
+        Some(id) => format!("vortexnav-route-{}", id),
+        None => "vortexnav-route".to_string(),
+    }
+}
+
+fn route_resource(route: &ActiveNavigation) -> Value {
+    let coordinates: Vec<Value> = route.waypoints.iter().map(|w| json!([w.lon, w.lat])).collect();
+    let distance_nm: f64 = route
+        .waypoints
+        .windows(2)
+        .map(|leg| haversine_distance(leg[0].lat, leg[0].lon, leg[1].lat, leg[1].lon))
+        .sum();
+    let names: Vec<Value> = route.waypoints.iter().map(|w| Value::from(w.name.clone())).collect();
+    json!({
+        "name": route.route_name,
+        "distance": distance_nm * METERS_PER_NM,
+        "feature": {
+            "type": "Feature",
+            "geometry": {"type": "LineString", "coordinates": coordinates},
+            "properties": {"coordinatesMeta": names},
+        },
+    })
+}
+
+fn run_signalk_server(
+    listener: TcpListener,
+    provider: SnapshotProvider,
+    stop_flag: Arc<AtomicBool>,
+    client_count: Arc<AtomicUsize>,
+    self_urn: String,
+) {
+    // Every open connection, HTTP or WebSocket, holds a thread
+    let connections = Arc::new(AtomicUsize::new(0));
+    while !stop_flag.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                if connections.load(Ordering::SeqCst) >= MAX_CLIENTS {
+                    log::warn!("Turning away Signal K client {}: {} already connected", peer, MAX_CLIENTS);
+                    continue;
+                }
+                connections.fetch_add(1, Ordering::SeqCst);
+                let (provider, stop_flag, client_count, connections, self_urn) = (
+                    Arc::clone(&provider),
+                    Arc::clone(&stop_flag),
+                    Arc::clone(&client_count),
+                    Arc::clone(&connections),
+                    self_urn.clone(),
+                );
+                thread::spawn(move || {
+                    if let Err(e) = handle_signalk_client(stream, &provider, &stop_flag, &client_count, &self_urn) {
+                        log::debug!("Signal K client error: {}", e);
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+struct HttpRequest {
+    path: String,
+    host: Option<String>,
+    websocket_key: Option<String>,
+}
+
+fn read_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !raw.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf)?;
+        if n == 0 || raw.len() > 16 * 1024 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Incomplete HTTP request"));
+        }
+        raw.extend_from_slice(&buf[..n]);
+    }
+    let text = String::from_utf8_lossy(&raw);
+    let mut lines = text.lines();
+    let path = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Bad request line"))?
+        .to_string();
+    let mut request = HttpRequest { path, host: None, websocket_key: None };
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => request.host = Some(value.trim().to_string()),
+                "sec-websocket-key" => request.websocket_key = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    Ok(request)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &Value) -> io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+fn handle_signalk_client(
+    mut stream: TcpStream,
+    provider: &SnapshotProvider,
+    stop_flag: &AtomicBool,
+    client_count: &AtomicUsize,
+    self_urn: &str,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let request = read_request(&mut stream)?;
+    let host = request.host.clone().unwrap_or_else(|| stream.local_addr().map(|a| a.to_string()).unwrap_or_default());
+    let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+    let path = path.trim_end_matches('/');
+
+    if let Some(key) = request.websocket_key.as_deref().filter(|_| path == "/signalk/v1/stream") {
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            tungstenite::handshake::derive_accept_key(key.as_bytes())
+        )?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        client_count.fetch_add(1, Ordering::SeqCst);
+        let result = stream_deltas(socket, provider, stop_flag, self_urn, !query.contains("subscribe=none"));
+        client_count.fetch_sub(1, Ordering::SeqCst);
+        return result;
+    }
+
+    let snapshot = provider();
+    let timestamp = Utc::now().to_rfc3339();
+    let self_id = self_urn.trim_start_matches("vessels.");
+    let own_vessel = || full_model(own_values(&snapshot), &timestamp);
+    let routes = || {
+        let mut routes = Map::new();
+        if let Some(route) = &snapshot.active_route {
+            routes.insert(route_key(route), route_resource(route));
+        }
+        Value::Object(routes)
+    };
+
+    match path {
+        "/signalk" => respond(
+            &mut stream,
+            "200 OK",
+            &json!({
+                "endpoints": {"v1": {
+                    "version": SIGNALK_VERSION,
+                    "signalk-http": format!("http://{}/signalk/v1/api/", host),
+                    "signalk-ws": format!("ws://{}/signalk/v1/stream", host),
+                }},
+                "server": {"id": "vortexnav", "version": env!("CARGO_PKG_VERSION")},
+            }),
+        ),
+        "/signalk/v1/api" => {
+            let mut vessels = Map::new();
+            vessels.insert(self_id.to_string(), own_vessel());
+            for target in &snapshot.ais_targets {
+                let context = ais_context(target);
+                vessels.insert(context.trim_start_matches("vessels.").to_string(), full_model(ais_values(target), &timestamp));
+            }
+            respond(
+                &mut stream,
+                "200 OK",
+                &json!({"version": SIGNALK_VERSION, "self": self_urn, "vessels": vessels, "resources": {"routes": routes()}}),
+            )
+        }
+        "/signalk/v1/api/vessels/self" => respond(&mut stream, "200 OK", &own_vessel()),
+        "/signalk/v1/api/resources/routes" => respond(&mut stream, "200 OK", &routes()),
+        _ => respond(&mut stream, "404 Not Found", &json!({"message": "Not found"})),
+    }
+}
+
+/// Hello, then a delta for our own vessel every second and AIS targets every ten.
+/// With subscribe=none nothing is sent until the client subscribes.
+fn stream_deltas(
+    mut socket: WebSocket<TcpStream>,
+    provider: &SnapshotProvider,
+    stop_flag: &AtomicBool,
+    self_urn: &str,
+    mut subscribed: bool,
+) -> io::Result<()> {
+    let to_io = |e: tungstenite::Error| io::Error::other(e.to_string());
+    let hello = json!({
+        "name": "VortexNav",
+        "version": env!("CARGO_PKG_VERSION"),
+        "self": self_urn,
+        "roles": ["master", "main"],
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+    socket.send(Message::Text(hello.to_string())).map_err(to_io)?;
+
+    let mut last_broadcast: Option<Instant> = None;
+    let mut broadcasts: u64 = 0;
+    while !stop_flag.load(Ordering::SeqCst) {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if text.contains("\"subscribe\"") {
+                    subscribed = true;
+                }
+            }
+            Ok(Message::Close(_)) => {
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e)) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {}
+            Err(e) => return Err(to_io(e)),
+        }
+
+        if subscribed && last_broadcast.is_none_or(|at| at.elapsed() >= BROADCAST_INTERVAL) {
+            let snapshot = provider();
+            let timestamp = Utc::now().to_rfc3339();
+            let values = own_values(&snapshot);
+            if !values.is_empty() {
+                socket.send(Message::Text(delta(self_urn, values, &timestamp))).map_err(to_io)?;
+            }
+            if broadcasts.is_multiple_of(AIS_BROADCAST_EVERY) {
+                for target in &snapshot.ais_targets {
+                    socket
+                        .send(Message::Text(delta(&ais_context(target), ais_values(target), &timestamp)))
+                        .map_err(to_io)?;
+                }
+            }
+            last_broadcast = Some(Instant::now());
+            broadcasts += 1;
+        }
+    }
+    let _ = socket.close(None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::VesselData;
+    use crate::nmea::{checksum_valid, NmeaParser};
+    use crate::signalk::{SignalKConnection, SignalKState};
+    use std::io::{BufRead, BufReader};
+
+    fn snapshot() -> DataSnapshot {
+        DataSnapshot {
+            gps: GpsData {
+                latitude: Some(-36.5),
+                longitude: Some(174.25),
+                speed_knots: Some(6.0),
+                course: Some(0.0),
+                fix_quality: Some(1),
+                satellites: Some(9),
+                ..Default::default()
+            },
+            instruments: InstrumentData { depth_m: Some(12.4), ..Default::default() },
+            ais_targets: vec![AisTarget {
+                id: "vessels.urn:mrn:imo:mmsi:512123456".to_string(),
+                mmsi: Some("512123456".to_string()),
+                name: Some("SPIRIT OF ADVENTURE".to_string()),
+                latitude: Some(-36.83),
+                longitude: Some(174.8),
+                sog_knots: Some(10.0),
+                cog: Some(45.0),
+                ..Default::default()
+            }],
+            active_route: Some(ActiveNavigation {
+                route_id: Some(7),
+                route_name: "Kawau".to_string(),
+                waypoints: vec![Waypoint::at("Start", -37.0, 174.25), Waypoint::at("Kawau, North", -36.0, 174.25)],
+                next_index: 1,
+            }),
+        }
+    }
+
+    fn decode_bits(payload: &str, start: usize, width: usize) -> i64 {
+        let bits: Vec<bool> = payload
+            .bytes()
+            .flat_map(|c| {
+                let v = if c - 48 > 40 { c - 56 } else { c - 48 };
+                (0..6).rev().map(move |i| (v >> i) & 1 == 1)
+            })
+            .collect();
+        let raw = bits[start..start + width].iter().fold(0i64, |acc, b| (acc << 1) | *b as i64);
+        // Sign-extend
+        if raw >> (width - 1) == 1 { raw - (1 << width) } else { raw }
+    }
+
+    #[test]
+    fn test_nmea_and_ais_sentences() {
+        let snapshot = snapshot();
+        let now = Utc::now();
+        let sentences = nmea_sentences(&snapshot, now);
+        assert!(sentences.iter().all(|s| checksum_valid(s) == Some(true)), "{:?}", sentences);
+
+        let parser = NmeaParser::new();
+        let rmc = parser.parse_sentence(&sentences[0]).unwrap();
+        assert!((rmc.latitude.unwrap() + 36.5).abs() < 1e-6);
+        assert!((rmc.longitude.unwrap() - 174.25).abs() < 1e-6);
+
+        let rmb = sentences.iter().find(|s| s.starts_with("$ECRMB")).unwrap();
+        let fields: Vec<&str> = rmb.split(',').collect();
+        assert_eq!(&fields[4..7], &["Start", "Kawau  North", "3600.0000"]);
+        assert_eq!(fields[10], "30.0");
+        assert!(sentences.iter().any(|s| s.starts_with("$IIDPT,12.4,")));
+
+        let ais = ais_position_report(&snapshot.ais_targets[0]).unwrap();
+        assert_eq!(checksum_valid(&ais), Some(true));
+        let payload = ais.split(',').nth(5).unwrap();
+        assert_eq!(payload.len(), 28);
+        assert_eq!(decode_bits(payload, 0, 6), 1);
+        assert_eq!(decode_bits(payload, 8, 30), 512123456);
+        assert_eq!(decode_bits(payload, 50, 10), 100);
+        assert_eq!(decode_bits(payload, 61, 28), (174.8 * 600_000.0_f64).round() as i64);
+        assert_eq!(decode_bits(payload, 89, 27), (-36.83 * 600_000.0_f64).round() as i64);
+    }
+
+    #[test]
+    fn test_server_nmea_and_signalk_clients() {
+        let settings = DataServerSettings {
+            enabled: true,
+            bind_address: "127.0.0.1".to_string(),
+            nmea_port: 0,
+            signalk_port: 0,
+        };
+        let server = DataServer::start(&settings, Arc::new(snapshot)).unwrap();
+        let status = server.status();
+
+        // NMEA over TCP
+        let nmea = TcpStream::connect(status.nmea_address.unwrap()).unwrap();
+        nmea.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut line = String::new();
+        BufReader::new(nmea).read_line(&mut line).unwrap();
+        assert!(line.starts_with("$GPRMC") && line.ends_with("\r\n"));
+
+        // Signal K, through our own client
+        let address = status.signalk_address.unwrap();
+        let routes: Value = {
+            let mut http = TcpStream::connect(&address).unwrap();
+            write!(http, "GET /signalk/v1/api/resources/routes HTTP/1.0\r\nHost: {}\r\n\r\n", address).unwrap();
+            let mut response = String::new();
+            http.read_to_string(&mut response).unwrap();
+            serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap()
+        };
+        assert_eq!(routes["vortexnav-route-7"]["feature"]["geometry"]["coordinates"][1][1], -36.0);
+
+        let mut connection = SignalKConnection::connect(&address).unwrap();
+        let mut state = SignalKState::new();
+        let mut vessel = VesselData::default();
+        let mut position = None;
+        for _ in 0..10 {
+            if let Ok(Some(text)) = connection.next_message() {
+                if text.contains("courseGreatCircle") {
+                    assert!(text.contains("nextPoint.distance"));
+                }
+                if let Some(update) = state.handle_message(&text, &mut vessel, Instant::now()) {
+                    position = update.gps.latitude;
+                }
+                if position.is_some() && !vessel.ais_targets.is_empty() {
+                    break;
+                }
+            }
+        }
+        assert_eq!(position, Some(-36.5));
+        assert_eq!(vessel.instruments.depth_m, Some(12.4));
+        let targets = vessel.current_ais_targets(Instant::now());
+        assert_eq!(targets[0].name.as_deref(), Some("SPIRIT OF ADVENTURE"));
+
+        // Dropping the server frees its ports straight away
+        let same_ports = DataServerSettings {
+            nmea_port: server.nmea_address.port(),
+            signalk_port: server.signalk_address.port(),
+            ..settings
+        };
+        drop(server);
+        DataServer::start(&same_ports, Arc::new(DataSnapshot::default)).unwrap();
+    }
+}
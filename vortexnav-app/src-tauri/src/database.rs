@@ -1,11 +1,13 @@
 // Database module for SQLite configuration and MBTiles tile serving
 
-use rusqlite::{Connection, Result as SqliteResult, params, OptionalExtension};
+use rusqlite::{Connection, Result as SqliteResult, Transaction, TransactionBehavior, params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Mutex;
 use thiserror::Error;
 
+use crate::migrations;
+
 #[derive(Error, Debug)]
 pub enum DatabaseError {
     #[error("SQLite error: {0}")]
@@ -16,6 +18,12 @@ pub enum DatabaseError {
     TileNotFound { z: u32, x: u32, y: u32 },
     #[error("MBTiles file not found: {0}")]
     MBTilesNotFound(String),
+    #[error("Migration to schema version {version} failed: {source}")]
+    Migration { version: u32, source: rusqlite::Error },
+    #[error("Database schema version {found} is newer than this app supports ({supported})")]
+    SchemaTooNew { found: u32, supported: u32 },
+    #[error("A waypoint group cannot be moved inside itself")]
+    GroupCycle,
 }
 
 // Waypoint definition
@@ -30,6 +38,58 @@ pub struct Waypoint {
     pub show_label: bool,
     pub hidden: bool,
     pub created_at: Option<String>,
+    /// Stable id across devices and file round trips; assigned on create if empty
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Folder in the waypoint list; stays local to this device
+    #[serde(default)]
+    pub group_id: Option<i64>,
+    /// Distance at which the waypoint counts as reached; the default when unset
+    #[serde(default)]
+    pub arrival_radius_nm: Option<f64>,
+    /// Water depth in meters, e.g. from a GPX sounding
+    #[serde(default)]
+    pub depth_m: Option<f64>,
+    /// Water temperature in degrees Celsius
+    #[serde(default)]
+    pub temperature_c: Option<f64>,
+}
+
+#[cfg(test)]
+impl Waypoint {
+    /// Labelled, visible waypoint with nothing else set
+    pub fn at(name: &str, lat: f64, lon: f64) -> Self {
+        Waypoint {
+            id: None,
+            name: name.to_string(),
+            lat,
+            lon,
+            description: None,
+            symbol: None,
+            show_label: true,
+            hidden: false,
+            created_at: None,
+            uuid: None,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: None,
+            depth_m: None,
+            temperature_c: None,
+        }
+    }
+}
+
+/// Folder of waypoints. Groups nest through `parent_id`; hiding a group hides
+/// everything inside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaypointGroup {
+    pub id: Option<i64>,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub hidden: bool,
+    pub created_at: Option<String>,
 }
 
 // Route definition
@@ -45,6 +105,8 @@ pub struct Route {
     pub estimated_speed_kn: f64,         // For ETA calculations
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
 }
 
 impl Default for Route {
@@ -60,6 +122,7 @@ impl Default for Route {
             estimated_speed_kn: 5.0,
             created_at: None,
             updated_at: None,
+            uuid: None,
         }
     }
 }
@@ -71,6 +134,10 @@ pub struct RouteTag {
     pub name: String,
     pub color: Option<String>,
     pub created_at: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 // Route with full data loaded (waypoints and tags)
@@ -95,6 +162,10 @@ pub struct Track {
     pub point_count: i64,
     pub hidden: bool,
     pub created_at: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 impl Default for Track {
@@ -111,6 +182,8 @@ impl Default for Track {
             point_count: 0,
             hidden: false,
             created_at: None,
+            uuid: None,
+            updated_at: None,
         }
     }
 }
@@ -122,11 +195,21 @@ pub struct TrackPoint {
     pub track_id: i64,
     pub lat: f64,
     pub lon: f64,
-    pub timestamp: String,
+    /// None for imported points that had no time
+    pub timestamp: Option<String>,
     pub sequence: i64,
     pub heading: Option<f64>,
     pub cog: Option<f64>,
     pub sog: Option<f64>,
+    /// Points in different segments are not joined, e.g. across a gap in recording
+    #[serde(default)]
+    pub segment: i64,
+    /// Water depth in meters
+    #[serde(default)]
+    pub depth_m: Option<f64>,
+    /// Water temperature in degrees Celsius
+    #[serde(default)]
+    pub temperature_c: Option<f64>,
 }
 
 // Track with all its points loaded
@@ -144,6 +227,15 @@ pub struct RouteStatistics {
     pub estimated_time_hours: f64,
     pub leg_distances: Vec<f64>,
     pub leg_bearings: Vec<f64>,
+    /// Time on each leg (hours), including tidal streams when worked in
+    #[serde(default)]
+    pub leg_times_hours: Vec<f64>,
+    /// Course to steer on each leg allowing for set and drift (empty without currents)
+    #[serde(default)]
+    pub leg_courses_to_steer: Vec<f64>,
+    /// Average stream along each leg, knots (positive fair, negative foul)
+    #[serde(default)]
+    pub leg_stream_kn: Vec<f64>,
 }
 
 // App settings stored in database
@@ -182,6 +274,8 @@ pub struct GpsSourceRecord {
     pub baud_rate: u32,
     pub enabled: bool,
     pub priority: i32,
+    #[serde(default)]
+    pub raw_nmea: bool,
 }
 
 // MBTiles metadata
@@ -285,6 +379,109 @@ impl Default for Cm93Settings {
     }
 }
 
+// GPS position filter and integrity settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionFilterSettings {
+    pub enabled: bool,
+    pub position_alpha: f64, // Position gain (0-1), lower is smoother
+    pub velocity_beta: f64,  // Velocity gain (0-1)
+    pub max_speed_kn: f64,   // Fixes implying a faster jump are rejected
+    pub low_speed_kn: f64,   // Below this COG/SOG are smoothed more heavily
+    pub max_hdop: f64,       // HDOP above this degrades integrity
+    pub min_satellites: u32, // Fewer satellites used degrades integrity
+}
+
+impl Default for PositionFilterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            position_alpha: 0.3,
+            velocity_beta: 0.05,
+            max_speed_kn: 50.0,
+            low_speed_kn: 1.0,
+            max_hdop: 2.5,
+            min_satellites: 6,
+        }
+    }
+}
+
+// Embedded NMEA 0183 / Signal K server for other devices on the boat network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataServerSettings {
+    pub enabled: bool,
+    pub bind_address: String, // 0.0.0.0 for all interfaces
+    pub nmea_port: u16,       // NMEA 0183 over TCP
+    pub signalk_port: u16,    // Signal K REST and WebSocket
+}
+
+impl Default for DataServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "0.0.0.0".to_string(),
+            nmea_port: 10110,
+            signalk_port: 3000,
+        }
+    }
+}
+
+// Sync with other VortexNav devices on the local network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSettings {
+    pub enabled: bool,        // Accept pairing and sync from other devices
+    pub device_name: String,  // Shown to the other device when pairing
+    #[serde(default = "default_sync_bind_address")]
+    pub bind_address: String, // 0.0.0.0 listens on every network the device joins
+    pub port: u16,
+}
+
+fn default_sync_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device_name: "VortexNav".to_string(),
+            bind_address: default_sync_bind_address(),
+            port: 10120,
+        }
+    }
+}
+
+// Device paired for sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPeer {
+    pub device_id: String,
+    pub name: String,
+    pub address: Option<String>, // host:port of its sync server, if it runs one
+    #[serde(skip_serializing, default)]
+    pub token: String,           // Shared secret agreed when pairing
+    pub last_sent_seq: i64,      // Our change log position it has received
+    pub last_received_seq: i64,  // Its change log position we have received
+    pub last_synced_at: Option<String>,
+    pub paired_at: Option<String>,
+}
+
+// Entry in the sync change log: the latest change to one object
+#[derive(Debug, Clone)]
+pub struct SyncLogEntry {
+    pub seq: i64,
+    pub table_name: String,
+    pub uuid: String,
+}
+
+// Stored state of one object, including deleted ones, as sync sees it
+#[derive(Debug, Clone)]
+pub struct SyncVersion {
+    pub name: String,
+    pub updated_at: String,
+    pub deleted: bool,
+    pub seq: Option<i64>,
+    pub origin: Option<String>, // Device the last change was synced from
+}
+
 // Chart catalog (imported from XML)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChartCatalog {
@@ -330,262 +527,214 @@ pub struct DownloadProgress {
     pub error: Option<String>,
 }
 
-// Configuration database manager
-pub struct ConfigDatabase {
-    conn: Mutex<Connection>,
+// Boat polar diagram (performance table stored as JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoatPolarRecord {
+    pub id: Option<i64>,
+    pub name: String,
+    pub created_at: Option<String>,
 }
 
-impl ConfigDatabase {
-    pub fn new(data_dir: &PathBuf) -> Result<Self, DatabaseError> {
-        std::fs::create_dir_all(data_dir)?;
-        let db_path = data_dir.join("vortexnav.db");
-        let conn = Connection::open(db_path)?;
+// Tide station (harmonic constituents stored as JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideStationRecord {
+    pub id: Option<i64>,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub mean_level_m: f64,
+    pub source: Option<String>,
+    pub created_at: Option<String>,
+}
 
-        let db = Self { conn: Mutex::new(conn) };
-        db.init_schema()?;
-        Ok(db)
-    }
+// Tidal current station (harmonic constituents stored as JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalCurrentStationRecord {
+    pub id: Option<i64>,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub flood_direction: f64,
+    pub ebb_direction: f64,
+    pub mean_speed_kn: f64,
+    pub source: Option<String>,
+    pub created_at: Option<String>,
+}
 
-    fn init_schema(&self) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+// Gridded tidal stream table (points stored as JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalStreamGridRecord {
+    pub id: Option<i64>,
+    pub name: String,
+    pub reference_station: String,
+    pub point_count: i64,
+    pub created_at: Option<String>,
+}
 
-        // Settings table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-            [],
-        )?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Waypoint,
+    Route,
+    Track,
+}
 
-        // Waypoints table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS waypoints (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                lat REAL NOT NULL,
-                lon REAL NOT NULL,
-                description TEXT,
-                symbol TEXT,
-                show_label INTEGER NOT NULL DEFAULT 1,
-                hidden INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+impl SearchKind {
+    pub const ALL: [SearchKind; 3] = [SearchKind::Waypoint, SearchKind::Route, SearchKind::Track];
 
-        // Migration: add show_label column if it doesn't exist (for existing databases)
-        let _ = conn.execute(
-            "ALTER TABLE waypoints ADD COLUMN show_label INTEGER NOT NULL DEFAULT 1",
-            [],
-        );
+    fn code(self) -> i64 {
+        match self {
+            SearchKind::Waypoint => migrations::SEARCH_KIND_WAYPOINT,
+            SearchKind::Route => migrations::SEARCH_KIND_ROUTE,
+            SearchKind::Track => migrations::SEARCH_KIND_TRACK,
+        }
+    }
 
-        // Migration: add hidden column if it doesn't exist (for existing databases)
-        let _ = conn.execute(
-            "ALTER TABLE waypoints ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0",
-            [],
-        );
+    fn from_code(code: i64) -> Option<Self> {
+        SearchKind::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+}
 
-        // Routes table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS routes (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                description TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+/// Object found through the search indexes, with the positions that make it up:
+/// the waypoint itself, a route's waypoints or a track's points
+#[derive(Debug, Clone)]
+pub struct SearchCandidate {
+    pub kind: SearchKind,
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub points: Vec<(f64, f64)>,
+}
 
-        // Route waypoints junction table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS route_waypoints (
-                route_id INTEGER NOT NULL,
-                waypoint_id INTEGER NOT NULL,
-                sequence INTEGER NOT NULL,
-                PRIMARY KEY (route_id, waypoint_id),
-                FOREIGN KEY (route_id) REFERENCES routes(id) ON DELETE CASCADE,
-                FOREIGN KEY (waypoint_id) REFERENCES waypoints(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+/// Change timestamp for user objects: RFC 3339 UTC with milliseconds, so
+/// timestamps from any device sort correctly as text
+pub fn timestamp_now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
 
-        // Route tags table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS route_tags (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                color TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+fn new_uuid(uuid: &Option<String>) -> String {
+    uuid.clone().unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
 
-        // Route-tag junction table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS route_tag_assignments (
-                route_id INTEGER NOT NULL,
-                tag_id INTEGER NOT NULL,
-                PRIMARY KEY (route_id, tag_id),
-                FOREIGN KEY (route_id) REFERENCES routes(id) ON DELETE CASCADE,
-                FOREIGN KEY (tag_id) REFERENCES route_tags(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+/// Guards SQL built from a table name that arrived over the network
+fn check_tracked_table(table: &str) -> SqliteResult<()> {
+    if migrations::TRACKED_TABLES.contains(&table) {
+        Ok(())
+    } else {
+        Err(rusqlite::Error::InvalidParameterName(format!("Not a synced table: {}", table)))
+    }
+}
 
-        // Migrations: Add new columns to routes table (for existing databases)
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN color TEXT DEFAULT '#c026d3'", []);
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN is_active INTEGER NOT NULL DEFAULT 0", []);
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0", []);
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN total_distance_nm REAL", []);
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN estimated_speed_kn REAL DEFAULT 5.0", []);
-        let _ = conn.execute("ALTER TABLE routes ADD COLUMN updated_at TEXT DEFAULT CURRENT_TIMESTAMP", []);
-
-        // Insert default route tags
-        let default_tags = [
-            ("Favorites", "#f59e0b"),
-            ("Coastal", "#3b82f6"),
-            ("Ocean Crossing", "#8b5cf6"),
-            ("Harbor Entry", "#22c55e"),
-            ("Anchorage Approach", "#06b6d4"),
-            ("Emergency", "#ef4444"),
-            ("Historic", "#a855f7"),
-        ];
-        for (name, color) in default_tags {
-            let _ = conn.execute(
-                "INSERT OR IGNORE INTO route_tags (name, color) VALUES (?, ?)",
-                params![name, color],
-            );
-        }
-
-        // MBTiles registry - tracks available offline chart files
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS mbtiles_registry (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                file_path TEXT NOT NULL UNIQUE,
-                minzoom INTEGER,
-                maxzoom INTEGER,
-                bounds TEXT,
-                enabled INTEGER DEFAULT 1,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+fn find_live_id(conn: &Connection, table: &str, uuid: &str) -> SqliteResult<Option<i64>> {
+    conn.query_row(
+        &format!("SELECT id FROM {} WHERE uuid = ? AND deleted_at IS NULL", table),
+        params![uuid],
+        |row| row.get(0),
+    ).optional()
+}
 
-        // GPS sources configuration
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS gps_sources (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                source_type TEXT NOT NULL,
-                port_name TEXT,
-                baud_rate INTEGER NOT NULL DEFAULT 4800,
-                enabled INTEGER NOT NULL DEFAULT 1,
-                priority INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+/// A group followed by every group nested inside it
+fn group_subtree(conn: &Connection, id: i64) -> SqliteResult<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE subtree(id) AS (
+            SELECT ?
+            UNION SELECT g.id FROM waypoint_groups g JOIN subtree s ON g.parent_id = s.id
+         )
+         SELECT id FROM subtree"
+    )?;
+    let ids = stmt.query_map(params![id], |row| row.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    Ok(ids)
+}
 
-        // Chart layer state - stores user preferences for each chart layer
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS chart_layers (
-                chart_id TEXT PRIMARY KEY,
-                enabled INTEGER NOT NULL DEFAULT 1,
-                opacity REAL NOT NULL DEFAULT 1.0,
-                z_order INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
+/// Name, description and positions of a live object, or None if it is gone
+fn load_search_candidate(conn: &Connection, kind: SearchKind, id: i64) -> SqliteResult<Option<SearchCandidate>> {
+    let (table, points_sql) = match kind {
+        SearchKind::Waypoint => ("waypoints", "SELECT lat, lon FROM waypoints WHERE id = ?"),
+        SearchKind::Route => (
+            "routes",
+            "SELECT w.lat, w.lon FROM route_waypoints rw JOIN waypoints w ON w.id = rw.waypoint_id
+             WHERE rw.route_id = ? ORDER BY rw.sequence",
+        ),
+        SearchKind::Track => ("tracks", "SELECT lat, lon FROM track_points WHERE track_id = ? ORDER BY sequence"),
+    };
+    let found: Option<(String, Option<String>)> = conn.query_row(
+        &format!("SELECT name, description FROM {} WHERE id = ? AND deleted_at IS NULL", table),
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    let Some((name, description)) = found else { return Ok(None) };
+    let points = conn.prepare_cached(points_sql)?
+        .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(SearchCandidate { kind, id, name, description, points }))
+}
 
-        // Migrations: Add custom metadata columns to chart_layers (for existing databases)
-        let _ = conn.execute("ALTER TABLE chart_layers ADD COLUMN custom_name TEXT", []);
-        let _ = conn.execute("ALTER TABLE chart_layers ADD COLUMN custom_description TEXT", []);
-        let _ = conn.execute("ALTER TABLE chart_layers ADD COLUMN custom_min_zoom INTEGER", []);
-        let _ = conn.execute("ALTER TABLE chart_layers ADD COLUMN custom_max_zoom INTEGER", []);
+// Deleted objects keep their row as a tombstone; their links are removed
+fn tombstone_waypoint(conn: &Connection, id: i64, now: &str) -> SqliteResult<()> {
+    // Routes through the waypoint change too
+    conn.execute(
+        "UPDATE routes SET updated_at = ? WHERE id IN (SELECT route_id FROM route_waypoints WHERE waypoint_id = ?)",
+        params![now, id],
+    )?;
+    conn.execute("DELETE FROM route_waypoints WHERE waypoint_id = ?", params![id])?;
+    conn.execute("UPDATE waypoints SET deleted_at = ?, updated_at = ? WHERE id = ?", params![now, now, id])?;
+    Ok(())
+}
 
-        // Chart catalogs - imported from XML files or URLs
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS chart_catalogs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                catalog_type TEXT NOT NULL,
-                source_type TEXT NOT NULL,
-                source_path TEXT NOT NULL,
-                imported_at TEXT DEFAULT CURRENT_TIMESTAMP,
-                last_refreshed TEXT
-            )",
-            [],
-        )?;
+fn tombstone_route(conn: &Connection, id: i64, now: &str) -> SqliteResult<()> {
+    conn.execute("DELETE FROM route_waypoints WHERE route_id = ?", params![id])?;
+    conn.execute("DELETE FROM route_tag_assignments WHERE route_id = ?", params![id])?;
+    conn.execute(
+        "UPDATE routes SET deleted_at = ?, updated_at = ?, is_active = 0 WHERE id = ?",
+        params![now, now, id],
+    )?;
+    Ok(())
+}
 
-        // Catalog charts - charts available in imported catalogs
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS catalog_charts (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                catalog_id INTEGER NOT NULL,
-                chart_id TEXT NOT NULL,
-                title TEXT NOT NULL,
-                chart_type TEXT NOT NULL,
-                format TEXT,
-                scale INTEGER,
-                status TEXT,
-                download_url TEXT NOT NULL,
-                file_size INTEGER,
-                last_updated TEXT,
-                bounds TEXT,
-                download_status TEXT DEFAULT 'available',
-                download_progress INTEGER DEFAULT 0,
-                download_path TEXT,
-                mbtiles_path TEXT,
-                error_message TEXT,
-                FOREIGN KEY (catalog_id) REFERENCES chart_catalogs(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+// Configuration database manager
+/// Settings that belong to this device rather than its user, so backups and
+/// restores leave them alone
+pub const DEVICE_LOCAL_SETTINGS: [&str; 4] =
+    ["sync_device_id", "sync_device_name", "sync_bind_address", "data_server_bind_address"];
 
-        // Tracks table - recorded vessel trails
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tracks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                description TEXT,
-                color TEXT DEFAULT '#06b6d4',
-                is_recording INTEGER NOT NULL DEFAULT 0,
-                started_at TEXT,
-                ended_at TEXT,
-                total_distance_nm REAL,
-                point_count INTEGER DEFAULT 0,
-                hidden INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            )",
-            [],
-        )?;
+pub struct ConfigDatabase {
+    conn: Mutex<Connection>,
+    backup_dir: PathBuf,
+}
 
-        // Track points table - individual positions in a track
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS track_points (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                track_id INTEGER NOT NULL,
-                lat REAL NOT NULL,
-                lon REAL NOT NULL,
-                timestamp TEXT NOT NULL,
-                sequence INTEGER NOT NULL,
-                heading REAL,
-                cog REAL,
-                sog REAL,
-                FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+impl ConfigDatabase {
+    pub fn new(data_dir: &PathBuf) -> Result<Self, DatabaseError> {
+        std::fs::create_dir_all(data_dir)?;
+        let db_path = data_dir.join("vortexnav.db");
+        let mut conn = Connection::open(db_path)?;
 
-        // Index for faster track point lookups
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_track_points_track_id ON track_points(track_id)",
-            [],
-        )?;
+        let backup_dir = data_dir.join("backups");
+        migrations::migrate(&mut conn, Some(&backup_dir))?;
 
-        Ok(())
+        Ok(Self { conn: Mutex::new(conn), backup_dir })
+    }
+
+    /// Copy the database into the backups folder, e.g. before a destructive import
+    pub fn backup(&self) -> Result<PathBuf, DatabaseError> {
+        let conn = self.conn.lock().unwrap();
+        let version = migrations::schema_version(&conn)?;
+        migrations::backup(&conn, &self.backup_dir, version)
+    }
+
+    /// Run `f` as one transaction, holding the connection lock throughout.
+    /// Its writes are kept if it returns Ok and rolled back otherwise.
+    pub fn transaction<T, E: From<rusqlite::Error>>(&self, f: impl FnOnce(&Transaction) -> Result<T, E>) -> Result<T, E> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let value = f(&tx)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Delete everything a user data archive holds, in one transaction.
+    /// Device-local settings stay.
+    pub fn clear_user_data(&self) -> SqliteResult<()> {
+        self.transaction(|tx| clear_user_data(tx))
     }
 
     // Settings methods
@@ -601,12 +750,12 @@ impl ConfigDatabase {
     }
 
     pub fn set_setting(&self, key: &str, value: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)",
-            params![key, value],
-        )?;
-        Ok(())
+        set_setting(&self.conn.lock().unwrap(), key, value)
+    }
+
+    /// Every raw key/value pair in the settings table
+    pub fn get_setting_entries(&self) -> SqliteResult<Vec<(String, String)>> {
+        get_setting_entries(&self.conn.lock().unwrap())
     }
 
     pub fn get_all_settings(&self) -> SqliteResult<AppSettings> {
@@ -768,52 +917,95 @@ impl ConfigDatabase {
         Ok(())
     }
 
+    // GPS position filter settings methods
+    pub fn get_position_filter_settings(&self) -> SqliteResult<PositionFilterSettings> {
+        let mut settings = PositionFilterSettings::default();
+
+        if let Some(v) = self.get_setting("position_filter_enabled")? {
+            settings.enabled = v == "true";
+        }
+        let number = |key: &str| -> SqliteResult<Option<f64>> { Ok(self.get_setting(key)?.and_then(|v| v.parse().ok())) };
+        if let Some(v) = number("position_filter_alpha")? {
+            settings.position_alpha = v;
+        }
+        if let Some(v) = number("position_filter_beta")? {
+            settings.velocity_beta = v;
+        }
+        if let Some(v) = number("position_filter_max_speed_kn")? {
+            settings.max_speed_kn = v;
+        }
+        if let Some(v) = number("position_filter_low_speed_kn")? {
+            settings.low_speed_kn = v;
+        }
+        if let Some(v) = number("position_filter_max_hdop")? {
+            settings.max_hdop = v;
+        }
+        if let Some(v) = number("position_filter_min_satellites")? {
+            settings.min_satellites = v as u32;
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save_position_filter_settings(&self, settings: &PositionFilterSettings) -> SqliteResult<()> {
+        self.set_setting("position_filter_enabled", if settings.enabled { "true" } else { "false" })?;
+        self.set_setting("position_filter_alpha", &settings.position_alpha.to_string())?;
+        self.set_setting("position_filter_beta", &settings.velocity_beta.to_string())?;
+        self.set_setting("position_filter_max_speed_kn", &settings.max_speed_kn.to_string())?;
+        self.set_setting("position_filter_low_speed_kn", &settings.low_speed_kn.to_string())?;
+        self.set_setting("position_filter_max_hdop", &settings.max_hdop.to_string())?;
+        self.set_setting("position_filter_min_satellites", &settings.min_satellites.to_string())?;
+        Ok(())
+    }
+
+    pub fn get_data_server_settings(&self) -> SqliteResult<DataServerSettings> {
+        let mut settings = DataServerSettings::default();
+
+        if let Some(v) = self.get_setting("data_server_enabled")? {
+            settings.enabled = v == "true";
+        }
+        if let Some(v) = self.get_setting("data_server_bind_address")? {
+            settings.bind_address = v;
+        }
+        if let Some(port) = self.get_setting("data_server_nmea_port")?.and_then(|v| v.parse().ok()) {
+            settings.nmea_port = port;
+        }
+        if let Some(port) = self.get_setting("data_server_signalk_port")?.and_then(|v| v.parse().ok()) {
+            settings.signalk_port = port;
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save_data_server_settings(&self, settings: &DataServerSettings) -> SqliteResult<()> {
+        self.set_setting("data_server_enabled", if settings.enabled { "true" } else { "false" })?;
+        self.set_setting("data_server_bind_address", &settings.bind_address)?;
+        self.set_setting("data_server_nmea_port", &settings.nmea_port.to_string())?;
+        self.set_setting("data_server_signalk_port", &settings.signalk_port.to_string())?;
+        Ok(())
+    }
+
     // Waypoint methods
+    /// Create a waypoint. A waypoint that already has this UUID, even a deleted
+    /// one, is updated instead, so re-importing the same object never duplicates it.
     pub fn create_waypoint(&self, waypoint: &Waypoint) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO waypoints (name, lat, lon, description, symbol, show_label, hidden) VALUES (?, ?, ?, ?, ?, ?, ?)",
-            params![waypoint.name, waypoint.lat, waypoint.lon, waypoint.description, waypoint.symbol, if waypoint.show_label { 1 } else { 0 }, if waypoint.hidden { 1 } else { 0 }],
-        )?;
-        Ok(conn.last_insert_rowid())
+        create_waypoint(&self.conn.lock().unwrap(), waypoint)
     }
 
     pub fn get_waypoints(&self) -> SqliteResult<Vec<Waypoint>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, lat, lon, description, symbol, show_label, hidden, created_at FROM waypoints ORDER BY name"
-        )?;
-        let waypoints = stmt.query_map([], |row| {
-            Ok(Waypoint {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                lat: row.get(2)?,
-                lon: row.get(3)?,
-                description: row.get(4)?,
-                symbol: row.get(5)?,
-                show_label: row.get::<_, i32>(6)? == 1,
-                hidden: row.get::<_, i32>(7)? == 1,
-                created_at: row.get(8)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(waypoints)
+        get_waypoints(&self.conn.lock().unwrap())
     }
 
     pub fn update_waypoint(&self, waypoint: &Waypoint) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE waypoints SET name = ?, lat = ?, lon = ?, description = ?, symbol = ?, show_label = ?, hidden = ? WHERE id = ?",
-            params![waypoint.name, waypoint.lat, waypoint.lon, waypoint.description, waypoint.symbol, if waypoint.show_label { 1 } else { 0 }, if waypoint.hidden { 1 } else { 0 }, waypoint.id],
-        )?;
-        Ok(())
+        update_waypoint(&self.conn.lock().unwrap(), waypoint)
     }
 
     /// Toggle the hidden state of a waypoint
     pub fn toggle_waypoint_hidden(&self, id: i64, hidden: bool) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE waypoints SET hidden = ? WHERE id = ?",
-            params![if hidden { 1 } else { 0 }, id],
+            "UPDATE waypoints SET hidden = ?, updated_at = ? WHERE id = ?",
+            params![if hidden { 1 } else { 0 }, timestamp_now(), id],
         )?;
         Ok(())
     }
@@ -823,295 +1015,163 @@ impl ConfigDatabase {
     pub fn update_waypoint_position(&self, id: i64, lat: f64, lon: f64) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE waypoints SET lat = ?, lon = ? WHERE id = ?",
-            params![lat, lon, id],
+            "UPDATE waypoints SET lat = ?, lon = ?, updated_at = ? WHERE id = ?",
+            params![lat, lon, timestamp_now(), id],
         )?;
         Ok(())
     }
 
+    /// Delete a waypoint, keeping a tombstone so the deletion can be synced
     pub fn delete_waypoint(&self, id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM waypoints WHERE id = ?", params![id])?;
-        Ok(())
+        delete_waypoint(&self.conn.lock().unwrap(), id)
     }
 
-    // ============ Route Tag Methods ============
+    pub fn find_waypoint_id(&self, uuid: &str) -> SqliteResult<Option<i64>> {
+        find_waypoint_id(&self.conn.lock().unwrap(), uuid)
+    }
 
-    pub fn create_route_tag(&self, tag: &RouteTag) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO route_tags (name, color) VALUES (?, ?)",
-            params![tag.name, tag.color],
-        )?;
-        Ok(conn.last_insert_rowid())
+    // ============ Bulk Waypoint Methods ============
+
+    /// Move waypoints into a group, or out of every group with None.
+    /// Groups are local, so this does not count as an edit for sync.
+    pub fn move_waypoints_to_group(&self, ids: &[i64], group_id: Option<i64>) -> SqliteResult<()> {
+        self.transaction(|tx| move_waypoints_to_group(tx, ids, group_id))
     }
 
-    pub fn get_route_tags(&self) -> SqliteResult<Vec<RouteTag>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, color, created_at FROM route_tags ORDER BY name"
-        )?;
-        let tags = stmt.query_map([], |row| {
-            Ok(RouteTag {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                color: row.get(2)?,
-                created_at: row.get(3)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(tags)
+    pub fn set_waypoints_hidden(&self, ids: &[i64], hidden: bool) -> SqliteResult<()> {
+        self.update_waypoints(ids, "hidden", if hidden { 1 } else { 0 })
     }
 
-    pub fn update_route_tag(&self, tag: &RouteTag) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE route_tags SET name = ?, color = ? WHERE id = ?",
-            params![tag.name, tag.color, tag.id],
-        )?;
-        Ok(())
+    pub fn set_waypoints_show_label(&self, ids: &[i64], show_label: bool) -> SqliteResult<()> {
+        self.update_waypoints(ids, "show_label", if show_label { 1 } else { 0 })
     }
 
-    pub fn delete_route_tag(&self, id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM route_tags WHERE id = ?", params![id])?;
-        Ok(())
+    pub fn set_waypoints_symbol(&self, ids: &[i64], symbol: Option<&str>) -> SqliteResult<()> {
+        self.update_waypoints(ids, "symbol", symbol)
     }
 
-    // ============ Route Methods ============
+    // `column` is always one of the literals above
+    fn update_waypoints(&self, ids: &[i64], column: &str, value: impl rusqlite::ToSql) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.savepoint()?;
+        let now = timestamp_now();
+        for id in ids {
+            tx.execute(
+                &format!("UPDATE waypoints SET {} = ?, updated_at = ? WHERE id = ?", column),
+                params![value, now, id],
+            )?;
+        }
+        tx.commit()
+    }
 
-    pub fn create_route(&self, route: &Route, waypoint_ids: &[i64], tag_ids: &[i64]) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    /// Delete several waypoints in one transaction; like `delete_waypoint`,
+    /// each one is also taken out of the routes using it
+    pub fn delete_waypoints(&self, ids: &[i64]) -> SqliteResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.savepoint()?;
+        let now = timestamp_now();
+        for id in ids {
+            tombstone_waypoint(&tx, *id, &now)?;
+        }
+        tx.commit()
+    }
 
-        // Insert the route
-        conn.execute(
-            "INSERT INTO routes (name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-            params![
-                route.name,
-                route.description,
-                route.color,
-                if route.is_active { 1 } else { 0 },
-                if route.hidden { 1 } else { 0 },
-                route.total_distance_nm,
-                route.estimated_speed_kn
-            ],
-        )?;
-        let route_id = conn.last_insert_rowid();
-
-        // Insert waypoint associations with sequence
-        for (seq, wp_id) in waypoint_ids.iter().enumerate() {
-            conn.execute(
-                "INSERT INTO route_waypoints (route_id, waypoint_id, sequence) VALUES (?, ?, ?)",
-                params![route_id, wp_id, seq as i32],
-            )?;
-        }
+    // ============ Waypoint Group Methods ============
 
-        // Insert tag associations
-        for tag_id in tag_ids {
-            conn.execute(
-                "INSERT INTO route_tag_assignments (route_id, tag_id) VALUES (?, ?)",
-                params![route_id, tag_id],
-            )?;
-        }
+    pub fn create_waypoint_group(&self, group: &WaypointGroup) -> SqliteResult<i64> {
+        create_waypoint_group(&self.conn.lock().unwrap(), group)
+    }
 
-        Ok(route_id)
+    pub fn get_waypoint_groups(&self) -> SqliteResult<Vec<WaypointGroup>> {
+        get_waypoint_groups(&self.conn.lock().unwrap())
     }
 
-    pub fn get_routes(&self) -> SqliteResult<Vec<RouteWithWaypoints>> {
+    /// Rename a group or move it under another parent
+    pub fn update_waypoint_group(&self, group: &WaypointGroup) -> Result<(), DatabaseError> {
         let conn = self.conn.lock().unwrap();
-
-        // Get all routes
-        let mut routes_stmt = conn.prepare(
-            "SELECT id, name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, created_at, updated_at
-             FROM routes ORDER BY name"
+        if let (Some(id), Some(parent_id)) = (group.id, group.parent_id) {
+            if group_subtree(&conn, id)?.contains(&parent_id) {
+                return Err(DatabaseError::GroupCycle);
+            }
+        }
+        conn.execute(
+            "UPDATE waypoint_groups SET name = ?, parent_id = ?, hidden = ? WHERE id = ?",
+            params![group.name, group.parent_id, if group.hidden { 1 } else { 0 }, group.id],
         )?;
+        Ok(())
+    }
 
-        let routes: Vec<Route> = routes_stmt.query_map([], |row| {
-            Ok(Route {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                description: row.get(2)?,
-                color: row.get(3)?,
-                is_active: row.get::<_, i32>(4)? == 1,
-                hidden: row.get::<_, i32>(5).unwrap_or(0) == 1,
-                total_distance_nm: row.get(6)?,
-                estimated_speed_kn: row.get::<_, f64>(7).unwrap_or(5.0),
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-
-        // For each route, get waypoints and tags
-        let mut result = Vec::with_capacity(routes.len());
-
-        for route in routes {
-            let route_id = route.id.unwrap();
+    /// Show or hide a group. Nested groups keep their own setting, but nothing
+    /// inside a hidden group is drawn.
+    pub fn toggle_waypoint_group_hidden(&self, id: i64, hidden: bool) -> SqliteResult<()> {
+        toggle_waypoint_group_hidden(&self.conn.lock().unwrap(), id, hidden)
+    }
 
-            // Get waypoints for this route (ordered by sequence)
-            let mut wp_stmt = conn.prepare(
-                "SELECT w.id, w.name, w.lat, w.lon, w.description, w.symbol, w.show_label, w.hidden, w.created_at
-                 FROM waypoints w
-                 JOIN route_waypoints rw ON w.id = rw.waypoint_id
-                 WHERE rw.route_id = ?
-                 ORDER BY rw.sequence"
-            )?;
+    /// Get waypoints in a group or its subgroups that no route uses
+    pub fn get_exclusive_group_waypoints(&self, group_id: i64) -> SqliteResult<Vec<i64>> {
+        get_exclusive_group_waypoints(&self.conn.lock().unwrap(), group_id)
+    }
 
-            let waypoints: Vec<Waypoint> = wp_stmt.query_map(params![route_id], |row| {
-                Ok(Waypoint {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    lat: row.get(2)?,
-                    lon: row.get(3)?,
-                    description: row.get(4)?,
-                    symbol: row.get(5)?,
-                    show_label: row.get::<_, i32>(6)? == 1,
-                    hidden: row.get::<_, i32>(7)? == 1,
-                    created_at: row.get(8)?,
-                })
-            })?.collect::<Result<Vec<_>, _>>()?;
+    /// Delete a group and the groups nested in it. With `delete_waypoints`, their
+    /// waypoints go too, except those used by a route; whatever is kept moves up
+    /// to the deleted group's parent. Returns the ids of deleted waypoints.
+    pub fn delete_waypoint_group(&self, id: i64, delete_waypoints: bool) -> SqliteResult<Vec<i64>> {
+        self.transaction(|tx| delete_waypoint_group(tx, id, delete_waypoints))
+    }
 
-            // Get tags for this route
-            let mut tag_stmt = conn.prepare(
-                "SELECT t.id, t.name, t.color, t.created_at
-                 FROM route_tags t
-                 JOIN route_tag_assignments rta ON t.id = rta.tag_id
-                 WHERE rta.route_id = ?"
-            )?;
+    // ============ Route Tag Methods ============
 
-            let tags: Vec<RouteTag> = tag_stmt.query_map(params![route_id], |row| {
-                Ok(RouteTag {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    color: row.get(2)?,
-                    created_at: row.get(3)?,
-                })
-            })?.collect::<Result<Vec<_>, _>>()?;
+    /// Create a tag, or update the tag that already has its UUID. Tag names are
+    /// unique, so a deleted tag with the same name is brought back instead.
+    pub fn create_route_tag(&self, tag: &RouteTag) -> SqliteResult<i64> {
+        create_route_tag(&self.conn.lock().unwrap(), tag)
+    }
 
-            result.push(RouteWithWaypoints { route, waypoints, tags });
-        }
+    pub fn find_route_tag_id(&self, uuid: &str) -> SqliteResult<Option<i64>> {
+        find_route_tag_id(&self.conn.lock().unwrap(), uuid)
+    }
 
-        Ok(result)
+    /// Give a tag another device's UUID, when both created a tag with the same name
+    pub fn set_route_tag_uuid(&self, id: i64, uuid: &str) -> SqliteResult<()> {
+        set_route_tag_uuid(&self.conn.lock().unwrap(), id, uuid)
     }
 
-    pub fn get_route(&self, id: i64) -> SqliteResult<Option<RouteWithWaypoints>> {
-        let conn = self.conn.lock().unwrap();
+    pub fn get_route_tags(&self) -> SqliteResult<Vec<RouteTag>> {
+        get_route_tags(&self.conn.lock().unwrap())
+    }
 
-        // Get the route
-        let mut route_stmt = conn.prepare(
-            "SELECT id, name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, created_at, updated_at
-             FROM routes WHERE id = ?"
-        )?;
+    pub fn update_route_tag(&self, tag: &RouteTag) -> SqliteResult<()> {
+        update_route_tag(&self.conn.lock().unwrap(), tag)
+    }
 
-        let route: Option<Route> = route_stmt.query_row(params![id], |row| {
-            Ok(Route {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                description: row.get(2)?,
-                color: row.get(3)?,
-                is_active: row.get::<_, i32>(4)? == 1,
-                hidden: row.get::<_, i32>(5).unwrap_or(0) == 1,
-                total_distance_nm: row.get(6)?,
-                estimated_speed_kn: row.get::<_, f64>(7).unwrap_or(5.0),
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        }).optional()?;
+    pub fn delete_route_tag(&self, id: i64) -> SqliteResult<()> {
+        delete_route_tag(&self.conn.lock().unwrap(), id)
+    }
 
-        match route {
-            Some(route) => {
-                // Get waypoints
-                let mut wp_stmt = conn.prepare(
-                    "SELECT w.id, w.name, w.lat, w.lon, w.description, w.symbol, w.show_label, w.hidden, w.created_at
-                     FROM waypoints w
-                     JOIN route_waypoints rw ON w.id = rw.waypoint_id
-                     WHERE rw.route_id = ?
-                     ORDER BY rw.sequence"
-                )?;
+    // ============ Route Methods ============
 
-                let waypoints: Vec<Waypoint> = wp_stmt.query_map(params![id], |row| {
-                    Ok(Waypoint {
-                        id: Some(row.get(0)?),
-                        name: row.get(1)?,
-                        lat: row.get(2)?,
-                        lon: row.get(3)?,
-                        description: row.get(4)?,
-                        symbol: row.get(5)?,
-                        show_label: row.get::<_, i32>(6)? == 1,
-                        hidden: row.get::<_, i32>(7)? == 1,
-                        created_at: row.get(8)?,
-                    })
-                })?.collect::<Result<Vec<_>, _>>()?;
-
-                // Get tags
-                let mut tag_stmt = conn.prepare(
-                    "SELECT t.id, t.name, t.color, t.created_at
-                     FROM route_tags t
-                     JOIN route_tag_assignments rta ON t.id = rta.tag_id
-                     WHERE rta.route_id = ?"
-                )?;
+    pub fn create_route(&self, route: &Route, waypoint_ids: &[i64], tag_ids: &[i64]) -> SqliteResult<i64> {
+        self.transaction(|tx| create_route(tx, route, waypoint_ids, tag_ids))
+    }
 
-                let tags: Vec<RouteTag> = tag_stmt.query_map(params![id], |row| {
-                    Ok(RouteTag {
-                        id: Some(row.get(0)?),
-                        name: row.get(1)?,
-                        color: row.get(2)?,
-                        created_at: row.get(3)?,
-                    })
-                })?.collect::<Result<Vec<_>, _>>()?;
+    pub fn get_routes(&self) -> SqliteResult<Vec<RouteWithWaypoints>> {
+        get_routes(&self.conn.lock().unwrap())
+    }
 
-                Ok(Some(RouteWithWaypoints { route, waypoints, tags }))
-            }
-            None => Ok(None),
-        }
+    pub fn get_route(&self, id: i64) -> SqliteResult<Option<RouteWithWaypoints>> {
+        get_route(&self.conn.lock().unwrap(), id)
     }
 
     pub fn update_route(&self, route: &Route, waypoint_ids: &[i64], tag_ids: &[i64]) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        let route_id = route.id.ok_or(rusqlite::Error::InvalidParameterName("Route must have an id".to_string()))?;
-
-        // Update route metadata
-        conn.execute(
-            "UPDATE routes SET name = ?, description = ?, color = ?, is_active = ?, hidden = ?,
-             total_distance_nm = ?, estimated_speed_kn = ?, updated_at = CURRENT_TIMESTAMP
-             WHERE id = ?",
-            params![
-                route.name,
-                route.description,
-                route.color,
-                if route.is_active { 1 } else { 0 },
-                if route.hidden { 1 } else { 0 },
-                route.total_distance_nm,
-                route.estimated_speed_kn,
-                route_id
-            ],
-        )?;
-
-        // Replace waypoint associations
-        conn.execute("DELETE FROM route_waypoints WHERE route_id = ?", params![route_id])?;
-        for (seq, wp_id) in waypoint_ids.iter().enumerate() {
-            conn.execute(
-                "INSERT INTO route_waypoints (route_id, waypoint_id, sequence) VALUES (?, ?, ?)",
-                params![route_id, wp_id, seq as i32],
-            )?;
-        }
-
-        // Replace tag associations
-        conn.execute("DELETE FROM route_tag_assignments WHERE route_id = ?", params![route_id])?;
-        for tag_id in tag_ids {
-            conn.execute(
-                "INSERT INTO route_tag_assignments (route_id, tag_id) VALUES (?, ?)",
-                params![route_id, tag_id],
-            )?;
-        }
-
-        Ok(())
+        self.transaction(|tx| update_route(tx, route, waypoint_ids, tag_ids))
     }
 
     pub fn delete_route(&self, id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        // CASCADE will delete route_waypoints and route_tag_assignments
-        conn.execute("DELETE FROM routes WHERE id = ?", params![id])?;
-        Ok(())
+        delete_route(&self.conn.lock().unwrap(), id)
+    }
+
+    pub fn find_route_id(&self, uuid: &str) -> SqliteResult<Option<i64>> {
+        find_route_id(&self.conn.lock().unwrap(), uuid)
     }
 
     /// Get waypoints that belong to a route and are not used by any other route
@@ -1145,14 +1205,14 @@ impl ConfigDatabase {
         };
 
         let conn = self.conn.lock().unwrap();
+        let now = timestamp_now();
 
-        // Delete the route (CASCADE will delete route_waypoints and route_tag_assignments)
-        conn.execute("DELETE FROM routes WHERE id = ?", params![id])?;
+        tombstone_route(&conn, id, &now)?;
 
         // Delete the exclusive waypoints if requested
         if delete_waypoints {
             for wp_id in &waypoints_to_delete {
-                conn.execute("DELETE FROM waypoints WHERE id = ?", params![wp_id])?;
+                tombstone_waypoint(&conn, *wp_id, &now)?;
             }
         }
 
@@ -1173,9 +1233,9 @@ impl ConfigDatabase {
 
         // Create the new route (duplicates start visible and not active)
         conn.execute(
-            "INSERT INTO routes (name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn)
-             VALUES (?, ?, ?, 0, 0, ?, ?)",
-            params![new_name, description, color, distance, speed],
+            "INSERT INTO routes (name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, uuid, updated_at)
+             VALUES (?, ?, ?, 0, 0, ?, ?, ?, ?)",
+            params![new_name, description, color, distance, speed, new_uuid(&None), timestamp_now()],
         )?;
         let new_id = conn.last_insert_rowid();
 
@@ -1214,8 +1274,8 @@ impl ConfigDatabase {
 
         // Update the route's updated_at timestamp
         conn.execute(
-            "UPDATE routes SET updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-            params![id],
+            "UPDATE routes SET updated_at = ? WHERE id = ?",
+            params![timestamp_now(), id],
         )?;
 
         Ok(())
@@ -1242,8 +1302,8 @@ impl ConfigDatabase {
     pub fn toggle_route_hidden(&self, id: i64, hidden: bool) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE routes SET hidden = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-            params![if hidden { 1 } else { 0 }, id],
+            "UPDATE routes SET hidden = ?, updated_at = ? WHERE id = ?",
+            params![if hidden { 1 } else { 0 }, timestamp_now(), id],
         )?;
         Ok(())
     }
@@ -1261,8 +1321,8 @@ impl ConfigDatabase {
     pub fn get_waypoint(&self, id: i64) -> SqliteResult<Option<Waypoint>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, name, lat, lon, description, symbol, show_label, hidden, created_at
-             FROM waypoints WHERE id = ?"
+            "SELECT id, name, lat, lon, description, symbol, show_label, hidden, created_at, uuid, updated_at, group_id, arrival_radius_nm, depth_m, temperature_c
+             FROM waypoints WHERE id = ? AND deleted_at IS NULL"
         )?;
         let waypoint = stmt.query_row(params![id], |row| {
             Ok(Waypoint {
@@ -1275,6 +1335,12 @@ impl ConfigDatabase {
                 show_label: row.get::<_, i32>(6)? == 1,
                 hidden: row.get::<_, i32>(7)? == 1,
                 created_at: row.get(8)?,
+                uuid: row.get(9)?,
+                updated_at: row.get(10)?,
+                group_id: row.get(11)?,
+                arrival_radius_nm: row.get(12)?,
+                depth_m: row.get(13)?,
+                temperature_c: row.get(14)?,
             })
         }).optional()?;
         Ok(waypoint)
@@ -1304,41 +1370,11 @@ impl ConfigDatabase {
 
     // GPS source methods
     pub fn save_gps_source(&self, source: &GpsSourceRecord) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO gps_sources (id, name, source_type, port_name, baud_rate, enabled, priority)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-            params![
-                source.id,
-                source.name,
-                source.source_type,
-                source.port_name,
-                source.baud_rate,
-                if source.enabled { 1 } else { 0 },
-                source.priority
-            ],
-        )?;
-        Ok(())
+        save_gps_source(&self.conn.lock().unwrap(), source)
     }
 
     pub fn get_gps_sources(&self) -> SqliteResult<Vec<GpsSourceRecord>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, source_type, port_name, baud_rate, enabled, priority
-             FROM gps_sources ORDER BY priority ASC, name ASC"
-        )?;
-        let sources = stmt.query_map([], |row| {
-            Ok(GpsSourceRecord {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                source_type: row.get(2)?,
-                port_name: row.get(3)?,
-                baud_rate: row.get(4)?,
-                enabled: row.get::<_, i32>(5)? == 1,
-                priority: row.get(6)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(sources)
+        get_gps_sources(&self.conn.lock().unwrap())
     }
 
     pub fn delete_gps_source(&self, id: &str) -> SqliteResult<()> {
@@ -1358,34 +1394,11 @@ impl ConfigDatabase {
 
     // Chart layer state methods
     pub fn save_chart_layer_state(&self, state: &ChartLayerState) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO chart_layers (chart_id, enabled, opacity, z_order)
-             VALUES (?, ?, ?, ?)",
-            params![
-                state.chart_id,
-                if state.enabled { 1 } else { 0 },
-                state.opacity,
-                state.z_order
-            ],
-        )?;
-        Ok(())
+        save_chart_layer_state(&self.conn.lock().unwrap(), state)
     }
 
     pub fn get_chart_layer_states(&self) -> SqliteResult<Vec<ChartLayerState>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT chart_id, enabled, opacity, z_order FROM chart_layers ORDER BY z_order ASC"
-        )?;
-        let states = stmt.query_map([], |row| {
-            Ok(ChartLayerState {
-                chart_id: row.get(0)?,
-                enabled: row.get::<_, i32>(1)? == 1,
-                opacity: row.get(2)?,
-                z_order: row.get(3)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(states)
+        get_chart_layer_states(&self.conn.lock().unwrap())
     }
 
     pub fn get_chart_layer_state(&self, chart_id: &str) -> SqliteResult<Option<ChartLayerState>> {
@@ -1416,26 +1429,7 @@ impl ConfigDatabase {
 
     /// Save custom metadata for a chart layer
     pub fn save_chart_custom_metadata(&self, metadata: &ChartCustomMetadata) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        // First ensure the row exists (create with defaults if not)
-        conn.execute(
-            "INSERT OR IGNORE INTO chart_layers (chart_id, enabled, opacity, z_order)
-             VALUES (?, 1, 1.0, 0)",
-            params![metadata.chart_id],
-        )?;
-        // Then update the custom fields
-        conn.execute(
-            "UPDATE chart_layers SET custom_name = ?, custom_description = ?, custom_min_zoom = ?, custom_max_zoom = ?
-             WHERE chart_id = ?",
-            params![
-                metadata.custom_name,
-                metadata.custom_description,
-                metadata.custom_min_zoom,
-                metadata.custom_max_zoom,
-                metadata.chart_id,
-            ],
-        )?;
-        Ok(())
+        save_chart_custom_metadata(&self.conn.lock().unwrap(), metadata)
     }
 
     /// Get custom metadata for a chart
@@ -1463,23 +1457,7 @@ impl ConfigDatabase {
 
     /// Get all custom metadata for charts
     pub fn get_all_chart_custom_metadata(&self) -> SqliteResult<Vec<ChartCustomMetadata>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT chart_id, custom_name, custom_description, custom_min_zoom, custom_max_zoom
-             FROM chart_layers
-             WHERE custom_name IS NOT NULL OR custom_description IS NOT NULL
-                OR custom_min_zoom IS NOT NULL OR custom_max_zoom IS NOT NULL"
-        )?;
-        let metadata = stmt.query_map([], |row| {
-            Ok(ChartCustomMetadata {
-                chart_id: row.get(0)?,
-                custom_name: row.get(1)?,
-                custom_description: row.get(2)?,
-                custom_min_zoom: row.get(3)?,
-                custom_max_zoom: row.get(4)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(metadata)
+        get_all_chart_custom_metadata(&self.conn.lock().unwrap())
     }
 
     pub fn delete_mbtiles_registry(&self, chart_id: &str) -> SqliteResult<()> {
@@ -1494,36 +1472,11 @@ impl ConfigDatabase {
 
     // Chart catalog methods
     pub fn create_catalog(&self, catalog: &ChartCatalog) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO chart_catalogs (name, catalog_type, source_type, source_path)
-             VALUES (?, ?, ?, ?)",
-            params![catalog.name, catalog.catalog_type, catalog.source_type, catalog.source_path],
-        )?;
-        Ok(conn.last_insert_rowid())
+        create_catalog(&self.conn.lock().unwrap(), catalog)
     }
 
     pub fn get_catalogs(&self) -> SqliteResult<Vec<ChartCatalog>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT c.id, c.name, c.catalog_type, c.source_type, c.source_path,
-                    c.imported_at, c.last_refreshed,
-                    (SELECT COUNT(*) FROM catalog_charts WHERE catalog_id = c.id) as chart_count
-             FROM chart_catalogs c ORDER BY c.name"
-        )?;
-        let catalogs = stmt.query_map([], |row| {
-            Ok(ChartCatalog {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                catalog_type: row.get(2)?,
-                source_type: row.get(3)?,
-                source_path: row.get(4)?,
-                imported_at: row.get(5)?,
-                last_refreshed: row.get(6)?,
-                chart_count: row.get(7)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(catalogs)
+        get_catalogs(&self.conn.lock().unwrap())
     }
 
     pub fn get_catalog(&self, id: i64) -> SqliteResult<Option<ChartCatalog>> {
@@ -1571,58 +1524,11 @@ impl ConfigDatabase {
 
     // Catalog chart methods
     pub fn create_catalog_chart(&self, chart: &CatalogChart) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO catalog_charts (catalog_id, chart_id, title, chart_type, format, scale,
-                                         status, download_url, file_size, last_updated, bounds)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            params![
-                chart.catalog_id,
-                chart.chart_id,
-                chart.title,
-                chart.chart_type,
-                chart.format,
-                chart.scale,
-                chart.status,
-                chart.download_url,
-                chart.file_size,
-                chart.last_updated,
-                chart.bounds
-            ],
-        )?;
-        Ok(conn.last_insert_rowid())
+        create_catalog_chart(&self.conn.lock().unwrap(), chart)
     }
 
     pub fn get_catalog_charts(&self, catalog_id: i64) -> SqliteResult<Vec<CatalogChart>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, catalog_id, chart_id, title, chart_type, format, scale, status,
-                    download_url, file_size, last_updated, bounds, download_status,
-                    download_progress, download_path, mbtiles_path, error_message
-             FROM catalog_charts WHERE catalog_id = ? ORDER BY title"
-        )?;
-        let charts = stmt.query_map([catalog_id], |row| {
-            Ok(CatalogChart {
-                id: Some(row.get(0)?),
-                catalog_id: row.get(1)?,
-                chart_id: row.get(2)?,
-                title: row.get(3)?,
-                chart_type: row.get(4)?,
-                format: row.get(5)?,
-                scale: row.get(6)?,
-                status: row.get(7)?,
-                download_url: row.get(8)?,
-                file_size: row.get(9)?,
-                last_updated: row.get(10)?,
-                bounds: row.get(11)?,
-                download_status: row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "available".to_string()),
-                download_progress: row.get::<_, Option<i64>>(13)?.unwrap_or(0),
-                download_path: row.get(14)?,
-                mbtiles_path: row.get(15)?,
-                error_message: row.get(16)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(charts)
+        get_catalog_charts(&self.conn.lock().unwrap(), catalog_id)
     }
 
     pub fn get_catalog_chart(&self, id: i64) -> SqliteResult<Option<CatalogChart>> {
@@ -1724,8 +1630,8 @@ impl ConfigDatabase {
         let conn = self.conn.lock().unwrap();
         let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
-            "INSERT INTO tracks (name, is_recording, started_at, point_count) VALUES (?, 1, ?, 0)",
-            params![name, now],
+            "INSERT INTO tracks (name, is_recording, started_at, point_count, uuid, updated_at) VALUES (?, 1, ?, 0, ?, ?)",
+            params![name, now, new_uuid(&None), timestamp_now()],
         )?;
         Ok(conn.last_insert_rowid())
     }
@@ -1735,8 +1641,8 @@ impl ConfigDatabase {
         let conn = self.conn.lock().unwrap();
         let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
-            "UPDATE tracks SET is_recording = 0, ended_at = ? WHERE id = ?",
-            params![now, id],
+            "UPDATE tracks SET is_recording = 0, ended_at = ?, updated_at = ? WHERE id = ?",
+            params![now, timestamp_now(), id],
         )?;
         Ok(())
     }
@@ -1746,8 +1652,8 @@ impl ConfigDatabase {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, color, is_recording, started_at, ended_at,
-                    total_distance_nm, point_count, hidden, created_at
-             FROM tracks WHERE is_recording = 1 LIMIT 1"
+                    total_distance_nm, point_count, hidden, created_at, uuid, updated_at
+             FROM tracks WHERE is_recording = 1 AND deleted_at IS NULL LIMIT 1"
         )?;
         let result = stmt.query_row([], |row| {
             Ok(Track {
@@ -1762,6 +1668,8 @@ impl ConfigDatabase {
                 point_count: row.get::<_, i64>(8).unwrap_or(0),
                 hidden: row.get::<_, i32>(9).unwrap_or(0) == 1,
                 created_at: row.get(10)?,
+                uuid: row.get(11)?,
+                updated_at: row.get(12)?,
             })
         });
         match result {
@@ -1784,18 +1692,18 @@ impl ConfigDatabase {
         let conn = self.conn.lock().unwrap();
         let now = chrono::Utc::now().to_rfc3339();
 
-        // Get current sequence number
-        let sequence: i64 = conn.query_row(
-            "SELECT COALESCE(MAX(sequence), -1) + 1 FROM track_points WHERE track_id = ?",
+        // Get current sequence number; recording carries on in the last segment
+        let (sequence, segment): (i64, i64) = conn.query_row(
+            "SELECT COALESCE(MAX(sequence), -1) + 1, COALESCE(MAX(segment), 0) FROM track_points WHERE track_id = ?",
             params![track_id],
-            |row| row.get(0),
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
 
         // Insert new point
         conn.execute(
-            "INSERT INTO track_points (track_id, lat, lon, timestamp, sequence, heading, cog, sog)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-            params![track_id, lat, lon, now, sequence, heading, cog, sog],
+            "INSERT INTO track_points (track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![track_id, lat, lon, now, sequence, heading, cog, sog, segment],
         )?;
         let point_id = conn.last_insert_rowid();
 
@@ -1809,54 +1717,40 @@ impl ConfigDatabase {
     fn update_track_stats_internal(&self, conn: &Connection, track_id: i64) -> SqliteResult<()> {
         // Get all points for distance calculation
         let mut stmt = conn.prepare(
-            "SELECT lat, lon FROM track_points WHERE track_id = ? ORDER BY sequence"
+            "SELECT lat, lon, segment FROM track_points WHERE track_id = ? ORDER BY sequence"
         )?;
-        let points: Vec<(f64, f64)> = stmt.query_map(params![track_id], |row| {
-            Ok((row.get(0)?, row.get(1)?))
+        let points: Vec<(f64, f64, i64)> = stmt.query_map(params![track_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
         })?.collect::<Result<Vec<_>, _>>()?;
 
         let point_count = points.len() as i64;
 
-        // Calculate total distance
+        // Calculate total distance, not counting the gaps between segments
         let mut total_distance = 0.0;
         for i in 1..points.len() {
-            let (lat1, lon1) = points[i - 1];
-            let (lat2, lon2) = points[i];
-            total_distance += haversine_distance_nm(lat1, lon1, lat2, lon2);
+            let (lat1, lon1, segment1) = points[i - 1];
+            let (lat2, lon2, segment2) = points[i];
+            if segment1 == segment2 {
+                total_distance += haversine_distance_nm(lat1, lon1, lat2, lon2);
+            }
         }
 
         conn.execute(
-            "UPDATE tracks SET point_count = ?, total_distance_nm = ? WHERE id = ?",
-            params![point_count, total_distance, track_id],
+            "UPDATE tracks SET point_count = ?, total_distance_nm = ?, updated_at = ? WHERE id = ?",
+            params![point_count, total_distance, timestamp_now(), track_id],
         )?;
 
         Ok(())
     }
 
+    /// Insert a finished track with its points, e.g. from a backup archive
+    pub fn import_track(&self, track: &Track, points: &[TrackPoint]) -> SqliteResult<i64> {
+        self.transaction(|tx| import_track(tx, track, points))
+    }
+
     /// Get all tracks
     pub fn get_tracks(&self) -> SqliteResult<Vec<Track>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, description, color, is_recording, started_at, ended_at,
-                    total_distance_nm, point_count, hidden, created_at
-             FROM tracks ORDER BY created_at DESC"
-        )?;
-        let tracks = stmt.query_map([], |row| {
-            Ok(Track {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                description: row.get(2)?,
-                color: row.get(3)?,
-                is_recording: row.get::<_, i32>(4)? == 1,
-                started_at: row.get(5)?,
-                ended_at: row.get(6)?,
-                total_distance_nm: row.get(7)?,
-                point_count: row.get::<_, i64>(8).unwrap_or(0),
-                hidden: row.get::<_, i32>(9).unwrap_or(0) == 1,
-                created_at: row.get(10)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(tracks)
+        get_tracks(&self.conn.lock().unwrap())
     }
 
     /// Get a track by ID
@@ -1864,8 +1758,8 @@ impl ConfigDatabase {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, name, description, color, is_recording, started_at, ended_at,
-                    total_distance_nm, point_count, hidden, created_at
-             FROM tracks WHERE id = ?"
+                    total_distance_nm, point_count, hidden, created_at, uuid, updated_at
+             FROM tracks WHERE id = ? AND deleted_at IS NULL"
         )?;
         let result = stmt.query_row(params![id], |row| {
             Ok(Track {
@@ -1880,6 +1774,8 @@ impl ConfigDatabase {
                 point_count: row.get::<_, i64>(8).unwrap_or(0),
                 hidden: row.get::<_, i32>(9).unwrap_or(0) == 1,
                 created_at: row.get(10)?,
+                uuid: row.get(11)?,
+                updated_at: row.get(12)?,
             })
         });
         match result {
@@ -1898,7 +1794,7 @@ impl ConfigDatabase {
 
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog
+            "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment, depth_m, temperature_c
              FROM track_points WHERE track_id = ? ORDER BY sequence"
         )?;
         let points: Vec<TrackPoint> = stmt.query_map(params![id], |row| {
@@ -1912,6 +1808,9 @@ impl ConfigDatabase {
                 heading: row.get(6)?,
                 cog: row.get(7)?,
                 sog: row.get(8)?,
+                segment: row.get(9)?,
+                depth_m: row.get(10)?,
+                temperature_c: row.get(11)?,
             })
         })?.collect::<Result<Vec<_>, _>>()?;
 
@@ -1920,69 +1819,38 @@ impl ConfigDatabase {
 
     /// Get all tracks with their points
     pub fn get_tracks_with_points(&self) -> SqliteResult<Vec<TrackWithPoints>> {
-        let tracks = self.get_tracks()?;
-        let conn = self.conn.lock().unwrap();
-
-        let mut result = Vec::with_capacity(tracks.len());
-        for track in tracks {
-            let track_id = track.id.unwrap();
-            let mut stmt = conn.prepare(
-                "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog
-                 FROM track_points WHERE track_id = ? ORDER BY sequence"
-            )?;
-            let points: Vec<TrackPoint> = stmt.query_map(params![track_id], |row| {
-                Ok(TrackPoint {
-                    id: Some(row.get(0)?),
-                    track_id: row.get(1)?,
-                    lat: row.get(2)?,
-                    lon: row.get(3)?,
-                    timestamp: row.get(4)?,
-                    sequence: row.get(5)?,
-                    heading: row.get(6)?,
-                    cog: row.get(7)?,
-                    sog: row.get(8)?,
-                })
-            })?.collect::<Result<Vec<_>, _>>()?;
-
-            result.push(TrackWithPoints { track, points });
-        }
-
-        Ok(result)
+        get_tracks_with_points(&self.conn.lock().unwrap())
     }
 
     /// Update track metadata
     pub fn update_track(&self, track: &Track) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE tracks SET name = ?, description = ?, color = ?, hidden = ? WHERE id = ?",
-            params![track.name, track.description, track.color, if track.hidden { 1 } else { 0 }, track.id],
-        )?;
-        Ok(())
+        update_track(&self.conn.lock().unwrap(), track)
     }
 
     /// Toggle track visibility
     pub fn toggle_track_hidden(&self, id: i64, hidden: bool) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE tracks SET hidden = ? WHERE id = ?",
-            params![if hidden { 1 } else { 0 }, id],
+            "UPDATE tracks SET hidden = ?, updated_at = ? WHERE id = ?",
+            params![if hidden { 1 } else { 0 }, timestamp_now(), id],
         )?;
         Ok(())
     }
 
     /// Delete a track and all its points
     pub fn delete_track(&self, id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
-        // CASCADE will delete track_points
-        conn.execute("DELETE FROM tracks WHERE id = ?", params![id])?;
-        Ok(())
+        delete_track(&self.conn.lock().unwrap(), id)
     }
 
-    /// Get track points for a track
+    pub fn find_track_id(&self, uuid: &str) -> SqliteResult<Option<i64>> {
+        find_track_id(&self.conn.lock().unwrap(), uuid)
+    }
+
+    /// Get track points for a track
     pub fn get_track_points(&self, track_id: i64) -> SqliteResult<Vec<TrackPoint>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog
+            "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment, depth_m, temperature_c
              FROM track_points WHERE track_id = ? ORDER BY sequence"
         )?;
         let points = stmt.query_map(params![track_id], |row| {
@@ -1996,10 +1864,1278 @@ impl ConfigDatabase {
                 heading: row.get(6)?,
                 cog: row.get(7)?,
                 sog: row.get(8)?,
+                segment: row.get(9)?,
+                depth_m: row.get(10)?,
+                temperature_c: row.get(11)?,
             })
         })?.collect::<Result<Vec<_>, _>>()?;
         Ok(points)
     }
+
+    // ============ Boat Polar Methods ============
+
+    pub fn create_boat_polar(&self, name: &str, polar_data: &str) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO boat_polars (name, polar_data) VALUES (?, ?)",
+            params![name, polar_data],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_boat_polars(&self) -> SqliteResult<Vec<BoatPolarRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, created_at FROM boat_polars ORDER BY name"
+        )?;
+        let polars = stmt.query_map([], |row| {
+            Ok(BoatPolarRecord {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(polars)
+    }
+
+    /// Get the stored polar table JSON
+    pub fn get_boat_polar_data(&self, id: i64) -> SqliteResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT polar_data FROM boat_polars WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ).optional()
+    }
+
+    pub fn delete_boat_polar(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM boat_polars WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    // ============ Tide Station Methods ============
+
+    /// Add a tide station. A station with the same name and position is
+    /// updated in place and keeps its id, so re-importing a file does not
+    /// duplicate it.
+    pub fn upsert_tide_station(&self, station: &TideStationRecord, constituents: &str) -> SqliteResult<i64> {
+        upsert_tide_station(&self.conn.lock().unwrap(), station, constituents)
+    }
+
+    pub fn get_tide_stations(&self) -> SqliteResult<Vec<TideStationRecord>> {
+        Ok(self.get_tide_stations_with_constituents()?.into_iter().map(|(s, _)| s).collect())
+    }
+
+    /// Get all tide stations with their constituent JSON
+    pub fn get_tide_stations_with_constituents(&self) -> SqliteResult<Vec<(TideStationRecord, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, lat, lon, mean_level_m, source, created_at, constituents
+             FROM tide_stations ORDER BY name"
+        )?;
+        let stations = stmt.query_map([], |row| {
+            Ok((
+                TideStationRecord {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    lat: row.get(2)?,
+                    lon: row.get(3)?,
+                    mean_level_m: row.get(4)?,
+                    source: row.get(5)?,
+                    created_at: row.get(6)?,
+                },
+                row.get(7)?,
+            ))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(stations)
+    }
+
+    pub fn delete_tide_station(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM tide_stations WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    // ============ Tidal Stream Methods ============
+
+    /// Add a tidal current station, updating one with the same name and
+    /// position in place as `upsert_tide_station` does
+    pub fn upsert_tidal_current_station(&self, station: &TidalCurrentStationRecord, constituents: &str) -> SqliteResult<i64> {
+        upsert_tidal_current_station(&self.conn.lock().unwrap(), station, constituents)
+    }
+
+    pub fn get_tidal_current_stations(&self) -> SqliteResult<Vec<TidalCurrentStationRecord>> {
+        Ok(self.get_tidal_current_stations_with_constituents()?.into_iter().map(|(s, _)| s).collect())
+    }
+
+    /// Get all tidal current stations with their constituent JSON
+    pub fn get_tidal_current_stations_with_constituents(&self) -> SqliteResult<Vec<(TidalCurrentStationRecord, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, lat, lon, flood_direction, ebb_direction, mean_speed_kn, source, created_at, constituents
+             FROM tidal_current_stations ORDER BY name"
+        )?;
+        let stations = stmt.query_map([], |row| {
+            Ok((
+                TidalCurrentStationRecord {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    lat: row.get(2)?,
+                    lon: row.get(3)?,
+                    flood_direction: row.get(4)?,
+                    ebb_direction: row.get(5)?,
+                    mean_speed_kn: row.get(6)?,
+                    source: row.get(7)?,
+                    created_at: row.get(8)?,
+                },
+                row.get(9)?,
+            ))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(stations)
+    }
+
+    pub fn delete_tidal_current_station(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM tidal_current_stations WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    /// Add a tidal stream grid. A grid with the same name and reference
+    /// station is replaced in place and keeps its id.
+    pub fn upsert_tidal_stream_grid(&self, grid: &TidalStreamGridRecord, grid_data: &str) -> SqliteResult<i64> {
+        upsert_tidal_stream_grid(&self.conn.lock().unwrap(), grid, grid_data)
+    }
+
+    pub fn get_tidal_stream_grids(&self) -> SqliteResult<Vec<TidalStreamGridRecord>> {
+        Ok(self.get_tidal_stream_grids_with_data()?.into_iter().map(|(g, _)| g).collect())
+    }
+
+    /// Get all tidal stream grids with their point JSON
+    pub fn get_tidal_stream_grids_with_data(&self) -> SqliteResult<Vec<(TidalStreamGridRecord, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, reference_station, point_count, created_at, grid_data
+             FROM tidal_stream_grids ORDER BY name"
+        )?;
+        let grids = stmt.query_map([], |row| {
+            Ok((
+                TidalStreamGridRecord {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    reference_station: row.get(2)?,
+                    point_count: row.get(3)?,
+                    created_at: row.get(4)?,
+                },
+                row.get(5)?,
+            ))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(grids)
+    }
+
+    pub fn delete_tidal_stream_grid(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM tidal_stream_grids WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    // ============ Search Methods ============
+
+    /// Objects whose bounding box overlaps the given box
+    pub fn find_in_bbox(&self, kind: SearchKind, min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> SqliteResult<Vec<SearchCandidate>> {
+        let conn = self.conn.lock().unwrap();
+        let index = match kind {
+            SearchKind::Waypoint => "waypoints_rtree",
+            SearchKind::Route => "routes_rtree",
+            SearchKind::Track => "tracks_rtree",
+        };
+        let ids: Vec<i64> = conn.prepare(&format!(
+            "SELECT id FROM {} WHERE max_lat >= ? AND min_lat <= ? AND max_lon >= ? AND min_lon <= ?",
+            index
+        ))?
+        .query_map(params![min_lat, max_lat, min_lon, max_lon], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+        let mut candidates = Vec::with_capacity(ids.len());
+        for id in ids {
+            candidates.extend(load_search_candidate(&conn, kind, id)?);
+        }
+        Ok(candidates)
+    }
+
+    /// Full-text match on names and descriptions of the given kinds, best
+    /// matches first. `query` uses FTS5 syntax.
+    pub fn find_text(&self, query: &str, kinds: &[SearchKind], limit: usize) -> SqliteResult<Vec<SearchCandidate>> {
+        let conn = self.conn.lock().unwrap();
+        // The kind is the low bits of the rowid, so it is filtered before the limit applies
+        let codes: Vec<String> = kinds.iter().map(|kind| kind.code().to_string()).collect();
+        let rowids: Vec<i64> = conn.prepare(&format!(
+            "SELECT rowid FROM search_text WHERE search_text MATCH ? AND rowid % 4 IN ({}) ORDER BY rank LIMIT ?",
+            codes.join(", ")
+        ))?
+        .query_map(params![query, limit as i64], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+
+        let mut candidates = Vec::with_capacity(rowids.len());
+        for rowid in rowids {
+            if let Some(kind) = SearchKind::from_code(rowid % 4) {
+                candidates.extend(load_search_candidate(&conn, kind, rowid / 4)?);
+            }
+        }
+        Ok(candidates)
+    }
+
+    // ============ Sync Methods ============
+
+    pub fn get_sync_settings(&self) -> SqliteResult<SyncSettings> {
+        let mut settings = SyncSettings::default();
+
+        if let Some(v) = self.get_setting("sync_enabled")? {
+            settings.enabled = v == "true";
+        }
+        if let Some(v) = self.get_setting("sync_device_name")? {
+            settings.device_name = v;
+        }
+        if let Some(v) = self.get_setting("sync_bind_address")? {
+            settings.bind_address = v;
+        }
+        if let Some(port) = self.get_setting("sync_port")?.and_then(|v| v.parse().ok()) {
+            settings.port = port;
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save_sync_settings(&self, settings: &SyncSettings) -> SqliteResult<()> {
+        self.set_setting("sync_enabled", if settings.enabled { "true" } else { "false" })?;
+        self.set_setting("sync_device_name", &settings.device_name)?;
+        self.set_setting("sync_bind_address", &settings.bind_address)?;
+        self.set_setting("sync_port", &settings.port.to_string())?;
+        Ok(())
+    }
+
+    /// This device's sync identity, created the first time it is asked for. It
+    /// lives in its own table so backups and exports never carry it to another device.
+    pub fn get_sync_device_id(&self) -> SqliteResult<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO device_identity (id, device_id) VALUES (1, ?)",
+            [uuid::Uuid::new_v4().to_string()],
+        )?;
+        conn.query_row("SELECT device_id FROM device_identity WHERE id = 1", [], |row| row.get(0))
+    }
+
+    pub fn save_sync_peer(&self, peer: &SyncPeer) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO sync_peers (device_id, name, address, token, last_sent_seq, last_received_seq)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(device_id) DO UPDATE SET
+                name = excluded.name, address = COALESCE(excluded.address, address), token = excluded.token,
+                last_sent_seq = excluded.last_sent_seq, last_received_seq = excluded.last_received_seq",
+            params![peer.device_id, peer.name, peer.address, peer.token, peer.last_sent_seq, peer.last_received_seq],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_sync_peers(&self) -> SqliteResult<Vec<SyncPeer>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT device_id, name, address, token, last_sent_seq, last_received_seq, last_synced_at, paired_at
+             FROM sync_peers ORDER BY name"
+        )?;
+        let peers = stmt.query_map([], |row| {
+            Ok(SyncPeer {
+                device_id: row.get(0)?,
+                name: row.get(1)?,
+                address: row.get(2)?,
+                token: row.get(3)?,
+                last_sent_seq: row.get(4)?,
+                last_received_seq: row.get(5)?,
+                last_synced_at: row.get(6)?,
+                paired_at: row.get(7)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(peers)
+    }
+
+    pub fn get_sync_peer(&self, device_id: &str) -> SqliteResult<Option<SyncPeer>> {
+        Ok(self.get_sync_peers()?.into_iter().find(|p| p.device_id == device_id))
+    }
+
+    pub fn delete_sync_peer(&self, device_id: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM sync_peers WHERE device_id = ?", params![device_id])?;
+        Ok(())
+    }
+
+    /// Record a completed sync with a peer
+    pub fn update_sync_peer_progress(&self, device_id: &str, last_sent_seq: i64, last_received_seq: i64) -> SqliteResult<()> {
+        update_sync_peer_progress(&self.conn.lock().unwrap(), device_id, last_sent_seq, last_received_seq)
+    }
+
+    /// Latest position in the change log
+    pub fn latest_sync_seq(&self) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM sync_changes", [], |row| row.get(0))
+    }
+
+    /// Objects changed after `since`, leaving out changes that came from `peer`
+    pub fn get_sync_changes(&self, since: i64, peer: &str) -> SqliteResult<Vec<SyncLogEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT seq, table_name, uuid FROM sync_changes
+             WHERE seq > ? AND (origin IS NULL OR origin != ?) ORDER BY seq"
+        )?;
+        let entries = stmt.query_map(params![since, peer], |row| {
+            Ok(SyncLogEntry {
+                seq: row.get(0)?,
+                table_name: row.get(1)?,
+                uuid: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Stored version of an object, whether live or deleted
+    pub fn get_sync_version(&self, table: &str, uuid: &str) -> SqliteResult<Option<SyncVersion>> {
+        get_sync_version(&self.conn.lock().unwrap(), table, uuid)
+    }
+
+    /// After storing a change from a peer, keep its timestamp and remember where
+    /// it came from so it isn't sent straight back
+    pub fn mark_sync_applied(&self, table: &str, uuid: &str, updated_at: &str, origin: &str) -> SqliteResult<()> {
+        mark_sync_applied(&self.conn.lock().unwrap(), table, uuid, updated_at, origin)
+    }
+}
+
+pub fn get_setting_entries(conn: &Connection) -> SqliteResult<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let entries = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+pub fn clear_user_data(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "DELETE FROM route_tag_assignments;
+         DELETE FROM route_waypoints;
+         DELETE FROM routes;
+         DELETE FROM route_tags;
+         DELETE FROM waypoints;
+         DELETE FROM waypoint_groups;
+         DELETE FROM track_points;
+         DELETE FROM tracks;
+         DELETE FROM gps_sources;
+         DELETE FROM chart_layers;
+         DELETE FROM catalog_charts;
+         DELETE FROM chart_catalogs;"
+    )?;
+    let keep = vec!["?"; DEVICE_LOCAL_SETTINGS.len()].join(", ");
+    conn.execute(
+        &format!("DELETE FROM settings WHERE key NOT IN ({})", keep),
+        rusqlite::params_from_iter(DEVICE_LOCAL_SETTINGS),
+    )?;
+    Ok(())
+}
+
+pub fn create_waypoint(conn: &Connection, waypoint: &Waypoint) -> SqliteResult<i64> {
+    conn.query_row(
+        "INSERT INTO waypoints (name, lat, lon, description, symbol, show_label, hidden, uuid, updated_at, group_id, arrival_radius_nm, depth_m, temperature_c)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(uuid) DO UPDATE SET
+            name = excluded.name, lat = excluded.lat, lon = excluded.lon, description = excluded.description,
+            symbol = excluded.symbol, show_label = excluded.show_label, hidden = excluded.hidden,
+            updated_at = excluded.updated_at, deleted_at = NULL, group_id = COALESCE(excluded.group_id, group_id),
+            arrival_radius_nm = excluded.arrival_radius_nm, depth_m = excluded.depth_m, temperature_c = excluded.temperature_c
+         RETURNING id",
+        params![waypoint.name, waypoint.lat, waypoint.lon, waypoint.description, waypoint.symbol, if waypoint.show_label { 1 } else { 0 }, if waypoint.hidden { 1 } else { 0 }, new_uuid(&waypoint.uuid), timestamp_now(), waypoint.group_id, waypoint.arrival_radius_nm, waypoint.depth_m, waypoint.temperature_c],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_waypoints(conn: &Connection) -> SqliteResult<Vec<Waypoint>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, lat, lon, description, symbol, show_label, hidden, created_at, uuid, updated_at, group_id, arrival_radius_nm, depth_m, temperature_c
+         FROM waypoints WHERE deleted_at IS NULL ORDER BY name"
+    )?;
+    let waypoints = stmt.query_map([], |row| {
+        Ok(Waypoint {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            lat: row.get(2)?,
+            lon: row.get(3)?,
+            description: row.get(4)?,
+            symbol: row.get(5)?,
+            show_label: row.get::<_, i32>(6)? == 1,
+            hidden: row.get::<_, i32>(7)? == 1,
+            created_at: row.get(8)?,
+            uuid: row.get(9)?,
+            updated_at: row.get(10)?,
+            group_id: row.get(11)?,
+            arrival_radius_nm: row.get(12)?,
+            depth_m: row.get(13)?,
+            temperature_c: row.get(14)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(waypoints)
+}
+
+pub fn update_waypoint(conn: &Connection, waypoint: &Waypoint) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE waypoints SET name = ?, lat = ?, lon = ?, description = ?, symbol = ?, show_label = ?, hidden = ?, arrival_radius_nm = ?, depth_m = ?, temperature_c = ?, updated_at = ? WHERE id = ?",
+        params![waypoint.name, waypoint.lat, waypoint.lon, waypoint.description, waypoint.symbol, if waypoint.show_label { 1 } else { 0 }, if waypoint.hidden { 1 } else { 0 }, waypoint.arrival_radius_nm, waypoint.depth_m, waypoint.temperature_c, timestamp_now(), waypoint.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_waypoint(conn: &Connection, id: i64) -> SqliteResult<()> {
+    tombstone_waypoint(conn, id, &timestamp_now())
+}
+
+pub fn find_waypoint_id(conn: &Connection, uuid: &str) -> SqliteResult<Option<i64>> {
+    find_live_id(conn, "waypoints", uuid)
+}
+
+pub fn move_waypoints_to_group(conn: &Connection, ids: &[i64], group_id: Option<i64>) -> SqliteResult<()> {
+    for id in ids {
+        conn.execute("UPDATE waypoints SET group_id = ? WHERE id = ?", params![group_id, id])?;
+    }
+    Ok(())
+}
+
+pub fn create_waypoint_group(conn: &Connection, group: &WaypointGroup) -> SqliteResult<i64> {
+    conn.execute(
+        "INSERT INTO waypoint_groups (name, parent_id, hidden) VALUES (?, ?, ?)",
+        params![group.name, group.parent_id, if group.hidden { 1 } else { 0 }],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_waypoint_groups(conn: &Connection) -> SqliteResult<Vec<WaypointGroup>> {
+    let mut stmt = conn.prepare("SELECT id, name, parent_id, hidden, created_at FROM waypoint_groups ORDER BY name")?;
+    let groups = stmt.query_map([], |row| {
+        Ok(WaypointGroup {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            parent_id: row.get(2)?,
+            hidden: row.get::<_, i32>(3)? == 1,
+            created_at: row.get(4)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(groups)
+}
+
+pub fn toggle_waypoint_group_hidden(conn: &Connection, id: i64, hidden: bool) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE waypoint_groups SET hidden = ? WHERE id = ?",
+        params![if hidden { 1 } else { 0 }, id],
+    )?;
+    Ok(())
+}
+
+pub fn get_exclusive_group_waypoints(conn: &Connection, group_id: i64) -> SqliteResult<Vec<i64>> {
+    let groups = group_subtree(conn, group_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT w.id FROM waypoints w
+         WHERE w.group_id = ? AND w.deleted_at IS NULL
+         AND NOT EXISTS (SELECT 1 FROM route_waypoints rw WHERE rw.waypoint_id = w.id)"
+    )?;
+    let mut ids = Vec::new();
+    for group in groups {
+        for id in stmt.query_map(params![group], |row| row.get(0))? {
+            ids.push(id?);
+        }
+    }
+    Ok(ids)
+}
+
+pub fn delete_waypoint_group(conn: &Connection, id: i64, delete_waypoints: bool) -> SqliteResult<Vec<i64>> {
+    let waypoints_to_delete = if delete_waypoints {
+        get_exclusive_group_waypoints(conn, id)?
+    } else {
+        vec![]
+    };
+
+    let now = timestamp_now();
+    for wp_id in &waypoints_to_delete {
+        tombstone_waypoint(conn, *wp_id, &now)?;
+    }
+
+    let parent_id: Option<i64> = conn.query_row(
+        "SELECT parent_id FROM waypoint_groups WHERE id = ?",
+        params![id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    // Children before their parents, for the foreign keys
+    for group in group_subtree(conn, id)?.into_iter().rev() {
+        conn.execute("UPDATE waypoints SET group_id = ? WHERE group_id = ?", params![parent_id, group])?;
+        conn.execute("DELETE FROM waypoint_groups WHERE id = ?", params![group])?;
+    }
+
+    Ok(waypoints_to_delete)
+}
+
+pub fn create_route_tag(conn: &Connection, tag: &RouteTag) -> SqliteResult<i64> {
+    let now = timestamp_now();
+    let revived: Option<i64> = conn.query_row(
+        "UPDATE route_tags SET color = ?, updated_at = ?, deleted_at = NULL
+         WHERE name = ? AND deleted_at IS NOT NULL RETURNING id",
+        params![tag.color, now, tag.name],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(id) = revived {
+        return Ok(id);
+    }
+    conn.query_row(
+        "INSERT INTO route_tags (name, color, uuid, updated_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT(uuid) DO UPDATE SET
+            name = excluded.name, color = excluded.color, updated_at = excluded.updated_at, deleted_at = NULL
+         RETURNING id",
+        params![tag.name, tag.color, new_uuid(&tag.uuid), now],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_route_tags(conn: &Connection) -> SqliteResult<Vec<RouteTag>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, color, created_at, uuid, updated_at FROM route_tags WHERE deleted_at IS NULL ORDER BY name"
+    )?;
+    let tags = stmt.query_map([], |row| {
+        Ok(RouteTag {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            color: row.get(2)?,
+            created_at: row.get(3)?,
+            uuid: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(tags)
+}
+
+pub fn update_route_tag(conn: &Connection, tag: &RouteTag) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE route_tags SET name = ?, color = ?, updated_at = ? WHERE id = ?",
+        params![tag.name, tag.color, timestamp_now(), tag.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_route_tag(conn: &Connection, id: i64) -> SqliteResult<()> {
+    let now = timestamp_now();
+    // Routes that carried the tag change too
+    conn.execute(
+        "UPDATE routes SET updated_at = ? WHERE id IN (SELECT route_id FROM route_tag_assignments WHERE tag_id = ?)",
+        params![now, id],
+    )?;
+    conn.execute("DELETE FROM route_tag_assignments WHERE tag_id = ?", params![id])?;
+    conn.execute("UPDATE route_tags SET deleted_at = ?, updated_at = ? WHERE id = ?", params![now, now, id])?;
+    Ok(())
+}
+
+pub fn set_route_tag_uuid(conn: &Connection, id: i64, uuid: &str) -> SqliteResult<()> {
+    conn.execute("UPDATE route_tags SET uuid = ? WHERE id = ?", params![uuid, id])?;
+    Ok(())
+}
+
+pub fn find_route_tag_id(conn: &Connection, uuid: &str) -> SqliteResult<Option<i64>> {
+    find_live_id(conn, "route_tags", uuid)
+}
+
+pub fn create_route(conn: &Connection, route: &Route, waypoint_ids: &[i64], tag_ids: &[i64]) -> SqliteResult<i64> {
+    // Insert the route (or update the one that already has its UUID)
+    let route_id: i64 = conn.query_row(
+        "INSERT INTO routes (name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, uuid, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(uuid) DO UPDATE SET
+            name = excluded.name, description = excluded.description, color = excluded.color,
+            is_active = excluded.is_active, hidden = excluded.hidden, total_distance_nm = excluded.total_distance_nm,
+            estimated_speed_kn = excluded.estimated_speed_kn, updated_at = excluded.updated_at, deleted_at = NULL
+         RETURNING id",
+        params![
+            route.name,
+            route.description,
+            route.color,
+            if route.is_active { 1 } else { 0 },
+            if route.hidden { 1 } else { 0 },
+            route.total_distance_nm,
+            route.estimated_speed_kn,
+            new_uuid(&route.uuid),
+            timestamp_now()
+        ],
+        |row| row.get(0),
+    )?;
+    conn.execute("DELETE FROM route_waypoints WHERE route_id = ?", params![route_id])?;
+    conn.execute("DELETE FROM route_tag_assignments WHERE route_id = ?", params![route_id])?;
+
+    // Insert waypoint associations with sequence
+    for (seq, wp_id) in waypoint_ids.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO route_waypoints (route_id, waypoint_id, sequence) VALUES (?, ?, ?)",
+            params![route_id, wp_id, seq as i32],
+        )?;
+    }
+
+    // Insert tag associations
+    for tag_id in tag_ids {
+        conn.execute(
+            "INSERT INTO route_tag_assignments (route_id, tag_id) VALUES (?, ?)",
+            params![route_id, tag_id],
+        )?;
+    }
+
+    Ok(route_id)
+}
+
+pub fn get_routes(conn: &Connection) -> SqliteResult<Vec<RouteWithWaypoints>> {
+    // Get all routes
+    let mut routes_stmt = conn.prepare(
+        "SELECT id, name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, created_at, updated_at, uuid
+         FROM routes WHERE deleted_at IS NULL ORDER BY name"
+    )?;
+
+    let routes: Vec<Route> = routes_stmt.query_map([], |row| {
+        Ok(Route {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            description: row.get(2)?,
+            color: row.get(3)?,
+            is_active: row.get::<_, i32>(4)? == 1,
+            hidden: row.get::<_, i32>(5).unwrap_or(0) == 1,
+            total_distance_nm: row.get(6)?,
+            estimated_speed_kn: row.get::<_, f64>(7).unwrap_or(5.0),
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            uuid: row.get(10)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    // For each route, get waypoints and tags
+    let mut result = Vec::with_capacity(routes.len());
+
+    for route in routes {
+        let route_id = route.id.unwrap();
+
+        // Get waypoints for this route (ordered by sequence)
+        let mut wp_stmt = conn.prepare(
+            "SELECT w.id, w.name, w.lat, w.lon, w.description, w.symbol, w.show_label, w.hidden, w.created_at, w.uuid, w.updated_at, w.group_id, w.arrival_radius_nm, w.depth_m, w.temperature_c
+             FROM waypoints w
+             JOIN route_waypoints rw ON w.id = rw.waypoint_id
+             WHERE rw.route_id = ? AND w.deleted_at IS NULL
+             ORDER BY rw.sequence"
+        )?;
+
+        let waypoints: Vec<Waypoint> = wp_stmt.query_map(params![route_id], |row| {
+            Ok(Waypoint {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                lat: row.get(2)?,
+                lon: row.get(3)?,
+                description: row.get(4)?,
+                symbol: row.get(5)?,
+                show_label: row.get::<_, i32>(6)? == 1,
+                hidden: row.get::<_, i32>(7)? == 1,
+                created_at: row.get(8)?,
+                uuid: row.get(9)?,
+                updated_at: row.get(10)?,
+                group_id: row.get(11)?,
+                arrival_radius_nm: row.get(12)?,
+                depth_m: row.get(13)?,
+                temperature_c: row.get(14)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        // Get tags for this route
+        let mut tag_stmt = conn.prepare(
+            "SELECT t.id, t.name, t.color, t.created_at, t.uuid, t.updated_at
+             FROM route_tags t
+             JOIN route_tag_assignments rta ON t.id = rta.tag_id
+             WHERE rta.route_id = ? AND t.deleted_at IS NULL"
+        )?;
+
+        let tags: Vec<RouteTag> = tag_stmt.query_map(params![route_id], |row| {
+            Ok(RouteTag {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                color: row.get(2)?,
+                created_at: row.get(3)?,
+                uuid: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        result.push(RouteWithWaypoints { route, waypoints, tags });
+    }
+
+    Ok(result)
+}
+
+pub fn get_route(conn: &Connection, id: i64) -> SqliteResult<Option<RouteWithWaypoints>> {
+    // Get the route
+    let mut route_stmt = conn.prepare(
+        "SELECT id, name, description, color, is_active, hidden, total_distance_nm, estimated_speed_kn, created_at, updated_at, uuid
+         FROM routes WHERE id = ? AND deleted_at IS NULL"
+    )?;
+
+    let route: Option<Route> = route_stmt.query_row(params![id], |row| {
+        Ok(Route {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            description: row.get(2)?,
+            color: row.get(3)?,
+            is_active: row.get::<_, i32>(4)? == 1,
+            hidden: row.get::<_, i32>(5).unwrap_or(0) == 1,
+            total_distance_nm: row.get(6)?,
+            estimated_speed_kn: row.get::<_, f64>(7).unwrap_or(5.0),
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            uuid: row.get(10)?,
+        })
+    }).optional()?;
+
+    match route {
+        Some(route) => {
+            // Get waypoints
+            let mut wp_stmt = conn.prepare(
+                "SELECT w.id, w.name, w.lat, w.lon, w.description, w.symbol, w.show_label, w.hidden, w.created_at, w.uuid, w.updated_at, w.group_id, w.arrival_radius_nm, w.depth_m, w.temperature_c
+                 FROM waypoints w
+                 JOIN route_waypoints rw ON w.id = rw.waypoint_id
+                 WHERE rw.route_id = ? AND w.deleted_at IS NULL
+                 ORDER BY rw.sequence"
+            )?;
+
+            let waypoints: Vec<Waypoint> = wp_stmt.query_map(params![id], |row| {
+                Ok(Waypoint {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    lat: row.get(2)?,
+                    lon: row.get(3)?,
+                    description: row.get(4)?,
+                    symbol: row.get(5)?,
+                    show_label: row.get::<_, i32>(6)? == 1,
+                    hidden: row.get::<_, i32>(7)? == 1,
+                    created_at: row.get(8)?,
+                    uuid: row.get(9)?,
+                    updated_at: row.get(10)?,
+                    group_id: row.get(11)?,
+                    arrival_radius_nm: row.get(12)?,
+                    depth_m: row.get(13)?,
+                    temperature_c: row.get(14)?,
+                })
+            })?.collect::<Result<Vec<_>, _>>()?;
+
+            // Get tags
+            let mut tag_stmt = conn.prepare(
+                "SELECT t.id, t.name, t.color, t.created_at, t.uuid, t.updated_at
+                 FROM route_tags t
+                 JOIN route_tag_assignments rta ON t.id = rta.tag_id
+                 WHERE rta.route_id = ? AND t.deleted_at IS NULL"
+            )?;
+
+            let tags: Vec<RouteTag> = tag_stmt.query_map(params![id], |row| {
+                Ok(RouteTag {
+                    id: Some(row.get(0)?),
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                    created_at: row.get(3)?,
+                    uuid: row.get(4)?,
+                    updated_at: row.get(5)?,
+                })
+            })?.collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Some(RouteWithWaypoints { route, waypoints, tags }))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn update_route(conn: &Connection, route: &Route, waypoint_ids: &[i64], tag_ids: &[i64]) -> SqliteResult<()> {
+    let route_id = route.id.ok_or(rusqlite::Error::InvalidParameterName("Route must have an id".to_string()))?;
+
+    // Update route metadata
+    conn.execute(
+        "UPDATE routes SET name = ?, description = ?, color = ?, is_active = ?, hidden = ?,
+         total_distance_nm = ?, estimated_speed_kn = ?, updated_at = ?
+         WHERE id = ?",
+        params![
+            route.name,
+            route.description,
+            route.color,
+            if route.is_active { 1 } else { 0 },
+            if route.hidden { 1 } else { 0 },
+            route.total_distance_nm,
+            route.estimated_speed_kn,
+            timestamp_now(),
+            route_id
+        ],
+    )?;
+
+    // Replace waypoint associations
+    conn.execute("DELETE FROM route_waypoints WHERE route_id = ?", params![route_id])?;
+    for (seq, wp_id) in waypoint_ids.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO route_waypoints (route_id, waypoint_id, sequence) VALUES (?, ?, ?)",
+            params![route_id, wp_id, seq as i32],
+        )?;
+    }
+
+    // Replace tag associations
+    conn.execute("DELETE FROM route_tag_assignments WHERE route_id = ?", params![route_id])?;
+    for tag_id in tag_ids {
+        conn.execute(
+            "INSERT INTO route_tag_assignments (route_id, tag_id) VALUES (?, ?)",
+            params![route_id, tag_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn delete_route(conn: &Connection, id: i64) -> SqliteResult<()> {
+    tombstone_route(conn, id, &timestamp_now())
+}
+
+pub fn find_route_id(conn: &Connection, uuid: &str) -> SqliteResult<Option<i64>> {
+    find_live_id(conn, "routes", uuid)
+}
+
+pub fn import_track(conn: &Connection, track: &Track, points: &[TrackPoint]) -> SqliteResult<i64> {
+    // A deleted track with this UUID is replaced
+    let uuid = new_uuid(&track.uuid);
+    conn.execute("DELETE FROM tracks WHERE uuid = ? AND deleted_at IS NOT NULL", params![uuid])?;
+    conn.execute(
+        "INSERT INTO tracks (name, description, color, is_recording, started_at, ended_at,
+                             total_distance_nm, point_count, hidden, created_at, uuid, updated_at)
+         VALUES (?, ?, ?, 0, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP), ?, ?)",
+        params![
+            track.name,
+            track.description,
+            track.color,
+            track.started_at,
+            track.ended_at,
+            track.total_distance_nm,
+            points.len() as i64,
+            if track.hidden { 1 } else { 0 },
+            track.created_at,
+            uuid,
+            timestamp_now()
+        ],
+    )?;
+    let track_id = conn.last_insert_rowid();
+
+    for (sequence, point) in points.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO track_points (track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment, depth_m, temperature_c)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![track_id, point.lat, point.lon, point.timestamp, sequence as i64, point.heading, point.cog, point.sog, point.segment, point.depth_m, point.temperature_c],
+        )?;
+    }
+    Ok(track_id)
+}
+
+pub fn get_tracks(conn: &Connection) -> SqliteResult<Vec<Track>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, color, is_recording, started_at, ended_at,
+                total_distance_nm, point_count, hidden, created_at, uuid, updated_at
+         FROM tracks WHERE deleted_at IS NULL ORDER BY created_at DESC"
+    )?;
+    let tracks = stmt.query_map([], |row| {
+        Ok(Track {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            description: row.get(2)?,
+            color: row.get(3)?,
+            is_recording: row.get::<_, i32>(4)? == 1,
+            started_at: row.get(5)?,
+            ended_at: row.get(6)?,
+            total_distance_nm: row.get(7)?,
+            point_count: row.get::<_, i64>(8).unwrap_or(0),
+            hidden: row.get::<_, i32>(9).unwrap_or(0) == 1,
+            created_at: row.get(10)?,
+            uuid: row.get(11)?,
+            updated_at: row.get(12)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(tracks)
+}
+
+pub fn get_tracks_with_points(conn: &Connection) -> SqliteResult<Vec<TrackWithPoints>> {
+    let tracks = get_tracks(conn)?;
+    let mut result = Vec::with_capacity(tracks.len());
+    for track in tracks {
+        let track_id = track.id.unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment, depth_m, temperature_c
+             FROM track_points WHERE track_id = ? ORDER BY sequence"
+        )?;
+        let points: Vec<TrackPoint> = stmt.query_map(params![track_id], |row| {
+            Ok(TrackPoint {
+                id: Some(row.get(0)?),
+                track_id: row.get(1)?,
+                lat: row.get(2)?,
+                lon: row.get(3)?,
+                timestamp: row.get(4)?,
+                sequence: row.get(5)?,
+                heading: row.get(6)?,
+                cog: row.get(7)?,
+                sog: row.get(8)?,
+                segment: row.get(9)?,
+                depth_m: row.get(10)?,
+                temperature_c: row.get(11)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        result.push(TrackWithPoints { track, points });
+    }
+
+    Ok(result)
+}
+
+pub fn update_track(conn: &Connection, track: &Track) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE tracks SET name = ?, description = ?, color = ?, hidden = ?, updated_at = ? WHERE id = ?",
+        params![track.name, track.description, track.color, if track.hidden { 1 } else { 0 }, timestamp_now(), track.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_track(conn: &Connection, id: i64) -> SqliteResult<()> {
+    // Only the tombstone is kept, not the points
+    let now = timestamp_now();
+    conn.execute("DELETE FROM track_points WHERE track_id = ?", params![id])?;
+    conn.execute(
+        "UPDATE tracks SET deleted_at = ?, updated_at = ?, point_count = 0, is_recording = 0 WHERE id = ?",
+        params![now, now, id],
+    )?;
+    Ok(())
+}
+
+pub fn find_track_id(conn: &Connection, uuid: &str) -> SqliteResult<Option<i64>> {
+    find_live_id(conn, "tracks", uuid)
+}
+
+pub fn save_gps_source(conn: &Connection, source: &GpsSourceRecord) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO gps_sources (id, name, source_type, port_name, baud_rate, enabled, priority, raw_nmea)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            source.id,
+            source.name,
+            source.source_type,
+            source.port_name,
+            source.baud_rate,
+            if source.enabled { 1 } else { 0 },
+            source.priority,
+            if source.raw_nmea { 1 } else { 0 }
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_gps_sources(conn: &Connection) -> SqliteResult<Vec<GpsSourceRecord>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, source_type, port_name, baud_rate, enabled, priority, raw_nmea
+         FROM gps_sources ORDER BY priority ASC, name ASC"
+    )?;
+    let sources = stmt.query_map([], |row| {
+        Ok(GpsSourceRecord {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            source_type: row.get(2)?,
+            port_name: row.get(3)?,
+            baud_rate: row.get(4)?,
+            enabled: row.get::<_, i32>(5)? == 1,
+            priority: row.get(6)?,
+            raw_nmea: row.get::<_, i32>(7)? == 1,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(sources)
+}
+
+pub fn save_chart_layer_state(conn: &Connection, state: &ChartLayerState) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO chart_layers (chart_id, enabled, opacity, z_order)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT(chart_id) DO UPDATE SET
+            enabled = excluded.enabled, opacity = excluded.opacity, z_order = excluded.z_order",
+        params![
+            state.chart_id,
+            if state.enabled { 1 } else { 0 },
+            state.opacity,
+            state.z_order
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_chart_layer_states(conn: &Connection) -> SqliteResult<Vec<ChartLayerState>> {
+    let mut stmt = conn.prepare(
+        "SELECT chart_id, enabled, opacity, z_order FROM chart_layers ORDER BY z_order ASC"
+    )?;
+    let states = stmt.query_map([], |row| {
+        Ok(ChartLayerState {
+            chart_id: row.get(0)?,
+            enabled: row.get::<_, i32>(1)? == 1,
+            opacity: row.get(2)?,
+            z_order: row.get(3)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(states)
+}
+
+pub fn save_chart_custom_metadata(conn: &Connection, metadata: &ChartCustomMetadata) -> SqliteResult<()> {
+    // First ensure the row exists (create with defaults if not)
+    conn.execute(
+        "INSERT OR IGNORE INTO chart_layers (chart_id, enabled, opacity, z_order)
+         VALUES (?, 1, 1.0, 0)",
+        params![metadata.chart_id],
+    )?;
+    // Then update the custom fields
+    conn.execute(
+        "UPDATE chart_layers SET custom_name = ?, custom_description = ?, custom_min_zoom = ?, custom_max_zoom = ?
+         WHERE chart_id = ?",
+        params![
+            metadata.custom_name,
+            metadata.custom_description,
+            metadata.custom_min_zoom,
+            metadata.custom_max_zoom,
+            metadata.chart_id,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_all_chart_custom_metadata(conn: &Connection) -> SqliteResult<Vec<ChartCustomMetadata>> {
+    let mut stmt = conn.prepare(
+        "SELECT chart_id, custom_name, custom_description, custom_min_zoom, custom_max_zoom
+         FROM chart_layers
+         WHERE custom_name IS NOT NULL OR custom_description IS NOT NULL
+            OR custom_min_zoom IS NOT NULL OR custom_max_zoom IS NOT NULL"
+    )?;
+    let metadata = stmt.query_map([], |row| {
+        Ok(ChartCustomMetadata {
+            chart_id: row.get(0)?,
+            custom_name: row.get(1)?,
+            custom_description: row.get(2)?,
+            custom_min_zoom: row.get(3)?,
+            custom_max_zoom: row.get(4)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(metadata)
+}
+
+pub fn create_catalog(conn: &Connection, catalog: &ChartCatalog) -> SqliteResult<i64> {
+    conn.execute(
+        "INSERT INTO chart_catalogs (name, catalog_type, source_type, source_path)
+         VALUES (?, ?, ?, ?)",
+        params![catalog.name, catalog.catalog_type, catalog.source_type, catalog.source_path],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_catalogs(conn: &Connection) -> SqliteResult<Vec<ChartCatalog>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, c.catalog_type, c.source_type, c.source_path,
+                c.imported_at, c.last_refreshed,
+                (SELECT COUNT(*) FROM catalog_charts WHERE catalog_id = c.id) as chart_count
+         FROM chart_catalogs c ORDER BY c.name"
+    )?;
+    let catalogs = stmt.query_map([], |row| {
+        Ok(ChartCatalog {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            catalog_type: row.get(2)?,
+            source_type: row.get(3)?,
+            source_path: row.get(4)?,
+            imported_at: row.get(5)?,
+            last_refreshed: row.get(6)?,
+            chart_count: row.get(7)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(catalogs)
+}
+
+pub fn create_catalog_chart(conn: &Connection, chart: &CatalogChart) -> SqliteResult<i64> {
+    conn.execute(
+        "INSERT INTO catalog_charts (catalog_id, chart_id, title, chart_type, format, scale,
+                                     status, download_url, file_size, last_updated, bounds)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            chart.catalog_id,
+            chart.chart_id,
+            chart.title,
+            chart.chart_type,
+            chart.format,
+            chart.scale,
+            chart.status,
+            chart.download_url,
+            chart.file_size,
+            chart.last_updated,
+            chart.bounds
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_catalog_charts(conn: &Connection, catalog_id: i64) -> SqliteResult<Vec<CatalogChart>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, catalog_id, chart_id, title, chart_type, format, scale, status,
+                download_url, file_size, last_updated, bounds, download_status,
+                download_progress, download_path, mbtiles_path, error_message
+         FROM catalog_charts WHERE catalog_id = ? ORDER BY title"
+    )?;
+    let charts = stmt.query_map([catalog_id], |row| {
+        Ok(CatalogChart {
+            id: Some(row.get(0)?),
+            catalog_id: row.get(1)?,
+            chart_id: row.get(2)?,
+            title: row.get(3)?,
+            chart_type: row.get(4)?,
+            format: row.get(5)?,
+            scale: row.get(6)?,
+            status: row.get(7)?,
+            download_url: row.get(8)?,
+            file_size: row.get(9)?,
+            last_updated: row.get(10)?,
+            bounds: row.get(11)?,
+            download_status: row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "available".to_string()),
+            download_progress: row.get::<_, Option<i64>>(13)?.unwrap_or(0),
+            download_path: row.get(14)?,
+            mbtiles_path: row.get(15)?,
+            error_message: row.get(16)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(charts)
+}
+
+pub fn upsert_tide_station(conn: &Connection, station: &TideStationRecord, constituents: &str) -> SqliteResult<i64> {
+    let existing: Option<i64> = conn.query_row(
+        "SELECT id FROM tide_stations WHERE name = ? AND lat = ? AND lon = ? ORDER BY id LIMIT 1",
+        params![station.name, station.lat, station.lon],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE tide_stations SET mean_level_m = ?, source = ?, constituents = ? WHERE id = ?",
+            params![station.mean_level_m, station.source, constituents, id],
+        )?;
+        return Ok(id);
+    }
+    conn.execute(
+        "INSERT INTO tide_stations (name, lat, lon, mean_level_m, source, constituents)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        params![station.name, station.lat, station.lon, station.mean_level_m, station.source, constituents],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn upsert_tidal_current_station(conn: &Connection, station: &TidalCurrentStationRecord, constituents: &str) -> SqliteResult<i64> {
+    let existing: Option<i64> = conn.query_row(
+        "SELECT id FROM tidal_current_stations WHERE name = ? AND lat = ? AND lon = ? ORDER BY id LIMIT 1",
+        params![station.name, station.lat, station.lon],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE tidal_current_stations
+             SET flood_direction = ?, ebb_direction = ?, mean_speed_kn = ?, source = ?, constituents = ?
+             WHERE id = ?",
+            params![
+                station.flood_direction,
+                station.ebb_direction,
+                station.mean_speed_kn,
+                station.source,
+                constituents,
+                id,
+            ],
+        )?;
+        return Ok(id);
+    }
+    conn.execute(
+        "INSERT INTO tidal_current_stations
+         (name, lat, lon, flood_direction, ebb_direction, mean_speed_kn, source, constituents)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            station.name,
+            station.lat,
+            station.lon,
+            station.flood_direction,
+            station.ebb_direction,
+            station.mean_speed_kn,
+            station.source,
+            constituents,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn upsert_tidal_stream_grid(conn: &Connection, grid: &TidalStreamGridRecord, grid_data: &str) -> SqliteResult<i64> {
+    let existing: Option<i64> = conn.query_row(
+        "SELECT id FROM tidal_stream_grids WHERE name = ? AND reference_station = ? ORDER BY id LIMIT 1",
+        params![grid.name, grid.reference_station],
+        |row| row.get(0),
+    ).optional()?;
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE tidal_stream_grids SET point_count = ?, grid_data = ? WHERE id = ?",
+            params![grid.point_count, grid_data, id],
+        )?;
+        return Ok(id);
+    }
+    conn.execute(
+        "INSERT INTO tidal_stream_grids (name, reference_station, point_count, grid_data) VALUES (?, ?, ?, ?)",
+        params![grid.name, grid.reference_station, grid.point_count, grid_data],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_sync_peer_progress(conn: &Connection, device_id: &str, last_sent_seq: i64, last_received_seq: i64) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE sync_peers SET last_sent_seq = ?, last_received_seq = ?, last_synced_at = ? WHERE device_id = ?",
+        params![last_sent_seq, last_received_seq, timestamp_now(), device_id],
+    )?;
+    Ok(())
+}
+
+pub fn get_sync_version(conn: &Connection, table: &str, uuid: &str) -> SqliteResult<Option<SyncVersion>> {
+    check_tracked_table(table)?;
+    conn.query_row(
+        &format!(
+            "SELECT t.name, t.updated_at, t.deleted_at IS NOT NULL, c.seq, c.origin FROM {t} t
+             LEFT JOIN sync_changes c ON c.table_name = '{t}' AND c.uuid = t.uuid
+             WHERE t.uuid = ?",
+            t = table
+        ),
+        params![uuid],
+        |row| {
+            Ok(SyncVersion {
+                name: row.get(0)?,
+                updated_at: row.get(1)?,
+                deleted: row.get(2)?,
+                seq: row.get(3)?,
+                origin: row.get(4)?,
+            })
+        },
+    ).optional()
+}
+
+pub fn mark_sync_applied(conn: &Connection, table: &str, uuid: &str, updated_at: &str, origin: &str) -> SqliteResult<()> {
+    check_tracked_table(table)?;
+    conn.execute(&format!("UPDATE {} SET updated_at = ? WHERE uuid = ?", table), params![updated_at, uuid])?;
+    conn.execute(
+        "UPDATE sync_changes SET origin = ? WHERE table_name = ? AND uuid = ?",
+        params![origin, table, uuid],
+    )?;
+    Ok(())
 }
 
 /// Calculate haversine distance between two points in nautical miles
@@ -2234,6 +3370,12 @@ mod tests {
             show_label: true,
             hidden: false,
             created_at: None,
+            uuid: None,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: None,
+            depth_m: None,
+            temperature_c: None,
         };
         let id = db.create_waypoint(&wp).unwrap();
         let waypoints = db.get_waypoints().unwrap();
@@ -2243,4 +3385,178 @@ mod tests {
         // Cleanup
         std::fs::remove_dir_all(temp).ok();
     }
+
+    #[test]
+    fn test_delete_leaves_tombstone() {
+        let temp = temp_dir().join(format!("vortexnav_tombstone_{}", std::process::id()));
+        std::fs::remove_dir_all(&temp).ok();
+        let db = ConfigDatabase::new(&temp).unwrap();
+
+        let wp = Waypoint::at("Anchorage", -36.8, 174.8);
+        let id = db.create_waypoint(&wp).unwrap();
+        let route_id = db.create_route(&Route { name: "Out".to_string(), ..Default::default() }, &[id], &[]).unwrap();
+        let stored = db.get_waypoint(id).unwrap().unwrap();
+        let uuid = stored.uuid.clone().unwrap();
+        assert!(stored.updated_at.unwrap().ends_with('Z'));
+
+        // Deleted rows disappear from every getter but keep their uuid
+        db.delete_waypoint(id).unwrap();
+        assert!(db.get_waypoints().unwrap().is_empty());
+        assert!(db.get_route(route_id).unwrap().unwrap().waypoints.is_empty());
+        assert_eq!(db.find_waypoint_id(&uuid).unwrap(), None);
+
+        // Recreating the same uuid revives the row rather than adding another
+        let revived = db.create_waypoint(&Waypoint { uuid: Some(uuid.clone()), ..wp }).unwrap();
+        assert_eq!(revived, id);
+        assert_eq!(db.find_waypoint_id(&uuid).unwrap(), Some(id));
+
+        std::fs::remove_dir_all(temp).ok();
+    }
+
+    #[test]
+    fn test_depth_and_temperature_stored() {
+        let temp = temp_dir().join(format!("vortexnav_soundings_{}", std::process::id()));
+        std::fs::remove_dir_all(&temp).ok();
+        let db = ConfigDatabase::new(&temp).unwrap();
+
+        let wp = Waypoint { depth_m: Some(4.2), temperature_c: Some(17.5), ..Waypoint::at("Reef", -36.8, 174.8) };
+        let id = db.create_waypoint(&wp).unwrap();
+        let stored = db.get_waypoint(id).unwrap().unwrap();
+        assert_eq!((stored.depth_m, stored.temperature_c), (Some(4.2), Some(17.5)));
+
+        let point = TrackPoint {
+            id: None,
+            track_id: 0,
+            lat: -36.8,
+            lon: 174.8,
+            timestamp: None,
+            sequence: 0,
+            heading: None,
+            cog: None,
+            sog: None,
+            segment: 0,
+            depth_m: Some(11.0),
+            temperature_c: Some(18.5),
+        };
+        let track_id = db.import_track(&Track { name: "Sounding run".to_string(), ..Track::default() }, &[point]).unwrap();
+        let points = db.get_track_points(track_id).unwrap();
+        assert_eq!((points[0].depth_m, points[0].temperature_c), (Some(11.0), Some(18.5)));
+
+        std::fs::remove_dir_all(temp).ok();
+    }
+
+    #[test]
+    fn test_delete_group_keeps_route_waypoints() {
+        let temp = temp_dir().join(format!("vortexnav_groups_{}", std::process::id()));
+        std::fs::remove_dir_all(&temp).ok();
+        let db = ConfigDatabase::new(&temp).unwrap();
+
+        let group = |name: &str, parent_id| WaypointGroup { id: None, name: name.to_string(), parent_id, hidden: false, created_at: None };
+        let guide = db.create_waypoint_group(&group("Cruising guide", None)).unwrap();
+        let bays = db.create_waypoint_group(&group("Bays", Some(guide))).unwrap();
+        let waypoint = |name: &str| Waypoint { group_id: Some(bays), ..Waypoint::at(name, -36.6, 174.9) };
+        let on_route = db.create_waypoint(&waypoint("Home Bay")).unwrap();
+        let spare = db.create_waypoint(&waypoint("Oneroa")).unwrap();
+        let loose = db.create_waypoint(&Waypoint { group_id: None, ..waypoint("Fuel dock") }).unwrap();
+        db.create_route(&Route { name: "Weekend".to_string(), ..Default::default() }, &[loose, on_route], &[]).unwrap();
+
+        // A group cannot move under its own subgroup
+        let moved = db.update_waypoint_group(&WaypointGroup { id: Some(guide), parent_id: Some(bays), ..group("Cruising guide", None) });
+        assert!(matches!(moved, Err(DatabaseError::GroupCycle)));
+
+        db.set_waypoints_symbol(&[on_route, spare], Some("anchor")).unwrap();
+        assert!(db.get_waypoints().unwrap().iter().filter(|w| w.group_id == Some(bays)).all(|w| w.symbol.as_deref() == Some("anchor")));
+
+        assert_eq!(db.get_exclusive_group_waypoints(guide).unwrap(), vec![spare]);
+        assert_eq!(db.delete_waypoint_group(guide, true).unwrap(), vec![spare]);
+        assert!(db.get_waypoint_groups().unwrap().is_empty());
+        let names: Vec<_> = db.get_waypoints().unwrap().into_iter().map(|w| (w.name, w.group_id)).collect();
+        assert_eq!(names, [("Fuel dock".to_string(), None), ("Home Bay".to_string(), None)]);
+
+        std::fs::remove_dir_all(temp).ok();
+    }
+
+    #[test]
+    fn test_tide_station_import_updates_in_place() {
+        let temp = temp_dir().join(format!("vortexnav_tide_stations_{}", std::process::id()));
+        std::fs::remove_dir_all(&temp).ok();
+        let db = ConfigDatabase::new(&temp).unwrap();
+
+        let station = |name: &str, lat| TideStationRecord {
+            id: None,
+            name: name.to_string(),
+            lat,
+            lon: 174.7684,
+            mean_level_m: 1.92,
+            source: Some("LINZ".to_string()),
+            created_at: None,
+        };
+        let auckland = db.upsert_tide_station(&station("Auckland", -36.8439), "[]").unwrap();
+        db.upsert_tide_station(&station("Auckland", -36.9), "[]").unwrap();
+
+        // The same station again replaces its constants and keeps its id
+        let again = db.upsert_tide_station(&TideStationRecord { mean_level_m: 1.95, ..station("Auckland", -36.8439) }, "[1]").unwrap();
+        assert_eq!(again, auckland);
+        let stations = db.get_tide_stations_with_constituents().unwrap();
+        assert_eq!(stations.len(), 2);
+        let (stored, constituents) = stations.iter().find(|(s, _)| s.id == Some(auckland)).unwrap();
+        assert_eq!((stored.mean_level_m, constituents.as_str()), (1.95, "[1]"));
+
+        // A failed import leaves nothing behind
+        let failed: Result<(), DatabaseError> = db.transaction(|tx| {
+            upsert_tide_station(tx, &station("Onehunga", -36.93), "[]")?;
+            Err(DatabaseError::Sqlite(rusqlite::Error::InvalidQuery))
+        });
+        assert!(failed.is_err());
+        assert_eq!(db.get_tide_stations().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(temp).ok();
+    }
+
+    #[test]
+    fn test_tidal_stream_import_updates_in_place() {
+        let temp = temp_dir().join(format!("vortexnav_tidal_streams_{}", std::process::id()));
+        std::fs::remove_dir_all(&temp).ok();
+        let db = ConfigDatabase::new(&temp).unwrap();
+
+        let station = TidalCurrentStationRecord {
+            id: None,
+            name: "Rangitoto Channel".to_string(),
+            lat: -36.80,
+            lon: 174.83,
+            flood_direction: 250.0,
+            ebb_direction: 70.0,
+            mean_speed_kn: 0.0,
+            source: None,
+            created_at: None,
+        };
+        let id = db.upsert_tidal_current_station(&station, "[]").unwrap();
+        let again = db.upsert_tidal_current_station(&TidalCurrentStationRecord { flood_direction: 245.0, ..station.clone() }, "[1]").unwrap();
+        assert_eq!(again, id);
+        let stations = db.get_tidal_current_stations_with_constituents().unwrap();
+        assert_eq!(stations.len(), 1);
+        assert_eq!((stations[0].0.flood_direction, stations[0].1.as_str()), (245.0, "[1]"));
+
+        let grid = TidalStreamGridRecord {
+            id: None,
+            name: "Hauraki Gulf".to_string(),
+            reference_station: "Auckland".to_string(),
+            point_count: 1,
+            created_at: None,
+        };
+        let grid_id = db.upsert_tidal_stream_grid(&grid, "{}").unwrap();
+        assert_eq!(db.upsert_tidal_stream_grid(&TidalStreamGridRecord { point_count: 2, ..grid.clone() }, "{}").unwrap(), grid_id);
+        let grids = db.get_tidal_stream_grids().unwrap();
+        assert_eq!((grids.len(), grids[0].point_count), (1, 2));
+
+        // An update made by a failed import is rolled back with it
+        let failed: Result<(), DatabaseError> = db.transaction(|tx| {
+            upsert_tidal_stream_grid(tx, &TidalStreamGridRecord { point_count: 3, ..grid.clone() }, "{}")?;
+            Err(DatabaseError::Sqlite(rusqlite::Error::InvalidQuery))
+        });
+        assert!(failed.is_err());
+        assert_eq!(db.get_tidal_stream_grids().unwrap()[0].point_count, 2);
+
+        std::fs::remove_dir_all(temp).ok();
+    }
 }
@@ -0,0 +1,120 @@
+// Horizontal datums for positions given on older charts and in printed guides
+// Shifts to WGS84 use the standard Molodensky transformation with published mean parameters
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Datum {
+    /// Also NZGD2000, GDA94, NAD83 and ETRS89, which agree with it at chart scale
+    #[default]
+    Wgs84,
+    /// New Zealand Geodetic Datum 1949
+    Nzgd49,
+    /// Australian Geodetic Datum 1966
+    Agd66,
+    /// European Datum 1950
+    Ed50,
+    /// North American Datum 1927 (contiguous US)
+    Nad27,
+    /// Ordnance Survey of Great Britain 1936
+    Osgb36,
+    /// Tokyo datum, on older Japanese and Korean charts
+    Tokyo,
+}
+
+struct Ellipsoid {
+    a: f64,
+    inverse_flattening: f64,
+}
+
+const WGS84: Ellipsoid = Ellipsoid { a: 6_378_137.0, inverse_flattening: 298.257_223_563 };
+const INTERNATIONAL_1924: Ellipsoid = Ellipsoid { a: 6_378_388.0, inverse_flattening: 297.0 };
+const AUSTRALIAN_NATIONAL: Ellipsoid = Ellipsoid { a: 6_378_160.0, inverse_flattening: 298.25 };
+const CLARKE_1866: Ellipsoid = Ellipsoid { a: 6_378_206.4, inverse_flattening: 294.978_698_2 };
+const AIRY_1830: Ellipsoid = Ellipsoid { a: 6_377_563.396, inverse_flattening: 299.324_964_6 };
+const BESSEL_1841: Ellipsoid = Ellipsoid { a: 6_377_397.155, inverse_flattening: 299.152_812_8 };
+
+impl Datum {
+    /// Source ellipsoid and the shift of its centre to WGS84, in metres
+    fn parameters(self) -> Option<(&'static Ellipsoid, [f64; 3])> {
+        match self {
+            Datum::Wgs84 => None,
+            Datum::Nzgd49 => Some((&INTERNATIONAL_1924, [84.0, -22.0, 209.0])),
+            Datum::Agd66 => Some((&AUSTRALIAN_NATIONAL, [-133.0, -48.0, 148.0])),
+            Datum::Ed50 => Some((&INTERNATIONAL_1924, [-87.0, -98.0, -121.0])),
+            Datum::Nad27 => Some((&CLARKE_1866, [-8.0, 160.0, 176.0])),
+            Datum::Osgb36 => Some((&AIRY_1830, [375.0, -111.0, 431.0])),
+            Datum::Tokyo => Some((&BESSEL_1841, [-148.0, 507.0, 685.0])),
+        }
+    }
+
+    /// Convert a position on this datum to WGS84, good to a few metres
+    pub fn to_wgs84(self, lat: f64, lon: f64) -> (f64, f64) {
+        let Some((ellipsoid, [dx, dy, dz])) = self.parameters() else { return (lat, lon) };
+
+        let a = ellipsoid.a;
+        let f = 1.0 / ellipsoid.inverse_flattening;
+        let b = a * (1.0 - f);
+        let e2 = 2.0 * f - f * f;
+        let da = WGS84.a - a;
+        let df = 1.0 / WGS84.inverse_flattening - f;
+
+        let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+        let w = 1.0 - e2 * sin_lat * sin_lat;
+        // Radii of curvature in the prime vertical and the meridian
+        let rn = a / w.sqrt();
+        let rm = a * (1.0 - e2) / w.powf(1.5);
+
+        let d_lat = (-dx * sin_lat * cos_lon - dy * sin_lat * sin_lon
+            + dz * cos_lat
+            + da * rn * e2 * sin_lat * cos_lat / a
+            + df * (rm * a / b + rn * b / a) * sin_lat * cos_lat)
+            / rm;
+        // Longitude has no meaning at a pole, so it is left as given
+        let d_lon = if cos_lat.abs() < 1e-12 { 0.0 } else { (-dx * sin_lon + dy * cos_lon) / (rn * cos_lat) };
+
+        ((lat + d_lat.to_degrees()).clamp(-90.0, 90.0), wrap_longitude(lon + d_lon.to_degrees()))
+    }
+}
+
+fn wrap_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::haversine_distance;
+
+    #[test]
+    fn test_shift_to_wgs84() {
+        assert_eq!(Datum::Wgs84.to_wgs84(-36.84, 174.76), (-36.84, 174.76));
+
+        // The Greenwich meridian on OSGB36 lies about 100 m west of WGS84's
+        let (lat, lon) = Datum::Osgb36.to_wgs84(51.4778, 0.0);
+        assert!((lat - 51.4778).abs() < 0.0005, "{}", lat);
+        assert!(lon < -0.0012 && lon > -0.0018, "{}", lon);
+
+        // NZGD49 positions around Auckland move roughly 200 m
+        let (lat, lon) = Datum::Nzgd49.to_wgs84(-36.84, 174.76);
+        let shift_m = haversine_distance(-36.84, 174.76, lat, lon) * 1852.0;
+        assert!(shift_m > 150.0 && shift_m < 250.0, "{}", shift_m);
+    }
+
+    #[test]
+    fn test_shift_at_poles_and_antimeridian() {
+        let (lat, lon) = Datum::Ed50.to_wgs84(90.0, 0.0);
+        assert!(lat.is_finite() && lat <= 90.0, "{}", lat);
+        assert_eq!(lon, 0.0);
+        let (lat, lon) = Datum::Tokyo.to_wgs84(-90.0, 45.0);
+        assert!(lat.is_finite() && lat >= -90.0, "{}", lat);
+        assert_eq!(lon, 45.0);
+
+        // NZGD49 moves Chatham Islands positions east, across 180°
+        let (_, lon) = Datum::Nzgd49.to_wgs84(-44.0, 179.9999);
+        assert!((-180.0..=180.0).contains(&lon), "{}", lon);
+        assert!(lon < -179.99, "{}", lon);
+    }
+}
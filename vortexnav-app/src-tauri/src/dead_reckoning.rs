@@ -0,0 +1,206 @@
+// Dead Reckoning
+// Estimated position from the last fix when the GPS is lost or its fix goes stale
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+use crate::navigation::{destination_point, haversine_distance};
+
+/// A fix older than this is stale and the position is estimated
+pub const STALE_FIX: Duration = Duration::from_secs(5);
+/// Heading and log speed older than this are not trusted for DR
+const SENSOR_TIMEOUT: Duration = Duration::from_secs(30);
+/// Uncertainty growth as a fraction of distance run
+const DISTANCE_ERROR_FRACTION: f64 = 0.1;
+/// Uncertainty growth from unknown set and drift (knots)
+const UNKNOWN_DRIFT_KN: f64 = 0.5;
+
+/// What the estimate is being advanced with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrSource {
+    /// Compass heading and speed through the water
+    HeadingLog,
+    /// Last course and speed over ground
+    CogSog,
+}
+
+impl DrSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DrSource::HeadingLog => "DR",
+            DrSource::CogSog => "EP",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrEstimate {
+    pub lat: f64,
+    pub lon: f64,
+    pub uncertainty_nm: f64,
+    pub source: DrSource,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    value: f64,
+    at: Instant,
+}
+
+impl Reading {
+    fn fresh(reading: Option<Reading>, now: Instant) -> Option<f64> {
+        reading.filter(|r| now.duration_since(r.at) < SENSOR_TIMEOUT).map(|r| r.value)
+    }
+}
+
+/// Position and vector at the start of the current DR segment
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    lat: f64,
+    lon: f64,
+    at: Instant,
+    course: f64,
+    speed_kn: f64,
+    source: DrSource,
+    /// Distance run and uncertainty accumulated before this segment
+    run_nm: f64,
+}
+
+/// Tracks the last fix and advances an estimate from it while there is no fix
+#[derive(Debug, Default)]
+pub struct DeadReckoning {
+    last_fix: Option<(f64, f64, Instant)>,
+    last_fix_time: Option<DateTime<Utc>>,
+    cog: Option<f64>,
+    sog: Option<f64>,
+    heading: Option<Reading>,
+    log_speed: Option<Reading>,
+    segment: Option<Segment>,
+}
+
+impl DeadReckoning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a GPS fix. If the position was being estimated, returns the DR error (nm).
+    pub fn update_fix(&mut self, lat: f64, lon: f64, cog: Option<f64>, sog: Option<f64>, now: Instant) -> Option<f64> {
+        let error = self.estimate(now).map(|e| haversine_distance(e.lat, e.lon, lat, lon));
+        self.last_fix = Some((lat, lon, now));
+        self.last_fix_time = Some(Utc::now());
+        self.segment = None;
+        if cog.is_some() {
+            self.cog = cog;
+        }
+        if sog.is_some() {
+            self.sog = sog;
+        }
+        error
+    }
+
+    pub fn update_heading(&mut self, heading: f64, now: Instant) {
+        self.heading = Some(Reading { value: heading, at: now });
+        self.restart_segment(now);
+    }
+
+    pub fn update_log_speed(&mut self, speed_kn: f64, now: Instant) {
+        self.log_speed = Some(Reading { value: speed_kn, at: now });
+        self.restart_segment(now);
+    }
+
+    pub fn last_fix_time(&self) -> Option<DateTime<Utc>> {
+        self.last_fix_time
+    }
+
+    pub fn is_stale(&self, now: Instant) -> bool {
+        self.last_fix.is_some_and(|(_, _, at)| now.duration_since(at) >= STALE_FIX)
+    }
+
+    /// Estimated position, or None while the fix is fresh
+    pub fn estimate(&self, now: Instant) -> Option<DrEstimate> {
+        if !self.is_stale(now) {
+            return None;
+        }
+        let segment = self.segment.or_else(|| self.initial_segment(now))?;
+        let hours = now.saturating_duration_since(segment.at).as_secs_f64() / 3600.0;
+        let distance = segment.speed_kn * hours;
+        let (lat, lon) = destination_point(segment.lat, segment.lon, segment.course, distance);
+
+        let (_, _, fix_at) = self.last_fix?;
+        let hours_since_fix = now.duration_since(fix_at).as_secs_f64() / 3600.0;
+        Some(DrEstimate {
+            lat,
+            lon,
+            uncertainty_nm: DISTANCE_ERROR_FRACTION * (segment.run_nm + distance) + UNKNOWN_DRIFT_KN * hours_since_fix,
+            source: segment.source,
+        })
+    }
+
+    /// Vector to steer the estimate with: heading and log when both are live,
+    /// otherwise the last course and speed over ground
+    fn vector(&self, now: Instant) -> (f64, f64, DrSource) {
+        match (Reading::fresh(self.heading, now), Reading::fresh(self.log_speed, now)) {
+            (Some(heading), Some(speed)) => (heading, speed, DrSource::HeadingLog),
+            _ => (self.cog.unwrap_or(0.0), self.sog.unwrap_or(0.0), DrSource::CogSog),
+        }
+    }
+
+    /// DR starts from the last fix when it went stale
+    fn initial_segment(&self, now: Instant) -> Option<Segment> {
+        let (lat, lon, at) = self.last_fix?;
+        let (course, speed_kn, source) = self.vector(now);
+        Some(Segment { lat, lon, at, course, speed_kn, source, run_nm: 0.0 })
+    }
+
+    /// Close the current leg of the estimate when the heading or speed changes
+    fn restart_segment(&mut self, now: Instant) {
+        if !self.is_stale(now) {
+            return;
+        }
+        let Some(previous) = self.segment.or_else(|| self.initial_segment(now)) else {
+            return;
+        };
+        let hours = now.saturating_duration_since(previous.at).as_secs_f64() / 3600.0;
+        let distance = previous.speed_kn * hours;
+        let (lat, lon) = destination_point(previous.lat, previous.lon, previous.course, distance);
+        let (course, speed_kn, source) = self.vector(now);
+        self.segment = Some(Segment { lat, lon, at: now, course, speed_kn, source, run_nm: previous.run_nm + distance });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_from_cog_sog_then_heading_log() {
+        let start = Instant::now();
+        let mut dr = DeadReckoning::new();
+        assert!(dr.update_fix(-36.8, 174.8, Some(0.0), Some(6.0), start).is_none());
+        assert!(dr.estimate(start + Duration::from_secs(2)).is_none());
+
+        // Half an hour on the last COG/SOG: 3 nm north
+        let t1 = start + Duration::from_secs(1800);
+        let estimate = dr.estimate(t1).unwrap();
+        assert_eq!(estimate.source, DrSource::CogSog);
+        assert!((haversine_distance(-36.8, 174.8, estimate.lat, estimate.lon) - 3.0).abs() < 0.01);
+        assert!((estimate.uncertainty_nm - 0.55).abs() < 0.01);
+
+        // Compass and log take over: half an hour east at 4 knots
+        dr.update_heading(90.0, t1);
+        dr.update_log_speed(4.0, t1);
+        let t2 = t1 + Duration::from_secs(1800);
+        let estimate = dr.estimate(t2).unwrap();
+        assert_eq!(estimate.source, DrSource::HeadingLog);
+        let (lat, lon) = destination_point(-36.8, 174.8, 0.0, 3.0);
+        let (lat, lon) = destination_point(lat, lon, 90.0, 2.0);
+        assert!(haversine_distance(lat, lon, estimate.lat, estimate.lon) < 0.01);
+        assert!((estimate.uncertainty_nm - 1.0).abs() < 0.01);
+
+        // The fix returns 0.2 nm from the estimate
+        let (fix_lat, fix_lon) = destination_point(estimate.lat, estimate.lon, 180.0, 0.2);
+        let error = dr.update_fix(fix_lat, fix_lon, None, None, t2).unwrap();
+        assert!((error - 0.2).abs() < 0.01);
+        assert!(dr.estimate(t2).is_none());
+    }
+}
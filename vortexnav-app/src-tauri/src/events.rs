@@ -0,0 +1,110 @@
+// Event channel
+// Throttled per-topic events carrying sequence numbers so listeners can detect drops
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Sends an event name and payload to the frontend (Tauri's emit in the app)
+pub type EventEmitter = Arc<dyn Fn(&str, Value) + Send + Sync>;
+
+/// Payload of every topic event
+#[derive(Debug, Clone, Serialize)]
+pub struct TopicEvent {
+    pub topic: String,
+    /// Increases by one per event on this topic; a gap means events were missed
+    pub sequence: u64,
+    pub timestamp: String,
+    pub data: Value,
+}
+
+#[derive(Default)]
+struct TopicState {
+    sequence: u64,
+    last_sent: Option<Instant>,
+    last_data: Option<Value>,
+}
+
+/// Emits a topic only when its data changes, and no more often than its interval.
+/// Callers publish the current value on every tick, so a change made inside the
+/// interval goes out once the interval has passed.
+pub struct EventChannel {
+    emitter: EventEmitter,
+    topics: HashMap<&'static str, TopicState>,
+}
+
+impl EventChannel {
+    pub fn new(emitter: EventEmitter) -> Self {
+        Self { emitter, topics: HashMap::new() }
+    }
+
+    /// Publish `data` on `topic` if it changed and `min_interval` has passed.
+    /// Returns whether an event was emitted.
+    pub fn publish(&mut self, topic: &'static str, data: impl Serialize, min_interval: Duration, now: Instant) -> bool {
+        let Ok(data) = serde_json::to_value(data) else {
+            return false;
+        };
+        let state = self.topics.entry(topic).or_default();
+        if state.last_data.as_ref() == Some(&data) {
+            return false;
+        }
+        if state.last_sent.is_some_and(|sent| now.saturating_duration_since(sent) < min_interval) {
+            return false;
+        }
+
+        state.sequence += 1;
+        state.last_sent = Some(now);
+        state.last_data = Some(data.clone());
+        let event = TopicEvent {
+            topic: topic.to_string(),
+            sequence: state.sequence,
+            timestamp: Utc::now().to_rfc3339(),
+            data,
+        };
+        if let Ok(payload) = serde_json::to_value(event) {
+            (self.emitter)(topic, payload);
+        }
+        true
+    }
+
+    /// Last sequence number sent on a topic
+    pub fn sequence(&self, topic: &str) -> u64 {
+        self.topics.get(topic).map(|t| t.sequence).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_throttle_and_sequence() {
+        let sent: Arc<Mutex<Vec<(String, Value)>>> = Arc::default();
+        let recorder = Arc::clone(&sent);
+        let mut channel = EventChannel::new(Arc::new(move |topic, payload| {
+            recorder.lock().unwrap().push((topic.to_string(), payload));
+        }));
+        let interval = Duration::from_millis(200);
+        let start = Instant::now();
+
+        assert!(channel.publish("gps:position", 1, interval, start));
+        // Unchanged data is never re-sent
+        assert!(!channel.publish("gps:position", 1, interval, start + Duration::from_secs(1)));
+        // A change inside the interval waits for it to pass
+        assert!(!channel.publish("gps:position", 2, interval, start + Duration::from_millis(100)));
+        assert!(channel.publish("gps:position", 2, interval, start + Duration::from_millis(250)));
+        // Topics are throttled and numbered independently
+        assert!(channel.publish("gps:status", "connected", interval, start + Duration::from_millis(260)));
+
+        let sent = sent.lock().unwrap();
+        let sequences: Vec<(&str, u64)> =
+            sent.iter().map(|(topic, event)| (topic.as_str(), event["sequence"].as_u64().unwrap())).collect();
+        assert_eq!(sequences, [("gps:position", 1), ("gps:position", 2), ("gps:status", 1)]);
+        assert_eq!(sent[1].1["data"], 2);
+        assert_eq!(channel.sequence("gps:position"), 2);
+    }
+}
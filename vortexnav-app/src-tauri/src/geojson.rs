@@ -0,0 +1,295 @@
+// GeoJSON parser and generator for route import/export
+// Uses simplestyle property names so colours and symbols show up in other tools
+
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::gpx::{GpxRoute, GpxRoutePoint, GpxTrack, GpxWaypoint, ParsedGpx};
+
+/// Parse a GeoJSON file from disk
+pub fn parse_geojson_file(path: &Path) -> Result<ParsedGpx, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read GeoJSON file: {}", e))?;
+    parse_geojson_string(&content)
+}
+
+/// Parse a FeatureCollection or single Feature. Points become waypoints,
+/// LineStrings routes (tracks when typed so or timestamped) and MultiLineStrings tracks.
+pub fn parse_geojson_string(content: &str) -> Result<ParsedGpx, String> {
+    let root: Value = serde_json::from_str(content).map_err(|e| format!("Failed to parse GeoJSON: {}", e))?;
+    let features = match root["type"].as_str() {
+        Some("FeatureCollection") => root["features"].as_array().cloned().unwrap_or_default(),
+        Some("Feature") => vec![root],
+        _ => return Err("GeoJSON must be a FeatureCollection or Feature".to_string()),
+    };
+
+    let mut parsed = ParsedGpx { waypoints: vec![], routes: vec![], tracks: vec![] };
+    for feature in &features {
+        add_feature(&mut parsed, &feature["geometry"], feature["properties"].as_object().unwrap_or(&Map::new()));
+    }
+    Ok(parsed)
+}
+
+fn add_feature(parsed: &mut ParsedGpx, geometry: &Value, properties: &Map<String, Value>) {
+    let text = |keys: &[&str]| keys.iter().find_map(|key| properties.get(*key).and_then(Value::as_str)).map(str::to_string);
+    let name = text(&["name", "title"]);
+    let desc = text(&["description", "desc"]);
+    let uuid = text(&["uuid"]);
+    let color = text(&["stroke", "color"]);
+    let times = properties.get("coordTimes");
+    let coordinates = &geometry["coordinates"];
+
+    match geometry["type"].as_str() {
+        Some("Point") => {
+            if let Some(point) = to_point(coordinates) {
+                parsed.waypoints.push(GpxWaypoint {
+                    name,
+                    lat: point.lat,
+                    lon: point.lon,
+                    ele: point.ele,
+                    time: text(&["time"]),
+                    desc,
+                    sym: text(&["symbol", "marker-symbol", "sym"]),
+                    uuid,
+                    extensions: Default::default(),
+                });
+            }
+        }
+        Some("MultiPoint") => {
+            for point in to_line(coordinates, None) {
+                parsed.waypoints.push(GpxWaypoint {
+                    name: name.clone(),
+                    lat: point.lat,
+                    lon: point.lon,
+                    ele: point.ele,
+                    time: None,
+                    desc: desc.clone(),
+                    sym: None,
+                    uuid: None,
+                    extensions: Default::default(),
+                });
+            }
+        }
+        Some("LineString") if text(&["type"]).as_deref() != Some("track") && times.is_none() => {
+            let mut points = to_line(coordinates, None);
+            // Route point details ride along in a list matching the coordinates
+            if let Some(details) = properties.get("waypoints").and_then(Value::as_array) {
+                for (point, detail) in points.iter_mut().zip(details) {
+                    let field = |key: &str| detail[key].as_str().map(str::to_string);
+                    point.name = field("name");
+                    point.desc = field("description");
+                    point.sym = field("symbol");
+                    point.uuid = field("uuid");
+                }
+            }
+            parsed.routes.push(GpxRoute { name, desc, uuid, color, points });
+        }
+        Some("LineString") => {
+            let segment = to_line(coordinates, times);
+            parsed.tracks.push(GpxTrack { name, desc, uuid, color, segments: vec![segment] });
+        }
+        Some("MultiLineString") => {
+            let lines = coordinates.as_array().cloned().unwrap_or_default();
+            let segments = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| to_line(line, times.and_then(|t| t.get(i))))
+                .collect();
+            parsed.tracks.push(GpxTrack { name, desc, uuid, color, segments });
+        }
+        Some("GeometryCollection") => {
+            for geometry in geometry["geometries"].as_array().into_iter().flatten() {
+                add_feature(parsed, geometry, properties);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `[lon, lat, ele?]`
+fn to_point(position: &Value) -> Option<GpxRoutePoint> {
+    let lon = position.get(0)?.as_f64()?;
+    let lat = position.get(1)?.as_f64()?;
+    let ele = position.get(2).and_then(Value::as_f64);
+    Some(GpxRoutePoint { name: None, lat, lon, ele, time: None, desc: None, sym: None, uuid: None, extensions: Default::default() })
+}
+
+fn to_line(positions: &Value, times: Option<&Value>) -> Vec<GpxRoutePoint> {
+    positions
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(i, position)| {
+            let time = times.and_then(|t| t.get(i)).and_then(Value::as_str).map(str::to_string);
+            to_point(position).map(|point| GpxRoutePoint { time, ..point })
+        })
+        .collect()
+}
+
+fn position(lat: f64, lon: f64, ele: Option<f64>) -> Value {
+    match ele {
+        Some(ele) => json!([lon, lat, ele]),
+        None => json!([lon, lat]),
+    }
+}
+
+/// Insert only the properties that have a value
+fn properties(entries: &[(&str, Option<Value>)]) -> Value {
+    let map: Map<String, Value> = entries
+        .iter()
+        .filter_map(|(key, value)| value.clone().map(|v| (key.to_string(), v)))
+        .collect();
+    Value::Object(map)
+}
+
+fn feature(geometry: Value, properties: Value) -> Value {
+    json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+/// Generate a GeoJSON FeatureCollection
+pub fn generate_geojson(data: &ParsedGpx) -> Result<String, String> {
+    let mut features = Vec::new();
+
+    for w in &data.waypoints {
+        features.push(feature(
+            json!({ "type": "Point", "coordinates": position(w.lat, w.lon, w.ele) }),
+            properties(&[
+                ("type", Some(json!("waypoint"))),
+                ("name", w.name.clone().map(Value::from)),
+                ("description", w.desc.clone().map(Value::from)),
+                ("marker-symbol", w.sym.clone().map(Value::from)),
+                ("uuid", w.uuid.clone().map(Value::from)),
+            ]),
+        ));
+    }
+
+    for route in &data.routes {
+        let details: Vec<Value> = route
+            .points
+            .iter()
+            .map(|p| {
+                properties(&[
+                    ("name", p.name.clone().map(Value::from)),
+                    ("description", p.desc.clone().map(Value::from)),
+                    ("symbol", p.sym.clone().map(Value::from)),
+                    ("uuid", p.uuid.clone().map(Value::from)),
+                ])
+            })
+            .collect();
+        let coordinates: Vec<Value> = route.points.iter().map(|p| position(p.lat, p.lon, p.ele)).collect();
+        features.push(feature(
+            json!({ "type": "LineString", "coordinates": coordinates }),
+            properties(&[
+                ("type", Some(json!("route"))),
+                ("name", route.name.clone().map(Value::from)),
+                ("description", route.desc.clone().map(Value::from)),
+                ("stroke", route.color.clone().map(Value::from)),
+                ("uuid", route.uuid.clone().map(Value::from)),
+                ("waypoints", Some(Value::from(details))),
+            ]),
+        ));
+    }
+
+    for track in &data.tracks {
+        let coordinates: Vec<Vec<Value>> = track
+            .segments
+            .iter()
+            .map(|segment| segment.iter().map(|p| position(p.lat, p.lon, p.ele)).collect())
+            .collect();
+        let times: Vec<Vec<Value>> = track
+            .segments
+            .iter()
+            .map(|segment| segment.iter().map(|p| p.time.clone().map(Value::from).unwrap_or(Value::Null)).collect())
+            .collect();
+        features.push(feature(
+            json!({ "type": "MultiLineString", "coordinates": coordinates }),
+            properties(&[
+                ("type", Some(json!("track"))),
+                ("name", track.name.clone().map(Value::from)),
+                ("description", track.desc.clone().map(Value::from)),
+                ("stroke", track.color.clone().map(Value::from)),
+                ("uuid", track.uuid.clone().map(Value::from)),
+                ("coordTimes", Some(json!(times))),
+            ]),
+        ));
+    }
+
+    serde_json::to_string_pretty(&json!({ "type": "FeatureCollection", "features": features }))
+        .map_err(|e| format!("Failed to generate GeoJSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let point = |name: &str, lat: f64, lon: f64, time: Option<&str>| GpxRoutePoint {
+            name: Some(name.to_string()),
+            lat,
+            lon,
+            ele: None,
+            time: time.map(str::to_string),
+            desc: None,
+            sym: Some("anchor".to_string()),
+            uuid: None,
+            extensions: Default::default(),
+        };
+        let data = ParsedGpx {
+            waypoints: vec![GpxWaypoint {
+                name: Some("Westhaven".to_string()),
+                lat: -36.84,
+                lon: 174.74,
+                ele: None,
+                time: None,
+                desc: Some("Marina".to_string()),
+                sym: Some("marina".to_string()),
+                uuid: Some("wp-uuid".to_string()),
+                extensions: Default::default(),
+            }],
+            routes: vec![GpxRoute {
+                name: Some("Kawau run".to_string()),
+                desc: None,
+                uuid: Some("route-uuid".to_string()),
+                color: Some("#c026d3".to_string()),
+                points: vec![point("Westhaven", -36.84, 174.74, None), point("Kawau", -36.42, 174.83, None)],
+            }],
+            tracks: vec![GpxTrack {
+                name: Some("Sunday sail".to_string()),
+                desc: None,
+                uuid: None,
+                color: Some("#3b82f6".to_string()),
+                segments: vec![
+                    vec![point("", -36.80, 174.80, Some("2026-03-01T01:00:00Z"))],
+                    vec![point("", -36.70, 174.85, Some("2026-03-01T02:00:00Z"))],
+                ],
+            }],
+        };
+
+        let parsed = parse_geojson_string(&generate_geojson(&data).unwrap()).unwrap();
+
+        let waypoint = &parsed.waypoints[0];
+        assert_eq!((waypoint.name.as_deref(), waypoint.desc.as_deref()), (Some("Westhaven"), Some("Marina")));
+        assert_eq!((waypoint.sym.as_deref(), waypoint.uuid.as_deref()), (Some("marina"), Some("wp-uuid")));
+
+        let route = &parsed.routes[0];
+        assert_eq!((route.uuid.as_deref(), route.color.as_deref()), (Some("route-uuid"), Some("#c026d3")));
+        assert_eq!(route.points[1].name.as_deref(), Some("Kawau"));
+        assert_eq!(route.points[1].sym.as_deref(), Some("anchor"));
+        assert_eq!((route.points[1].lat, route.points[1].lon), (-36.42, 174.83));
+
+        let track = &parsed.tracks[0];
+        assert_eq!(track.color.as_deref(), Some("#3b82f6"));
+        assert_eq!(track.segments.len(), 2);
+        assert_eq!(track.segments[1][0].time.as_deref(), Some("2026-03-01T02:00:00Z"));
+
+        // A plain feature from another tool, with a timestamped line as a track
+        let external = r##"{"type": "Feature", "properties": {"name": "Log", "coordTimes": ["2026-03-01T01:00:00Z", "2026-03-01T01:01:00Z"]},
+            "geometry": {"type": "LineString", "coordinates": [[174.8, -36.8, 2.0], [174.81, -36.79]]}}"##;
+        let parsed = parse_geojson_string(external).unwrap();
+        assert!(parsed.routes.is_empty());
+        assert_eq!(parsed.tracks[0].segments[0][0].ele, Some(2.0));
+        assert_eq!(parsed.tracks[0].segments[0][1].time.as_deref(), Some("2026-03-01T01:01:00Z"));
+    }
+}
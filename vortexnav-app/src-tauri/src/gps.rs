@@ -1,14 +1,24 @@
 // GPS source management module
 // Handles serial port enumeration, connection, and NMEA reading
 
-use crate::nmea::{GpsData, NmeaParser};
+use crate::database::PositionFilterSettings;
+use crate::dead_reckoning::DeadReckoning;
+use crate::events::{EventChannel, EventEmitter};
+use crate::gpsd::{GpsdConnection, GpsdMessage, GpsdReport, DEFAULT_GPSD_ADDRESS};
+use crate::instruments::{AisTarget, InstrumentData, OwnVesselUpdate, VesselData};
+use crate::navigation::{ActiveNavigation, RouteProgress};
+use crate::nmea::{checksum_valid, is_position_fix, parse_heading_log, GpsData, NmeaParser, SatelliteInfo, SentenceStats, SentenceTypeStats};
+use crate::nmea2000::{is_nmea2000_line, Nmea2000Decoder};
+use crate::position_filter::PositionFilter;
+use crate::signalk::{SignalKConnection, SignalKState};
 use serde::{Deserialize, Serialize};
 use serialport::SerialPortType;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpStream, ToSocketAddrs};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,6 +31,8 @@ pub enum GpsError {
     NoSourcesConfigured,
     #[error("GPS source not found: {0}")]
     SourceNotFound(String),
+    #[error("Signal K error: {0}")]
+    SignalK(String),
 }
 
 // Available GPS source types
@@ -30,6 +42,32 @@ pub enum GpsSourceType {
     SerialPort,
     TcpStream,
     Simulated,
+    Gpsd,
+    SignalK,
+}
+
+impl GpsSourceType {
+    /// Name stored in the gps_sources table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GpsSourceType::SerialPort => "serial_port",
+            GpsSourceType::TcpStream => "tcp_stream",
+            GpsSourceType::Simulated => "simulated",
+            GpsSourceType::Gpsd => "gpsd",
+            GpsSourceType::SignalK => "signal_k",
+        }
+    }
+
+    /// Parse a stored name, falling back to a serial port
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "tcp_stream" => GpsSourceType::TcpStream,
+            "simulated" => GpsSourceType::Simulated,
+            "gpsd" => GpsSourceType::Gpsd,
+            "signal_k" => GpsSourceType::SignalK,
+            _ => GpsSourceType::SerialPort,
+        }
+    }
 }
 
 // Information about a detected serial port
@@ -49,10 +87,12 @@ pub struct GpsSourceConfig {
     pub id: String,
     pub name: String,
     pub source_type: GpsSourceType,
-    pub port_name: Option<String>,
+    pub port_name: Option<String>, // Serial port, or host:port for TCP, gpsd and Signal K
     pub baud_rate: u32,
     pub enabled: bool,
     pub priority: i32, // Lower number = higher priority
+    #[serde(default)]
+    pub raw_nmea: bool, // gpsd: also pass raw NMEA through to the buffer
 }
 
 impl Default for GpsSourceConfig {
@@ -65,6 +105,7 @@ impl Default for GpsSourceConfig {
             baud_rate: 4800, // Standard NMEA baud rate
             enabled: true,
             priority: 0,
+            raw_nmea: false,
         }
     }
 }
@@ -89,6 +130,12 @@ pub struct GpsSourceStatus {
     pub last_error: Option<String>,
     pub sentences_received: u64,
     pub last_fix_time: Option<String>,
+    #[serde(default)]
+    pub bad_checksums: u64,      // Sentences dropped for a checksum mismatch
+    #[serde(default)]
+    pub unparsed_sentences: u64, // Valid sentences that could not be parsed
+    #[serde(default)]
+    pub sentence_stats: Vec<SentenceTypeStats>,
 }
 
 impl Default for GpsSourceStatus {
@@ -100,6 +147,9 @@ impl Default for GpsSourceStatus {
             last_error: None,
             sentences_received: 0,
             last_fix_time: None,
+            bad_checksums: 0,
+            unparsed_sentences: 0,
+            sentence_stats: Vec::new(),
         }
     }
 }
@@ -107,6 +157,32 @@ impl Default for GpsSourceStatus {
 // Buffer for storing recent NMEA sentences
 const NMEA_BUFFER_SIZE: usize = 100;
 
+// How often per-sentence statistics are copied into the status
+const SENTENCE_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Event topics pushed to the frontend
+pub const POSITION_TOPIC: &str = "gps:position";
+pub const SATELLITES_TOPIC: &str = "gps:satellites";
+pub const STATUS_TOPIC: &str = "gps:status";
+pub const NAVIGATION_TOPIC: &str = "navigation:progress";
+
+// How often the event thread checks for changes, and the fastest rate per topic
+const EVENT_TICK: Duration = Duration::from_millis(100);
+const POSITION_EVENT_INTERVAL: Duration = Duration::from_millis(200);
+const SATELLITES_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+const STATUS_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+const NAVIGATION_EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+// Payload of the satellites topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SatelliteUpdate {
+    pub satellites: Option<u32>,
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+    pub pdop: Option<f32>,
+    pub satellites_info: Vec<SatelliteInfo>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct NmeaBuffer {
     pub sentences: Vec<String>,
@@ -128,6 +204,25 @@ pub struct GpsManager {
     reader_handle: Mutex<Option<thread::JoinHandle<()>>>,
     // Recent NMEA sentences buffer
     nmea_buffer: RwLock<Vec<String>>,
+    // Filtering and dead reckoning applied to fixes
+    fix_processor: RwLock<FixProcessor>,
+    // Instruments and AIS targets from Signal K and NMEA 2000
+    vessel: RwLock<VesselData>,
+    // Route being followed, for progress events and the data server
+    navigation: RwLock<Option<ActiveNavigation>>,
+    // Set once the event thread is running; cleared to stop it
+    events_running: Arc<AtomicBool>,
+    // Event thread handle, joined on drop
+    events_handle: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+// Processing between the NMEA parser and the shared GPS data
+#[derive(Default)]
+struct FixProcessor {
+    // Smoothing, outlier rejection and integrity checks
+    filter: PositionFilter,
+    // Estimated position while the fix is lost
+    dead_reckoning: DeadReckoning,
 }
 
 impl GpsManager {
@@ -140,9 +235,19 @@ impl GpsManager {
             stop_flag: Arc::new(AtomicBool::new(false)),
             reader_handle: Mutex::new(None),
             nmea_buffer: RwLock::new(Vec::with_capacity(NMEA_BUFFER_SIZE)),
+            fix_processor: RwLock::new(FixProcessor::default()),
+            vessel: RwLock::new(VesselData::default()),
+            navigation: RwLock::new(None),
+            events_running: Arc::new(AtomicBool::new(false)),
+            events_handle: Mutex::new(None),
         }
     }
 
+    /// Configure the position filter and integrity thresholds
+    pub fn set_filter_settings(&self, settings: PositionFilterSettings) {
+        self.fix_processor.write().unwrap().filter.set_settings(settings);
+    }
+
     /// Get recent NMEA sentences
     pub fn get_nmea_buffer(&self) -> Vec<String> {
         self.nmea_buffer.read().unwrap().clone()
@@ -242,9 +347,34 @@ impl GpsManager {
         self.sources.read().unwrap().clone()
     }
 
-    /// Get current GPS data
+    /// Get current GPS data, with a dead reckoning position if the fix is lost
     pub fn get_data(&self) -> GpsData {
-        self.data.read().unwrap().clone()
+        let mut data = self.data.read().unwrap().clone();
+        if self.status.read().unwrap().status == GpsConnectionStatus::Disconnected {
+            return data;
+        }
+
+        let processor = self.fix_processor.read().unwrap();
+        let dr = &processor.dead_reckoning;
+        if let Some(estimate) = dr.estimate(Instant::now()) {
+            data.latitude = Some(estimate.lat);
+            data.longitude = Some(estimate.lon);
+            data.fix_type = Some(estimate.source.label().to_string());
+            data.is_estimated = true;
+            data.estimate_uncertainty_nm = Some(estimate.uncertainty_nm);
+            data.estimated_since = dr.last_fix_time().map(|t| t.to_rfc3339());
+        }
+        data
+    }
+
+    /// Latest depth, wind and environment readings
+    pub fn get_instruments(&self) -> InstrumentData {
+        self.vessel.read().unwrap().instruments.clone()
+    }
+
+    /// AIS targets heard from recently
+    pub fn get_ais_targets(&self) -> Vec<AisTarget> {
+        self.vessel.write().unwrap().current_ais_targets(Instant::now())
     }
 
     /// Get current status
@@ -252,6 +382,69 @@ impl GpsManager {
         self.status.read().unwrap().clone()
     }
 
+    /// Set or clear the route being followed
+    pub fn set_navigation(&self, navigation: Option<ActiveNavigation>) {
+        *self.navigation.write().unwrap() = navigation;
+    }
+
+    pub fn get_navigation(&self) -> Option<ActiveNavigation> {
+        self.navigation.read().unwrap().clone()
+    }
+
+    /// Progress along the active route from the current position
+    pub fn get_route_progress(&self) -> Option<RouteProgress> {
+        let data = self.get_data();
+        let (lat, lon) = (data.latitude?, data.longitude?);
+        self.navigation.read().unwrap().as_ref()?.progress(lat, lon, data.speed_knots, data.course)
+    }
+
+    /// Push position, satellite, status and route progress changes through `emitter`
+    /// until the manager is dropped. Must be called once the manager has its final
+    /// address (after it is placed in the app state).
+    pub fn start_events(&self, emitter: EventEmitter) {
+        if self.events_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let running = Arc::clone(&self.events_running);
+        // Safety: the manager is not moved once it is in the app state, and Drop
+        // joins the thread before the manager goes away
+        let manager = unsafe { &*(self as *const GpsManager) };
+        *self.events_handle.lock().unwrap() = Some(thread::spawn(move || manager.event_thread(emitter, running)));
+    }
+
+    fn event_thread(&self, emitter: EventEmitter, running: Arc<AtomicBool>) {
+        let mut channel = EventChannel::new(emitter);
+        let mut last_connection: Option<GpsConnectionStatus> = None;
+
+        while running.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            let mut data = self.get_data();
+            let satellites = SatelliteUpdate {
+                satellites: data.satellites,
+                hdop: data.hdop,
+                vdop: data.vdop,
+                pdop: data.pdop,
+                satellites_info: std::mem::take(&mut data.satellites_info),
+            };
+            channel.publish(POSITION_TOPIC, &data, POSITION_EVENT_INTERVAL, now);
+            channel.publish(SATELLITES_TOPIC, &satellites, SATELLITES_EVENT_INTERVAL, now);
+
+            // Connection changes go out immediately, counters at most once a second
+            let status = self.get_status();
+            let interval = if last_connection.as_ref() != Some(&status.status) {
+                Duration::ZERO
+            } else {
+                STATUS_EVENT_INTERVAL
+            };
+            if channel.publish(STATUS_TOPIC, &status, interval, now) {
+                last_connection = Some(status.status);
+            }
+            channel.publish(NAVIGATION_TOPIC, self.get_route_progress(), NAVIGATION_EVENT_INTERVAL, now);
+
+            thread::sleep(EVENT_TICK);
+        }
+    }
+
     /// Start reading from configured GPS sources
     pub fn start(&self) -> Result<(), GpsError> {
         // Stop any existing reader
@@ -281,12 +474,13 @@ impl GpsManager {
         };
         let status_lock = unsafe { &*(&self.status as *const RwLock<GpsSourceStatus>) };
         let nmea_buffer_lock = unsafe { &*(&self.nmea_buffer as *const RwLock<Vec<String>>) };
-        let parser = NmeaParser::new();
+        let processor_lock = unsafe { &*(&self.fix_processor as *const RwLock<FixProcessor>) };
+        let vessel_lock = unsafe { &*(&self.vessel as *const RwLock<VesselData>) };
         let sources_for_thread = enabled_sources.clone();
 
         // Start reader thread
         let handle = thread::spawn(move || {
-            Self::reader_thread(stop_flag, data_lock, status_lock, nmea_buffer_lock, parser, sources_for_thread);
+            Self::reader_thread(stop_flag, data_lock, status_lock, nmea_buffer_lock, processor_lock, vessel_lock, sources_for_thread);
         });
 
         *self.reader_handle.lock().unwrap() = Some(handle);
@@ -307,11 +501,10 @@ impl GpsManager {
     pub fn stop(&self) {
         self.stop_flag.store(true, Ordering::SeqCst);
 
-        if let Some(handle) = self.reader_handle.lock().unwrap().take() {
-            // Give it a moment to stop gracefully
-            thread::sleep(Duration::from_millis(100));
-            // We can't really force-stop a thread, but setting the flag should work
-            drop(handle);
+        // Wait for the reader to see the flag, so two readers never overlap
+        let reader = self.reader_handle.lock().unwrap().take();
+        if let Some(handle) = reader {
+            let _ = handle.join();
         }
 
         let mut status = self.status.write().unwrap();
@@ -324,7 +517,8 @@ impl GpsManager {
         data_lock: &RwLock<GpsData>,
         status_lock: &RwLock<GpsSourceStatus>,
         nmea_buffer_lock: &RwLock<Vec<String>>,
-        parser: NmeaParser,
+        processor_lock: &RwLock<FixProcessor>,
+        vessel_lock: &RwLock<VesselData>,
         sources: Vec<GpsSourceConfig>,
     ) {
         let mut current_source_idx = 0;
@@ -348,18 +542,12 @@ impl GpsManager {
             let source = &sources[current_source_idx];
 
             match &source.source_type {
-                GpsSourceType::SerialPort => {
+                GpsSourceType::SerialPort | GpsSourceType::TcpStream => {
                     if let Some(ref port_name) = source.port_name {
-                        match Self::read_from_serial(
-                            &stop_flag,
-                            data_lock,
-                            status_lock,
-                            nmea_buffer_lock,
-                            &parser,
-                            port_name,
-                            source.baud_rate,
-                            source,
-                        ) {
+                        let result = Self::open_line_reader(source, port_name).and_then(|reader| {
+                            Self::read_lines(&stop_flag, data_lock, status_lock, nmea_buffer_lock, processor_lock, vessel_lock, reader, source)
+                        });
+                        match result {
                             Ok(()) => {
                                 // Normal stop requested
                                 return;
@@ -373,11 +561,35 @@ impl GpsManager {
                         }
                     }
                 }
+                GpsSourceType::Gpsd => {
+                    let address = source.port_name.as_deref().unwrap_or(DEFAULT_GPSD_ADDRESS);
+                    match Self::read_from_gpsd(&stop_flag, data_lock, status_lock, nmea_buffer_lock, processor_lock, address, source) {
+                        Ok(()) => return,
+                        Err(e) => {
+                            log::warn!("GPS source {} failed: {}", source.name, e);
+                            let mut status = status_lock.write().unwrap();
+                            status.last_error = Some(e.to_string());
+                            status.status = GpsConnectionStatus::Error;
+                        }
+                    }
+                }
+                GpsSourceType::SignalK => {
+                    if let Some(ref address) = source.port_name {
+                        match Self::read_from_signalk(&stop_flag, data_lock, status_lock, processor_lock, vessel_lock, address, source) {
+                            Ok(()) => return,
+                            Err(e) => {
+                                log::warn!("GPS source {} failed: {}", source.name, e);
+                                let mut status = status_lock.write().unwrap();
+                                status.last_error = Some(e.to_string());
+                                status.status = GpsConnectionStatus::Error;
+                            }
+                        }
+                    }
+                }
                 GpsSourceType::Simulated => {
-                    Self::run_simulated_gps(&stop_flag, data_lock, status_lock, nmea_buffer_lock, source);
+                    Self::run_simulated_gps(&stop_flag, data_lock, status_lock, nmea_buffer_lock, processor_lock, source);
                     return;
                 }
-                _ => {}
             }
 
             current_source_idx += 1;
@@ -385,20 +597,42 @@ impl GpsManager {
         }
     }
 
-    /// Read GPS data from a serial port
-    fn read_from_serial(
+    /// Open a serial port, or connect to "host:port" for a TCP stream
+    fn open_line_reader(source: &GpsSourceConfig, port_name: &str) -> Result<BufReader<Box<dyn Read + Send>>, GpsError> {
+        let stream: Box<dyn Read + Send> = match source.source_type {
+            GpsSourceType::TcpStream => {
+                let address = port_name
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| GpsError::SourceNotFound(port_name.to_string()))?;
+                let stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+                stream.set_read_timeout(Some(Duration::from_millis(1000)))?;
+                Box::new(stream)
+            }
+            _ => Box::new(
+                serialport::new(port_name, source.baud_rate)
+                    .timeout(Duration::from_millis(1000))
+                    .open()?,
+            ),
+        };
+        Ok(BufReader::new(stream))
+    }
+
+    /// Read NMEA 0183 sentences and NMEA 2000 gateway lines (Actisense ASCII or
+    /// Yacht Devices RAW) from a serial port or TCP stream
+    #[allow(clippy::too_many_arguments)]
+    fn read_lines(
         stop_flag: &Arc<AtomicBool>,
         data_lock: &RwLock<GpsData>,
         status_lock: &RwLock<GpsSourceStatus>,
         nmea_buffer_lock: &RwLock<Vec<String>>,
-        parser: &NmeaParser,
-        port_name: &str,
-        baud_rate: u32,
+        processor_lock: &RwLock<FixProcessor>,
+        vessel_lock: &RwLock<VesselData>,
+        mut reader: impl BufRead,
         source: &GpsSourceConfig,
     ) -> Result<(), GpsError> {
-        let port = serialport::new(port_name, baud_rate)
-            .timeout(Duration::from_millis(1000))
-            .open()?;
+        let parser = NmeaParser::new();
+        let mut n2k_decoder = Nmea2000Decoder::new();
 
         // Update status to connected
         {
@@ -407,11 +641,17 @@ impl GpsManager {
             status.source_name = Some(source.name.clone());
             status.status = GpsConnectionStatus::Connected;
             status.last_error = None;
+            status.bad_checksums = 0;
+            status.unparsed_sentences = 0;
+            status.sentence_stats.clear();
         }
 
-        let mut reader = BufReader::new(port);
         let mut line = String::new();
         let mut sentences_received: u64 = 0;
+        let mut bad_checksums: u64 = 0;
+        let mut unparsed_sentences: u64 = 0;
+        let mut sentence_stats = SentenceStats::new();
+        let mut stats_published = Instant::now();
 
         while !stop_flag.load(Ordering::SeqCst) {
             line.clear();
@@ -423,63 +663,38 @@ impl GpsManager {
                 Ok(_) => {
                     let trimmed = line.trim();
                     if trimmed.starts_with('$') {
-                        sentences_received += 1;
+                        Self::push_nmea(nmea_buffer_lock, trimmed.to_string());
 
-                        // Add to NMEA buffer (ring buffer behavior)
-                        {
-                            let mut buffer = nmea_buffer_lock.write().unwrap();
-                            if buffer.len() >= NMEA_BUFFER_SIZE {
-                                buffer.remove(0);
-                            }
-                            buffer.push(trimmed.to_string());
+                        // Drop corrupted sentences; a missing checksum is tolerated
+                        if checksum_valid(trimmed) == Some(false) {
+                            bad_checksums += 1;
+                            status_lock.write().unwrap().bad_checksums = bad_checksums;
+                            continue;
                         }
+                        sentences_received += 1;
+                        sentence_stats.record(trimmed, Instant::now());
 
                         // Parse the NMEA sentence
-                        if let Ok(new_data) = parser.parse_sentence(trimmed) {
-                            // Update GPS data
-                            let mut data = data_lock.write().unwrap();
-                            if new_data.latitude.is_some() {
-                                data.latitude = new_data.latitude;
-                            }
-                            if new_data.longitude.is_some() {
-                                data.longitude = new_data.longitude;
-                            }
-                            if new_data.speed_knots.is_some() {
-                                data.speed_knots = new_data.speed_knots;
-                            }
-                            if new_data.course.is_some() {
-                                data.course = new_data.course;
-                            }
-                            if new_data.heading.is_some() {
-                                data.heading = new_data.heading;
-                            }
-                            if new_data.altitude.is_some() {
-                                data.altitude = new_data.altitude;
-                            }
-                            if new_data.fix_quality.is_some() {
-                                data.fix_quality = new_data.fix_quality;
-                            }
-                            if new_data.satellites.is_some() {
-                                data.satellites = new_data.satellites;
-                            }
-                            if new_data.hdop.is_some() {
-                                data.hdop = new_data.hdop;
-                            }
-                            if new_data.vdop.is_some() {
-                                data.vdop = new_data.vdop;
-                            }
-                            if new_data.pdop.is_some() {
-                                data.pdop = new_data.pdop;
-                            }
-                            if new_data.timestamp.is_some() {
-                                data.timestamp = new_data.timestamp.clone();
-                            }
-                            if new_data.fix_type.is_some() {
-                                data.fix_type = new_data.fix_type.clone();
+                        let parsed = parser.parse_sentence(trimmed);
+                        let is_parsed = parsed.is_ok();
+                        if let Ok(new_data) = parsed {
+                            Self::apply_update(data_lock, processor_lock, new_data, is_position_fix(trimmed), source);
+                        }
+
+                        // Compass heading and log speed keep dead reckoning going without a fix
+                        let (heading, log_speed) = parse_heading_log(trimmed);
+                        if heading.is_some() || log_speed.is_some() {
+                            let now = Instant::now();
+                            let dr = &mut processor_lock.write().unwrap().dead_reckoning;
+                            if let Some(heading) = heading {
+                                dr.update_heading(heading, now);
+                                data_lock.write().unwrap().heading = Some(heading);
                             }
-                            if !new_data.satellites_info.is_empty() {
-                                data.satellites_info = new_data.satellites_info.clone();
+                            if let Some(speed) = log_speed {
+                                dr.update_log_speed(speed, now);
                             }
+                        } else if !is_parsed {
+                            unparsed_sentences += 1;
                         }
 
                         // Update status
@@ -487,14 +702,35 @@ impl GpsManager {
                             let mut status = status_lock.write().unwrap();
                             status.status = GpsConnectionStatus::ReceivingData;
                             status.sentences_received = sentences_received;
+                            status.unparsed_sentences = unparsed_sentences;
+                            if stats_published.elapsed() >= SENTENCE_STATS_INTERVAL {
+                                status.sentence_stats = sentence_stats.snapshot(Instant::now());
+                                stats_published = Instant::now();
+                            }
                             if let Some(ref ts) = data_lock.read().unwrap().timestamp {
                                 status.last_fix_time = Some(ts.clone());
                             }
                         }
+                    } else if is_nmea2000_line(trimmed) {
+                        Self::push_nmea(nmea_buffer_lock, trimmed.to_string());
+                        sentences_received += 1;
+
+                        let now = Instant::now();
+                        let update = n2k_decoder.handle_line(trimmed, &mut vessel_lock.write().unwrap(), now);
+                        if let Some(update) = update {
+                            Self::apply_own_vessel_update(data_lock, processor_lock, update, source, now);
+                        }
+
+                        let mut status = status_lock.write().unwrap();
+                        status.status = GpsConnectionStatus::ReceivingData;
+                        status.sentences_received = sentences_received;
+                        if let Some(ref ts) = data_lock.read().unwrap().timestamp {
+                            status.last_fix_time = Some(ts.clone());
+                        }
                     }
                 }
                 Err(e) => {
-                    if e.kind() != std::io::ErrorKind::TimedOut {
+                    if !matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) {
                         return Err(GpsError::Io(e));
                     }
                     // Timeout is okay, just continue
@@ -505,12 +741,221 @@ impl GpsManager {
         Ok(())
     }
 
+    /// Read TPV/SKY reports from a gpsd daemon
+    fn read_from_gpsd(
+        stop_flag: &Arc<AtomicBool>,
+        data_lock: &RwLock<GpsData>,
+        status_lock: &RwLock<GpsSourceStatus>,
+        nmea_buffer_lock: &RwLock<Vec<String>>,
+        processor_lock: &RwLock<FixProcessor>,
+        address: &str,
+        source: &GpsSourceConfig,
+    ) -> Result<(), GpsError> {
+        let mut connection = GpsdConnection::connect(address, source.raw_nmea)?;
+
+        {
+            let mut status = status_lock.write().unwrap();
+            status.source_id = Some(source.id.clone());
+            status.source_name = Some(source.name.clone());
+            status.status = GpsConnectionStatus::Connected;
+            status.last_error = None;
+            status.bad_checksums = 0;
+            status.unparsed_sentences = 0;
+            status.sentence_stats.clear();
+        }
+
+        let mut reports_received: u64 = 0;
+        let mut sentence_stats = SentenceStats::new();
+        let mut stats_published = Instant::now();
+
+        while !stop_flag.load(Ordering::SeqCst) {
+            match connection.next_message()? {
+                Some(GpsdMessage::Report(report)) => {
+                    if matches!(report, GpsdReport::Other) {
+                        continue;
+                    }
+                    reports_received += 1;
+                    let is_fix = report.is_fix();
+                    Self::apply_update(data_lock, processor_lock, report.to_gps_data(), is_fix, source);
+
+                    let mut status = status_lock.write().unwrap();
+                    status.status = GpsConnectionStatus::ReceivingData;
+                    status.sentences_received = reports_received;
+                    if let Some(ref ts) = data_lock.read().unwrap().timestamp {
+                        status.last_fix_time = Some(ts.clone());
+                    }
+                }
+                // Raw sentences are for display only; gpsd's reports carry the data
+                Some(GpsdMessage::Nmea(sentence)) => {
+                    sentence_stats.record(&sentence, Instant::now());
+                    Self::push_nmea(nmea_buffer_lock, sentence);
+                }
+                None => {}
+            }
+
+            if stats_published.elapsed() >= SENTENCE_STATS_INTERVAL {
+                status_lock.write().unwrap().sentence_stats = sentence_stats.snapshot(Instant::now());
+                stats_published = Instant::now();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read own-vessel, instrument and AIS data from a Signal K server
+    fn read_from_signalk(
+        stop_flag: &Arc<AtomicBool>,
+        data_lock: &RwLock<GpsData>,
+        status_lock: &RwLock<GpsSourceStatus>,
+        processor_lock: &RwLock<FixProcessor>,
+        vessel_lock: &RwLock<VesselData>,
+        address: &str,
+        source: &GpsSourceConfig,
+    ) -> Result<(), GpsError> {
+        let mut connection = SignalKConnection::connect(address)?;
+
+        {
+            let mut status = status_lock.write().unwrap();
+            status.source_id = Some(source.id.clone());
+            status.source_name = Some(source.name.clone());
+            status.status = GpsConnectionStatus::Connected;
+            status.last_error = None;
+            status.bad_checksums = 0;
+            status.unparsed_sentences = 0;
+            status.sentence_stats.clear();
+        }
+
+        let mut state = SignalKState::new();
+        let mut deltas_received: u64 = 0;
+
+        while !stop_flag.load(Ordering::SeqCst) {
+            let Some(text) = connection.next_message()? else {
+                continue;
+            };
+            deltas_received += 1;
+            let now = Instant::now();
+            let update = state.handle_message(&text, &mut vessel_lock.write().unwrap(), now);
+
+            if let Some(update) = update {
+                Self::apply_own_vessel_update(data_lock, processor_lock, update, source, now);
+            }
+
+            let mut status = status_lock.write().unwrap();
+            status.status = GpsConnectionStatus::ReceivingData;
+            status.sentences_received = deltas_received;
+            if let Some(ref ts) = data_lock.read().unwrap().timestamp {
+                status.last_fix_time = Some(ts.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Filter one update and merge the fields it provides into the shared data,
+    /// then refresh integrity and dead reckoning
+    fn apply_update(
+        data_lock: &RwLock<GpsData>,
+        processor_lock: &RwLock<FixProcessor>,
+        mut new_data: GpsData,
+        is_fix: bool,
+        source: &GpsSourceConfig,
+    ) {
+        let mut processor = processor_lock.write().unwrap();
+        processor.filter.apply(&mut new_data, is_fix, Instant::now());
+
+        // Update GPS data
+        let mut data = data_lock.write().unwrap();
+        if new_data.latitude.is_some() {
+            data.latitude = new_data.latitude;
+        }
+        if new_data.longitude.is_some() {
+            data.longitude = new_data.longitude;
+        }
+        if new_data.speed_knots.is_some() {
+            data.speed_knots = new_data.speed_knots;
+        }
+        if new_data.course.is_some() {
+            data.course = new_data.course;
+        }
+        if new_data.heading.is_some() {
+            data.heading = new_data.heading;
+        }
+        if new_data.altitude.is_some() {
+            data.altitude = new_data.altitude;
+        }
+        if new_data.fix_quality.is_some() {
+            data.fix_quality = new_data.fix_quality;
+        }
+        if new_data.satellites.is_some() {
+            data.satellites = new_data.satellites;
+        }
+        if new_data.hdop.is_some() {
+            data.hdop = new_data.hdop;
+        }
+        if new_data.vdop.is_some() {
+            data.vdop = new_data.vdop;
+        }
+        if new_data.pdop.is_some() {
+            data.pdop = new_data.pdop;
+        }
+        if new_data.timestamp.is_some() {
+            data.timestamp = new_data.timestamp.clone();
+        }
+        if new_data.fix_type.is_some() {
+            data.fix_type = new_data.fix_type.clone();
+        }
+        if !new_data.satellites_info.is_empty() {
+            data.satellites_info = new_data.satellites_info.clone();
+        }
+
+        let (integrity, warnings) = processor.filter.integrity(&data);
+        data.integrity = Some(integrity);
+        data.integrity_warnings = warnings;
+        data.rejected_fixes = processor.filter.rejected_fixes;
+
+        if let (true, Some(lat), Some(lon)) = (is_fix, new_data.latitude, new_data.longitude) {
+            if let Some(error) = processor.dead_reckoning.update_fix(lat, lon, data.course, data.speed_knots, Instant::now()) {
+                log::info!("GPS fix regained on {}, dead reckoning error {:.2} nm", source.name, error);
+            }
+        }
+    }
+
+    /// Feed heading and water speed to dead reckoning, then apply the GPS fields
+    fn apply_own_vessel_update(
+        data_lock: &RwLock<GpsData>,
+        processor_lock: &RwLock<FixProcessor>,
+        update: OwnVesselUpdate,
+        source: &GpsSourceConfig,
+        now: Instant,
+    ) {
+        {
+            let dr = &mut processor_lock.write().unwrap().dead_reckoning;
+            if let Some(heading) = update.heading {
+                dr.update_heading(heading, now);
+            }
+            if let Some(speed) = update.water_speed {
+                dr.update_log_speed(speed, now);
+            }
+        }
+        Self::apply_update(data_lock, processor_lock, update.gps, update.is_fix, source);
+    }
+
+    /// Add a sentence to the NMEA buffer (ring buffer behavior)
+    fn push_nmea(nmea_buffer_lock: &RwLock<Vec<String>>, sentence: String) {
+        let mut buffer = nmea_buffer_lock.write().unwrap();
+        if buffer.len() >= NMEA_BUFFER_SIZE {
+            buffer.remove(0);
+        }
+        buffer.push(sentence);
+    }
+
     /// Run simulated GPS for testing
     fn run_simulated_gps(
         stop_flag: &Arc<AtomicBool>,
         data_lock: &RwLock<GpsData>,
         status_lock: &RwLock<GpsSourceStatus>,
         nmea_buffer_lock: &RwLock<Vec<String>>,
+        processor_lock: &RwLock<FixProcessor>,
         source: &GpsSourceConfig,
     ) {
         use crate::nmea::SatelliteInfo;
@@ -580,6 +1025,7 @@ impl GpsManager {
                 data.fix_type = Some("GPS".to_string());
                 data.satellites_info = simulated_satellites.clone();
             }
+            processor_lock.write().unwrap().dead_reckoning.update_fix(lat, lon, Some(heading), Some(5.5), Instant::now());
 
             {
                 let mut status = status_lock.write().unwrap();
@@ -593,7 +1039,11 @@ impl GpsManager {
 
 impl Drop for GpsManager {
     fn drop(&mut self) {
+        self.events_running.store(false, Ordering::SeqCst);
         self.stop();
+        if let Some(handle) = self.events_handle.get_mut().unwrap().take() {
+            let _ = handle.join();
+        }
     }
 }
 
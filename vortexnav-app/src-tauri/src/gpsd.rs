@@ -0,0 +1,274 @@
+// gpsd client
+// Reads TPV/SKY reports from gpsd's JSON protocol, with optional raw NMEA passthrough
+
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::gps::GpsError;
+use crate::instruments::METERS_PER_SECOND_TO_KNOTS;
+use crate::nmea::{GpsData, SatelliteInfo};
+
+/// gpsd's default address when a source has none
+pub const DEFAULT_GPSD_ADDRESS: &str = "127.0.0.1:2947";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Time-position-velocity report
+#[derive(Debug, Clone, Deserialize)]
+pub struct TpvReport {
+    #[serde(default)]
+    pub mode: u8,
+    pub status: Option<u8>,
+    pub time: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    #[serde(rename = "altHAE")]
+    pub alt_hae: Option<f64>,
+    pub alt: Option<f64>,
+    pub track: Option<f64>,
+    /// Speed over ground (m/s)
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SkySatellite {
+    #[serde(rename = "PRN")]
+    pub prn: u32,
+    pub el: Option<f32>,
+    pub az: Option<f32>,
+    pub ss: Option<f32>,
+    #[serde(default)]
+    pub used: bool,
+    pub gnssid: Option<u8>,
+}
+
+/// Satellites in view and dilution of precision
+#[derive(Debug, Clone, Deserialize)]
+pub struct SkyReport {
+    pub hdop: Option<f32>,
+    pub vdop: Option<f32>,
+    pub pdop: Option<f32>,
+    #[serde(default)]
+    pub satellites: Vec<SkySatellite>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "class")]
+pub enum GpsdReport {
+    #[serde(rename = "TPV")]
+    Tpv(TpvReport),
+    #[serde(rename = "SKY")]
+    Sky(SkyReport),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub enum GpsdMessage {
+    Report(GpsdReport),
+    /// Raw sentence passed through from the receiver
+    Nmea(String),
+}
+
+impl GpsdReport {
+    /// Convert to the fields of GpsData it provides; everything else is left None
+    pub fn to_gps_data(&self) -> GpsData {
+        let mut data = GpsData::default();
+        match self {
+            GpsdReport::Tpv(tpv) => {
+                let has_fix = tpv.mode >= 2;
+                if has_fix {
+                    data.latitude = tpv.lat;
+                    data.longitude = tpv.lon;
+                    data.speed_knots = tpv.speed.map(|s| s * METERS_PER_SECOND_TO_KNOTS);
+                    data.course = tpv.track;
+                    data.altitude = tpv.alt_hae.or(tpv.alt);
+                }
+                data.fix_quality = Some(match (has_fix, tpv.status) {
+                    (false, _) => 0,
+                    (true, Some(2)) => 2,
+                    _ => 1,
+                });
+                data.fix_type = Some(
+                    match (has_fix, tpv.status) {
+                        (false, _) => "No Fix",
+                        (true, Some(2)) => "DGPS",
+                        _ => "GPS",
+                    }
+                    .to_string(),
+                );
+                data.timestamp = tpv.time.clone();
+            }
+            GpsdReport::Sky(sky) => {
+                data.hdop = sky.hdop;
+                data.vdop = sky.vdop;
+                data.pdop = sky.pdop;
+                if !sky.satellites.is_empty() {
+                    data.satellites = Some(sky.satellites.iter().filter(|s| s.used).count() as u32);
+                }
+                data.satellites_info = sky
+                    .satellites
+                    .iter()
+                    .map(|s| SatelliteInfo {
+                        prn: s.prn,
+                        elevation: s.el,
+                        azimuth: s.az,
+                        snr: s.ss,
+                        constellation: constellation_name(s.gnssid, s.prn).to_string(),
+                    })
+                    .collect();
+            }
+            GpsdReport::Other => {}
+        }
+        data
+    }
+
+    /// Whether this report carries a position fix
+    pub fn is_fix(&self) -> bool {
+        matches!(self, GpsdReport::Tpv(tpv) if tpv.mode >= 2 && tpv.lat.is_some() && tpv.lon.is_some())
+    }
+}
+
+/// Constellation from gpsd's gnssid, or the NMEA PRN range for older gpsd
+fn constellation_name(gnssid: Option<u8>, prn: u32) -> &'static str {
+    match gnssid {
+        Some(0) => "GPS",
+        Some(1) => "SBAS",
+        Some(2) => "Galileo",
+        Some(3) => "BeiDou",
+        Some(5) => "QZSS",
+        Some(6) => "GLONASS",
+        Some(7) => "NavIC",
+        Some(_) => "Other",
+        None => match prn {
+            1..=32 => "GPS",
+            33..=64 => "SBAS",
+            65..=96 => "GLONASS",
+            _ => "Other",
+        },
+    }
+}
+
+/// Connection to a gpsd daemon that has been asked to stream reports
+pub struct GpsdConnection {
+    reader: BufReader<TcpStream>,
+    line: String,
+}
+
+impl GpsdConnection {
+    /// Connect to "host:port" and send ?WATCH, optionally with raw NMEA passthrough
+    pub fn connect(address: &str, raw_nmea: bool) -> Result<Self, GpsError> {
+        let socket_addr = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| GpsError::SourceNotFound(address.to_string()))?;
+        let mut stream = TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let watch = if raw_nmea {
+            "?WATCH={\"enable\":true,\"json\":true,\"raw\":1};\n"
+        } else {
+            "?WATCH={\"enable\":true,\"json\":true};\n"
+        };
+        stream.write_all(watch.as_bytes())?;
+
+        Ok(Self { reader: BufReader::new(stream), line: String::new() })
+    }
+
+    /// Next report or NMEA sentence. Ok(None) on a read timeout; an error when
+    /// gpsd closes the connection.
+    pub fn next_message(&mut self) -> Result<Option<GpsdMessage>, GpsError> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    return Err(GpsError::Io(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "gpsd closed the connection",
+                    )))
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(GpsError::Io(e)),
+            }
+
+            let trimmed = self.line.trim();
+            if trimmed.starts_with('{') {
+                match serde_json::from_str::<GpsdReport>(trimmed) {
+                    Ok(report) => return Ok(Some(GpsdMessage::Report(report))),
+                    Err(e) => log::debug!("Ignoring gpsd message: {}", e),
+                }
+            } else if trimmed.starts_with('$') || trimmed.starts_with('!') {
+                return Ok(Some(GpsdMessage::Nmea(trimmed.to_string())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_fake_gpsd_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(stream.try_clone().unwrap()).read_line(&mut request).unwrap();
+            let messages = [
+                r#"{"class":"VERSION","release":"3.25","proto_major":3,"proto_minor":15}"#,
+                r#"{"class":"DEVICES","devices":[{"path":"/dev/ttyACM0"}]}"#,
+                "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A",
+                r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"status":2,"time":"2024-06-01T10:00:00.000Z","lat":-36.84,"lon":174.77,"altHAE":12.5,"track":45.0,"speed":2.572}"#,
+                r#"{"class":"SKY","hdop":0.8,"vdop":1.1,"pdop":1.4,"satellites":[{"PRN":5,"el":45,"az":120,"ss":41,"used":true,"gnssid":0},{"PRN":70,"el":12,"az":300,"ss":22,"used":false}]}"#,
+            ];
+            for message in messages {
+                writeln!(stream, "{}", message).unwrap();
+            }
+            request
+        });
+
+        let mut connection = GpsdConnection::connect(&address, true).unwrap();
+        let mut data = GpsData::default();
+        let mut nmea = Vec::new();
+        let mut fixes = 0;
+        while let Ok(Some(message)) = connection.next_message() {
+            match message {
+                GpsdMessage::Report(report) => {
+                    fixes += report.is_fix() as usize;
+                    let update = report.to_gps_data();
+                    if update.latitude.is_some() {
+                        data = GpsData { satellites_info: data.satellites_info, hdop: data.hdop, ..update };
+                    } else if !update.satellites_info.is_empty() {
+                        data.satellites_info = update.satellites_info;
+                        data.hdop = update.hdop;
+                        data.satellites = update.satellites;
+                    }
+                }
+                GpsdMessage::Nmea(sentence) => nmea.push(sentence),
+            }
+        }
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("?WATCH={") && request.contains("\"raw\":1"));
+
+        assert_eq!(fixes, 1);
+        assert_eq!(data.latitude, Some(-36.84));
+        assert!((data.speed_knots.unwrap() - 5.0).abs() < 0.01);
+        assert_eq!(data.fix_type.as_deref(), Some("DGPS"));
+        assert_eq!(data.satellites, Some(1));
+        assert_eq!(data.hdop, Some(0.8));
+        assert_eq!(data.satellites_info[1].constellation, "GLONASS");
+        assert_eq!(nmea.len(), 1);
+    }
+}
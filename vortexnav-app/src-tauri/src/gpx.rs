@@ -1,5 +1,5 @@
 // GPX file parser and generator for route import/export
-// Supports GPX 1.0 and 1.1 formats
+// Supports GPX 1.0 and 1.1 formats, with Garmin and OpenCPN extensions
 
 use quick_xml::de::from_str;
 use quick_xml::se::to_string;
@@ -7,14 +7,75 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::database::{Track, TrackPoint, TrackWithPoints};
+use crate::instruments::METERS_PER_SECOND_TO_KNOTS;
+use crate::navigation::haversine_distance;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpxImportResult {
     pub routes_imported: usize,
     pub waypoints_imported: usize,
     pub tracks_imported: usize,
+    /// Objects whose uuid matched one already stored and were updated instead
+    #[serde(default)]
+    pub routes_updated: usize,
+    #[serde(default)]
+    pub waypoints_updated: usize,
+    #[serde(default)]
+    pub tracks_updated: usize,
     pub errors: Vec<String>,
 }
 
+/// Garmin `gpxx:DisplayMode`; OpenCPN's `viz_name` maps onto the first two
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GpxDisplayMode {
+    /// Symbol only
+    Symbol,
+    /// Symbol and name
+    Name,
+    /// Symbol and description
+    Description,
+}
+
+impl GpxDisplayMode {
+    fn from_garmin(value: &str) -> Option<Self> {
+        match value.trim() {
+            "SymbolOnly" => Some(Self::Symbol),
+            "SymbolAndName" => Some(Self::Name),
+            "SymbolAndDescription" => Some(Self::Description),
+            _ => None,
+        }
+    }
+
+    fn as_garmin(self) -> &'static str {
+        match self {
+            Self::Symbol => "SymbolOnly",
+            Self::Name => "SymbolAndName",
+            Self::Description => "SymbolAndDescription",
+        }
+    }
+
+    /// Whether a label is drawn beside the symbol
+    pub fn shows_label(self) -> bool {
+        self != Self::Symbol
+    }
+}
+
+/// Point details other tools keep in GPX extensions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpxExtensions {
+    /// Water depth in metres
+    pub depth_m: Option<f64>,
+    /// Water temperature in °C
+    pub temperature_c: Option<f64>,
+    /// Speed over ground in knots
+    pub speed_kn: Option<f64>,
+    /// Course over ground in degrees true
+    pub course: Option<f64>,
+    pub display_mode: Option<GpxDisplayMode>,
+    pub arrival_radius_nm: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpxRoutePoint {
     pub name: Option<String>,
@@ -24,12 +85,21 @@ pub struct GpxRoutePoint {
     pub time: Option<String>,
     pub desc: Option<String>,
     pub sym: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub extensions: GpxExtensions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpxRoute {
     pub name: Option<String>,
     pub desc: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    /// Line colour as `#rrggbb`, for formats that carry one
+    #[serde(default)]
+    pub color: Option<String>,
     pub points: Vec<GpxRoutePoint>,
 }
 
@@ -42,12 +112,20 @@ pub struct GpxWaypoint {
     pub time: Option<String>,
     pub desc: Option<String>,
     pub sym: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub extensions: GpxExtensions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpxTrack {
     pub name: Option<String>,
     pub desc: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
     pub segments: Vec<Vec<GpxRoutePoint>>,
 }
 
@@ -58,6 +136,67 @@ pub struct ParsedGpx {
     pub tracks: Vec<GpxTrack>,
 }
 
+pub const GPX_NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+
+/// Namespace for the VortexNav extension elements written into exported GPX
+pub const VORTEXNAV_GPX_NAMESPACE: &str = "https://github.com/tony-sparks-nz/VortexNav/gpx/1";
+
+pub const GARMIN_GPXX_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/GpxExtensions/v3";
+
+pub const GARMIN_TRACK_POINT_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v2";
+
+pub const OPENCPN_NAMESPACE: &str = "http://www.opencpn.org";
+
+const SCHEMA_LOCATION: &str = "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd \
+    http://www.garmin.com/xmlschemas/GpxExtensions/v3 http://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd \
+    http://www.garmin.com/xmlschemas/TrackPointExtension/v2 http://www8.garmin.com/xmlschemas/TrackPointExtensionv2.xsd";
+
+/// Garmin `gpxx:DisplayColor` names, as written by Garmin units and OpenCPN
+pub const GARMIN_COLORS: [(&str, &str); 16] = [
+    ("Black", "#000000"),
+    ("DarkRed", "#8b0000"),
+    ("DarkGreen", "#006400"),
+    ("DarkYellow", "#808000"),
+    ("DarkBlue", "#00008b"),
+    ("DarkMagenta", "#8b008b"),
+    ("DarkCyan", "#008b8b"),
+    ("LightGray", "#d3d3d3"),
+    ("DarkGray", "#a9a9a9"),
+    ("Red", "#ff0000"),
+    ("Green", "#00ff00"),
+    ("Yellow", "#ffff00"),
+    ("Blue", "#0000ff"),
+    ("Magenta", "#ff00ff"),
+    ("Cyan", "#00ffff"),
+    ("White", "#ffffff"),
+];
+
+/// `#rrggbb` for a Garmin colour name; None for Transparent or unknown names
+pub fn garmin_color_to_hex(name: &str) -> Option<String> {
+    GARMIN_COLORS
+        .iter()
+        .find(|(garmin, _)| garmin.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, hex)| hex.to_string())
+}
+
+/// The Garmin colour closest to `#rrggbb`
+pub fn hex_to_garmin_color(hex: &str) -> Option<&'static str> {
+    let rgb = |hex: &str| -> Option<[i32; 3]> {
+        let hex = hex.trim().trim_start_matches('#');
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| i32::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    };
+    let target = rgb(hex)?;
+    GARMIN_COLORS
+        .iter()
+        .filter_map(|(name, hex)| rgb(hex).map(|c| (name, c)))
+        .min_by_key(|(_, c)| c.iter().zip(&target).map(|(a, b)| (a - b) * (a - b)).sum::<i32>())
+        .map(|(name, _)| *name)
+}
+
 // GPX XML structures for deserialization
 #[derive(Debug, Deserialize)]
 #[serde(rename = "gpx")]
@@ -81,6 +220,104 @@ struct WptXml {
     time: Option<String>,
     desc: Option<String>,
     sym: Option<String>,
+    /// GPX 1.0 only, in m/s
+    speed: Option<f64>,
+    /// GPX 1.0 only
+    course: Option<f64>,
+    #[serde(default)]
+    extensions: ExtensionsXml,
+}
+
+/// The extension elements we read, matched by local name whatever their prefix
+#[derive(Debug, Default, Deserialize)]
+struct ExtensionsXml {
+    /// `vortexnav:uuid`
+    uuid: Option<String>,
+    /// `vortexnav:color`, exact where Garmin's palette is not
+    color: Option<String>,
+    /// `opencpn:guid`
+    guid: Option<String>,
+    /// "0" when OpenCPN hides the name
+    viz_name: Option<String>,
+    /// Nautical miles
+    arrival_radius: Option<f64>,
+    #[serde(rename = "WaypointExtension")]
+    waypoint_extension: Option<GarminWaypointXml>,
+    #[serde(rename = "RouteExtension")]
+    route_extension: Option<GarminLineXml>,
+    #[serde(rename = "TrackExtension")]
+    track_extension: Option<GarminLineXml>,
+    /// gpxtpx and the older gpxx both call theirs TrackPointExtension
+    #[serde(default, rename = "TrackPointExtension")]
+    track_point_extension: Vec<TrackPointExtensionXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GarminWaypointXml {
+    #[serde(rename = "Depth")]
+    depth: Option<f64>,
+    #[serde(rename = "Temperature")]
+    temperature: Option<f64>,
+    #[serde(rename = "DisplayMode")]
+    display_mode: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GarminLineXml {
+    #[serde(rename = "DisplayColor")]
+    display_color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackPointExtensionXml {
+    // gpxtpx
+    depth: Option<f64>,
+    wtemp: Option<f64>,
+    /// m/s
+    speed: Option<f64>,
+    course: Option<f64>,
+    // gpxx
+    #[serde(rename = "Depth")]
+    garmin_depth: Option<f64>,
+    #[serde(rename = "Temperature")]
+    garmin_temperature: Option<f64>,
+}
+
+impl ExtensionsXml {
+    fn uuid(&self) -> Option<String> {
+        self.uuid.clone().or_else(|| self.guid.clone())
+    }
+
+    fn color(&self) -> Option<String> {
+        self.color.clone().or_else(|| {
+            self.route_extension
+                .as_ref()
+                .or(self.track_extension.as_ref())
+                .and_then(|e| e.display_color.as_deref())
+                .and_then(garmin_color_to_hex)
+        })
+    }
+
+    fn point(&self, speed_ms: Option<f64>, course: Option<f64>) -> GpxExtensions {
+        let garmin = self.waypoint_extension.as_ref();
+        let track_point = |field: fn(&TrackPointExtensionXml) -> Option<f64>| self.track_point_extension.iter().find_map(field);
+        let display_mode = garmin
+            .and_then(|g| g.display_mode.as_deref())
+            .and_then(GpxDisplayMode::from_garmin)
+            .or_else(|| match self.viz_name.as_deref().map(str::trim) {
+                Some("0") => Some(GpxDisplayMode::Symbol),
+                Some("1") => Some(GpxDisplayMode::Name),
+                _ => None,
+            });
+        GpxExtensions {
+            depth_m: garmin.and_then(|g| g.depth).or_else(|| track_point(|t| t.depth.or(t.garmin_depth))),
+            temperature_c: garmin.and_then(|g| g.temperature).or_else(|| track_point(|t| t.wtemp.or(t.garmin_temperature))),
+            speed_kn: track_point(|t| t.speed).or(speed_ms).map(|s| s * METERS_PER_SECOND_TO_KNOTS),
+            course: track_point(|t| t.course).or(course),
+            display_mode,
+            arrival_radius_nm: self.arrival_radius.filter(|r| *r > 0.0),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -88,6 +325,8 @@ struct RteXml {
     name: Option<String>,
     desc: Option<String>,
     #[serde(default)]
+    extensions: ExtensionsXml,
+    #[serde(default)]
     rtept: Vec<WptXml>,
 }
 
@@ -96,6 +335,8 @@ struct TrkXml {
     name: Option<String>,
     desc: Option<String>,
     #[serde(default)]
+    extensions: ExtensionsXml,
+    #[serde(default)]
     trkseg: Vec<TrksegXml>,
 }
 
@@ -105,7 +346,8 @@ struct TrksegXml {
     trkpt: Vec<WptXml>,
 }
 
-// GPX XML structures for serialization
+// GPX XML structures for serialization. Child elements are declared in the
+// order the GPX 1.1 schema requires.
 #[derive(Debug, Serialize)]
 #[serde(rename = "gpx")]
 struct GpxXmlOut {
@@ -115,6 +357,18 @@ struct GpxXmlOut {
     creator: String,
     #[serde(rename = "@xmlns")]
     xmlns: String,
+    #[serde(rename = "@xmlns:xsi")]
+    xmlns_xsi: String,
+    #[serde(rename = "@xsi:schemaLocation")]
+    schema_location: String,
+    #[serde(rename = "@xmlns:gpxx")]
+    xmlns_gpxx: String,
+    #[serde(rename = "@xmlns:gpxtpx")]
+    xmlns_gpxtpx: String,
+    #[serde(rename = "@xmlns:opencpn")]
+    xmlns_opencpn: String,
+    #[serde(rename = "@xmlns:vortexnav")]
+    xmlns_vortexnav: String,
     metadata: Option<MetadataXmlOut>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     wpt: Vec<WptXmlOut>,
@@ -125,64 +379,166 @@ struct GpxXmlOut {
 }
 
 #[derive(Debug, Serialize)]
-struct TrkXmlOut {
+struct MetadataXmlOut {
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    desc: Option<String>,
-    trkseg: Vec<TrksegXmlOut>,
-}
-
-#[derive(Debug, Serialize)]
-struct TrksegXmlOut {
-    trkpt: Vec<TrkptXmlOut>,
+    time: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
-struct TrkptXmlOut {
+struct WptXmlOut {
     #[serde(rename = "@lat")]
     lat: f64,
     #[serde(rename = "@lon")]
     lon: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
+    ele: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     time: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    ele: Option<f64>,
+    name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    course: Option<f64>,
+    desc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sym: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<PointExtensionsXmlOut>,
+}
+
+#[derive(Debug, Serialize)]
+struct PointExtensionsXmlOut {
+    #[serde(rename = "opencpn:guid", skip_serializing_if = "Option::is_none")]
+    guid: Option<String>,
+    #[serde(rename = "opencpn:viz_name", skip_serializing_if = "Option::is_none")]
+    viz_name: Option<u8>,
+    #[serde(rename = "opencpn:arrival_radius", skip_serializing_if = "Option::is_none")]
+    arrival_radius: Option<f64>,
+    #[serde(rename = "gpxx:WaypointExtension", skip_serializing_if = "Option::is_none")]
+    waypoint_extension: Option<GarminWaypointXmlOut>,
+    #[serde(rename = "gpxtpx:TrackPointExtension", skip_serializing_if = "Option::is_none")]
+    track_point_extension: Option<TrackPointExtensionXmlOut>,
+    #[serde(rename = "vortexnav:uuid", skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct GarminWaypointXmlOut {
+    #[serde(rename = "gpxx:Temperature", skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(rename = "gpxx:Depth", skip_serializing_if = "Option::is_none")]
+    depth: Option<f64>,
+    #[serde(rename = "gpxx:DisplayMode", skip_serializing_if = "Option::is_none")]
+    display_mode: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct TrackPointExtensionXmlOut {
+    #[serde(rename = "gpxtpx:wtemp", skip_serializing_if = "Option::is_none")]
+    wtemp: Option<f64>,
+    #[serde(rename = "gpxtpx:depth", skip_serializing_if = "Option::is_none")]
+    depth: Option<f64>,
+    #[serde(rename = "gpxtpx:speed", skip_serializing_if = "Option::is_none")]
     speed: Option<f64>,
+    #[serde(rename = "gpxtpx:course", skip_serializing_if = "Option::is_none")]
+    course: Option<f64>,
+}
+
+impl PointExtensionsXmlOut {
+    /// Waypoints and route points use Garmin's waypoint extension, track points gpxtpx
+    fn new(extensions: GpxExtensions, uuid: Option<String>, track_point: bool) -> Option<Self> {
+        let GpxExtensions { depth_m, temperature_c, speed_kn, course, display_mode, arrival_radius_nm } = extensions;
+        let waypoint_extension = (!track_point && (depth_m.is_some() || temperature_c.is_some() || display_mode.is_some()))
+            .then(|| GarminWaypointXmlOut {
+                temperature: temperature_c,
+                depth: depth_m,
+                display_mode: display_mode.map(GpxDisplayMode::as_garmin),
+            });
+        let track_point_extension = (track_point && (depth_m.is_some() || temperature_c.is_some() || speed_kn.is_some() || course.is_some()))
+            .then(|| TrackPointExtensionXmlOut {
+                wtemp: temperature_c,
+                depth: depth_m,
+                speed: speed_kn.map(|s| s / METERS_PER_SECOND_TO_KNOTS),
+                course,
+            });
+        let out = PointExtensionsXmlOut {
+            guid: uuid.clone(),
+            viz_name: display_mode.map(|mode| mode.shows_label() as u8),
+            arrival_radius: arrival_radius_nm,
+            waypoint_extension,
+            track_point_extension,
+            uuid,
+        };
+        let empty = out.guid.is_none()
+            && out.viz_name.is_none()
+            && out.arrival_radius.is_none()
+            && out.waypoint_extension.is_none()
+            && out.track_point_extension.is_none();
+        (!empty).then_some(out)
+    }
 }
 
 #[derive(Debug, Serialize)]
-struct MetadataXmlOut {
-    name: Option<String>,
-    time: Option<String>,
+struct LineExtensionsXmlOut {
+    #[serde(rename = "opencpn:guid", skip_serializing_if = "Option::is_none")]
+    guid: Option<String>,
+    #[serde(rename = "gpxx:RouteExtension", skip_serializing_if = "Option::is_none")]
+    route_extension: Option<GarminLineXmlOut>,
+    #[serde(rename = "gpxx:TrackExtension", skip_serializing_if = "Option::is_none")]
+    track_extension: Option<GarminLineXmlOut>,
+    #[serde(rename = "vortexnav:color", skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(rename = "vortexnav:uuid", skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
-struct WptXmlOut {
-    #[serde(rename = "@lat")]
-    lat: f64,
-    #[serde(rename = "@lon")]
-    lon: f64,
+struct GarminLineXmlOut {
+    /// Required on routes, not allowed on tracks
+    #[serde(rename = "gpxx:IsAutoNamed", skip_serializing_if = "Option::is_none")]
+    is_auto_named: Option<bool>,
+    #[serde(rename = "gpxx:DisplayColor")]
+    display_color: &'static str,
+}
+
+impl LineExtensionsXmlOut {
+    fn new(uuid: Option<String>, color: Option<String>, route: bool) -> Option<Self> {
+        if uuid.is_none() && color.is_none() {
+            return None;
+        }
+        let garmin = color.as_deref().and_then(hex_to_garmin_color).map(|display_color| GarminLineXmlOut {
+            is_auto_named: route.then_some(false),
+            display_color,
+        });
+        let (route_extension, track_extension) = if route { (garmin, None) } else { (None, garmin) };
+        Some(LineExtensionsXmlOut { guid: uuid.clone(), route_extension, track_extension, color, uuid })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RteXmlOut {
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    ele: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     desc: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    sym: Option<String>,
+    extensions: Option<LineExtensionsXmlOut>,
+    rtept: Vec<WptXmlOut>,
 }
 
 #[derive(Debug, Serialize)]
-struct RteXmlOut {
+struct TrkXmlOut {
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     desc: Option<String>,
-    rtept: Vec<WptXmlOut>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<LineExtensionsXmlOut>,
+    trkseg: Vec<TrksegXmlOut>,
+}
+
+#[derive(Debug, Serialize)]
+struct TrksegXmlOut {
+    trkpt: Vec<WptXmlOut>,
 }
 
 /// Parse a GPX file from disk
@@ -192,6 +548,20 @@ pub fn parse_gpx_file(path: &Path) -> Result<ParsedGpx, String> {
     parse_gpx_string(&content)
 }
 
+fn to_point(p: WptXml) -> GpxRoutePoint {
+    GpxRoutePoint {
+        uuid: p.extensions.uuid(),
+        extensions: p.extensions.point(p.speed, p.course),
+        name: p.name,
+        lat: p.lat,
+        lon: p.lon,
+        ele: p.ele,
+        time: p.time,
+        desc: p.desc,
+        sym: p.sym,
+    }
+}
+
 /// Parse GPX content from a string
 pub fn parse_gpx_string(content: &str) -> Result<ParsedGpx, String> {
     // Try to parse the GPX XML
@@ -199,21 +569,9 @@ pub fn parse_gpx_string(content: &str) -> Result<ParsedGpx, String> {
         .map_err(|e| format!("Failed to parse GPX XML: {}", e))?;
 
     // Convert waypoints
-    let waypoints: Vec<GpxWaypoint> = gpx.wpt.into_iter().map(|w| GpxWaypoint {
-        name: w.name,
-        lat: w.lat,
-        lon: w.lon,
-        ele: w.ele,
-        time: w.time,
-        desc: w.desc,
-        sym: w.sym,
-    }).collect();
-
-    // Convert routes
-    let routes: Vec<GpxRoute> = gpx.rte.into_iter().map(|r| GpxRoute {
-        name: r.name,
-        desc: r.desc,
-        points: r.rtept.into_iter().map(|p| GpxRoutePoint {
+    let waypoints: Vec<GpxWaypoint> = gpx.wpt.into_iter().map(|w| {
+        let p = to_point(w);
+        GpxWaypoint {
             name: p.name,
             lat: p.lat,
             lon: p.lon,
@@ -221,62 +579,97 @@ pub fn parse_gpx_string(content: &str) -> Result<ParsedGpx, String> {
             time: p.time,
             desc: p.desc,
             sym: p.sym,
-        }).collect(),
+            uuid: p.uuid,
+            extensions: p.extensions,
+        }
+    }).collect();
+
+    // Convert routes
+    let routes: Vec<GpxRoute> = gpx.rte.into_iter().map(|r| GpxRoute {
+        name: r.name,
+        desc: r.desc,
+        uuid: r.extensions.uuid(),
+        color: r.extensions.color(),
+        points: r.rtept.into_iter().map(to_point).collect(),
     }).collect();
 
-    // Convert tracks
+    // Convert tracks, keeping each segment
     let tracks: Vec<GpxTrack> = gpx.trk.into_iter().map(|t| GpxTrack {
         name: t.name,
         desc: t.desc,
-        segments: t.trkseg.into_iter().map(|seg| {
-            seg.trkpt.into_iter().map(|p| GpxRoutePoint {
-                name: p.name,
-                lat: p.lat,
-                lon: p.lon,
-                ele: p.ele,
-                time: p.time,
-                desc: p.desc,
-                sym: p.sym,
-            }).collect()
-        }).collect(),
+        uuid: t.extensions.uuid(),
+        color: t.extensions.color(),
+        segments: t.trkseg.into_iter()
+            .map(|seg| seg.trkpt.into_iter().map(to_point).collect::<Vec<_>>())
+            .filter(|seg| !seg.is_empty())
+            .collect(),
     }).collect();
 
     Ok(ParsedGpx { waypoints, routes, tracks })
 }
 
+fn to_point_out(p: GpxRoutePoint, track_point: bool) -> WptXmlOut {
+    WptXmlOut {
+        lat: p.lat,
+        lon: p.lon,
+        ele: p.ele,
+        time: p.time,
+        name: p.name,
+        desc: p.desc,
+        sym: p.sym,
+        extensions: PointExtensionsXmlOut::new(p.extensions, p.uuid, track_point),
+    }
+}
+
 /// Generate GPX XML for a list of routes
 pub fn generate_gpx(routes: Vec<GpxRoute>, waypoints: Vec<GpxWaypoint>, name: Option<String>) -> Result<String, String> {
+    generate_gpx_document(ParsedGpx { waypoints, routes, tracks: vec![] }, name)
+}
+
+/// Generate a GPX 1.1 document holding waypoints, routes and tracks
+pub fn generate_gpx_document(data: ParsedGpx, name: Option<String>) -> Result<String, String> {
     let now = chrono::Utc::now().to_rfc3339();
 
     let gpx = GpxXmlOut {
         version: "1.1".to_string(),
         creator: "VortexNav".to_string(),
-        xmlns: "http://www.topografix.com/GPX/1/1".to_string(),
+        xmlns: GPX_NAMESPACE.to_string(),
+        xmlns_xsi: "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+        schema_location: SCHEMA_LOCATION.to_string(),
+        xmlns_gpxx: GARMIN_GPXX_NAMESPACE.to_string(),
+        xmlns_gpxtpx: GARMIN_TRACK_POINT_NAMESPACE.to_string(),
+        xmlns_opencpn: OPENCPN_NAMESPACE.to_string(),
+        xmlns_vortexnav: VORTEXNAV_GPX_NAMESPACE.to_string(),
         metadata: Some(MetadataXmlOut {
             name,
             time: Some(now),
         }),
-        wpt: waypoints.into_iter().map(|w| WptXmlOut {
+        wpt: data.waypoints.into_iter().map(|w| to_point_out(GpxRoutePoint {
+            name: w.name,
             lat: w.lat,
             lon: w.lon,
-            name: w.name,
             ele: w.ele,
+            time: w.time,
             desc: w.desc,
             sym: w.sym,
-        }).collect(),
-        rte: routes.into_iter().map(|r| RteXmlOut {
+            uuid: w.uuid,
+            extensions: w.extensions,
+        }, false)).collect(),
+        rte: data.routes.into_iter().map(|r| RteXmlOut {
             name: r.name,
             desc: r.desc,
-            rtept: r.points.into_iter().map(|p| WptXmlOut {
-                lat: p.lat,
-                lon: p.lon,
-                name: p.name,
-                ele: p.ele,
-                desc: p.desc,
-                sym: p.sym,
-            }).collect(),
+            extensions: LineExtensionsXmlOut::new(r.uuid, r.color, true),
+            rtept: r.points.into_iter().map(|p| to_point_out(p, false)).collect(),
+        }).collect(),
+        trk: data.tracks.into_iter().map(|t| TrkXmlOut {
+            name: t.name,
+            desc: t.desc,
+            extensions: LineExtensionsXmlOut::new(t.uuid, t.color, false),
+            trkseg: t.segments.into_iter()
+                .filter(|seg| !seg.is_empty())
+                .map(|seg| TrksegXmlOut { trkpt: seg.into_iter().map(|p| to_point_out(p, true)).collect() })
+                .collect(),
         }).collect(),
-        trk: vec![],
     };
 
     let xml = to_string(&gpx)
@@ -286,54 +679,96 @@ pub fn generate_gpx(routes: Vec<GpxRoute>, waypoints: Vec<GpxWaypoint>, name: Op
     Ok(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", xml))
 }
 
-/// Generate GPX XML for a track
-pub fn generate_track_gpx(track_with_points: &crate::database::TrackWithPoints) -> Result<String, String> {
-    let now = chrono::Utc::now().to_rfc3339();
+/// A stored track as GPX, one segment per run of points with the same segment number
+pub fn track_to_gpx(track_with_points: &TrackWithPoints) -> GpxTrack {
     let track = &track_with_points.track;
-    let points = &track_with_points.points;
-
-    // Convert track points to GPX format
-    let trkpts: Vec<TrkptXmlOut> = points.iter().map(|p| {
-        // Convert SOG from knots to m/s for GPX (optional, most apps accept either)
-        let speed_ms = p.sog.map(|s| s * 0.514444);
+    let segments = track_with_points
+        .points
+        .chunk_by(|a, b| a.segment == b.segment)
+        .map(|run| {
+            run.iter()
+                .map(|p| GpxRoutePoint {
+                    name: None,
+                    lat: p.lat,
+                    lon: p.lon,
+                    ele: None,
+                    time: p.timestamp.clone(),
+                    desc: None,
+                    sym: None,
+                    uuid: None,
+                    extensions: GpxExtensions {
+                        depth_m: p.depth_m,
+                        temperature_c: p.temperature_c,
+                        speed_kn: p.sog,
+                        course: p.cog,
+                        ..Default::default()
+                    },
+                })
+                .collect()
+        })
+        .collect();
+    GpxTrack {
+        name: Some(track.name.clone()),
+        desc: track.description.clone(),
+        uuid: track.uuid.clone(),
+        color: track.color.clone(),
+        segments,
+    }
+}
 
-        TrkptXmlOut {
+/// Points to store for a GPX track, numbered by segment
+pub fn track_points_from_gpx(track: &GpxTrack) -> Vec<TrackPoint> {
+    track
+        .segments
+        .iter()
+        .enumerate()
+        .flat_map(|(segment, points)| points.iter().map(move |p| (segment, p)))
+        .enumerate()
+        .map(|(sequence, (segment, p))| TrackPoint {
+            id: None,
+            track_id: 0,
             lat: p.lat,
             lon: p.lon,
-            time: Some(p.timestamp.clone()),
-            ele: None,
-            course: p.cog,
-            speed: speed_ms,
-        }
-    }).collect();
+            timestamp: p.time.clone(),
+            sequence: sequence as i64,
+            heading: None,
+            cog: p.extensions.course,
+            sog: p.extensions.speed_kn,
+            segment: segment as i64,
+            depth_m: p.extensions.depth_m,
+            temperature_c: p.extensions.temperature_c,
+        })
+        .collect()
+}
 
-    let gpx = GpxXmlOut {
-        version: "1.1".to_string(),
-        creator: "VortexNav".to_string(),
-        xmlns: "http://www.topografix.com/GPX/1/1".to_string(),
-        metadata: Some(MetadataXmlOut {
-            name: Some(format!("VortexNav Track: {}", track.name)),
-            time: Some(now),
-        }),
-        wpt: vec![],
-        rte: vec![],
-        trk: vec![TrkXmlOut {
-            name: Some(track.name.clone()),
-            desc: track.description.clone(),
-            trkseg: vec![TrksegXmlOut { trkpt: trkpts }],
-        }],
-    };
+/// Length in nautical miles, leaving out the gaps between segments
+pub fn track_distance_nm(points: &[TrackPoint]) -> f64 {
+    points
+        .windows(2)
+        .filter(|w| w[0].segment == w[1].segment)
+        .map(|w| haversine_distance(w[0].lat, w[0].lon, w[1].lat, w[1].lon))
+        .sum()
+}
 
-    let xml = to_string(&gpx)
-        .map_err(|e| format!("Failed to generate GPX XML: {}", e))?;
+/// Times of the first and last points that have one
+pub fn track_time_span(points: &[TrackPoint]) -> (Option<String>, Option<String>) {
+    let first = points.iter().find_map(|p| p.timestamp.clone());
+    let last = points.iter().rev().find_map(|p| p.timestamp.clone());
+    (first, last)
+}
 
-    // Add XML declaration
-    Ok(format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", xml))
+/// Generate GPX XML for a track
+pub fn generate_track_gpx(track_with_points: &TrackWithPoints) -> Result<String, String> {
+    let track = &track_with_points.track;
+    generate_gpx_document(
+        ParsedGpx { waypoints: vec![], routes: vec![], tracks: vec![track_to_gpx(track_with_points)] },
+        Some(format!("VortexNav Track: {}", track.name)),
+    )
 }
 
 /// Generate a text summary of a track for sharing
 pub fn generate_track_summary(
-    track: &crate::database::Track,
+    track: &Track,
     point_count: usize,
 ) -> String {
     let mut summary = format!("Track: {}\n", track.name);
@@ -400,6 +835,68 @@ pub fn generate_route_summary(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quick_xml::events::Event;
+    use quick_xml::reader::Reader;
+
+    /// Child elements allowed by the GPX 1.1 schema, in the order it requires
+    fn gpx_1_1_child_order(element: &str) -> Option<&'static [&'static str]> {
+        const POINT: &[&str] = &[
+            "ele", "time", "magvar", "geoidheight", "name", "cmt", "desc", "src", "link", "sym", "type",
+            "fix", "sat", "hdop", "vdop", "pdop", "ageofdgpsdata", "dgpsid", "extensions",
+        ];
+        match element {
+            "gpx" => Some(&["metadata", "wpt", "rte", "trk", "extensions"]),
+            "metadata" => Some(&["name", "desc", "author", "copyright", "link", "time", "keywords", "bounds", "extensions"]),
+            "wpt" | "rtept" | "trkpt" => Some(POINT),
+            "rte" => Some(&["name", "cmt", "desc", "src", "link", "number", "type", "extensions", "rtept"]),
+            "trk" => Some(&["name", "cmt", "desc", "src", "link", "number", "type", "extensions", "trkseg"]),
+            "trkseg" => Some(&["trkpt", "extensions"]),
+            _ => None,
+        }
+    }
+
+    /// Check the nesting and order of element names against GPX 1.1.
+    /// Only names are compared; attributes, values and required elements are not.
+    /// Anything goes inside `extensions`, and only there may other namespaces appear.
+    fn assert_child_element_order(xml: &str) {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+        // Element name and position in its parent's sequence reached so far
+        let mut stack: Vec<(String, usize)> = Vec::new();
+        loop {
+            let (name, empty) = match reader.read_event().unwrap() {
+                Event::Start(e) => (String::from_utf8_lossy(e.name().as_ref()).to_string(), false),
+                Event::Empty(e) => (String::from_utf8_lossy(e.name().as_ref()).to_string(), true),
+                Event::End(_) => {
+                    stack.pop();
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            if stack.iter().any(|(parent, _)| parent == "extensions") {
+                if !empty {
+                    stack.push((name, 0));
+                }
+                continue;
+            }
+            match stack.last_mut() {
+                None => assert_eq!(name, "gpx"),
+                Some((parent, position)) => {
+                    let allowed = gpx_1_1_child_order(parent).unwrap_or_else(|| panic!("<{}> can't contain <{}>", parent, name));
+                    let index = allowed
+                        .iter()
+                        .position(|child| *child == name)
+                        .unwrap_or_else(|| panic!("<{}> not allowed in <{}>", name, parent));
+                    assert!(index >= *position, "<{}> out of order in <{}>", name, parent);
+                    *position = index;
+                }
+            }
+            if !empty {
+                stack.push((name, 0));
+            }
+        }
+    }
 
     #[test]
     fn test_parse_simple_gpx() {
@@ -433,6 +930,8 @@ mod tests {
         let route = GpxRoute {
             name: Some("Test Route".to_string()),
             desc: Some("A test route".to_string()),
+            uuid: None,
+            color: None,
             points: vec![
                 GpxRoutePoint {
                     name: Some("Start".to_string()),
@@ -442,6 +941,8 @@ mod tests {
                     time: None,
                     desc: None,
                     sym: None,
+                    uuid: None,
+                    extensions: Default::default(),
                 },
                 GpxRoutePoint {
                     name: Some("End".to_string()),
@@ -451,6 +952,8 @@ mod tests {
                     time: None,
                     desc: None,
                     sym: None,
+                    uuid: None,
+                    extensions: Default::default(),
                 },
             ],
         };
@@ -462,4 +965,179 @@ mod tests {
         assert!(xml.contains("Test Route"));
         assert!(xml.contains("37.8044"));
     }
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let route = GpxRoute {
+            name: Some("Passage".to_string()),
+            desc: None,
+            uuid: Some("route-uuid".to_string()),
+            color: None,
+            points: vec![GpxRoutePoint {
+                name: Some("Start".to_string()),
+                lat: -36.84,
+                lon: 174.76,
+                ele: None,
+                time: None,
+                desc: None,
+                sym: None,
+                uuid: Some("point-uuid".to_string()),
+                extensions: Default::default(),
+            }],
+        };
+
+        let xml = generate_route_gpx(route).unwrap();
+        assert!(xml.contains(VORTEXNAV_GPX_NAMESPACE));
+
+        let parsed = parse_gpx_string(&xml).unwrap();
+        assert_eq!(parsed.routes[0].uuid.as_deref(), Some("route-uuid"));
+        assert_eq!(parsed.routes[0].points[0].uuid.as_deref(), Some("point-uuid"));
+    }
+
+    #[test]
+    fn test_extensions_and_segments_round_trip() {
+        let point = |lat: f64, lon: f64, time: &str, extensions: GpxExtensions| GpxRoutePoint {
+            name: None,
+            lat,
+            lon,
+            ele: None,
+            time: Some(time.to_string()),
+            desc: None,
+            sym: None,
+            uuid: None,
+            extensions,
+        };
+        let logged = |depth: f64, speed: f64| GpxExtensions {
+            depth_m: Some(depth),
+            temperature_c: Some(18.5),
+            speed_kn: Some(speed),
+            course: Some(45.0),
+            ..Default::default()
+        };
+        let data = ParsedGpx {
+            waypoints: vec![GpxWaypoint {
+                name: Some("Bean Rock".to_string()),
+                lat: -36.83,
+                lon: 174.83,
+                ele: Some(1.0),
+                time: Some("2026-03-01T00:00:00Z".to_string()),
+                desc: Some("Lighthouse".to_string()),
+                sym: Some("light".to_string()),
+                uuid: Some("wp-uuid".to_string()),
+                extensions: GpxExtensions {
+                    depth_m: Some(4.2),
+                    display_mode: Some(GpxDisplayMode::Symbol),
+                    arrival_radius_nm: Some(0.1),
+                    ..Default::default()
+                },
+            }],
+            routes: vec![GpxRoute {
+                name: Some("Harbour".to_string()),
+                desc: None,
+                uuid: Some("route-uuid".to_string()),
+                color: Some("#c026d3".to_string()),
+                points: vec![point(-36.84, 174.74, "2026-03-01T00:00:00Z", Default::default())],
+            }],
+            tracks: vec![GpxTrack {
+                name: Some("Sunday".to_string()),
+                desc: None,
+                uuid: Some("track-uuid".to_string()),
+                color: Some("#0000ff".to_string()),
+                segments: vec![
+                    vec![point(-36.80, 174.80, "2026-03-01T01:00:00Z", logged(12.0, 6.0)), point(-36.79, 174.81, "2026-03-01T01:05:00Z", logged(11.0, 6.5))],
+                    vec![point(-36.70, 174.85, "2026-03-01T03:00:00Z", logged(8.0, 4.0))],
+                ],
+            }],
+        };
+
+        let xml = generate_gpx_document(data, Some("Export".to_string())).unwrap();
+        assert_child_element_order(&xml);
+        assert!(xml.contains("<gpxx:DisplayColor>Magenta</gpxx:DisplayColor>"));
+
+        let parsed = parse_gpx_string(&xml).unwrap();
+        let waypoint = &parsed.waypoints[0];
+        assert_eq!((waypoint.ele, waypoint.time.as_deref()), (Some(1.0), Some("2026-03-01T00:00:00Z")));
+        assert_eq!(waypoint.extensions.depth_m, Some(4.2));
+        assert_eq!(waypoint.extensions.display_mode, Some(GpxDisplayMode::Symbol));
+        assert_eq!(waypoint.extensions.arrival_radius_nm, Some(0.1));
+        // The exact colour survives alongside Garmin's nearest match
+        assert_eq!(parsed.routes[0].color.as_deref(), Some("#c026d3"));
+
+        let track = &parsed.tracks[0];
+        assert_eq!(track.color.as_deref(), Some("#0000ff"));
+        assert_eq!(track.segments.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+        let first = &track.segments[0][1].extensions;
+        assert!((first.speed_kn.unwrap() - 6.5).abs() < 1e-9);
+        assert_eq!((first.course, first.depth_m, first.temperature_c), (Some(45.0), Some(11.0), Some(18.5)));
+
+        // Stored segments come back as separate trksegs
+        let points = track_points_from_gpx(track);
+        assert_eq!(points.iter().map(|p| p.segment).collect::<Vec<_>>(), [0, 0, 1]);
+        let stored = TrackWithPoints { track: Track { name: "Sunday".to_string(), ..Track::default() }, points };
+        let xml = generate_track_gpx(&stored).unwrap();
+        assert_child_element_order(&xml);
+        assert_eq!(xml.matches("<trkseg>").count(), 2);
+        let reparsed = parse_gpx_string(&xml).unwrap();
+        let first = &reparsed.tracks[0].segments[0][1].extensions;
+        assert_eq!((first.depth_m, first.temperature_c), (Some(11.0), Some(18.5)));
+    }
+
+    #[test]
+    fn test_parse_garmin_and_opencpn_extensions() {
+        let gpx_content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="OpenCPN" xmlns="http://www.topografix.com/GPX/1/1"
+     xmlns:gpxx="http://www.garmin.com/xmlschemas/GpxExtensions/v3"
+     xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v2"
+     xmlns:opencpn="http://www.opencpn.org">
+    <wpt lat="-36.42" lon="174.83">
+        <name>Kawau</name>
+        <sym>anchorage</sym>
+        <extensions>
+            <opencpn:guid>abc-123</opencpn:guid>
+            <opencpn:viz_name>0</opencpn:viz_name>
+            <opencpn:arrival_radius>0.250</opencpn:arrival_radius>
+            <gpxx:WaypointExtension>
+                <gpxx:Temperature>17.5</gpxx:Temperature>
+                <gpxx:Depth>6.1</gpxx:Depth>
+            </gpxx:WaypointExtension>
+        </extensions>
+    </wpt>
+    <trk>
+        <name>Log</name>
+        <extensions>
+            <gpxx:TrackExtension><gpxx:DisplayColor>DarkGreen</gpxx:DisplayColor></gpxx:TrackExtension>
+        </extensions>
+        <trkseg>
+            <trkpt lat="-36.80" lon="174.80">
+                <time>2026-03-01T01:00:00Z</time>
+                <extensions>
+                    <gpxtpx:TrackPointExtension>
+                        <gpxtpx:wtemp>19.0</gpxtpx:wtemp>
+                        <gpxtpx:depth>15.0</gpxtpx:depth>
+                        <gpxtpx:speed>2.572222</gpxtpx:speed>
+                        <gpxtpx:course>90</gpxtpx:course>
+                    </gpxtpx:TrackPointExtension>
+                </extensions>
+            </trkpt>
+        </trkseg>
+        <trkseg>
+            <trkpt lat="-36.70" lon="174.85"><time>2026-03-01T02:00:00Z</time></trkpt>
+        </trkseg>
+    </trk>
+</gpx>"#;
+
+        let parsed = parse_gpx_string(gpx_content).unwrap();
+        let waypoint = &parsed.waypoints[0];
+        assert_eq!(waypoint.uuid.as_deref(), Some("abc-123"));
+        assert_eq!(waypoint.extensions.display_mode, Some(GpxDisplayMode::Symbol));
+        assert_eq!(waypoint.extensions.arrival_radius_nm, Some(0.25));
+        assert_eq!((waypoint.extensions.depth_m, waypoint.extensions.temperature_c), (Some(6.1), Some(17.5)));
+
+        let track = &parsed.tracks[0];
+        assert_eq!(track.color.as_deref(), Some("#006400"));
+        assert_eq!(track.segments.len(), 2);
+        let point = &track.segments[0][0].extensions;
+        assert!((point.speed_kn.unwrap() - 5.0).abs() < 1e-3);
+        assert_eq!((point.course, point.depth_m, point.temperature_c), (Some(90.0), Some(15.0), Some(19.0)));
+    }
 }
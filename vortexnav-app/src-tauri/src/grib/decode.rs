@@ -0,0 +1,1047 @@
+// GRIB Decoder
+// Supports GRIB1 and GRIB2 messages on regular lat/lon grids with simple packing,
+// plus GRIB2 complex packing (with and without spatial differencing)
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use super::{GribError, GribField, GribGrid, GribParameter};
+
+/// More points than any real forecast grid (a 0.05° global grid has 26 million).
+/// Bounds what a header can make us allocate when the values take no bits.
+const MAX_GRID_POINTS: usize = 1 << 25;
+
+/// Decode every supported field from a buffer containing one or more GRIB messages.
+/// Messages with unsupported grids or packing are skipped.
+pub fn decode_messages(data: &[u8]) -> Result<Vec<GribField>, GribError> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    let mut skipped = 0;
+
+    while let Some(start) = find_marker(data, pos) {
+        if start + 8 > data.len() {
+            break;
+        }
+
+        let edition = data[start + 7];
+        let length = match edition {
+            1 => read_u24(data, start + 4) as usize,
+            2 => {
+                if start + 16 > data.len() {
+                    break;
+                }
+                read_u64(data, start + 8) as usize
+            }
+            _ => {
+                pos = start + 4;
+                continue;
+            }
+        };
+
+        if length < 8 || start + length > data.len() {
+            return Err(GribError::InvalidData(format!(
+                "Truncated GRIB message at offset {}",
+                start
+            )));
+        }
+
+        let message = &data[start..start + length];
+        let result = match edition {
+            1 => decode_grib1(message).map(|f| fields.extend(f)),
+            _ => decode_grib2(message, &mut fields),
+        };
+
+        if let Err(e) = result {
+            match e {
+                GribError::Unsupported(msg) => {
+                    log::debug!("Skipping GRIB message at offset {}: {}", start, msg);
+                    skipped += 1;
+                }
+                other => return Err(other),
+            }
+        }
+
+        pos = start + length;
+    }
+
+    if skipped > 0 {
+        log::warn!("Skipped {} unsupported GRIB messages", skipped);
+    }
+
+    Ok(fields)
+}
+
+fn find_marker(data: &[u8], from: usize) -> Option<usize> {
+    if from >= data.len() {
+        return None;
+    }
+    data[from..]
+        .windows(4)
+        .position(|w| w == b"GRIB")
+        .map(|p| p + from)
+}
+
+// ============ Byte helpers ============
+
+fn read_u16(data: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes([data[off], data[off + 1]])
+}
+
+fn read_u24(data: &[u8], off: usize) -> u32 {
+    ((data[off] as u32) << 16) | ((data[off + 1] as u32) << 8) | data[off + 2] as u32
+}
+
+fn read_u32(data: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes([data[off], data[off + 1], data[off + 2], data[off + 3]])
+}
+
+fn read_u64(data: &[u8], off: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[off..off + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// GRIB stores signed integers as sign bit + magnitude, not two's complement
+fn sign_magnitude(value: u64, bits: u32) -> i64 {
+    let sign = 1u64 << (bits - 1);
+    let magnitude = (value & (sign - 1)) as i64;
+    if value & sign != 0 { -magnitude } else { magnitude }
+}
+
+fn read_i16_sm(data: &[u8], off: usize) -> i64 {
+    sign_magnitude(read_u16(data, off) as u64, 16)
+}
+
+fn read_i24_sm(data: &[u8], off: usize) -> i64 {
+    sign_magnitude(read_u24(data, off) as u64, 24)
+}
+
+fn read_i32_sm(data: &[u8], off: usize) -> i64 {
+    sign_magnitude(read_u32(data, off) as u64, 32)
+}
+
+/// IBM System/360 single precision float used by GRIB1 reference values
+fn ibm_to_f64(bits: u32) -> f64 {
+    let sign = if bits & 0x8000_0000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 24) & 0x7f) as i32;
+    let mantissa = (bits & 0x00ff_ffff) as f64 / 16_777_216.0;
+    sign * mantissa * 16f64.powi(exponent - 64)
+}
+
+/// Reads big-endian packed integers of arbitrary bit width
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> Result<u64, GribError> {
+        let mut value = 0u64;
+        for _ in 0..bits {
+            let byte = *self
+                .data
+                .get(self.pos / 8)
+                .ok_or_else(|| GribError::InvalidData("Packed data ended early".to_string()))?;
+            let bit = (byte >> (7 - (self.pos % 8))) & 1;
+            value = (value << 1) | bit as u64;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+fn forecast_offset(unit: u8, value: i64) -> Result<Duration, GribError> {
+    Ok(match unit {
+        0 => Duration::minutes(value),
+        1 => Duration::hours(value),
+        2 => Duration::days(value),
+        10 => Duration::hours(value * 3),
+        11 => Duration::hours(value * 6),
+        12 => Duration::hours(value * 12),
+        13 | 254 => Duration::seconds(value),
+        other => return Err(GribError::Unsupported(format!("time unit {}", other))),
+    })
+}
+
+fn make_time(year: i32, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Result<DateTime<Utc>, GribError> {
+    Utc.with_ymd_and_hms(year, month as u32, day as u32, hour as u32, minute as u32, second as u32)
+        .single()
+        .ok_or_else(|| GribError::InvalidData("Invalid reference time".to_string()))
+}
+
+/// Apply the bitmap (if any) and scanning order to produce a row-major value grid
+fn expand_values(
+    packed: Vec<f32>,
+    bitmap: Option<&[bool]>,
+    grid: &GribGrid,
+    j_consecutive: bool,
+) -> Result<Vec<f32>, GribError> {
+    let total = grid.len();
+    let mut values = match bitmap {
+        Some(mask) => {
+            let mut out = vec![f32::NAN; total];
+            let mut src = packed.into_iter();
+            for (slot, present) in out.iter_mut().zip(mask.iter()) {
+                if *present {
+                    *slot = src.next().unwrap_or(f32::NAN);
+                }
+            }
+            out
+        }
+        None => packed,
+    };
+
+    if values.len() < total {
+        return Err(GribError::InvalidData(format!(
+            "Expected {} values, found {}",
+            total,
+            values.len()
+        )));
+    }
+    values.truncate(total);
+
+    if j_consecutive {
+        let mut row_major = vec![f32::NAN; total];
+        for (k, v) in values.iter().enumerate() {
+            let i = k / grid.nj;
+            let j = k % grid.nj;
+            row_major[j * grid.ni + i] = *v;
+        }
+        values = row_major;
+    }
+
+    Ok(values)
+}
+
+/// Check a grid's point count before anything is sized from it
+fn check_grid(grid: &GribGrid) -> Result<(), GribError> {
+    match grid.ni.checked_mul(grid.nj) {
+        Some(points) if points <= MAX_GRID_POINTS => Ok(()),
+        _ => Err(GribError::InvalidData(format!("GRIB grid of {} x {} points", grid.ni, grid.nj))),
+    }
+}
+
+fn read_bitmap(data: &[u8], count: usize) -> Result<Vec<bool>, GribError> {
+    if count.div_ceil(8) > data.len() {
+        return Err(GribError::InvalidData(format!(
+            "Bitmap of {} bytes can't cover {} points",
+            data.len(),
+            count
+        )));
+    }
+    Ok((0..count).map(|k| data[k / 8] & (0x80 >> (k % 8)) != 0).collect())
+}
+
+fn accept_level(parameter: GribParameter, surface: u8, level: f64, grib1: bool) -> bool {
+    let (ground, height_above_ground, isobaric) = if grib1 { (1, 105, 100) } else { (1, 103, 100) };
+    match parameter {
+        GribParameter::WindU | GribParameter::WindV => {
+            surface == ground || (surface == height_above_ground && (level - 10.0).abs() < 0.5)
+        }
+        _ => surface != isobaric,
+    }
+}
+
+// ============ GRIB1 ============
+
+fn grib1_parameter(code: u8) -> Option<GribParameter> {
+    match code {
+        2 => Some(GribParameter::Pressure),
+        33 => Some(GribParameter::WindU),
+        34 => Some(GribParameter::WindV),
+        49 => Some(GribParameter::CurrentU),
+        50 => Some(GribParameter::CurrentV),
+        100 => Some(GribParameter::WaveHeight),
+        180 => Some(GribParameter::WindGust),
+        _ => None,
+    }
+}
+
+fn decode_grib1(msg: &[u8]) -> Result<Option<GribField>, GribError> {
+    let too_short = || GribError::InvalidData("GRIB1 message too short".to_string());
+
+    // Section 1: product definition
+    let pds = 8;
+    if msg.len() < pds + 28 {
+        return Err(too_short());
+    }
+    let pds_len = read_u24(msg, pds) as usize;
+    let flags = msg[pds + 7];
+    let Some(parameter) = grib1_parameter(msg[pds + 8]) else {
+        return Ok(None);
+    };
+    let level_type = msg[pds + 9];
+    let level = read_u16(msg, pds + 10) as f64;
+    if !accept_level(parameter, level_type, level, true) {
+        return Ok(None);
+    }
+
+    let century = msg[pds + 24] as i32;
+    let year = (century - 1) * 100 + msg[pds + 12] as i32;
+    let reference_time = make_time(year, msg[pds + 13], msg[pds + 14], msg[pds + 15], msg[pds + 16], 0)?;
+
+    let unit = msg[pds + 17];
+    let (p1, p2) = (msg[pds + 18] as i64, msg[pds + 19] as i64);
+    let step = match msg[pds + 20] {
+        0 | 1 => p1,
+        2..=5 => p2,
+        10 => (p1 << 8) | p2,
+        other => return Err(GribError::Unsupported(format!("GRIB1 time range {}", other))),
+    };
+    let valid_time = reference_time + forecast_offset(unit, step)?;
+    let decimal_scale = read_i16_sm(msg, pds + 26) as i32;
+
+    // Section 2: grid description (required)
+    if flags & 0x80 == 0 {
+        return Err(GribError::Unsupported("GRIB1 message without grid description".to_string()));
+    }
+    let gds = pds + pds_len;
+    if msg.len() < gds + 28 {
+        return Err(too_short());
+    }
+    let gds_len = read_u24(msg, gds) as usize;
+    if msg[gds + 5] != 0 {
+        return Err(GribError::Unsupported(format!("GRIB1 grid type {}", msg[gds + 5])));
+    }
+    let scan = msg[gds + 27];
+    let di = read_u16(msg, gds + 23) as f64 / 1000.0;
+    let dj = read_u16(msg, gds + 25) as f64 / 1000.0;
+    let grid = GribGrid {
+        ni: read_u16(msg, gds + 6) as usize,
+        nj: read_u16(msg, gds + 8) as usize,
+        lat1: read_i24_sm(msg, gds + 10) as f64 / 1000.0,
+        lon1: read_i24_sm(msg, gds + 13) as f64 / 1000.0,
+        dlat: if scan & 0x40 != 0 { dj } else { -dj },
+        dlon: if scan & 0x80 != 0 { -di } else { di },
+    };
+    check_grid(&grid)?;
+
+    // Section 3: optional bitmap
+    let mut next = gds + gds_len;
+    let bitmap = if flags & 0x40 != 0 {
+        if msg.len() < next + 6 {
+            return Err(too_short());
+        }
+        let bms_len = read_u24(msg, next) as usize;
+        if bms_len < 6 {
+            return Err(GribError::InvalidData(format!("Bad GRIB1 bitmap section length {}", bms_len)));
+        }
+        if read_u16(msg, next + 4) != 0 {
+            return Err(GribError::Unsupported("GRIB1 predefined bitmap".to_string()));
+        }
+        let bitmap = read_bitmap(&msg[next + 6..(next + bms_len).min(msg.len())], grid.len())?;
+        next += bms_len;
+        Some(bitmap)
+    } else {
+        None
+    };
+
+    // Section 4: binary data
+    if msg.len() < next + 11 {
+        return Err(too_short());
+    }
+    let bds_len = read_u24(msg, next) as usize;
+    if bds_len < 11 {
+        return Err(GribError::InvalidData(format!("Bad GRIB1 data section length {}", bds_len)));
+    }
+    let bds_flags = msg[next + 3];
+    if bds_flags & 0xf0 != 0 {
+        return Err(GribError::Unsupported("GRIB1 non-simple packing".to_string()));
+    }
+    let unused_bits = (bds_flags & 0x0f) as usize;
+    let binary_scale = read_i16_sm(msg, next + 4) as i32;
+    let reference = ibm_to_f64(read_u32(msg, next + 6));
+    let bits = msg[next + 10] as u32;
+    if bits >= 64 {
+        return Err(GribError::InvalidData(format!("GRIB1 packing width {} bits", bits)));
+    }
+
+    let count = bitmap
+        .as_ref()
+        .map(|b| b.iter().filter(|v| **v).count())
+        .unwrap_or(grid.len());
+    let data_end = (next + bds_len).min(msg.len());
+    let data = &msg[next + 11..data_end];
+    let available = if bits > 0 {
+        ((data.len() * 8).saturating_sub(unused_bits)) / bits as usize
+    } else {
+        count
+    };
+
+    let packed = unpack_simple(data, count.min(available), bits, reference, binary_scale, decimal_scale)?;
+    let values = expand_values(packed, bitmap.as_deref(), &grid, scan & 0x20 != 0)?;
+
+    Ok(Some(GribField { parameter, reference_time, valid_time, grid, values }))
+}
+
+// ============ GRIB2 ============
+
+fn grib2_parameter(discipline: u8, category: u8, number: u8) -> Option<GribParameter> {
+    match (discipline, category, number) {
+        (0, 2, 2) => Some(GribParameter::WindU),
+        (0, 2, 3) => Some(GribParameter::WindV),
+        (0, 2, 22) => Some(GribParameter::WindGust),
+        (0, 3, 1) => Some(GribParameter::Pressure),
+        (10, 0, 3) => Some(GribParameter::WaveHeight),
+        (10, 1, 2) => Some(GribParameter::CurrentU),
+        (10, 1, 3) => Some(GribParameter::CurrentV),
+        _ => None,
+    }
+}
+
+struct Product {
+    parameter: Option<GribParameter>,
+    valid_time: DateTime<Utc>,
+}
+
+enum Packing {
+    Simple {
+        reference: f64,
+        binary_scale: i32,
+        decimal_scale: i32,
+        bits: u32,
+    },
+    Complex {
+        reference: f64,
+        binary_scale: i32,
+        decimal_scale: i32,
+        bits: u32,
+        missing_management: u8,
+        groups: usize,
+        width_reference: u64,
+        width_bits: u32,
+        length_reference: u64,
+        length_increment: u64,
+        last_length: u64,
+        length_bits: u32,
+        spatial_order: u8,
+        extra_octets: u32,
+    },
+}
+
+struct Grib2State {
+    discipline: u8,
+    reference_time: Option<DateTime<Utc>>,
+    grid: Option<(GribGrid, bool)>,
+    product: Option<Product>,
+    packing: Option<(Packing, usize)>,
+    bitmap: Option<Vec<bool>>,
+}
+
+fn decode_grib2(msg: &[u8], fields: &mut Vec<GribField>) -> Result<(), GribError> {
+    let mut state = Grib2State {
+        discipline: msg[6],
+        reference_time: None,
+        grid: None,
+        product: None,
+        packing: None,
+        bitmap: None,
+    };
+
+    let mut pos = 16;
+    while pos + 4 <= msg.len() {
+        if &msg[pos..pos + 4] == b"7777" {
+            break;
+        }
+        if pos + 5 > msg.len() {
+            break;
+        }
+        let len = read_u32(msg, pos) as usize;
+        if len < 5 || pos + len > msg.len() {
+            return Err(GribError::InvalidData(format!("Bad GRIB2 section length {}", len)));
+        }
+        let section = &msg[pos..pos + len];
+
+        match section[4] {
+            1 => state.reference_time = Some(parse_identification(section)?),
+            3 => state.grid = Some(parse_grid(section)?),
+            4 => state.product = Some(parse_product(section, &state)?),
+            5 => state.packing = Some(parse_packing(section)?),
+            6 => match section.get(5).copied().unwrap_or(255) {
+                0 => {
+                    let count = state.grid.as_ref().map(|(g, _)| g.len()).unwrap_or(0);
+                    state.bitmap = Some(read_bitmap(&section[6..], count)?);
+                }
+                254 => {} // Reuse previously defined bitmap
+                255 => state.bitmap = None,
+                other => {
+                    return Err(GribError::Unsupported(format!("GRIB2 predefined bitmap {}", other)))
+                }
+            },
+            7 => {
+                if let Some(field) = decode_data_section(section, &state)? {
+                    fields.push(field);
+                }
+            }
+            _ => {}
+        }
+
+        pos += len;
+    }
+
+    Ok(())
+}
+
+fn parse_identification(section: &[u8]) -> Result<DateTime<Utc>, GribError> {
+    if section.len() < 19 {
+        return Err(GribError::InvalidData("Short GRIB2 identification section".to_string()));
+    }
+    make_time(
+        read_u16(section, 12) as i32,
+        section[14],
+        section[15],
+        section[16],
+        section[17],
+        section[18],
+    )
+}
+
+fn parse_grid(section: &[u8]) -> Result<(GribGrid, bool), GribError> {
+    if section.len() < 14 {
+        return Err(GribError::InvalidData("Short GRIB2 grid section".to_string()));
+    }
+    let template = read_u16(section, 12);
+    if template != 0 {
+        return Err(GribError::Unsupported(format!("GRIB2 grid template 3.{}", template)));
+    }
+    if section.len() < 72 {
+        return Err(GribError::InvalidData("Short GRIB2 lat/lon grid template".to_string()));
+    }
+
+    let basic_angle = read_u32(section, 38);
+    let subdivisions = read_u32(section, 42);
+    let unit = if basic_angle == 0 || subdivisions == 0 || subdivisions == u32::MAX {
+        1e-6
+    } else {
+        basic_angle as f64 / subdivisions as f64
+    };
+
+    let scan = section[71];
+    let di = read_u32(section, 63) as f64 * unit;
+    let dj = read_u32(section, 67) as f64 * unit;
+
+    let grid = GribGrid {
+        ni: read_u32(section, 30) as usize,
+        nj: read_u32(section, 34) as usize,
+        lat1: read_i32_sm(section, 46) as f64 * unit,
+        lon1: read_i32_sm(section, 50) as f64 * unit,
+        dlat: if scan & 0x40 != 0 { dj } else { -dj },
+        dlon: if scan & 0x80 != 0 { -di } else { di },
+    };
+    check_grid(&grid)?;
+
+    Ok((grid, scan & 0x20 != 0))
+}
+
+fn parse_product(section: &[u8], state: &Grib2State) -> Result<Product, GribError> {
+    if section.len() < 34 {
+        return Err(GribError::InvalidData("Short GRIB2 product section".to_string()));
+    }
+    let template = read_u16(section, 7);
+    if !matches!(template, 0 | 1 | 8 | 11) {
+        return Ok(Product { parameter: None, valid_time: Utc::now() });
+    }
+
+    let reference_time = state
+        .reference_time
+        .ok_or_else(|| GribError::InvalidData("Product defined before identification".to_string()))?;
+
+    let mut parameter = grib2_parameter(state.discipline, section[9], section[10]);
+    let surface = section[22];
+    let scale = sign_magnitude(section[23] as u64, 8) as i32;
+    let level = read_i32_sm(section, 24) as f64 / 10f64.powi(scale);
+    if let Some(p) = parameter {
+        if !accept_level(p, surface, level, false) {
+            parameter = None;
+        }
+    }
+
+    // Statistically processed fields are valid at the end of their interval
+    let valid_time = if template == 8 && section.len() >= 41 {
+        make_time(
+            read_u16(section, 34) as i32,
+            section[36],
+            section[37],
+            section[38],
+            section[39],
+            section[40],
+        )?
+    } else {
+        reference_time + forecast_offset(section[17], read_i32_sm(section, 18))?
+    };
+
+    Ok(Product { parameter, valid_time })
+}
+
+fn parse_packing(section: &[u8]) -> Result<(Packing, usize), GribError> {
+    if section.len() < 21 {
+        return Err(GribError::InvalidData("Short GRIB2 data representation section".to_string()));
+    }
+    let count = read_u32(section, 5) as usize;
+    let template = read_u16(section, 9);
+    let reference = f32::from_bits(read_u32(section, 11)) as f64;
+    let binary_scale = read_i16_sm(section, 15) as i32;
+    let decimal_scale = read_i16_sm(section, 17) as i32;
+    let bits = section[19] as u32;
+
+    let packing = match template {
+        0 => Packing::Simple { reference, binary_scale, decimal_scale, bits },
+        2 | 3 => {
+            let needed = if template == 3 { 49 } else { 47 };
+            if section.len() < needed {
+                return Err(GribError::InvalidData("Short GRIB2 complex packing template".to_string()));
+            }
+            Packing::Complex {
+                reference,
+                binary_scale,
+                decimal_scale,
+                bits,
+                missing_management: section[22],
+                groups: read_u32(section, 31) as usize,
+                width_reference: section[35] as u64,
+                width_bits: section[36] as u32,
+                length_reference: read_u32(section, 37) as u64,
+                length_increment: section[41] as u64,
+                last_length: read_u32(section, 42) as u64,
+                length_bits: section[46] as u32,
+                spatial_order: if template == 3 { section[47] } else { 0 },
+                extra_octets: if template == 3 { section[48] as u32 } else { 0 },
+            }
+        }
+        other => {
+            return Err(GribError::Unsupported(format!("GRIB2 data template 5.{}", other)));
+        }
+    };
+
+    Ok((packing, count))
+}
+
+fn decode_data_section(section: &[u8], state: &Grib2State) -> Result<Option<GribField>, GribError> {
+    let Some(product) = state.product.as_ref() else { return Ok(None) };
+    let Some(parameter) = product.parameter else { return Ok(None) };
+    let (grid, j_consecutive) = state
+        .grid
+        .as_ref()
+        .ok_or_else(|| GribError::InvalidData("Data section without grid".to_string()))?;
+    let (packing, count) = state
+        .packing
+        .as_ref()
+        .ok_or_else(|| GribError::InvalidData("Data section without packing".to_string()))?;
+    let reference_time = state
+        .reference_time
+        .ok_or_else(|| GribError::InvalidData("Data section without reference time".to_string()))?;
+
+    let data = &section[5..];
+    let packed = match packing {
+        Packing::Simple { reference, binary_scale, decimal_scale, bits } => {
+            unpack_simple(data, *count, *bits, *reference, *binary_scale, *decimal_scale)?
+        }
+        Packing::Complex { .. } => unpack_complex(data, *count, packing)?,
+    };
+
+    let values = expand_values(packed, state.bitmap.as_deref(), grid, *j_consecutive)?;
+
+    Ok(Some(GribField {
+        parameter,
+        reference_time,
+        valid_time: product.valid_time,
+        grid: grid.clone(),
+        values,
+    }))
+}
+
+// ============ Unpacking ============
+
+fn unpack_simple(
+    data: &[u8],
+    count: usize,
+    bits: u32,
+    reference: f64,
+    binary_scale: i32,
+    decimal_scale: i32,
+) -> Result<Vec<f32>, GribError> {
+    let bscale = 2f64.powi(binary_scale);
+    let dscale = 10f64.powi(-decimal_scale);
+
+    if count > MAX_GRID_POINTS {
+        return Err(GribError::InvalidData(format!("{} packed values", count)));
+    }
+    if bits == 0 {
+        return Ok(vec![(reference * dscale) as f32; count]);
+    }
+    if bits >= 64 {
+        return Err(GribError::InvalidData(format!("Packing width {} bits", bits)));
+    }
+    if count.checked_mul(bits as usize).is_none_or(|needed| needed > data.len() * 8) {
+        return Err(GribError::InvalidData(format!(
+            "{} values of {} bits don't fit in {} bytes",
+            count,
+            bits,
+            data.len()
+        )));
+    }
+
+    let mut reader = BitReader::new(data);
+    (0..count)
+        .map(|_| {
+            let x = reader.read(bits)? as f64;
+            Ok(((reference + x * bscale) * dscale) as f32)
+        })
+        .collect()
+}
+
+fn unpack_complex(data: &[u8], count: usize, packing: &Packing) -> Result<Vec<f32>, GribError> {
+    let Packing::Complex {
+        reference,
+        binary_scale,
+        decimal_scale,
+        bits,
+        missing_management,
+        groups,
+        width_reference,
+        width_bits,
+        length_reference,
+        length_increment,
+        last_length,
+        length_bits,
+        spatial_order,
+        extra_octets,
+    } = *packing
+    else {
+        return Err(GribError::InvalidData("Expected complex packing".to_string()));
+    };
+
+    // Widths of 64 bits or more can't be held, and mean the message is corrupt
+    let bad_width = |what: &str, width: u64| GribError::InvalidData(format!("Complex packing {} width {} bits", what, width));
+    if bits >= 64 {
+        return Err(bad_width("reference", bits as u64));
+    }
+    if width_bits >= 64 || length_bits >= 64 {
+        return Err(bad_width("group", width_bits.max(length_bits) as u64));
+    }
+    // Every group holds at least one value
+    if count > MAX_GRID_POINTS || groups > count {
+        return Err(GribError::InvalidData(format!("{} values in {} groups", count, groups)));
+    }
+
+    let mut reader = BitReader::new(data);
+
+    // Spatial differencing descriptors precede the group data
+    let mut first_values = [0i64; 2];
+    let mut min_difference = 0i64;
+    if spatial_order > 0 {
+        if spatial_order > 2 || extra_octets == 0 {
+            return Err(GribError::Unsupported(format!("spatial differencing order {}", spatial_order)));
+        }
+        if extra_octets > 8 {
+            return Err(GribError::InvalidData(format!("Spatial differencing uses {} octets", extra_octets)));
+        }
+        let width = extra_octets * 8;
+        for value in first_values.iter_mut().take(spatial_order as usize) {
+            *value = sign_magnitude(reader.read(width)?, width);
+        }
+        min_difference = sign_magnitude(reader.read(width)?, width);
+    }
+
+    let references = (0..groups).map(|_| reader.read(bits)).collect::<Result<Vec<_>, _>>()?;
+    reader.align();
+    let widths = (0..groups)
+        .map(|_| {
+            let width = reader.read(width_bits)? + width_reference;
+            if width >= 64 { Err(bad_width("group", width)) } else { Ok(width) }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    reader.align();
+    let mut lengths = (0..groups)
+        .map(|_| {
+            let length = reader.read(length_bits)?;
+            length
+                .checked_mul(length_increment)
+                .and_then(|l| l.checked_add(length_reference))
+                .ok_or_else(|| GribError::InvalidData(format!("Complex packing group length {} overflows", length)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    reader.align();
+    if let Some(last) = lengths.last_mut() {
+        *last = last_length;
+    }
+
+    let ref_missing = if bits > 0 { (1u64 << bits) - 1 } else { u64::MAX };
+    let mut raw: Vec<Option<i64>> = Vec::with_capacity(count);
+    for g in 0..groups {
+        let width = widths[g] as u32;
+        let group_missing = if width > 0 { (1u64 << width) - 1 } else { u64::MAX };
+        // Group lengths come from the file; never read past the declared value count
+        for _ in 0..lengths[g].min((count - raw.len()) as u64) {
+            let value = if width == 0 {
+                if missing_management >= 1 && references[g] == ref_missing {
+                    None
+                } else {
+                    Some(references[g] as i64)
+                }
+            } else {
+                let x = reader.read(width)?;
+                let missing = (missing_management >= 1 && x == group_missing)
+                    || (missing_management == 2 && x == group_missing - 1);
+                if missing {
+                    None
+                } else {
+                    let value = references[g]
+                        .checked_add(x)
+                        .filter(|v| *v <= i64::MAX as u64)
+                        .ok_or_else(|| GribError::InvalidData("Complex packing value overflows".to_string()))?;
+                    Some(value as i64)
+                }
+            };
+            raw.push(value);
+        }
+    }
+
+    // Undo spatial differencing over the non-missing values
+    if spatial_order > 0 {
+        let mut previous: Vec<i64> = Vec::with_capacity(2);
+        for slot in raw.iter_mut() {
+            let Some(value) = slot.as_mut() else { continue };
+            let n = previous.len();
+            if n < spatial_order as usize {
+                *value = first_values[n];
+            } else {
+                let predicted = if spatial_order == 1 {
+                    Some(previous[n - 1])
+                } else {
+                    previous[n - 1].checked_mul(2).and_then(|v| v.checked_sub(previous[n - 2]))
+                };
+                *value = predicted
+                    .and_then(|p| p.checked_add(min_difference))
+                    .and_then(|p| p.checked_add(*value))
+                    .ok_or_else(|| GribError::InvalidData("Spatial differencing overflows".to_string()))?;
+            }
+            previous.push(*value);
+            if previous.len() > 2 {
+                previous.remove(0);
+            }
+        }
+    }
+
+    let bscale = 2f64.powi(binary_scale);
+    let dscale = 10f64.powi(-decimal_scale);
+    Ok(raw
+        .into_iter()
+        .map(|v| match v {
+            Some(x) => ((reference + x as f64 * bscale) * dscale) as f32,
+            None => f32::NAN,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal GRIB2 message: 2x2 lat/lon grid, 10m U wind, simple packing
+    pub(crate) fn build_grib2_u_wind(values: [u16; 4], forecast_hours: u32) -> Vec<u8> {
+        let mut sec5 = vec![0u8; 21];
+        sec5[..4].copy_from_slice(&21u32.to_be_bytes());
+        sec5[4] = 5;
+        sec5[5..9].copy_from_slice(&4u32.to_be_bytes());
+        sec5[11..15].copy_from_slice(&(-5.0f32).to_bits().to_be_bytes());
+        // Decimal scale 1 => values in tenths
+        sec5[17..19].copy_from_slice(&1u16.to_be_bytes());
+        sec5[19] = 16;
+
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        build_grib2_u_wind_packed(sec5, &data, forecast_hours)
+    }
+
+    /// Same grid and product, with the given data representation section and packed data
+    fn build_grib2_u_wind_packed(sec5: Vec<u8>, data: &[u8], forecast_hours: u32) -> Vec<u8> {
+        let mut sec1 = vec![0u8; 21];
+        sec1[..4].copy_from_slice(&21u32.to_be_bytes());
+        sec1[4] = 1;
+        sec1[12..14].copy_from_slice(&2024u16.to_be_bytes());
+        sec1[14] = 1;
+        sec1[15] = 1;
+
+        let mut sec3 = vec![0u8; 72];
+        sec3[..4].copy_from_slice(&72u32.to_be_bytes());
+        sec3[4] = 3;
+        sec3[6..10].copy_from_slice(&4u32.to_be_bytes());
+        sec3[30..34].copy_from_slice(&2u32.to_be_bytes());
+        sec3[34..38].copy_from_slice(&2u32.to_be_bytes());
+        sec3[46..50].copy_from_slice(&1_000_000u32.to_be_bytes());
+        sec3[50..54].copy_from_slice(&0u32.to_be_bytes());
+        sec3[63..67].copy_from_slice(&1_000_000u32.to_be_bytes());
+        sec3[67..71].copy_from_slice(&1_000_000u32.to_be_bytes());
+
+        let mut sec4 = vec![0u8; 34];
+        sec4[..4].copy_from_slice(&34u32.to_be_bytes());
+        sec4[4] = 4;
+        sec4[9] = 2;
+        sec4[10] = 2;
+        sec4[17] = 1;
+        sec4[18..22].copy_from_slice(&forecast_hours.to_be_bytes());
+        sec4[22] = 103;
+        sec4[24..28].copy_from_slice(&10u32.to_be_bytes());
+
+        let sec6 = vec![0, 0, 0, 6, 6, 255];
+
+        let mut sec7 = ((5 + data.len()) as u32).to_be_bytes().to_vec();
+        sec7.push(7);
+        sec7.extend_from_slice(data);
+
+        let body: Vec<u8> = [sec1, sec3, sec4, sec5, sec6, sec7].concat();
+        let total = 16 + body.len() + 4;
+        let mut msg = b"GRIB".to_vec();
+        msg.extend_from_slice(&[0, 0, 0, 2]);
+        msg.extend_from_slice(&(total as u64).to_be_bytes());
+        msg.extend_from_slice(&body);
+        msg.extend_from_slice(b"7777");
+        msg
+    }
+
+    #[test]
+    fn test_decode_grib2_simple_packing() {
+        let mut data = build_grib2_u_wind([0, 50, 100, 150], 0);
+        data.extend(build_grib2_u_wind([150, 100, 50, 0], 6));
+
+        let fields = decode_messages(&data).unwrap();
+        assert_eq!(fields.len(), 2);
+
+        let f = &fields[0];
+        assert_eq!(f.parameter, GribParameter::WindU);
+        assert_eq!(f.grid.ni, 2);
+        assert_eq!(f.grid.dlat, -1.0);
+        // (R + X) / 10 with R = -5
+        assert!((f.values[0] - -0.5).abs() < 1e-6);
+        assert!((f.values[3] - 14.5).abs() < 1e-6);
+        assert_eq!((fields[1].valid_time - f.valid_time).num_hours(), 6);
+
+        // Counts the data can't back are rejected before anything is allocated
+        let mut huge_count = build_grib2_u_wind([0; 4], 0);
+        huge_count[148..152].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(decode_messages(&huge_count), Err(GribError::InvalidData(_))));
+        let mut huge_grid = build_grib2_u_wind([0; 4], 0);
+        huge_grid[67..71].copy_from_slice(&u32::MAX.to_be_bytes());
+        huge_grid[71..75].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(decode_messages(&huge_grid), Err(GribError::InvalidData(_))));
+    }
+
+    /// Template 5.3: complex packing with first-order spatial differencing
+    fn complex_packing_section(extra_octets: u8) -> Vec<u8> {
+        let mut sec5 = vec![0u8; 49];
+        sec5[..4].copy_from_slice(&49u32.to_be_bytes());
+        sec5[4] = 5;
+        sec5[5..9].copy_from_slice(&4u32.to_be_bytes());
+        sec5[9..11].copy_from_slice(&3u16.to_be_bytes());
+        sec5[19] = 1; // bits per group reference
+        sec5[31..35].copy_from_slice(&2u32.to_be_bytes()); // two groups
+        sec5[36] = 1; // bits per group width
+        sec5[37..41].copy_from_slice(&2u32.to_be_bytes()); // group length reference
+        sec5[41] = 1;
+        sec5[42..46].copy_from_slice(&2u32.to_be_bytes()); // last group length
+        sec5[46] = 1; // bits per scaled group length
+        sec5[47] = 1; // first-order differencing
+        sec5[48] = extra_octets;
+        sec5
+    }
+
+    #[test]
+    fn test_decode_grib2_complex_packing() {
+        // Values 10, 12, 15, 19: first value 10, differences 2, 3, 4 stored
+        // less their minimum of 2 as 0, 1, 2 in a constant group and a 1-bit group
+        let data = [
+            10, 2, // first value, minimum difference
+            0b0100_0000, // group references 0, 1
+            0b0100_0000, // group widths 0, 1
+            0b0000_0000, // scaled group lengths
+            0b0100_0000, // second group's values 0, 1
+        ];
+        let fields = decode_messages(&build_grib2_u_wind_packed(complex_packing_section(1), &data, 0)).unwrap();
+        assert_eq!(fields[0].values, vec![10.0, 12.0, 15.0, 19.0]);
+
+        // A bogus group length stops at the declared value count instead of filling memory
+        let mut huge_groups = complex_packing_section(1);
+        huge_groups[37..41].copy_from_slice(&u32::MAX.to_be_bytes());
+        let fields = decode_messages(&build_grib2_u_wind_packed(huge_groups, &data, 0)).unwrap();
+        assert_eq!(fields[0].values.len(), 4);
+
+        // Descriptors wider than 8 octets can't be read and must not panic
+        let corrupt = build_grib2_u_wind_packed(complex_packing_section(9), &[0; 32], 0);
+        assert!(matches!(decode_messages(&corrupt), Err(GribError::InvalidData(_))));
+    }
+
+    /// GRIB1 10m U wind on a 2x2 grid with a bitmap leaving out the second point
+    fn build_grib1_u_wind(bitmap_len: u32) -> Vec<u8> {
+        let mut pds = vec![0u8; 28];
+        pds[..3].copy_from_slice(&28u32.to_be_bytes()[1..]);
+        pds[7] = 0xc0; // grid and bitmap present
+        pds[8] = 33;
+        pds[9] = 105;
+        pds[10..12].copy_from_slice(&10u16.to_be_bytes());
+        pds[12] = 24;
+        pds[13] = 1;
+        pds[14] = 1;
+        pds[17] = 1;
+        pds[18] = 6;
+        pds[24] = 21;
+        pds[26..28].copy_from_slice(&1u16.to_be_bytes());
+
+        let mut gds = vec![0u8; 32];
+        gds[..3].copy_from_slice(&32u32.to_be_bytes()[1..]);
+        gds[6..8].copy_from_slice(&2u16.to_be_bytes());
+        gds[8..10].copy_from_slice(&2u16.to_be_bytes());
+        gds[10..13].copy_from_slice(&(0x80_0000u32 | 36_000).to_be_bytes()[1..]);
+        gds[13..16].copy_from_slice(&174_000u32.to_be_bytes()[1..]);
+        gds[23..25].copy_from_slice(&1000u16.to_be_bytes());
+        gds[25..27].copy_from_slice(&1000u16.to_be_bytes());
+
+        let mut bms = bitmap_len.to_be_bytes()[1..].to_vec();
+        bms.extend_from_slice(&[0, 0, 0, 0b1011_0000]);
+
+        let mut bds = 14u32.to_be_bytes()[1..].to_vec();
+        bds.push(0);
+        bds.extend_from_slice(&[0, 0]);
+        bds.extend_from_slice(&0x4264_0000u32.to_be_bytes()); // 100.0
+        bds.extend_from_slice(&[8, 0, 20, 40]);
+
+        let body = [pds, gds, bms, bds].concat();
+        let total = 8 + body.len() + 4;
+        let mut msg = b"GRIB".to_vec();
+        msg.extend_from_slice(&(total as u32).to_be_bytes()[1..]);
+        msg.push(1);
+        msg.extend_from_slice(&body);
+        msg.extend_from_slice(b"7777");
+        msg
+    }
+
+    #[test]
+    fn test_decode_grib1_with_bitmap() {
+        let fields = decode_messages(&build_grib1_u_wind(7)).unwrap();
+        let f = &fields[0];
+        assert_eq!(f.parameter, GribParameter::WindU);
+        assert_eq!((f.grid.lat1, f.grid.lon1, f.grid.dlat), (-36.0, 174.0, -1.0));
+        assert_eq!((f.valid_time - f.reference_time).num_hours(), 6);
+        // (100 + X) / 10, with the masked point missing
+        assert_eq!(f.values[0], 10.0);
+        assert!(f.values[1].is_nan());
+        assert_eq!(&f.values[2..], &[12.0, 14.0]);
+
+        // A bitmap section shorter than its own header is rejected, not sliced
+        assert!(matches!(decode_messages(&build_grib1_u_wind(3)), Err(GribError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_sign_magnitude_and_ibm_float() {
+        assert_eq!(sign_magnitude(0x8005, 16), -5);
+        assert_eq!(sign_magnitude(0x0005, 16), 5);
+        // 0x42640000 = 100.0 in IBM hexadecimal floating point
+        assert!((ibm_to_f64(0x4264_0000) - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,413 @@
+// GRIB Weather Data Module
+// Decodes GRIB1/GRIB2 forecast files and samples fields by position and time
+
+mod decode;
+mod renderer;
+mod route;
+
+pub use decode::*;
+pub use renderer::*;
+pub use route::*;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+/// Metres per second to knots
+pub const MS_TO_KNOTS: f64 = 1.943_844;
+
+/// Errors that can occur when loading or rendering GRIB data
+#[derive(Error, Debug)]
+pub enum GribError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid GRIB data: {0}")]
+    InvalidData(String),
+    #[error("Unsupported GRIB feature: {0}")]
+    Unsupported(String),
+    #[error("No usable fields found in GRIB file")]
+    NoFields,
+    #[error("Parameter not available: {0}")]
+    ParameterNotAvailable(String),
+    #[error("Render error: {0}")]
+    Render(String),
+}
+
+/// Forecast parameters VortexNav knows how to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GribParameter {
+    /// 10m wind, eastward component (m/s)
+    WindU,
+    /// 10m wind, northward component (m/s)
+    WindV,
+    /// Surface wind gust (m/s)
+    WindGust,
+    /// Mean sea level pressure (Pa)
+    Pressure,
+    /// Significant height of combined wind waves and swell (m)
+    WaveHeight,
+    /// Ocean current, eastward component (m/s)
+    CurrentU,
+    /// Ocean current, northward component (m/s)
+    CurrentV,
+}
+
+/// Regular latitude/longitude grid, values stored row by row (j rows of i columns)
+#[derive(Debug, Clone)]
+pub struct GribGrid {
+    pub ni: usize,
+    pub nj: usize,
+    /// Latitude of the first grid point
+    pub lat1: f64,
+    /// Longitude of the first grid point
+    pub lon1: f64,
+    /// Signed latitude increment between rows
+    pub dlat: f64,
+    /// Signed longitude increment between columns
+    pub dlon: f64,
+}
+
+impl GribGrid {
+    /// Number of grid points
+    pub fn len(&self) -> usize {
+        self.ni * self.nj
+    }
+
+    /// Whether the grid wraps all the way around the globe
+    pub fn is_global(&self) -> bool {
+        (self.ni as f64 * self.dlon.abs()) >= 359.9
+    }
+
+    /// Geographic bounds as [min_lon, min_lat, max_lon, max_lat]
+    pub fn bounds(&self) -> [f64; 4] {
+        let lat2 = self.lat1 + self.dlat * (self.nj.saturating_sub(1)) as f64;
+        let lon2 = self.lon1 + self.dlon * (self.ni.saturating_sub(1)) as f64;
+        let (min_lon, max_lon) = if self.is_global() {
+            (-180.0, 180.0)
+        } else {
+            let a = normalize_lon(self.lon1.min(lon2));
+            (a, a + (lon2 - self.lon1).abs())
+        };
+        [min_lon, self.lat1.min(lat2), max_lon, self.lat1.max(lat2)]
+    }
+
+    /// Fractional grid indices (i, j) for a position, if it falls inside the grid
+    fn fractional_index(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        if self.ni == 0 || self.nj == 0 || self.dlat == 0.0 || self.dlon == 0.0 {
+            return None;
+        }
+
+        let fj = (lat - self.lat1) / self.dlat;
+        if fj < -1e-9 || fj > (self.nj - 1) as f64 + 1e-9 {
+            return None;
+        }
+
+        let offset = if self.dlon > 0.0 { lon - self.lon1 } else { self.lon1 - lon };
+        let fi = offset.rem_euclid(360.0) / self.dlon.abs();
+        let max_i = if self.is_global() { self.ni as f64 } else { (self.ni - 1) as f64 + 1e-9 };
+        if fi > max_i {
+            return None;
+        }
+
+        Some((fi, fj.clamp(0.0, (self.nj - 1) as f64)))
+    }
+
+    /// Bilinear interpolation of a value grid at a position.
+    /// Missing (NaN) corners are ignored; returns None if all are missing.
+    pub fn interpolate(&self, values: &[f32], lat: f64, lon: f64) -> Option<f64> {
+        let (fi, fj) = self.fractional_index(lat, lon)?;
+
+        let i0 = fi.floor() as usize;
+        let j0 = fj.floor() as usize;
+        let ti = fi - i0 as f64;
+        let tj = fj - j0 as f64;
+
+        let wrap_i = |i: usize| -> Option<usize> {
+            if i < self.ni {
+                Some(i)
+            } else if self.is_global() {
+                Some(i % self.ni)
+            } else {
+                None
+            }
+        };
+
+        let corners = [
+            (wrap_i(i0), j0, (1.0 - ti) * (1.0 - tj)),
+            (wrap_i(i0 + 1), j0, ti * (1.0 - tj)),
+            (wrap_i(i0), j0 + 1, (1.0 - ti) * tj),
+            (wrap_i(i0 + 1), j0 + 1, ti * tj),
+        ];
+
+        let mut sum = 0.0;
+        let mut weight = 0.0;
+        for (i, j, w) in corners {
+            let Some(i) = i else { continue };
+            if j >= self.nj || w <= 0.0 {
+                continue;
+            }
+            let v = values[j * self.ni + i];
+            if v.is_nan() {
+                continue;
+            }
+            sum += v as f64 * w;
+            weight += w;
+        }
+
+        // Exactly on a grid node gives zero weight to every other corner
+        if weight <= 0.0 {
+            let i = wrap_i(fi.round() as usize)?;
+            let j = fj.round() as usize;
+            let v = *values.get(j * self.ni + i)?;
+            return if v.is_nan() { None } else { Some(v as f64) };
+        }
+
+        Some(sum / weight)
+    }
+}
+
+/// A single decoded GRIB field for one parameter at one forecast time
+#[derive(Debug, Clone)]
+pub struct GribField {
+    pub parameter: GribParameter,
+    pub reference_time: DateTime<Utc>,
+    pub valid_time: DateTime<Utc>,
+    pub grid: GribGrid,
+    /// Grid values in SI units, NaN where missing
+    pub values: Vec<f32>,
+}
+
+/// Two fields bracketing a requested time, with the interpolation weight of the second
+pub struct TimeSlice<'a> {
+    pub first: &'a GribField,
+    pub second: &'a GribField,
+    pub weight: f64,
+}
+
+impl TimeSlice<'_> {
+    /// Sample the slice at a position, interpolating in space then time
+    pub fn value_at(&self, lat: f64, lon: f64) -> Option<f64> {
+        let a = self.first.grid.interpolate(&self.first.values, lat, lon);
+        if self.weight <= 0.0 || std::ptr::eq(self.first, self.second) {
+            return a;
+        }
+        let b = self.second.grid.interpolate(&self.second.values, lat, lon);
+        match (a, b) {
+            (Some(a), Some(b)) => Some(a + (b - a) * self.weight),
+            (Some(a), None) if self.weight < 0.5 => Some(a),
+            (None, Some(b)) if self.weight >= 0.5 => Some(b),
+            _ => None,
+        }
+    }
+}
+
+/// Wind (or current) vector resolved to speed and direction
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VectorSample {
+    pub speed_kn: f64,
+    /// Direction in degrees true ("from" for wind, "towards" for current)
+    pub direction: f64,
+}
+
+/// All weather values available at one place and time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeatherSample {
+    pub tws_kn: Option<f64>,
+    pub twd: Option<f64>,
+    pub gust_kn: Option<f64>,
+    pub wave_height_m: Option<f64>,
+    pub pressure_hpa: Option<f64>,
+    pub current_speed_kn: Option<f64>,
+    pub current_set: Option<f64>,
+}
+
+/// A loaded GRIB file
+#[derive(Debug, Clone, Default)]
+pub struct GribDataset {
+    pub source_path: Option<String>,
+    pub fields: Vec<GribField>,
+}
+
+impl GribDataset {
+    /// Load and decode a GRIB file from disk
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GribError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let mut dataset = Self::from_bytes(&data)?;
+        dataset.source_path = Some(path.to_string_lossy().to_string());
+        Ok(dataset)
+    }
+
+    /// Decode all supported fields from raw GRIB bytes
+    pub fn from_bytes(data: &[u8]) -> Result<Self, GribError> {
+        let mut fields = decode_messages(data)?;
+        if fields.is_empty() {
+            return Err(GribError::NoFields);
+        }
+        fields.sort_by_key(|f| f.valid_time);
+        Ok(Self { source_path: None, fields })
+    }
+
+    /// Parameters present in the dataset
+    pub fn parameters(&self) -> Vec<GribParameter> {
+        let mut params: Vec<GribParameter> = Vec::new();
+        for field in &self.fields {
+            if !params.contains(&field.parameter) {
+                params.push(field.parameter);
+            }
+        }
+        params
+    }
+
+    pub fn has_parameter(&self, parameter: GribParameter) -> bool {
+        self.fields.iter().any(|f| f.parameter == parameter)
+    }
+
+    /// Distinct forecast valid times in ascending order
+    pub fn forecast_times(&self) -> Vec<DateTime<Utc>> {
+        let mut times: Vec<DateTime<Utc>> = self.fields.iter().map(|f| f.valid_time).collect();
+        times.sort();
+        times.dedup();
+        times
+    }
+
+    /// Combined bounds of every field as [min_lon, min_lat, max_lon, max_lat]
+    pub fn bounds(&self) -> Option<[f64; 4]> {
+        self.fields.iter().map(|f| f.grid.bounds()).reduce(|a, b| {
+            [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+        })
+    }
+
+    /// Find the fields bracketing a time for one parameter.
+    /// Times outside the forecast range are not extrapolated.
+    pub fn time_slice(&self, parameter: GribParameter, time: DateTime<Utc>) -> Option<TimeSlice<'_>> {
+        let fields: Vec<&GribField> = self.fields.iter().filter(|f| f.parameter == parameter).collect();
+        let first = *fields.first()?;
+        let last = *fields.last()?;
+
+        if time < first.valid_time || time > last.valid_time {
+            return None;
+        }
+
+        for pair in fields.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if time >= a.valid_time && time <= b.valid_time {
+                let span = (b.valid_time - a.valid_time).num_seconds() as f64;
+                let weight = if span > 0.0 {
+                    (time - a.valid_time).num_seconds() as f64 / span
+                } else {
+                    0.0
+                };
+                return Some(TimeSlice { first: a, second: b, weight });
+            }
+        }
+
+        Some(TimeSlice { first, second: first, weight: 0.0 })
+    }
+
+    /// Scalar value of a parameter at a position and time (SI units)
+    pub fn value_at(&self, parameter: GribParameter, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<f64> {
+        self.time_slice(parameter, time)?.value_at(lat, lon)
+    }
+
+    /// True wind speed (knots) and direction (degrees, from)
+    pub fn wind_at(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<VectorSample> {
+        let u = self.value_at(GribParameter::WindU, lat, lon, time)?;
+        let v = self.value_at(GribParameter::WindV, lat, lon, time)?;
+        Some(VectorSample {
+            speed_kn: (u * u + v * v).sqrt() * MS_TO_KNOTS,
+            direction: wind_direction_from(u, v),
+        })
+    }
+
+    /// Ocean current drift (knots) and set (degrees, towards)
+    pub fn current_at(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<VectorSample> {
+        let u = self.value_at(GribParameter::CurrentU, lat, lon, time)?;
+        let v = self.value_at(GribParameter::CurrentV, lat, lon, time)?;
+        Some(VectorSample {
+            speed_kn: (u * u + v * v).sqrt() * MS_TO_KNOTS,
+            direction: (u.atan2(v).to_degrees() + 360.0) % 360.0,
+        })
+    }
+
+    /// Everything the dataset knows about a position and time, in display units
+    pub fn sample(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> WeatherSample {
+        let wind = self.wind_at(lat, lon, time);
+        let current = self.current_at(lat, lon, time);
+        WeatherSample {
+            tws_kn: wind.map(|w| w.speed_kn),
+            twd: wind.map(|w| w.direction),
+            gust_kn: self
+                .value_at(GribParameter::WindGust, lat, lon, time)
+                .map(|g| g * MS_TO_KNOTS),
+            wave_height_m: self.value_at(GribParameter::WaveHeight, lat, lon, time),
+            pressure_hpa: self
+                .value_at(GribParameter::Pressure, lat, lon, time)
+                .map(|p| p / 100.0),
+            current_speed_kn: current.map(|c| c.speed_kn),
+            current_set: current.map(|c| c.direction),
+        }
+    }
+}
+
+/// Meteorological direction (degrees the wind blows from) of a u/v vector
+pub fn wind_direction_from(u: f64, v: f64) -> f64 {
+    ((-u).atan2(-v).to_degrees() + 360.0) % 360.0
+}
+
+/// Normalize longitude to -180..180
+pub fn normalize_lon(lon: f64) -> f64 {
+    let l = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    if l == -180.0 && lon > 0.0 { 180.0 } else { l }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn test_field(parameter: GribParameter, hour: u32, value: f32) -> GribField {
+        GribField {
+            parameter,
+            reference_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            valid_time: Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap(),
+            grid: GribGrid { ni: 3, nj: 3, lat1: 10.0, lon1: 0.0, dlat: -1.0, dlon: 1.0 },
+            values: vec![value; 9],
+        }
+    }
+
+    #[test]
+    fn test_grid_interpolation() {
+        let grid = GribGrid { ni: 2, nj: 2, lat1: 1.0, lon1: 0.0, dlat: -1.0, dlon: 1.0 };
+        let values = [0.0, 10.0, 20.0, 30.0];
+        assert_eq!(grid.interpolate(&values, 1.0, 0.0), Some(0.0));
+        assert!((grid.interpolate(&values, 0.5, 0.5).unwrap() - 15.0).abs() < 1e-9);
+        assert!(grid.interpolate(&values, 2.0, 0.5).is_none());
+        // Negative longitude maps onto a 0..360 grid only if covered
+        assert!(grid.interpolate(&values, 0.5, -0.5).is_none());
+    }
+
+    #[test]
+    fn test_time_interpolation_and_wind() {
+        let dataset = GribDataset {
+            source_path: None,
+            fields: vec![
+                test_field(GribParameter::WindU, 0, 0.0),
+                test_field(GribParameter::WindV, 0, -10.0),
+                test_field(GribParameter::WindU, 6, 0.0),
+                test_field(GribParameter::WindV, 6, -20.0),
+            ],
+        };
+
+        let t = Utc.with_ymd_and_hms(2024, 1, 1, 3, 0, 0).unwrap();
+        let wind = dataset.wind_at(9.0, 1.0, t).unwrap();
+        // Wind blowing towards the south comes from the north
+        assert!((wind.direction - 0.0).abs() < 1e-6);
+        assert!((wind.speed_kn - 15.0 * MS_TO_KNOTS).abs() < 1e-6);
+
+        let late = Utc.with_ymd_and_hms(2024, 1, 1, 7, 0, 0).unwrap();
+        assert!(dataset.wind_at(9.0, 1.0, late).is_none());
+    }
+}
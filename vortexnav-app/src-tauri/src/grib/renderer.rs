@@ -0,0 +1,517 @@
+// GRIB Overlay Renderer
+// Colour-ramped raster tiles and GeoJSON wind barbs/isobars for forecast fields
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use super::{wind_direction_from, GribDataset, GribError, GribParameter, TimeSlice, MS_TO_KNOTS};
+use crate::cm93::renderer::{tile_bounds, RenderConfig, TileRasterizer};
+use crate::cm93::{scale_for_zoom, GeoJsonFeature, GeoJsonGeometry, GeoJsonProperties, GeoJsonTile, TileInfo};
+
+/// Overlay layers that can be rendered from a GRIB dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GribLayer {
+    Wind,
+    Gust,
+    Pressure,
+    Waves,
+    Current,
+}
+
+impl GribLayer {
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "wind" => Some(Self::Wind),
+            "gust" => Some(Self::Gust),
+            "pressure" => Some(Self::Pressure),
+            "waves" => Some(Self::Waves),
+            "current" => Some(Self::Current),
+            _ => None,
+        }
+    }
+
+    /// Colour ramp stops as (value in display units, RGBA)
+    fn ramp(&self) -> &'static [(f64, [u8; 4])] {
+        match self {
+            // Knots, Beaufort-ish bands
+            Self::Wind | Self::Gust => &[
+                (0.0, [98, 113, 183, 255]),
+                (5.0, [57, 133, 204, 255]),
+                (10.0, [77, 175, 74, 255]),
+                (15.0, [166, 206, 57, 255]),
+                (20.0, [242, 208, 46, 255]),
+                (25.0, [245, 143, 39, 255]),
+                (30.0, [228, 61, 40, 255]),
+                (40.0, [170, 26, 72, 255]),
+                (50.0, [110, 20, 120, 255]),
+            ],
+            // hPa
+            Self::Pressure => &[
+                (960.0, [90, 30, 130, 255]),
+                (980.0, [60, 90, 190, 255]),
+                (1000.0, [90, 170, 210, 255]),
+                (1013.0, [230, 230, 230, 255]),
+                (1025.0, [240, 180, 90, 255]),
+                (1040.0, [200, 70, 50, 255]),
+            ],
+            // Metres
+            Self::Waves => &[
+                (0.0, [40, 80, 160, 255]),
+                (1.0, [50, 150, 200, 255]),
+                (2.0, [80, 190, 120, 255]),
+                (3.0, [230, 210, 60, 255]),
+                (5.0, [235, 120, 40, 255]),
+                (8.0, [190, 30, 60, 255]),
+            ],
+            // Knots
+            Self::Current => &[
+                (0.0, [30, 60, 120, 255]),
+                (0.5, [40, 120, 180, 255]),
+                (1.0, [60, 180, 160, 255]),
+                (2.0, [220, 200, 60, 255]),
+                (3.0, [220, 90, 40, 255]),
+            ],
+        }
+    }
+}
+
+/// Linear interpolation along a colour ramp, clamped at both ends
+fn ramp_color(stops: &[(f64, [u8; 4])], value: f64) -> [u8; 4] {
+    let (first, last) = (stops[0], stops[stops.len() - 1]);
+    if value <= first.0 {
+        return first.1;
+    }
+    if value >= last.0 {
+        return last.1;
+    }
+    for pair in stops.windows(2) {
+        let ((v0, c0), (v1, c1)) = (pair[0], pair[1]);
+        if value <= v1 {
+            let t = (value - v0) / (v1 - v0);
+            let mut out = [0u8; 4];
+            for k in 0..4 {
+                out[k] = (c0[k] as f64 + (c1[k] as f64 - c0[k] as f64) * t).round() as u8;
+            }
+            return out;
+        }
+    }
+    last.1
+}
+
+/// Samples one overlay layer at a fixed forecast time
+struct LayerSampler<'a> {
+    layer: GribLayer,
+    primary: TimeSlice<'a>,
+    secondary: Option<TimeSlice<'a>>,
+}
+
+impl<'a> LayerSampler<'a> {
+    fn new(dataset: &'a GribDataset, layer: GribLayer, time: DateTime<Utc>) -> Result<Self, GribError> {
+        let (primary, secondary) = match layer {
+            GribLayer::Wind => (GribParameter::WindU, Some(GribParameter::WindV)),
+            GribLayer::Current => (GribParameter::CurrentU, Some(GribParameter::CurrentV)),
+            GribLayer::Gust => (GribParameter::WindGust, None),
+            GribLayer::Pressure => (GribParameter::Pressure, None),
+            GribLayer::Waves => (GribParameter::WaveHeight, None),
+        };
+
+        let slice = |p: GribParameter| {
+            if !dataset.has_parameter(p) {
+                return Err(GribError::ParameterNotAvailable(format!("{:?}", p)));
+            }
+            dataset
+                .time_slice(p, time)
+                .ok_or_else(|| GribError::ParameterNotAvailable(format!("{:?} at {}", p, time.to_rfc3339())))
+        };
+
+        Ok(Self {
+            layer,
+            primary: slice(primary)?,
+            secondary: secondary.map(slice).transpose()?,
+        })
+    }
+
+    /// Value in display units (knots, hPa, metres)
+    fn value_at(&self, lat: f64, lon: f64) -> Option<f64> {
+        let a = self.primary.value_at(lat, lon)?;
+        match self.layer {
+            GribLayer::Wind | GribLayer::Current => {
+                let b = self.secondary.as_ref()?.value_at(lat, lon)?;
+                Some((a * a + b * b).sqrt() * MS_TO_KNOTS)
+            }
+            GribLayer::Gust => Some(a * MS_TO_KNOTS),
+            GribLayer::Pressure => Some(a / 100.0),
+            GribLayer::Waves => Some(a),
+        }
+    }
+
+    /// Raw u/v components (m/s) for vector layers
+    fn vector_at(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        let u = self.primary.value_at(lat, lon)?;
+        let v = self.secondary.as_ref()?.value_at(lat, lon)?;
+        Some((u, v))
+    }
+}
+
+/// Render a colour-ramped PNG tile for one layer at a forecast time.
+/// Pixels outside the GRIB coverage are left transparent.
+pub fn render_grib_tile(
+    dataset: &GribDataset,
+    layer: GribLayer,
+    time: DateTime<Utc>,
+    z: u8,
+    x: u32,
+    y: u32,
+) -> Result<Vec<u8>, GribError> {
+    let sampler = LayerSampler::new(dataset, layer, time)?;
+    let stops = layer.ramp();
+
+    let config = RenderConfig::default();
+    let size = config.tile_size;
+    let mut rasterizer = TileRasterizer::new(config);
+    rasterizer.clear();
+
+    let (min_lon, _, max_lon, _) = tile_bounds(z, x, y);
+    let world_px = size as f64 * 2f64.powi(z as i32);
+
+    for py in 0..size {
+        // Web Mercator rows are not linear in latitude
+        let merc_y = (y as usize * size as usize + py as usize) as f64 + 0.5;
+        let lat = (PI * (1.0 - 2.0 * merc_y / world_px)).sinh().atan().to_degrees();
+
+        for px in 0..size {
+            let lon = min_lon + (max_lon - min_lon) * (px as f64 + 0.5) / size as f64;
+            if let Some(value) = sampler.value_at(lat, lon) {
+                rasterizer.set_pixel(px as i32, py as i32, ramp_color(stops, value));
+            }
+        }
+    }
+
+    rasterizer.to_png().map_err(|e| GribError::Render(e.to_string()))
+}
+
+/// Build wind barbs, current arrows and isobars for a bounding box at a forecast time
+pub fn grib_vector_features(
+    dataset: &GribDataset,
+    time: DateTime<Utc>,
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+    zoom: u8,
+) -> GeoJsonTile {
+    let mut features = Vec::new();
+
+    // Sample on a grid snapped to multiples of the spacing so symbols stay put while panning
+    let spacing = barb_spacing(max_lon - min_lon);
+
+    if let Ok(wind) = LayerSampler::new(dataset, GribLayer::Wind, time) {
+        for (lat, lon) in snapped_points(min_lat, min_lon, max_lat, max_lon, spacing) {
+            if let Some((u, v)) = wind.vector_at(lat, lon) {
+                let speed = (u * u + v * v).sqrt() * MS_TO_KNOTS;
+                let direction = wind_direction_from(u, v);
+                features.push(vector_feature(lat, lon, "WNDBRB", "Wind barb", "wind_barbs", speed, direction, true));
+            }
+        }
+    }
+
+    if let Ok(current) = LayerSampler::new(dataset, GribLayer::Current, time) {
+        for (lat, lon) in snapped_points(min_lat, min_lon, max_lat, max_lon, spacing) {
+            if let Some((u, v)) = current.vector_at(lat, lon) {
+                let speed = (u * u + v * v).sqrt() * MS_TO_KNOTS;
+                let set = (u.atan2(v).to_degrees() + 360.0) % 360.0;
+                features.push(vector_feature(lat, lon, "CURENT", "Current arrow", "current_arrows", speed, set, false));
+            }
+        }
+    }
+
+    if let Ok(pressure) = LayerSampler::new(dataset, GribLayer::Pressure, time) {
+        let interval = if zoom >= 7 { 2.0 } else { 4.0 };
+        features.extend(isobars(&pressure, min_lat, min_lon, max_lat, max_lon, spacing / 2.0, interval));
+    }
+
+    GeoJsonTile {
+        tile_type: "FeatureCollection".to_string(),
+        features,
+        tile_info: TileInfo {
+            z: zoom,
+            x: 0,
+            y: 0,
+            scale: scale_for_zoom(zoom).to_char(),
+        },
+    }
+}
+
+/// Degrees between symbols, aiming for roughly 20 across the view
+fn barb_spacing(lon_span: f64) -> f64 {
+    let target = (lon_span.abs() / 20.0).max(0.01);
+    // Round to a 1/2/5 sequence
+    let magnitude = 10f64.powf(target.log10().floor());
+    let normalized = target / magnitude;
+    let step = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    step * magnitude
+}
+
+fn snapped_points(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64, spacing: f64) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    let mut lat = (min_lat / spacing).ceil() * spacing;
+    while lat <= max_lat {
+        let mut lon = (min_lon / spacing).ceil() * spacing;
+        while lon <= max_lon {
+            points.push((lat, lon));
+            lon += spacing;
+        }
+        lat += spacing;
+    }
+    points
+}
+
+#[allow(clippy::too_many_arguments)]
+fn vector_feature(
+    lat: f64,
+    lon: f64,
+    acronym: &str,
+    name: &str,
+    layer: &str,
+    speed_kn: f64,
+    direction: f64,
+    barb: bool,
+) -> GeoJsonFeature {
+    let mut attributes = HashMap::new();
+    attributes.insert("speed_kn".to_string(), serde_json::json!((speed_kn * 10.0).round() / 10.0));
+    attributes.insert("direction".to_string(), serde_json::json!(direction.round()));
+    if barb {
+        // Barbs are drawn in 5 knot steps
+        attributes.insert("barb".to_string(), serde_json::json!(((speed_kn / 5.0).round() * 5.0) as i64));
+    }
+
+    GeoJsonFeature {
+        feature_type: "Feature".to_string(),
+        geometry: GeoJsonGeometry {
+            geom_type: "Point".to_string(),
+            coordinates: serde_json::json!([lon, lat]),
+        },
+        properties: GeoJsonProperties {
+            obj_class: 0,
+            obj_acronym: acronym.to_string(),
+            obj_name: name.to_string(),
+            geom_type: "Point".to_string(),
+            layer: layer.to_string(),
+            depth: None,
+            name: None,
+            color: None,
+            attributes,
+        },
+    }
+}
+
+/// Contour the pressure field with marching squares, one MultiLineString per level
+fn isobars(
+    pressure: &LayerSampler,
+    min_lat: f64,
+    min_lon: f64,
+    max_lat: f64,
+    max_lon: f64,
+    step: f64,
+    interval: f64,
+) -> Vec<GeoJsonFeature> {
+    let cols = (((max_lon - min_lon) / step).ceil() as usize + 1).min(400);
+    let rows = (((max_lat - min_lat) / step).ceil() as usize + 1).min(400);
+    if cols < 2 || rows < 2 {
+        return Vec::new();
+    }
+    let dx = (max_lon - min_lon) / (cols - 1) as f64;
+    let dy = (max_lat - min_lat) / (rows - 1) as f64;
+
+    let mut grid = vec![f64::NAN; cols * rows];
+    let (mut lo, mut hi) = (f64::MAX, f64::MIN);
+    for r in 0..rows {
+        for c in 0..cols {
+            if let Some(v) = pressure.value_at(min_lat + r as f64 * dy, min_lon + c as f64 * dx) {
+                grid[r * cols + c] = v;
+                lo = lo.min(v);
+                hi = hi.max(v);
+            }
+        }
+    }
+    if lo > hi {
+        return Vec::new();
+    }
+
+    let mut features = Vec::new();
+    let mut level = (lo / interval).ceil() * interval;
+    while level <= hi {
+        let segments = contour_segments(&grid, cols, rows, level);
+        if !segments.is_empty() {
+            let coordinates: Vec<serde_json::Value> = segments
+                .iter()
+                .map(|((x0, y0), (x1, y1))| {
+                    serde_json::json!([
+                        [min_lon + x0 * dx, min_lat + y0 * dy],
+                        [min_lon + x1 * dx, min_lat + y1 * dy]
+                    ])
+                })
+                .collect();
+
+            let mut attributes = HashMap::new();
+            attributes.insert("pressure_hpa".to_string(), serde_json::json!(level));
+            attributes.insert("major".to_string(), serde_json::json!((level % 20.0).abs() < 1e-6));
+
+            features.push(GeoJsonFeature {
+                feature_type: "Feature".to_string(),
+                geometry: GeoJsonGeometry {
+                    geom_type: "MultiLineString".to_string(),
+                    coordinates: serde_json::Value::Array(coordinates),
+                },
+                properties: GeoJsonProperties {
+                    obj_class: 0,
+                    obj_acronym: "ISOBAR".to_string(),
+                    obj_name: "Isobar".to_string(),
+                    geom_type: "Line".to_string(),
+                    layer: "isobars".to_string(),
+                    depth: None,
+                    name: Some(format!("{:.0}", level)),
+                    color: None,
+                    attributes,
+                },
+            });
+        }
+        level += interval;
+    }
+
+    features
+}
+
+type Segment = ((f64, f64), (f64, f64));
+
+/// Marching squares over a row-major grid, returning segments in grid coordinates
+fn contour_segments(grid: &[f64], cols: usize, rows: usize, level: f64) -> Vec<Segment> {
+    let mut segments = Vec::new();
+
+    for r in 0..rows - 1 {
+        for c in 0..cols - 1 {
+            let v00 = grid[r * cols + c];
+            let v10 = grid[r * cols + c + 1];
+            let v11 = grid[(r + 1) * cols + c + 1];
+            let v01 = grid[(r + 1) * cols + c];
+            if v00.is_nan() || v10.is_nan() || v11.is_nan() || v01.is_nan() {
+                continue;
+            }
+
+            let case = (v00 > level) as u8
+                | ((v10 > level) as u8) << 1
+                | ((v11 > level) as u8) << 2
+                | ((v01 > level) as u8) << 3;
+
+            let (x, y) = (c as f64, r as f64);
+            let lerp = |a: f64, b: f64| if (b - a).abs() < 1e-12 { 0.5 } else { (level - a) / (b - a) };
+            // Edge crossing points: bottom, right, top, left
+            let edge = |e: u8| -> (f64, f64) {
+                match e {
+                    0 => (x + lerp(v00, v10), y),
+                    1 => (x + 1.0, y + lerp(v10, v11)),
+                    2 => (x + lerp(v01, v11), y + 1.0),
+                    _ => (x, y + lerp(v00, v01)),
+                }
+            };
+
+            let center_high = (v00 + v10 + v11 + v01) / 4.0 > level;
+            let pairs: &[(u8, u8)] = match case {
+                0 | 15 => &[],
+                1 | 14 => &[(3, 0)],
+                2 | 13 => &[(0, 1)],
+                3 | 12 => &[(3, 1)],
+                4 | 11 => &[(1, 2)],
+                6 | 9 => &[(0, 2)],
+                7 | 8 => &[(3, 2)],
+                5 if center_high => &[(0, 1), (3, 2)],
+                5 => &[(3, 0), (1, 2)],
+                10 if center_high => &[(3, 0), (1, 2)],
+                _ => &[(0, 1), (3, 2)],
+            };
+
+            for (a, b) in pairs {
+                segments.push((edge(*a), edge(*b)));
+            }
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grib::{GribField, GribGrid};
+    use chrono::TimeZone;
+
+    fn pressure_dataset() -> GribDataset {
+        // 1000 hPa in the west rising to 1020 hPa in the east
+        let grid = GribGrid { ni: 21, nj: 11, lat1: 10.0, lon1: 0.0, dlat: -1.0, dlon: 1.0 };
+        let values = (0..grid.len())
+            .map(|k| (100_000.0 + (k % 21) as f64 * 100.0) as f32)
+            .collect();
+        GribDataset {
+            source_path: None,
+            fields: vec![GribField {
+                parameter: GribParameter::Pressure,
+                reference_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                valid_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                grid,
+                values,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_ramp_color() {
+        let stops = GribLayer::Wind.ramp();
+        assert_eq!(ramp_color(stops, -1.0), stops[0].1);
+        assert_eq!(ramp_color(stops, 100.0), stops[stops.len() - 1].1);
+        let mid = ramp_color(&[(0.0, [0, 0, 0, 255]), (10.0, [100, 200, 0, 255])], 5.0);
+        assert_eq!(mid, [50, 100, 0, 255]);
+    }
+
+    #[test]
+    fn test_isobars_follow_gradient() {
+        let dataset = pressure_dataset();
+        let time = dataset.forecast_times()[0];
+        let tile = grib_vector_features(&dataset, time, 1.0, 1.0, 9.0, 19.0, 6);
+
+        let levels: Vec<f64> = tile
+            .features
+            .iter()
+            .filter(|f| f.properties.layer == "isobars")
+            .map(|f| f.properties.attributes["pressure_hpa"].as_f64().unwrap())
+            .collect();
+        assert_eq!(levels, vec![1004.0, 1008.0, 1012.0, 1016.0]);
+
+        // The 1008 hPa line runs north-south along 8°E
+        let isobar = tile.features.iter().find(|f| f.properties.name.as_deref() == Some("1008")).unwrap();
+        for segment in isobar.geometry.coordinates.as_array().unwrap() {
+            for point in segment.as_array().unwrap() {
+                assert!((point[0].as_f64().unwrap() - 8.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_tile_requires_parameter() {
+        let dataset = pressure_dataset();
+        let time = dataset.forecast_times()[0];
+        assert!(render_grib_tile(&dataset, GribLayer::Wind, time, 4, 8, 7).is_err());
+        let png = render_grib_tile(&dataset, GribLayer::Pressure, time, 4, 8, 7).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
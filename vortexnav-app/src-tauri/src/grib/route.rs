@@ -0,0 +1,208 @@
+// GRIB Route Weather
+// Forecast conditions along a route's ETA table and departure window comparison
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{GribDataset, WeatherSample};
+use crate::database::Waypoint;
+use crate::navigation::{angle_difference, build_eta_table, calculate_statistics, time_after, RouteEtaPoint};
+
+/// True wind angle below which a point counts as upwind sailing
+const UPWIND_TWA: f64 = 45.0;
+
+/// Forecast at one point of the route ETA table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteWeatherPoint {
+    #[serde(flatten)]
+    pub point: RouteEtaPoint,
+    #[serde(flatten)]
+    pub weather: WeatherSample,
+    /// True wind angle relative to the course (0-180, degrees)
+    pub twa: Option<f64>,
+}
+
+/// Worst-case conditions over a passage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouteWeatherSummary {
+    pub max_tws_kn: Option<f64>,
+    pub avg_tws_kn: Option<f64>,
+    pub max_gust_kn: Option<f64>,
+    pub max_wave_height_m: Option<f64>,
+    pub min_pressure_hpa: Option<f64>,
+    /// Fraction of points with the wind forward of the upwind limit
+    pub upwind_fraction: f64,
+    /// Fraction of points inside the forecast area and time range
+    pub coverage: f64,
+}
+
+/// Forecast along a route for one departure time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteWeatherReport {
+    pub departure_time: String,
+    pub arrival_time: String,
+    pub points: Vec<RouteWeatherPoint>,
+    pub summary: RouteWeatherSummary,
+}
+
+/// One candidate departure time in a weather window comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepartureWindow {
+    pub departure_time: String,
+    pub arrival_time: String,
+    pub summary: RouteWeatherSummary,
+    /// Lower is better
+    pub score: f64,
+    pub best: bool,
+}
+
+/// Sample the forecast at every point of an ETA table
+pub fn route_weather(dataset: &GribDataset, points: &[RouteEtaPoint], departure: DateTime<Utc>) -> RouteWeatherReport {
+    let weather_points: Vec<RouteWeatherPoint> = points
+        .iter()
+        .map(|p| {
+            let weather = dataset.sample(p.lat, p.lon, time_after(departure, p.hours_from_start));
+            let twa = weather.twd.map(|twd| angle_difference(twd, p.bearing).abs());
+            RouteWeatherPoint { point: p.clone(), weather, twa }
+        })
+        .collect();
+
+    let arrival_hours = points.last().map(|p| p.hours_from_start).unwrap_or(0.0);
+
+    RouteWeatherReport {
+        departure_time: departure.to_rfc3339(),
+        arrival_time: time_after(departure, arrival_hours).to_rfc3339(),
+        summary: summarize(&weather_points),
+        points: weather_points,
+    }
+}
+
+fn summarize(points: &[RouteWeatherPoint]) -> RouteWeatherSummary {
+    let max = |values: Vec<f64>| values.into_iter().reduce(f64::max);
+    let tws: Vec<f64> = points.iter().filter_map(|p| p.weather.tws_kn).collect();
+    let total = points.len().max(1) as f64;
+
+    RouteWeatherSummary {
+        max_tws_kn: max(tws.clone()),
+        avg_tws_kn: if tws.is_empty() { None } else { Some(tws.iter().sum::<f64>() / tws.len() as f64) },
+        max_gust_kn: max(points.iter().filter_map(|p| p.weather.gust_kn).collect()),
+        max_wave_height_m: max(points.iter().filter_map(|p| p.weather.wave_height_m).collect()),
+        min_pressure_hpa: points
+            .iter()
+            .filter_map(|p| p.weather.pressure_hpa)
+            .reduce(f64::min),
+        upwind_fraction: points.iter().filter(|p| p.twa.is_some_and(|a| a < UPWIND_TWA)).count() as f64 / total,
+        coverage: tws.len() as f64 / total,
+    }
+}
+
+/// Comfort score for a passage: penalises strong wind and gusts, big seas,
+/// beating to windward, motoring in calms and legs outside the forecast
+fn score(summary: &RouteWeatherSummary) -> f64 {
+    let wind = summary.max_gust_kn.or(summary.max_tws_kn).unwrap_or(0.0);
+    let calm = summary.avg_tws_kn.map(|a| (6.0 - a).max(0.0) * 2.0).unwrap_or(0.0);
+    wind
+        + summary.max_wave_height_m.unwrap_or(0.0) * 5.0
+        + summary.upwind_fraction * 20.0
+        + calm
+        + (1.0 - summary.coverage) * 100.0
+}
+
+/// Compare forecast conditions for a route over several departure times.
+/// Results are in departure order with the lowest-scoring window marked best.
+pub fn compare_departures(
+    dataset: &GribDataset,
+    waypoints: &[Waypoint],
+    speed_kn: f64,
+    departures: &[DateTime<Utc>],
+    interval_hours: Option<f64>,
+) -> Vec<DepartureWindow> {
+    let stats = calculate_statistics(waypoints, speed_kn);
+
+    let mut windows: Vec<DepartureWindow> = departures
+        .iter()
+        .map(|departure| {
+            let table = build_eta_table(waypoints, &stats, speed_kn, *departure, interval_hours);
+            let report = route_weather(dataset, &table, *departure);
+            DepartureWindow {
+                departure_time: report.departure_time,
+                arrival_time: report.arrival_time,
+                score: score(&report.summary),
+                summary: report.summary,
+                best: false,
+            }
+        })
+        .collect();
+
+    if let Some(best) = windows
+        .iter_mut()
+        .filter(|w| w.summary.coverage > 0.0)
+        .min_by(|a, b| a.score.total_cmp(&b.score))
+    {
+        best.best = true;
+    }
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grib::{GribField, GribGrid, GribParameter};
+    use chrono::TimeZone;
+
+    /// Northerly wind of 10 m/s at 00Z easing to 2 m/s by 12Z
+    fn easing_northerly() -> GribDataset {
+        let grid = GribGrid { ni: 5, nj: 5, lat1: 4.0, lon1: 0.0, dlat: -1.0, dlon: 1.0 };
+        let field = |parameter, hour, value: f32| GribField {
+            parameter,
+            reference_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            valid_time: Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap(),
+            grid: grid.clone(),
+            values: vec![value; grid.len()],
+        };
+        GribDataset {
+            source_path: None,
+            fields: vec![
+                field(GribParameter::WindU, 0, 0.0),
+                field(GribParameter::WindV, 0, -10.0),
+                field(GribParameter::WindU, 12, 0.0),
+                field(GribParameter::WindV, 12, -2.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_route_weather_upwind() {
+        let dataset = easing_northerly();
+        let waypoints = vec![Waypoint::at("", 0.0, 1.0), Waypoint::at("", 1.0, 1.0)];
+        let stats = calculate_statistics(&waypoints, 6.0);
+        let departure = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let table = build_eta_table(&waypoints, &stats, 6.0, departure, Some(2.0));
+
+        let report = route_weather(&dataset, &table, departure);
+        assert_eq!(report.points.len(), table.len());
+        // Heading north into a northerly is dead upwind
+        assert!(report.points[0].twa.unwrap() < 1.0);
+        assert_eq!(report.summary.upwind_fraction, 1.0);
+        assert_eq!(report.summary.coverage, 1.0);
+    }
+
+    #[test]
+    fn test_compare_departures_prefers_lighter_wind() {
+        let dataset = easing_northerly();
+        // Heading south, downwind, so only wind strength matters
+        let waypoints = vec![Waypoint::at("", 1.0, 1.0), Waypoint::at("", 0.5, 1.0)];
+        let departures: Vec<DateTime<Utc>> = [0, 3, 6, 20]
+            .iter()
+            .map(|h| Utc.with_ymd_and_hms(2024, 1, 1, *h, 0, 0).unwrap())
+            .collect();
+
+        let windows = compare_departures(&dataset, &waypoints, 6.0, &departures, Some(1.0));
+        assert_eq!(windows.len(), 4);
+        // Outside the forecast entirely
+        assert_eq!(windows[3].summary.coverage, 0.0);
+        assert!(!windows[3].best);
+        assert!(windows[2].best);
+    }
+}
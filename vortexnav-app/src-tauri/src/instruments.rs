@@ -0,0 +1,108 @@
+// Instrument data
+// Depth, wind, environment and AIS targets received alongside the GPS
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::nmea::GpsData;
+
+pub const METERS_PER_SECOND_TO_KNOTS: f64 = 1.943_844;
+
+/// AIS targets not heard from for this long are dropped
+const AIS_TARGET_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Own-vessel data from one Signal K delta or NMEA 2000 message
+#[derive(Debug, Default)]
+pub struct OwnVesselUpdate {
+    /// Fields of GpsData present in the message; everything else is None
+    pub gps: GpsData,
+    pub is_fix: bool,
+    /// Heading and speed through the water for dead reckoning
+    pub heading: Option<f64>,
+    pub water_speed: Option<f64>,
+}
+
+/// Radians to degrees, 0..360
+pub fn degrees(radians: f64) -> f64 {
+    radians.to_degrees().rem_euclid(360.0)
+}
+
+/// Relative angle in degrees, -180..180
+pub fn relative_degrees(radians: f64) -> f64 {
+    let deg = radians.to_degrees().rem_euclid(360.0);
+    if deg > 180.0 { deg - 360.0 } else { deg }
+}
+
+pub fn knots(meters_per_second: f64) -> f64 {
+    meters_per_second * METERS_PER_SECOND_TO_KNOTS
+}
+
+// Latest instrument readings for our own vessel
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct InstrumentData {
+    pub depth_m: Option<f64>,                // Below transducer
+    pub depth_below_keel_m: Option<f64>,
+    pub water_temp_c: Option<f64>,
+    pub air_temp_c: Option<f64>,
+    pub pressure_mb: Option<f64>,
+    pub humidity_pct: Option<f64>,
+    pub apparent_wind_angle: Option<f64>,    // Degrees, positive to starboard
+    pub apparent_wind_speed_kn: Option<f64>,
+    pub true_wind_angle: Option<f64>,        // Degrees, positive to starboard
+    pub true_wind_direction: Option<f64>,    // Degrees true
+    pub true_wind_speed_kn: Option<f64>,
+    pub speed_through_water_kn: Option<f64>,
+    pub heading_magnetic: Option<f64>,
+    pub magnetic_variation: Option<f64>,     // Degrees, east positive
+    pub rate_of_turn: Option<f64>,           // Degrees per minute
+    pub log_nm: Option<f64>,
+    pub updated_at: Option<String>,
+}
+
+// Another vessel received over AIS
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AisTarget {
+    pub id: String,
+    pub mmsi: Option<String>,
+    pub name: Option<String>,
+    pub call_sign: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub sog_knots: Option<f64>,
+    pub cog: Option<f64>,
+    pub heading: Option<f64>,
+    pub ship_type: Option<String>,
+    pub nav_state: Option<String>,
+    pub last_update: Option<String>,
+    #[serde(skip)]
+    pub seen_at: Option<Instant>,
+}
+
+/// Instruments and AIS targets shared between the reader thread and commands
+#[derive(Debug, Default)]
+pub struct VesselData {
+    pub instruments: InstrumentData,
+    pub ais_targets: HashMap<String, AisTarget>,
+}
+
+impl VesselData {
+    /// Target for an AIS context, created on first sight
+    pub fn ais_target(&mut self, id: &str, now: Instant) -> &mut AisTarget {
+        let target = self.ais_targets.entry(id.to_string()).or_insert_with(|| AisTarget {
+            id: id.to_string(),
+            ..Default::default()
+        });
+        target.seen_at = Some(now);
+        target
+    }
+
+    /// Drop targets that have gone quiet and return the rest
+    pub fn current_ais_targets(&mut self, now: Instant) -> Vec<AisTarget> {
+        self.ais_targets
+            .retain(|_, t| t.seen_at.is_none_or(|seen| now.saturating_duration_since(seen) < AIS_TARGET_TIMEOUT));
+        let mut targets: Vec<AisTarget> = self.ais_targets.values().cloned().collect();
+        targets.sort_by(|a, b| a.id.cmp(&b.id));
+        targets
+    }
+}
@@ -0,0 +1,704 @@
+// KML/KMZ parser and generator for exchanging routes with Google Earth
+// Placemarks map onto the same waypoint, route and track structures as GPX
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::gpx::{GpxRoute, GpxRoutePoint, GpxTrack, GpxWaypoint, ParsedGpx};
+
+/// Main document inside a KMZ archive
+const KMZ_DOC_ENTRY: &str = "doc.kml";
+/// Largest document unpacked from a KMZ, so a small archive can't exhaust memory
+const MAX_KMZ_DOC_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Google Earth icons standing in for the app's waypoint symbols
+const ICON_BASE_URL: &str = "http://maps.google.com/mapfiles/kml/shapes/";
+const SYMBOL_ICONS: &[(&str, &str)] = &[
+    ("anchor", "sailing"),
+    ("harbor", "marina"),
+    ("fuel", "gas_stations"),
+    ("danger", "caution"),
+    ("fishing", "fishing"),
+    ("dive", "water"),
+    ("beach", "swimming"),
+    ("fix", "star"),
+];
+
+// ExtendedData keys written on export. A folder typed "route" holds the route
+// line plus one placemark per route point, the way GPSBabel lays routes out.
+const DATA_TYPE: &str = "type";
+const DATA_UUID: &str = "uuid";
+const DATA_SYMBOL: &str = "symbol";
+
+#[derive(Debug, Default, Clone)]
+struct Style {
+    line_color: Option<String>,
+    icon: Option<String>,
+}
+
+/// Inline style or a `#id` reference, resolved once the whole document is read
+#[derive(Debug, Default, Clone)]
+struct StyleRef {
+    url: Option<String>,
+    inline: Style,
+}
+
+#[derive(Debug, Default)]
+struct Placemark {
+    name: Option<String>,
+    desc: Option<String>,
+    style: StyleRef,
+    data: HashMap<String, String>,
+    points: Vec<GpxRoutePoint>,
+    lines: Vec<Vec<GpxRoutePoint>>,
+    tracks: Vec<Vec<GpxRoutePoint>>,
+}
+
+#[derive(Debug, Default)]
+struct Folder {
+    name: Option<String>,
+    desc: Option<String>,
+    data: HashMap<String, String>,
+    route_points: Vec<(GpxRoutePoint, StyleRef)>,
+    route_line: Option<(Vec<GpxRoutePoint>, StyleRef)>,
+}
+
+impl Folder {
+    fn is_route(&self) -> bool {
+        self.data.get(DATA_TYPE).map(String::as_str) == Some("route")
+    }
+}
+
+#[derive(Debug, Default)]
+struct KmlParser {
+    styles: HashMap<String, Style>,
+    style_maps: HashMap<String, String>,
+    folders: Vec<Folder>,
+    placemark: Option<Placemark>,
+    style: Option<(Option<String>, Style)>,
+    style_map: Option<(String, Option<String>, Option<String>)>,
+    data_name: Option<String>,
+    track_times: Vec<String>,
+    track_coords: Vec<GpxRoutePoint>,
+    waypoints: Vec<(GpxWaypoint, StyleRef)>,
+    // Route style, then one style per point when the points came from placemarks
+    routes: Vec<(GpxRoute, StyleRef, Vec<StyleRef>)>,
+    tracks: Vec<(GpxTrack, StyleRef)>,
+}
+
+/// Parse a KML file, or a KMZ archive holding one
+pub fn parse_kml_file(path: &Path) -> Result<ParsedGpx, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read KML file: {}", e))?;
+    if bytes.starts_with(b"PK") {
+        return parse_kmz(bytes);
+    }
+    let content = String::from_utf8(bytes).map_err(|e| format!("KML file is not UTF-8: {}", e))?;
+    parse_kml_string(&content)
+}
+
+fn parse_kmz(bytes: Vec<u8>) -> Result<ParsedGpx, String> {
+    let mut zip = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Failed to open KMZ archive: {}", e))?;
+    // doc.kml by convention, otherwise the first KML file in the archive
+    let entry = match zip.file_names().any(|name| name == KMZ_DOC_ENTRY) {
+        true => KMZ_DOC_ENTRY.to_string(),
+        false => zip
+            .file_names()
+            .find(|name| name.to_lowercase().ends_with(".kml"))
+            .map(str::to_string)
+            .ok_or("KMZ archive contains no KML document")?,
+    };
+    let mut content = String::new();
+    zip.by_name(&entry)
+        .map_err(|e| format!("Failed to read {} from KMZ: {}", entry, e))?
+        .take(MAX_KMZ_DOC_BYTES + 1)
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read {} from KMZ: {}", entry, e))?;
+    if content.len() as u64 > MAX_KMZ_DOC_BYTES {
+        return Err(format!("{} in KMZ is larger than {} MB", entry, MAX_KMZ_DOC_BYTES / (1024 * 1024)));
+    }
+    parse_kml_string(&content)
+}
+
+/// Parse KML content. Point placemarks become waypoints, single lines become
+/// routes and gx:Tracks or multi-line placemarks become tracks.
+pub fn parse_kml_string(content: &str) -> Result<ParsedGpx, String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut parser = KmlParser { folders: vec![Folder::default()], ..Default::default() };
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = local_name(&e);
+                parser.start(&name, &e);
+                path.push(name);
+                text.clear();
+            }
+            Ok(Event::Empty(e)) => {
+                let name = local_name(&e);
+                parser.start(&name, &e);
+                path.push(name);
+                parser.end(&path, "");
+                path.pop();
+            }
+            Ok(Event::Text(e)) => {
+                text.push_str(&e.unescape().map_err(|e| format!("Failed to parse KML: {}", e))?);
+            }
+            Ok(Event::CData(e)) => text.push_str(&String::from_utf8_lossy(&e)),
+            Ok(Event::End(_)) => {
+                parser.end(&path, text.trim());
+                path.pop();
+                text.clear();
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("Failed to parse KML at position {}: {}", reader.buffer_position(), e)),
+        }
+    }
+
+    Ok(parser.finish())
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).to_string()
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.to_string())
+}
+
+impl KmlParser {
+    fn start(&mut self, name: &str, e: &BytesStart) {
+        match name {
+            "Folder" | "Document" => self.folders.push(Folder::default()),
+            "Placemark" => self.placemark = Some(Placemark::default()),
+            "Style" => self.style = Some((attribute(e, "id"), Style::default())),
+            "StyleMap" => self.style_map = attribute(e, "id").map(|id| (id, None, None)),
+            "Data" => self.data_name = attribute(e, "name"),
+            "Track" => {
+                self.track_times.clear();
+                self.track_coords.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, path: &[String], text: &str) {
+        let Some(name) = path.last().map(String::as_str) else { return };
+        let parent = path.len().checked_sub(2).map(|i| path[i].as_str()).unwrap_or_default();
+        let value = || (!text.is_empty()).then(|| text.to_string());
+
+        match (parent, name) {
+            ("Placemark", "name") => self.with_placemark(|p| p.name = value()),
+            ("Placemark", "description") => self.with_placemark(|p| p.desc = value()),
+            ("Folder" | "Document", "name") => self.folder().name = value(),
+            ("Folder" | "Document", "description") => self.folder().desc = value(),
+            ("Placemark", "styleUrl") => self.with_placemark(|p| p.style.url = value()),
+            ("Data", "value") => {
+                if let (Some(key), Some(data)) = (self.data_name.clone(), value()) {
+                    match self.placemark.as_mut() {
+                        Some(placemark) => placemark.data.insert(key, data),
+                        None => self.folder().data.insert(key, data),
+                    };
+                }
+            }
+            ("LineStyle", "color") => {
+                if let Some((_, style)) = self.style.as_mut() {
+                    style.line_color = value().and_then(|c| kml_color_to_hex(&c));
+                }
+            }
+            ("Icon", "href") => {
+                if let Some((_, style)) = self.style.as_mut() {
+                    style.icon = value();
+                }
+            }
+            ("Pair", "key") => {
+                if let Some(map) = self.style_map.as_mut() {
+                    map.1 = value();
+                }
+            }
+            ("Pair", "styleUrl") => {
+                if let Some(map) = self.style_map.as_mut() {
+                    map.2 = value();
+                }
+            }
+            (_, "Pair") => {
+                if let Some((id, key, url)) = self.style_map.as_mut() {
+                    if key.as_deref() == Some("normal") {
+                        if let Some(url) = url.take() {
+                            self.style_maps.insert(id.clone(), url);
+                        }
+                    }
+                }
+            }
+            ("Point", "coordinates") => {
+                let points = parse_coordinates(text);
+                self.with_placemark(|p| p.points.extend(points));
+            }
+            ("LineString", "coordinates") => {
+                let line = parse_coordinates(text);
+                self.with_placemark(|p| p.lines.push(line));
+            }
+            ("Track", "when") => self.track_times.push(text.to_string()),
+            ("Track", "coord") => {
+                if let Some(mut point) = parse_coordinate(text, ' ') {
+                    point.time = self.track_times.get(self.track_coords.len()).cloned();
+                    self.track_coords.push(point);
+                }
+            }
+            (_, "Track") => {
+                let segment = std::mem::take(&mut self.track_coords);
+                self.with_placemark(|p| p.tracks.push(segment));
+            }
+            (_, "Style") => {
+                if let Some((id, style)) = self.style.take() {
+                    if let Some(placemark) = self.placemark.as_mut() {
+                        placemark.style.inline = style.clone();
+                    }
+                    if let Some(id) = id {
+                        self.styles.insert(id, style);
+                    }
+                }
+            }
+            (_, "StyleMap") => self.style_map = None,
+            (_, "Placemark") => {
+                if let Some(placemark) = self.placemark.take() {
+                    self.add_placemark(placemark);
+                }
+            }
+            // The outermost folder collects anything outside a document and is never popped
+            (_, "Folder" | "Document") if self.folders.len() > 1 => {
+                let folder = self.folders.pop().unwrap_or_default();
+                self.add_folder(folder);
+            }
+            _ => {}
+        }
+    }
+
+    fn folder(&mut self) -> &mut Folder {
+        self.folders.last_mut().expect("root folder is never popped")
+    }
+
+    fn with_placemark(&mut self, f: impl FnOnce(&mut Placemark)) {
+        if let Some(placemark) = self.placemark.as_mut() {
+            f(placemark);
+        }
+    }
+
+    fn add_placemark(&mut self, placemark: Placemark) {
+        let kind = placemark.data.get(DATA_TYPE).cloned();
+        let uuid = placemark.data.get(DATA_UUID).cloned();
+        let sym = placemark.data.get(DATA_SYMBOL).cloned();
+        let in_route = self.folder().is_route();
+
+        if !placemark.tracks.is_empty() || placemark.lines.len() > 1 || kind.as_deref() == Some("track") {
+            let segments = if placemark.tracks.is_empty() { placemark.lines } else { placemark.tracks };
+            let track = GpxTrack { name: placemark.name, desc: placemark.desc, uuid, color: None, segments };
+            self.tracks.push((track, placemark.style));
+        } else if let Some(line) = placemark.lines.into_iter().next() {
+            if in_route {
+                self.folder().route_line = Some((line, placemark.style));
+            } else {
+                let route = GpxRoute { name: placemark.name, desc: placemark.desc, uuid, color: None, points: line };
+                self.routes.push((route, placemark.style, Vec::new()));
+            }
+        } else {
+            for point in placemark.points {
+                let point = GpxRoutePoint {
+                    name: placemark.name.clone(),
+                    desc: placemark.desc.clone(),
+                    sym: sym.clone(),
+                    uuid: uuid.clone(),
+                    ..point
+                };
+                if in_route {
+                    self.folder().route_points.push((point, placemark.style.clone()));
+                } else {
+                    let waypoint = GpxWaypoint {
+                        name: point.name,
+                        lat: point.lat,
+                        lon: point.lon,
+                        ele: point.ele,
+                        time: point.time,
+                        desc: point.desc,
+                        sym: point.sym,
+                        uuid: point.uuid,
+                        extensions: point.extensions,
+                    };
+                    self.waypoints.push((waypoint, placemark.style.clone()));
+                }
+            }
+        }
+    }
+
+    fn add_folder(&mut self, folder: Folder) {
+        if !folder.is_route() {
+            return;
+        }
+        let (line, style) = folder.route_line.unwrap_or_default();
+        // Named route points when there are any, otherwise the bare line
+        let (points, point_styles) = if folder.route_points.is_empty() {
+            (line, Vec::new())
+        } else {
+            folder.route_points.into_iter().unzip()
+        };
+        let route = GpxRoute {
+            name: folder.name,
+            desc: folder.desc,
+            uuid: folder.data.get(DATA_UUID).cloned(),
+            color: None,
+            points,
+        };
+        self.routes.push((route, style, point_styles));
+    }
+
+    /// Inline style settings, falling back to the shared style it refers to
+    fn resolve(&self, style: &StyleRef) -> Style {
+        let shared = style.url.as_deref().and_then(|url| {
+            let id = url.trim_start_matches('#');
+            let id = self.style_maps.get(id).map(|u| u.trim_start_matches('#')).unwrap_or(id);
+            self.styles.get(id)
+        });
+        let shared = shared.cloned().unwrap_or_default();
+        Style {
+            line_color: style.inline.line_color.clone().or(shared.line_color),
+            icon: style.inline.icon.clone().or(shared.icon),
+        }
+    }
+
+    /// Symbol saved in ExtendedData, otherwise the one matching the placemark's icon
+    fn resolve_symbol(&self, sym: Option<String>, style: &StyleRef) -> Option<String> {
+        sym.or_else(|| self.resolve(style).icon.as_deref().and_then(icon_symbol))
+    }
+
+    fn finish(mut self) -> ParsedGpx {
+        let waypoints = std::mem::take(&mut self.waypoints)
+            .into_iter()
+            .map(|(waypoint, style)| GpxWaypoint { sym: self.resolve_symbol(waypoint.sym.clone(), &style), ..waypoint })
+            .collect();
+        let routes = std::mem::take(&mut self.routes)
+            .into_iter()
+            .map(|(mut route, style, point_styles)| {
+                for (point, point_style) in route.points.iter_mut().zip(&point_styles) {
+                    point.sym = self.resolve_symbol(point.sym.take(), point_style);
+                }
+                GpxRoute { color: self.resolve(&style).line_color, ..route }
+            })
+            .collect();
+        let tracks = std::mem::take(&mut self.tracks)
+            .into_iter()
+            .map(|(track, style)| GpxTrack { color: self.resolve(&style).line_color, ..track })
+            .collect();
+        ParsedGpx { waypoints, routes, tracks }
+    }
+}
+
+/// `lon,lat[,alt]` tuples separated by whitespace
+fn parse_coordinates(text: &str) -> Vec<GpxRoutePoint> {
+    text.split_whitespace().filter_map(|tuple| parse_coordinate(tuple, ',')).collect()
+}
+
+fn parse_coordinate(text: &str, separator: char) -> Option<GpxRoutePoint> {
+    let mut parts = text.split(separator).map(|v| v.trim().parse::<f64>());
+    let lon = parts.next()?.ok()?;
+    let lat = parts.next()?.ok()?;
+    let ele = parts.next().and_then(|v| v.ok());
+    Some(GpxRoutePoint { name: None, lat, lon, ele, time: None, desc: None, sym: None, uuid: None, extensions: Default::default() })
+}
+
+/// KML colours are `aabbggrr`; the app stores `#rrggbb`
+fn kml_color_to_hex(color: &str) -> Option<String> {
+    let color = color.trim().trim_start_matches('#');
+    if color.len() != 8 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("#{}{}{}", &color[6..8], &color[4..6], &color[2..4]).to_lowercase())
+}
+
+fn symbol_icon(symbol: &str) -> Option<String> {
+    SYMBOL_ICONS.iter().find(|(s, _)| *s == symbol).map(|(_, icon)| format!("{}{}.png", ICON_BASE_URL, icon))
+}
+
+/// Matches on the icon's file name, so any copy of the Google Earth icon counts
+fn icon_symbol(href: &str) -> Option<String> {
+    let file = href.rsplit('/').next()?;
+    let stem = file.split('.').next()?;
+    SYMBOL_ICONS.iter().find(|(_, icon)| *icon == stem).map(|(symbol, _)| symbol.to_string())
+}
+
+fn hex_to_kml_color(color: &str) -> Option<String> {
+    let color = color.trim().trim_start_matches('#');
+    if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("ff{}{}{}", &color[4..6], &color[2..4], &color[0..2]).to_lowercase())
+}
+
+/// Generate a KML document. Routes are written as folders of named points so
+/// they come back as routes rather than bare lines.
+pub fn generate_kml(data: &ParsedGpx, name: &str) -> String {
+    let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    kml.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\" xmlns:gx=\"http://www.google.com/kml/ext/2.2\">\n<Document>\n");
+    push_element(&mut kml, "name", Some(name));
+
+    if !data.waypoints.is_empty() {
+        kml.push_str("<Folder>\n<name>Waypoints</name>\n");
+        for w in &data.waypoints {
+            push_point(&mut kml, w.name.as_deref(), w.desc.as_deref(), w.sym.as_deref(), w.uuid.as_deref(), w.lat, w.lon);
+        }
+        kml.push_str("</Folder>\n");
+    }
+
+    for route in &data.routes {
+        kml.push_str("<Folder>\n");
+        push_element(&mut kml, "name", route.name.as_deref());
+        push_element(&mut kml, "description", route.desc.as_deref());
+        push_data(&mut kml, &[(DATA_TYPE, Some("route")), (DATA_UUID, route.uuid.as_deref())]);
+        kml.push_str("<Placemark>\n");
+        push_element(&mut kml, "name", route.name.as_deref());
+        push_line_style(&mut kml, route.color.as_deref(), 3);
+        kml.push_str("<LineString>\n<tessellate>1</tessellate>\n");
+        push_coordinates(&mut kml, &route.points);
+        kml.push_str("</LineString>\n</Placemark>\n");
+        for p in &route.points {
+            push_point(&mut kml, p.name.as_deref(), p.desc.as_deref(), p.sym.as_deref(), p.uuid.as_deref(), p.lat, p.lon);
+        }
+        kml.push_str("</Folder>\n");
+    }
+
+    for track in &data.tracks {
+        kml.push_str("<Placemark>\n");
+        push_element(&mut kml, "name", track.name.as_deref());
+        push_element(&mut kml, "description", track.desc.as_deref());
+        push_line_style(&mut kml, track.color.as_deref(), 2);
+        push_data(&mut kml, &[(DATA_TYPE, Some("track")), (DATA_UUID, track.uuid.as_deref())]);
+        // gx:Track keeps the timestamps, but needs one for every point
+        if track.segments.iter().flatten().all(|p| p.time.is_some()) {
+            kml.push_str("<gx:MultiTrack>\n");
+            for segment in &track.segments {
+                kml.push_str("<gx:Track>\n");
+                for p in segment {
+                    push_element(&mut kml, "when", p.time.as_deref());
+                }
+                for p in segment {
+                    kml.push_str(&format!("<gx:coord>{} {} {}</gx:coord>\n", p.lon, p.lat, p.ele.unwrap_or(0.0)));
+                }
+                kml.push_str("</gx:Track>\n");
+            }
+            kml.push_str("</gx:MultiTrack>\n");
+        } else {
+            kml.push_str("<MultiGeometry>\n");
+            for segment in &track.segments {
+                kml.push_str("<LineString>\n<tessellate>1</tessellate>\n");
+                push_coordinates(&mut kml, segment);
+                kml.push_str("</LineString>\n");
+            }
+            kml.push_str("</MultiGeometry>\n");
+        }
+        kml.push_str("</Placemark>\n");
+    }
+
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+/// Write KML to disk, zipped as KMZ when the path ends in `.kmz`
+pub fn write_kml_file(path: &Path, data: &ParsedGpx, name: &str) -> Result<(), String> {
+    let kml = generate_kml(data, name);
+    let is_kmz = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("kmz"));
+    if !is_kmz {
+        return fs::write(path, kml).map_err(|e| format!("Failed to write KML file: {}", e));
+    }
+    write_kmz(path, &kml).map_err(|e| format!("Failed to write KMZ file: {}", e))
+}
+
+fn write_kmz(path: &Path, kml: &str) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(fs::File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(KMZ_DOC_ENTRY, options)?;
+    zip.write_all(kml.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+fn push_element(kml: &mut String, name: &str, value: Option<&str>) {
+    if let Some(value) = value {
+        kml.push_str(&format!("<{0}>{1}</{0}>\n", name, escape(value)));
+    }
+}
+
+fn push_data(kml: &mut String, data: &[(&str, Option<&str>)]) {
+    if data.iter().all(|(_, value)| value.is_none()) {
+        return;
+    }
+    kml.push_str("<ExtendedData>\n");
+    for (key, value) in data {
+        if let Some(value) = value {
+            kml.push_str(&format!("<Data name=\"{}\"><value>{}</value></Data>\n", key, escape(value)));
+        }
+    }
+    kml.push_str("</ExtendedData>\n");
+}
+
+fn push_line_style(kml: &mut String, color: Option<&str>, width: u32) {
+    if let Some(color) = color.and_then(hex_to_kml_color) {
+        kml.push_str(&format!("<Style>\n<LineStyle>\n<color>{}</color>\n<width>{}</width>\n</LineStyle>\n</Style>\n", color, width));
+    }
+}
+
+fn push_coordinates(kml: &mut String, points: &[GpxRoutePoint]) {
+    let coordinates: Vec<String> = points.iter().map(|p| format!("{},{},{}", p.lon, p.lat, p.ele.unwrap_or(0.0))).collect();
+    kml.push_str(&format!("<coordinates>{}</coordinates>\n", coordinates.join(" ")));
+}
+
+fn push_point(kml: &mut String, name: Option<&str>, desc: Option<&str>, sym: Option<&str>, uuid: Option<&str>, lat: f64, lon: f64) {
+    kml.push_str("<Placemark>\n");
+    push_element(kml, "name", name);
+    push_element(kml, "description", desc);
+    if let Some(icon) = sym.and_then(symbol_icon) {
+        kml.push_str(&format!("<Style>\n<IconStyle>\n<Icon>\n<href>{}</href>\n</Icon>\n</IconStyle>\n</Style>\n", icon));
+    }
+    push_data(kml, &[(DATA_SYMBOL, sym), (DATA_UUID, uuid)]);
+    kml.push_str(&format!("<Point>\n<coordinates>{},{},0</coordinates>\n</Point>\n</Placemark>\n", lon, lat));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(name: &str, lat: f64, lon: f64, time: Option<&str>) -> GpxRoutePoint {
+        GpxRoutePoint {
+            name: Some(name.to_string()),
+            lat,
+            lon,
+            ele: None,
+            time: time.map(str::to_string),
+            desc: None,
+            sym: None,
+            uuid: None,
+            extensions: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = ParsedGpx {
+            waypoints: vec![GpxWaypoint {
+                name: Some("Fish & chips".to_string()),
+                lat: -36.84,
+                lon: 174.74,
+                ele: None,
+                time: None,
+                desc: Some("Best <in> town".to_string()),
+                sym: Some("anchor".to_string()),
+                uuid: Some("wp-uuid".to_string()),
+                extensions: Default::default(),
+            }],
+            routes: vec![GpxRoute {
+                name: Some("Kawau run".to_string()),
+                desc: Some("Weekend trip".to_string()),
+                uuid: Some("route-uuid".to_string()),
+                color: Some("#c026d3".to_string()),
+                points: vec![
+                    GpxRoutePoint { sym: Some("marina".to_string()), uuid: Some("start-uuid".to_string()), ..point("Westhaven", -36.84, 174.74, None) },
+                    point("Kawau", -36.42, 174.83, None),
+                ],
+            }],
+            tracks: vec![GpxTrack {
+                name: Some("Sunday sail".to_string()),
+                desc: None,
+                uuid: Some("track-uuid".to_string()),
+                color: Some("#3b82f6".to_string()),
+                segments: vec![
+                    vec![point("", -36.80, 174.80, Some("2026-03-01T01:00:00Z")), point("", -36.79, 174.81, Some("2026-03-01T01:05:00Z"))],
+                    vec![point("", -36.70, 174.85, Some("2026-03-01T02:00:00Z"))],
+                ],
+            }],
+        };
+
+        let kml = generate_kml(&data, "Export");
+        assert!(kml.contains("<href>http://maps.google.com/mapfiles/kml/shapes/sailing.png</href>"));
+        let parsed = parse_kml_string(&kml).unwrap();
+
+        let waypoint = &parsed.waypoints[0];
+        assert_eq!(waypoint.name.as_deref(), Some("Fish & chips"));
+        assert_eq!(waypoint.desc.as_deref(), Some("Best <in> town"));
+        assert_eq!((waypoint.sym.as_deref(), waypoint.uuid.as_deref()), (Some("anchor"), Some("wp-uuid")));
+        assert_eq!((waypoint.lat, waypoint.lon), (-36.84, 174.74));
+
+        let route = &parsed.routes[0];
+        assert_eq!(parsed.routes.len(), 1);
+        assert_eq!((route.name.as_deref(), route.desc.as_deref()), (Some("Kawau run"), Some("Weekend trip")));
+        assert_eq!((route.uuid.as_deref(), route.color.as_deref()), (Some("route-uuid"), Some("#c026d3")));
+        let names: Vec<_> = route.points.iter().map(|p| p.name.as_deref().unwrap_or_default()).collect();
+        assert_eq!(names, ["Westhaven", "Kawau"]);
+        assert_eq!((route.points[0].sym.as_deref(), route.points[0].uuid.as_deref()), (Some("marina"), Some("start-uuid")));
+
+        let track = &parsed.tracks[0];
+        assert_eq!((track.uuid.as_deref(), track.color.as_deref()), (Some("track-uuid"), Some("#3b82f6")));
+        assert_eq!(track.segments.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(track.segments[0][1].time.as_deref(), Some("2026-03-01T01:05:00Z"));
+        assert_eq!((track.segments[1][0].lat, track.segments[1][0].lon), (-36.70, 174.85));
+    }
+
+    #[test]
+    fn test_parse_google_earth_kml() {
+        // Shared styles through a StyleMap, nested folders and a polygon to ignore
+        let kml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+<Document>
+    <name>Trip</name>
+    <Placemark>
+        <name>Passage</name>
+        <styleUrl>#passage</styleUrl>
+        <LineString><coordinates>
+            174.74,-36.84,0 174.83,-36.42,0
+        </coordinates></LineString>
+    </Placemark>
+    <StyleMap id="passage">
+        <Pair><key>normal</key><styleUrl>#passage-normal</styleUrl></Pair>
+        <Pair><key>highlight</key><styleUrl>#passage-hl</styleUrl></Pair>
+    </StyleMap>
+    <Style id="passage-normal"><LineStyle><color>ff0000ff</color></LineStyle></Style>
+    <Folder>
+        <name>Anchorages</name>
+        <Placemark>
+            <name>Bon Accord</name>
+            <description><![CDATA[<b>Good</b> holding]]></description>
+            <styleUrl>#anchorage</styleUrl>
+            <Point><coordinates>174.815,-36.425</coordinates></Point>
+        </Placemark>
+        <Placemark>
+            <name>Reserve</name>
+            <Polygon><outerBoundaryIs><LinearRing><coordinates>174.1,-36.1 174.2,-36.1 174.2,-36.2 174.1,-36.1</coordinates></LinearRing></outerBoundaryIs></Polygon>
+        </Placemark>
+    </Folder>
+    <Style id="anchorage"><IconStyle><Icon><href>https://maps.google.com/mapfiles/kml/shapes/sailing.png</href></Icon></IconStyle></Style>
+</Document>
+</kml>"##;
+
+        let parsed = parse_kml_string(kml).unwrap();
+        assert_eq!(parsed.waypoints.len(), 1);
+        assert_eq!(parsed.waypoints[0].desc.as_deref(), Some("<b>Good</b> holding"));
+        assert_eq!((parsed.waypoints[0].lat, parsed.waypoints[0].lon), (-36.425, 174.815));
+        assert_eq!(parsed.waypoints[0].sym.as_deref(), Some("anchor"));
+        assert_eq!(parsed.routes.len(), 1);
+        assert_eq!(parsed.routes[0].color.as_deref(), Some("#ff0000"));
+        assert_eq!(parsed.routes[0].points.len(), 2);
+        assert!(parsed.tracks.is_empty());
+    }
+}
@@ -1,23 +1,47 @@
 // VortexNav - Marine Navigation Application
 // Tauri 2.0 Backend
 
+mod almanac;
 mod catalog_parser;
+mod celestial;
 mod chart_converter;
 pub mod cm93;
 mod commands;
+mod csv_import;
+mod data_server;
 mod database;
+mod datum;
+mod dead_reckoning;
 mod download_manager;
+mod events;
+mod geojson;
 mod gps;
+mod gpsd;
 mod gpx;
+mod grib;
+mod instruments;
+mod kml;
 mod licensing;
+mod migrations;
+mod navigation;
 mod nmea;
+mod nmea2000;
+mod opencpn;
+mod position_filter;
+mod search;
+mod signalk;
+mod sync;
+mod tides;
+mod user_data;
+mod weather_routing;
 
 use commands::AppState;
 use database::ConfigDatabase;
 use gps::GpsManager;
 use std::collections::HashMap;
-use std::sync::Mutex;
-use tauri::Manager;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -42,6 +66,7 @@ pub fn run() {
 
             // Initialize GPS manager
             let gps_manager = GpsManager::new();
+            gps_manager.set_filter_settings(config_db.get_position_filter_settings().unwrap_or_default());
 
             // Create app state
             let state = AppState {
@@ -50,11 +75,39 @@ pub fn run() {
                 mbtiles_readers: Mutex::new(HashMap::new()),
                 charts_dir,
                 cm93_server: Mutex::new(None),
+                grib_data: Mutex::new(None),
+                weather_routing_cancel: Mutex::new(None),
+                data_server: Mutex::new(None),
+                sync_server: Mutex::new(None),
             };
 
             // Manage state in Tauri
+            let data_server_settings = state.config_db.get_data_server_settings().unwrap_or_default();
+            let sync_settings = state.config_db.get_sync_settings().unwrap_or_default();
             app.manage(state);
 
+            // Push GPS and navigation changes to the frontend as events
+            let handle = app.handle().clone();
+            app.state::<AppState>().gps_manager.start_events(Arc::new(move |topic, payload| {
+                let _ = handle.emit(topic, payload);
+            }));
+
+            // Start the NMEA/Signal K data server if enabled
+            if data_server_settings.enabled {
+                match commands::start_data_server(app.handle(), &data_server_settings) {
+                    Ok(server) => *app.state::<AppState>().data_server.lock().unwrap() = Some(server),
+                    Err(e) => log::error!("Failed to start data server: {}", e),
+                }
+            }
+
+            // Accept sync from paired devices if enabled
+            if sync_settings.enabled {
+                match commands::start_sync_server(app.handle(), &sync_settings) {
+                    Ok(server) => *app.state::<AppState>().sync_server.lock().unwrap() = Some(server),
+                    Err(e) => log::error!("Failed to start sync server: {}", e),
+                }
+            }
+
             log::info!("VortexNav initialized. Data directory: {:?}", app_data_dir);
 
             Ok(())
@@ -79,11 +132,53 @@ pub fn run() {
             commands::get_cm93_features,
             commands::get_cm93_settings,
             commands::save_cm93_settings,
+            // GRIB Weather
+            commands::load_grib_file,
+            commands::get_grib_status,
+            commands::clear_grib_data,
+            commands::get_grib_tile,
+            commands::get_grib_vectors,
+            commands::get_route_weather,
+            commands::compare_route_departures,
+            // Weather Routing
+            commands::import_boat_polar,
+            commands::get_boat_polars,
+            commands::get_boat_polar,
+            commands::delete_boat_polar,
+            commands::run_weather_routing,
+            commands::cancel_weather_routing,
+            // Tides
+            commands::import_tide_stations,
+            commands::get_tide_stations,
+            commands::delete_tide_station,
+            commands::find_nearest_tide_station,
+            commands::predict_tides,
+            commands::get_route_tides,
+            // Tidal Streams
+            commands::import_tidal_current_stations,
+            commands::get_tidal_current_stations,
+            commands::delete_tidal_current_station,
+            commands::import_tidal_stream_grid,
+            commands::get_tidal_stream_grids,
+            commands::delete_tidal_stream_grid,
+            commands::predict_tidal_currents,
+            commands::get_tidal_stream_at,
+            commands::get_course_to_steer,
+            // Almanac
+            commands::get_almanac,
+            commands::get_sky_conditions,
+            commands::get_route_daylight,
+            // Celestial
+            commands::get_celestial_bodies,
+            commands::get_celestial_almanac,
+            commands::reduce_celestial_sight,
+            commands::compute_celestial_fix,
             // GPS
             commands::get_gps_data,
             commands::get_gps_status,
             commands::list_serial_ports,
             commands::test_gps_port,
+            commands::discover_signalk_server,
             commands::get_gps_sources,
             commands::save_gps_source,
             commands::delete_gps_source,
@@ -92,6 +187,24 @@ pub fn run() {
             commands::stop_gps,
             commands::get_nmea_buffer,
             commands::clear_nmea_buffer,
+            commands::get_position_filter_settings,
+            commands::save_position_filter_settings,
+            commands::get_instrument_data,
+            commands::get_ais_targets,
+            // Data Server
+            commands::get_data_server_settings,
+            commands::save_data_server_settings,
+            commands::get_data_server_status,
+            // Device Sync
+            commands::get_sync_settings,
+            commands::save_sync_settings,
+            commands::get_sync_status,
+            commands::start_sync_pairing,
+            commands::pair_sync_device,
+            commands::get_sync_peers,
+            commands::remove_sync_peer,
+            commands::sync_with_device,
+            commands::set_navigation_target,
             // Waypoints
             commands::get_waypoints,
             commands::create_waypoint,
@@ -99,6 +212,18 @@ pub fn run() {
             commands::update_waypoint_position,
             commands::delete_waypoint,
             commands::toggle_waypoint_hidden,
+            commands::move_waypoints_to_group,
+            commands::set_waypoints_hidden,
+            commands::set_waypoints_show_label,
+            commands::set_waypoints_symbol,
+            commands::delete_waypoints,
+            // Waypoint Groups
+            commands::get_waypoint_groups,
+            commands::create_waypoint_group,
+            commands::update_waypoint_group,
+            commands::toggle_waypoint_group_hidden,
+            commands::get_waypoint_group_exclusive_waypoint_count,
+            commands::delete_waypoint_group,
             // Routes
             commands::get_routes,
             commands::get_route,
@@ -118,6 +243,7 @@ pub fn run() {
             commands::delete_route_tag,
             // Route Statistics
             commands::calculate_route_statistics,
+            commands::get_route_eta_table,
             // Tracks
             commands::get_tracks,
             commands::get_track,
@@ -134,12 +260,30 @@ pub fn run() {
             commands::get_track_gpx_string,
             commands::export_track_gpx,
             commands::convert_track_to_route,
+            // Search
+            commands::search_nearest,
+            commands::search_within_radius,
+            commands::search_within_bbox,
+            commands::search_text,
             // GPX Import/Export
             commands::import_gpx,
             commands::export_route_gpx,
             commands::export_routes_gpx,
             commands::get_route_gpx_string,
             commands::get_route_summary_text,
+            // KML and GeoJSON
+            commands::import_kml,
+            commands::import_geojson,
+            commands::export_kml,
+            commands::export_geojson,
+            // OpenCPN migration
+            commands::import_opencpn,
+            // CSV and spreadsheet waypoint lists
+            commands::preview_csv_import,
+            commands::import_csv,
+            // User data backup
+            commands::export_user_data,
+            commands::import_user_data,
             // Charts/MBTiles
             commands::list_charts,
             commands::get_tile,
@@ -0,0 +1,765 @@
+// Schema migrations for the configuration database
+// Ordered steps tracked with PRAGMA user_version, each applied in its own transaction
+
+use chrono::Utc;
+use rusqlite::{params, Connection, Result as SqliteResult, Transaction};
+use std::path::{Path, PathBuf};
+
+use crate::database::DatabaseError;
+
+/// One schema change, applied when the database is older than `version`.
+/// Databases from before versioning start at 0, with any mix of the tables and
+/// columns of earlier releases, so steps up to version 5 only add what is missing.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&Transaction) -> SqliteResult<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "Baseline schema", apply: baseline },
+    Migration { version: 2, description: "Boat polars", apply: boat_polars },
+    Migration { version: 3, description: "Tide stations", apply: tide_stations },
+    Migration { version: 4, description: "Tidal streams", apply: tidal_streams },
+    Migration { version: 5, description: "gpsd raw NMEA passthrough", apply: gps_raw_nmea },
+    Migration { version: 6, description: "Stable ids and tombstones for user objects", apply: object_ids },
+    Migration { version: 7, description: "Sync change log and paired devices", apply: sync_log },
+    Migration { version: 8, description: "Waypoint groups", apply: waypoint_groups },
+    Migration { version: 9, description: "Spatial and text search indexes", apply: search_indexes },
+    Migration { version: 10, description: "Waypoint arrival radius", apply: waypoint_arrival_radius },
+    Migration { version: 11, description: "Track segments", apply: track_segments },
+    Migration { version: 12, description: "Device identity", apply: device_identity },
+    Migration { version: 13, description: "Track points without a time", apply: untimed_track_points },
+    Migration { version: 14, description: "Water depth and temperature", apply: water_depth_temperature },
+];
+
+/// Version of a fully migrated database
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn schema_version(conn: &Connection) -> SqliteResult<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Bring the database up to the latest version. An existing database is copied
+/// into `backup_dir` first. Returns the version the database was at.
+pub fn migrate(conn: &mut Connection, backup_dir: Option<&Path>) -> Result<u32, DatabaseError> {
+    run_migrations(conn, MIGRATIONS, backup_dir)
+}
+
+fn run_migrations(conn: &mut Connection, migrations: &[Migration], backup_dir: Option<&Path>) -> Result<u32, DatabaseError> {
+    let current = schema_version(conn)?;
+    let supported = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > supported {
+        return Err(DatabaseError::SchemaTooNew { found: current, supported });
+    }
+    if current == supported {
+        return Ok(current);
+    }
+
+    // A brand new database has nothing worth keeping
+    if let Some(dir) = backup_dir {
+        if has_tables(conn)? {
+            let path = backup(conn, dir, current)?;
+            log::info!("Backed up database at schema version {} to {:?}", current, path);
+        }
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (migration.apply)(&tx).map_err(|source| DatabaseError::Migration { version: migration.version, source })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        log::info!("Migrated database to schema version {}: {}", migration.version, migration.description);
+    }
+    Ok(current)
+}
+
+fn has_tables(conn: &Connection) -> SqliteResult<bool> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')", [], |row| row.get(0))
+}
+
+/// Copy the database to `dir` with VACUUM INTO, named after its schema version
+pub fn backup(conn: &Connection, dir: &Path, version: u32) -> Result<PathBuf, DatabaseError> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("vortexnav-v{}-{}.db", version, Utc::now().format("%Y%m%d%H%M%S")));
+    conn.execute("VACUUM INTO ?", [path.to_string_lossy()])?;
+    Ok(path)
+}
+
+fn add_column_if_missing(tx: &Transaction, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+    let exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        tx.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn baseline(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS waypoints (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            description TEXT,
+            symbol TEXT,
+            show_label INTEGER NOT NULL DEFAULT 1,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS routes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT,
+            color TEXT DEFAULT '#c026d3',
+            is_active INTEGER NOT NULL DEFAULT 0,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            total_distance_nm REAL,
+            estimated_speed_kn REAL DEFAULT 5.0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Route waypoints junction table
+        CREATE TABLE IF NOT EXISTS route_waypoints (
+            route_id INTEGER NOT NULL,
+            waypoint_id INTEGER NOT NULL,
+            sequence INTEGER NOT NULL,
+            PRIMARY KEY (route_id, waypoint_id),
+            FOREIGN KEY (route_id) REFERENCES routes(id) ON DELETE CASCADE,
+            FOREIGN KEY (waypoint_id) REFERENCES waypoints(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS route_tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Route-tag junction table
+        CREATE TABLE IF NOT EXISTS route_tag_assignments (
+            route_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (route_id, tag_id),
+            FOREIGN KEY (route_id) REFERENCES routes(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES route_tags(id) ON DELETE CASCADE
+        );
+
+        -- MBTiles registry - tracks available offline chart files
+        CREATE TABLE IF NOT EXISTS mbtiles_registry (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            file_path TEXT NOT NULL UNIQUE,
+            minzoom INTEGER,
+            maxzoom INTEGER,
+            bounds TEXT,
+            enabled INTEGER DEFAULT 1,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS gps_sources (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            source_type TEXT NOT NULL,
+            port_name TEXT,
+            baud_rate INTEGER NOT NULL DEFAULT 4800,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            priority INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Chart layer state - stores user preferences for each chart layer
+        CREATE TABLE IF NOT EXISTS chart_layers (
+            chart_id TEXT PRIMARY KEY,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            opacity REAL NOT NULL DEFAULT 1.0,
+            z_order INTEGER NOT NULL DEFAULT 0,
+            custom_name TEXT,
+            custom_description TEXT,
+            custom_min_zoom INTEGER,
+            custom_max_zoom INTEGER
+        );
+
+        -- Chart catalogs - imported from XML files or URLs
+        CREATE TABLE IF NOT EXISTS chart_catalogs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            catalog_type TEXT NOT NULL,
+            source_type TEXT NOT NULL,
+            source_path TEXT NOT NULL,
+            imported_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            last_refreshed TEXT
+        );
+
+        -- Catalog charts - charts available in imported catalogs
+        CREATE TABLE IF NOT EXISTS catalog_charts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            catalog_id INTEGER NOT NULL,
+            chart_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            chart_type TEXT NOT NULL,
+            format TEXT,
+            scale INTEGER,
+            status TEXT,
+            download_url TEXT NOT NULL,
+            file_size INTEGER,
+            last_updated TEXT,
+            bounds TEXT,
+            download_status TEXT DEFAULT 'available',
+            download_progress INTEGER DEFAULT 0,
+            download_path TEXT,
+            mbtiles_path TEXT,
+            error_message TEXT,
+            FOREIGN KEY (catalog_id) REFERENCES chart_catalogs(id) ON DELETE CASCADE
+        );
+
+        -- Tracks table - recorded vessel trails
+        CREATE TABLE IF NOT EXISTS tracks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT,
+            color TEXT DEFAULT '#06b6d4',
+            is_recording INTEGER NOT NULL DEFAULT 0,
+            started_at TEXT,
+            ended_at TEXT,
+            total_distance_nm REAL,
+            point_count INTEGER DEFAULT 0,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Track points table - individual positions in a track
+        CREATE TABLE IF NOT EXISTS track_points (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            track_id INTEGER NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            timestamp TEXT NOT NULL,
+            sequence INTEGER NOT NULL,
+            heading REAL,
+            cog REAL,
+            sog REAL,
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_track_points_track_id ON track_points(track_id);",
+    )?;
+
+    // Columns added to early releases before the schema was versioned
+    add_column_if_missing(tx, "waypoints", "show_label", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(tx, "waypoints", "hidden", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "routes", "color", "TEXT DEFAULT '#c026d3'")?;
+    add_column_if_missing(tx, "routes", "is_active", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "routes", "hidden", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(tx, "routes", "total_distance_nm", "REAL")?;
+    add_column_if_missing(tx, "routes", "estimated_speed_kn", "REAL DEFAULT 5.0")?;
+    // SQLite cannot add a CURRENT_TIMESTAMP default to a table that already has rows
+    add_column_if_missing(tx, "routes", "updated_at", "TEXT")?;
+    add_column_if_missing(tx, "chart_layers", "custom_name", "TEXT")?;
+    add_column_if_missing(tx, "chart_layers", "custom_description", "TEXT")?;
+    add_column_if_missing(tx, "chart_layers", "custom_min_zoom", "INTEGER")?;
+    add_column_if_missing(tx, "chart_layers", "custom_max_zoom", "INTEGER")?;
+
+    // Default route tags
+    let default_tags = [
+        ("Favorites", "#f59e0b"),
+        ("Coastal", "#3b82f6"),
+        ("Ocean Crossing", "#8b5cf6"),
+        ("Harbor Entry", "#22c55e"),
+        ("Anchorage Approach", "#06b6d4"),
+        ("Emergency", "#ef4444"),
+        ("Historic", "#a855f7"),
+    ];
+    for (name, color) in default_tags {
+        tx.execute("INSERT OR IGNORE INTO route_tags (name, color) VALUES (?, ?)", params![name, color])?;
+    }
+    Ok(())
+}
+
+fn boat_polars(tx: &Transaction) -> SqliteResult<()> {
+    // Performance tables for weather routing
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS boat_polars (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            polar_data TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+}
+
+fn tide_stations(tx: &Transaction) -> SqliteResult<()> {
+    // Harmonic constants for offline tide prediction
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tide_stations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            mean_level_m REAL NOT NULL DEFAULT 0,
+            source TEXT,
+            constituents TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+}
+
+fn tidal_streams(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "-- Harmonic constants for tidal streams
+        CREATE TABLE IF NOT EXISTS tidal_current_stations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            flood_direction REAL NOT NULL,
+            ebb_direction REAL NOT NULL,
+            mean_speed_kn REAL NOT NULL DEFAULT 0,
+            source TEXT,
+            constituents TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Stream tables referenced to HW at a tide station
+        CREATE TABLE IF NOT EXISTS tidal_stream_grids (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            reference_station TEXT NOT NULL,
+            point_count INTEGER NOT NULL DEFAULT 0,
+            grid_data TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );",
+    )
+}
+
+fn gps_raw_nmea(tx: &Transaction) -> SqliteResult<()> {
+    add_column_if_missing(tx, "gps_sources", "raw_nmea", "INTEGER NOT NULL DEFAULT 0")
+}
+
+/// Tables whose rows carry a UUID, change time and deletion tombstone
+pub const TRACKED_TABLES: [&str; 4] = ["waypoints", "routes", "route_tags", "tracks"];
+
+fn object_ids(tx: &Transaction) -> SqliteResult<()> {
+    for table in TRACKED_TABLES {
+        add_column_if_missing(tx, table, "uuid", "TEXT")?;
+        add_column_if_missing(tx, table, "updated_at", "TEXT")?;
+        add_column_if_missing(tx, table, "deleted_at", "TEXT")?;
+
+        // Same format as timestamps written from Rust, so they compare as text
+        tx.execute(
+            &format!(
+                "UPDATE {} SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', COALESCE(updated_at, created_at, 'now'))",
+                table
+            ),
+            [],
+        )?;
+
+        let ids: Vec<i64> = tx
+            .prepare(&format!("SELECT id FROM {} WHERE uuid IS NULL", table))?
+            .query_map([], |row| row.get(0))?
+            .collect::<SqliteResult<_>>()?;
+        for id in ids {
+            tx.execute(
+                &format!("UPDATE {} SET uuid = ? WHERE id = ?", table),
+                params![uuid::Uuid::new_v4().to_string(), id],
+            )?;
+        }
+        tx.execute(&format!("CREATE UNIQUE INDEX IF NOT EXISTS idx_{}_uuid ON {}(uuid)", table, table), [])?;
+    }
+    Ok(())
+}
+
+fn sync_log(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sync_changes (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            uuid TEXT NOT NULL,
+            origin TEXT,
+            UNIQUE(table_name, uuid)
+        );
+
+        CREATE TABLE IF NOT EXISTS sync_peers (
+            device_id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            address TEXT,
+            token TEXT NOT NULL,
+            last_sent_seq INTEGER NOT NULL DEFAULT 0,
+            last_received_seq INTEGER NOT NULL DEFAULT 0,
+            last_synced_at TEXT,
+            paired_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    // Every write to a user object sets updated_at, so these triggers keep one
+    // log entry per object, moved to the end of the log on each change. Upserts
+    // override OR REPLACE inside triggers, hence the explicit delete.
+    for table in TRACKED_TABLES {
+        tx.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {t}_sync_insert AFTER INSERT ON {t} WHEN NEW.uuid IS NOT NULL
+             BEGIN
+                DELETE FROM sync_changes WHERE table_name = '{t}' AND uuid = NEW.uuid;
+                INSERT INTO sync_changes (table_name, uuid) VALUES ('{t}', NEW.uuid);
+             END;
+             CREATE TRIGGER IF NOT EXISTS {t}_sync_update AFTER UPDATE OF updated_at ON {t} WHEN NEW.uuid IS NOT NULL
+             BEGIN
+                DELETE FROM sync_changes WHERE table_name = '{t}' AND uuid = NEW.uuid;
+                INSERT INTO sync_changes (table_name, uuid) VALUES ('{t}', NEW.uuid);
+             END;
+             INSERT OR IGNORE INTO sync_changes (table_name, uuid)
+                SELECT '{t}', uuid FROM {t} WHERE uuid IS NOT NULL ORDER BY updated_at;",
+            t = table
+        ))?;
+    }
+    Ok(())
+}
+
+fn waypoint_groups(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS waypoint_groups (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            parent_id INTEGER REFERENCES waypoint_groups(id),
+            hidden INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+    add_column_if_missing(tx, "waypoints", "group_id", "INTEGER REFERENCES waypoint_groups(id)")?;
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_waypoints_group ON waypoints(group_id)", [])?;
+    Ok(())
+}
+
+/// Row ids in `search_text` are `object id * 4 + kind`, so triggers can find an
+/// object's entry without scanning
+pub const SEARCH_KIND_WAYPOINT: i64 = 1;
+pub const SEARCH_KIND_ROUTE: i64 = 2;
+pub const SEARCH_KIND_TRACK: i64 = 3;
+
+// Bounding box of a route's waypoints, for the route ids matched by `routes`
+const ROUTE_BOUNDS: &str = "SELECT rw.route_id, MIN(w.lat), MAX(w.lat), MIN(w.lon), MAX(w.lon)
+    FROM route_waypoints rw JOIN waypoints w ON w.id = rw.waypoint_id";
+
+fn search_indexes(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS waypoints_rtree USING rtree(id, min_lat, max_lat, min_lon, max_lon);
+        CREATE VIRTUAL TABLE IF NOT EXISTS routes_rtree USING rtree(id, min_lat, max_lat, min_lon, max_lon);
+        CREATE VIRTUAL TABLE IF NOT EXISTS tracks_rtree USING rtree(id, min_lat, max_lat, min_lon, max_lon);
+        CREATE VIRTUAL TABLE IF NOT EXISTS search_text USING fts5(name, description, tokenize = 'unicode61 remove_diacritics 2');",
+    )?;
+
+    // Deleted objects leave the indexes; a revived tombstone is indexed again.
+    // Route bounds follow their waypoints, track bounds grow with each point.
+    // Explicit deletes rather than OR REPLACE, which an outer upsert would override.
+    tx.execute_batch(&format!(
+        "CREATE TRIGGER IF NOT EXISTS waypoints_search_insert AFTER INSERT ON waypoints WHEN NEW.deleted_at IS NULL
+         BEGIN
+            INSERT INTO waypoints_rtree VALUES (NEW.id, NEW.lat, NEW.lat, NEW.lon, NEW.lon);
+            INSERT INTO search_text (rowid, name, description) VALUES (NEW.id * 4 + {wp}, NEW.name, NEW.description);
+         END;
+         CREATE TRIGGER IF NOT EXISTS waypoints_search_update AFTER UPDATE OF name, description, lat, lon, deleted_at ON waypoints
+         BEGIN
+            DELETE FROM waypoints_rtree WHERE id = OLD.id;
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {wp};
+            INSERT INTO waypoints_rtree SELECT NEW.id, NEW.lat, NEW.lat, NEW.lon, NEW.lon WHERE NEW.deleted_at IS NULL;
+            INSERT INTO search_text (rowid, name, description)
+                SELECT NEW.id * 4 + {wp}, NEW.name, NEW.description WHERE NEW.deleted_at IS NULL;
+            DELETE FROM routes_rtree WHERE id IN (SELECT route_id FROM route_waypoints WHERE waypoint_id = NEW.id);
+            INSERT INTO routes_rtree {bounds}
+                WHERE rw.route_id IN (SELECT route_id FROM route_waypoints WHERE waypoint_id = NEW.id) GROUP BY rw.route_id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS waypoints_search_delete AFTER DELETE ON waypoints
+         BEGIN
+            DELETE FROM waypoints_rtree WHERE id = OLD.id;
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {wp};
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS route_waypoints_search_insert AFTER INSERT ON route_waypoints
+         BEGIN
+            DELETE FROM routes_rtree WHERE id = NEW.route_id;
+            INSERT INTO routes_rtree {bounds} WHERE rw.route_id = NEW.route_id GROUP BY rw.route_id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS route_waypoints_search_delete AFTER DELETE ON route_waypoints
+         BEGIN
+            DELETE FROM routes_rtree WHERE id = OLD.route_id;
+            INSERT INTO routes_rtree {bounds} WHERE rw.route_id = OLD.route_id GROUP BY rw.route_id;
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS routes_search_insert AFTER INSERT ON routes WHEN NEW.deleted_at IS NULL
+         BEGIN
+            INSERT INTO search_text (rowid, name, description) VALUES (NEW.id * 4 + {route}, NEW.name, NEW.description);
+         END;
+         CREATE TRIGGER IF NOT EXISTS routes_search_update AFTER UPDATE OF name, description, deleted_at ON routes
+         BEGIN
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {route};
+            INSERT INTO search_text (rowid, name, description)
+                SELECT NEW.id * 4 + {route}, NEW.name, NEW.description WHERE NEW.deleted_at IS NULL;
+         END;
+         CREATE TRIGGER IF NOT EXISTS routes_search_delete AFTER DELETE ON routes
+         BEGIN
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {route};
+            DELETE FROM routes_rtree WHERE id = OLD.id;
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS tracks_search_insert AFTER INSERT ON tracks WHEN NEW.deleted_at IS NULL
+         BEGIN
+            INSERT INTO search_text (rowid, name, description) VALUES (NEW.id * 4 + {track}, NEW.name, NEW.description);
+         END;
+         CREATE TRIGGER IF NOT EXISTS tracks_search_update AFTER UPDATE OF name, description, deleted_at ON tracks
+         BEGIN
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {track};
+            INSERT INTO search_text (rowid, name, description)
+                SELECT NEW.id * 4 + {track}, NEW.name, NEW.description WHERE NEW.deleted_at IS NULL;
+         END;
+         CREATE TRIGGER IF NOT EXISTS tracks_search_bounds AFTER UPDATE OF deleted_at ON tracks
+         BEGIN
+            DELETE FROM tracks_rtree WHERE id = NEW.id;
+            INSERT INTO tracks_rtree SELECT track_id, MIN(lat), MAX(lat), MIN(lon), MAX(lon)
+                FROM track_points WHERE track_id = NEW.id AND NEW.deleted_at IS NULL GROUP BY track_id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS tracks_search_delete AFTER DELETE ON tracks
+         BEGIN
+            DELETE FROM search_text WHERE rowid = OLD.id * 4 + {track};
+            DELETE FROM tracks_rtree WHERE id = OLD.id;
+         END;
+         CREATE TRIGGER IF NOT EXISTS track_points_search_insert AFTER INSERT ON track_points
+         BEGIN
+            UPDATE tracks_rtree SET min_lat = MIN(min_lat, NEW.lat), max_lat = MAX(max_lat, NEW.lat),
+                min_lon = MIN(min_lon, NEW.lon), max_lon = MAX(max_lon, NEW.lon)
+                WHERE id = NEW.track_id;
+            -- An R-tree can't be read while it is written, so the first point is found from track_points
+            INSERT INTO tracks_rtree SELECT NEW.track_id, NEW.lat, NEW.lat, NEW.lon, NEW.lon
+                WHERE NOT EXISTS (SELECT 1 FROM track_points WHERE track_id = NEW.track_id AND id <> NEW.id);
+         END;
+
+         INSERT INTO waypoints_rtree SELECT id, lat, lat, lon, lon FROM waypoints WHERE deleted_at IS NULL;
+         INSERT INTO routes_rtree {bounds} GROUP BY rw.route_id;
+         INSERT INTO tracks_rtree SELECT p.track_id, MIN(p.lat), MAX(p.lat), MIN(p.lon), MAX(p.lon)
+            FROM track_points p JOIN tracks t ON t.id = p.track_id WHERE t.deleted_at IS NULL GROUP BY p.track_id;
+         INSERT INTO search_text (rowid, name, description)
+            SELECT id * 4 + {wp}, name, description FROM waypoints WHERE deleted_at IS NULL
+            UNION ALL SELECT id * 4 + {route}, name, description FROM routes WHERE deleted_at IS NULL
+            UNION ALL SELECT id * 4 + {track}, name, description FROM tracks WHERE deleted_at IS NULL;",
+        wp = SEARCH_KIND_WAYPOINT,
+        route = SEARCH_KIND_ROUTE,
+        track = SEARCH_KIND_TRACK,
+        bounds = ROUTE_BOUNDS,
+    ))?;
+    Ok(())
+}
+
+fn waypoint_arrival_radius(tx: &Transaction) -> SqliteResult<()> {
+    add_column_if_missing(tx, "waypoints", "arrival_radius_nm", "REAL")
+}
+
+fn track_segments(tx: &Transaction) -> SqliteResult<()> {
+    add_column_if_missing(tx, "track_points", "segment", "INTEGER NOT NULL DEFAULT 0")
+}
+
+// The sync identity moves out of settings, which backups copy between devices
+fn device_identity(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS device_identity (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            device_id TEXT NOT NULL
+        );
+        INSERT OR IGNORE INTO device_identity (id, device_id)
+            SELECT 1, value FROM settings WHERE key = 'sync_device_id';
+        DELETE FROM settings WHERE key = 'sync_device_id';",
+    )
+}
+
+// Imported tracks may have no times. SQLite can't drop NOT NULL in place, so the
+// table is rebuilt; its index and search trigger go with the old table.
+fn untimed_track_points(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE track_points_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            track_id INTEGER NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            timestamp TEXT,
+            sequence INTEGER NOT NULL,
+            heading REAL,
+            cog REAL,
+            sog REAL,
+            segment INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+        );
+        INSERT INTO track_points_new (id, track_id, lat, lon, timestamp, sequence, heading, cog, sog, segment)
+            SELECT id, track_id, lat, lon, NULLIF(timestamp, ''), sequence, heading, cog, sog, segment FROM track_points;
+        DROP TABLE track_points;
+        -- Triggers on tracks still name track_points; legacy mode leaves them alone
+        PRAGMA legacy_alter_table = ON;
+        ALTER TABLE track_points_new RENAME TO track_points;
+        PRAGMA legacy_alter_table = OFF;
+
+        CREATE INDEX idx_track_points_track_id ON track_points(track_id);
+        CREATE TRIGGER track_points_search_insert AFTER INSERT ON track_points
+        BEGIN
+            UPDATE tracks_rtree SET min_lat = MIN(min_lat, NEW.lat), max_lat = MAX(max_lat, NEW.lat),
+                min_lon = MIN(min_lon, NEW.lon), max_lon = MAX(max_lon, NEW.lon)
+                WHERE id = NEW.track_id;
+            INSERT INTO tracks_rtree SELECT NEW.track_id, NEW.lat, NEW.lat, NEW.lon, NEW.lon
+                WHERE NOT EXISTS (SELECT 1 FROM track_points WHERE track_id = NEW.track_id AND id <> NEW.id);
+        END;",
+    )
+}
+
+fn water_depth_temperature(tx: &Transaction) -> SqliteResult<()> {
+    for table in ["waypoints", "track_points"] {
+        add_column_if_missing(tx, table, "depth_m", "REAL")?;
+        add_column_if_missing(tx, table, "temperature_c", "REAL")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::ConfigDatabase;
+    use std::collections::BTreeMap;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vortexnav_migrations_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Table name to sorted column names
+    fn schema(conn: &Connection) -> BTreeMap<String, Vec<String>> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+            .unwrap();
+        let tables: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+        tables
+            .into_iter()
+            .map(|table| {
+                let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?)").unwrap();
+                let mut columns: Vec<String> =
+                    stmt.query_map([&table], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+                columns.sort();
+                (table, columns)
+            })
+            .collect()
+    }
+
+    fn backups(dir: &Path) -> usize {
+        std::fs::read_dir(dir.join("backups")).map(|entries| entries.count()).unwrap_or(0)
+    }
+
+    #[test]
+    fn test_upgrade_every_historical_version() {
+        let fresh_dir = temp_dir("fresh");
+        let fresh = Connection::open(fresh_dir.join("vortexnav.db")).unwrap();
+        drop(ConfigDatabase::new(&fresh_dir).unwrap());
+        let expected = schema(&fresh);
+        assert_eq!(schema_version(&fresh).unwrap(), latest_version());
+
+        for version in 1..latest_version() {
+            let dir = temp_dir(&format!("v{}", version));
+            {
+                let mut conn = Connection::open(dir.join("vortexnav.db")).unwrap();
+                run_migrations(&mut conn, &MIGRATIONS[..version as usize], None).unwrap();
+                assert_eq!(schema_version(&conn).unwrap(), version);
+                conn.execute("INSERT INTO waypoints (name, lat, lon) VALUES ('Rangitoto', -36.78, 174.86)", []).unwrap();
+            }
+
+            let db = ConfigDatabase::new(&dir).unwrap();
+            assert_eq!(db.get_waypoints().unwrap()[0].name, "Rangitoto", "version {}", version);
+            drop(db);
+            let conn = Connection::open(dir.join("vortexnav.db")).unwrap();
+            assert_eq!(schema_version(&conn).unwrap(), latest_version());
+            assert_eq!(schema(&conn), expected, "version {}", version);
+            assert_eq!(backups(&dir), 1, "version {}", version);
+            std::fs::remove_dir_all(dir).ok();
+        }
+
+        // Unversioned database from the first release, before show_label, hidden,
+        // route colours and chart layer metadata
+        let dir = temp_dir("legacy");
+        {
+            let conn = Connection::open(dir.join("vortexnav.db")).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+                CREATE TABLE waypoints (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, lat REAL NOT NULL,
+                    lon REAL NOT NULL, description TEXT, symbol TEXT, created_at TEXT DEFAULT CURRENT_TIMESTAMP);
+                CREATE TABLE routes (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, description TEXT,
+                    created_at TEXT DEFAULT CURRENT_TIMESTAMP);
+                CREATE TABLE chart_layers (chart_id TEXT PRIMARY KEY, enabled INTEGER NOT NULL DEFAULT 1,
+                    opacity REAL NOT NULL DEFAULT 1.0, z_order INTEGER NOT NULL DEFAULT 0);
+                INSERT INTO waypoints (name, lat, lon) VALUES ('Tiritiri', -36.6, 174.89);
+                INSERT INTO routes (name) VALUES ('Day sail');",
+            )
+            .unwrap();
+        }
+        let db = ConfigDatabase::new(&dir).unwrap();
+        let waypoints = db.get_waypoints().unwrap();
+        assert!(waypoints[0].show_label && !waypoints[0].hidden);
+        assert_eq!(db.get_routes().unwrap()[0].route.color.as_deref(), Some("#c026d3"));
+        drop(db);
+        let conn = Connection::open(dir.join("vortexnav.db")).unwrap();
+        assert_eq!(schema(&conn), expected);
+        assert_eq!(backups(&dir), 1);
+
+        // Reopening a current database neither migrates nor backs up again
+        drop(ConfigDatabase::new(&dir).unwrap());
+        assert_eq!(backups(&dir), 1);
+
+        std::fs::remove_dir_all(dir).ok();
+        std::fs::remove_dir_all(fresh_dir).ok();
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        fn broken(tx: &Transaction) -> SqliteResult<()> {
+            tx.execute_batch("CREATE TABLE half_done (id INTEGER); INSERT INTO missing_table VALUES (1);")
+        }
+        let migrations = [
+            Migration { version: 1, description: "Baseline schema", apply: baseline },
+            Migration { version: 2, description: "Broken", apply: broken },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let result = run_migrations(&mut conn, &migrations, None);
+        assert!(matches!(result, Err(DatabaseError::Migration { version: 2, .. })));
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+        assert!(!schema(&conn).contains_key("half_done"));
+
+        // A database from a newer release is left alone
+        conn.pragma_update(None, "user_version", 99).unwrap();
+        assert!(matches!(migrate(&mut conn, None), Err(DatabaseError::SchemaTooNew { found: 99, .. })));
+    }
+
+    #[test]
+    fn test_untimed_track_points() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, &MIGRATIONS[..12], None).unwrap();
+        conn.execute_batch(
+            "INSERT INTO tracks (id, name) VALUES (1, 'Sunday sail');
+            INSERT INTO track_points (track_id, lat, lon, timestamp, sequence) VALUES (1, -36.8, 174.8, '', 0);",
+        )
+        .unwrap();
+        migrate(&mut conn, None).unwrap();
+
+        let timestamp: Option<String> = conn.query_row("SELECT timestamp FROM track_points", [], |row| row.get(0)).unwrap();
+        assert_eq!(timestamp, None);
+        // The search bounds still follow new points
+        conn.execute("INSERT INTO track_points (track_id, lat, lon, sequence) VALUES (1, -36.7, 174.9, 1)", []).unwrap();
+        let max_lat: f64 = conn.query_row("SELECT max_lat FROM tracks_rtree WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert!((max_lat + 36.7).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,520 @@
+// Route navigation module
+// Great-circle helpers, route statistics, the route ETA table and progress along the active route
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{RouteStatistics, Waypoint};
+
+/// Earth radius in nautical miles
+pub const EARTH_RADIUS_NM: f64 = 3440.065;
+
+/// Time step used when working a leg through changing tidal streams (hours)
+const CURRENT_STEP_HOURS: f64 = 0.25;
+
+/// Closest spacing of intermediate ETA points (hours)
+const MIN_ETA_INTERVAL_HOURS: f64 = 5.0 / 60.0;
+
+/// Most intermediate points in one ETA table; longer passages get a wider spacing
+const MAX_ETA_INTERMEDIATE_POINTS: f64 = 2000.0;
+
+/// Source of water movement (tidal streams, ocean currents) for passage planning
+pub trait CurrentProvider {
+    /// Set (degrees true, the direction the water flows towards) and drift (knots)
+    fn current_at(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<(f64, f64)>;
+}
+
+/// Heading that makes good a track through a current
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseToSteer {
+    /// Desired track over the ground (degrees true)
+    pub track: f64,
+    /// Heading to steer (degrees true)
+    pub heading: f64,
+    pub boat_speed_kn: f64,
+    pub set: f64,
+    pub drift_kn: f64,
+    /// Speed made good along the track
+    pub sog_kn: f64,
+}
+
+/// One row of the route ETA table: a route waypoint or an intermediate point on a leg
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteEtaPoint {
+    /// Index of the leg this point lies on (the arrival waypoint belongs to the last leg)
+    pub leg_index: usize,
+    /// Set for route waypoints, None for intermediate points
+    pub waypoint_id: Option<i64>,
+    pub name: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// Course to steer from this point along the leg (degrees true)
+    pub bearing: f64,
+    pub distance_from_start_nm: f64,
+    pub hours_from_start: f64,
+    /// Estimated time of arrival (RFC 3339)
+    pub eta: String,
+}
+
+/// Route being followed and the waypoint currently steered for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveNavigation {
+    pub route_id: Option<i64>,
+    pub route_name: String,
+    pub waypoints: Vec<Waypoint>,
+    pub next_index: usize,
+}
+
+/// Where the vessel is relative to the current leg of the active route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteProgress {
+    pub next_index: usize,
+    pub next_waypoint: Waypoint,
+    /// Start of the current leg, None when heading for the first waypoint
+    pub previous_waypoint: Option<Waypoint>,
+    pub distance_nm: f64,
+    /// Bearing to the next waypoint (degrees true)
+    pub bearing: f64,
+    /// Cross-track error (nm), positive when right of the leg
+    pub cross_track_nm: Option<f64>,
+    /// Velocity made good towards the next waypoint
+    pub vmg_kn: Option<f64>,
+    /// Distance to the end of the route via the remaining waypoints
+    pub remaining_nm: f64,
+}
+
+impl ActiveNavigation {
+    pub fn progress(&self, lat: f64, lon: f64, sog: Option<f64>, cog: Option<f64>) -> Option<RouteProgress> {
+        let next = self.waypoints.get(self.next_index)?;
+        let previous = self.next_index.checked_sub(1).and_then(|i| self.waypoints.get(i));
+        let distance_nm = haversine_distance(lat, lon, next.lat, next.lon);
+        let bearing = calculate_bearing(lat, lon, next.lat, next.lon);
+
+        let cross_track_nm = previous.map(|from| {
+            let from_boat = haversine_distance(from.lat, from.lon, lat, lon) / EARTH_RADIUS_NM;
+            let boat_bearing = calculate_bearing(from.lat, from.lon, lat, lon);
+            let leg_bearing = calculate_bearing(from.lat, from.lon, next.lat, next.lon);
+            (from_boat.sin() * angle_difference(boat_bearing, leg_bearing).to_radians().sin()).asin() * EARTH_RADIUS_NM
+        });
+        let vmg_kn = match (sog, cog) {
+            (Some(sog), Some(cog)) => Some(sog * angle_difference(cog, bearing).to_radians().cos()),
+            _ => None,
+        };
+        let remaining_nm = distance_nm
+            + self.waypoints[self.next_index..]
+                .windows(2)
+                .map(|leg| haversine_distance(leg[0].lat, leg[0].lon, leg[1].lat, leg[1].lon))
+                .sum::<f64>();
+
+        Some(RouteProgress {
+            next_index: self.next_index,
+            next_waypoint: next.clone(),
+            previous_waypoint: previous.cloned(),
+            distance_nm,
+            bearing,
+            cross_track_nm,
+            vmg_kn,
+            remaining_nm,
+        })
+    }
+}
+
+/// Calculate route statistics from a list of waypoints
+pub fn calculate_statistics(waypoints: &[Waypoint], speed_kn: f64) -> RouteStatistics {
+    let mut total_distance_nm = 0.0;
+    let mut leg_distances = Vec::new();
+    let mut leg_bearings = Vec::new();
+
+    for i in 0..waypoints.len().saturating_sub(1) {
+        let from = &waypoints[i];
+        let to = &waypoints[i + 1];
+
+        let distance = haversine_distance(from.lat, from.lon, to.lat, to.lon);
+        let bearing = calculate_bearing(from.lat, from.lon, to.lat, to.lon);
+
+        total_distance_nm += distance;
+        leg_distances.push(distance);
+        leg_bearings.push(bearing);
+    }
+
+    let estimated_time_hours = if speed_kn > 0.0 {
+        total_distance_nm / speed_kn
+    } else {
+        0.0
+    };
+    let leg_times_hours = leg_distances
+        .iter()
+        .map(|d| if speed_kn > 0.0 { d / speed_kn } else { 0.0 })
+        .collect();
+
+    RouteStatistics {
+        total_distance_nm,
+        waypoint_count: waypoints.len(),
+        estimated_time_hours,
+        leg_distances,
+        leg_bearings,
+        leg_times_hours,
+        leg_courses_to_steer: Vec::new(),
+        leg_stream_kn: Vec::new(),
+    }
+}
+
+/// Route statistics for a departure time, working each leg through the currents:
+/// leg times reflect fair or foul tide and each leg gets a course to steer.
+pub fn calculate_statistics_with_currents(
+    waypoints: &[Waypoint],
+    speed_kn: f64,
+    departure: DateTime<Utc>,
+    currents: &dyn CurrentProvider,
+) -> RouteStatistics {
+    let mut stats = calculate_statistics(waypoints, speed_kn);
+    if speed_kn <= 0.0 {
+        return stats;
+    }
+
+    let mut hours = 0.0;
+    let mut leg_times = Vec::new();
+    let mut courses = Vec::new();
+    let mut streams = Vec::new();
+
+    for (leg, pair) in waypoints.windows(2).enumerate() {
+        let (from, to) = (&pair[0], &pair[1]);
+        let leg_distance = stats.leg_distances[leg];
+        let (mut lat, mut lon) = (from.lat, from.lon);
+        let mut remaining = leg_distance;
+        let mut leg_hours = 0.0;
+        let (mut heading_x, mut heading_y) = (0.0, 0.0);
+
+        while remaining > 1e-6 {
+            let track = calculate_bearing(lat, lon, to.lat, to.lon);
+            let time = time_after(departure, hours + leg_hours);
+            let (set, drift) = currents.current_at(lat, lon, time).unwrap_or((0.0, 0.0));
+
+            // Stemming a foul stream: hold the track and creep along at a tenth of boat speed
+            let (heading, sog) = match course_to_steer(track, speed_kn, set, drift) {
+                Some(cts) => (cts.heading, cts.sog_kn.max(speed_kn * 0.1)),
+                None => (track, speed_kn * 0.1),
+            };
+
+            let step = remaining.min(sog * CURRENT_STEP_HOURS);
+            let dt = step / sog;
+            heading_x += heading.to_radians().sin() * dt;
+            heading_y += heading.to_radians().cos() * dt;
+            (lat, lon) = destination_point(lat, lon, track, step);
+            remaining -= step;
+            leg_hours += dt;
+        }
+
+        leg_times.push(leg_hours);
+        courses.push((heading_x.atan2(heading_y).to_degrees() + 360.0) % 360.0);
+        streams.push(if leg_hours > 0.0 { leg_distance / leg_hours - speed_kn } else { 0.0 });
+        hours += leg_hours;
+    }
+
+    stats.estimated_time_hours = hours;
+    stats.leg_times_hours = leg_times;
+    stats.leg_courses_to_steer = courses;
+    stats.leg_stream_kn = streams;
+    stats
+}
+
+/// Heading to steer at `boat_speed_kn` through the water to make good `track`
+/// in a current setting `set` at `drift_kn`. None when the track can't be made.
+pub fn course_to_steer(track: f64, boat_speed_kn: f64, set: f64, drift_kn: f64) -> Option<CourseToSteer> {
+    if boat_speed_kn <= 0.0 {
+        return None;
+    }
+
+    let relative = (set - track).to_radians();
+    let cross = drift_kn * relative.sin();
+    if cross.abs() > boat_speed_kn {
+        return None;
+    }
+
+    let correction = (cross / boat_speed_kn).asin();
+    let sog_kn = boat_speed_kn * correction.cos() + drift_kn * relative.cos();
+    if sog_kn <= 0.0 {
+        return None;
+    }
+
+    Some(CourseToSteer {
+        track,
+        heading: (track - correction.to_degrees() + 360.0) % 360.0,
+        boat_speed_kn,
+        set,
+        drift_kn,
+        sog_kn,
+    })
+}
+
+/// Build the ETA table for a route. Leg times come from the statistics (so tidal
+/// streams are reflected when worked in), falling back to a constant speed.
+/// With `interval_hours`, intermediate points are added along each leg at that spacing,
+/// widened when needed to keep the table to a bounded size.
+pub fn build_eta_table(
+    waypoints: &[Waypoint],
+    stats: &RouteStatistics,
+    speed_kn: f64,
+    departure: DateTime<Utc>,
+    interval_hours: Option<f64>,
+) -> Vec<RouteEtaPoint> {
+    let mut points = Vec::new();
+    if waypoints.is_empty() || speed_kn <= 0.0 {
+        return points;
+    }
+
+    let total_hours: f64 = (0..waypoints.len() - 1)
+        .map(|leg| {
+            let leg_distance = stats.leg_distances.get(leg).copied().unwrap_or(0.0);
+            stats.leg_times_hours.get(leg).copied().unwrap_or(leg_distance / speed_kn)
+        })
+        .sum();
+    let interval = interval_hours
+        .filter(|h| *h > 0.0)
+        .map(|h| h.max(MIN_ETA_INTERVAL_HOURS).max(total_hours / MAX_ETA_INTERMEDIATE_POINTS));
+    let mut distance_run = 0.0;
+    let mut hours = 0.0;
+
+    for (leg, pair) in waypoints.windows(2).enumerate() {
+        let (from, to) = (&pair[0], &pair[1]);
+        let leg_distance = stats.leg_distances.get(leg).copied().unwrap_or(0.0);
+        let leg_hours = stats.leg_times_hours.get(leg).copied().unwrap_or(leg_distance / speed_kn);
+
+        points.push(eta_point(
+            leg,
+            Some(from),
+            from.lat,
+            from.lon,
+            stats.leg_bearings.get(leg).copied().unwrap_or(0.0),
+            distance_run,
+            hours,
+            departure,
+        ));
+
+        if let Some(interval) = interval {
+            // Skip a point that would land almost on top of the next waypoint
+            let mut along = interval;
+            while along < leg_hours - interval * 0.25 {
+                let fraction = along / leg_hours;
+                let (lat, lon) = intermediate_point(from.lat, from.lon, to.lat, to.lon, fraction);
+                let bearing = calculate_bearing(lat, lon, to.lat, to.lon);
+                points.push(eta_point(
+                    leg,
+                    None,
+                    lat,
+                    lon,
+                    bearing,
+                    distance_run + leg_distance * fraction,
+                    hours + along,
+                    departure,
+                ));
+                along += interval;
+            }
+        }
+
+        distance_run += leg_distance;
+        hours += leg_hours;
+    }
+
+    let last = &waypoints[waypoints.len() - 1];
+    let last_leg = waypoints.len().saturating_sub(2);
+    points.push(eta_point(
+        last_leg,
+        Some(last),
+        last.lat,
+        last.lon,
+        stats.leg_bearings.last().copied().unwrap_or(0.0),
+        distance_run,
+        hours,
+        departure,
+    ));
+
+    points
+}
+
+#[allow(clippy::too_many_arguments)]
+fn eta_point(
+    leg_index: usize,
+    waypoint: Option<&Waypoint>,
+    lat: f64,
+    lon: f64,
+    bearing: f64,
+    distance_from_start_nm: f64,
+    hours_from_start: f64,
+    departure: DateTime<Utc>,
+) -> RouteEtaPoint {
+    RouteEtaPoint {
+        leg_index,
+        waypoint_id: waypoint.and_then(|w| w.id),
+        name: waypoint.map(|w| w.name.clone()),
+        lat,
+        lon,
+        bearing,
+        distance_from_start_nm,
+        hours_from_start,
+        eta: time_after(departure, hours_from_start).to_rfc3339(),
+    }
+}
+
+/// Time a number of (fractional) hours after a start time
+pub fn time_after(start: DateTime<Utc>, hours: f64) -> DateTime<Utc> {
+    start + Duration::seconds((hours * 3600.0).round() as i64)
+}
+
+/// Calculate distance between two points using Haversine formula
+pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1_rad = lat1.to_radians();
+    let lat2_rad = lat2.to_radians();
+    let delta_lat = (lat2 - lat1).to_radians();
+    let delta_lon = (lon2 - lon1).to_radians();
+
+    let a = (delta_lat / 2.0).sin().powi(2)
+        + lat1_rad.cos() * lat2_rad.cos() * (delta_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().asin();
+
+    EARTH_RADIUS_NM * c
+}
+
+/// Calculate initial bearing from point 1 to point 2
+pub fn calculate_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1_rad = lat1.to_radians();
+    let lat2_rad = lat2.to_radians();
+    let delta_lon = (lon2 - lon1).to_radians();
+
+    let y = delta_lon.sin() * lat2_rad.cos();
+    let x = lat1_rad.cos() * lat2_rad.sin() - lat1_rad.sin() * lat2_rad.cos() * delta_lon.cos();
+
+    let bearing_rad = y.atan2(x);
+    let bearing_deg = bearing_rad.to_degrees();
+
+    // Normalize to 0-360
+    (bearing_deg + 360.0) % 360.0
+}
+
+/// Point reached travelling a distance (nm) on an initial bearing from a start point
+pub fn destination_point(lat: f64, lon: f64, bearing: f64, distance_nm: f64) -> (f64, f64) {
+    let delta = distance_nm / EARTH_RADIUS_NM;
+    let theta = bearing.to_radians();
+    let lat1 = lat.to_radians();
+    let lon1 = lon.to_radians();
+
+    let lat2 = (lat1.sin() * delta.cos() + lat1.cos() * delta.sin() * theta.cos()).asin();
+    let lon2 = lon1
+        + (theta.sin() * delta.sin() * lat1.cos()).atan2(delta.cos() - lat1.sin() * lat2.sin());
+
+    (lat2.to_degrees(), (lon2.to_degrees() + 540.0) % 360.0 - 180.0)
+}
+
+/// Point a fraction of the way along the great circle between two points
+pub fn intermediate_point(lat1: f64, lon1: f64, lat2: f64, lon2: f64, fraction: f64) -> (f64, f64) {
+    let distance = haversine_distance(lat1, lon1, lat2, lon2);
+    if distance <= 0.0 {
+        return (lat1, lon1);
+    }
+    let bearing = calculate_bearing(lat1, lon1, lat2, lon2);
+    destination_point(lat1, lon1, bearing, distance * fraction)
+}
+
+/// Smallest signed difference between two angles (degrees, -180..180)
+pub fn angle_difference(a: f64, b: f64) -> f64 {
+    (a - b + 540.0) % 360.0 - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn waypoint(id: i64, lat: f64, lon: f64) -> Waypoint {
+        Waypoint { id: Some(id), ..Waypoint::at(&format!("WP{}", id), lat, lon) }
+    }
+
+    #[test]
+    fn test_eta_table() {
+        // Two legs of 60nm due north along a meridian
+        let waypoints = vec![waypoint(1, 0.0, 0.0), waypoint(2, 1.0, 0.0), waypoint(3, 2.0, 0.0)];
+        let stats = calculate_statistics(&waypoints, 6.0);
+        let departure = Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+
+        let table = build_eta_table(&waypoints, &stats, 6.0, departure, Some(5.0));
+        // WP1, +30nm, WP2, +30nm, WP3
+        assert_eq!(table.len(), 5);
+        assert_eq!(table[1].waypoint_id, None);
+        assert_eq!(table[2].waypoint_id, Some(2));
+        assert!((table[2].hours_from_start - stats.leg_distances[0] / 6.0).abs() < 1e-9);
+        assert_eq!(table[4].leg_index, 1);
+        assert!(table[4].eta.starts_with("2024-06-02T04:"));
+
+        // A tiny interval is widened instead of filling memory
+        let table = build_eta_table(&waypoints, &stats, 6.0, departure, Some(1e-9));
+        assert!(table.len() <= 3 + MAX_ETA_INTERMEDIATE_POINTS as usize);
+        assert!(table[1].hours_from_start >= MIN_ETA_INTERVAL_HOURS);
+    }
+
+    #[test]
+    fn test_route_progress_cross_track() {
+        let navigation = ActiveNavigation {
+            route_id: Some(1),
+            route_name: "Passage".to_string(),
+            waypoints: vec![waypoint(1, -36.0, 174.0), waypoint(2, -35.0, 174.0), waypoint(3, -35.0, 175.0)],
+            next_index: 1,
+        };
+
+        // 0.5 nm east of a northbound leg is right of track
+        let (lat, lon) = destination_point(-35.5, 174.0, 90.0, 0.5);
+        let progress = navigation.progress(lat, lon, Some(6.0), Some(0.0)).unwrap();
+        assert_eq!(progress.next_waypoint.id, Some(2));
+        assert!((progress.cross_track_nm.unwrap() - 0.5).abs() < 0.001);
+        assert!((progress.distance_nm - 30.0).abs() < 0.1);
+        assert!((progress.vmg_kn.unwrap() - 6.0).abs() < 0.01);
+        let last_leg = haversine_distance(-35.0, 174.0, -35.0, 175.0);
+        assert!((progress.remaining_nm - progress.distance_nm - last_leg).abs() < 1e-9);
+
+        let west = destination_point(-35.5, 174.0, 270.0, 0.5);
+        assert!(navigation.progress(west.0, west.1, None, None).unwrap().cross_track_nm.unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_destination_point_roundtrip() {
+        let (lat, lon) = destination_point(-36.8, 174.8, 45.0, 100.0);
+        assert!((haversine_distance(-36.8, 174.8, lat, lon) - 100.0).abs() < 1e-6);
+        assert!((calculate_bearing(-36.8, 174.8, lat, lon) - 45.0).abs() < 1e-6);
+        assert!((angle_difference(10.0, 350.0) - 20.0).abs() < 1e-9);
+    }
+
+    struct SteadyStream(f64, f64);
+
+    impl CurrentProvider for SteadyStream {
+        fn current_at(&self, _lat: f64, _lon: f64, _time: DateTime<Utc>) -> Option<(f64, f64)> {
+            Some((self.0, self.1))
+        }
+    }
+
+    #[test]
+    fn test_course_to_steer_and_tidal_eta() {
+        // 2 knots setting east across a northbound track at 6 knots
+        let cts = course_to_steer(0.0, 6.0, 90.0, 2.0).unwrap();
+        assert!((cts.heading - (360.0 - (2.0f64 / 6.0).asin().to_degrees())).abs() < 1e-9);
+        assert!((cts.sog_kn - (36.0f64 - 4.0).sqrt()).abs() < 1e-9);
+        // Stream across the track faster than the boat
+        assert!(course_to_steer(0.0, 2.0, 90.0, 3.0).is_none());
+
+        let waypoints = vec![waypoint(1, 0.0, 0.0), waypoint(2, 1.0, 0.0)];
+        let departure = Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+        let still = calculate_statistics(&waypoints, 6.0);
+
+        // A 2 knot fair tide: 60nm at 8 knots
+        let fair = calculate_statistics_with_currents(&waypoints, 6.0, departure, &SteadyStream(0.0, 2.0));
+        assert!((fair.estimated_time_hours - still.total_distance_nm / 8.0).abs() < 1e-6);
+        assert!((fair.leg_stream_kn[0] - 2.0).abs() < 1e-6);
+
+        let foul = calculate_statistics_with_currents(&waypoints, 6.0, departure, &SteadyStream(180.0, 2.0));
+        let table = build_eta_table(&waypoints, &foul, 6.0, departure, Some(5.0));
+        assert!((table.last().unwrap().hours_from_start - still.total_distance_nm / 4.0).abs() < 1e-6);
+        // Points every 5 hours at 4 knots over the ground: 20nm apart
+        assert!((table[1].distance_from_start_nm - 20.0).abs() < 1e-6);
+
+        let cross = calculate_statistics_with_currents(&waypoints, 6.0, departure, &SteadyStream(90.0, 2.0));
+        assert!((cross.leg_courses_to_steer[0] - cts.heading).abs() < 0.5);
+    }
+}
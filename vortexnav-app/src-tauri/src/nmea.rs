@@ -2,8 +2,12 @@
 
 use nmea::Nmea;
 use nmea::sentences::{FixType, GnssType};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::position_filter::GnssIntegrity;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -43,6 +47,15 @@ pub struct GpsData {
     pub timestamp: Option<String>,
     pub fix_type: Option<String>,      // No fix, 2D, 3D
     pub satellites_info: Vec<SatelliteInfo>,  // Individual satellite data
+    #[serde(default)]
+    pub is_estimated: bool,                   // Position from dead reckoning, not a fix
+    pub estimate_uncertainty_nm: Option<f64>, // Radius of the estimated position
+    pub estimated_since: Option<String>,      // Time of the last real fix
+    pub integrity: Option<GnssIntegrity>,     // Confidence in the fix
+    #[serde(default)]
+    pub integrity_warnings: Vec<String>,      // Reasons integrity is not good
+    #[serde(default)]
+    pub rejected_fixes: u64,                  // Position jumps discarded by the filter
 }
 
 // NMEA parser state
@@ -116,6 +129,12 @@ impl NmeaParser {
             timestamp: nmea.fix_time.map(|t| t.to_string()),
             fix_type,
             satellites_info,
+            is_estimated: false,
+            estimate_uncertainty_nm: None,
+            estimated_since: None,
+            integrity: None,
+            integrity_warnings: Vec::new(),
+            rejected_fixes: 0,
         };
 
         Ok(data)
@@ -158,6 +177,135 @@ impl NmeaParser {
     }
 }
 
+/// True heading and speed through the water from HDT, HDG and VHW sentences,
+/// which the nmea crate does not handle
+pub fn parse_heading_log(sentence: &str) -> (Option<f64>, Option<f64>) {
+    let body = sentence.trim().split('*').next().unwrap_or("");
+    let fields: Vec<&str> = body.split(',').collect();
+    let field = |i: usize| fields.get(i).and_then(|f| f.parse::<f64>().ok());
+    let signed = |i: usize| field(i).map(|v| if fields.get(i + 1) == Some(&"W") { -v } else { v });
+
+    let kind = fields[0].get(3..).unwrap_or("");
+    match kind {
+        "HDT" => (field(1), None),
+        // Magnetic sensor heading corrected for deviation and variation
+        "HDG" => (
+            field(1).map(|h| (h + signed(2).unwrap_or(0.0) + signed(4).unwrap_or(0.0)).rem_euclid(360.0)),
+            None,
+        ),
+        "VHW" => (field(1), field(5)),
+        _ => (None, None),
+    }
+}
+
+// Window over which sentence rates are measured
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Check the XOR checksum after '*'. None if the sentence has no checksum.
+pub fn checksum_valid(sentence: &str) -> Option<bool> {
+    let sentence = sentence.trim();
+    let body = sentence.strip_prefix('$').or_else(|| sentence.strip_prefix('!'))?;
+    let (data, checksum) = body.rsplit_once('*')?;
+    let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+    Some(data.bytes().fold(0u8, |acc, b| acc ^ b) == expected)
+}
+
+// Rate and last-seen time of one talker/sentence type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceTypeStats {
+    pub sentence_id: String,   // Address field, e.g. GPRMC
+    pub talker: String,        // GP, GN, II, ... or P for proprietary
+    pub sentence_type: String, // RMC, DBT, ...
+    pub count: u64,
+    pub rate_hz: f64,          // Over the last 10 seconds
+    pub last_seen: String,
+}
+
+#[derive(Debug)]
+struct SentenceTypeEntry {
+    count: u64,
+    recent: VecDeque<Instant>,
+    last_seen: DateTime<Utc>,
+}
+
+// Per talker/sentence type counters for diagnosing wiring and multiplexers
+#[derive(Debug, Default)]
+pub struct SentenceStats {
+    entries: HashMap<String, SentenceTypeEntry>,
+}
+
+impl SentenceStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a received sentence
+    pub fn record(&mut self, sentence: &str, now: Instant) {
+        let address = sentence.trim().get(1..).unwrap_or("").split([',', '*']).next().unwrap_or("");
+        if address.is_empty() {
+            return;
+        }
+        let entry = self.entries.entry(address.to_string()).or_insert_with(|| SentenceTypeEntry {
+            count: 0,
+            recent: VecDeque::new(),
+            last_seen: Utc::now(),
+        });
+        entry.count += 1;
+        entry.last_seen = Utc::now();
+        entry.recent.push_back(now);
+        while entry.recent.front().is_some_and(|t| now.duration_since(*t) > RATE_WINDOW) {
+            entry.recent.pop_front();
+        }
+    }
+
+    /// Current statistics, sorted by sentence id
+    pub fn snapshot(&self, now: Instant) -> Vec<SentenceTypeStats> {
+        let mut stats: Vec<SentenceTypeStats> = self
+            .entries
+            .iter()
+            .map(|(id, entry)| {
+                let recent: Vec<&Instant> = entry.recent.iter().filter(|t| now.duration_since(**t) <= RATE_WINDOW).collect();
+                let rate_hz = match (recent.first(), recent.last()) {
+                    (Some(first), Some(last)) if recent.len() > 1 && *last > *first => {
+                        (recent.len() - 1) as f64 / last.duration_since(**first).as_secs_f64()
+                    }
+                    _ => 0.0,
+                };
+                let (talker, sentence_type) = if let Some(proprietary) = id.strip_prefix('P') {
+                    ("P".to_string(), proprietary.to_string())
+                } else {
+                    (id.chars().take(2).collect(), id.chars().skip(2).collect())
+                };
+                SentenceTypeStats {
+                    sentence_id: id.clone(),
+                    talker,
+                    sentence_type,
+                    count: entry.count,
+                    rate_hz,
+                    last_seen: entry.last_seen.to_rfc3339(),
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| a.sentence_id.cmp(&b.sentence_id));
+        stats
+    }
+}
+
+/// Whether a sentence carries a valid position fix (GGA, RMC, GLL or GNS)
+pub fn is_position_fix(sentence: &str) -> bool {
+    let body = sentence.trim().split('*').next().unwrap_or("");
+    let fields: Vec<&str> = body.split(',').collect();
+    let field = |i: usize| fields.get(i).copied().unwrap_or("");
+
+    match fields[0].get(3..).unwrap_or("") {
+        "GGA" => !field(2).is_empty() && !matches!(field(6), "" | "0"),
+        "RMC" => !field(3).is_empty() && field(2) == "A",
+        "GLL" => !field(1).is_empty() && field(6) == "A",
+        "GNS" => !field(2).is_empty() && !field(6).is_empty() && !field(6).chars().all(|c| c == 'N'),
+        _ => false,
+    }
+}
+
 // Shared GPS state for the application
 pub struct GpsState {
     pub data: Mutex<GpsData>,
@@ -244,6 +392,50 @@ mod tests {
         assert!(gps.speed_knots.is_some());
     }
 
+    #[test]
+    fn test_parse_heading_log() {
+        assert_eq!(parse_heading_log("$GPHDT,123.4,T*31"), (Some(123.4), None));
+        let (heading, _) = parse_heading_log("$HCHDG,98.3,0.0,E,12.6,W*57");
+        assert!((heading.unwrap() - 85.7).abs() < 1e-9);
+        assert_eq!(parse_heading_log("$VWVHW,045.0,T,031.0,M,6.2,N,11.5,K*66"), (Some(45.0), Some(6.2)));
+        assert_eq!(parse_heading_log("$GPVHW,,T,,M,5.5,N,,K"), (None, Some(5.5)));
+        assert_eq!(parse_heading_log("$GPGGA,123519"), (None, None));
+
+        assert!(is_position_fix("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A"));
+        assert!(!is_position_fix("$GPRMC,123519,V,,,,,,,230394,,*33"));
+        assert!(!is_position_fix("$GPGGA,123519,4807.038,N,01131.000,E,0,00,,,M,,M,,*52"));
+        assert!(!is_position_fix("$GPHDT,123.4,T*31"));
+    }
+
+    #[test]
+    fn test_checksum_and_sentence_stats() {
+        assert_eq!(checksum_valid("$GPHDT,123.4,T*31"), Some(true));
+        assert_eq!(checksum_valid("$GPHDT,123.4,T*1D"), Some(false));
+        assert_eq!(checksum_valid("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47"), Some(true));
+        assert_eq!(checksum_valid("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,47.0,M,,*47"), Some(false));
+        assert_eq!(checksum_valid("$IIDBT,12.3,f,3.7,M,2.0,F"), None);
+
+        let mut stats = SentenceStats::new();
+        let start = Instant::now();
+        for i in 0..20 {
+            let t = start + Duration::from_millis(i * 500);
+            stats.record("$IIDBT,12.3,f,3.7,M,2.0,F*27", t);
+            if i % 2 == 0 {
+                stats.record("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A", t);
+            }
+        }
+        stats.record("$PGRME,15.0,M,45.0,M,25.0,M*1C", start);
+
+        let snapshot = stats.snapshot(start + Duration::from_millis(9500));
+        let ids: Vec<&str> = snapshot.iter().map(|s| s.sentence_id.as_str()).collect();
+        assert_eq!(ids, ["GPRMC", "IIDBT", "PGRME"]);
+        assert_eq!(snapshot[0].count, 10);
+        assert!((snapshot[0].rate_hz - 1.0).abs() < 1e-9);
+        assert!((snapshot[1].rate_hz - 2.0).abs() < 1e-9);
+        assert_eq!((snapshot[1].talker.as_str(), snapshot[1].sentence_type.as_str()), ("II", "DBT"));
+        assert_eq!((snapshot[2].talker.as_str(), snapshot[2].sentence_type.as_str()), ("P", "GRME"));
+    }
+
     #[test]
     fn test_gps_state() {
         let state = GpsState::new();
@@ -0,0 +1,471 @@
+// NMEA 2000 decoder
+// Reads PGNs from Actisense ASCII and Yacht Devices RAW gateway logs, reassembles fast packets
+// and converts them to GPS, instrument and AIS data
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::instruments::{degrees, knots, relative_degrees, OwnVesselUpdate, VesselData};
+
+/// PGNs we decode that span several CAN frames
+const FAST_PACKET_PGNS: [u32; 3] = [129029, 129038, 129039];
+
+/// Incomplete fast packets are discarded after this long
+const FAST_PACKET_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One complete NMEA 2000 message
+#[derive(Debug, Clone, PartialEq)]
+pub struct N2kMessage {
+    pub pgn: u32,
+    pub priority: u8,
+    pub source: u8,
+    pub destination: u8,
+    pub data: Vec<u8>,
+}
+
+/// A line from a gateway: a whole message (Actisense ASCII) or one CAN frame (YD RAW)
+#[derive(Debug, Clone, PartialEq)]
+enum GatewayLine {
+    Message(N2kMessage),
+    Frame(N2kMessage),
+}
+
+/// Whether a line is in one of the NMEA 2000 gateway formats rather than NMEA 0183
+pub fn is_nmea2000_line(line: &str) -> bool {
+    parse_line(line).is_some()
+}
+
+fn parse_hex_bytes<'a>(parts: impl Iterator<Item = &'a str>) -> Option<Vec<u8>> {
+    parts.map(|b| u8::from_str_radix(b, 16).ok()).collect()
+}
+
+/// Split a 29-bit CAN identifier into priority, PGN, source and destination
+fn decode_can_id(id: u32) -> (u8, u32, u8, u8) {
+    let priority = ((id >> 26) & 0x7) as u8;
+    let source = (id & 0xFF) as u8;
+    let pdu_format = (id >> 16) & 0xFF;
+    let pgn = (id >> 8) & 0x3FFFF;
+    if pdu_format < 240 {
+        // PDU1: the low byte is the destination address
+        (priority, pgn & 0x3FF00, source, ((id >> 8) & 0xFF) as u8)
+    } else {
+        (priority, pgn, source, 0xFF)
+    }
+}
+
+/// Yacht Devices RAW: "17:33:21.107 R 19F51323 01 2F 30 70 00 2F 30 70"
+fn parse_ydraw(line: &str) -> Option<GatewayLine> {
+    let mut parts = line.split_whitespace();
+    let time = parts.next()?;
+    if time.len() < 8 || time.as_bytes()[2] != b':' {
+        return None;
+    }
+    if !matches!(parts.next()?, "R" | "T") {
+        return None;
+    }
+    let id = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let data = parse_hex_bytes(parts)?;
+    if data.is_empty() || data.len() > 8 {
+        return None;
+    }
+    let (priority, pgn, source, destination) = decode_can_id(id);
+    Some(GatewayLine::Frame(N2kMessage { pgn, priority, source, destination, data }))
+}
+
+/// Actisense N2K ASCII: "A173321.107 23FF7 1F513 012F3070002F3070"
+fn parse_actisense_ascii(line: &str) -> Option<GatewayLine> {
+    let mut parts = line.split_whitespace();
+    let time = parts.next()?;
+    if !time.starts_with('A') || !time[1..].chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    // Fields are sliced by byte offset, so anything non-ASCII (line noise, or
+    // U+FFFD from lossy decoding) is rejected before it can split a character
+    let header = parts.next()?;
+    if header.len() != 5 || !header.is_ascii() {
+        return None;
+    }
+    let source = u8::from_str_radix(&header[0..2], 16).ok()?;
+    let destination = u8::from_str_radix(&header[2..4], 16).ok()?;
+    let priority = u8::from_str_radix(&header[4..5], 16).ok()?;
+    let pgn = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let hex = parts.next()?;
+    if hex.len() % 2 != 0 || !hex.is_ascii() || parts.next().is_some() {
+        return None;
+    }
+    let data = parse_hex_bytes((0..hex.len()).step_by(2).map(|i| &hex[i..i + 2]))?;
+    Some(GatewayLine::Message(N2kMessage { pgn, priority, source, destination, data }))
+}
+
+fn parse_line(line: &str) -> Option<GatewayLine> {
+    let line = line.trim();
+    parse_ydraw(line).or_else(|| parse_actisense_ascii(line))
+}
+
+struct PartialPacket {
+    sequence: u8,
+    expected_len: usize,
+    next_frame: u8,
+    data: Vec<u8>,
+    started: Instant,
+}
+
+/// Joins fast-packet frames into messages, per source and PGN
+#[derive(Default)]
+pub struct FastPacketAssembler {
+    partial: HashMap<(u8, u32), PartialPacket>,
+}
+
+impl FastPacketAssembler {
+    /// Add a frame; returns the message once its last frame arrives.
+    /// Frames out of order or from an interleaved sequence restart assembly.
+    pub fn push(&mut self, frame: &N2kMessage, now: Instant) -> Option<N2kMessage> {
+        let &first = frame.data.first()?;
+        let sequence = first >> 5;
+        let index = first & 0x1F;
+        let key = (frame.source, frame.pgn);
+        self.partial.retain(|_, p| now.saturating_duration_since(p.started) < FAST_PACKET_TIMEOUT);
+
+        if index == 0 {
+            let expected_len = *frame.data.get(1)? as usize;
+            let mut data = Vec::with_capacity(expected_len);
+            data.extend_from_slice(&frame.data[2..]);
+            self.partial.insert(key, PartialPacket { sequence, expected_len, next_frame: 1, data, started: now });
+        } else {
+            let partial = self.partial.get_mut(&key)?;
+            if partial.sequence != sequence || partial.next_frame != index {
+                self.partial.remove(&key);
+                return None;
+            }
+            partial.data.extend_from_slice(&frame.data[1..]);
+            partial.next_frame += 1;
+        }
+
+        let partial = self.partial.get(&key)?;
+        if partial.data.len() < partial.expected_len {
+            return None;
+        }
+        let mut packet = self.partial.remove(&key)?;
+        packet.data.truncate(packet.expected_len);
+        Some(N2kMessage { data: packet.data, ..frame.clone() })
+    }
+}
+
+// Field readers returning None for the "not available" values
+fn u8_at(data: &[u8], i: usize) -> Option<u8> {
+    data.get(i).copied().filter(|&v| v != 0xFF)
+}
+
+fn u16_at(data: &[u8], i: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?)).filter(|&v| v < 0xFFFE)
+}
+
+fn i16_at(data: &[u8], i: usize) -> Option<i16> {
+    Some(i16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?)).filter(|&v| v < 0x7FFE)
+}
+
+fn u32_at(data: &[u8], i: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(i..i + 4)?.try_into().ok()?)).filter(|&v| v < 0xFFFF_FFFE)
+}
+
+fn i32_at(data: &[u8], i: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(data.get(i..i + 4)?.try_into().ok()?)).filter(|&v| v < 0x7FFF_FFFE)
+}
+
+fn i64_at(data: &[u8], i: usize) -> Option<i64> {
+    Some(i64::from_le_bytes(data.get(i..i + 8)?.try_into().ok()?)).filter(|&v| v < 0x7FFF_FFFF_FFFF_FFFE)
+}
+
+/// AIS navigational status, named as in Signal K
+fn nav_state_name(status: u8) -> Option<&'static str> {
+    Some(match status {
+        0 => "motoring",
+        1 => "anchored",
+        2 => "not under command",
+        3 => "restricted manouverability",
+        4 => "constrained by draft",
+        5 => "moored",
+        6 => "aground",
+        7 => "fishing",
+        8 => "sailing",
+        _ => return None,
+    })
+}
+
+/// Decodes NMEA 2000 gateway lines into the shared data model
+#[derive(Default)]
+pub struct Nmea2000Decoder {
+    assembler: FastPacketAssembler,
+}
+
+impl Nmea2000Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse one gateway line and reassemble it into a complete message if needed
+    pub fn message(&mut self, line: &str, now: Instant) -> Option<N2kMessage> {
+        match parse_line(line)? {
+            GatewayLine::Message(message) => Some(message),
+            GatewayLine::Frame(frame) if FAST_PACKET_PGNS.contains(&frame.pgn) => self.assembler.push(&frame, now),
+            GatewayLine::Frame(frame) => Some(frame),
+        }
+    }
+
+    /// Apply one gateway line. Instruments and AIS targets are updated in place;
+    /// own position and motion are returned for the GPS pipeline.
+    pub fn handle_line(&mut self, line: &str, vessel: &mut VesselData, now: Instant) -> Option<OwnVesselUpdate> {
+        let message = self.message(line, now)?;
+        Self::apply(&message, vessel, now)
+    }
+
+    /// Apply a complete message
+    pub fn apply(message: &N2kMessage, vessel: &mut VesselData, now: Instant) -> Option<OwnVesselUpdate> {
+        let data = message.data.as_slice();
+        let mut update = OwnVesselUpdate::default();
+        let instruments = &mut vessel.instruments;
+
+        match message.pgn {
+            // Position, rapid update
+            129025 => {
+                update.gps.latitude = i32_at(data, 0).map(|v| v as f64 * 1e-7);
+                update.gps.longitude = i32_at(data, 4).map(|v| v as f64 * 1e-7);
+                update.is_fix = update.gps.latitude.is_some() && update.gps.longitude.is_some();
+            }
+            // COG and SOG, rapid update
+            129026 => {
+                // COG reference 0 is true; a magnetic COG is not used
+                if data.get(1).map(|b| b & 0x03) == Some(0) {
+                    update.gps.course = u16_at(data, 2).map(|v| degrees(v as f64 * 1e-4));
+                }
+                update.gps.speed_knots = u16_at(data, 4).map(|v| knots(v as f64 * 0.01));
+            }
+            // GNSS position data
+            129029 => {
+                if let (Some(days), Some(seconds)) = (u16_at(data, 1), u32_at(data, 3)) {
+                    let millis = days as i64 * 86_400_000 + seconds as i64 / 10;
+                    update.gps.timestamp = DateTime::<Utc>::from_timestamp_millis(millis).map(|t| t.to_rfc3339());
+                }
+                let method = data.get(31).map(|b| b >> 4);
+                update.gps.latitude = i64_at(data, 7).map(|v| v as f64 * 1e-16);
+                update.gps.longitude = i64_at(data, 15).map(|v| v as f64 * 1e-16);
+                update.gps.altitude = i64_at(data, 23).map(|v| v as f64 * 1e-6);
+                update.gps.satellites = u8_at(data, 33).map(u32::from);
+                update.gps.hdop = i16_at(data, 34).map(|v| v as f32 * 0.01);
+                update.gps.pdop = i16_at(data, 36).map(|v| v as f32 * 0.01);
+                if let Some(method) = method {
+                    let (quality, name) = match method {
+                        0 => (0, "No Fix"),
+                        1 => (1, "GPS"),
+                        2 => (2, "DGPS"),
+                        3 => (3, "PPS"),
+                        4 => (4, "RTK"),
+                        5 => (5, "Float RTK"),
+                        6 => (6, "Estimated"),
+                        7 => (7, "Manual"),
+                        _ => (8, "Simulation"),
+                    };
+                    update.gps.fix_quality = Some(quality);
+                    update.gps.fix_type = Some(name.to_string());
+                }
+                update.is_fix = matches!(method, Some(1..=5))
+                    && update.gps.latitude.is_some()
+                    && update.gps.longitude.is_some();
+                if !update.is_fix {
+                    update.gps.latitude = None;
+                    update.gps.longitude = None;
+                }
+            }
+            // Vessel heading
+            127250 => {
+                let heading = u16_at(data, 1).map(|v| v as f64 * 1e-4);
+                let deviation = i16_at(data, 3).map(|v| v as f64 * 1e-4).unwrap_or(0.0);
+                let variation = i16_at(data, 5).map(|v| v as f64 * 1e-4);
+                if variation.is_some() {
+                    instruments.magnetic_variation = variation.map(f64::to_degrees);
+                }
+                match data.get(7).map(|b| b & 0x03) {
+                    Some(0) => update.gps.heading = heading.map(degrees),
+                    Some(1) => {
+                        instruments.heading_magnetic = heading.map(|h| degrees(h + deviation));
+                        let variation = variation.or(instruments.magnetic_variation.map(f64::to_radians));
+                        if let (Some(h), Some(v)) = (heading, variation) {
+                            update.gps.heading = Some(degrees(h + deviation + v));
+                        }
+                    }
+                    _ => return None,
+                }
+                update.heading = update.gps.heading;
+            }
+            // Water depth
+            128267 => {
+                instruments.depth_m = u32_at(data, 1).map(|v| v as f64 * 0.01);
+                // A negative offset is the distance from the transducer to the keel
+                if let (Some(depth), Some(offset)) = (instruments.depth_m, i16_at(data, 5)) {
+                    if offset < 0 {
+                        instruments.depth_below_keel_m = Some(depth + offset as f64 * 0.001);
+                    }
+                }
+                return None;
+            }
+            // Wind data
+            130306 => {
+                let speed = u16_at(data, 1).map(|v| knots(v as f64 * 0.01));
+                let angle = u16_at(data, 3).map(|v| v as f64 * 1e-4);
+                match data.get(5).map(|b| b & 0x07) {
+                    Some(0) => {
+                        instruments.true_wind_direction = angle.map(degrees);
+                        instruments.true_wind_speed_kn = speed;
+                    }
+                    Some(2) => {
+                        instruments.apparent_wind_angle = angle.map(relative_degrees);
+                        instruments.apparent_wind_speed_kn = speed;
+                    }
+                    Some(3) | Some(4) => {
+                        instruments.true_wind_angle = angle.map(relative_degrees);
+                        instruments.true_wind_speed_kn = speed;
+                    }
+                    _ => {}
+                }
+                return None;
+            }
+            // AIS class A and class B position reports
+            129038 | 129039 => {
+                let mmsi = u32_at(data, 1)?;
+                let target = vessel.ais_target(&format!("vessels.urn:mrn:imo:mmsi:{:09}", mmsi), now);
+                target.mmsi = Some(format!("{:09}", mmsi));
+                target.longitude = i32_at(data, 5).map(|v| v as f64 * 1e-7);
+                target.latitude = i32_at(data, 9).map(|v| v as f64 * 1e-7);
+                target.cog = u16_at(data, 14).map(|v| degrees(v as f64 * 1e-4));
+                target.sog_knots = u16_at(data, 16).map(|v| knots(v as f64 * 0.01));
+                target.heading = u16_at(data, 21).map(|v| degrees(v as f64 * 1e-4));
+                if message.pgn == 129038 {
+                    target.nav_state = data.get(25).and_then(|b| nav_state_name(b & 0x0F)).map(str::to_string);
+                }
+                target.last_update = Some(Utc::now().to_rfc3339());
+                return None;
+            }
+            _ => return None,
+        }
+
+        instruments.updated_at = Some(Utc::now().to_rfc3339());
+        Some(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ydraw(pgn: u32, source: u8, data: &[u8]) -> String {
+        let id = (2 << 26) | (pgn << 8) | source as u32;
+        let bytes: Vec<String> = data.iter().map(|b| format!("{:02X}", b)).collect();
+        format!("17:33:21.107 R {:08X} {}", id, bytes.join(" "))
+    }
+
+    /// Split a message into fast-packet frames the way a device sends them
+    fn fast_packet_lines(pgn: u32, source: u8, sequence: u8, payload: &[u8]) -> Vec<String> {
+        let mut frames = vec![[&[sequence << 5, payload.len() as u8][..], &payload[..6]].concat()];
+        for (i, chunk) in payload[6..].chunks(7).enumerate() {
+            let mut frame = vec![(sequence << 5) | (i as u8 + 1)];
+            frame.extend_from_slice(chunk);
+            frame.resize(8, 0xFF);
+            frames.push(frame);
+        }
+        frames.iter().map(|f| ydraw(pgn, source, f)).collect()
+    }
+
+    fn gnss_position(lat: f64, lon: f64) -> Vec<u8> {
+        let mut data = vec![0x01];
+        data.extend_from_slice(&19875u16.to_le_bytes()); // 2024-06-01
+        data.extend_from_slice(&(36_000u32 * 10_000).to_le_bytes()); // 10:00:00
+        data.extend_from_slice(&((lat * 1e16) as i64).to_le_bytes());
+        data.extend_from_slice(&((lon * 1e16) as i64).to_le_bytes());
+        data.extend_from_slice(&12_500_000i64.to_le_bytes());
+        data.extend_from_slice(&[0x20, 0xFC, 11]); // DGNSS, 11 satellites
+        data.extend_from_slice(&80i16.to_le_bytes());
+        data.extend_from_slice(&140i16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_gateway_line_formats() {
+        assert!(!is_nmea2000_line("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A"));
+        // Garbage from a noisy link, with multi-byte characters where hex is expected
+        assert!(!is_nmea2000_line("A173321.107 2\u{fffd}F5 1F50B 01D80400000CFEFF"));
+        assert!(!is_nmea2000_line("A173321.107 23FF5 1F50B 01\u{fffd}0400000CFE"));
+        assert!(!is_nmea2000_line("A1 \u{fffd}\u{fffd} \u{0}\u{7f}\u{fffd}"));
+
+        // The same depth message as YD RAW and as Actisense ASCII
+        let depth_ydraw = "17:33:21.107 R 15F50B23 01 D8 04 00 00 0C FE FF";
+        let depth_actisense = "A173321.107 23FF5 1F50B 01D80400000CFEFF";
+        let mut decoder = Nmea2000Decoder::new();
+        let now = Instant::now();
+        let from_ydraw = decoder.message(depth_ydraw, now).unwrap();
+        let from_actisense = decoder.message(depth_actisense, now).unwrap();
+        assert_eq!(from_ydraw, from_actisense);
+        assert_eq!((from_ydraw.pgn, from_ydraw.source, from_ydraw.priority), (128267, 0x23, 5));
+
+        let mut vessel = VesselData::default();
+        assert!(decoder.handle_line(depth_ydraw, &mut vessel, now).is_none());
+        assert!((vessel.instruments.depth_m.unwrap() - 12.4).abs() < 1e-9);
+        assert!((vessel.instruments.depth_below_keel_m.unwrap() - 11.9).abs() < 1e-9);
+
+        // Apparent wind 15.0 m/s at 315 degrees relative
+        let wind = ydraw(130306, 0x23, &[0x00, 0xDC, 0x05, 0xC2, 0xD6, 0x02, 0xFF, 0xFF]);
+        decoder.handle_line(&wind, &mut vessel, now);
+        assert!((vessel.instruments.apparent_wind_angle.unwrap() + 45.0).abs() < 0.01);
+        assert!((vessel.instruments.apparent_wind_speed_kn.unwrap() - 29.16).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fast_packet_position_and_ais() {
+        let mut decoder = Nmea2000Decoder::new();
+        let mut vessel = VesselData::default();
+        let now = Instant::now();
+
+        let position = fast_packet_lines(129029, 0x10, 3, &gnss_position(-36.84, 174.77));
+        assert_eq!(position.len(), 7);
+        // A stray continuation frame from another sequence does not corrupt assembly
+        let stray = fast_packet_lines(129029, 0x10, 5, &gnss_position(0.0, 0.0)).remove(2);
+        assert!(decoder.handle_line(&stray, &mut vessel, now).is_none());
+
+        let mut updates: Vec<OwnVesselUpdate> =
+            position.iter().filter_map(|line| decoder.handle_line(line, &mut vessel, now)).collect();
+        assert_eq!(updates.len(), 1);
+        let update = updates.remove(0);
+        assert!(update.is_fix);
+        assert!((update.gps.latitude.unwrap() + 36.84).abs() < 1e-9);
+        assert!((update.gps.longitude.unwrap() - 174.77).abs() < 1e-9);
+        assert_eq!(update.gps.fix_type.as_deref(), Some("DGPS"));
+        assert_eq!(update.gps.satellites, Some(11));
+        assert!((update.gps.hdop.unwrap() - 0.8).abs() < 1e-6);
+        assert_eq!(update.gps.timestamp.as_deref(), Some("2024-06-01T10:00:00+00:00"));
+
+        // Class A position report: 5 m/s on 90 degrees, under way sailing
+        let mut ais = vec![0x01];
+        ais.extend_from_slice(&512_123_456u32.to_le_bytes());
+        ais.extend_from_slice(&1_748_000_000i32.to_le_bytes());
+        ais.extend_from_slice(&(-368_300_000i32).to_le_bytes());
+        ais.push(0x00);
+        ais.extend_from_slice(&15_708u16.to_le_bytes());
+        ais.extend_from_slice(&500u16.to_le_bytes());
+        ais.extend_from_slice(&[0, 0, 0]);
+        ais.extend_from_slice(&0xFFFFu16.to_le_bytes());
+        ais.extend_from_slice(&0x7FFFi16.to_le_bytes());
+        ais.extend_from_slice(&[0x08, 0xFF]);
+        for line in fast_packet_lines(129038, 0x40, 1, &ais) {
+            decoder.handle_line(&line, &mut vessel, now);
+        }
+        let targets = vessel.current_ais_targets(now);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].mmsi.as_deref(), Some("512123456"));
+        assert!((targets[0].latitude.unwrap() + 36.83).abs() < 1e-9);
+        assert!((targets[0].cog.unwrap() - 90.0).abs() < 0.01);
+        assert!((targets[0].sog_knots.unwrap() - 9.72).abs() < 0.01);
+        assert_eq!(targets[0].heading, None);
+        assert_eq!(targets[0].nav_state.as_deref(), Some("sailing"));
+    }
+}
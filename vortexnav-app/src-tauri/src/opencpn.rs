@@ -0,0 +1,496 @@
+// OpenCPN navobj.xml and GPX importer
+// Maps OpenCPN's GPX extensions onto waypoints, routes, route tags and tracks
+
+use quick_xml::de::from_str;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::database::{ConfigDatabase, Route, RouteTag, Track, TrackPoint, Waypoint};
+use crate::gpx::{
+    garmin_color_to_hex, track_distance_nm, track_points_from_gpx, track_time_span, GpxImportResult, GpxRoutePoint,
+    GpxTrack,
+};
+
+/// Tag given to every route brought over from OpenCPN
+pub const OPENCPN_ROUTE_TAG: &str = "OpenCPN";
+
+/// OpenCPN's own route colour when a route doesn't set one
+const OPENCPN_DEFAULT_ROUTE_COLOR: &str = "#ff0000";
+
+/// Routes, waypoints and tracks read from OpenCPN, already in our own types
+#[derive(Debug, Clone, Default)]
+pub struct NavObj {
+    pub waypoints: Vec<Waypoint>,
+    pub routes: Vec<NavObjRoute>,
+    pub tracks: Vec<NavObjTrack>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NavObjRoute {
+    pub route: Route,
+    pub waypoints: Vec<Waypoint>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NavObjTrack {
+    pub track: Track,
+    pub points: Vec<TrackPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "gpx")]
+struct NavObjXml {
+    #[serde(default)]
+    wpt: Vec<WptXml>,
+    #[serde(default)]
+    rte: Vec<RteXml>,
+    #[serde(default)]
+    trk: Vec<TrkXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WptXml {
+    #[serde(rename = "@lat")]
+    lat: f64,
+    #[serde(rename = "@lon")]
+    lon: f64,
+    time: Option<String>,
+    name: Option<String>,
+    desc: Option<String>,
+    sym: Option<String>,
+    #[serde(default)]
+    extensions: ExtensionsXml,
+}
+
+#[derive(Debug, Deserialize)]
+struct RteXml {
+    name: Option<String>,
+    desc: Option<String>,
+    #[serde(default)]
+    extensions: ExtensionsXml,
+    #[serde(default)]
+    rtept: Vec<WptXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrkXml {
+    name: Option<String>,
+    desc: Option<String>,
+    #[serde(default)]
+    extensions: ExtensionsXml,
+    #[serde(default)]
+    trkseg: Vec<TrksegXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrksegXml {
+    #[serde(default)]
+    trkpt: Vec<WptXml>,
+}
+
+/// The `opencpn:` and `gpxx:` elements we use; matched by local name
+#[derive(Debug, Default, Deserialize)]
+struct ExtensionsXml {
+    guid: Option<String>,
+    /// "0" when hidden on the chart
+    viz: Option<String>,
+    /// "0" when the name isn't shown
+    viz_name: Option<String>,
+    /// Nautical miles
+    arrival_radius: Option<f64>,
+    /// Knots
+    planned_speed: Option<f64>,
+    #[serde(rename = "RouteExtension")]
+    route_extension: Option<GarminExtensionXml>,
+    #[serde(rename = "TrackExtension")]
+    track_extension: Option<GarminExtensionXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GarminExtensionXml {
+    #[serde(rename = "DisplayColor")]
+    display_color: Option<String>,
+}
+
+impl ExtensionsXml {
+    fn visible(&self) -> bool {
+        self.viz.as_deref().map(str::trim) != Some("0")
+    }
+
+    fn name_visible(&self) -> bool {
+        self.viz_name.as_deref().map(str::trim) != Some("0")
+    }
+
+    fn color(&self) -> Option<String> {
+        self.route_extension
+            .as_ref()
+            .or(self.track_extension.as_ref())
+            .and_then(|e| e.display_color.as_deref())
+            .and_then(garmin_color_to_hex)
+    }
+}
+
+/// Parse OpenCPN's navobj.xml, or a GPX file exported from OpenCPN
+pub fn parse_navobj_file(path: &Path) -> Result<NavObj, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read OpenCPN file: {}", e))?;
+    parse_navobj_string(&content)
+}
+
+pub fn parse_navobj_string(content: &str) -> Result<NavObj, String> {
+    let xml: NavObjXml = from_str(content).map_err(|e| format!("Failed to parse OpenCPN file: {}", e))?;
+
+    let waypoints = xml
+        .wpt
+        .into_iter()
+        .enumerate()
+        .map(|(i, w)| to_waypoint(w, || format!("Waypoint {}", i + 1)))
+        .collect();
+
+    let routes = xml
+        .rte
+        .into_iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let name = r.name.clone().unwrap_or_else(|| format!("OpenCPN Route {}", i + 1));
+            let route = Route {
+                name: name.clone(),
+                description: r.desc,
+                color: r.extensions.color().or_else(|| Some(OPENCPN_DEFAULT_ROUTE_COLOR.to_string())),
+                hidden: !r.extensions.visible(),
+                // OpenCPN leaves the planned speed at zero until it is set
+                estimated_speed_kn: r.extensions.planned_speed.filter(|s| *s > 0.0).unwrap_or(Route::default().estimated_speed_kn),
+                uuid: r.extensions.guid.clone(),
+                ..Route::default()
+            };
+            let waypoints = r
+                .rtept
+                .into_iter()
+                .enumerate()
+                .map(|(n, p)| to_waypoint(p, || format!("{} - WP{}", name, n + 1)))
+                .collect();
+            NavObjRoute { route, waypoints }
+        })
+        .collect();
+
+    let tracks = xml
+        .trk
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| to_track(t, i))
+        .collect();
+
+    Ok(NavObj { waypoints, routes, tracks })
+}
+
+fn to_waypoint(w: WptXml, default_name: impl FnOnce() -> String) -> Waypoint {
+    Waypoint {
+        id: None,
+        name: w.name.filter(|n| !n.trim().is_empty()).unwrap_or_else(default_name),
+        lat: w.lat,
+        lon: w.lon,
+        description: w.desc,
+        symbol: w.sym,
+        show_label: w.extensions.name_visible(),
+        hidden: !w.extensions.visible(),
+        created_at: w.time,
+        uuid: w.extensions.guid,
+        updated_at: None,
+        group_id: None,
+        arrival_radius_nm: w.extensions.arrival_radius.filter(|r| *r > 0.0),
+        depth_m: None,
+        temperature_c: None,
+    }
+}
+
+fn to_track(t: TrkXml, index: usize) -> NavObjTrack {
+    // Stored the same way as a GPX track
+    let segments = t
+        .trkseg
+        .into_iter()
+        .map(|seg| {
+            seg.trkpt
+                .into_iter()
+                .map(|p| GpxRoutePoint {
+                    name: None,
+                    lat: p.lat,
+                    lon: p.lon,
+                    ele: None,
+                    time: p.time,
+                    desc: None,
+                    sym: None,
+                    uuid: None,
+                    extensions: Default::default(),
+                })
+                .collect()
+        })
+        .collect();
+    let points = track_points_from_gpx(&GpxTrack { name: None, desc: None, uuid: None, color: None, segments });
+    let (started_at, ended_at) = track_time_span(&points);
+    let track = Track {
+        name: t.name.unwrap_or_else(|| format!("OpenCPN Track {}", index + 1)),
+        description: t.desc,
+        color: t.extensions.color().or(Track::default().color),
+        started_at,
+        ended_at,
+        total_distance_nm: Some(track_distance_nm(&points)),
+        point_count: points.len() as i64,
+        hidden: !t.extensions.visible(),
+        uuid: t.extensions.guid,
+        ..Track::default()
+    };
+    NavObjTrack { track, points }
+}
+
+/// Store everything read from OpenCPN. Objects whose OpenCPN GUID is already
+/// known here are updated, so migrating again after more sailing only adds what's new.
+pub fn import_navobj(db: &ConfigDatabase, navobj: NavObj) -> GpxImportResult {
+    let mut result = GpxImportResult::default();
+    // Route points shared between routes, or also listed on their own, are saved once
+    let mut saved: HashMap<String, i64> = HashMap::new();
+
+    for waypoint in navobj.waypoints {
+        let name = waypoint.name.clone();
+        if let Err(e) = save_waypoint(db, waypoint, &mut saved, &mut result) {
+            result.errors.push(format!("Failed to import waypoint '{}': {}", name, e));
+        }
+    }
+
+    let mut tag_id = None;
+    for NavObjRoute { route, waypoints } in navobj.routes {
+        if let Err(e) = save_route(db, route.clone(), waypoints, &mut tag_id, &mut saved, &mut result) {
+            result.errors.push(format!("Failed to import route '{}': {}", route.name, e));
+        }
+    }
+
+    for NavObjTrack { track, points } in navobj.tracks {
+        if points.is_empty() {
+            continue;
+        }
+        if let Err(e) = save_track(db, &track, &points, &mut result) {
+            result.errors.push(format!("Failed to import track '{}': {}", track.name, e));
+        }
+    }
+
+    result
+}
+
+fn save_waypoint(
+    db: &ConfigDatabase,
+    waypoint: Waypoint,
+    saved: &mut HashMap<String, i64>,
+    result: &mut GpxImportResult,
+) -> rusqlite::Result<i64> {
+    let uuid = waypoint.uuid.clone();
+    if let Some(id) = uuid.as_ref().and_then(|uuid| saved.get(uuid)) {
+        return Ok(*id);
+    }
+    let existing = match uuid.as_deref() {
+        Some(uuid) => db.find_waypoint_id(uuid)?,
+        None => None,
+    };
+    let id = match existing {
+        Some(id) => {
+            db.update_waypoint(&Waypoint { id: Some(id), ..waypoint })?;
+            result.waypoints_updated += 1;
+            id
+        }
+        None => {
+            let id = db.create_waypoint(&waypoint)?;
+            result.waypoints_imported += 1;
+            id
+        }
+    };
+    if let Some(uuid) = uuid {
+        saved.insert(uuid, id);
+    }
+    Ok(id)
+}
+
+fn save_route(
+    db: &ConfigDatabase,
+    route: Route,
+    waypoints: Vec<Waypoint>,
+    tag_id: &mut Option<i64>,
+    saved: &mut HashMap<String, i64>,
+    result: &mut GpxImportResult,
+) -> rusqlite::Result<()> {
+    let mut waypoint_ids = Vec::new();
+    for waypoint in waypoints {
+        waypoint_ids.push(save_waypoint(db, waypoint, saved, result)?);
+    }
+    if waypoint_ids.is_empty() {
+        return Ok(());
+    }
+
+    let tag = match *tag_id {
+        Some(id) => id,
+        None => *tag_id.insert(opencpn_tag(db)?),
+    };
+    let existing = match route.uuid.as_deref() {
+        Some(uuid) => db.find_route_id(uuid)?.map(|id| db.get_route(id)).transpose()?.flatten(),
+        None => None,
+    };
+    match existing {
+        Some(current) => {
+            let mut tag_ids: Vec<i64> = current.tags.iter().filter_map(|t| t.id).collect();
+            if !tag_ids.contains(&tag) {
+                tag_ids.push(tag);
+            }
+            let updated = Route { id: current.route.id, is_active: current.route.is_active, total_distance_nm: None, ..route };
+            db.update_route(&updated, &waypoint_ids, &tag_ids)?;
+            result.routes_updated += 1;
+        }
+        None => {
+            db.create_route(&route, &waypoint_ids, &[tag])?;
+            result.routes_imported += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Tracks are history, so a known track only has its details refreshed
+fn save_track(db: &ConfigDatabase, track: &Track, points: &[TrackPoint], result: &mut GpxImportResult) -> rusqlite::Result<()> {
+    let existing = match track.uuid.as_deref() {
+        Some(uuid) => db.find_track_id(uuid)?.map(|id| db.get_track(id)).transpose()?.flatten(),
+        None => None,
+    };
+    match existing {
+        Some(current) => {
+            db.update_track(&Track { id: current.id, ..track.clone() })?;
+            result.tracks_updated += 1;
+        }
+        None => {
+            db.import_track(track, points)?;
+            result.tracks_imported += 1;
+        }
+    }
+    Ok(())
+}
+
+/// The OpenCPN route tag, created on first use
+fn opencpn_tag(db: &ConfigDatabase) -> rusqlite::Result<i64> {
+    if let Some(id) = db.get_route_tags()?.into_iter().find(|t| t.name == OPENCPN_ROUTE_TAG).and_then(|t| t.id) {
+        return Ok(id);
+    }
+    db.create_route_tag(&RouteTag {
+        id: None,
+        name: OPENCPN_ROUTE_TAG.to_string(),
+        color: Some("#2563eb".to_string()),
+        created_at: None,
+        uuid: None,
+        updated_at: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAVOBJ: &str = r##"<?xml version="1.0"?>
+<gpx version="1.1" creator="OpenCPN" xmlns="http://www.topografix.com/GPX/1/1" xmlns:gpxx="http://www.garmin.com/xmlschemas/GpxExtensions/v3" xmlns:opencpn="http://www.opencpn.org">
+    <wpt lat="-36.425000000" lon="174.815000000">
+        <time>2025-12-01T02:00:00Z</time>
+        <name>Bon Accord</name>
+        <sym>anchorage</sym>
+        <type>WPT</type>
+        <extensions>
+            <opencpn:guid>6f1c2a4e-0000-4000-8000-000000000001</opencpn:guid>
+            <opencpn:viz>1</opencpn:viz>
+            <opencpn:viz_name>0</opencpn:viz_name>
+            <opencpn:arrival_radius>0.250</opencpn:arrival_radius>
+            <opencpn:waypoint_range_rings visible="false" number="0" step="1" units="0" colour="#FF0000" />
+            <opencpn:scale_min_max UseScale="false" ScaleMin="2147483646" ScaleMax="0" />
+        </extensions>
+    </wpt>
+    <rte>
+        <name>Kawau run</name>
+        <extensions>
+            <opencpn:start>Westhaven</opencpn:start>
+            <opencpn:end>Bon Accord</opencpn:end>
+            <opencpn:viz>0</opencpn:viz>
+            <opencpn:guid>6f1c2a4e-0000-4000-8000-0000000000aa</opencpn:guid>
+            <opencpn:planned_speed>6.50</opencpn:planned_speed>
+            <opencpn:time_display>PC</opencpn:time_display>
+            <gpxx:RouteExtension>
+                <gpxx:IsAutoNamed>false</gpxx:IsAutoNamed>
+                <gpxx:DisplayColor>DarkBlue</gpxx:DisplayColor>
+            </gpxx:RouteExtension>
+        </extensions>
+        <rtept lat="-36.840000000" lon="174.740000000">
+            <name>Westhaven</name>
+            <sym>diamond</sym>
+            <extensions>
+                <opencpn:guid>6f1c2a4e-0000-4000-8000-000000000002</opencpn:guid>
+            </extensions>
+        </rtept>
+        <rtept lat="-36.425000000" lon="174.815000000">
+            <name>Bon Accord</name>
+            <sym>anchorage</sym>
+            <extensions>
+                <opencpn:guid>6f1c2a4e-0000-4000-8000-000000000001</opencpn:guid>
+                <opencpn:arrival_radius>0.250</opencpn:arrival_radius>
+            </extensions>
+        </rtept>
+    </rte>
+    <trk>
+        <name>Sunday sail</name>
+        <extensions>
+            <opencpn:guid>6f1c2a4e-0000-4000-8000-0000000000bb</opencpn:guid>
+            <opencpn:viz>1</opencpn:viz>
+            <opencpn:style style="100" width="2" />
+            <gpxx:TrackExtension>
+                <gpxx:DisplayColor>Green</gpxx:DisplayColor>
+            </gpxx:TrackExtension>
+        </extensions>
+        <trkseg>
+            <trkpt lat="-36.800000000" lon="174.800000000"><time>2025-12-07T01:00:00Z</time></trkpt>
+            <trkpt lat="-36.790000000" lon="174.810000000"><time>2025-12-07T01:05:00Z</time></trkpt>
+        </trkseg>
+        <trkseg>
+            <trkpt lat="-36.700000000" lon="174.850000000"><time>2025-12-07T02:00:00Z</time></trkpt>
+            <trkpt lat="-36.690000000" lon="174.860000000"></trkpt>
+        </trkseg>
+    </trk>
+</gpx>"##;
+
+    #[test]
+    fn test_import_navobj() {
+        let dir = std::env::temp_dir().join(format!("vortexnav_opencpn_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let db = ConfigDatabase::new(&dir).unwrap();
+
+        let navobj = parse_navobj_string(NAVOBJ).unwrap();
+        let mark = &navobj.waypoints[0];
+        assert_eq!((mark.show_label, mark.hidden, mark.arrival_radius_nm), (false, false, Some(0.25)));
+        let route = &navobj.routes[0].route;
+        assert_eq!((route.hidden, route.estimated_speed_kn, route.color.as_deref()), (true, 6.5, Some("#00008b")));
+        let track = &navobj.tracks[0];
+        assert_eq!((track.track.color.as_deref(), track.points.len()), (Some("#00ff00"), 4));
+        // A point without a time is kept, but has none stored
+        assert_eq!(track.points[3].timestamp, None);
+        assert_eq!(track.track.ended_at.as_deref(), Some("2025-12-07T02:00:00Z"));
+
+        // The anchorage is both a mark and a route point, but stored once
+        let result = import_navobj(&db, navobj.clone());
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!((result.waypoints_imported, result.routes_imported, result.tracks_imported), (2, 1, 1));
+        assert_eq!(db.get_waypoints().unwrap().len(), 2);
+        let routes = db.get_routes().unwrap();
+        assert_eq!(routes[0].tags.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), [OPENCPN_ROUTE_TAG]);
+        assert_eq!(routes[0].waypoints[1].arrival_radius_nm, Some(0.25));
+        let tracks = db.get_tracks().unwrap();
+        assert_eq!(tracks[0].uuid.as_deref(), Some("6f1c2a4e-0000-4000-8000-0000000000bb"));
+        let points = db.get_track_points(tracks[0].id.unwrap()).unwrap();
+        assert_eq!((points.len(), points[3].timestamp.as_deref()), (4, None));
+
+        // Migrating again updates in place
+        let result = import_navobj(&db, navobj);
+        assert_eq!((result.waypoints_imported, result.routes_updated, result.tracks_updated), (0, 1, 1));
+        assert_eq!((db.get_waypoints().unwrap().len(), db.get_routes().unwrap().len(), db.get_tracks().unwrap().len()), (2, 1, 1));
+        assert_eq!(db.get_route_tags().unwrap().iter().filter(|t| t.name == OPENCPN_ROUTE_TAG).count(), 1);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,306 @@
+// Position Filter
+// α-β smoothing of GPS fixes, outlier rejection and GNSS integrity monitoring
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::database::PositionFilterSettings;
+use crate::navigation::EARTH_RADIUS_NM;
+use crate::nmea::GpsData;
+
+/// Consecutive rejected fixes after which the filter accepts the new position
+const MAX_CONSECUTIVE_REJECTIONS: u32 = 5;
+/// Jump allowed on top of the speed limit for ordinary fix noise (nm)
+const POSITION_NOISE_NM: f64 = 0.03;
+/// Fixes further apart than this restart the filter (seconds)
+const RESET_GAP_SECS: f64 = 30.0;
+/// Mean signal strength below which satellites are considered weak (dB-Hz)
+const WEAK_SIGNAL_DB: f32 = 25.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GnssIntegrity {
+    Good,
+    Degraded,
+    Poor,
+    NoFix,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FilterState {
+    lat: f64,
+    lon: f64,
+    /// Velocity north and east (knots)
+    vel_n: f64,
+    vel_e: f64,
+    at: Instant,
+}
+
+impl FilterState {
+    fn is_finite(&self) -> bool {
+        [self.lat, self.lon, self.vel_n, self.vel_e].iter().all(|v| v.is_finite())
+    }
+}
+
+/// Smooths positions and COG/SOG between the NMEA parser and the shared GpsData
+#[derive(Debug, Default)]
+pub struct PositionFilter {
+    settings: PositionFilterSettings,
+    state: Option<FilterState>,
+    /// Smoothed reported SOG/COG as a north/east vector (knots)
+    ground_vector: Option<(f64, f64)>,
+    last_cog: Option<f64>,
+    consecutive_rejections: u32,
+    pub rejected_fixes: u64,
+}
+
+impl PositionFilter {
+    pub fn new(settings: PositionFilterSettings) -> Self {
+        Self { settings, ..Default::default() }
+    }
+
+    pub fn set_settings(&mut self, settings: PositionFilterSettings) {
+        self.settings = settings;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.state = None;
+        self.ground_vector = None;
+        self.consecutive_rejections = 0;
+    }
+
+    /// Filter the output of one parsed sentence in place. Position, SOG and COG
+    /// are only taken from sentences carrying a fix; a rejected fix clears them
+    /// so the previous values are kept.
+    pub fn apply(&mut self, data: &mut GpsData, is_fix: bool, now: Instant) {
+        if !self.settings.enabled {
+            return;
+        }
+        if !is_fix {
+            data.latitude = None;
+            data.longitude = None;
+            data.speed_knots = None;
+            data.course = None;
+            return;
+        }
+        let (Some(lat), Some(lon)) = (data.latitude, data.longitude) else {
+            return;
+        };
+
+        if !lat.is_finite() || !lon.is_finite() || !self.update_position(lat, lon, now) {
+            data.latitude = None;
+            data.longitude = None;
+            data.speed_knots = None;
+            data.course = None;
+            return;
+        }
+        let state = self.state.unwrap();
+        data.latitude = Some(state.lat);
+        data.longitude = Some(state.lon);
+
+        let (sog, cog) = self.smooth_ground_track(data.speed_knots, data.course, &state);
+        data.speed_knots = Some(sog);
+        data.course = cog;
+    }
+
+    /// α-β update of the position. Returns false if the fix was rejected as an outlier.
+    fn update_position(&mut self, lat: f64, lon: f64, now: Instant) -> bool {
+        // Start again rather than carry a NaN or infinity forward
+        if self.state.is_some_and(|s| !s.is_finite()) {
+            self.reset();
+        }
+        let Some(prev) = self.state else {
+            self.state = Some(FilterState { lat, lon, vel_n: 0.0, vel_e: 0.0, at: now });
+            return true;
+        };
+
+        let dt = now.saturating_duration_since(prev.at).as_secs_f64();
+        if dt > RESET_GAP_SECS || self.consecutive_rejections >= MAX_CONSECUTIVE_REJECTIONS {
+            self.reset();
+            self.state = Some(FilterState { lat, lon, vel_n: 0.0, vel_e: 0.0, at: now });
+            return true;
+        }
+        let dt = dt.max(0.1);
+        let hours = dt / 3600.0;
+
+        // Offset of the fix from the last filtered position (nm, local plane)
+        let nm_per_deg_lon = EARTH_RADIUS_NM.to_radians() * prev.lat.to_radians().cos();
+        let d_n = (lat - prev.lat) * EARTH_RADIUS_NM.to_radians();
+        let d_e = (lon - prev.lon) * nm_per_deg_lon;
+
+        if d_n.hypot(d_e) > self.settings.max_speed_kn * hours + POSITION_NOISE_NM {
+            self.consecutive_rejections += 1;
+            self.rejected_fixes += 1;
+            return false;
+        }
+        self.consecutive_rejections = 0;
+
+        let (alpha, beta) = (self.settings.position_alpha, self.settings.velocity_beta);
+        let (res_n, res_e) = (d_n - prev.vel_n * hours, d_e - prev.vel_e * hours);
+        let n = prev.vel_n * hours + alpha * res_n;
+        let e = prev.vel_e * hours + alpha * res_e;
+
+        self.state = Some(FilterState {
+            lat: prev.lat + n / EARTH_RADIUS_NM.to_radians(),
+            lon: prev.lon + e / nm_per_deg_lon,
+            vel_n: prev.vel_n + beta * res_n / hours,
+            vel_e: prev.vel_e + beta * res_e / hours,
+            at: now,
+        });
+        true
+    }
+
+    /// Average the reported SOG/COG as a vector, more heavily at low speed where
+    /// the course is mostly noise. Falls back to the filter velocity.
+    fn smooth_ground_track(&mut self, sog: Option<f64>, cog: Option<f64>, state: &FilterState) -> (f64, Option<f64>) {
+        let measured = match (sog, cog) {
+            (Some(sog), Some(cog)) => (sog * cog.to_radians().cos(), sog * cog.to_radians().sin()),
+            (Some(sog), None) => (sog * self.last_cog.unwrap_or(0.0).to_radians().cos(), sog * self.last_cog.unwrap_or(0.0).to_radians().sin()),
+            _ => (state.vel_n, state.vel_e),
+        };
+        let speed = measured.0.hypot(measured.1);
+        let gain = self.settings.position_alpha * (speed / self.settings.low_speed_kn).clamp(0.1, 1.0);
+
+        let vector = match self.ground_vector.filter(|(n, e)| n.is_finite() && e.is_finite()) {
+            Some((n, e)) => (n + gain * (measured.0 - n), e + gain * (measured.1 - e)),
+            None => measured,
+        };
+        self.ground_vector = Some(vector);
+
+        let smoothed = vector.0.hypot(vector.1);
+        // Hold the last course while nearly stopped
+        if smoothed >= self.settings.low_speed_kn / 2.0 {
+            self.last_cog = Some(vector.1.atan2(vector.0).to_degrees().rem_euclid(360.0));
+        }
+        (smoothed, self.last_cog)
+    }
+
+    /// Integrity of the current solution from fix quality, HDOP, satellites used
+    /// and the signal strengths reported in GSV, with the reasons it is not good
+    pub fn integrity(&self, data: &GpsData) -> (GnssIntegrity, Vec<String>) {
+        if data.latitude.is_none() || data.fix_quality == Some(0) {
+            return (GnssIntegrity::NoFix, vec!["No position fix".to_string()]);
+        }
+
+        let mut poor = Vec::new();
+        let mut degraded = Vec::new();
+        let max_hdop = self.settings.max_hdop as f32;
+
+        if let Some(hdop) = data.hdop {
+            if hdop > max_hdop * 2.0 {
+                poor.push(format!("HDOP {:.1}", hdop));
+            } else if hdop > max_hdop {
+                degraded.push(format!("HDOP {:.1}", hdop));
+            }
+        }
+        if let Some(used) = data.satellites {
+            if used < 4 {
+                poor.push(format!("Only {} satellites used", used));
+            } else if used < self.settings.min_satellites {
+                degraded.push(format!("Only {} satellites used", used));
+            }
+        }
+
+        let signals: Vec<f32> = data.satellites_info.iter().filter_map(|s| s.snr).filter(|snr| *snr > 0.0).collect();
+        if !signals.is_empty() {
+            let mean = signals.iter().sum::<f32>() / signals.len() as f32;
+            if mean < WEAK_SIGNAL_DB {
+                degraded.push(format!("Weak signals ({:.0} dB-Hz)", mean));
+            }
+        }
+        if self.consecutive_rejections > 0 {
+            degraded.push(format!("{} position jumps rejected", self.consecutive_rejections));
+        }
+
+        let integrity = if !poor.is_empty() {
+            GnssIntegrity::Poor
+        } else if !degraded.is_empty() {
+            GnssIntegrity::Degraded
+        } else {
+            GnssIntegrity::Good
+        };
+        poor.extend(degraded);
+        (integrity, poor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fix(lat: f64, lon: f64, sog: f64, cog: f64) -> GpsData {
+        GpsData {
+            latitude: Some(lat),
+            longitude: Some(lon),
+            speed_knots: Some(sog),
+            course: Some(cog),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_smooths_anchor_jitter_and_rejects_jumps() {
+        let mut filter = PositionFilter::new(PositionFilterSettings::default());
+        let start = Instant::now();
+        let (lat0, lon0) = (-36.84, 174.77);
+
+        // At anchor: ±10 m jitter and random COG at 0.3 knots
+        let mut worst = 0.0_f64;
+        for i in 0..60 {
+            let jitter = if i % 2 == 0 { 0.0001 } else { -0.0001 };
+            let mut data = fix(lat0 + jitter, lon0 - jitter, 0.3, (i * 97 % 360) as f64);
+            filter.apply(&mut data, true, start + Duration::from_secs(i));
+            if i > 10 {
+                worst = worst.max((data.latitude.unwrap() - lat0).abs());
+                assert!(data.speed_knots.unwrap() < 0.3);
+            }
+        }
+        assert!(worst < 0.00006, "{}", worst);
+
+        // A fix 1 nm away one second later is impossible
+        let mut data = fix(lat0 + 1.0 / 60.0, lon0, 0.3, 0.0);
+        filter.apply(&mut data, true, start + Duration::from_secs(60));
+        assert!(data.latitude.is_none());
+        assert_eq!(filter.rejected_fixes, 1);
+
+        // Sentences without a fix never move the position
+        let mut data = fix(lat0 + 0.01, lon0, 0.3, 0.0);
+        filter.apply(&mut data, false, start + Duration::from_secs(61));
+        assert!(data.latitude.is_none());
+    }
+
+    #[test]
+    fn test_integrity() {
+        let filter = PositionFilter::new(PositionFilterSettings::default());
+        let mut data = fix(-36.84, 174.77, 5.0, 90.0);
+        data.fix_quality = Some(1);
+        data.satellites = Some(9);
+        data.hdop = Some(0.9);
+        assert_eq!(filter.integrity(&data).0, GnssIntegrity::Good);
+
+        data.hdop = Some(3.5);
+        assert_eq!(filter.integrity(&data).0, GnssIntegrity::Degraded);
+        data.satellites = Some(3);
+        let (integrity, reasons) = filter.integrity(&data);
+        assert_eq!(integrity, GnssIntegrity::Poor);
+        assert_eq!(reasons.len(), 2);
+
+        data.fix_quality = Some(0);
+        assert_eq!(filter.integrity(&data).0, GnssIntegrity::NoFix);
+    }
+
+    #[test]
+    fn test_non_finite_state_restarts() {
+        let mut filter = PositionFilter::new(PositionFilterSettings::default());
+        let start = Instant::now();
+        filter.state = Some(FilterState { lat: f64::NAN, lon: 174.77, vel_n: 0.0, vel_e: f64::INFINITY, at: start });
+        filter.ground_vector = Some((f64::NAN, 0.0));
+
+        let mut data = fix(-36.84, 174.77, 5.0, 90.0);
+        filter.apply(&mut data, true, start + Duration::from_secs(1));
+        assert_eq!((data.latitude, data.longitude), (Some(-36.84), Some(174.77)));
+        assert!((data.speed_knots.unwrap() - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,263 @@
+// Waypoint, route and track search
+// Nearest, radius and bounding box queries over the R-tree indexes, plus full-text search on names
+
+use rusqlite::Result as SqliteResult;
+use serde::{Deserialize, Serialize};
+
+use crate::database::{ConfigDatabase, SearchCandidate, SearchKind};
+use crate::navigation::{calculate_bearing, haversine_distance};
+
+/// Far enough to reach anywhere on Earth
+const HALF_CIRCUMFERENCE_NM: f64 = 10_800.0;
+
+/// Search hit. For routes and tracks `lat`/`lon` is their waypoint or point
+/// closest to the search origin, and None when they have no points.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub kind: SearchKind,
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    /// From the search origin, when one was given
+    pub distance_nm: Option<f64>,
+    pub bearing: Option<f64>,
+}
+
+/// Area between two latitudes and two longitudes. `min_lon` is greater than
+/// `max_lon` when the box crosses the antimeridian.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// Box holding every position within `radius_nm` of a point
+    pub fn around(lat: f64, lon: f64, radius_nm: f64) -> Self {
+        let radius = (radius_nm / 60.0).to_radians();
+        let lat_rad = lat.to_radians();
+        let min_lat = lat - radius_nm / 60.0;
+        let max_lat = lat + radius_nm / 60.0;
+        // Over a pole, or wide enough to wrap, every longitude is in range
+        if min_lat <= -90.0 || max_lat >= 90.0 || radius.sin() >= lat_rad.cos() {
+            return Self { min_lat: min_lat.max(-90.0), min_lon: -180.0, max_lat: max_lat.min(90.0), max_lon: 180.0 };
+        }
+        let delta_lon = (radius.sin() / lat_rad.cos()).asin().to_degrees();
+        Self { min_lat, min_lon: wrap_lon(lon - delta_lon), max_lat, max_lon: wrap_lon(lon + delta_lon) }
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let in_lon = if self.min_lon <= self.max_lon {
+            lon >= self.min_lon && lon <= self.max_lon
+        } else {
+            lon >= self.min_lon || lon <= self.max_lon
+        };
+        lat >= self.min_lat && lat <= self.max_lat && in_lon
+    }
+
+    // The index has no notion of wrapping, so a box across the antimeridian is queried as two
+    fn index_ranges(&self) -> Vec<(f64, f64)> {
+        if self.min_lon <= self.max_lon {
+            vec![(self.min_lon, self.max_lon)]
+        } else {
+            vec![(self.min_lon, 180.0), (-180.0, self.max_lon)]
+        }
+    }
+}
+
+fn wrap_lon(lon: f64) -> f64 {
+    if lon < -180.0 {
+        lon + 360.0
+    } else if lon > 180.0 {
+        lon - 360.0
+    } else {
+        lon
+    }
+}
+
+fn kinds_or_all(kinds: &[SearchKind]) -> &[SearchKind] {
+    if kinds.is_empty() { &SearchKind::ALL } else { kinds }
+}
+
+fn candidates(db: &ConfigDatabase, bbox: &BoundingBox, kinds: &[SearchKind]) -> SqliteResult<Vec<SearchCandidate>> {
+    let mut found = Vec::new();
+    for kind in kinds_or_all(kinds) {
+        for (min_lon, max_lon) in bbox.index_ranges() {
+            for candidate in db.find_in_bbox(*kind, bbox.min_lat, bbox.max_lat, min_lon, max_lon)? {
+                // A route or track spanning both halves shows up twice
+                if !found.iter().any(|c: &SearchCandidate| c.kind == candidate.kind && c.id == candidate.id) {
+                    found.push(candidate);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Turn a candidate into a result positioned at its point closest to `origin`,
+/// considering only `points`. Without points the result has no position.
+fn to_result(candidate: SearchCandidate, points: &[(f64, f64)], origin: Option<(f64, f64)>) -> SearchResult {
+    let (position, distance_nm, bearing) = match origin {
+        Some((from_lat, from_lon)) => match points
+            .iter()
+            .map(|&(lat, lon)| (lat, lon, haversine_distance(from_lat, from_lon, lat, lon)))
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+        {
+            Some((lat, lon, distance)) => (Some((lat, lon)), Some(distance), Some(calculate_bearing(from_lat, from_lon, lat, lon))),
+            None => (None, None, None),
+        },
+        None => (points.first().copied(), None, None),
+    };
+    SearchResult {
+        kind: candidate.kind,
+        id: candidate.id,
+        name: candidate.name,
+        description: candidate.description,
+        lat: position.map(|(lat, _)| lat),
+        lon: position.map(|(_, lon)| lon),
+        distance_nm,
+        bearing,
+    }
+}
+
+fn sort_by_distance(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| a.distance_nm.unwrap_or(0.0).total_cmp(&b.distance_nm.unwrap_or(0.0)));
+}
+
+/// Everything within `radius_nm` of a point, nearest first. Empty `kinds` searches all.
+pub fn within_radius(db: &ConfigDatabase, lat: f64, lon: f64, radius_nm: f64, kinds: &[SearchKind]) -> SqliteResult<Vec<SearchResult>> {
+    let mut results: Vec<SearchResult> = candidates(db, &BoundingBox::around(lat, lon, radius_nm), kinds)?
+        .into_iter()
+        .map(|c| {
+            let points = c.points.clone();
+            to_result(c, &points, Some((lat, lon)))
+        })
+        .filter(|r| r.distance_nm.is_some_and(|d| d <= radius_nm))
+        .collect();
+    sort_by_distance(&mut results);
+    Ok(results)
+}
+
+/// The `count` objects nearest a point, nearest first
+pub fn nearest(db: &ConfigDatabase, lat: f64, lon: f64, count: usize, kinds: &[SearchKind]) -> SqliteResult<Vec<SearchResult>> {
+    // Widen the search until it holds enough objects
+    let mut radius_nm = 1.0;
+    loop {
+        let mut results = within_radius(db, lat, lon, radius_nm, kinds)?;
+        if results.len() >= count || radius_nm >= HALF_CIRCUMFERENCE_NM {
+            results.truncate(count);
+            return Ok(results);
+        }
+        radius_nm *= 4.0;
+    }
+}
+
+/// Objects with a position inside the box. With an origin, results are nearest
+/// first and measured from it; otherwise they are sorted by name.
+pub fn within_bbox(db: &ConfigDatabase, bbox: &BoundingBox, origin: Option<(f64, f64)>, kinds: &[SearchKind]) -> SqliteResult<Vec<SearchResult>> {
+    let mut results: Vec<SearchResult> = candidates(db, bbox, kinds)?
+        .into_iter()
+        .filter_map(|c| {
+            let inside: Vec<(f64, f64)> = c.points.iter().copied().filter(|&(lat, lon)| bbox.contains(lat, lon)).collect();
+            (!inside.is_empty()).then(|| to_result(c, &inside, origin))
+        })
+        .collect();
+    if origin.is_some() {
+        sort_by_distance(&mut results);
+    } else {
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    Ok(results)
+}
+
+/// Objects whose name or description has words starting with each word of
+/// `text`, best matches first
+pub fn text(db: &ConfigDatabase, text: &str, origin: Option<(f64, f64)>, kinds: &[SearchKind], limit: usize) -> SqliteResult<Vec<SearchResult>> {
+    let Some(query) = fts_query(text) else { return Ok(Vec::new()) };
+    Ok(db
+        .find_text(&query, kinds_or_all(kinds), limit)?
+        .into_iter()
+        .map(|c| {
+            let points = c.points.clone();
+            to_result(c, &points, origin)
+        })
+        .collect())
+}
+
+/// Quote each word so user input can't form FTS5 syntax, and match it as a prefix
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    if terms.is_empty() { None } else { Some(terms.join(" ")) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Route, Waypoint};
+
+    fn names(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_spatial_and_text_search() {
+        let dir = std::env::temp_dir().join(format!("vortexnav_search_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let db = ConfigDatabase::new(&dir).unwrap();
+
+        let kawau = db.create_waypoint(&Waypoint::at("Kawau", -36.42, 174.83)).unwrap();
+        let bon_accord = db.create_waypoint(&Waypoint { description: Some("Anchorage in Bon Accord Harbour".to_string()), ..Waypoint::at("North Cove", -36.425, 174.815) }).unwrap();
+        db.create_waypoint(&Waypoint::at("Tiritiri", -36.60, 174.89)).unwrap();
+        let westhaven = db.create_waypoint(&Waypoint::at("Westhaven", -36.84, 174.74)).unwrap();
+        db.create_route(&Route { name: "Kawau run".to_string(), ..Default::default() }, &[westhaven, kawau], &[]).unwrap();
+        let here = (-36.41, 174.84);
+
+        let nearest = nearest(&db, here.0, here.1, 2, &[SearchKind::Waypoint]).unwrap();
+        assert_eq!(names(&nearest), ["Kawau", "North Cove"]);
+        let first = &nearest[0];
+        assert!((first.distance_nm.unwrap() - 0.77).abs() < 0.05, "{:?}", first);
+        assert!(first.bearing.unwrap() > 180.0 && first.bearing.unwrap() < 270.0);
+
+        // The route counts from its Kawau end
+        let close = within_radius(&db, here.0, here.1, 2.0, &[]).unwrap();
+        assert_eq!(names(&close), ["Kawau", "Kawau run", "North Cove"]);
+
+        let harbour = BoundingBox { min_lat: -36.9, min_lon: 174.6, max_lat: -36.8, max_lon: 174.9 };
+        let inside = within_bbox(&db, &harbour, None, &[]).unwrap();
+        assert_eq!(names(&inside), ["Kawau run", "Westhaven"]);
+        assert_eq!((inside[0].lat, inside[0].lon), (Some(-36.84), Some(174.74)));
+
+        assert_eq!(names(&text(&db, "kaw", None, &[], 10).unwrap()).len(), 2);
+        assert_eq!(names(&text(&db, "bon harb", Some(here), &[], 10).unwrap()), ["North Cove"]);
+        assert!(text(&db, "\"", None, &[], 10).unwrap().is_empty());
+
+        // The kind filter applies before the limit, and a route without
+        // waypoints is found with no position
+        assert_eq!(names(&text(&db, "kaw", None, &[SearchKind::Route], 1).unwrap()), ["Kawau run"]);
+        db.create_route(&Route { name: "Tiritiri passage".to_string(), ..Default::default() }, &[], &[]).unwrap();
+        let empty = text(&db, "passage", Some(here), &[], 10).unwrap();
+        assert_eq!(names(&empty), ["Tiritiri passage"]);
+        assert_eq!((empty[0].lat, empty[0].distance_nm), (None, None));
+
+        // Edits and deletions reach the indexes
+        db.update_waypoint_position(kawau, -35.0, 174.0).unwrap();
+        db.delete_waypoint(bon_accord).unwrap();
+        assert_eq!(names(&within_radius(&db, here.0, here.1, 2.0, &[]).unwrap()), Vec::<&str>::new());
+        assert!(text(&db, "bon", None, &[], 10).unwrap().is_empty());
+
+        // Across the antimeridian
+        db.create_waypoint(&Waypoint::at("Date line", -17.0, 179.99)).unwrap();
+        assert_eq!(names(&within_radius(&db, -17.0, -179.99, 2.0, &[]).unwrap()), ["Date line"]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
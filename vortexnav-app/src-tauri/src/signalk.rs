@@ -0,0 +1,428 @@
+// Signal K client
+// Subscribes to a Signal K server over WebSocket and converts deltas to GPS, instrument and AIS data
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+
+use crate::gps::GpsError;
+use crate::instruments::{degrees, knots, relative_degrees, OwnVesselUpdate, VesselData};
+
+/// Signal K server's default port when an address has none
+pub const DEFAULT_SIGNALK_PORT: u16 = 3000;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+pub const METERS_PER_NM: f64 = 1852.0;
+
+// Server found at a host/port
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalKServer {
+    pub address: String,
+    pub server_id: Option<String>,
+    pub server_version: Option<String>,
+    pub stream_url: String,
+}
+
+/// Add the default port to a bare host
+fn with_default_port(address: &str) -> String {
+    if address.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_SIGNALK_PORT)
+    }
+}
+
+fn connect_tcp(address: &str) -> Result<TcpStream, GpsError> {
+    let socket_addr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| GpsError::SourceNotFound(address.to_string()))?;
+    let stream = TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    Ok(stream)
+}
+
+/// Ask the server at "host[:port]" for its endpoints (GET /signalk)
+pub fn discover(address: &str) -> Result<SignalKServer, GpsError> {
+    let address = with_default_port(address);
+    let mut stream = connect_tcp(&address)?;
+    write!(stream, "GET /signalk HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n", address)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body)
+        .ok_or_else(|| GpsError::SignalK("Malformed discovery response".to_string()))?;
+    let json: Value = serde_json::from_str(body)
+        .map_err(|e| GpsError::SignalK(format!("Not a Signal K server: {}", e)))?;
+
+    let stream_url = json["endpoints"]["v1"]["signalk-ws"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| GpsError::SignalK("Server has no v1 WebSocket endpoint".to_string()))?;
+    Ok(SignalKServer {
+        address,
+        server_id: json["server"]["id"].as_str().map(str::to_string),
+        server_version: json["server"]["version"].as_str().map(str::to_string),
+        stream_url,
+    })
+}
+
+/// Host and port of a stream URL. Only plain ws:// streams can be opened,
+/// as there is no TLS support for wss://.
+fn stream_host(url: &str) -> Result<&str, GpsError> {
+    if url.starts_with("wss://") {
+        return Err(GpsError::SignalK(
+            "The server only offers a secure (wss://) stream, which is not supported; enable plain ws:// on the server".to_string(),
+        ));
+    }
+    let rest = url
+        .strip_prefix("ws://")
+        .ok_or_else(|| GpsError::SignalK(format!("Unsupported stream URL {}", url)))?;
+    Ok(rest.split('/').next().unwrap_or(rest))
+}
+
+/// WebSocket subscription to a Signal K server
+pub struct SignalKConnection {
+    socket: WebSocket<TcpStream>,
+}
+
+impl SignalKConnection {
+    /// Discover the stream endpoint at "host[:port]" (falling back to the standard
+    /// path) and subscribe to our own navigation and environment data and to
+    /// other vessels' navigation data
+    pub fn connect(address: &str) -> Result<Self, GpsError> {
+        let address = with_default_port(address);
+        let stream_url = match discover(&address) {
+            Ok(server) => server.stream_url,
+            Err(e) => {
+                log::debug!("Signal K discovery on {} failed: {}", address, e);
+                format!("ws://{}/signalk/v1/stream", address)
+            }
+        };
+        let url = if stream_url.contains('?') {
+            format!("{}&subscribe=none", stream_url)
+        } else {
+            format!("{}?subscribe=none", stream_url)
+        };
+
+        // Connect to the host the server advertised, not necessarily the one we asked
+        let stream = connect_tcp(stream_host(&url)?)?;
+        let (mut socket, _) = tungstenite::client(url.as_str(), stream)
+            .map_err(|e| GpsError::SignalK(format!("WebSocket handshake failed: {}", e)))?;
+        socket.get_mut().set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let subscriptions = [
+            r#"{"context":"vessels.self","subscribe":[{"path":"navigation.*","period":1000},{"path":"environment.*","period":1000}]}"#,
+            r#"{"context":"vessels.*","subscribe":[{"path":"navigation.*","period":5000},{"path":"","period":5000},{"path":"design.aisShipType","period":5000},{"path":"communication.callsignVhf","period":5000}]}"#,
+        ];
+        for subscription in subscriptions {
+            socket
+                .send(Message::Text(subscription.to_string()))
+                .map_err(|e| GpsError::SignalK(e.to_string()))?;
+        }
+        Ok(Self { socket })
+    }
+
+    /// Next text message. Ok(None) on a read timeout.
+    pub fn next_message(&mut self) -> Result<Option<String>, GpsError> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => return Ok(Some(text)),
+                Ok(Message::Close(_)) => {
+                    // Sends the close reply
+                    let _ = self.socket.flush();
+                    return Err(GpsError::SignalK("Server closed the connection".to_string()));
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(GpsError::SignalK(e.to_string())),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    context: Option<String>,
+    #[serde(default)]
+    updates: Vec<DeltaUpdate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeltaUpdate {
+    timestamp: Option<String>,
+    #[serde(default)]
+    values: Vec<PathValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PathValue {
+    path: String,
+    value: Value,
+}
+
+fn celsius(kelvin: f64) -> f64 {
+    kelvin - 273.15
+}
+
+/// Tracks which context is our own vessel and routes deltas accordingly
+#[derive(Debug, Default)]
+pub struct SignalKState {
+    self_context: Option<String>,
+}
+
+impl SignalKState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn is_self(&self, context: Option<&str>) -> bool {
+        match context {
+            None | Some("vessels.self") => true,
+            Some(context) => self.self_context.as_deref() == Some(context),
+        }
+    }
+
+    /// Apply one server message. Instruments and AIS targets are updated in
+    /// place; own position and motion are returned for the GPS pipeline.
+    pub fn handle_message(&mut self, text: &str, vessel: &mut VesselData, now: Instant) -> Option<OwnVesselUpdate> {
+        let json: Value = serde_json::from_str(text).ok()?;
+        // The hello message names our own vessel
+        if json.get("updates").is_none() {
+            if let Some(self_id) = json["self"].as_str() {
+                self.self_context = Some(if self_id.starts_with("vessels.") {
+                    self_id.to_string()
+                } else {
+                    format!("vessels.{}", self_id)
+                });
+            }
+            return None;
+        }
+
+        let delta: Delta = serde_json::from_value(json).ok()?;
+        if self.is_self(delta.context.as_deref()) {
+            Some(Self::apply_own(&delta, vessel))
+        } else {
+            if let Some(context) = delta.context.as_deref().filter(|c| c.starts_with("vessels.")) {
+                Self::apply_ais(context, &delta, vessel, now);
+            }
+            None
+        }
+    }
+
+    fn apply_own(delta: &Delta, vessel: &mut VesselData) -> OwnVesselUpdate {
+        let mut update = OwnVesselUpdate::default();
+        let instruments = &mut vessel.instruments;
+        for delta_update in &delta.updates {
+            for PathValue { path, value } in &delta_update.values {
+                let number = value.as_f64();
+                match path.as_str() {
+                    "navigation.position" => {
+                        update.gps.latitude = value["latitude"].as_f64();
+                        update.gps.longitude = value["longitude"].as_f64();
+                        update.gps.altitude = value["altitude"].as_f64();
+                        update.is_fix = update.gps.latitude.is_some() && update.gps.longitude.is_some();
+                        update.gps.timestamp = delta_update.timestamp.clone();
+                    }
+                    "navigation.speedOverGround" => update.gps.speed_knots = number.map(knots),
+                    "navigation.courseOverGroundTrue" => update.gps.course = number.map(degrees),
+                    "navigation.headingTrue" => {
+                        update.gps.heading = number.map(degrees);
+                        update.heading = update.gps.heading;
+                    }
+                    "navigation.headingMagnetic" => instruments.heading_magnetic = number.map(degrees),
+                    "navigation.magneticVariation" => instruments.magnetic_variation = number.map(f64::to_degrees),
+                    "navigation.rateOfTurn" => instruments.rate_of_turn = number.map(|r| r.to_degrees() * 60.0),
+                    "navigation.speedThroughWater" => {
+                        instruments.speed_through_water_kn = number.map(knots);
+                        update.water_speed = instruments.speed_through_water_kn;
+                    }
+                    "navigation.log" => instruments.log_nm = number.map(|m| m / METERS_PER_NM),
+                    "navigation.gnss.satellites" => update.gps.satellites = number.map(|n| n as u32),
+                    "navigation.gnss.horizontalDilution" => update.gps.hdop = number.map(|h| h as f32),
+                    "navigation.gnss.positionDilution" => update.gps.pdop = number.map(|p| p as f32),
+                    "navigation.gnss.methodQuality" => {
+                        if let Some(method) = value.as_str() {
+                            update.gps.fix_quality = Some(match method {
+                                "no GPS" => 0,
+                                "DGNSS fix" => 2,
+                                "RTK fixed integer" => 4,
+                                "RTK float" => 5,
+                                _ => 1,
+                            });
+                            update.gps.fix_type = Some(method.to_string());
+                        }
+                    }
+                    "environment.depth.belowTransducer" => instruments.depth_m = number,
+                    "environment.depth.belowKeel" => instruments.depth_below_keel_m = number,
+                    "environment.water.temperature" => instruments.water_temp_c = number.map(celsius),
+                    "environment.outside.temperature" => instruments.air_temp_c = number.map(celsius),
+                    "environment.outside.pressure" => instruments.pressure_mb = number.map(|pa| pa / 100.0),
+                    "environment.outside.relativeHumidity" | "environment.outside.humidity" => {
+                        instruments.humidity_pct = number.map(|ratio| ratio * 100.0)
+                    }
+                    "environment.wind.angleApparent" => instruments.apparent_wind_angle = number.map(relative_degrees),
+                    "environment.wind.speedApparent" => instruments.apparent_wind_speed_kn = number.map(knots),
+                    "environment.wind.angleTrueWater" => instruments.true_wind_angle = number.map(relative_degrees),
+                    "environment.wind.directionTrue" => instruments.true_wind_direction = number.map(degrees),
+                    "environment.wind.speedTrue" => instruments.true_wind_speed_kn = number.map(knots),
+                    _ => continue,
+                }
+                if delta_update.timestamp.is_some() {
+                    instruments.updated_at = delta_update.timestamp.clone();
+                }
+            }
+        }
+        update
+    }
+
+    fn apply_ais(context: &str, delta: &Delta, vessel: &mut VesselData, now: Instant) {
+        let target = vessel.ais_target(context, now);
+        if target.mmsi.is_none() {
+            target.mmsi = context.split("mmsi:").nth(1).map(str::to_string);
+        }
+        for delta_update in &delta.updates {
+            for PathValue { path, value } in &delta_update.values {
+                let number = value.as_f64();
+                match path.as_str() {
+                    "navigation.position" => {
+                        target.latitude = value["latitude"].as_f64();
+                        target.longitude = value["longitude"].as_f64();
+                    }
+                    "navigation.speedOverGround" => target.sog_knots = number.map(knots),
+                    "navigation.courseOverGroundTrue" => target.cog = number.map(degrees),
+                    "navigation.headingTrue" => target.heading = number.map(degrees),
+                    "navigation.state" => target.nav_state = value.as_str().map(str::to_string),
+                    "design.aisShipType" => target.ship_type = value["name"].as_str().map(str::to_string),
+                    "communication.callsignVhf" => target.call_sign = value.as_str().map(str::to_string),
+                    "name" => target.name = value.as_str().map(str::to_string),
+                    // Root properties arrive as an object on the empty path
+                    "" => {
+                        if let Some(name) = value["name"].as_str() {
+                            target.name = Some(name.to_string());
+                        }
+                        if let Some(mmsi) = value["mmsi"].as_str() {
+                            target.mmsi = Some(mmsi.to_string());
+                        }
+                    }
+                    _ => continue,
+                }
+                if delta_update.timestamp.is_some() {
+                    target.last_update = delta_update.timestamp.clone();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Recorded from a Signal K server fed by an NMEA 0183 multiplexer
+    const RECORDED: [&str; 4] = [
+        r#"{"name":"signalk-server","version":"2.8.0","self":"vessels.urn:mrn:imo:mmsi:512000001","roles":["master","main"],"timestamp":"2024-06-01T10:00:00.000Z"}"#,
+        r#"{"context":"vessels.urn:mrn:imo:mmsi:512000001","updates":[{"$source":"nmea0183.GP","timestamp":"2024-06-01T10:00:01.000Z","values":[{"path":"navigation.position","value":{"longitude":174.77,"latitude":-36.84}},{"path":"navigation.speedOverGround","value":3.086},{"path":"navigation.courseOverGroundTrue","value":1.5708},{"path":"navigation.gnss.methodQuality","value":"DGNSS fix"},{"path":"navigation.gnss.satellites","value":9}]}]}"#,
+        r#"{"context":"vessels.urn:mrn:imo:mmsi:512000001","updates":[{"$source":"nmea0183.II","timestamp":"2024-06-01T10:00:01.200Z","values":[{"path":"environment.depth.belowTransducer","value":12.4},{"path":"environment.water.temperature","value":291.15},{"path":"environment.wind.angleApparent","value":-0.7854},{"path":"environment.wind.speedApparent","value":7.716},{"path":"navigation.speedThroughWater","value":2.9}]}]}"#,
+        r#"{"context":"vessels.urn:mrn:imo:mmsi:512123456","updates":[{"$source":"nmea0183.AI","timestamp":"2024-06-01T10:00:02.000Z","values":[{"path":"navigation.position","value":{"longitude":174.8,"latitude":-36.83}},{"path":"navigation.speedOverGround","value":5.144},{"path":"","value":{"name":"SPIRIT OF ADVENTURE"}},{"path":"design.aisShipType","value":{"id":36,"name":"Sailing"}}]}]}"#,
+    ];
+
+    /// Serves discovery over HTTP, then the recorded deltas over WebSocket
+    fn mock_server() -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let ws_url = format!("ws://{}/signalk/v1/stream", address);
+
+        let handle = thread::spawn(move || {
+            let (mut http, _) = listener.accept().unwrap();
+            let mut request_line = String::new();
+            BufReader::new(http.try_clone().unwrap()).read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("GET /signalk "));
+            let body = format!(
+                r#"{{"endpoints":{{"v1":{{"version":"1.7.0","signalk-ws":"{}"}}}},"server":{{"id":"signalk-server-node","version":"2.8.0"}}}}"#,
+                ws_url
+            );
+            write!(http, "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}", body).unwrap();
+            drop(http);
+
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let mut subscriptions = Vec::new();
+            for _ in 0..2 {
+                if let Message::Text(text) = socket.read().unwrap() {
+                    subscriptions.push(text);
+                }
+            }
+            for message in RECORDED {
+                socket.send(Message::Text(message.to_string())).unwrap();
+            }
+            socket.close(None).unwrap();
+            let _ = socket.read();
+            subscriptions
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_stream_host() {
+        assert_eq!(stream_host("ws://10.0.0.5:3000/signalk/v1/stream?subscribe=none").unwrap(), "10.0.0.5:3000");
+        let secure = stream_host("wss://boat.local:3443/signalk/v1/stream").unwrap_err();
+        assert!(secure.to_string().contains("wss://"));
+        assert!(stream_host("http://boat.local/stream").is_err());
+    }
+
+    #[test]
+    fn test_mock_server_replay() {
+        let (address, server) = mock_server();
+        let mut connection = SignalKConnection::connect(&address).unwrap();
+        let mut state = SignalKState::new();
+        let mut vessel = VesselData::default();
+        let mut own = Vec::new();
+        while let Ok(Some(text)) = connection.next_message() {
+            if let Some(update) = state.handle_message(&text, &mut vessel, Instant::now()) {
+                own.push(update);
+            }
+        }
+
+        drop(connection);
+        let subscriptions = server.join().unwrap();
+        assert!(subscriptions[0].contains("navigation.*") && subscriptions[0].contains("environment.*"));
+        assert!(subscriptions[1].contains("\"vessels.*\""));
+
+        // Own vessel by MMSI context from the hello message
+        assert_eq!(own.len(), 2);
+        assert!(own[0].is_fix);
+        assert_eq!(own[0].gps.latitude, Some(-36.84));
+        assert!((own[0].gps.speed_knots.unwrap() - 6.0).abs() < 0.01);
+        assert!((own[0].gps.course.unwrap() - 90.0).abs() < 0.01);
+        assert_eq!(own[0].gps.fix_quality, Some(2));
+        assert!(!own[1].is_fix);
+        assert!(own[1].water_speed.is_some());
+
+        let instruments = &vessel.instruments;
+        assert_eq!(instruments.depth_m, Some(12.4));
+        assert!((instruments.water_temp_c.unwrap() - 18.0).abs() < 0.01);
+        assert!((instruments.apparent_wind_angle.unwrap() + 45.0).abs() < 0.01);
+        assert!((instruments.apparent_wind_speed_kn.unwrap() - 15.0).abs() < 0.01);
+
+        let targets = vessel.current_ais_targets(Instant::now());
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].mmsi.as_deref(), Some("512123456"));
+        assert_eq!(targets[0].name.as_deref(), Some("SPIRIT OF ADVENTURE"));
+        assert_eq!(targets[0].ship_type.as_deref(), Some("Sailing"));
+        assert!((targets[0].sog_knots.unwrap() - 10.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,824 @@
+// Device sync
+// Keeps routes, waypoints, tags and tracks in step between paired VortexNav devices on the local network
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use rusqlite::Connection;
+
+use crate::database::{self, ConfigDatabase, Route, RouteTag, SyncPeer, SyncSettings, Track, TrackWithPoints, Waypoint};
+
+/// Bumped when a change would confuse an older peer
+pub const SYNC_PROTOCOL_VERSION: u32 = 1;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const PAIRING_CODE_LIFETIME: Duration = Duration::from_secs(300);
+/// Longest request or reply read, so an unpaired client can't exhaust memory
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Network error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Device is not paired")]
+    NotPaired,
+    #[error("Peer uses sync protocol {0}, this app supports {SYNC_PROTOCOL_VERSION}")]
+    UnsupportedProtocol(u32),
+    #[error("Peer refused: {0}")]
+    Rejected(String),
+    #[error("Unexpected reply from peer")]
+    UnexpectedResponse,
+    #[error("Message is larger than {MAX_MESSAGE_BYTES} bytes")]
+    MessageTooLarge,
+}
+
+/// Synced tables, in the order new objects must be stored so references resolve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncTable {
+    RouteTags,
+    Waypoints,
+    Routes,
+    Tracks,
+}
+
+impl SyncTable {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncTable::RouteTags => "route_tags",
+            SyncTable::Waypoints => "waypoints",
+            SyncTable::Routes => "routes",
+            SyncTable::Tracks => "tracks",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [SyncTable::RouteTags, SyncTable::Waypoints, SyncTable::Routes, SyncTable::Tracks]
+            .into_iter()
+            .find(|table| table.as_str() == name)
+    }
+}
+
+/// Route as sent to a peer, with waypoints and tags referenced by uuid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRoute {
+    pub route: Route,
+    pub waypoints: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncObject {
+    RouteTag(RouteTag),
+    Waypoint(Waypoint),
+    Route(SyncRoute),
+    Track(TrackWithPoints),
+}
+
+/// Latest state of one object. `object` is None when it has been deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncChange {
+    pub table: SyncTable,
+    pub uuid: String,
+    pub updated_at: String,
+    pub object: Option<SyncObject>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictWinner {
+    Local,
+    Remote,
+}
+
+/// Object edited on both devices since they last synced. The newer edit is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub table: SyncTable,
+    pub uuid: String,
+    pub name: String,
+    pub local_updated_at: String,
+    pub remote_updated_at: String,
+    pub kept: ConflictWinner,
+}
+
+impl SyncConflict {
+    /// The same conflict as seen from the other device
+    fn reversed(self) -> Self {
+        Self {
+            local_updated_at: self.remote_updated_at,
+            remote_updated_at: self.local_updated_at,
+            kept: match self.kept {
+                ConflictWinner::Local => ConflictWinner::Remote,
+                ConflictWinner::Remote => ConflictWinner::Local,
+            },
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncRequest {
+    Pair {
+        protocol: u32,
+        device_id: String,
+        name: String,
+        code: String,
+        /// Our own sync port, so the peer can start syncs too
+        port: Option<u16>,
+    },
+    /// Send our changes and ask for the peer's changes after `since`
+    Exchange {
+        protocol: u32,
+        device_id: String,
+        token: String,
+        since: i64,
+        changes: Vec<SyncChange>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncResponse {
+    Paired {
+        device_id: String,
+        name: String,
+        token: String,
+    },
+    Changes {
+        changes: Vec<SyncChange>,
+        latest_seq: i64,
+        conflicts: Vec<SyncConflict>,
+        errors: Vec<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Outcome of one sync, from this device's point of view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub peer: String,
+    pub sent: usize,
+    pub received: usize,
+    pub conflicts: Vec<SyncConflict>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingCode {
+    pub code: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncStatus {
+    pub running: bool,
+    pub address: Option<String>,
+    pub pairing_code: Option<PairingCode>,
+}
+
+struct ActivePairing {
+    code: String,
+    expires: Instant,
+}
+
+/// Gives the server threads the database owned by the app state
+pub type DatabaseProvider = Arc<dyn Fn(&mut dyn FnMut(&ConfigDatabase)) + Send + Sync>;
+
+fn with_db<R>(provider: &DatabaseProvider, f: impl FnOnce(&ConfigDatabase) -> R) -> R {
+    let mut f = Some(f);
+    let mut result = None;
+    provider(&mut |db| result = f.take().map(|f| f(db)));
+    result.expect("database provider did not run")
+}
+
+/// Listener accepting pairing and sync requests from other devices
+pub struct SyncServer {
+    stop_flag: Arc<AtomicBool>,
+    address: SocketAddr,
+    pairing: Arc<Mutex<Option<ActivePairing>>>,
+    listener: Option<thread::JoinHandle<()>>,
+}
+
+impl SyncServer {
+    /// Bind the sync port on the configured address and start serving. Port 0 picks a free port.
+    pub fn start(settings: &SyncSettings, db: DatabaseProvider) -> io::Result<Self> {
+        let listener = TcpListener::bind((settings.bind_address.as_str(), settings.port))?;
+        listener.set_nonblocking(true)?;
+
+        let mut server = Self {
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            address: listener.local_addr()?,
+            pairing: Arc::new(Mutex::new(None)),
+            listener: None,
+        };
+
+        let (stop_flag, pairing) = (Arc::clone(&server.stop_flag), Arc::clone(&server.pairing));
+        server.listener = Some(thread::spawn(move || run_server(listener, db, pairing, stop_flag)));
+
+        log::info!("Sync server listening on {}", server.address);
+        Ok(server)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// Issue a code that another device can use to pair, replacing any earlier one
+    pub fn start_pairing(&self) -> PairingCode {
+        let code = format!("{:06}", uuid::Uuid::new_v4().as_u128() % 1_000_000);
+        *self.pairing.lock().unwrap() =
+            Some(ActivePairing { code: code.clone(), expires: Instant::now() + PAIRING_CODE_LIFETIME });
+        PairingCode { code, expires_in_secs: PAIRING_CODE_LIFETIME.as_secs() }
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        let pairing = self.pairing.lock().unwrap();
+        let now = Instant::now();
+        SyncStatus {
+            running: !self.stop_flag.load(Ordering::SeqCst),
+            address: Some(self.address.to_string()),
+            pairing_code: pairing.as_ref().filter(|p| p.expires > now).map(|p| PairingCode {
+                code: p.code.clone(),
+                expires_in_secs: (p.expires - now).as_secs(),
+            }),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for SyncServer {
+    // Waits for the listener to close, so the port can be bound again straight away
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
+
+fn run_server(
+    listener: TcpListener,
+    db: DatabaseProvider,
+    pairing: Arc<Mutex<Option<ActivePairing>>>,
+    stop_flag: Arc<AtomicBool>,
+) {
+    while !stop_flag.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, peer_addr)) => {
+                let (db, pairing) = (Arc::clone(&db), Arc::clone(&pairing));
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, peer_addr, &db, &pairing) {
+                        log::debug!("Sync client error: {}", e);
+                    }
+                });
+            }
+            Err(_) => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+    db: &DatabaseProvider,
+    pairing: &Mutex<Option<ActivePairing>>,
+) -> Result<(), SyncError> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let request: SyncRequest = read_message(&stream)?;
+    let response = with_db(db, |db| {
+        respond(db, pairing, request, peer_addr).unwrap_or_else(|e| SyncResponse::Error { message: e.to_string() })
+    });
+    write_message(&stream, &response)
+}
+
+fn respond(
+    db: &ConfigDatabase,
+    pairing: &Mutex<Option<ActivePairing>>,
+    request: SyncRequest,
+    peer_addr: SocketAddr,
+) -> Result<SyncResponse, SyncError> {
+    match request {
+        SyncRequest::Pair { protocol, device_id, name, code, port } => {
+            check_protocol(protocol)?;
+            // Any attempt uses up the code, so it can't be guessed
+            let active = pairing.lock().unwrap().take();
+            if !active.is_some_and(|p| p.code == code && p.expires > Instant::now()) {
+                return Err(SyncError::Rejected("Pairing code is wrong or has expired".to_string()));
+            }
+
+            let token = uuid::Uuid::new_v4().to_string();
+            db.save_sync_peer(&SyncPeer {
+                device_id,
+                name,
+                address: port.map(|port| SocketAddr::new(peer_addr.ip(), port).to_string()),
+                token: token.clone(),
+                last_sent_seq: 0,
+                last_received_seq: 0,
+                last_synced_at: None,
+                paired_at: None,
+            })?;
+            Ok(SyncResponse::Paired {
+                device_id: db.get_sync_device_id()?,
+                name: db.get_sync_settings()?.device_name,
+                token,
+            })
+        }
+        SyncRequest::Exchange { protocol, device_id, token, since, changes } => {
+            check_protocol(protocol)?;
+            let peer = db.get_sync_peer(&device_id)?.filter(|p| p.token == token).ok_or(SyncError::NotPaired)?;
+
+            let outcome = db.transaction(|tx| -> Result<_, SyncError> {
+                let outcome = apply_changes(tx, &peer.device_id, since, changes)?;
+                // The peer only asks for changes after `since` once it has stored
+                // everything up to there, so that is what it is known to have
+                database::update_sync_peer_progress(tx, &peer.device_id, since.max(peer.last_sent_seq), peer.last_received_seq)?;
+                Ok(outcome)
+            })?;
+            let latest_seq = db.latest_sync_seq()?;
+            let changes = collect_changes(db, since, &peer.device_id)?;
+
+            Ok(SyncResponse::Changes { changes, latest_seq, conflicts: outcome.conflicts, errors: outcome.errors })
+        }
+    }
+}
+
+fn check_protocol(protocol: u32) -> Result<(), SyncError> {
+    if protocol == SYNC_PROTOCOL_VERSION {
+        Ok(())
+    } else {
+        Err(SyncError::UnsupportedProtocol(protocol))
+    }
+}
+
+// Each connection carries one request and one response, as a line of JSON each
+fn read_message<T: for<'de> Deserialize<'de>>(stream: &TcpStream) -> Result<T, SyncError> {
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_BYTES)).read_line(&mut line)?;
+    if line.len() as u64 >= MAX_MESSAGE_BYTES && !line.ends_with('\n') {
+        return Err(SyncError::MessageTooLarge);
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+fn write_message<T: Serialize>(mut stream: &TcpStream, message: &T) -> Result<(), SyncError> {
+    let mut json = serde_json::to_string(message)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn send_request(address: &str, request: &SyncRequest) -> Result<SyncResponse, SyncError> {
+    let addr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Cannot resolve {}", address)))?;
+    let stream = TcpStream::connect_timeout(&addr, IO_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    write_message(&stream, request)?;
+    match read_message(&stream)? {
+        SyncResponse::Error { message } => Err(SyncError::Rejected(message)),
+        response => Ok(response),
+    }
+}
+
+/// Pair with the device at `address` using the code it is showing
+pub fn pair_with(db: &ConfigDatabase, address: &str, code: &str, own_port: Option<u16>) -> Result<SyncPeer, SyncError> {
+    let request = SyncRequest::Pair {
+        protocol: SYNC_PROTOCOL_VERSION,
+        device_id: db.get_sync_device_id()?,
+        name: db.get_sync_settings()?.device_name,
+        code: code.trim().to_string(),
+        port: own_port,
+    };
+    let SyncResponse::Paired { device_id, name, token } = send_request(address, &request)? else {
+        return Err(SyncError::UnexpectedResponse);
+    };
+
+    let peer = SyncPeer {
+        device_id,
+        name,
+        address: Some(address.to_string()),
+        token,
+        last_sent_seq: 0,
+        last_received_seq: 0,
+        last_synced_at: None,
+        paired_at: None,
+    };
+    db.save_sync_peer(&peer)?;
+    Ok(peer)
+}
+
+/// Swap changes with a paired device
+pub fn sync_with_peer(db: &ConfigDatabase, peer: &SyncPeer) -> Result<SyncReport, SyncError> {
+    let address = peer
+        .address
+        .as_deref()
+        .ok_or_else(|| SyncError::Rejected(format!("No address known for {}", peer.name)))?;
+
+    let latest = db.latest_sync_seq()?;
+    let changes = collect_changes(db, peer.last_sent_seq, &peer.device_id)?;
+    let sent = changes.len();
+    let request = SyncRequest::Exchange {
+        protocol: SYNC_PROTOCOL_VERSION,
+        device_id: db.get_sync_device_id()?,
+        token: peer.token.clone(),
+        since: peer.last_received_seq,
+        changes,
+    };
+    let SyncResponse::Changes { changes, latest_seq, conflicts, mut errors } = send_request(address, &request)? else {
+        return Err(SyncError::UnexpectedResponse);
+    };
+
+    let outcome = db.transaction(|tx| -> Result<_, SyncError> {
+        let outcome = apply_changes(tx, &peer.device_id, latest, changes)?;
+        database::update_sync_peer_progress(tx, &peer.device_id, latest, latest_seq)?;
+        Ok(outcome)
+    })?;
+
+    let mut conflicts: Vec<SyncConflict> = conflicts.into_iter().map(SyncConflict::reversed).collect();
+    conflicts.extend(outcome.conflicts);
+    errors.extend(outcome.errors);
+    Ok(SyncReport { peer: peer.name.clone(), sent, received: outcome.applied, conflicts, errors })
+}
+
+/// Everything changed after `since` that didn't come from the peer itself
+fn collect_changes(db: &ConfigDatabase, since: i64, peer: &str) -> Result<Vec<SyncChange>, SyncError> {
+    let mut changes = Vec::new();
+    for entry in db.get_sync_changes(since, peer)? {
+        let Some(table) = SyncTable::from_name(&entry.table_name) else { continue };
+        // Rows removed outright, e.g. by restoring a backup, have nothing to send
+        let Some(version) = db.get_sync_version(table.as_str(), &entry.uuid)? else { continue };
+        let object = if version.deleted {
+            None
+        } else {
+            match load_object(db, table, &entry.uuid)? {
+                Some(object) => Some(object),
+                None => continue,
+            }
+        };
+        // Tracks being recorded go once they are finished
+        if let Some(SyncObject::Track(track)) = &object {
+            if track.track.is_recording {
+                continue;
+            }
+        }
+        changes.push(SyncChange { table, uuid: entry.uuid, updated_at: version.updated_at, object });
+    }
+    Ok(changes)
+}
+
+fn load_object(db: &ConfigDatabase, table: SyncTable, uuid: &str) -> rusqlite::Result<Option<SyncObject>> {
+    Ok(match table {
+        SyncTable::RouteTags => {
+            db.get_route_tags()?.into_iter().find(|t| t.uuid.as_deref() == Some(uuid)).map(SyncObject::RouteTag)
+        }
+        SyncTable::Waypoints => match db.find_waypoint_id(uuid)? {
+            // Groups are organised separately on each device
+            Some(id) => db.get_waypoint(id)?.map(|w| SyncObject::Waypoint(Waypoint { group_id: None, ..w })),
+            None => None,
+        },
+        SyncTable::Routes => match db.find_route_id(uuid)? {
+            Some(id) => db.get_route(id)?.map(|r| {
+                SyncObject::Route(SyncRoute {
+                    waypoints: r.waypoints.iter().filter_map(|w| w.uuid.clone()).collect(),
+                    tags: r.tags.iter().filter_map(|t| t.uuid.clone()).collect(),
+                    route: r.route,
+                })
+            }),
+            None => None,
+        },
+        SyncTable::Tracks => match db.find_track_id(uuid)? {
+            Some(id) => db.get_track_with_points(id)?.map(SyncObject::Track),
+            None => None,
+        },
+    })
+}
+
+#[derive(Debug, Default)]
+struct ApplyOutcome {
+    applied: usize,
+    conflicts: Vec<SyncConflict>,
+    errors: Vec<String>,
+}
+
+/// Store a peer's changes, last writer wins. `since` is the position in our own
+/// change log the peer has already seen; anything later was edited on both sides.
+/// Run inside a transaction, so a failed write leaves none of the batch stored.
+fn apply_changes(conn: &Connection, peer: &str, since: i64, changes: Vec<SyncChange>) -> Result<ApplyOutcome, SyncError> {
+    // New and updated objects first, parents before the routes using them; then
+    // deletions the other way round, so removing a waypoint doesn't touch a route
+    // that was already updated without it
+    let (mut updates, mut deletions): (Vec<_>, Vec<_>) = changes.into_iter().partition(|c| c.object.is_some());
+    updates.sort_by_key(|c| c.table);
+    deletions.sort_by_key(|c| Reverse(c.table));
+
+    let mut outcome = ApplyOutcome::default();
+    let mut tag_aliases = HashMap::new();
+    for change in updates.iter().chain(&deletions) {
+        let table = change.table.as_str();
+        let local = database::get_sync_version(conn, table, &change.uuid)?;
+        let remote_wins = match &local {
+            None => change.object.is_some(),
+            Some(local) => {
+                if local.updated_at == change.updated_at && local.deleted == change.object.is_none() {
+                    continue;
+                }
+                let remote_wins = change.updated_at > local.updated_at;
+                let edited_here = local.seq.is_some_and(|seq| seq > since) && local.origin.as_deref() != Some(peer);
+                if edited_here {
+                    outcome.conflicts.push(SyncConflict {
+                        table: change.table,
+                        uuid: change.uuid.clone(),
+                        name: local.name.clone(),
+                        local_updated_at: local.updated_at.clone(),
+                        remote_updated_at: change.updated_at.clone(),
+                        kept: if remote_wins { ConflictWinner::Remote } else { ConflictWinner::Local },
+                    });
+                }
+                remote_wins
+            }
+        };
+        if !remote_wins {
+            continue;
+        }
+
+        if store_change(conn, change, &mut tag_aliases, &mut outcome.errors)? {
+            database::mark_sync_applied(conn, table, &change.uuid, &change.updated_at, peer)?;
+            outcome.applied += 1;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Write one change. Returns false when there was nothing to store. Parts that
+/// couldn't be stored are added to `errors`.
+fn store_change(
+    conn: &Connection,
+    change: &SyncChange,
+    tag_aliases: &mut HashMap<String, i64>,
+    errors: &mut Vec<String>,
+) -> rusqlite::Result<bool> {
+    let uuid = Some(change.uuid.clone());
+    match &change.object {
+        None => {
+            let id = match change.table {
+                SyncTable::RouteTags => database::find_route_tag_id(conn, &change.uuid)?,
+                SyncTable::Waypoints => database::find_waypoint_id(conn, &change.uuid)?,
+                SyncTable::Routes => database::find_route_id(conn, &change.uuid)?,
+                SyncTable::Tracks => database::find_track_id(conn, &change.uuid)?,
+            };
+            let Some(id) = id else { return Ok(false) };
+            match change.table {
+                SyncTable::RouteTags => database::delete_route_tag(conn, id)?,
+                SyncTable::Waypoints => database::delete_waypoint(conn, id)?,
+                SyncTable::Routes => database::delete_route(conn, id)?,
+                SyncTable::Tracks => database::delete_track(conn, id)?,
+            }
+        }
+        Some(SyncObject::RouteTag(tag)) => {
+            // Tag names are unique, so a tag created on both devices under the same
+            // name is treated as one. Both sides settle on the lower UUID.
+            let same_name = database::get_route_tags(conn)?.into_iter().find(|t| t.name == tag.name && t.uuid != uuid);
+            if let Some(existing) = same_name {
+                let id = existing.id.unwrap_or_default();
+                if existing.uuid < uuid {
+                    tag_aliases.insert(change.uuid.clone(), id);
+                    return Ok(false);
+                }
+                database::set_route_tag_uuid(conn, id, &change.uuid)?;
+            }
+            database::create_route_tag(conn, &RouteTag { id: None, uuid, ..tag.clone() })?;
+        }
+        Some(SyncObject::Waypoint(waypoint)) => {
+            database::create_waypoint(conn, &Waypoint { id: None, uuid, group_id: None, ..waypoint.clone() })?;
+        }
+        Some(SyncObject::Route(route)) => {
+            let mut waypoint_ids = Vec::new();
+            for waypoint in &route.waypoints {
+                match database::find_waypoint_id(conn, waypoint)? {
+                    Some(id) => waypoint_ids.push(id),
+                    None => errors.push(format!("Route {} refers to unknown waypoint {}", route.route.name, waypoint)),
+                }
+            }
+            let mut tag_ids = Vec::new();
+            for tag in &route.tags {
+                if let Some(id) = tag_aliases.get(tag).copied().or(database::find_route_tag_id(conn, tag)?) {
+                    tag_ids.push(id);
+                }
+            }
+            // Which route is being navigated stays local to each device
+            let is_active = match database::find_route_id(conn, &change.uuid)? {
+                Some(id) => database::get_route(conn, id)?.is_some_and(|r| r.route.is_active),
+                None => false,
+            };
+            database::create_route(conn, &Route { id: None, uuid, is_active, ..route.route.clone() }, &waypoint_ids, &tag_ids)?;
+        }
+        Some(SyncObject::Track(track)) => match database::find_track_id(conn, &change.uuid)? {
+            Some(id) => database::update_track(conn, &Track { id: Some(id), ..track.track.clone() })?,
+            None => {
+                database::import_track(conn, &Track { id: None, uuid, ..track.track.clone() }, &track.points)?;
+            }
+        },
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn settings() -> SyncSettings {
+        SyncSettings { enabled: true, device_name: String::new(), bind_address: "127.0.0.1".to_string(), port: 0 }
+    }
+
+    struct Device {
+        dir: PathBuf,
+        db: Arc<ConfigDatabase>,
+    }
+
+    impl Device {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("vortexnav_sync_{}_{}", name, std::process::id()));
+            std::fs::remove_dir_all(&dir).ok();
+            let db = Arc::new(ConfigDatabase::new(&dir).unwrap());
+            db.save_sync_settings(&SyncSettings { device_name: name.to_string(), ..settings() }).unwrap();
+            Self { dir, db }
+        }
+
+        fn serve(&self) -> SyncServer {
+            self.serve_on(0)
+        }
+
+        fn serve_on(&self, port: u16) -> SyncServer {
+            let db = Arc::clone(&self.db);
+            let provider: DatabaseProvider = Arc::new(move |f| f(&db));
+            SyncServer::start(&SyncSettings { port, ..settings() }, provider).unwrap()
+        }
+
+        fn waypoint(&self, name: &str, lat: f64, lon: f64) -> i64 {
+            self.db.create_waypoint(&Waypoint::at(name, lat, lon)).unwrap()
+        }
+
+        fn waypoint_names(&self) -> Vec<String> {
+            self.db.get_waypoints().unwrap().into_iter().map(|w| w.name).collect()
+        }
+    }
+
+    impl Drop for Device {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+
+    /// Pair `helm` with the server run by `nav` and return the peer record
+    fn pair(helm: &Device, server: &SyncServer) -> SyncPeer {
+        let address = format!("127.0.0.1:{}", server.port());
+        assert!(pair_with(&helm.db, &address, "000000x", None).is_err());
+        let code = server.start_pairing().code;
+        pair_with(&helm.db, &address, &code, None).unwrap()
+    }
+
+    fn peer(device: &Device, peer: &SyncPeer) -> SyncPeer {
+        device.db.get_sync_peer(&peer.device_id).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_sync_two_devices() {
+        let (helm, nav) = (Device::new("helm"), Device::new("nav"));
+        let server = nav.serve();
+        let paired = pair(&helm, &server);
+        assert_eq!(paired.name, "nav");
+        assert_eq!(nav.db.get_sync_peers().unwrap()[0].name, "helm");
+
+        let start = helm.waypoint("Westhaven", -36.84, 174.74);
+        let end = helm.waypoint("Kawau", -36.42, 174.83);
+        let tag = helm.db.create_route_tag(&RouteTag { id: None, name: "Day sail".to_string(), color: None, created_at: None, uuid: None, updated_at: None }).unwrap();
+        helm.db.create_route(&Route { name: "Kawau run".to_string(), ..Default::default() }, &[start, end], &[tag]).unwrap();
+        nav.waypoint("Fuel dock", -36.85, 174.76);
+
+        let report = sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+        assert!(report.conflicts.is_empty() && report.errors.is_empty(), "{:?}", report);
+        // Both devices started with the same default tags and now agree on their ids
+        let tag_ids = |device: &Device| {
+            let mut tags: Vec<_> = device.db.get_route_tags().unwrap().into_iter().map(|t| (t.name, t.uuid)).collect();
+            tags.sort();
+            tags
+        };
+        assert_eq!(tag_ids(&helm), tag_ids(&nav));
+        assert_eq!(helm.waypoint_names(), vec!["Fuel dock", "Kawau", "Westhaven"]);
+        assert_eq!(nav.waypoint_names(), helm.waypoint_names());
+        let routes = nav.db.get_routes().unwrap();
+        assert_eq!(routes[0].waypoints.len(), 2);
+        assert_eq!(routes[0].tags[0].name, "Day sail");
+
+        // Nothing changed, so nothing moves in either direction
+        let report = sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+        assert_eq!((report.sent, report.received), (0, 0));
+        // The nav station counts its changes as delivered once helm asks for what follows them
+        assert_eq!(nav.db.get_sync_peers().unwrap()[0].last_sent_seq, peer(&helm, &paired).last_received_seq);
+
+        // Deletions travel as tombstones
+        let route_id = nav.db.get_routes().unwrap()[0].route.id.unwrap();
+        nav.db.delete_route(route_id).unwrap();
+        let report = sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+        assert_eq!(report.received, 1);
+        assert!(helm.db.get_routes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_conflict_keeps_latest_edit() {
+        let (helm, nav) = (Device::new("helm_conflict"), Device::new("nav_conflict"));
+        let server = nav.serve();
+        let paired = pair(&helm, &server);
+
+        let id = helm.waypoint("Anchorage", -36.6, 174.9);
+        sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+
+        // Both devices rename it; the nav station does so last
+        let mut waypoint = helm.db.get_waypoint(id).unwrap().unwrap();
+        waypoint.name = "Helm anchorage".to_string();
+        helm.db.update_waypoint(&waypoint).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let nav_id = nav.db.find_waypoint_id(waypoint.uuid.as_deref().unwrap()).unwrap().unwrap();
+        let mut other = nav.db.get_waypoint(nav_id).unwrap().unwrap();
+        other.name = "Nav anchorage".to_string();
+        nav.db.update_waypoint(&other).unwrap();
+
+        let report = sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+        assert_eq!(report.conflicts.len(), 1, "{:?}", report);
+        assert!(report.errors.is_empty(), "{:?}", report);
+        assert_eq!(report.conflicts[0].kept, ConflictWinner::Remote);
+        assert_eq!(helm.waypoint_names(), vec!["Nav anchorage"]);
+        assert_eq!(nav.waypoint_names(), vec!["Nav anchorage"]);
+
+        // Settled: the next sync is quiet
+        let report = sync_with_peer(&helm.db, &peer(&helm, &paired)).unwrap();
+        assert!(report.conflicts.is_empty() && report.received == 0 && report.sent == 0, "{:?}", report);
+    }
+
+    #[test]
+    fn test_identity_and_missing_route_waypoints() {
+        let device = Device::new("identity");
+        // The identity is stable and kept apart from the settings that backups copy
+        let id = device.db.get_sync_device_id().unwrap();
+        assert_eq!(device.db.get_sync_device_id().unwrap(), id);
+        assert!(device.db.get_setting_entries().unwrap().iter().all(|(_, value)| *value != id));
+
+        let route = SyncRoute { route: Route { name: "Gulf run".to_string(), ..Default::default() }, waypoints: vec!["gone".to_string()], tags: vec![] };
+        let change = SyncChange {
+            table: SyncTable::Routes,
+            uuid: uuid::Uuid::new_v4().to_string(),
+            updated_at: "2026-03-01T00:00:00Z".to_string(),
+            object: Some(SyncObject::Route(route)),
+        };
+        let outcome = device.db.transaction(|tx| apply_changes(tx, "peer", 0, vec![change])).unwrap();
+        assert_eq!(outcome.errors, ["Route Gulf run refers to unknown waypoint gone"]);
+    }
+
+    #[test]
+    fn test_oversize_message_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let sender = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            // The reader stops early, so the tail of this write may fail
+            let _ = stream.write_all(&vec![b'x'; MAX_MESSAGE_BYTES as usize + 1]);
+        });
+        let (stream, _) = listener.accept().unwrap();
+        assert!(matches!(read_message::<SyncRequest>(&stream), Err(SyncError::MessageTooLarge)));
+        drop(stream);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_port_free_after_drop() {
+        let device = Device::new("rebind");
+        let port = device.serve().port();
+        // Dropping the first server joined its listener, so the port is free again
+        assert_eq!(device.serve_on(port).port(), port);
+    }
+}
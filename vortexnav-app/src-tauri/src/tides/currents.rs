@@ -0,0 +1,480 @@
+// Tidal Streams
+// Harmonic tidal current stations, gridded tidal stream tables referenced to
+// high water at a tide station, and the combined current model for navigation
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{check_range, harmonic_sum, parse_sections, sample_times, turning_points, TideConstituent, TideError, TideStation};
+use crate::navigation::{haversine_distance, CurrentProvider};
+
+/// Harmonic current stations are used up to this distance from a position
+const STATION_RANGE_NM: f64 = 10.0;
+/// Grid points are interpolated within this distance of a position
+const GRID_RANGE_NM: f64 = 5.0;
+/// Tidal stream tables cover HW-6 to HW+6
+const GRID_HOURS: usize = 13;
+/// Along-axis speed below which the stream is reported as slack
+const SLACK_KN: f64 = 0.1;
+
+/// A tidal current station: along-axis speed from harmonic constituents (knots, flood positive)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalCurrentStation {
+    pub id: Option<i64>,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// Direction of the flood stream (degrees true)
+    pub flood_direction: f64,
+    /// Direction of the ebb stream (degrees true)
+    pub ebb_direction: f64,
+    /// Residual along-axis speed (knots)
+    pub mean_speed_kn: f64,
+    pub source: Option<String>,
+    pub constituents: Vec<TideConstituent>,
+}
+
+/// Predicted stream at a time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalCurrentSample {
+    pub time: String,
+    /// Direction the stream flows towards (degrees true)
+    pub set: f64,
+    pub drift_kn: f64,
+    /// Along-axis speed, positive flood, negative ebb
+    pub speed_kn: f64,
+}
+
+/// Slack water or maximum flood/ebb
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalCurrentEvent {
+    pub time: String,
+    pub kind: String, // "slack", "max_flood" or "max_ebb"
+    pub speed_kn: f64,
+}
+
+/// Stream predictions for a current station over a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalCurrentPrediction {
+    pub station_id: Option<i64>,
+    pub station_name: String,
+    pub samples: Vec<TidalCurrentSample>,
+    pub events: Vec<TidalCurrentEvent>,
+}
+
+impl TidalCurrentStation {
+    /// Along-axis speed (knots), positive flood
+    pub fn speed_at(&self, time: DateTime<Utc>) -> f64 {
+        self.mean_speed_kn + harmonic_sum(&self.constituents, time)
+    }
+
+    /// Set and drift at a time
+    pub fn current_at(&self, time: DateTime<Utc>) -> (f64, f64) {
+        let speed = self.speed_at(time);
+        let set = if speed >= 0.0 { self.flood_direction } else { self.ebb_direction };
+        (set, speed.abs())
+    }
+
+    pub fn predict(&self, start: DateTime<Utc>, end: DateTime<Utc>, step_minutes: i64) -> Result<TidalCurrentPrediction, TideError> {
+        check_range(start, end)?;
+
+        let samples = sample_times(start, end, step_minutes)
+            .map(|time| {
+                let speed_kn = self.speed_at(time);
+                let (set, drift_kn) = self.current_at(time);
+                TidalCurrentSample { time: time.to_rfc3339(), set, drift_kn, speed_kn }
+            })
+            .collect();
+
+        let mut events: Vec<(DateTime<Utc>, TidalCurrentEvent)> = turning_points(|t| self.speed_at(t), start, end)
+            .into_iter()
+            .filter_map(|(time, speed_kn, is_max)| {
+                let kind = match (is_max, speed_kn > 0.0) {
+                    (true, true) => "max_flood",
+                    (false, false) => "max_ebb",
+                    _ => return None,
+                };
+                Some((time, TidalCurrentEvent { time: time.to_rfc3339(), kind: kind.to_string(), speed_kn }))
+            })
+            .collect();
+
+        // Slack water where the along-axis speed changes sign
+        let step = Duration::minutes(10);
+        let mut t0 = start;
+        let mut v0 = self.speed_at(t0);
+        while t0 < end {
+            let t1 = (t0 + step).min(end);
+            let v1 = self.speed_at(t1);
+            if (v0 > 0.0) != (v1 > 0.0) {
+                let fraction = v0 / (v0 - v1);
+                let time = t0 + Duration::seconds(((t1 - t0).num_seconds() as f64 * fraction).round() as i64);
+                events.push((time, TidalCurrentEvent { time: time.to_rfc3339(), kind: "slack".to_string(), speed_kn: 0.0 }));
+            }
+            t0 = t1;
+            v0 = v1;
+        }
+        events.sort_by_key(|(time, _)| *time);
+
+        Ok(TidalCurrentPrediction {
+            station_id: self.id,
+            station_name: self.name.clone(),
+            samples,
+            events: events.into_iter().map(|(_, e)| e).collect(),
+        })
+    }
+
+    /// Whether the stream is weaker than the slack threshold at a time
+    pub fn is_slack(&self, time: DateTime<Utc>) -> bool {
+        self.speed_at(time).abs() < SLACK_KN
+    }
+}
+
+/// Parse a tidal current station file. Same layout as tide station files, with
+/// the flood and ebb directions and constituent amplitudes in knots:
+///
+/// ```text
+/// [Rangitoto Channel]
+/// lat = -36.79
+/// lon = 174.85
+/// flood = 225
+/// ebb = 45
+/// M2 = 1.8, 165.0
+/// ```
+///
+/// `ebb` defaults to the reciprocal of `flood`; `mean` sets a residual speed.
+pub fn parse_current_station_file(text: &str) -> Result<Vec<TidalCurrentStation>, TideError> {
+    parse_sections(text, &["flood", "ebb", "mean"])?
+        .into_iter()
+        .map(|section| {
+            let flood_direction = section
+                .value(&["flood"])
+                .ok_or_else(|| TideError::InvalidFile(format!("Station '{}' has no flood direction", section.name)))?;
+            Ok(TidalCurrentStation {
+                id: None,
+                ebb_direction: section.value(&["ebb"]).unwrap_or((flood_direction + 180.0) % 360.0),
+                flood_direction,
+                mean_speed_kn: section.value(&["mean"]).unwrap_or(0.0),
+                name: section.name,
+                lat: section.lat,
+                lon: section.lon,
+                source: section.source,
+                constituents: section.constituents,
+            })
+        })
+        .collect()
+}
+
+/// One position of a tidal stream table: set and rates for each hour from HW-6 to HW+6
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalStreamPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub set: Vec<f64>,
+    pub spring_kn: Vec<f64>,
+    pub neap_kn: Vec<f64>,
+}
+
+/// Gridded tidal stream table referenced to high water at a tide station
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalStreamGrid {
+    pub id: Option<i64>,
+    pub name: String,
+    /// Name of the tide station the hours are referenced to
+    pub reference_station: String,
+    /// Mean spring and neap ranges at the reference station (meters)
+    pub spring_range_m: f64,
+    pub neap_range_m: f64,
+    pub points: Vec<TidalStreamPoint>,
+}
+
+impl TidalStreamGrid {
+    /// Set and drift at a position and time, interpolated between nearby table points
+    /// and scaled between neap and spring rates by the day's range at the reference
+    pub fn current_at(&self, reference: &TideStation, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<(f64, f64)> {
+        let nearby: Vec<(&TidalStreamPoint, f64)> = self
+            .points
+            .iter()
+            .map(|p| (p, haversine_distance(lat, lon, p.lat, p.lon)))
+            .filter(|(_, d)| *d <= GRID_RANGE_NM)
+            .collect();
+        if nearby.is_empty() {
+            return None;
+        }
+
+        let (offset_hours, range) = self.reference_tide(reference, time)?;
+        let spread = self.spring_range_m - self.neap_range_m;
+        let factor = if spread > 0.0 {
+            ((range - self.neap_range_m) / spread).clamp(-0.5, 1.5)
+        } else {
+            1.0
+        };
+
+        let index = (offset_hours + 6.0).clamp(0.0, (GRID_HOURS - 1) as f64);
+        let i0 = index.floor() as usize;
+        let i1 = (i0 + 1).min(GRID_HOURS - 1);
+        let t = index - i0 as f64;
+
+        // Inverse-distance weighted vector average
+        let (mut east, mut north, mut weights) = (0.0, 0.0, 0.0);
+        for (point, distance) in nearby {
+            let weight = 1.0 / (distance * distance).max(1e-6);
+            for (i, w) in [(i0, 1.0 - t), (i1, t)] {
+                let rate = (point.neap_kn[i] + (point.spring_kn[i] - point.neap_kn[i]) * factor).max(0.0);
+                east += weight * w * rate * point.set[i].to_radians().sin();
+                north += weight * w * rate * point.set[i].to_radians().cos();
+            }
+            weights += weight;
+        }
+        east /= weights;
+        north /= weights;
+
+        Some(((east.atan2(north).to_degrees() + 360.0) % 360.0, (east * east + north * north).sqrt()))
+    }
+
+    /// Hours from the nearest high water at the reference station and that tide's range
+    fn reference_tide(&self, reference: &TideStation, time: DateTime<Utc>) -> Option<(f64, f64)> {
+        let extremes = turning_points(|t| reference.height_at(t), time - Duration::hours(13), time + Duration::hours(13));
+        let (hw_index, (hw_time, hw_height, _)) = extremes
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, is_max))| *is_max)
+            .min_by_key(|(_, (t, _, _))| (*t - time).num_seconds().abs())?;
+
+        let lows: Vec<f64> = [hw_index.checked_sub(1), Some(hw_index + 1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| extremes.get(i))
+            .filter(|(_, _, is_max)| !is_max)
+            .map(|(_, h, _)| *h)
+            .collect();
+        if lows.is_empty() {
+            return None;
+        }
+        let range = hw_height - lows.iter().sum::<f64>() / lows.len() as f64;
+
+        Some(((time - *hw_time).num_seconds() as f64 / 3600.0, range))
+    }
+}
+
+/// Parse a tidal stream table:
+///
+/// ```text
+/// name = Hauraki Gulf
+/// reference = Auckland
+/// spring_range = 3.0
+/// neap_range = 1.9
+/// # lat, lon, then set/spring/neap for HW-6 .. HW+6
+/// -36.80, 174.90, 045/1.2/0.6, 050/1.0/0.5, ...
+/// ```
+pub fn parse_stream_grid_file(text: &str) -> Result<TidalStreamGrid, TideError> {
+    let mut grid = TidalStreamGrid {
+        id: None,
+        name: String::new(),
+        reference_station: String::new(),
+        spring_range_m: 0.0,
+        neap_range_m: 0.0,
+        points: Vec::new(),
+    };
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line_no = index + 1;
+        let number = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|_| TideError::InvalidFile(format!("Line {}: invalid number '{}'", line_no, v.trim())))
+        };
+
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => grid.name = value.to_string(),
+                "reference" => grid.reference_station = value.to_string(),
+                "spring_range" => grid.spring_range_m = number(value)?,
+                "neap_range" => grid.neap_range_m = number(value)?,
+                other => return Err(TideError::InvalidFile(format!("Line {}: unknown setting '{}'", line_no, other))),
+            }
+            continue;
+        }
+
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        if cells.len() != GRID_HOURS + 2 {
+            return Err(TideError::InvalidFile(format!(
+                "Line {}: expected lat, lon and {} set/spring/neap entries",
+                line_no, GRID_HOURS
+            )));
+        }
+
+        let mut point = TidalStreamPoint {
+            lat: number(cells[0])?,
+            lon: number(cells[1])?,
+            set: Vec::with_capacity(GRID_HOURS),
+            spring_kn: Vec::with_capacity(GRID_HOURS),
+            neap_kn: Vec::with_capacity(GRID_HOURS),
+        };
+        for cell in &cells[2..] {
+            let parts: Vec<&str> = cell.split('/').collect();
+            let [set, spring, neap] = parts.as_slice() else {
+                return Err(TideError::InvalidFile(format!("Line {}: expected set/spring/neap, got '{}'", line_no, cell)));
+            };
+            point.set.push(number(set)?);
+            point.spring_kn.push(number(spring)?);
+            point.neap_kn.push(number(neap)?);
+        }
+        grid.points.push(point);
+    }
+
+    if grid.reference_station.is_empty() {
+        return Err(TideError::InvalidFile("No reference station".to_string()));
+    }
+    if grid.points.is_empty() {
+        return Err(TideError::InvalidFile("No tidal stream positions".to_string()));
+    }
+    if grid.name.is_empty() {
+        grid.name = grid.reference_station.clone();
+    }
+    Ok(grid)
+}
+
+/// Tidal streams from tables and current stations, as used for passage planning
+#[derive(Default)]
+pub struct TidalCurrentModel {
+    stations: Vec<TidalCurrentStation>,
+    grids: Vec<(TidalStreamGrid, TideStation)>,
+}
+
+/// Stream at a position and the source it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidalStreamSample {
+    pub set: f64,
+    pub drift_kn: f64,
+    pub source: String,
+}
+
+impl TidalCurrentModel {
+    /// Build the model, pairing each grid with its reference tide station by name.
+    /// Grids whose reference station isn't loaded are left out.
+    pub fn new(stations: Vec<TidalCurrentStation>, grids: Vec<TidalStreamGrid>, tide_stations: &[TideStation]) -> Self {
+        let grids = grids
+            .into_iter()
+            .filter_map(|grid| {
+                let reference = tide_stations
+                    .iter()
+                    .find(|s| s.name.eq_ignore_ascii_case(&grid.reference_station))
+                    .cloned();
+                if reference.is_none() {
+                    log::warn!(
+                        "Tidal stream table '{}' skipped: reference station '{}' not loaded",
+                        grid.name,
+                        grid.reference_station
+                    );
+                }
+                reference.map(|r| (grid, r))
+            })
+            .collect();
+
+        Self { stations, grids }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty() && self.grids.is_empty()
+    }
+
+    /// Stream at a position: tidal stream tables first, then the nearest current station in range
+    pub fn sample(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<TidalStreamSample> {
+        for (grid, reference) in &self.grids {
+            if let Some((set, drift_kn)) = grid.current_at(reference, lat, lon, time) {
+                return Some(TidalStreamSample { set, drift_kn, source: grid.name.clone() });
+            }
+        }
+
+        self.stations
+            .iter()
+            .map(|s| (s, haversine_distance(lat, lon, s.lat, s.lon)))
+            .filter(|(_, d)| *d <= STATION_RANGE_NM)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(station, _)| {
+                let (set, drift_kn) = station.current_at(time);
+                TidalStreamSample { set, drift_kn, source: station.name.clone() }
+            })
+    }
+}
+
+impl CurrentProvider for TidalCurrentModel {
+    fn current_at(&self, lat: f64, lon: f64, time: DateTime<Utc>) -> Option<(f64, f64)> {
+        self.sample(lat, lon, time).map(|s| (s.set, s.drift_kn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tides::parse_station_file;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_current_station_events() {
+        let stations = parse_current_station_file(
+            "[Channel]\nlat = -36.79\nlon = 174.85\nflood = 225\nM2 = 2.0, 100.0\n",
+        )
+        .unwrap();
+        let station = &stations[0];
+        assert_eq!(station.ebb_direction, 45.0);
+
+        let start = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        let prediction = station.predict(start, start + Duration::days(1), 60).unwrap();
+        assert_eq!(prediction.samples.len(), 25);
+
+        let kinds: Vec<&str> = prediction.events.iter().map(|e| e.kind.as_str()).collect();
+        assert!(kinds.iter().filter(|k| **k == "slack").count() >= 3);
+        assert!(kinds.contains(&"max_flood") && kinds.contains(&"max_ebb"));
+        // Slack falls between a maximum flood and a maximum ebb
+        for window in prediction.events.windows(2) {
+            assert_ne!(window[0].kind, window[1].kind);
+        }
+        for event in prediction.events.iter().filter(|e| e.kind == "slack") {
+            assert!(station.is_slack(DateTime::parse_from_rfc3339(&event.time).unwrap().with_timezone(&Utc)));
+        }
+
+        assert!(parse_current_station_file("[X]\nlat = 1\nlon = 2\nM2 = 1, 2\n").is_err());
+    }
+
+    #[test]
+    fn test_stream_grid_follows_reference_high_water() {
+        let reference = parse_station_file("[Port]\nlat = -36.8\nlon = 174.8\nz0 = 2.0\nM2 = 1.5, 0.0\n")
+            .unwrap()
+            .remove(0);
+
+        // Stream runs north before HW and south after, at 1 knot neaps, 2 knots springs
+        let mut row = String::from("-36.80, 174.90");
+        for hour in -6i32..=6 {
+            let set = if hour < 0 { 0 } else { 180 };
+            row.push_str(&format!(", {}/{}/{}", set, 2.0, 1.0));
+        }
+        let text = format!("name = Gulf\nreference = port\nspring_range = 3.0\nneap_range = 1.5\n{}\n", row);
+        let grid = parse_stream_grid_file(&text).unwrap();
+        assert_eq!(grid.points[0].set.len(), GRID_HOURS);
+
+        let model = TidalCurrentModel::new(Vec::new(), vec![grid], std::slice::from_ref(&reference));
+        let start = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        let hw = reference
+            .find_extremes(start, start + Duration::hours(13))
+            .into_iter()
+            .find(|e| e.kind == "high")
+            .map(|e| DateTime::parse_from_rfc3339(&e.time).unwrap().with_timezone(&Utc))
+            .unwrap();
+
+        // Range at the reference is about 3 m (less the M2 node factor), so close to spring rates
+        let before = model.sample(-36.80, 174.91, hw - Duration::hours(3)).unwrap();
+        assert!(before.set.min(360.0 - before.set) < 1.0);
+        assert!((before.drift_kn - 2.0).abs() < 0.1, "{}", before.drift_kn);
+        let after = model.current_at(-36.80, 174.91, hw + Duration::hours(3)).unwrap();
+        assert!((after.0 - 180.0).abs() < 1.0);
+
+        // Outside the table and with no current stations there is no stream
+        assert!(model.sample(-37.5, 175.5, hw).is_none());
+    }
+}
@@ -0,0 +1,163 @@
+// Tidal Harmonics
+// Constituent table, astronomical arguments and nodal corrections (after Schureman)
+
+use chrono::{DateTime, TimeZone, Utc};
+
+/// Lunar node groups used for nodal corrections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    M2,
+    K1,
+    O1,
+    K2,
+    J1,
+    OO1,
+    Mm,
+    Mf,
+}
+
+/// Equilibrium argument and nodal behaviour of one harmonic constituent
+pub struct ConstituentDef {
+    pub name: &'static str,
+    /// Multipliers of T (mean solar hour angle), s, h, p and p1
+    doodson: [i8; 5],
+    /// Constant phase offset (degrees)
+    offset: f64,
+    /// Node groups and their multipliers: f = product of f^|n|, u = sum of n * u
+    nodal: &'static [(Node, i8)],
+}
+
+const M2: &[(Node, i8)] = &[(Node::M2, 1)];
+const NONE: &[(Node, i8)] = &[];
+
+/// Supported constituents
+pub const CONSTITUENTS: &[ConstituentDef] = &[
+    ConstituentDef { name: "M2", doodson: [2, -2, 2, 0, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "S2", doodson: [2, 0, 0, 0, 0], offset: 0.0, nodal: NONE },
+    ConstituentDef { name: "N2", doodson: [2, -3, 2, 1, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "K2", doodson: [2, 0, 2, 0, 0], offset: 0.0, nodal: &[(Node::K2, 1)] },
+    ConstituentDef { name: "K1", doodson: [1, 0, 1, 0, 0], offset: -90.0, nodal: &[(Node::K1, 1)] },
+    ConstituentDef { name: "O1", doodson: [1, -2, 1, 0, 0], offset: 90.0, nodal: &[(Node::O1, 1)] },
+    ConstituentDef { name: "P1", doodson: [1, 0, -1, 0, 0], offset: 90.0, nodal: NONE },
+    ConstituentDef { name: "Q1", doodson: [1, -3, 1, 1, 0], offset: 90.0, nodal: &[(Node::O1, 1)] },
+    ConstituentDef { name: "2N2", doodson: [2, -4, 2, 2, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "NU2", doodson: [2, -3, 4, -1, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "MU2", doodson: [2, -4, 4, 0, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "L2", doodson: [2, -1, 2, -1, 0], offset: 180.0, nodal: M2 },
+    ConstituentDef { name: "LAM2", doodson: [2, -1, 0, 1, 0], offset: 180.0, nodal: M2 },
+    ConstituentDef { name: "T2", doodson: [2, 0, -1, 0, 1], offset: 0.0, nodal: NONE },
+    ConstituentDef { name: "2SM2", doodson: [2, 2, -2, 0, 0], offset: 0.0, nodal: &[(Node::M2, -1)] },
+    ConstituentDef { name: "MKS2", doodson: [2, -2, 4, 0, 0], offset: 0.0, nodal: &[(Node::M2, 1), (Node::K2, 1)] },
+    ConstituentDef { name: "J1", doodson: [1, 1, 1, -1, 0], offset: -90.0, nodal: &[(Node::J1, 1)] },
+    ConstituentDef { name: "OO1", doodson: [1, 2, 1, 0, 0], offset: -90.0, nodal: &[(Node::OO1, 1)] },
+    ConstituentDef { name: "RHO1", doodson: [1, -3, 3, -1, 0], offset: 90.0, nodal: &[(Node::O1, 1)] },
+    ConstituentDef { name: "2Q1", doodson: [1, -4, 1, 2, 0], offset: 90.0, nodal: &[(Node::O1, 1)] },
+    ConstituentDef { name: "MK3", doodson: [3, -2, 3, 0, 0], offset: -90.0, nodal: &[(Node::M2, 1), (Node::K1, 1)] },
+    ConstituentDef { name: "2MK3", doodson: [3, -4, 3, 0, 0], offset: 90.0, nodal: &[(Node::M2, 2), (Node::K1, -1)] },
+    ConstituentDef { name: "M4", doodson: [4, -4, 4, 0, 0], offset: 0.0, nodal: &[(Node::M2, 2)] },
+    ConstituentDef { name: "MS4", doodson: [4, -2, 2, 0, 0], offset: 0.0, nodal: M2 },
+    ConstituentDef { name: "MN4", doodson: [4, -5, 4, 1, 0], offset: 0.0, nodal: &[(Node::M2, 2)] },
+    ConstituentDef { name: "S4", doodson: [4, 0, 0, 0, 0], offset: 0.0, nodal: NONE },
+    ConstituentDef { name: "M6", doodson: [6, -6, 6, 0, 0], offset: 0.0, nodal: &[(Node::M2, 3)] },
+    ConstituentDef { name: "M8", doodson: [8, -8, 8, 0, 0], offset: 0.0, nodal: &[(Node::M2, 4)] },
+    ConstituentDef { name: "SA", doodson: [0, 0, 1, 0, 0], offset: 0.0, nodal: NONE },
+    ConstituentDef { name: "SSA", doodson: [0, 0, 2, 0, 0], offset: 0.0, nodal: NONE },
+    ConstituentDef { name: "MM", doodson: [0, 1, 0, -1, 0], offset: 0.0, nodal: &[(Node::Mm, 1)] },
+    ConstituentDef { name: "MF", doodson: [0, 2, 0, 0, 0], offset: 0.0, nodal: &[(Node::Mf, 1)] },
+    ConstituentDef { name: "MSF", doodson: [0, 2, -2, 0, 0], offset: 0.0, nodal: &[(Node::M2, -1)] },
+];
+
+/// Look up a constituent by name (case-insensitive)
+pub fn find_constituent(name: &str) -> Option<&'static ConstituentDef> {
+    CONSTITUENTS.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+}
+
+/// Mean astronomical longitudes at a time (degrees)
+#[derive(Debug, Clone, Copy)]
+pub struct Astro {
+    /// Hour angle of the mean sun at Greenwich
+    t: f64,
+    /// Mean longitude of the moon
+    s: f64,
+    /// Mean longitude of the sun
+    h: f64,
+    /// Longitude of the lunar perigee
+    p: f64,
+    /// Longitude of the moon's ascending node
+    n: f64,
+    /// Longitude of the solar perigee
+    p1: f64,
+}
+
+impl Astro {
+    pub fn at(time: DateTime<Utc>) -> Self {
+        let j2000 = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+        let days = (time - j2000).num_milliseconds() as f64 / 86_400_000.0;
+        let centuries = days / 36525.0;
+
+        Self {
+            // The mean sun is on the Greenwich meridian at 12:00 UT
+            t: (days.rem_euclid(1.0)) * 360.0,
+            s: 218.3164477 + 481_267.881_234_21 * centuries,
+            h: 280.46646 + 36_000.769_83 * centuries,
+            p: 83.3532465 + 4_069.013_728_7 * centuries,
+            n: 125.04452 - 1_934.136_261 * centuries,
+            p1: 282.93735 + 1.71946 * centuries,
+        }
+    }
+}
+
+fn node_factors(node: Node, n: f64) -> (f64, f64) {
+    let n = n.to_radians();
+    let (c1, c2, c3) = (n.cos(), (2.0 * n).cos(), (3.0 * n).cos());
+    let (s1, s2, s3) = (n.sin(), (2.0 * n).sin(), (3.0 * n).sin());
+    match node {
+        Node::M2 => (1.0004 - 0.0373 * c1 + 0.0002 * c2, -2.14 * s1),
+        Node::K1 => (1.0060 + 0.1150 * c1 - 0.0088 * c2 + 0.0006 * c3, -8.86 * s1 + 0.68 * s2 - 0.07 * s3),
+        Node::O1 => (1.0089 + 0.1871 * c1 - 0.0147 * c2 + 0.0014 * c3, 10.80 * s1 - 1.34 * s2 + 0.19 * s3),
+        Node::K2 => (1.0241 + 0.2863 * c1 + 0.0083 * c2 - 0.0015 * c3, -17.74 * s1 + 0.68 * s2 - 0.04 * s3),
+        Node::J1 => (1.1029 + 0.1676 * c1 - 0.0170 * c2 + 0.0016 * c3, -12.94 * s1 + 1.34 * s2 - 0.19 * s3),
+        Node::OO1 => (1.1027 + 0.6504 * c1 + 0.0317 * c2 - 0.0014 * c3, -36.68 * s1 + 4.02 * s2 - 0.57 * s3),
+        Node::Mm => (1.0 - 0.1300 * c1 + 0.0013 * c2, 0.0),
+        Node::Mf => (1.0429 + 0.4135 * c1 - 0.0040 * c2, -23.74 * s1 + 2.68 * s2 - 0.38 * s3),
+    }
+}
+
+impl ConstituentDef {
+    /// Equilibrium argument V + u (degrees) and node factor f at a time
+    pub fn argument(&self, astro: &Astro) -> (f64, f64) {
+        let [dt, ds, dh, dp, dp1] = self.doodson.map(f64::from);
+        let v = dt * astro.t + ds * astro.s + dh * astro.h + dp * astro.p + dp1 * astro.p1 + self.offset;
+
+        let mut f = 1.0;
+        let mut u = 0.0;
+        for (node, k) in self.nodal {
+            let (fn_, un) = node_factors(*node, astro.n);
+            f *= fn_.powi(k.unsigned_abs() as i32);
+            u += f64::from(*k) * un;
+        }
+
+        ((v + u).rem_euclid(360.0), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solar_arguments() {
+        // S2 is at its equilibrium maximum at 00:00 and 12:00 UT
+        let s2 = find_constituent("s2").unwrap();
+        for hour in [0, 12] {
+            let (arg, f) = s2.argument(&Astro::at(Utc.with_ymd_and_hms(2025, 3, 1, hour, 0, 0).unwrap()));
+            assert!(arg.min(360.0 - arg) < 1e-6, "{}", arg);
+            assert_eq!(f, 1.0);
+        }
+
+        // M2 node factor stays within its 18.6 year range
+        let m2 = find_constituent("M2").unwrap();
+        let (_, f) = m2.argument(&Astro::at(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap()));
+        assert!((0.962..=1.038).contains(&f));
+    }
+}
@@ -0,0 +1,436 @@
+// Tides Module
+// Offline tidal height prediction from harmonic constituents
+
+mod currents;
+mod harmonics;
+mod route;
+
+pub use currents::*;
+pub use route::*;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use harmonics::{find_constituent, Astro};
+
+use crate::navigation::haversine_distance;
+
+/// Sampling step used to locate high and low water
+const EXTREME_SEARCH_MINUTES: i64 = 10;
+
+#[derive(Error, Debug)]
+pub enum TideError {
+    #[error("Invalid tide station file: {0}")]
+    InvalidFile(String),
+    #[error("Unknown tidal constituent '{0}'")]
+    UnknownConstituent(String),
+    #[error("Invalid time range: {0}")]
+    InvalidRange(String),
+}
+
+/// Amplitude and Greenwich phase lag of one harmonic constituent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideConstituent {
+    pub name: String,
+    /// Meters for heights, knots for tidal streams
+    #[serde(alias = "amplitude_m")]
+    pub amplitude: f64,
+    /// Phase lag relative to UTC (degrees)
+    pub phase_deg: f64,
+}
+
+/// A tide station with its harmonic constants
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideStation {
+    pub id: Option<i64>,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// Mean sea level above chart datum (Z0, meters)
+    pub mean_level_m: f64,
+    pub source: Option<String>,
+    pub constituents: Vec<TideConstituent>,
+}
+
+/// Predicted height at a time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideHeight {
+    pub time: String,
+    pub height_m: f64,
+}
+
+/// High or low water
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideExtreme {
+    pub time: String,
+    pub height_m: f64,
+    pub kind: String, // "high" or "low"
+}
+
+/// Heights and high/low times for a station over a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TidePrediction {
+    pub station_id: Option<i64>,
+    pub station_name: String,
+    pub heights: Vec<TideHeight>,
+    pub extremes: Vec<TideExtreme>,
+}
+
+/// Tide state at a single time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TideState {
+    pub height_m: f64,
+    pub rising: bool,
+    pub previous_extreme: Option<TideExtreme>,
+    pub next_extreme: Option<TideExtreme>,
+}
+
+impl TideStation {
+    /// Predicted height above chart datum (meters) at a time
+    pub fn height_at(&self, time: DateTime<Utc>) -> f64 {
+        self.mean_level_m + harmonic_sum(&self.constituents, time)
+    }
+
+    /// Heights at a fixed interval from start to end (inclusive)
+    pub fn predict_heights(&self, start: DateTime<Utc>, end: DateTime<Utc>, step_minutes: i64) -> Vec<TideHeight> {
+        sample_times(start, end, step_minutes)
+            .map(|time| TideHeight { time: time.to_rfc3339(), height_m: self.height_at(time) })
+            .collect()
+    }
+
+    /// High and low waters between start and end
+    pub fn find_extremes(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<TideExtreme> {
+        self.timed_extremes(start, end).into_iter().map(|(_, extreme)| extreme).collect()
+    }
+
+    fn timed_extremes(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(DateTime<Utc>, TideExtreme)> {
+        turning_points(|t| self.height_at(t), start, end)
+            .into_iter()
+            .map(|(time, height_m, is_max)| {
+                let kind = if is_max { "high" } else { "low" }.to_string();
+                (time, TideExtreme { time: time.to_rfc3339(), height_m, kind })
+            })
+            .collect()
+    }
+
+    /// Heights and extremes over a range
+    pub fn predict(&self, start: DateTime<Utc>, end: DateTime<Utc>, step_minutes: i64) -> Result<TidePrediction, TideError> {
+        check_range(start, end)?;
+
+        Ok(TidePrediction {
+            station_id: self.id,
+            station_name: self.name.clone(),
+            heights: self.predict_heights(start, end, step_minutes),
+            extremes: self.find_extremes(start, end),
+        })
+    }
+
+    /// Height, direction and surrounding high/low waters at a time
+    pub fn state_at(&self, time: DateTime<Utc>) -> TideState {
+        let window = self.timed_extremes(time - Duration::hours(14), time + Duration::hours(14));
+        let previous_extreme = window.iter().rev().find(|(t, _)| *t <= time).map(|(_, e)| e.clone());
+        let next_extreme = window.iter().find(|(t, _)| *t > time).map(|(_, e)| e.clone());
+
+        let rising = match &next_extreme {
+            Some(next) => next.kind == "high",
+            None => self.height_at(time + Duration::minutes(5)) > self.height_at(time),
+        };
+
+        TideState { height_m: self.height_at(time), rising, previous_extreme, next_extreme }
+    }
+
+    pub fn distance_nm(&self, lat: f64, lon: f64) -> f64 {
+        haversine_distance(self.lat, self.lon, lat, lon)
+    }
+}
+
+/// Sum of harmonic constituents at a time (amplitude units)
+pub(crate) fn harmonic_sum(constituents: &[TideConstituent], time: DateTime<Utc>) -> f64 {
+    let astro = Astro::at(time);
+    constituents
+        .iter()
+        .filter_map(|c| find_constituent(&c.name).map(|def| (c, def)))
+        .map(|(c, def)| {
+            let (arg, f) = def.argument(&astro);
+            f * c.amplitude * (arg - c.phase_deg).to_radians().cos()
+        })
+        .sum()
+}
+
+pub(crate) fn check_range(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<(), TideError> {
+    if end <= start {
+        return Err(TideError::InvalidRange("End must be after start".to_string()));
+    }
+    if end - start > Duration::days(400) {
+        return Err(TideError::InvalidRange("Range is limited to 400 days".to_string()));
+    }
+    Ok(())
+}
+
+/// Times from start to end (inclusive) at a fixed step
+pub(crate) fn sample_times(start: DateTime<Utc>, end: DateTime<Utc>, step_minutes: i64) -> impl Iterator<Item = DateTime<Utc>> {
+    let step = Duration::minutes(step_minutes.max(1));
+    std::iter::successors(Some(start), move |t| Some(*t + step)).take_while(move |t| *t <= end)
+}
+
+/// Local maxima and minima of a smooth function of time between start and end,
+/// as (time, value, is_maximum)
+pub(crate) fn turning_points(
+    f: impl Fn(DateTime<Utc>) -> f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, f64, bool)> {
+    let step = Duration::minutes(EXTREME_SEARCH_MINUTES);
+    let step_seconds = EXTREME_SEARCH_MINUTES as f64 * 60.0;
+    let mut points = Vec::new();
+
+    let mut h0 = f(start - step);
+    let mut t1 = start;
+    let mut h1 = f(t1);
+    while t1 <= end + step {
+        let t2 = t1 + step;
+        let h2 = f(t2);
+
+        if (h1 >= h0 && h1 > h2) || (h1 <= h0 && h1 < h2) {
+            // Fit a parabola through the three samples to refine the turning point
+            let denom = h0 - 2.0 * h1 + h2;
+            let offset = if denom.abs() > 1e-12 { 0.5 * (h0 - h2) / denom } else { 0.0 };
+            let time = t1 + Duration::seconds((offset * step_seconds).round() as i64);
+            if time >= start && time <= end {
+                points.push((time, f(time), h1 > h2));
+            }
+        }
+
+        h0 = h1;
+        t1 = t2;
+        h1 = h2;
+    }
+
+    points
+}
+
+/// Nearest station to a position and its distance in nautical miles
+pub fn nearest_station(stations: &[TideStation], lat: f64, lon: f64) -> Option<(&TideStation, f64)> {
+    stations
+        .iter()
+        .map(|s| (s, s.distance_nm(lat, lon)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// One `[Station]` block of a station file
+pub(crate) struct StationSection {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub source: Option<String>,
+    /// Other `key = number` settings, keys lowercased
+    pub values: Vec<(String, f64)>,
+    pub constituents: Vec<TideConstituent>,
+}
+
+impl StationSection {
+    pub fn value(&self, keys: &[&str]) -> Option<f64> {
+        self.values.iter().find(|(k, _)| keys.contains(&k.as_str())).map(|(_, v)| *v)
+    }
+}
+
+/// Parse the bracketed station file format shared by tide and tidal current stations.
+/// `settings` lists the numeric keys accepted besides lat, lon and source;
+/// any other key must be a constituent name.
+pub(crate) fn parse_sections(text: &str, settings: &[&str]) -> Result<Vec<StationSection>, TideError> {
+    let mut sections: Vec<(StationSection, bool, bool)> = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line_no = index + 1;
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((
+                StationSection {
+                    name: name.trim().to_string(),
+                    lat: 0.0,
+                    lon: 0.0,
+                    source: None,
+                    values: Vec::new(),
+                    constituents: Vec::new(),
+                },
+                false,
+                false,
+            ));
+            continue;
+        }
+
+        let Some((section, has_lat, has_lon)) = sections.last_mut() else {
+            return Err(TideError::InvalidFile(format!("Line {}: expected [Station name]", line_no)));
+        };
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(TideError::InvalidFile(format!("Line {}: expected 'key = value'", line_no)));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let number = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|_| TideError::InvalidFile(format!("Line {}: invalid number '{}'", line_no, v.trim())))
+        };
+
+        let lower = key.to_ascii_lowercase();
+        match lower.as_str() {
+            "lat" | "latitude" => {
+                section.lat = number(value)?;
+                *has_lat = true;
+            }
+            "lon" | "longitude" => {
+                section.lon = number(value)?;
+                *has_lon = true;
+            }
+            "source" => section.source = Some(value.to_string()),
+            k if settings.contains(&k) => section.values.push((lower.clone(), number(value)?)),
+            _ => {
+                let def = find_constituent(key).ok_or_else(|| TideError::UnknownConstituent(key.to_string()))?;
+                let Some((amplitude, phase)) = value.split_once(',') else {
+                    return Err(TideError::InvalidFile(format!("Line {}: expected 'amplitude, phase'", line_no)));
+                };
+                section.constituents.push(TideConstituent {
+                    name: def.name.to_string(),
+                    amplitude: number(amplitude)?,
+                    phase_deg: number(phase)?,
+                });
+            }
+        }
+    }
+
+    if sections.is_empty() {
+        return Err(TideError::InvalidFile("No stations found".to_string()));
+    }
+    sections
+        .into_iter()
+        .map(|(section, has_lat, has_lon)| {
+            if !(has_lat && has_lon) {
+                return Err(TideError::InvalidFile(format!("Station '{}' has no position", section.name)));
+            }
+            if section.constituents.is_empty() {
+                return Err(TideError::InvalidFile(format!("Station '{}' has no constituents", section.name)));
+            }
+            Ok(section)
+        })
+        .collect()
+}
+
+/// Parse a tide station file. Each station starts with its name in brackets:
+///
+/// ```text
+/// # Harmonic constants, phases relative to UTC
+/// [Auckland]
+/// lat = -36.8439
+/// lon = 174.7684
+/// z0 = 1.92
+/// source = LINZ
+/// M2 = 1.155, 208.3
+/// S2 = 0.165, 273.6
+/// ```
+///
+/// Constituent lines are `NAME = amplitude (m), phase lag (degrees)`.
+pub fn parse_station_file(text: &str) -> Result<Vec<TideStation>, TideError> {
+    Ok(parse_sections(text, &["z0", "mean_level", "msl"])?
+        .into_iter()
+        .map(|section| TideStation {
+            id: None,
+            mean_level_m: section.value(&["z0", "mean_level", "msl"]).unwrap_or(0.0),
+            name: section.name,
+            lat: section.lat,
+            lon: section.lon,
+            source: section.source,
+            constituents: section.constituents,
+        })
+        .collect())
+}
+
+/// Parse an RFC 3339 time or a bare date (taken as 00:00 UTC)
+pub fn parse_tide_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const STATIONS: &str = "\
+# Test stations
+[Semi-diurnal]
+lat = -36.84
+lon = 174.77
+z0 = 2.0
+M2 = 1.0, 0.0
+
+[Mixed]  # comment
+lat = -41.28
+lon = 174.78
+z0 = 1.0
+m2 = 0.6, 120.0
+K1 = 0.2, 40.0
+";
+
+    #[test]
+    fn test_parse_station_file() {
+        let stations = parse_station_file(STATIONS).unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[1].name, "Mixed");
+        assert_eq!(stations[1].constituents[0].name, "M2");
+        assert_eq!(stations[1].constituents[1].phase_deg, 40.0);
+
+        let (nearest, distance) = nearest_station(&stations, -41.0, 174.8).unwrap();
+        assert_eq!(nearest.name, "Mixed");
+        assert!(distance < 20.0);
+
+        assert!(matches!(
+            parse_station_file("[X]\nlat = 1\nlon = 2\nZZ9 = 1, 2\n"),
+            Err(TideError::UnknownConstituent(_))
+        ));
+        assert!(parse_station_file("[X]\nM2 = 1, 2\n").is_err());
+    }
+
+    #[test]
+    fn test_predict_extremes() {
+        let station = parse_station_file(STATIONS).unwrap().remove(0);
+        let start = Utc.with_ymd_and_hms(2025, 1, 10, 0, 0, 0).unwrap();
+        let end = start + Duration::days(2);
+        let prediction = station.predict(start, end, 30).unwrap();
+
+        assert_eq!(prediction.heights.len(), 97);
+        // Pure M2: about four tides a day, alternating, 12h25m apart
+        let extremes = &prediction.extremes;
+        assert!(extremes.len() >= 7 && extremes.len() <= 8, "{}", extremes.len());
+        for pair in extremes.windows(2) {
+            assert_ne!(pair[0].kind, pair[1].kind);
+        }
+        for e in extremes {
+            let expected = if e.kind == "high" { 3.0 } else { 1.0 };
+            assert!((e.height_m - expected).abs() < 0.05, "{:?}", e);
+        }
+        let highs: Vec<_> = extremes.iter().filter(|e| e.kind == "high").map(|e| parse_tide_time(&e.time).unwrap()).collect();
+        let period = (highs[1] - highs[0]).num_minutes();
+        assert!((period - 745).abs() <= 2, "{}", period);
+
+        // High water is where the M2 argument matches the phase lag
+        let astro_arg = |t| find_constituent("M2").unwrap().argument(&Astro::at(t)).0;
+        let arg = astro_arg(highs[0]);
+        assert!(arg.min(360.0 - arg) < 1.0, "{}", arg);
+
+        let state = station.state_at(highs[0] + Duration::hours(2));
+        assert!(!state.rising);
+        assert_eq!(state.next_extreme.unwrap().kind, "low");
+    }
+}
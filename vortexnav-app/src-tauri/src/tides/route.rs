@@ -0,0 +1,50 @@
+// Tides Along a Route
+// Tide state at each point of a route's ETA table
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{nearest_station, TideExtreme, TideStation};
+use crate::navigation::{time_after, RouteEtaPoint};
+
+/// Tide at one point of the route ETA table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteTidePoint {
+    #[serde(flatten)]
+    pub point: RouteEtaPoint,
+    pub station_id: Option<i64>,
+    pub station_name: Option<String>,
+    pub station_distance_nm: Option<f64>,
+    pub height_m: Option<f64>,
+    pub rising: Option<bool>,
+    pub previous_extreme: Option<TideExtreme>,
+    pub next_extreme: Option<TideExtreme>,
+}
+
+/// Tide state at every point of an ETA table, using the nearest station
+/// within `max_distance_nm` of each point
+pub fn route_tides(
+    stations: &[TideStation],
+    points: &[RouteEtaPoint],
+    departure: DateTime<Utc>,
+    max_distance_nm: f64,
+) -> Vec<RouteTidePoint> {
+    points
+        .iter()
+        .map(|p| {
+            let station = nearest_station(stations, p.lat, p.lon).filter(|(_, d)| *d <= max_distance_nm);
+            let state = station.map(|(s, _)| s.state_at(time_after(departure, p.hours_from_start)));
+
+            RouteTidePoint {
+                point: p.clone(),
+                station_id: station.and_then(|(s, _)| s.id),
+                station_name: station.map(|(s, _)| s.name.clone()),
+                station_distance_nm: station.map(|(_, d)| d),
+                height_m: state.as_ref().map(|s| s.height_m),
+                rising: state.as_ref().map(|s| s.rising),
+                previous_extreme: state.as_ref().and_then(|s| s.previous_extreme.clone()),
+                next_extreme: state.and_then(|s| s.next_extreme),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,742 @@
+// User data archive
+// Exports everything the user has created to one zip and restores it by merging or replacing
+
+use chrono::Utc;
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use thiserror::Error;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::database::{
+    self, CatalogChart, ChartCatalog, ChartCustomMetadata, ChartLayerState, ConfigDatabase, DatabaseError, GpsSourceRecord,
+    Route, RouteTag, RouteWithWaypoints, TrackWithPoints, Waypoint, WaypointGroup, DEVICE_LOCAL_SETTINGS,
+};
+
+/// Bumped when the archive layout changes in a way older apps cannot read
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATA_ENTRY: &str = "data.json";
+
+#[derive(Error, Debug)]
+pub enum UserDataError {
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Invalid archive contents: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Archive version {0} is newer than this app supports")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub app_version: String,
+    pub exported_at: String,
+    pub waypoints: usize,
+    pub routes: usize,
+    pub tracks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogWithCharts {
+    pub catalog: ChartCatalog,
+    pub charts: Vec<CatalogChart>,
+}
+
+/// Everything a user data archive holds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserData {
+    pub settings: BTreeMap<String, String>,
+    pub waypoints: Vec<Waypoint>,
+    pub waypoint_groups: Vec<WaypointGroup>,
+    pub route_tags: Vec<RouteTag>,
+    pub routes: Vec<RouteWithWaypoints>,
+    pub tracks: Vec<TrackWithPoints>,
+    pub gps_sources: Vec<GpsSourceRecord>,
+    pub chart_layers: Vec<ChartLayerState>,
+    pub chart_metadata: Vec<ChartCustomMetadata>,
+    pub catalogs: Vec<CatalogWithCharts>,
+}
+
+impl UserData {
+    pub fn load(conn: &Connection) -> SqliteResult<Self> {
+        let catalogs = database::get_catalogs(conn)?
+            .into_iter()
+            .map(|catalog| {
+                let charts = database::get_catalog_charts(conn, catalog.id.unwrap_or_default())?;
+                Ok(CatalogWithCharts { catalog, charts })
+            })
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(Self {
+            settings: database::get_setting_entries(conn)?
+                .into_iter()
+                .filter(|(key, _)| !DEVICE_LOCAL_SETTINGS.contains(&key.as_str()))
+                .collect(),
+            waypoints: database::get_waypoints(conn)?,
+            waypoint_groups: database::get_waypoint_groups(conn)?,
+            route_tags: database::get_route_tags(conn)?,
+            routes: database::get_routes(conn)?,
+            tracks: database::get_tracks_with_points(conn)?,
+            gps_sources: database::get_gps_sources(conn)?,
+            chart_layers: database::get_chart_layer_states(conn)?,
+            chart_metadata: database::get_all_chart_custom_metadata(conn)?,
+            catalogs,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep existing data, add what is new and update what matches
+    Merge,
+    /// Delete existing user data first (after backing up the database)
+    Replace,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChangeCounts {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+impl ChangeCounts {
+    fn record(&mut self, change: Change) {
+        match change {
+            Change::Added => self.added += 1,
+            Change::Updated => self.updated += 1,
+            Change::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// What an import changed, or with `dry_run` what it would change
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    pub dry_run: bool,
+    pub exported_at: String,
+    /// Database copy taken before a replace
+    pub backup_path: Option<String>,
+    pub settings: ChangeCounts,
+    pub waypoints: ChangeCounts,
+    pub waypoint_groups: ChangeCounts,
+    pub route_tags: ChangeCounts,
+    pub routes: ChangeCounts,
+    pub tracks: ChangeCounts,
+    pub gps_sources: ChangeCounts,
+    pub chart_layers: ChangeCounts,
+    pub chart_metadata: ChangeCounts,
+    pub catalogs: ChangeCounts,
+}
+
+/// Write all user data to a zip at `path`
+pub fn export_archive(db: &ConfigDatabase, path: &Path) -> Result<ArchiveManifest, UserDataError> {
+    let data = db.transaction(|tx| UserData::load(tx))?;
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now().to_rfc3339(),
+        waypoints: data.waypoints.len(),
+        routes: data.routes.len(),
+        tracks: data.tracks.len(),
+    };
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(MANIFEST_ENTRY, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.start_file(DATA_ENTRY, options)?;
+    zip.write_all(&serde_json::to_vec(&data)?)?;
+    zip.finish()?;
+    Ok(manifest)
+}
+
+pub fn read_archive(path: &Path) -> Result<(ArchiveManifest, UserData), UserDataError> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let mut read_entry = |name: &str| -> Result<String, UserDataError> {
+        let mut content = String::new();
+        zip.by_name(name)?.read_to_string(&mut content)?;
+        Ok(content)
+    };
+
+    let manifest: ArchiveManifest = serde_json::from_str(&read_entry(MANIFEST_ENTRY)?)?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(UserDataError::UnsupportedVersion(manifest.version));
+    }
+    let data = serde_json::from_str(&read_entry(DATA_ENTRY)?)?;
+    Ok((manifest, data))
+}
+
+pub fn import_archive(db: &ConfigDatabase, path: &Path, mode: ImportMode, dry_run: bool) -> Result<ImportReport, UserDataError> {
+    let (manifest, data) = read_archive(path)?;
+    import_data(db, &data, mode, dry_run, manifest.exported_at)
+}
+
+/// Apply `data` to the database. A replace clears existing user data and then
+/// imports into the empty tables, so both modes share the matching below.
+/// Everything is written in one transaction, so a failure leaves the database as it was.
+pub fn import_data(
+    db: &ConfigDatabase,
+    data: &UserData,
+    mode: ImportMode,
+    dry_run: bool,
+    exported_at: String,
+) -> Result<ImportReport, UserDataError> {
+    if dry_run {
+        return db.transaction(|tx| apply(tx, data, mode, true, exported_at, None));
+    }
+    // The backup is taken first, as VACUUM INTO can't run inside a transaction
+    let backup_path = match mode {
+        ImportMode::Replace => Some(db.backup()?.to_string_lossy().into_owned()),
+        ImportMode::Merge => None,
+    };
+    db.transaction(|tx| apply(tx, data, mode, false, exported_at, backup_path))
+}
+
+fn apply(
+    conn: &Connection,
+    data: &UserData,
+    mode: ImportMode,
+    dry_run: bool,
+    exported_at: String,
+    backup_path: Option<String>,
+) -> Result<ImportReport, UserDataError> {
+    let mut report = ImportReport {
+        mode,
+        dry_run,
+        exported_at,
+        backup_path,
+        settings: ChangeCounts::default(),
+        waypoints: ChangeCounts::default(),
+        waypoint_groups: ChangeCounts::default(),
+        route_tags: ChangeCounts::default(),
+        routes: ChangeCounts::default(),
+        tracks: ChangeCounts::default(),
+        gps_sources: ChangeCounts::default(),
+        chart_layers: ChangeCounts::default(),
+        chart_metadata: ChangeCounts::default(),
+        catalogs: ChangeCounts::default(),
+    };
+
+    let current = UserData::load(conn)?;
+    let existing = match mode {
+        ImportMode::Merge => current,
+        ImportMode::Replace => {
+            report.settings.removed = current.settings.len();
+            report.waypoints.removed = current.waypoints.len();
+            report.waypoint_groups.removed = current.waypoint_groups.len();
+            report.route_tags.removed = current.route_tags.len();
+            report.routes.removed = current.routes.len();
+            report.tracks.removed = current.tracks.len();
+            report.gps_sources.removed = current.gps_sources.len();
+            report.chart_layers.removed = current.chart_layers.len();
+            report.chart_metadata.removed = current.chart_metadata.len();
+            report.catalogs.removed = current.catalogs.len();
+            if !dry_run {
+                database::clear_user_data(conn)?;
+            }
+            UserData::default()
+        }
+    };
+    let write = !dry_run;
+
+    // Archives made before device-local settings were left out may still carry them
+    let settings = data.settings.iter().filter(|(key, _)| !DEVICE_LOCAL_SETTINGS.contains(&key.as_str()));
+    for (key, value) in settings {
+        let change = match existing.settings.get(key) {
+            Some(current) if current == value => Change::Unchanged,
+            found => {
+                if write {
+                    database::set_setting(conn, key, value)?;
+                }
+                if found.is_some() { Change::Updated } else { Change::Added }
+            }
+        };
+        report.settings.record(change);
+    }
+
+    // Groups match on their path of names; parents are stored first so children
+    // can refer to them. Archive ids are mapped to the ids on this device.
+    let existing_groups: HashMap<Vec<String>, &WaypointGroup> =
+        existing.waypoint_groups.iter().map(|g| (group_path(&existing.waypoint_groups, g), g)).collect();
+    let mut imported_groups: Vec<_> =
+        data.waypoint_groups.iter().map(|g| (group_path(&data.waypoint_groups, g), g)).collect();
+    imported_groups.sort_by_key(|(path, _)| path.len());
+    let mut group_ids: HashMap<i64, i64> = HashMap::new();
+    for (path, group) in imported_groups {
+        let (id, change) = match existing_groups.get(&path) {
+            Some(current) if current.hidden == group.hidden => (current.id, Change::Unchanged),
+            Some(current) => {
+                if write {
+                    database::toggle_waypoint_group_hidden(conn, current.id.unwrap_or_default(), group.hidden)?;
+                }
+                (current.id, Change::Updated)
+            }
+            None => {
+                let parent_id = group.parent_id.and_then(|id| group_ids.get(&id).copied());
+                let id = if write { Some(database::create_waypoint_group(conn, &WaypointGroup { id: None, parent_id, ..group.clone() })?) } else { None };
+                (id, Change::Added)
+            }
+        };
+        if let (Some(archived), Some(id)) = (group.id, id) {
+            group_ids.insert(archived, id);
+        }
+        report.waypoint_groups.record(change);
+    }
+
+    // Waypoints match on uuid, falling back to name and position for archives
+    // made before uuids existed. Route waypoints missing from the waypoint list
+    // are added too, but only the list itself is counted.
+    let existing_waypoints = index_by(&existing.waypoints, |w| w.uuid.as_deref(), waypoint_key);
+    let mut waypoint_ids: HashMap<String, Option<i64>> = HashMap::new();
+    let route_waypoints = data.routes.iter().flat_map(|r| r.waypoints.iter());
+    for (index, waypoint) in data.waypoints.iter().chain(route_waypoints).enumerate() {
+        let key = waypoint_ref(waypoint);
+        if waypoint_ids.contains_key(&key) {
+            continue;
+        }
+        let current = lookup(&existing_waypoints, waypoint.uuid.as_deref(), &waypoint_key(waypoint));
+        // Waypoints the archive has no group for stay where they are
+        let group_id = waypoint.group_id.and_then(|id| group_ids.get(&id).copied());
+        let regroup = |current: &Waypoint| group_id.is_some() && current.group_id != group_id;
+        let (id, change) = match current {
+            Some(current) if same_waypoint(current, waypoint) && !regroup(current) => (current.id, Change::Unchanged),
+            Some(current) => {
+                if write {
+                    database::update_waypoint(conn, &Waypoint { id: current.id, ..waypoint.clone() })?;
+                    if regroup(current) {
+                        database::move_waypoints_to_group(conn, &[current.id.unwrap_or_default()], group_id)?;
+                    }
+                }
+                (current.id, Change::Updated)
+            }
+            None => {
+                let id = if write { Some(database::create_waypoint(conn, &Waypoint { group_id, ..waypoint.clone() })?) } else { None };
+                (id, Change::Added)
+            }
+        };
+        if index < data.waypoints.len() {
+            report.waypoints.record(change);
+        }
+        waypoint_ids.insert(key, id);
+    }
+
+    // Tags match on uuid, then name
+    let existing_tags = index_by(&existing.route_tags, |t| t.uuid.as_deref(), |t| t.name.clone());
+    let mut tag_ids: HashMap<String, Option<i64>> = HashMap::new();
+    let route_tags = data.routes.iter().flat_map(|r| r.tags.iter());
+    for (index, tag) in data.route_tags.iter().chain(route_tags).enumerate() {
+        if tag_ids.contains_key(&tag.name) {
+            continue;
+        }
+        let (id, change) = match lookup(&existing_tags, tag.uuid.as_deref(), &tag.name) {
+            Some(current) if current.name == tag.name && current.color == tag.color => (current.id, Change::Unchanged),
+            Some(current) => {
+                if write {
+                    database::update_route_tag(conn, &RouteTag { id: current.id, ..tag.clone() })?;
+                }
+                (current.id, Change::Updated)
+            }
+            None => {
+                let id = if write { Some(database::create_route_tag(conn, tag)?) } else { None };
+                (id, Change::Added)
+            }
+        };
+        if index < data.route_tags.len() {
+            report.route_tags.record(change);
+        }
+        tag_ids.insert(tag.name.clone(), id);
+    }
+
+    // Routes match on uuid, then name and the positions of their waypoints
+    let existing_routes = index_by(&existing.routes, |r| r.route.uuid.as_deref(), route_key);
+    for route in &data.routes {
+        let ids: Vec<i64> = route.waypoints.iter().filter_map(|w| waypoint_ids[&waypoint_ref(w)]).collect();
+        let tags: Vec<i64> = route.tags.iter().filter_map(|t| tag_ids[&t.name]).collect();
+        let change = match lookup(&existing_routes, route.route.uuid.as_deref(), &route_key(route)) {
+            Some(current) if same_route(current, route) => Change::Unchanged,
+            Some(current) => {
+                if write {
+                    // Keep whichever route is being navigated on this device
+                    let updated = Route { id: current.route.id, is_active: current.route.is_active, ..route.route.clone() };
+                    database::update_route(conn, &updated, &ids, &tags)?;
+                }
+                Change::Updated
+            }
+            None => {
+                if write {
+                    let is_active = mode == ImportMode::Replace && route.route.is_active;
+                    database::create_route(conn, &Route { id: None, is_active, ..route.route.clone() }, &ids, &tags)?;
+                }
+                Change::Added
+            }
+        };
+        report.routes.record(change);
+    }
+
+    // Tracks match on uuid, then name, start time and length
+    let existing_tracks = index_by(&existing.tracks, |t| t.track.uuid.as_deref(), track_key);
+    for track in &data.tracks {
+        let change = match lookup(&existing_tracks, track.track.uuid.as_deref(), &track_key(track)) {
+            Some(current)
+                if current.track.name == track.track.name
+                    && current.track.description == track.track.description
+                    && current.track.color == track.track.color
+                    && current.track.hidden == track.track.hidden =>
+            {
+                Change::Unchanged
+            }
+            Some(current) => {
+                if write {
+                    database::update_track(conn, &crate::database::Track { id: current.track.id, ..track.track.clone() })?;
+                }
+                Change::Updated
+            }
+            None => {
+                if write {
+                    database::import_track(conn, &track.track, &track.points)?;
+                }
+                Change::Added
+            }
+        };
+        report.tracks.record(change);
+    }
+
+    for source in &data.gps_sources {
+        let current = existing.gps_sources.iter().find(|s| s.id == source.id);
+        let change = upsert(current, source, write, |s| database::save_gps_source(conn, s))?;
+        report.gps_sources.record(change);
+    }
+
+    for layer in &data.chart_layers {
+        let current = existing.chart_layers.iter().find(|l| l.chart_id == layer.chart_id);
+        let change = upsert(current, layer, write, |l| database::save_chart_layer_state(conn, l))?;
+        report.chart_layers.record(change);
+    }
+
+    for metadata in &data.chart_metadata {
+        let current = existing.chart_metadata.iter().find(|m| m.chart_id == metadata.chart_id);
+        let change = upsert(current, metadata, write, |m| database::save_chart_custom_metadata(conn, m))?;
+        report.chart_metadata.record(change);
+    }
+
+    // Catalogs match on their source; charts missing from a known catalog are
+    // added. Download state stays behind, as the chart files are not archived.
+    for entry in &data.catalogs {
+        let current = existing.catalogs.iter().find(|c| c.catalog.source_path == entry.catalog.source_path);
+        let missing: Vec<&CatalogChart> = entry
+            .charts
+            .iter()
+            .filter(|chart| current.is_none_or(|c| c.charts.iter().all(|known| known.chart_id != chart.chart_id)))
+            .collect();
+        let change = match current {
+            Some(_) if missing.is_empty() => Change::Unchanged,
+            Some(_) => Change::Updated,
+            None => Change::Added,
+        };
+        if write && !matches!(change, Change::Unchanged) {
+            let catalog_id = match current.and_then(|c| c.catalog.id) {
+                Some(id) => id,
+                None => database::create_catalog(conn, &entry.catalog)?,
+            };
+            for chart in missing {
+                database::create_catalog_chart(conn, &CatalogChart { id: None, catalog_id, ..chart.clone() })?;
+            }
+        }
+        report.catalogs.record(change);
+    }
+
+    Ok(report)
+}
+
+fn upsert<T: Serialize>(
+    current: Option<&T>,
+    imported: &T,
+    write: bool,
+    save: impl FnOnce(&T) -> SqliteResult<()>,
+) -> SqliteResult<Change> {
+    let change = match current {
+        Some(current) if same(current, imported) => return Ok(Change::Unchanged),
+        Some(_) => Change::Updated,
+        None => Change::Added,
+    };
+    if write {
+        save(imported)?;
+    }
+    Ok(change)
+}
+
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Indexes records by uuid and by content key so older archives still match
+fn index_by<T>(items: &[T], uuid: impl Fn(&T) -> Option<&str>, key: impl Fn(&T) -> String) -> HashMap<String, &T> {
+    let mut index = HashMap::new();
+    for item in items {
+        if let Some(uuid) = uuid(item) {
+            index.insert(uuid.to_string(), item);
+        }
+        index.entry(key(item)).or_insert(item);
+    }
+    index
+}
+
+fn lookup<'a, T>(index: &HashMap<String, &'a T>, uuid: Option<&str>, key: &str) -> Option<&'a T> {
+    uuid.and_then(|uuid| index.get(uuid)).or_else(|| index.get(key)).copied()
+}
+
+fn waypoint_key(waypoint: &Waypoint) -> String {
+    format!("{}|{:.6}|{:.6}", waypoint.name, waypoint.lat, waypoint.lon)
+}
+
+/// Identifies a waypoint within one import, by uuid when it has one
+fn waypoint_ref(waypoint: &Waypoint) -> String {
+    waypoint.uuid.clone().unwrap_or_else(|| waypoint_key(waypoint))
+}
+
+fn same_waypoint(a: &Waypoint, b: &Waypoint) -> bool {
+    waypoint_key(a) == waypoint_key(b)
+        && a.description == b.description && a.symbol == b.symbol && a.show_label == b.show_label && a.hidden == b.hidden
+        && a.arrival_radius_nm == b.arrival_radius_nm && a.depth_m == b.depth_m && a.temperature_c == b.temperature_c
+}
+
+/// Names from the top-level group down to `group`, which identify it on any device
+fn group_path(groups: &[WaypointGroup], group: &WaypointGroup) -> Vec<String> {
+    let mut path = vec![group.name.clone()];
+    let mut parent_id = group.parent_id;
+    while let Some(parent) = parent_id.and_then(|id| groups.iter().find(|g| g.id == Some(id))) {
+        if path.len() > groups.len() {
+            break;
+        }
+        path.push(parent.name.clone());
+        parent_id = parent.parent_id;
+    }
+    path.reverse();
+    path
+}
+
+fn route_key(route: &RouteWithWaypoints) -> String {
+    let points: Vec<String> = route.waypoints.iter().map(|w| format!("{:.6},{:.6}", w.lat, w.lon)).collect();
+    format!("{}|{}", route.route.name, points.join(";"))
+}
+
+fn same_route(a: &RouteWithWaypoints, b: &RouteWithWaypoints) -> bool {
+    route_key(a) == route_key(b)
+        && a.route.description == b.route.description
+        && a.route.color == b.route.color
+        && a.route.hidden == b.route.hidden
+        && a.route.estimated_speed_kn == b.route.estimated_speed_kn
+        && waypoint_names(a) == waypoint_names(b)
+        && tag_names(a) == tag_names(b)
+}
+
+fn waypoint_names(route: &RouteWithWaypoints) -> Vec<&str> {
+    route.waypoints.iter().map(|w| w.name.as_str()).collect()
+}
+
+fn tag_names(route: &RouteWithWaypoints) -> Vec<&str> {
+    let mut names: Vec<&str> = route.tags.iter().map(|t| t.name.as_str()).collect();
+    names.sort_unstable();
+    names
+}
+
+fn track_key(track: &TrackWithPoints) -> String {
+    format!("{}|{}|{}", track.track.name, track.track.started_at.as_deref().unwrap_or(""), track.points.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Track;
+    use std::path::PathBuf;
+
+    fn temp_db(name: &str) -> (PathBuf, ConfigDatabase) {
+        let dir = std::env::temp_dir().join(format!("vortexnav_user_data_{}_{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let db = ConfigDatabase::new(&dir).unwrap();
+        (dir, db)
+    }
+
+    fn populate(db: &ConfigDatabase) {
+        db.set_setting("theme", "night").unwrap();
+        let gulf = db.create_waypoint_group(&WaypointGroup { id: None, name: "Gulf".to_string(), parent_id: None, hidden: false, created_at: None }).unwrap();
+        let marks = db.create_waypoint_group(&WaypointGroup { id: None, name: "Marks".to_string(), parent_id: Some(gulf), hidden: true, created_at: None }).unwrap();
+        let a = db.create_waypoint(&Waypoint { group_id: Some(marks), ..Waypoint::at("Bean Rock", -36.83, 174.83) }).unwrap();
+        let b = db.create_waypoint(&Waypoint::at("Rangitoto Light", -36.79, 174.83)).unwrap();
+        let tag = db.get_route_tags().unwrap().into_iter().find(|t| t.name == "Coastal").unwrap();
+        let route = Route { name: "Harbour run".to_string(), ..Route::default() };
+        db.create_route(&route, &[a, b], &[tag.id.unwrap()]).unwrap();
+
+        let track = Track { name: "Sunday".to_string(), started_at: Some("2026-03-01T01:00:00Z".to_string()), ..Track::default() };
+        let points: Vec<_> = (0..3)
+            .map(|i| crate::database::TrackPoint {
+                id: None,
+                track_id: 0,
+                lat: -36.8 + i as f64 * 0.01,
+                lon: 174.8,
+                timestamp: Some(format!("2026-03-01T01:0{}:00Z", i)),
+                sequence: i,
+                heading: None,
+                cog: Some(10.0),
+                sog: Some(5.5),
+                segment: 0,
+                depth_m: None,
+                temperature_c: None,
+            })
+            .collect();
+        db.import_track(&track, &points).unwrap();
+
+        db.save_gps_source(&GpsSourceRecord {
+            id: "gps-1".to_string(),
+            name: "Masthead".to_string(),
+            source_type: "serial_port".to_string(),
+            port_name: Some("/dev/ttyUSB0".to_string()),
+            baud_rate: 4800,
+            enabled: true,
+            priority: 0,
+            raw_nmea: false,
+        })
+        .unwrap();
+        db.save_chart_custom_metadata(&ChartCustomMetadata {
+            chart_id: "NZ532".to_string(),
+            custom_name: Some("Hauraki Gulf".to_string()),
+            custom_description: None,
+            custom_min_zoom: None,
+            custom_max_zoom: Some(16),
+        })
+        .unwrap();
+        // Saving layer state keeps the custom metadata
+        db.save_chart_layer_state(&ChartLayerState { chart_id: "NZ532".to_string(), enabled: false, opacity: 0.7, z_order: 2 })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_export_and_merge() {
+        let (source_dir, source) = temp_db("merge_source");
+        populate(&source);
+        let archive = source_dir.join("backup.zip");
+        let manifest = export_archive(&source, &archive).unwrap();
+        assert_eq!((manifest.waypoints, manifest.routes, manifest.tracks), (2, 1, 1));
+
+        let (target_dir, target) = temp_db("merge_target");
+        target.create_waypoint(&Waypoint::at("Home berth", -36.84, 174.75)).unwrap();
+
+        // A dry run reports without writing
+        let report = import_archive(&target, &archive, ImportMode::Merge, true).unwrap();
+        assert_eq!(report.waypoints.added, 2);
+        assert_eq!(target.get_waypoints().unwrap().len(), 1);
+
+        let report = import_archive(&target, &archive, ImportMode::Merge, false).unwrap();
+        assert_eq!((report.waypoints.added, report.routes.added, report.tracks.added), (2, 1, 1));
+        assert_eq!(report.route_tags.unchanged, 7);
+        assert_eq!(report.settings.added, 1);
+        assert_eq!(target.get_waypoints().unwrap().len(), 3);
+        let routes = target.get_routes().unwrap();
+        assert_eq!(routes[0].waypoints.len(), 2);
+        assert_eq!(routes[0].tags[0].name, "Coastal");
+        // Groups arrive nested as they were, under new ids
+        let groups = target.get_waypoint_groups().unwrap();
+        let marks = groups.iter().find(|g| g.name == "Marks").unwrap();
+        assert!(marks.hidden && marks.parent_id == groups.iter().find(|g| g.name == "Gulf").unwrap().id);
+        assert_eq!(routes[0].waypoints[0].group_id, marks.id);
+        let tracks = target.get_tracks_with_points().unwrap();
+        assert_eq!((tracks[0].points.len(), tracks[0].points[2].sog), (3, Some(5.5)));
+        let metadata = target.get_chart_custom_metadata("NZ532").unwrap().unwrap();
+        assert_eq!(metadata.custom_name.as_deref(), Some("Hauraki Gulf"));
+        assert!(!target.get_chart_layer_state("NZ532").unwrap().unwrap().enabled);
+
+        // Importing again finds everything already present
+        let report = import_archive(&target, &archive, ImportMode::Merge, false).unwrap();
+        assert_eq!(report.waypoints.unchanged, 2);
+        assert_eq!(report.waypoint_groups.unchanged, 2);
+        assert_eq!(report.routes.unchanged, 1);
+        assert_eq!(report.tracks.unchanged, 1);
+        assert_eq!(report.gps_sources.unchanged, 1);
+        assert_eq!(report.waypoints.added + report.routes.added + report.tracks.added, 0);
+        assert_eq!(target.get_waypoints().unwrap().len(), 3);
+
+        std::fs::remove_dir_all(source_dir).ok();
+        std::fs::remove_dir_all(target_dir).ok();
+    }
+
+    #[test]
+    fn test_replace() {
+        let (source_dir, source) = temp_db("replace_source");
+        populate(&source);
+        let archive = source_dir.join("backup.zip");
+        export_archive(&source, &archive).unwrap();
+
+        let (target_dir, target) = temp_db("replace_target");
+        target.create_waypoint(&Waypoint::at("Home berth", -36.84, 174.75)).unwrap();
+
+        let report = import_archive(&target, &archive, ImportMode::Replace, true).unwrap();
+        assert_eq!((report.waypoints.removed, report.waypoints.added), (1, 2));
+        assert!(report.backup_path.is_none());
+        assert_eq!(target.get_waypoints().unwrap()[0].name, "Home berth");
+
+        let report = import_archive(&target, &archive, ImportMode::Replace, false).unwrap();
+        assert!(Path::new(report.backup_path.as_deref().unwrap()).exists());
+        let names: Vec<String> = target.get_waypoints().unwrap().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, ["Bean Rock", "Rangitoto Light"]);
+        assert_eq!(target.get_setting("theme").unwrap().as_deref(), Some("night"));
+        assert_eq!(target.get_route_tags().unwrap().len(), 7);
+
+        std::fs::remove_dir_all(source_dir).ok();
+        std::fs::remove_dir_all(target_dir).ok();
+    }
+
+    #[test]
+    fn test_device_settings_stay_and_failed_replace_rolls_back() {
+        let (source_dir, source) = temp_db("device_source");
+        populate(&source);
+        source.set_setting("sync_device_name", "Nav station").unwrap();
+        let mut data = source.transaction(|tx| UserData::load(tx)).unwrap();
+        assert!(!data.settings.contains_key("sync_device_name"));
+
+        let (target_dir, target) = temp_db("device_target");
+        target.create_waypoint(&Waypoint::at("Home berth", -36.84, 174.75)).unwrap();
+        target.set_setting("sync_device_name", "Tablet").unwrap();
+
+        // Older archives may still carry device settings; they are skipped
+        data.settings.insert("sync_device_name".to_string(), "Nav station".to_string());
+        import_data(&target, &data, ImportMode::Replace, false, String::new()).unwrap();
+        assert_eq!(target.get_setting("sync_device_name").unwrap().as_deref(), Some("Tablet"));
+
+        // A second track with the same uuid fails partway, leaving everything as it was
+        let mut duplicate = data.tracks[0].clone();
+        duplicate.track.name = "Copy".to_string();
+        data.tracks.push(duplicate);
+        target.create_waypoint(&Waypoint::at("Home berth", -36.84, 174.75)).unwrap();
+        assert!(import_data(&target, &data, ImportMode::Replace, false, String::new()).is_err());
+        assert_eq!(target.get_waypoints().unwrap().len(), 3);
+        assert_eq!(target.get_tracks_with_points().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(source_dir).ok();
+        std::fs::remove_dir_all(target_dir).ok();
+    }
+}
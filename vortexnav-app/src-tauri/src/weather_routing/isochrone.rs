@@ -0,0 +1,367 @@
+// Isochrone Router
+// Expands reachable positions one time step at a time and keeps the
+// furthest point per bearing sector until the destination is reached
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{LandMask, Polar, RoutedPoint, RoutingError, WeatherRoutingProgress};
+use crate::grib::GribDataset;
+use crate::navigation::{angle_difference, calculate_bearing, destination_point, haversine_distance, time_after};
+
+/// Finest heading step the router will search (degrees)
+const MIN_HEADING_STEP_DEG: f64 = 1.0;
+
+/// Shortest time step the router will take (hours)
+const MIN_TIME_STEP_HOURS: f64 = 5.0 / 60.0;
+
+/// Most isochrones in one run, whatever the forecast length
+const MAX_STEPS: f64 = 2000.0;
+
+/// Parameters for one isochrone run. Steps finer than the minimums are widened.
+#[derive(Debug, Clone)]
+pub struct RoutingOptions {
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    pub departure: DateTime<Utc>,
+    pub time_step_hours: f64,
+    pub heading_step_deg: f64,
+    pub max_hours: f64,
+    pub use_currents: bool,
+}
+
+/// Boat performance on one heading at one place and time
+#[derive(Debug, Clone, Copy)]
+struct Performance {
+    heading: f64,
+    boat_speed: f64,
+    sog: f64,
+    cog: f64,
+    tws: f64,
+    twd: f64,
+    twa: f64,
+    current: Option<(f64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    lat: f64,
+    lon: f64,
+    hours: f64,
+    parent: Option<usize>,
+    /// Conditions on the leg that arrived at this node
+    arrival: Option<Performance>,
+}
+
+fn performance(
+    dataset: &GribDataset,
+    polar: &Polar,
+    lat: f64,
+    lon: f64,
+    time: DateTime<Utc>,
+    heading: f64,
+    use_currents: bool,
+) -> Option<Performance> {
+    let wind = dataset.wind_at(lat, lon, time)?;
+    let twa = angle_difference(heading, wind.direction).abs();
+    let boat_speed = polar.boat_speed(twa, wind.speed_kn)?;
+
+    let mut vx = boat_speed * heading.to_radians().sin();
+    let mut vy = boat_speed * heading.to_radians().cos();
+    let current = if use_currents { dataset.current_at(lat, lon, time) } else { None };
+    if let Some(c) = current {
+        vx += c.speed_kn * c.direction.to_radians().sin();
+        vy += c.speed_kn * c.direction.to_radians().cos();
+    }
+
+    let sog = (vx * vx + vy * vy).sqrt();
+    if sog < 0.05 {
+        return None;
+    }
+
+    Some(Performance {
+        heading,
+        boat_speed,
+        sog,
+        cog: (vx.atan2(vy).to_degrees() + 360.0) % 360.0,
+        tws: wind.speed_kn,
+        twd: wind.direction,
+        twa,
+        current: current.map(|c| (c.speed_kn, c.direction)),
+    })
+}
+
+/// Run the isochrone router. Returns the route from start to destination,
+/// one point per time step plus the arrival.
+pub fn run_isochrones(
+    dataset: &GribDataset,
+    polar: &Polar,
+    land: Option<&LandMask>,
+    options: &RoutingOptions,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(WeatherRoutingProgress),
+) -> Result<Vec<RoutedPoint>, RoutingError> {
+    let (start_lat, start_lon) = options.start;
+    let (end_lat, end_lon) = options.end;
+    let dt = options.time_step_hours.max(MIN_TIME_STEP_HOURS);
+    let heading_step = options.heading_step_deg.clamp(MIN_HEADING_STEP_DEG, 90.0);
+    let max_steps = (options.max_hours / dt).ceil().clamp(1.0, MAX_STEPS) as usize;
+    let total_distance = haversine_distance(start_lat, start_lon, end_lat, end_lon);
+    let crosses_land = |a: &Node, lat: f64, lon: f64| {
+        land.is_some_and(|l| l.leg_crosses_land(a.lat, a.lon, lat, lon))
+    };
+
+    if dataset.wind_at(start_lat, start_lon, options.departure).is_none() {
+        return Err(RoutingError::NoWeather(
+            "GRIB wind does not cover the start position at the departure time".to_string(),
+        ));
+    }
+    if land.is_some_and(|l| l.contains(end_lat, end_lon)) {
+        return Err(RoutingError::InvalidRequest("Destination is on land".to_string()));
+    }
+
+    let mut nodes = vec![Node { lat: start_lat, lon: start_lon, hours: 0.0, parent: None, arrival: None }];
+    let mut frontier = vec![0usize];
+    let headings = (360.0 / heading_step).round() as usize;
+
+    for step in 0..max_steps {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(RoutingError::Cancelled);
+        }
+
+        let hours = step as f64 * dt;
+        let time = time_after(options.departure, hours);
+
+        // Can any node on the current isochrone finish within this step?
+        let mut finish: Option<(usize, f64, Performance)> = None;
+        for &id in &frontier {
+            let node = &nodes[id];
+            let distance = haversine_distance(node.lat, node.lon, end_lat, end_lon);
+            let heading = calculate_bearing(node.lat, node.lon, end_lat, end_lon);
+            let Some(perf) = performance(dataset, polar, node.lat, node.lon, time, heading, options.use_currents)
+            else {
+                continue;
+            };
+            if perf.sog * dt >= distance && !crosses_land(node, end_lat, end_lon) {
+                let arrival = hours + distance / perf.sog;
+                if finish.is_none_or(|(_, best, _)| arrival < best) {
+                    finish = Some((id, arrival, perf));
+                }
+            }
+        }
+
+        if let Some((id, arrival, perf)) = finish {
+            nodes.push(Node { lat: end_lat, lon: end_lon, hours: arrival, parent: Some(id), arrival: Some(perf) });
+            progress(WeatherRoutingProgress {
+                phase: "complete".to_string(),
+                current: max_steps,
+                total: max_steps,
+                elapsed_hours: arrival,
+                distance_to_go_nm: 0.0,
+            });
+            return Ok(build_path(&nodes, nodes.len() - 1, options.departure));
+        }
+
+        // Expand every node on every heading, keeping the furthest point per sector
+        let mut sectors: HashMap<i64, (f64, Node)> = HashMap::new();
+        for &id in &frontier {
+            let node = &nodes[id];
+            for k in 0..headings {
+                if cancel.load(Ordering::SeqCst) {
+                    return Err(RoutingError::Cancelled);
+                }
+                let heading = k as f64 * 360.0 / headings as f64;
+                let Some(perf) = performance(dataset, polar, node.lat, node.lon, time, heading, options.use_currents)
+                else {
+                    continue;
+                };
+
+                let (lat, lon) = destination_point(node.lat, node.lon, perf.cog, perf.sog * dt);
+                // Discard positions wandering far off the rhumb of the passage
+                if haversine_distance(lat, lon, end_lat, end_lon) > total_distance * 1.25 + 5.0 {
+                    continue;
+                }
+                if crosses_land(node, lat, lon) {
+                    continue;
+                }
+
+                let from_start = haversine_distance(start_lat, start_lon, lat, lon);
+                let sector = (calculate_bearing(start_lat, start_lon, lat, lon) / heading_step).floor() as i64;
+                let candidate = Node { lat, lon, hours: hours + dt, parent: Some(id), arrival: Some(perf) };
+                match sectors.get(&sector) {
+                    Some((best, _)) if *best >= from_start => {}
+                    _ => {
+                        sectors.insert(sector, (from_start, candidate));
+                    }
+                }
+            }
+        }
+
+        if sectors.is_empty() {
+            return Err(RoutingError::NoRoute(format!(
+                "No progress possible after {:.1} hours (outside forecast or blocked by land)",
+                hours
+            )));
+        }
+
+        let mut keys: Vec<i64> = sectors.keys().copied().collect();
+        keys.sort_unstable();
+        frontier.clear();
+        let mut distance_to_go = f64::MAX;
+        for key in keys {
+            let (_, node) = sectors.remove(&key).unwrap();
+            distance_to_go = distance_to_go.min(haversine_distance(node.lat, node.lon, end_lat, end_lon));
+            frontier.push(nodes.len());
+            nodes.push(node);
+        }
+
+        progress(WeatherRoutingProgress {
+            phase: "routing".to_string(),
+            current: step + 1,
+            total: max_steps,
+            elapsed_hours: hours + dt,
+            distance_to_go_nm: distance_to_go,
+        });
+    }
+
+    Err(RoutingError::NoRoute(format!(
+        "Destination not reached within {:.0} hours",
+        options.max_hours
+    )))
+}
+
+/// Walk back from the arrival node and describe each leg
+fn build_path(nodes: &[Node], last: usize, departure: DateTime<Utc>) -> Vec<RoutedPoint> {
+    let mut chain = vec![last];
+    let mut current = last;
+    while let Some(parent) = nodes[current].parent {
+        chain.push(parent);
+        current = parent;
+    }
+    chain.reverse();
+
+    chain
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let node = &nodes[*id];
+            // Conditions on the leg leaving this point; the arrival keeps the final approach
+            let perf = chain
+                .get(i + 1)
+                .and_then(|next| nodes[*next].arrival)
+                .or(node.arrival);
+            RoutedPoint {
+                lat: node.lat,
+                lon: node.lon,
+                eta: time_after(departure, node.hours).to_rfc3339(),
+                hours_from_start: node.hours,
+                heading: perf.map(|p| p.heading),
+                boat_speed_kn: perf.map(|p| p.boat_speed),
+                sog_kn: perf.map(|p| p.sog),
+                cog: perf.map(|p| p.cog),
+                tws_kn: perf.map(|p| p.tws),
+                twd: perf.map(|p| p.twd),
+                twa: perf.map(|p| p.twa),
+                current_speed_kn: perf.and_then(|p| p.current.map(|c| c.0)),
+                current_set: perf.and_then(|p| p.current.map(|c| c.1)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grib::{GribField, GribGrid, GribParameter};
+    use chrono::TimeZone;
+
+    fn steady_wind(u: f32, v: f32) -> GribDataset {
+        let grid = GribGrid { ni: 11, nj: 11, lat1: 5.0, lon1: -5.0, dlat: -1.0, dlon: 1.0 };
+        let field = |parameter, hour, value: f32| GribField {
+            parameter,
+            reference_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            valid_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + chrono::Duration::hours(hour),
+            grid: grid.clone(),
+            values: vec![value; grid.len()],
+        };
+        GribDataset {
+            source_path: None,
+            fields: vec![
+                field(GribParameter::WindU, 0, u),
+                field(GribParameter::WindV, 0, v),
+                field(GribParameter::WindU, 72, u),
+                field(GribParameter::WindV, 72, v),
+            ],
+        }
+    }
+
+    fn options(end: (f64, f64)) -> RoutingOptions {
+        RoutingOptions {
+            start: (0.0, 0.0),
+            end,
+            departure: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            time_step_hours: 1.0,
+            heading_step_deg: 5.0,
+            max_hours: 72.0,
+            use_currents: false,
+        }
+    }
+
+    fn polar() -> Polar {
+        Polar::parse("Test", "TWA\\TWS\t10\t20\n40\t5\t6\n90\t6\t7\n180\t5\t6\n").unwrap()
+    }
+
+    #[test]
+    fn test_beam_reach_goes_straight() {
+        // 20 knot northerly (10.3 m/s from the north), destination due east
+        let dataset = steady_wind(0.0, -10.29);
+        let cancel = AtomicBool::new(false);
+        let path = run_isochrones(&dataset, &polar(), None, &options((0.0, 1.0)), &cancel, &mut |_| {}).unwrap();
+
+        let last = path.last().unwrap();
+        assert_eq!((last.lat, last.lon), (0.0, 1.0));
+        // 60nm at 7 knots on a beam reach
+        assert!((last.hours_from_start - 60.0 / 7.0).abs() < 0.5, "{}", last.hours_from_start);
+        assert!(path.iter().all(|p| p.lat.abs() < 0.1));
+    }
+
+    #[test]
+    fn test_upwind_tacks_and_avoids_land() {
+        let dataset = steady_wind(0.0, -10.29);
+        let cancel = AtomicBool::new(false);
+
+        // Destination dead upwind: must tack, so it takes longer than the straight distance
+        let path = run_isochrones(&dataset, &polar(), None, &options((0.5, 0.0)), &cancel, &mut |_| {}).unwrap();
+        assert!(path.last().unwrap().hours_from_start > 30.0 / 6.0);
+        assert!(path.iter().filter_map(|p| p.twa).all(|twa| twa >= 40.0 - 1e-6));
+
+        // A wall of land east of the start blocks the direct beam reach
+        let mut land = LandMask::default();
+        land.add_polygon(vec![vec![(0.4, -0.2), (0.5, -0.2), (0.5, 0.2), (0.4, 0.2), (0.4, -0.2)]]);
+        let path = run_isochrones(&dataset, &polar(), Some(&land), &options((0.0, 1.0)), &cancel, &mut |_| {}).unwrap();
+        for leg in path.windows(2) {
+            assert!(!land.leg_crosses_land(leg[0].lat, leg[0].lon, leg[1].lat, leg[1].lon));
+        }
+
+        cancel.store(true, Ordering::SeqCst);
+        let result = run_isochrones(&dataset, &polar(), None, &options((0.0, 1.0)), &cancel, &mut |_| {});
+        assert!(matches!(result, Err(RoutingError::Cancelled)));
+    }
+
+    #[test]
+    fn test_degenerate_steps_are_bounded() {
+        let dataset = steady_wind(0.0, -10.29);
+        let cancel = AtomicBool::new(false);
+        let tiny = RoutingOptions { time_step_hours: 1e-9, heading_step_deg: 1e-9, max_hours: 1e9, ..options((0.0, 1.0)) };
+
+        // Stop after the first isochrone, which reports the capped step count
+        let mut total = 0;
+        let result = run_isochrones(&dataset, &polar(), None, &tiny, &cancel, &mut |p| {
+            total = p.total;
+            cancel.store(true, Ordering::SeqCst);
+        });
+        assert!(matches!(result, Err(RoutingError::Cancelled)));
+        assert_eq!(total, MAX_STEPS as usize);
+    }
+}
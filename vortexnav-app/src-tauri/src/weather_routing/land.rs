@@ -0,0 +1,204 @@
+// Land Avoidance
+// Land polygons from CM93 land areas with a coarse grid index for crossing tests
+
+use std::collections::HashMap;
+
+use crate::cm93::GeoJsonTile;
+
+/// Grid cell size for the polygon index (degrees)
+const INDEX_CELL_DEG: f64 = 0.5;
+
+struct LandPolygon {
+    /// Rings as (lon, lat); the first is the outer boundary, the rest are holes
+    rings: Vec<Vec<(f64, f64)>>,
+    bbox: [f64; 4],
+}
+
+/// Land areas used to reject routing legs
+#[derive(Default)]
+pub struct LandMask {
+    polygons: Vec<LandPolygon>,
+    index: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl LandMask {
+    /// Build a land mask from the "land" layer of CM93 GeoJSON features
+    pub fn from_cm93_features(tile: &GeoJsonTile) -> Self {
+        let mut mask = Self::default();
+
+        for feature in tile.features.iter().filter(|f| f.properties.layer == "land") {
+            let coords = &feature.geometry.coordinates;
+            match feature.geometry.geom_type.as_str() {
+                "Polygon" => mask.add_polygon(parse_rings(coords)),
+                "MultiPolygon" => {
+                    for polygon in coords.as_array().into_iter().flatten() {
+                        mask.add_polygon(parse_rings(polygon));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        mask
+    }
+
+    /// Add a polygon given as (lon, lat) rings, outer ring first
+    pub fn add_polygon(&mut self, rings: Vec<Vec<(f64, f64)>>) {
+        let Some(outer) = rings.first() else { return };
+        if outer.len() < 3 {
+            return;
+        }
+
+        let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+        for (lon, lat) in outer {
+            bbox[0] = bbox[0].min(*lon);
+            bbox[1] = bbox[1].min(*lat);
+            bbox[2] = bbox[2].max(*lon);
+            bbox[3] = bbox[3].max(*lat);
+        }
+
+        let id = self.polygons.len();
+        for cell in cells_covering(bbox) {
+            self.index.entry(cell).or_default().push(id);
+        }
+        self.polygons.push(LandPolygon { rings, bbox });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// Whether a position is on land
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let cell = cell_of(lon, lat);
+        self.index.get(&cell).into_iter().flatten().any(|id| {
+            let p = &self.polygons[*id];
+            in_bbox(p.bbox, lon, lat) && point_in_polygon(&p.rings, lon, lat)
+        })
+    }
+
+    /// Whether a straight leg touches land: either end on land or crossing a coastline
+    pub fn leg_crosses_land(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> bool {
+        let bbox = [lon1.min(lon2), lat1.min(lat2), lon1.max(lon2), lat1.max(lat2)];
+        let mut checked = Vec::new();
+
+        for cell in cells_covering(bbox) {
+            for id in self.index.get(&cell).into_iter().flatten() {
+                if checked.contains(id) {
+                    continue;
+                }
+                checked.push(*id);
+
+                let p = &self.polygons[*id];
+                if !bboxes_overlap(p.bbox, bbox) {
+                    continue;
+                }
+                if point_in_polygon(&p.rings, lon2, lat2) || point_in_polygon(&p.rings, lon1, lat1) {
+                    return true;
+                }
+                for ring in &p.rings {
+                    for edge in ring.windows(2) {
+                        if segments_intersect((lon1, lat1), (lon2, lat2), edge[0], edge[1]) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
+
+fn parse_rings(value: &serde_json::Value) -> Vec<Vec<(f64, f64)>> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|ring| {
+            ring.as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|pt| Some((pt.get(0)?.as_f64()?, pt.get(1)?.as_f64()?)))
+                .collect()
+        })
+        .collect()
+}
+
+fn cell_of(lon: f64, lat: f64) -> (i32, i32) {
+    ((lon / INDEX_CELL_DEG).floor() as i32, (lat / INDEX_CELL_DEG).floor() as i32)
+}
+
+fn cells_covering(bbox: [f64; 4]) -> Vec<(i32, i32)> {
+    let (x0, y0) = cell_of(bbox[0], bbox[1]);
+    let (x1, y1) = cell_of(bbox[2], bbox[3]);
+    let mut cells = Vec::new();
+    for x in x0..=x1 {
+        for y in y0..=y1 {
+            cells.push((x, y));
+        }
+    }
+    cells
+}
+
+fn in_bbox(bbox: [f64; 4], lon: f64, lat: f64) -> bool {
+    lon >= bbox[0] && lon <= bbox[2] && lat >= bbox[1] && lat <= bbox[3]
+}
+
+fn bboxes_overlap(a: [f64; 4], b: [f64; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}
+
+/// Even-odd ray casting over all rings, so holes (lakes, lagoons) count as water
+fn point_in_polygon(rings: &[Vec<(f64, f64)>], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        let n = ring.len();
+        if n < 3 {
+            continue;
+        }
+        let mut j = n - 1;
+        for i in 0..n {
+            let (xi, yi) = ring[i];
+            let (xj, yj) = ring[j];
+            if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                inside = !inside;
+            }
+            j = i;
+        }
+    }
+    inside
+}
+
+fn segments_intersect(p1: (f64, f64), p2: (f64, f64), q1: (f64, f64), q2: (f64, f64)) -> bool {
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let d1 = cross(q1, q2, p1);
+    let d2 = cross(q1, q2, p2);
+    let d3 = cross(p1, p2, q1);
+    let d4 = cross(p1, p2, q2);
+    ((d1 > 0.0) != (d2 > 0.0)) && ((d3 > 0.0) != (d4 > 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_land_crossing() {
+        let mut mask = LandMask::default();
+        // An island from 1..2 E, 1..2 N with a lagoon in the middle
+        mask.add_polygon(vec![
+            vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (1.0, 1.0)],
+            vec![(1.4, 1.4), (1.6, 1.4), (1.6, 1.6), (1.4, 1.6), (1.4, 1.4)],
+        ]);
+
+        assert!(mask.contains(1.2, 1.2));
+        assert!(!mask.contains(1.5, 1.5));
+        assert!(!mask.contains(0.5, 0.5));
+
+        // West to east straight through the island
+        assert!(mask.leg_crosses_land(1.2, 0.5, 1.2, 2.5));
+        // Passing south of it
+        assert!(!mask.leg_crosses_land(0.8, 0.5, 0.8, 2.5));
+    }
+}
@@ -0,0 +1,139 @@
+// Weather Routing Module
+// Isochrone routing over GRIB wind (and currents) using boat polars,
+// avoiding CM93 land areas
+
+mod isochrone;
+mod land;
+mod polar;
+
+pub use isochrone::*;
+pub use land::*;
+pub use polar::*;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::database::{Route, Waypoint};
+use crate::navigation::haversine_distance;
+
+#[derive(Error, Debug)]
+pub enum RoutingError {
+    #[error("Invalid polar file: {0}")]
+    InvalidPolar(String),
+    #[error("Weather data unavailable: {0}")]
+    NoWeather(String),
+    #[error("No route found: {0}")]
+    NoRoute(String),
+    #[error("Invalid routing request: {0}")]
+    InvalidRequest(String),
+    #[error("Routing cancelled")]
+    Cancelled,
+}
+
+/// Weather routing request from the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherRoutingRequest {
+    pub start_lat: f64,
+    pub start_lon: f64,
+    pub end_lat: f64,
+    pub end_lon: f64,
+    /// RFC 3339 departure time
+    pub departure_time: String,
+    pub polar_id: i64,
+    pub name: Option<String>,
+    pub time_step_hours: Option<f64>,   // Default 1 hour
+    pub heading_step_deg: Option<f64>,  // Default 5 degrees
+    pub max_hours: Option<f64>,         // Default: end of the forecast
+    pub use_currents: Option<bool>,     // Default true when GRIB has currents
+}
+
+/// Progress update emitted while routing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherRoutingProgress {
+    pub phase: String,           // "preparing", "routing", "complete"
+    pub current: usize,          // Isochrones computed
+    pub total: usize,            // Maximum isochrones
+    pub elapsed_hours: f64,      // Passage time covered so far
+    pub distance_to_go_nm: f64,  // Closest approach to the destination so far
+}
+
+/// Expected conditions at one point of a routed passage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutedPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub eta: String,
+    pub hours_from_start: f64,
+    pub heading: Option<f64>,
+    pub boat_speed_kn: Option<f64>,
+    pub sog_kn: Option<f64>,
+    pub cog: Option<f64>,
+    pub tws_kn: Option<f64>,
+    pub twd: Option<f64>,
+    pub twa: Option<f64>,
+    pub current_speed_kn: Option<f64>,
+    pub current_set: Option<f64>,
+}
+
+/// Proposed route from a weather routing run (not yet saved)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherRoutingResult {
+    pub route: Route,
+    pub waypoints: Vec<Waypoint>,
+    /// Per-waypoint ETA and conditions, same order as `waypoints`
+    pub points: Vec<RoutedPoint>,
+    pub departure_time: String,
+    pub arrival_time: String,
+    pub duration_hours: f64,
+    /// Whether CM93 land areas were available to avoid land
+    pub land_checked: bool,
+}
+
+/// Turn routed points into an unsaved Route with one waypoint per point
+pub fn build_proposed_route(name: &str, points: Vec<RoutedPoint>, land_checked: bool) -> WeatherRoutingResult {
+    let total_distance_nm: f64 = points
+        .windows(2)
+        .map(|w| haversine_distance(w[0].lat, w[0].lon, w[1].lat, w[1].lon))
+        .sum();
+    let duration_hours = points.last().map(|p| p.hours_from_start).unwrap_or(0.0);
+
+    let waypoints = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| Waypoint {
+            id: None,
+            name: format!("{} {:03}", name, i + 1),
+            lat: p.lat,
+            lon: p.lon,
+            description: Some(format!("ETA {}", p.eta)),
+            symbol: None,
+            show_label: false,
+            hidden: false,
+            created_at: None,
+            uuid: None,
+            updated_at: None,
+            group_id: None,
+            arrival_radius_nm: None,
+            depth_m: None,
+            temperature_c: None,
+        })
+        .collect();
+
+    let route = Route {
+        name: name.to_string(),
+        description: Some(format!("Weather routed, {:.1} h passage", duration_hours)),
+        total_distance_nm: Some(total_distance_nm),
+        estimated_speed_kn: if duration_hours > 0.0 { total_distance_nm / duration_hours } else { 5.0 },
+        ..Default::default()
+    };
+
+    WeatherRoutingResult {
+        route,
+        waypoints,
+        departure_time: points.first().map(|p| p.eta.clone()).unwrap_or_default(),
+        arrival_time: points.last().map(|p| p.eta.clone()).unwrap_or_default(),
+        duration_hours,
+        points,
+        land_checked,
+    }
+}
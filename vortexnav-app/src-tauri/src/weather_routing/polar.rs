@@ -0,0 +1,190 @@
+// Boat Polar Diagrams
+// Parses TWA x TWS performance tables and interpolates boat speed
+
+use serde::{Deserialize, Serialize};
+
+use super::RoutingError;
+
+/// Boat speed table: rows are true wind angles, columns true wind speeds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Polar {
+    pub name: String,
+    /// True wind angles in degrees, ascending
+    pub twa: Vec<f64>,
+    /// True wind speeds in knots, ascending
+    pub tws: Vec<f64>,
+    /// Boat speed in knots, indexed [twa][tws]; None where the table has no value
+    pub speeds: Vec<Vec<Option<f64>>>,
+}
+
+impl Polar {
+    /// Parse the common polar text format:
+    ///
+    /// ```text
+    /// TWA\TWS  6    8    10   12
+    /// 52       5.1  6.0  6.6  6.9
+    /// 90       6.0  6.9  7.4  7.7
+    /// ```
+    ///
+    /// Columns may be separated by tabs, commas, semicolons or spaces.
+    /// Lines starting with `#` or `!` are comments; empty cells mean no data.
+    pub fn parse(name: &str, text: &str) -> Result<Self, RoutingError> {
+        let mut rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('!'));
+
+        let header = rows
+            .next()
+            .ok_or_else(|| RoutingError::InvalidPolar("File is empty".to_string()))?;
+        let delimiter = detect_delimiter(header);
+
+        let tws = split_row(header, delimiter)
+            .into_iter()
+            .skip(1)
+            .filter(|c| !c.is_empty())
+            .map(|c| parse_number(c, "wind speed"))
+            .collect::<Result<Vec<f64>, _>>()?;
+        if tws.is_empty() {
+            return Err(RoutingError::InvalidPolar("No wind speed columns in header".to_string()));
+        }
+
+        let mut twa = Vec::new();
+        let mut speeds = Vec::new();
+        for row in rows {
+            let cells = split_row(row, delimiter);
+            let angle = parse_number(cells[0], "wind angle")?;
+            let mut row_speeds = Vec::with_capacity(tws.len());
+            for k in 0..tws.len() {
+                row_speeds.push(match cells.get(k + 1) {
+                    Some(c) if !c.is_empty() => Some(parse_number(c, "boat speed")?),
+                    _ => None,
+                });
+            }
+            twa.push(angle);
+            speeds.push(row_speeds);
+        }
+
+        if twa.is_empty() {
+            return Err(RoutingError::InvalidPolar("No wind angle rows".to_string()));
+        }
+        if !is_ascending(&twa) || !is_ascending(&tws) {
+            return Err(RoutingError::InvalidPolar("Wind angles and speeds must be ascending".to_string()));
+        }
+
+        Ok(Self { name: name.to_string(), twa, tws, speeds })
+    }
+
+    /// Boat speed (knots) for a true wind angle (degrees, either tack) and speed (knots).
+    /// Angles tighter than the first row are treated as the no-go zone.
+    /// None when the speed depends on an empty cell of the table.
+    pub fn boat_speed(&self, twa: f64, tws: f64) -> Option<f64> {
+        let angle = {
+            let a = twa.abs() % 360.0;
+            if a > 180.0 { 360.0 - a } else { a }
+        };
+        if angle < self.twa[0] || tws <= 0.0 {
+            return Some(0.0);
+        }
+
+        let (r0, r1, tr) = bracket(&self.twa, angle);
+        let speed_at = |row: usize| -> Option<f64> {
+            let values = &self.speeds[row];
+            if tws < self.tws[0] {
+                // Scale linearly down to no wind
+                return values[0].map(|v| v * tws / self.tws[0]);
+            }
+            let (c0, c1, tc) = bracket(&self.tws, tws);
+            interpolate(values[c0], values[c1], tc)
+        };
+
+        interpolate(speed_at(r0), speed_at(r1), tr)
+    }
+}
+
+/// Linear interpolation that only needs the values it gives weight to
+fn interpolate(a: Option<f64>, b: Option<f64>, t: f64) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + (b - a) * t),
+        (Some(a), None) if t == 0.0 => Some(a),
+        (None, Some(b)) if t == 1.0 => Some(b),
+        _ => None,
+    }
+}
+
+/// Indices bracketing a value in an ascending table and the interpolation weight,
+/// clamped to the table ends
+fn bracket(values: &[f64], x: f64) -> (usize, usize, f64) {
+    let last = values.len() - 1;
+    if x <= values[0] {
+        return (0, 0, 0.0);
+    }
+    if x >= values[last] {
+        return (last, last, 0.0);
+    }
+    let upper = values.iter().position(|v| *v >= x).unwrap_or(last);
+    let lower = upper - 1;
+    let span = values[upper] - values[lower];
+    let t = if span > 0.0 { (x - values[lower]) / span } else { 0.0 };
+    (lower, upper, t)
+}
+
+fn is_ascending(values: &[f64]) -> bool {
+    values.windows(2).all(|w| w[1] > w[0])
+}
+
+fn detect_delimiter(line: &str) -> Option<char> {
+    ['\t', ';', ','].into_iter().find(|d| line.contains(*d))
+}
+
+fn split_row(line: &str, delimiter: Option<char>) -> Vec<&str> {
+    match delimiter {
+        Some(d) => line.split(d).map(str::trim).collect(),
+        None => line.split_whitespace().collect(),
+    }
+}
+
+fn parse_number(cell: &str, what: &str) -> Result<f64, RoutingError> {
+    cell.trim()
+        .parse::<f64>()
+        .map_err(|_| RoutingError::InvalidPolar(format!("Invalid {} '{}'", what, cell)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLAR: &str = "TWA\\TWS\t6\t10\t16\n\
+                         # comment\n\
+                         45\t4.0\t5.5\t6.0\n\
+                         90\t5.5\t7.0\t7.6\n\
+                         150\t4.5\t6.5\t\n";
+
+    #[test]
+    fn test_parse_polar() {
+        let polar = Polar::parse("Test", POLAR).unwrap();
+        assert_eq!(polar.tws, vec![6.0, 10.0, 16.0]);
+        assert_eq!(polar.twa, vec![45.0, 90.0, 150.0]);
+        assert_eq!(polar.speeds[2][2], None);
+
+        let csv = Polar::parse("Csv", "twa/tws;6;10\n60;5;6\n120;5.5;7\n").unwrap();
+        assert_eq!(csv.speeds[1], vec![Some(5.5), Some(7.0)]);
+
+        assert!(Polar::parse("Bad", "TWA\\TWS\t6\n90\tfast\n").is_err());
+    }
+
+    #[test]
+    fn test_boat_speed_interpolation() {
+        let polar = Polar::parse("Test", POLAR).unwrap();
+        assert_eq!(polar.boat_speed(30.0, 10.0), Some(0.0));
+        assert!((polar.boat_speed(90.0, 8.0).unwrap() - 6.25).abs() < 1e-9);
+        // Port and starboard tacks are symmetric
+        assert_eq!(polar.boat_speed(-90.0, 10.0), polar.boat_speed(270.0, 10.0));
+        // Light air scales towards zero, strong wind clamps
+        assert!((polar.boat_speed(90.0, 3.0).unwrap() - 2.75).abs() < 1e-9);
+        assert!((polar.boat_speed(90.0, 30.0).unwrap() - 7.6).abs() < 1e-9);
+        // The empty cell at 150° and 16 kn is no data, not a standstill
+        assert_eq!(polar.boat_speed(150.0, 20.0), None);
+        assert!((polar.boat_speed(150.0, 10.0).unwrap() - 6.5).abs() < 1e-9);
+    }
+}
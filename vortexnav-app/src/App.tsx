@@ -21,6 +21,9 @@ import {
   saveCm93Settings,
   initCm93Server,
   getCm93Features,
+  setNavigationTarget,
+  subscribeGpsEvents,
+  type GpsData,
   type GpsSourceStatus,
 } from './hooks/useTauri';
 import { useWaypointManager } from './hooks/useWaypointManager';
@@ -74,6 +77,7 @@ function App() {
   const {
     state: waypointState,
     stateRef: waypointStateRef,
+    mapWaypoints,
     activeWaypoint,
     editingPreview,
     loadWaypoints,
@@ -195,6 +199,14 @@ function App() {
     setCurrentRouteWaypointIndex(0);
   }, [routeManager.state.activeRouteId]);
 
+  // Share the route being navigated with the data server
+  useEffect(() => {
+    if (!isTauri()) return;
+    setNavigationTarget(routeManager.state.activeRouteId, currentRouteWaypointIndex).catch((err) =>
+      console.error('Failed to set navigation target:', err)
+    );
+  }, [routeManager.state.activeRouteId, currentRouteWaypointIndex]);
+
   // Panel visibility
   const [showWaypointPanel, setShowWaypointPanel] = useState(false);
   const [showRoutePanel, setShowRoutePanel] = useState(false);
@@ -522,45 +534,65 @@ function App() {
     return () => clearTimeout(timeoutId);
   }, [theme, basemap, showOpenSeaMap, apiKeys, settingsLoaded]);
 
-  // Poll GPS data and status from backend
+  // Follow GPS data and status pushed from the backend
   useEffect(() => {
     if (!isTauri()) {
       // Running in browser, simulate GPS for demo
       return;
     }
 
-    const pollGps = async () => {
-      try {
-        const [gpsData, status] = await Promise.all([
-          getGpsData(),
-          getGpsStatus(),
-        ]);
+    let statusConnected = false;
+    let hasPosition = false;
+    const applyStatus = (status: GpsSourceStatus) => {
+      setGpsStatus(status);
+      statusConnected = status.status === 'receiving_data' || status.status === 'connected';
+      setConnected(statusConnected && hasPosition);
+    };
+    const applyGpsData = (gpsData: GpsData) => {
+      hasPosition = gpsData.latitude != null && gpsData.longitude != null;
+      if (gpsData.latitude != null && gpsData.longitude != null) {
+        setVessel({
+          position: { lat: gpsData.latitude, lon: gpsData.longitude },
+          heading: gpsData.heading,
+          cog: gpsData.course,
+          sog: gpsData.speed_knots,
+        });
+      }
+      setConnected(statusConnected && hasPosition);
+    };
 
-        setGpsStatus(status);
+    const unlisteners: (() => void)[] = [];
+    let cancelled = false;
 
-        if (gpsData.latitude != null && gpsData.longitude != null) {
-          setVessel({
-            position: { lat: gpsData.latitude, lon: gpsData.longitude },
-            heading: gpsData.heading,
-            cog: gpsData.course,
-            sog: gpsData.speed_knots,
-          });
-          setConnected(status.status === 'receiving_data' || status.status === 'connected');
-        } else {
-          setConnected(false);
+    const subscribe = async () => {
+      try {
+        const subscriptions = await Promise.all([
+          subscribeGpsEvents<GpsSourceStatus>('gps:status', applyStatus),
+          subscribeGpsEvents<GpsData>('gps:position', applyGpsData),
+        ]);
+        if (cancelled) {
+          subscriptions.forEach((unlisten) => unlisten());
+          return;
         }
+        unlisteners.push(...subscriptions);
+
+        // Events only arrive on change, so start from the current state
+        const [gpsData, status] = await Promise.all([getGpsData(), getGpsStatus()]);
+        applyStatus(status);
+        applyGpsData(gpsData);
       } catch (error) {
         // GPS not available or error
-        console.debug('GPS poll:', error);
+        console.debug('GPS subscribe:', error);
         setConnected(false);
       }
     };
 
-    // Poll every second
-    const intervalId = setInterval(pollGps, 1000);
-    pollGps(); // Initial poll
+    subscribe();
 
-    return () => clearInterval(intervalId);
+    return () => {
+      cancelled = true;
+      unlisteners.forEach((unlisten) => unlisten());
+    };
   }, []);
 
   // Auto-center map on GPS position at startup (once)
@@ -864,7 +896,7 @@ function App() {
           apiKeys={apiKeys}
           zoom={12}
           vessel={vessel}
-          waypoints={waypointState.waypoints}
+          waypoints={mapWaypoints}
           activeWaypointId={waypointState.activeWaypointId}
           editingWaypointId={waypointState.editState.waypointId}
           editingPreview={editingPreview}
@@ -1223,6 +1255,7 @@ function App() {
               await loadWaypoints();
             }
           }}
+          onTracksImported={trackManager.loadTracks}
           onClose={() => setShowRoutePanel(false)}
         />
       )}
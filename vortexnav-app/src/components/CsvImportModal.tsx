@@ -0,0 +1,273 @@
+import { useState, useEffect, useCallback } from 'react';
+import type { ThemeMode, CoordinateDatum, CsvColumnMapping, CsvPreview, GpxImportResult } from '../types';
+import { previewCsvImport, importCsv } from '../hooks/useTauri';
+
+interface CsvImportModalProps {
+  theme: ThemeMode;
+  filePath: string;
+  onImported: (result: GpxImportResult) => void;
+  onClose: () => void;
+}
+
+const DATUMS: { value: CoordinateDatum; label: string }[] = [
+  { value: 'wgs84', label: 'WGS84 / NZGD2000 / GDA94' },
+  { value: 'nzgd49', label: 'NZGD49' },
+  { value: 'agd66', label: 'AGD66' },
+  { value: 'ed50', label: 'ED50' },
+  { value: 'nad27', label: 'NAD27' },
+  { value: 'osgb36', label: 'OSGB36' },
+  { value: 'tokyo', label: 'Tokyo' },
+];
+
+const DELIMITERS: { value: string; label: string }[] = [
+  { value: ',', label: 'Comma' },
+  { value: ';', label: 'Semicolon' },
+  { value: '\t', label: 'Tab' },
+  { value: '|', label: 'Pipe' },
+];
+
+const MAPPING_FIELDS: { key: keyof CsvColumnMapping; label: string }[] = [
+  { key: 'name', label: 'Name' },
+  { key: 'lat', label: 'Latitude' },
+  { key: 'lon', label: 'Longitude' },
+  { key: 'position', label: 'Lat & Lon' },
+  { key: 'description', label: 'Description' },
+  { key: 'symbol', label: 'Symbol' },
+];
+
+// Rows shown in the preview table; all rows are imported
+const PREVIEW_ROWS = 50;
+
+export function CsvImportModal({ theme, filePath, onImported, onClose }: CsvImportModalProps) {
+  const fileName = filePath.split(/[\\/]/).pop() ?? filePath;
+  const isSpreadsheet = /\.(xlsx|xlsm|xlsb|xls|ods)$/i.test(filePath);
+
+  // Null means "detect"; the first preview fills these in from the file
+  const [delimiter, setDelimiter] = useState<string | null>(null);
+  const [hasHeader, setHasHeader] = useState<boolean | null>(null);
+  const [mapping, setMapping] = useState<CsvColumnMapping | null>(null);
+  const [datum, setDatum] = useState<CoordinateDatum>('wgs84');
+  const [buildRoute, setBuildRoute] = useState(false);
+  const [routeName, setRouteName] = useState(fileName.replace(/\.[^.]+$/, ''));
+
+  const [preview, setPreview] = useState<CsvPreview | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [isImporting, setIsImporting] = useState(false);
+
+  // Re-read the file whenever the layout or datum changes
+  useEffect(() => {
+    let cancelled = false;
+    previewCsvImport(filePath, { delimiter, has_header: hasHeader, mapping, datum })
+      .then((result) => {
+        if (cancelled) return;
+        setPreview(result);
+        setError(null);
+      })
+      .catch((err) => {
+        if (!cancelled) setError(err instanceof Error ? err.message : 'Failed to read file');
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [filePath, delimiter, hasHeader, mapping, datum]);
+
+  const handleMappingChange = useCallback((key: keyof CsvColumnMapping, value: string) => {
+    if (!preview) return;
+    const column = value === '' ? null : Number(value);
+    const next = { ...preview.mapping, [key]: column };
+    // A combined position column and separate lat/lon columns are alternatives
+    if (key === 'position' && column !== null) {
+      next.lat = null;
+      next.lon = null;
+    } else if ((key === 'lat' || key === 'lon') && column !== null) {
+      next.position = null;
+    }
+    setMapping(next);
+  }, [preview]);
+
+  const handleDelimiterChange = useCallback((value: string) => {
+    setDelimiter(value);
+    // Columns move with the delimiter, so detect them again
+    setMapping(null);
+  }, []);
+
+  const handleImport = useCallback(async () => {
+    if (!preview) return;
+    setIsImporting(true);
+    try {
+      const result = await importCsv(filePath, {
+        delimiter: preview.delimiter,
+        has_header: preview.has_header,
+        mapping: preview.mapping,
+        datum,
+        build_route: buildRoute,
+        route_name: routeName,
+      });
+      onImported(result);
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to import file');
+      setIsImporting(false);
+    }
+  }, [preview, filePath, datum, buildRoute, routeName, onImported]);
+
+  const readable = preview?.rows.filter((row) => !row.error).length ?? 0;
+  const unreadable = (preview?.rows.length ?? 0) - readable;
+
+  return (
+    <>
+      <div className="modal-backdrop" onClick={onClose} />
+      <div className={`share-modal share-modal--${theme} csv-import`}>
+        <div className="share-modal__header">
+          <h3 className="share-modal__title">Import Waypoint List</h3>
+          <span className="share-modal__route-name">{fileName}</span>
+          <button className="share-modal__close" onClick={onClose}>
+            <svg width="20" height="20" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+              <line x1="18" y1="6" x2="6" y2="18" />
+              <line x1="6" y1="6" x2="18" y2="18" />
+            </svg>
+          </button>
+        </div>
+
+        {error && (
+          <div className="share-modal__status share-modal__status--error">
+            <span>{error}</span>
+          </div>
+        )}
+
+        <div className="share-modal__content">
+          {/* File layout */}
+          <div className="share-modal__section">
+            <h4 className="share-modal__section-title">Layout</h4>
+            <div className="csv-import__options">
+              {!isSpreadsheet && (
+                <label className="csv-import__field">
+                  <span>Separator</span>
+                  <select
+                    value={preview?.delimiter ?? ''}
+                    onChange={(e) => handleDelimiterChange(e.target.value)}
+                  >
+                    {DELIMITERS.map((d) => (
+                      <option key={d.label} value={d.value}>{d.label}</option>
+                    ))}
+                  </select>
+                </label>
+              )}
+              <label className="csv-import__field">
+                <span>Datum</span>
+                <select value={datum} onChange={(e) => setDatum(e.target.value as CoordinateDatum)}>
+                  {DATUMS.map((d) => (
+                    <option key={d.value} value={d.value}>{d.label}</option>
+                  ))}
+                </select>
+              </label>
+              <label className="csv-import__checkbox">
+                <input
+                  type="checkbox"
+                  checked={preview?.has_header ?? false}
+                  onChange={(e) => {
+                    setHasHeader(e.target.checked);
+                    setMapping(null);
+                  }}
+                />
+                <span>First row is a header</span>
+              </label>
+            </div>
+          </div>
+
+          {/* Column mapping */}
+          {preview && (
+            <div className="share-modal__section">
+              <h4 className="share-modal__section-title">Columns</h4>
+              <div className="csv-import__options">
+                {MAPPING_FIELDS.map(({ key, label }) => (
+                  <label key={key} className="csv-import__field">
+                    <span>{label}</span>
+                    <select
+                      value={preview.mapping[key] ?? ''}
+                      onChange={(e) => handleMappingChange(key, e.target.value)}
+                    >
+                      <option value="">None</option>
+                      {preview.columns.map((column, index) => (
+                        <option key={index} value={index}>{column}</option>
+                      ))}
+                    </select>
+                  </label>
+                ))}
+              </div>
+            </div>
+          )}
+
+          {/* Rows as they will be imported */}
+          {preview && (
+            <div className="share-modal__section">
+              <h4 className="share-modal__section-title">
+                Preview: {readable} {readable === 1 ? 'mark' : 'marks'}
+                {unreadable > 0 && `, ${unreadable} unreadable`}
+              </h4>
+              <div className="csv-import__table-wrap">
+                <table className="csv-import__table">
+                  <thead>
+                    <tr>
+                      <th>Row</th>
+                      <th>Name</th>
+                      <th>Latitude</th>
+                      <th>Longitude</th>
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {preview.rows.slice(0, PREVIEW_ROWS).map((row) => (
+                      <tr key={row.row} className={row.error ? 'csv-import__row--error' : undefined}>
+                        <td>{row.row}</td>
+                        <td>{row.name ?? ''}</td>
+                        {row.error ? (
+                          <td colSpan={2}>{row.error}</td>
+                        ) : (
+                          <>
+                            <td>{row.lat?.toFixed(6)}</td>
+                            <td>{row.lon?.toFixed(6)}</td>
+                          </>
+                        )}
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              </div>
+              {preview.rows.length > PREVIEW_ROWS && (
+                <p className="share-modal__hint">
+                  Showing the first {PREVIEW_ROWS} of {preview.rows.length} rows
+                </p>
+              )}
+            </div>
+          )}
+
+          {/* Route option */}
+          <div className="share-modal__section">
+            <label className="csv-import__checkbox">
+              <input type="checkbox" checked={buildRoute} onChange={(e) => setBuildRoute(e.target.checked)} />
+              <span>Build a route through the marks in file order</span>
+            </label>
+            {buildRoute && (
+              <label className="csv-import__field">
+                <span>Route name</span>
+                <input type="text" value={routeName} onChange={(e) => setRouteName(e.target.value)} />
+              </label>
+            )}
+          </div>
+        </div>
+
+        <div className="share-modal__footer csv-import__footer">
+          <button className="share-modal__btn share-modal__btn--compact" onClick={onClose}>
+            <span>Cancel</span>
+          </button>
+          <button
+            className="share-modal__btn share-modal__btn--compact"
+            onClick={handleImport}
+            disabled={!preview || readable === 0 || isImporting}
+          >
+            <span>{isImporting ? 'Importing...' : buildRoute ? 'Import Route' : 'Import Waypoints'}</span>
+          </button>
+        </div>
+      </div>
+    </>
+  );
+}
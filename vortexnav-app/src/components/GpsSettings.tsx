@@ -10,12 +10,35 @@ import {
   startGps,
   stopGps,
   getGpsStatus,
+  discoverSignalKServer,
+  getDataServerSettings,
+  saveDataServerSettings,
+  getDataServerStatus,
+  exportUserData,
+  importUserData,
+  importOpenCpn,
+  getSyncSettings,
+  saveSyncSettings,
+  getSyncStatus,
+  startSyncPairing,
+  pairSyncDevice,
+  getSyncPeers,
+  removeSyncPeer,
+  syncWithDevice,
   generateId,
   isTauri,
+  type DataServerSettings,
+  type DataServerStatus,
   type DetectedPort,
   type GpsSourceConfig,
   type GpsSourceStatus,
   type GpsSourceType,
+  type ImportMode,
+  type ImportReport,
+  type SyncPeer,
+  type SyncReport,
+  type SyncSettings,
+  type SyncStatus,
 } from '../hooks/useTauri';
 
 interface GpsSettingsProps {
@@ -32,6 +55,16 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
   const [loading, setLoading] = useState(true);
   const [testing, setTesting] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
+  const [serverSettings, setServerSettings] = useState<DataServerSettings | null>(null);
+  const [serverStatus, setServerStatus] = useState<DataServerStatus | null>(null);
+  const [restoreMode, setRestoreMode] = useState<ImportMode>('merge');
+  const [backupBusy, setBackupBusy] = useState(false);
+  const [syncSettings, setSyncSettings] = useState<SyncSettings | null>(null);
+  const [syncStatus, setSyncStatus] = useState<SyncStatus | null>(null);
+  const [syncPeers, setSyncPeers] = useState<SyncPeer[]>([]);
+  const [pairAddress, setPairAddress] = useState('');
+  const [pairCode, setPairCode] = useState('');
+  const [syncing, setSyncing] = useState<string | null>(null);
 
   // Form state for adding new source
   const [showAddForm, setShowAddForm] = useState(false);
@@ -53,14 +86,25 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
 
     try {
       setError(null);
-      const [portsData, sourcesData, statusData] = await Promise.all([
-        listSerialPorts(),
-        getGpsSources(),
-        getGpsStatus(),
-      ]);
+      const [portsData, sourcesData, statusData, serverSettingsData, serverStatusData, syncSettingsData, syncStatusData, peersData] =
+        await Promise.all([
+          listSerialPorts(),
+          getGpsSources(),
+          getGpsStatus(),
+          getDataServerSettings(),
+          getDataServerStatus(),
+          getSyncSettings(),
+          getSyncStatus(),
+          getSyncPeers(),
+        ]);
       setPorts(portsData);
       setSources(sourcesData);
       setStatus(statusData);
+      setServerSettings(serverSettingsData);
+      setServerStatus(serverStatusData);
+      setSyncSettings(syncSettingsData);
+      setSyncStatus(syncStatusData);
+      setSyncPeers(peersData);
     } catch (err) {
       setError(err instanceof Error ? err.message : 'Failed to load GPS data');
     } finally {
@@ -78,8 +122,16 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
 
     const interval = setInterval(async () => {
       try {
-        const statusData = await getGpsStatus();
+        const [statusData, serverStatusData, syncStatusData, peersData] = await Promise.all([
+          getGpsStatus(),
+          getDataServerStatus(),
+          getSyncStatus(),
+          getSyncPeers(),
+        ]);
         setStatus(statusData);
+        setServerStatus(serverStatusData);
+        setSyncStatus(syncStatusData);
+        setSyncPeers(peersData);
       } catch {
         // Ignore polling errors
       }
@@ -111,9 +163,184 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
     }
   };
 
+  // Find the Signal K server at the entered host/port
+  const handleDiscoverSignalK = async () => {
+    const address = newSource.port_name || 'localhost';
+    setTesting(address);
+    try {
+      const server = await discoverSignalKServer(address);
+      setNewSource({ ...newSource, port_name: server.address });
+      alert(`Found ${server.server_id || 'Signal K server'} ${server.server_version || ''} at ${server.address}`);
+    } catch (err) {
+      alert(`Discovery failed: ${err instanceof Error ? err.message : 'Unknown error'}`);
+    } finally {
+      setTesting(null);
+    }
+  };
+
+  // Save data server settings; the backend restarts the server to match
+  const handleSaveServer = async (settings: DataServerSettings) => {
+    setServerSettings(settings);
+    try {
+      setError(null);
+      setServerStatus(await saveDataServerSettings(settings));
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to save data server settings');
+    }
+  };
+
+  // Save sync settings; the backend restarts the sync server to match
+  const handleSaveSync = async (settings: SyncSettings) => {
+    setSyncSettings(settings);
+    try {
+      setError(null);
+      setSyncStatus(await saveSyncSettings(settings));
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to save sync settings');
+    }
+  };
+
+  // Show a code for the other device to enter
+  const handleStartPairing = async () => {
+    try {
+      setError(null);
+      await startSyncPairing();
+      setSyncStatus(await getSyncStatus());
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to start pairing');
+    }
+  };
+
+  // Pair with the device showing a code, then sync straight away
+  const handlePairDevice = async () => {
+    if (!pairAddress || !pairCode) {
+      setError('Enter the address and pairing code shown on the other device');
+      return;
+    }
+    setSyncing(pairAddress);
+    try {
+      setError(null);
+      const peer = await pairSyncDevice(pairAddress, pairCode);
+      setPairCode('');
+      alert(describeSync(await syncWithDevice(peer.device_id)));
+      setSyncPeers(await getSyncPeers());
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to pair device');
+    } finally {
+      setSyncing(null);
+    }
+  };
+
+  const handleSyncNow = async (peer: SyncPeer) => {
+    setSyncing(peer.device_id);
+    try {
+      setError(null);
+      const report = await syncWithDevice(peer.device_id);
+      setSyncPeers(await getSyncPeers());
+      alert(describeSync(report));
+      // Routes and waypoints are loaded at startup
+      if (report.received > 0) window.location.reload();
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to sync');
+    } finally {
+      setSyncing(null);
+    }
+  };
+
+  const handleRemovePeer = async (peer: SyncPeer) => {
+    if (!confirm(`Stop syncing with ${peer.name}?`)) return;
+    try {
+      await removeSyncPeer(peer.device_id);
+      setSyncPeers(await getSyncPeers());
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to remove device');
+    }
+  };
+
+  // Export all user data to one archive for moving to another device
+  const handleExportBackup = async () => {
+    try {
+      const { save } = await import('@tauri-apps/plugin-dialog');
+      const filePath = await save({
+        defaultPath: `vortexnav-backup-${new Date().toISOString().slice(0, 10)}.zip`,
+        filters: [{ name: 'VortexNav Backup', extensions: ['zip'] }],
+      });
+      if (!filePath) return;
+
+      setBackupBusy(true);
+      const manifest = await exportUserData(filePath);
+      alert(`Backup saved\n\nWaypoints: ${manifest.waypoints}\nRoutes: ${manifest.routes}\nTracks: ${manifest.tracks}`);
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to export backup');
+    } finally {
+      setBackupBusy(false);
+    }
+  };
+
+  // Restore from an archive: dry run first, then apply once confirmed
+  const handleRestoreBackup = async () => {
+    try {
+      const { open } = await import('@tauri-apps/plugin-dialog');
+      const selected = await open({
+        title: 'Restore Backup',
+        filters: [{ name: 'VortexNav Backup', extensions: ['zip'] }],
+        multiple: false,
+      });
+      if (!selected) return;
+
+      setBackupBusy(true);
+      const preview = await importUserData(selected, restoreMode, true);
+      const summary = describeImport(preview);
+      const prompt = restoreMode === 'replace'
+        ? `Replace all data on this device with the backup from ${preview.exported_at}?\n\n${summary}`
+        : `Merge the backup from ${preview.exported_at} into this device?\n\n${summary}`;
+      if (!confirm(prompt)) return;
+
+      await importUserData(selected, restoreMode, false);
+      // Routes, tracks and settings are loaded at startup
+      window.location.reload();
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to restore backup');
+    } finally {
+      setBackupBusy(false);
+    }
+  };
+
+  // One-step migration of routes, marks and tracks from OpenCPN
+  const handleImportOpenCpn = async () => {
+    try {
+      const { open } = await import('@tauri-apps/plugin-dialog');
+      const selected = await open({
+        title: 'Import from OpenCPN (navobj.xml or GPX)',
+        filters: [{ name: 'OpenCPN Data', extensions: ['xml', 'gpx'] }],
+        multiple: false,
+      });
+      if (!selected) return;
+
+      setBackupBusy(true);
+      const result = await importOpenCpn(selected);
+      const count = (added: number, updated: number) => (updated ? `${added} new, ${updated} updated` : `${added}`);
+      const counts = `Waypoints: ${count(result.waypoints_imported, result.waypoints_updated)}\nRoutes: ${count(result.routes_imported, result.routes_updated)}\nTracks: ${count(result.tracks_imported, result.tracks_updated)}`;
+      const warnings = result.errors.length ? `\n\nWarnings:\n${result.errors.join('\n')}` : '';
+      alert(`OpenCPN import complete\n\n${counts}${warnings}`);
+      // Routes, tracks and waypoints are loaded at startup
+      window.location.reload();
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to import OpenCPN data');
+    } finally {
+      setBackupBusy(false);
+    }
+  };
+
   // Add new source
   const handleAddSource = async () => {
-    if (!newSource.name || (newSource.source_type === 'serial_port' && !newSource.port_name)) {
+    if (
+      !newSource.name ||
+      ((newSource.source_type === 'serial_port' ||
+        newSource.source_type === 'tcp_stream' ||
+        newSource.source_type === 'signal_k') &&
+        !newSource.port_name)
+    ) {
       setError('Please fill in all required fields');
       return;
     }
@@ -126,6 +353,7 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
       baud_rate: newSource.baud_rate || 4800,
       enabled: newSource.enabled ?? true,
       priority: sources.length,
+      raw_nmea: newSource.raw_nmea ?? false,
     };
 
     try {
@@ -387,7 +615,11 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
                             ? `${source.port_name} @ ${source.baud_rate} baud`
                             : source.source_type === 'simulated'
                             ? 'Simulated GPS'
-                            : 'TCP Stream'}
+                            : source.source_type === 'signal_k'
+                            ? `Signal K ${source.port_name}`
+                            : source.source_type === 'gpsd'
+                            ? `gpsd ${source.port_name || '127.0.0.1:2947'}${source.raw_nmea ? ' (raw NMEA)' : ''}`
+                            : `TCP ${source.port_name}`}
                         </div>
                       </div>
                       <div className="gps-settings__source-actions">
@@ -468,6 +700,200 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
               )}
             </section>
 
+            {/* Data Server */}
+            {serverSettings && (
+              <section className="gps-settings__section">
+                <h3>Share Data</h3>
+                <div className="gps-settings__field">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={serverSettings.enabled}
+                      onChange={(e) => handleSaveServer({ ...serverSettings, enabled: e.target.checked })}
+                    />{' '}
+                    Serve position, route and AIS to other devices
+                  </label>
+                </div>
+                <div className="gps-settings__field">
+                  <label>NMEA 0183 TCP Port</label>
+                  <input
+                    type="number"
+                    value={serverSettings.nmea_port}
+                    onChange={(e) => setServerSettings({ ...serverSettings, nmea_port: parseInt(e.target.value) || 0 })}
+                    onBlur={() => handleSaveServer(serverSettings)}
+                  />
+                </div>
+                <div className="gps-settings__field">
+                  <label>Signal K Port</label>
+                  <input
+                    type="number"
+                    value={serverSettings.signalk_port}
+                    onChange={(e) => setServerSettings({ ...serverSettings, signalk_port: parseInt(e.target.value) || 0 })}
+                    onBlur={() => handleSaveServer(serverSettings)}
+                  />
+                </div>
+                {serverStatus?.running && (
+                  <p className="gps-settings__hint">
+                    NMEA on {serverStatus.nmea_address} ({serverStatus.nmea_clients} connected), Signal K on{' '}
+                    {serverStatus.signalk_address} ({serverStatus.signalk_clients} streaming)
+                  </p>
+                )}
+              </section>
+            )}
+
+            {/* Device Sync */}
+            {syncSettings && (
+              <section className="gps-settings__section">
+                <h3>Sync Devices</h3>
+                <p className="gps-settings__hint">
+                  Keep routes, waypoints and tracks the same on another VortexNav on this network. The newer edit wins
+                  when both devices changed the same item.
+                </p>
+                <div className="gps-settings__field">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={syncSettings.enabled}
+                      onChange={(e) => handleSaveSync({ ...syncSettings, enabled: e.target.checked })}
+                    />{' '}
+                    Allow other devices to pair and sync
+                  </label>
+                </div>
+                <div className="gps-settings__field">
+                  <label>Device Name</label>
+                  <input
+                    type="text"
+                    value={syncSettings.device_name}
+                    onChange={(e) => setSyncSettings({ ...syncSettings, device_name: e.target.value })}
+                    onBlur={() => handleSaveSync(syncSettings)}
+                  />
+                </div>
+                <div className="gps-settings__field">
+                  <label>Listen Address</label>
+                  <input
+                    type="text"
+                    value={syncSettings.bind_address}
+                    onChange={(e) => setSyncSettings({ ...syncSettings, bind_address: e.target.value })}
+                    onBlur={() => handleSaveSync(syncSettings)}
+                    placeholder="e.g., 192.168.1.10"
+                  />
+                  <p className="gps-settings__hint">
+                    This device's address on the boat network. 0.0.0.0 accepts connections on every network it
+                    joins, including marina Wi-Fi.
+                  </p>
+                </div>
+                <div className="gps-settings__field">
+                  <label>Sync Port</label>
+                  <input
+                    type="number"
+                    value={syncSettings.port}
+                    onChange={(e) => setSyncSettings({ ...syncSettings, port: parseInt(e.target.value) || 0 })}
+                    onBlur={() => handleSaveSync(syncSettings)}
+                  />
+                </div>
+                {syncStatus?.running && (
+                  <div className="gps-settings__actions">
+                    <button className="gps-settings__btn" onClick={handleStartPairing}>
+                      Show Pairing Code
+                    </button>
+                    {syncStatus.pairing_code && (
+                      <p className="gps-settings__hint">
+                        On the other device, pair with {syncStatus.address || `port ${syncSettings.port}`} using code{' '}
+                        <strong>{syncStatus.pairing_code.code}</strong> (expires in {Math.ceil(syncStatus.pairing_code.expires_in_secs / 60)} min)
+                      </p>
+                    )}
+                  </div>
+                )}
+
+                <div className="gps-settings__field">
+                  <label>Pair With Device</label>
+                  <input
+                    type="text"
+                    value={pairAddress}
+                    onChange={(e) => setPairAddress(e.target.value)}
+                    placeholder="e.g., 192.168.1.20:10120"
+                  />
+                  <input
+                    type="text"
+                    value={pairCode}
+                    onChange={(e) => setPairCode(e.target.value)}
+                    placeholder="Pairing code"
+                  />
+                  <button
+                    className="gps-settings__btn gps-settings__btn--small gps-settings__btn--primary"
+                    onClick={handlePairDevice}
+                    disabled={syncing !== null}
+                  >
+                    {syncing === pairAddress ? 'Pairing...' : 'Pair'}
+                  </button>
+                </div>
+
+                {syncPeers.length === 0 ? (
+                  <p className="gps-settings__empty">No paired devices.</p>
+                ) : (
+                  <div className="gps-settings__ports">
+                    {syncPeers.map((peer) => (
+                      <div key={peer.device_id} className="gps-settings__port">
+                        <div className="gps-settings__port-info">
+                          <div className="gps-settings__port-name">{peer.name}</div>
+                          <div className="gps-settings__port-details">
+                            {peer.address || 'Syncs when it connects'}
+                            {peer.last_synced_at && ` - last synced ${new Date(peer.last_synced_at).toLocaleString()}`}
+                          </div>
+                        </div>
+                        <div className="gps-settings__port-actions">
+                          <button
+                            className="gps-settings__btn gps-settings__btn--small gps-settings__btn--primary"
+                            onClick={() => handleSyncNow(peer)}
+                            disabled={!peer.address || syncing !== null}
+                          >
+                            {syncing === peer.device_id ? 'Syncing...' : 'Sync Now'}
+                          </button>
+                          <button
+                            className="gps-settings__btn gps-settings__btn--small"
+                            onClick={() => handleRemovePeer(peer)}
+                          >
+                            Remove
+                          </button>
+                        </div>
+                      </div>
+                    ))}
+                  </div>
+                )}
+              </section>
+            )}
+
+            {/* Backup & Restore */}
+            <section className="gps-settings__section">
+              <h3>Backup & Restore</h3>
+              <p className="gps-settings__hint">
+                Waypoints, routes, tracks, settings, GPS sources and chart setup in one file, for moving to another device.
+              </p>
+              <div className="gps-settings__field">
+                <label>Restore Mode</label>
+                <select value={restoreMode} onChange={(e) => setRestoreMode(e.target.value as ImportMode)}>
+                  <option value="merge">Merge with data on this device</option>
+                  <option value="replace">Replace data on this device</option>
+                </select>
+              </div>
+              <div className="gps-settings__actions">
+                <button className="gps-settings__btn" onClick={handleExportBackup} disabled={backupBusy}>
+                  Export Backup
+                </button>
+                <button className="gps-settings__btn gps-settings__btn--primary" onClick={handleRestoreBackup} disabled={backupBusy}>
+                  Restore Backup
+                </button>
+              </div>
+              <p className="gps-settings__hint">
+                Coming from OpenCPN? Import its navobj.xml to bring over every route, mark and track. Running it again later only updates.
+              </p>
+              <div className="gps-settings__actions">
+                <button className="gps-settings__btn" onClick={handleImportOpenCpn} disabled={backupBusy}>
+                  Import from OpenCPN
+                </button>
+              </div>
+            </section>
+
             {/* Add Form */}
             {showAddForm && (
               <div className="gps-settings__modal">
@@ -493,6 +919,9 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
                       }
                     >
                       <option value="serial_port">Serial Port</option>
+                      <option value="tcp_stream">TCP (NMEA 0183 / NMEA 2000 gateway)</option>
+                      <option value="gpsd">gpsd</option>
+                      <option value="signal_k">Signal K</option>
                       <option value="simulated">Simulated (Demo)</option>
                     </select>
                   </div>
@@ -532,6 +961,65 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
                     </>
                   )}
 
+                  {newSource.source_type === 'tcp_stream' && (
+                    <div className="gps-settings__field">
+                      <label>Address</label>
+                      <input
+                        type="text"
+                        value={newSource.port_name || ''}
+                        onChange={(e) => setNewSource({ ...newSource, port_name: e.target.value })}
+                        placeholder="192.168.4.1:1457"
+                      />
+                      <p className="gps-settings__hint">
+                        NMEA 0183, Actisense ASCII or Yacht Devices RAW
+                      </p>
+                    </div>
+                  )}
+
+                  {newSource.source_type === 'signal_k' && (
+                    <div className="gps-settings__field">
+                      <label>Server</label>
+                      <input
+                        type="text"
+                        value={newSource.port_name || ''}
+                        onChange={(e) => setNewSource({ ...newSource, port_name: e.target.value })}
+                        placeholder="localhost:3000"
+                      />
+                      <button
+                        className="gps-settings__btn gps-settings__btn--small"
+                        onClick={handleDiscoverSignalK}
+                        disabled={testing !== null}
+                      >
+                        {testing !== null ? 'Searching...' : 'Discover'}
+                      </button>
+                    </div>
+                  )}
+
+                  {newSource.source_type === 'gpsd' && (
+                    <>
+                      <div className="gps-settings__field">
+                        <label>Address</label>
+                        <input
+                          type="text"
+                          value={newSource.port_name || ''}
+                          onChange={(e) => setNewSource({ ...newSource, port_name: e.target.value })}
+                          placeholder="127.0.0.1:2947"
+                        />
+                      </div>
+
+                      <div className="gps-settings__field">
+                        <label>
+                          <input
+                            type="checkbox"
+                            checked={newSource.raw_nmea ?? false}
+                            onChange={(e) => setNewSource({ ...newSource, raw_nmea: e.target.checked })}
+                          />{' '}
+                          Show raw NMEA in the monitor
+                        </label>
+                      </div>
+                    </>
+                  )}
+
                   <div className="gps-settings__modal-actions">
                     <button
                       className="gps-settings__btn"
@@ -555,3 +1043,38 @@ export function GpsSettings({ theme, onClose }: GpsSettingsProps) {
     </div>
   );
 }
+
+// Sync outcome, listing each conflict and which edit was kept
+function describeSync(report: SyncReport): string {
+  const lines = [`Synced with ${report.peer}: ${report.sent} sent, ${report.received} received`];
+  if (report.conflicts.length > 0) {
+    lines.push('', 'Edited on both devices:');
+    for (const c of report.conflicts) {
+      lines.push(`${c.name}: kept ${c.kept === 'local' ? 'this device' : report.peer}'s version`);
+    }
+  }
+  if (report.errors.length > 0) {
+    lines.push('', 'Problems:', ...report.errors);
+  }
+  return lines.join('\n');
+}
+
+// One line per kind of data the import would touch
+function describeImport(report: ImportReport): string {
+  const rows: [string, ImportReport['waypoints']][] = [
+    ['Waypoints', report.waypoints],
+    ['Waypoint groups', report.waypoint_groups],
+    ['Routes', report.routes],
+    ['Route tags', report.route_tags],
+    ['Tracks', report.tracks],
+    ['Settings', report.settings],
+    ['GPS sources', report.gps_sources],
+    ['Chart layers', report.chart_layers],
+    ['Chart names', report.chart_metadata],
+    ['Catalogs', report.catalogs],
+  ];
+  return rows
+    .filter(([, c]) => c.added + c.updated + c.removed > 0)
+    .map(([label, c]) => `${label}: ${c.added} new, ${c.updated} updated${c.removed ? `, ${c.removed} removed` : ''}`)
+    .join('\n') || 'No changes';
+}
@@ -5,10 +5,12 @@ import {
   getGpsStatus,
   getNmeaBuffer,
   clearNmeaBuffer,
+  subscribeGpsEvents,
   isTauri,
   type GpsData,
   type GpsSourceStatus,
   type SatelliteInfo,
+  type SatelliteUpdate,
 } from '../hooks/useTauri';
 
 interface GpsStatusModalProps {
@@ -18,35 +20,64 @@ interface GpsStatusModalProps {
 
 export function GpsStatusModal({ theme, onClose }: GpsStatusModalProps) {
   const [gpsData, setGpsData] = useState<GpsData | null>(null);
+  const [satelliteInfo, setSatelliteInfo] = useState<SatelliteInfo[]>([]);
   const [status, setStatus] = useState<GpsSourceStatus | null>(null);
   const [nmeaBuffer, setNmeaBuffer] = useState<string[]>([]);
   const [autoScroll, setAutoScroll] = useState(true);
   const [paused, setPaused] = useState(false);
   const trafficRef = useRef<HTMLDivElement>(null);
 
-  // Poll GPS data and NMEA buffer
+  // Follow GPS data and status events while not paused
   useEffect(() => {
-    if (!isTauri()) return;
+    if (!isTauri() || paused) return;
 
-    const pollData = async () => {
-      if (paused) return;
+    const unlisteners: (() => void)[] = [];
+    let cancelled = false;
 
+    const subscribe = async () => {
       try {
-        const [data, statusData, buffer] = await Promise.all([
-          getGpsData(),
-          getGpsStatus(),
-          getNmeaBuffer(),
+        const subscriptions = await Promise.all([
+          subscribeGpsEvents<GpsData>('gps:position', setGpsData),
+          subscribeGpsEvents<SatelliteUpdate>('gps:satellites', (update) => setSatelliteInfo(update.satellites_info)),
+          subscribeGpsEvents<GpsSourceStatus>('gps:status', setStatus),
         ]);
+        if (cancelled) {
+          subscriptions.forEach((unlisten) => unlisten());
+          return;
+        }
+        unlisteners.push(...subscriptions);
+
+        // Events only arrive on change, so start from the current state
+        const [data, statusData] = await Promise.all([getGpsData(), getGpsStatus()]);
         setGpsData(data);
+        setSatelliteInfo(data.satellites_info);
         setStatus(statusData);
-        setNmeaBuffer(buffer);
       } catch (error) {
-        console.debug('GPS poll error:', error);
+        console.debug('GPS subscribe error:', error);
+      }
+    };
+
+    subscribe();
+    return () => {
+      cancelled = true;
+      unlisteners.forEach((unlisten) => unlisten());
+    };
+  }, [paused]);
+
+  // Poll the NMEA buffer
+  useEffect(() => {
+    if (!isTauri() || paused) return;
+
+    const pollBuffer = async () => {
+      try {
+        setNmeaBuffer(await getNmeaBuffer());
+      } catch (error) {
+        console.debug('NMEA buffer poll error:', error);
       }
     };
 
-    pollData();
-    const interval = setInterval(pollData, 500);
+    pollBuffer();
+    const interval = setInterval(pollBuffer, 500);
     return () => clearInterval(interval);
   }, [paused]);
 
@@ -93,7 +124,7 @@ export function GpsStatusModal({ theme, onClose }: GpsStatusModalProps) {
   };
 
   // Group satellites by constellation
-  const groupedSatellites = (gpsData?.satellites_info || []).reduce<Record<string, SatelliteInfo[]>>(
+  const groupedSatellites = satelliteInfo.reduce<Record<string, SatelliteInfo[]>>(
     (acc, sat) => {
       const key = sat.constellation || 'Unknown';
       if (!acc[key]) acc[key] = [];
@@ -3301,8 +3301,14 @@ export function MapView({
 
       if (points.length < 2) return;
 
-      // Build coordinates from track points
-      const coordinates: [number, number][] = points.map(pt => [pt.lon, pt.lat]);
+      // Build one line per segment so gaps in the track stay gaps
+      const coordinates: [number, number][][] = [];
+      points.forEach((pt, i) => {
+        if (i === 0 || (pt.segment ?? 0) !== (points[i - 1].segment ?? 0)) {
+          coordinates.push([]);
+        }
+        coordinates[coordinates.length - 1].push([pt.lon, pt.lat]);
+      });
 
       features.push({
         type: 'Feature',
@@ -3313,7 +3319,7 @@ export function MapView({
           isRecording,
         },
         geometry: {
-          type: 'LineString',
+          type: 'MultiLineString',
           coordinates,
         },
       });
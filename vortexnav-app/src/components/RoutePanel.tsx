@@ -4,9 +4,13 @@ import type { Position } from '../types';
 import { ROUTE_COLORS, DEFAULT_ROUTE_COLOR } from '../types';
 import type { useRouteManager } from '../hooks/useRouteManager';
 import type { Waypoint } from '../hooks/useTauri';
-import { importGpx, isTauri } from '../hooks/useTauri';
+import { importGeojson, importGpx, importKml, isTauri } from '../hooks/useTauri';
 import { open } from '@tauri-apps/plugin-dialog';
 import { ShareRouteModal } from './ShareRouteModal';
+import { CsvImportModal } from './CsvImportModal';
+
+// CSV, TSV and spreadsheet waypoint lists
+const WAYPOINT_LIST_EXTENSIONS = ['csv', 'tsv', 'txt', 'xlsx', 'xls', 'ods'];
 
 // Get the return type of useRouteManager
 type RouteManagerType = ReturnType<typeof useRouteManager>;
@@ -19,6 +23,7 @@ interface RoutePanelProps {
   onCenterOnRoute: (routeId: number) => void;
   onStartMapCreation: (name: string) => void;
   onRouteDeleted?: (deletedWaypointIds: boolean) => void;
+  onTracksImported?: () => void;
   onClose: () => void;
 }
 
@@ -30,6 +35,7 @@ export function RoutePanel({
   onCenterOnRoute,
   onStartMapCreation,
   onRouteDeleted,
+  onTracksImported,
   onClose,
 }: RoutePanelProps) {
   const {
@@ -162,44 +168,71 @@ export function RoutePanel({
   // GPX Import state
   const [_gpxImportResult, setGpxImportResult] = useState<GpxImportResult | null>(null);
   const [isImporting, setIsImporting] = useState(false);
+  // Waypoint lists open a preview before anything is imported
+  const [csvImportPath, setCsvImportPath] = useState<string | null>(null);
+
+  // Refresh after an import and report what was added
+  const handleImportResult = useCallback(async (result: GpxImportResult) => {
+    setGpxImportResult(result);
+
+    // Reload routes after import; tracks keep their own list
+    await routeManager.loadRoutes();
+    if (result.tracks_imported + (result.tracks_updated ?? 0) > 0) {
+      onTracksImported?.();
+    }
+
+    // Show success message
+    const count = (added: number, updated?: number) => (updated ? `${added} new, ${updated} updated` : `${added}`);
+    const counts = `Routes: ${count(result.routes_imported, result.routes_updated)}\nWaypoints: ${count(result.waypoints_imported, result.waypoints_updated)}\nTracks: ${count(result.tracks_imported, result.tracks_updated)}`;
+    if (result.errors.length === 0) {
+      alert(`Import complete!\n\n${counts}`);
+    } else {
+      alert(`Import complete with warnings:\n\n${counts}\n\nWarnings:\n${result.errors.join('\n')}`);
+    }
+  }, [routeManager, onTracksImported]);
 
-  // Handle GPX import
+  // Handle route file import (GPX, KML/KMZ, GeoJSON, or a CSV/spreadsheet waypoint list)
   const handleImportGpx = useCallback(async () => {
     if (!isTauri()) {
-      alert('GPX import is only available in the desktop app');
+      alert('Route import is only available in the desktop app');
       return;
     }
 
     try {
       setIsImporting(true);
       const selected = await open({
-        title: 'Import GPX File',
-        filters: [{ name: 'GPX Files', extensions: ['gpx'] }],
+        title: 'Import Route File',
+        filters: [
+          { name: 'Route Files', extensions: ['gpx', 'kml', 'kmz', 'geojson', 'json', ...WAYPOINT_LIST_EXTENSIONS] },
+          { name: 'GPX Files', extensions: ['gpx'] },
+          { name: 'Google Earth Files', extensions: ['kml', 'kmz'] },
+          { name: 'GeoJSON Files', extensions: ['geojson', 'json'] },
+          { name: 'Waypoint Lists', extensions: WAYPOINT_LIST_EXTENSIONS },
+        ],
         multiple: false,
       });
 
       if (selected) {
         const filePath = typeof selected === 'string' ? selected : selected;
-        const result = await importGpx(filePath);
-        setGpxImportResult(result);
-
-        // Reload routes after import
-        await routeManager.loadRoutes();
-
-        // Show success message
-        if (result.errors.length === 0) {
-          alert(`Import complete!\n\nRoutes: ${result.routes_imported}\nWaypoints: ${result.waypoints_imported}\nTracks: ${result.tracks_imported}`);
-        } else {
-          alert(`Import complete with warnings:\n\nRoutes: ${result.routes_imported}\nWaypoints: ${result.waypoints_imported}\nTracks: ${result.tracks_imported}\n\nWarnings:\n${result.errors.join('\n')}`);
+        const extension = filePath.split('.').pop()?.toLowerCase();
+        if (extension && WAYPOINT_LIST_EXTENSIONS.includes(extension)) {
+          setCsvImportPath(filePath);
+          return;
         }
+        const importFile = extension === 'kml' || extension === 'kmz'
+          ? importKml
+          : extension === 'geojson' || extension === 'json'
+            ? importGeojson
+            : importGpx;
+        await handleImportResult(await importFile(filePath));
       }
     } catch (error) {
-      console.error('GPX import error:', error);
-      alert(`Failed to import GPX: ${error instanceof Error ? error.message : 'Unknown error'}`);
+      console.error('Route import error:', error);
+      alert(`Failed to import file: ${error instanceof Error ? error.message : 'Unknown error'}`);
     } finally {
       setIsImporting(false);
     }
-  }, [routeManager]);
+  }, [handleImportResult]);
 
   // Handle waypoint selection toggle
   const handleWaypointToggle = (waypointId: number) => {
@@ -321,7 +354,7 @@ export function RoutePanel({
           className="route-panel__btn"
           onClick={handleImportGpx}
           disabled={isImporting}
-          title="Import GPX file"
+          title="Import GPX, KML/KMZ or GeoJSON file"
         >
           <svg viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2" width="16" height="16">
             <path d="M21 15v4a2 2 0 0 1-2 2H5a2 2 0 0 1-2-2v-4" />
@@ -781,6 +814,19 @@ export function RoutePanel({
         </div>
       )}
 
+      {/* Waypoint list import preview */}
+      {csvImportPath && (
+        <CsvImportModal
+          theme={theme}
+          filePath={csvImportPath}
+          onImported={(result) => {
+            setCsvImportPath(null);
+            handleImportResult(result);
+          }}
+          onClose={() => setCsvImportPath(null)}
+        />
+      )}
+
       {/* Share Route Modal */}
       {shareModalRoute && (
         <ShareRouteModal
@@ -1,6 +1,6 @@
 import { useState, useCallback } from 'react';
 import type { ThemeMode, RouteWithWaypoints } from '../types';
-import { getRouteGpxString, getRouteSummaryText, exportRouteGpx } from '../hooks/useTauri';
+import { getRouteGpxString, getRouteSummaryText, exportRouteGpx, exportKml, exportGeojson } from '../hooks/useTauri';
 
 interface ShareRouteModalProps {
   theme: ThemeMode;
@@ -53,8 +53,8 @@ export function ShareRouteModal({ theme, route, onClose }: ShareRouteModalProps)
     }
   }, [route.route.id, showStatus]);
 
-  // Download GPX file
-  const handleDownloadGpx = useCallback(async () => {
+  // Save the route as GPX, KML/KMZ or GeoJSON
+  const handleSaveRouteFile = useCallback(async () => {
     if (!route.route.id) return;
 
     setStatus('loading');
@@ -63,18 +63,31 @@ export function ShareRouteModal({ theme, route, onClose }: ShareRouteModalProps)
       const { save } = await import('@tauri-apps/plugin-dialog');
       const filePath = await save({
         defaultPath: `${route.route.name.replace(/[^a-zA-Z0-9]/g, '_')}.gpx`,
-        filters: [{ name: 'GPX Files', extensions: ['gpx'] }],
+        filters: [
+          { name: 'GPX Files', extensions: ['gpx'] },
+          { name: 'Google Earth KML', extensions: ['kml'] },
+          { name: 'Google Earth KMZ', extensions: ['kmz'] },
+          { name: 'GeoJSON Files', extensions: ['geojson'] },
+        ],
       });
 
       if (filePath) {
-        await exportRouteGpx(route.route.id, filePath);
-        showStatus('GPX file saved!', true);
+        // The format follows the extension chosen in the dialog
+        const extension = filePath.split('.').pop()?.toLowerCase();
+        if (extension === 'kml' || extension === 'kmz') {
+          await exportKml(filePath, { routeIds: [route.route.id] });
+        } else if (extension === 'geojson' || extension === 'json') {
+          await exportGeojson(filePath, { routeIds: [route.route.id] });
+        } else {
+          await exportRouteGpx(route.route.id, filePath);
+        }
+        showStatus('Route file saved!', true);
       } else {
         setStatus('idle');
       }
     } catch (error) {
-      console.error('Failed to download GPX:', error);
-      showStatus('Failed to save GPX file', false);
+      console.error('Failed to save route file:', error);
+      showStatus('Failed to save route file', false);
     }
   }, [route.route.id, route.route.name, showStatus]);
 
@@ -305,14 +318,14 @@ export function ShareRouteModal({ theme, route, onClose }: ShareRouteModalProps)
                 <span>Copy GPX to Clipboard</span>
                 <small>Paste into messages or files</small>
               </button>
-              <button className="share-modal__btn" onClick={handleDownloadGpx}>
+              <button className="share-modal__btn" onClick={handleSaveRouteFile}>
                 <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
                   <path d="M21 15v4a2 2 0 0 1-2 2H5a2 2 0 0 1-2-2v-4" />
                   <polyline points="7 10 12 15 17 10" />
                   <line x1="12" y1="15" x2="12" y2="3" />
                 </svg>
-                <span>Save GPX File</span>
-                <small>Download to your device</small>
+                <span>Save Route File</span>
+                <small>GPX, KML/KMZ or GeoJSON</small>
               </button>
             </div>
           </div>
@@ -1,8 +1,9 @@
 import { useState, useMemo, useCallback, useEffect } from 'react';
 import type { ThemeMode, Position } from '../types';
-import type { useWaypointManager } from '../hooks/useWaypointManager';
+import type { useWaypointManager, Waypoint, WaypointGroup } from '../hooks/useWaypointManager';
 import {
   isTauri,
+  getWaypointGroupExclusiveWaypointCount,
   calculateDistance,
   calculateBearing,
   formatDistance,
@@ -35,6 +36,7 @@ const WAYPOINT_SYMBOLS = [
   { id: 'fishing', label: 'Fishing', icon: '🎣' },
   { id: 'dive', label: 'Dive Site', icon: '🤿' },
   { id: 'beach', label: 'Beach', icon: '🏖️' },
+  { id: 'fix', label: 'Celestial Fix', icon: '✴️' },
 ];
 
 export function WaypointPanel({
@@ -63,9 +65,23 @@ export function WaypointPanel({
     toggleAllLabels,
     toggleAllMarkers,
     toggleWaypointHidden,
+    hiddenGroupIds,
+    moveWaypointsToGroup,
+    setWaypointsHidden,
+    setWaypointsShowLabel,
+    setWaypointsSymbol,
+    deleteWaypoints,
+    createGroup,
+    updateGroup,
+    toggleGroupHidden,
+    deleteGroup,
   } = waypointManager;
 
-  const { waypoints, editState, activeWaypointId, showAllLabels, showAllMarkers } = state;
+  const { waypoints, groups, editState, activeWaypointId, showAllLabels, showAllMarkers } = state;
+
+  // Ticked waypoints for bulk actions, and folded groups
+  const [checkedIds, setCheckedIds] = useState<Set<number>>(new Set());
+  const [collapsedGroups, setCollapsedGroups] = useState<Set<number>>(new Set());
 
   // Search and sort state
   const [searchQuery, setSearchQuery] = useState('');
@@ -228,6 +244,227 @@ export function WaypointPanel({
     }
   };
 
+  // ============ Groups and Bulk Actions ============
+
+  // Drop ticks for waypoints that no longer exist
+  useEffect(() => {
+    setCheckedIds((checked) => {
+      const live = new Set(waypoints.map((w) => w.id));
+      const kept = new Set([...checked].filter((id) => live.has(id)));
+      return kept.size === checked.size ? checked : kept;
+    });
+  }, [waypoints]);
+
+  // Full path of each group, e.g. "Cruising guide / Anchorages", for the move menu
+  const groupPaths = useMemo(() => {
+    const byId = new Map(groups.map((g) => [g.id, g]));
+    const paths = new Map<number, string>();
+    for (const group of groups) {
+      const names = [group.name];
+      let parent = group.parent_id !== null ? byId.get(group.parent_id) : undefined;
+      while (parent && names.length <= groups.length) {
+        names.unshift(parent.name);
+        parent = parent.parent_id !== null ? byId.get(parent.parent_id) : undefined;
+      }
+      paths.set(group.id!, names.join(' / '));
+    }
+    return paths;
+  }, [groups]);
+
+  const checked = [...checkedIds];
+
+  const toggleChecked = (waypointId: number) => {
+    setCheckedIds((current) => {
+      const next = new Set(current);
+      if (next.has(waypointId)) next.delete(waypointId);
+      else next.add(waypointId);
+      return next;
+    });
+  };
+
+  const toggleCollapsed = (groupId: number) => {
+    setCollapsedGroups((current) => {
+      const next = new Set(current);
+      if (next.has(groupId)) next.delete(groupId);
+      else next.add(groupId);
+      return next;
+    });
+  };
+
+  const runAction = async (action: () => Promise<unknown>) => {
+    try {
+      await action();
+    } catch (error) {
+      alert(error instanceof Error ? error.message : String(error));
+    }
+  };
+
+  const handleNewGroup = (parentId: number | null) => {
+    const name = prompt(parentId === null ? 'New group name' : 'New subgroup name');
+    if (name?.trim()) runAction(() => createGroup(name.trim(), parentId));
+  };
+
+  const handleRenameGroup = (group: WaypointGroup) => {
+    const name = prompt('Rename group', group.name);
+    if (name?.trim() && name.trim() !== group.name) runAction(() => updateGroup({ ...group, name: name.trim() }));
+  };
+
+  const handleDeleteGroup = async (group: WaypointGroup) => {
+    const exclusiveCount = await getWaypointGroupExclusiveWaypointCount(group.id!);
+    if (!confirm(`Delete group "${group.name}" and its subgroups?`)) return;
+    // Waypoints used by routes are never deleted with a group
+    const withWaypoints =
+      exclusiveCount > 0 &&
+      confirm(
+        `Also delete ${exclusiveCount} waypoint${exclusiveCount === 1 ? '' : 's'} in it? ` +
+          'Waypoints used by routes are kept either way. Choose Cancel to keep all waypoints.'
+      );
+    await deleteGroup(group.id!, withWaypoints);
+  };
+
+  const handleBulkMove = (value: string) => {
+    if (value === '') return;
+    runAction(() => moveWaypointsToGroup(checked, value === 'none' ? null : Number(value)));
+  };
+
+  const handleBulkDelete = async () => {
+    if (!confirm(`Delete ${checked.length} waypoint${checked.length === 1 ? '' : 's'}? They will also be removed from any routes.`)) return;
+    await deleteWaypoints(checked);
+  };
+
+  const renderWaypointRow = (waypoint: Waypoint, depth: number) => {
+    const nav = getDistanceBearing(waypoint.id);
+    const isSelected = state.selectedWaypointId === waypoint.id;
+    const isNavigating = activeWaypointId === waypoint.id;
+    const isHidden = waypoint.hidden;
+    const isChecked = waypoint.id !== null && checkedIds.has(waypoint.id);
+
+    return (
+      <div
+        key={waypoint.id}
+        className={`waypoint-panel__row ${isSelected ? 'waypoint-panel__row--selected' : ''} ${isNavigating ? 'waypoint-panel__row--navigating' : ''} ${isHidden ? 'waypoint-panel__row--hidden' : ''}`}
+        style={{ paddingLeft: `${0.5 + depth}rem` }}
+        onClick={() => waypoint.id && handleRowClick(waypoint.id)}
+        onDoubleClick={() => waypoint.id && handleRowDoubleClick(waypoint.id)}
+      >
+        <input
+          type="checkbox"
+          className="waypoint-panel__row-check"
+          checked={isChecked}
+          onClick={(e) => e.stopPropagation()}
+          onChange={() => waypoint.id && toggleChecked(waypoint.id)}
+        />
+        <span className={`waypoint-panel__row-icon ${isHidden ? 'waypoint-panel__row-icon--hidden' : ''}`}>
+          {getSymbolIcon(waypoint.symbol)}
+        </span>
+        <span className={`waypoint-panel__row-name ${isHidden ? 'waypoint-panel__row-name--hidden' : ''}`}>{waypoint.name}</span>
+        <span className={`waypoint-panel__row-desc ${isHidden ? 'waypoint-panel__row-desc--hidden' : ''}`} title={waypoint.description || ''}>
+          {waypoint.description || ''}
+        </span>
+        {nav && (
+          <>
+            <span className="waypoint-panel__row-dist">{nav.distance}</span>
+            <span className="waypoint-panel__row-bearing">{nav.bearing}</span>
+          </>
+        )}
+        {isNavigating && (
+          <span className="waypoint-panel__row-nav-indicator" title="Navigating">
+            <svg width="10" height="10" viewBox="0 0 24 24" fill="currentColor" stroke="none">
+              <polygon points="3 11 22 2 13 21 11 13 3 11" />
+            </svg>
+          </span>
+        )}
+        <button
+          className="waypoint-panel__row-hide-btn"
+          onClick={(e) => {
+            e.stopPropagation();
+            if (waypoint.id) toggleWaypointHidden(waypoint.id);
+          }}
+          title={isHidden ? 'Show on map' : 'Hide from map'}
+        >
+          <VisibilityIcon hidden={isHidden} />
+        </button>
+      </div>
+    );
+  };
+
+  // Waypoints directly in a group (null for ungrouped), in the current sort order
+  const waypointsIn = (groupId: number | null) =>
+    filteredWaypoints.filter((w) => (w.group_id ?? null) === groupId);
+
+  // Number of listed waypoints in a group and everything nested under it
+  const countIn = (groupId: number): number =>
+    waypointsIn(groupId).length +
+    groups.filter((g) => g.parent_id === groupId).reduce((sum, g) => sum + countIn(g.id!), 0);
+
+  const renderGroup = (group: WaypointGroup, depth: number): React.ReactNode => {
+    const count = countIn(group.id!);
+    // While searching, only groups with matches are listed
+    if (searchQuery.trim() && count === 0) return null;
+    const collapsed = collapsedGroups.has(group.id!);
+    const hiddenByParent = !group.hidden && hiddenGroupIds.has(group.id!);
+
+    return (
+      <div key={`group-${group.id}`} className="waypoint-panel__group">
+        <div
+          className={`waypoint-panel__group-header ${group.hidden || hiddenByParent ? 'waypoint-panel__row--hidden' : ''}`}
+          style={{ paddingLeft: `${0.25 + depth}rem` }}
+          onClick={() => toggleCollapsed(group.id!)}
+        >
+          <span className="waypoint-panel__group-toggle">{collapsed ? '▸' : '▾'}</span>
+          <span className="waypoint-panel__group-name">📁 {group.name}</span>
+          <span className="waypoint-panel__count">({count})</span>
+          <button
+            className="waypoint-panel__row-hide-btn"
+            onClick={(e) => {
+              e.stopPropagation();
+              runAction(() => toggleGroupHidden(group));
+            }}
+            title={group.hidden ? 'Show group on map' : hiddenByParent ? 'Hidden by a parent group' : 'Hide group from map'}
+          >
+            <VisibilityIcon hidden={group.hidden} />
+          </button>
+          <button
+            className="waypoint-panel__group-btn"
+            onClick={(e) => {
+              e.stopPropagation();
+              handleNewGroup(group.id);
+            }}
+            title="New subgroup"
+          >
+            +
+          </button>
+          <button
+            className="waypoint-panel__group-btn"
+            onClick={(e) => {
+              e.stopPropagation();
+              handleRenameGroup(group);
+            }}
+            title="Rename group"
+          >
+            ✎
+          </button>
+          <button
+            className="waypoint-panel__group-btn waypoint-panel__group-btn--danger"
+            onClick={(e) => {
+              e.stopPropagation();
+              handleDeleteGroup(group);
+            }}
+            title="Delete group"
+          >
+            ×
+          </button>
+        </div>
+        {!collapsed && (
+          <>
+            {groups.filter((g) => g.parent_id === group.id).map((child) => renderGroup(child, depth + 1))}
+            {waypointsIn(group.id).map((waypoint) => renderWaypointRow(waypoint, depth + 1))}
+          </>
+        )}
+      </div>
+    );
+  };
+
   // Don't render in browser mode
   if (!isTauri()) {
     return (
@@ -394,6 +631,15 @@ export function WaypointPanel({
                 </svg>
                 Add
               </button>
+              <button
+                className="waypoint-panel__action-btn"
+                onClick={() => handleNewGroup(null)}
+                title="New group"
+              >
+                <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+                  <path d="M22 19a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V5a2 2 0 0 1 2-2h5l2 3h9a2 2 0 0 1 2 2z" />
+                </svg>
+              </button>
               <div className="waypoint-panel__action-divider" />
               <button
                 className="waypoint-panel__action-btn"
@@ -475,6 +721,50 @@ export function WaypointPanel({
               </button>
             </div>
 
+            {/* Bulk actions on ticked waypoints */}
+            {checked.length > 0 && (
+              <div className="waypoint-panel__bulk">
+                <span className="waypoint-panel__bulk-count">{checked.length} selected</span>
+                <select value="" onChange={(e) => handleBulkMove(e.target.value)} title="Move to group">
+                  <option value="">Move to...</option>
+                  <option value="none">No group</option>
+                  {[...groupPaths.entries()]
+                    .sort(([, a], [, b]) => a.localeCompare(b))
+                    .map(([id, path]) => (
+                      <option key={id} value={id}>{path}</option>
+                    ))}
+                </select>
+                <select
+                  value=""
+                  onChange={(e) => e.target.value && runAction(() => setWaypointsSymbol(checked, e.target.value))}
+                  title="Set symbol"
+                >
+                  <option value="">Symbol...</option>
+                  {WAYPOINT_SYMBOLS.map((symbol) => (
+                    <option key={symbol.id} value={symbol.id}>{symbol.icon} {symbol.label}</option>
+                  ))}
+                </select>
+                <button className="waypoint-panel__action-btn" onClick={() => runAction(() => setWaypointsHidden(checked, false))}>
+                  Show
+                </button>
+                <button className="waypoint-panel__action-btn" onClick={() => runAction(() => setWaypointsHidden(checked, true))}>
+                  Hide
+                </button>
+                <button className="waypoint-panel__action-btn" onClick={() => runAction(() => setWaypointsShowLabel(checked, true))}>
+                  Labels on
+                </button>
+                <button className="waypoint-panel__action-btn" onClick={() => runAction(() => setWaypointsShowLabel(checked, false))}>
+                  Labels off
+                </button>
+                <button className="waypoint-panel__action-btn waypoint-panel__action-btn--danger" onClick={handleBulkDelete}>
+                  Delete
+                </button>
+                <button className="waypoint-panel__action-btn" onClick={() => setCheckedIds(new Set())} title="Clear selection">
+                  ×
+                </button>
+              </div>
+            )}
+
             {/* Sort options */}
             <div className="waypoint-panel__sort">
               <button
@@ -498,68 +788,14 @@ export function WaypointPanel({
             </div>
 
             {/* Waypoint list */}
-            {filteredWaypoints.length === 0 ? (
+            {filteredWaypoints.length === 0 && (groups.length === 0 || searchQuery.trim()) ? (
               <p className="waypoint-panel__empty">
                 {waypoints.length === 0 ? 'No waypoints yet' : 'No matching waypoints'}
               </p>
             ) : (
               <div className="waypoint-panel__list">
-                {filteredWaypoints.map((waypoint) => {
-                  const nav = getDistanceBearing(waypoint.id);
-                  const isSelected = state.selectedWaypointId === waypoint.id;
-                  const isNavigating = activeWaypointId === waypoint.id;
-                  const isHidden = waypoint.hidden;
-
-                  return (
-                    <div
-                      key={waypoint.id}
-                      className={`waypoint-panel__row ${isSelected ? 'waypoint-panel__row--selected' : ''} ${isNavigating ? 'waypoint-panel__row--navigating' : ''} ${isHidden ? 'waypoint-panel__row--hidden' : ''}`}
-                      onClick={() => waypoint.id && handleRowClick(waypoint.id)}
-                      onDoubleClick={() => waypoint.id && handleRowDoubleClick(waypoint.id)}
-                    >
-                      <span className={`waypoint-panel__row-icon ${isHidden ? 'waypoint-panel__row-icon--hidden' : ''}`}>
-                        {getSymbolIcon(waypoint.symbol)}
-                      </span>
-                      <span className={`waypoint-panel__row-name ${isHidden ? 'waypoint-panel__row-name--hidden' : ''}`}>{waypoint.name}</span>
-                      <span className={`waypoint-panel__row-desc ${isHidden ? 'waypoint-panel__row-desc--hidden' : ''}`} title={waypoint.description || ''}>
-                        {waypoint.description || ''}
-                      </span>
-                      {nav && (
-                        <>
-                          <span className="waypoint-panel__row-dist">{nav.distance}</span>
-                          <span className="waypoint-panel__row-bearing">{nav.bearing}</span>
-                        </>
-                      )}
-                      {isNavigating && (
-                        <span className="waypoint-panel__row-nav-indicator" title="Navigating">
-                          <svg width="10" height="10" viewBox="0 0 24 24" fill="currentColor" stroke="none">
-                            <polygon points="3 11 22 2 13 21 11 13 3 11" />
-                          </svg>
-                        </span>
-                      )}
-                      <button
-                        className="waypoint-panel__row-hide-btn"
-                        onClick={(e) => {
-                          e.stopPropagation();
-                          if (waypoint.id) toggleWaypointHidden(waypoint.id);
-                        }}
-                        title={isHidden ? 'Show on map' : 'Hide from map'}
-                      >
-                        {isHidden ? (
-                          <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
-                            <path d="M17.94 17.94A10.07 10.07 0 0 1 12 20c-7 0-11-8-11-8a18.45 18.45 0 0 1 5.06-5.94M9.9 4.24A9.12 9.12 0 0 1 12 4c7 0 11 8 11 8a18.5 18.5 0 0 1-2.16 3.19m-6.72-1.07a3 3 0 1 1-4.24-4.24" />
-                            <line x1="1" y1="1" x2="23" y2="23" />
-                          </svg>
-                        ) : (
-                          <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
-                            <path d="M1 12s4-8 11-8 11 8 11 8-4 8-11 8-11-8-11-8z" />
-                            <circle cx="12" cy="12" r="3" />
-                          </svg>
-                        )}
-                      </button>
-                    </div>
-                  );
-                })}
+                {groups.filter((g) => g.parent_id === null).map((group) => renderGroup(group, 0))}
+                {waypointsIn(null).map((waypoint) => renderWaypointRow(waypoint, 0))}
               </div>
             )}
           </>
@@ -568,3 +804,17 @@ export function WaypointPanel({
     </div>
   );
 }
+
+function VisibilityIcon({ hidden }: { hidden: boolean }) {
+  return hidden ? (
+    <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+      <path d="M17.94 17.94A10.07 10.07 0 0 1 12 20c-7 0-11-8-11-8a18.45 18.45 0 0 1 5.06-5.94M9.9 4.24A9.12 9.12 0 0 1 12 4c7 0 11 8 11 8a18.5 18.5 0 0 1-2.16 3.19m-6.72-1.07a3 3 0 1 1-4.24-4.24" />
+      <line x1="1" y1="1" x2="23" y2="23" />
+    </svg>
+  ) : (
+    <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+      <path d="M1 12s4-8 11-8 11 8 11 8-4 8-11 8-11-8-11-8z" />
+      <circle cx="12" cy="12" r="3" />
+    </svg>
+  );
+}
@@ -18,6 +18,7 @@ export { RouteNavigationModal } from './RouteNavigationModal';
 export { ShareRouteModal } from './ShareRouteModal';
 export { TrackPanel } from './TrackPanel';
 export { ShareTrackModal } from './ShareTrackModal';
+export { CsvImportModal } from './CsvImportModal';
 export { DeviceRegistration } from './DeviceRegistration';
 export { DeviceStatusIndicator } from './DeviceStatusIndicator';
 export { PackManager } from './PackManager';
@@ -1,7 +1,8 @@
 // Tauri command bindings for VortexNav
 
 import { invoke } from '@tauri-apps/api/core';
-import type { ApiKeys, ThemeMode, BasemapProvider, GebcoStatus, GebcoSettings, BaseNauticalStatus, BaseNauticalSettings, Cm93Status, GeoJsonTile, Cm93Settings, Cm93SettingsBackend, Route, RouteTag, RouteWithWaypoints, RouteStatistics, GpxImportResult, Track, TrackPoint, TrackWithPoints } from '../types';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { ApiKeys, ThemeMode, BasemapProvider, GebcoStatus, GebcoSettings, BaseNauticalStatus, BaseNauticalSettings, Cm93Status, GeoJsonTile, Cm93Settings, Cm93SettingsBackend, Route, RouteTag, RouteWithWaypoints, RouteStatistics, GpxImportResult, CsvImportOptions, CsvPreview, Track, TrackPoint, TrackWithPoints } from '../types';
 
 // ============ Response Types ============
 
@@ -47,10 +48,28 @@ export interface GpsData {
   timestamp: string | null;
   fix_type: string | null;
   satellites_info: SatelliteInfo[];
+  is_estimated: boolean;
+  estimate_uncertainty_nm: number | null;
+  estimated_since: string | null;
+  integrity: GnssIntegrity | null;
+  integrity_warnings: string[];
+  rejected_fixes: number;
+}
+
+export type GnssIntegrity = 'good' | 'degraded' | 'poor' | 'no_fix';
+
+export interface PositionFilterSettings {
+  enabled: boolean;
+  position_alpha: number;
+  velocity_beta: number;
+  max_speed_kn: number;
+  low_speed_kn: number;
+  max_hdop: number;
+  min_satellites: number;
 }
 
 // GPS source types
-export type GpsSourceType = 'serial_port' | 'tcp_stream' | 'simulated';
+export type GpsSourceType = 'serial_port' | 'tcp_stream' | 'simulated' | 'gpsd' | 'signal_k';
 
 // GPS connection status
 export type GpsConnectionStatus =
@@ -75,10 +94,11 @@ export interface GpsSourceConfig {
   id: string;
   name: string;
   source_type: GpsSourceType;
-  port_name: string | null;
+  port_name: string | null; // Serial port, or host:port for gpsd and Signal K
   baud_rate: number;
   enabled: boolean;
   priority: number;
+  raw_nmea?: boolean; // gpsd: pass raw NMEA through to the monitor
 }
 
 // GPS source status
@@ -89,6 +109,113 @@ export interface GpsSourceStatus {
   last_error: string | null;
   sentences_received: number;
   last_fix_time: string | null;
+  bad_checksums: number;
+  unparsed_sentences: number;
+  sentence_stats: SentenceTypeStats[];
+}
+
+// Rate and last-seen time of one talker/sentence type
+export interface SentenceTypeStats {
+  sentence_id: string;
+  talker: string;
+  sentence_type: string;
+  count: number;
+  rate_hz: number;
+  last_seen: string;
+}
+
+// Instrument readings from Signal K (SI converted to knots, degrees, °C, mb)
+export interface InstrumentData {
+  depth_m: number | null;
+  depth_below_keel_m: number | null;
+  water_temp_c: number | null;
+  air_temp_c: number | null;
+  pressure_mb: number | null;
+  humidity_pct: number | null;
+  apparent_wind_angle: number | null;
+  apparent_wind_speed_kn: number | null;
+  true_wind_angle: number | null;
+  true_wind_direction: number | null;
+  true_wind_speed_kn: number | null;
+  speed_through_water_kn: number | null;
+  heading_magnetic: number | null;
+  magnetic_variation: number | null;
+  rate_of_turn: number | null;
+  log_nm: number | null;
+  updated_at: string | null;
+}
+
+// Vessel received over AIS
+export interface AisTarget {
+  id: string;
+  mmsi: string | null;
+  name: string | null;
+  call_sign: string | null;
+  latitude: number | null;
+  longitude: number | null;
+  sog_knots: number | null;
+  cog: number | null;
+  heading: number | null;
+  ship_type: string | null;
+  nav_state: string | null;
+  last_update: string | null;
+}
+
+// Signal K server found by discovery
+export interface SignalKServer {
+  address: string;
+  server_id: string | null;
+  server_version: string | null;
+  stream_url: string;
+}
+
+// Embedded NMEA 0183 / Signal K server for other devices on the boat network
+export interface DataServerSettings {
+  enabled: boolean;
+  bind_address: string;
+  nmea_port: number;
+  signalk_port: number;
+}
+
+export interface DataServerStatus {
+  running: boolean;
+  nmea_address: string | null;
+  signalk_address: string | null;
+  nmea_clients: number;
+  signalk_clients: number;
+  last_error: string | null;
+}
+
+// Topics pushed by the backend instead of polling
+export type GpsEventTopic = 'gps:position' | 'gps:satellites' | 'gps:status' | 'navigation:progress';
+
+// Every topic event carries a per-topic sequence number; a gap means events were dropped
+export interface GpsEvent<T> {
+  topic: GpsEventTopic;
+  sequence: number;
+  timestamp: string;
+  data: T;
+}
+
+// Payload of gps:satellites
+export interface SatelliteUpdate {
+  satellites: number | null;
+  hdop: number | null;
+  vdop: number | null;
+  pdop: number | null;
+  satellites_info: SatelliteInfo[];
+}
+
+// Payload of navigation:progress (null when no route is being followed)
+export interface RouteProgress {
+  next_index: number;
+  next_waypoint: Waypoint;
+  previous_waypoint: Waypoint | null;
+  distance_nm: number;
+  bearing: number;
+  cross_track_nm: number | null; // Positive when right of the leg
+  vmg_kn: number | null;
+  remaining_nm: number;
 }
 
 // Waypoint definition
@@ -102,6 +229,42 @@ export interface Waypoint {
   show_label: boolean;
   hidden: boolean;
   created_at: string | null;
+  group_id?: number | null; // Folder in the waypoint list, local to this device
+  arrival_radius_nm?: number | null; // Counts as reached within this distance; default when unset
+  depth_m?: number | null;
+  temperature_c?: number | null;
+}
+
+// Waypoint folder; groups nest through parent_id and hiding one hides everything inside
+export interface WaypointGroup {
+  id: number | null;
+  name: string;
+  parent_id: number | null;
+  hidden: boolean;
+  created_at: string | null;
+}
+
+// Search over waypoints, routes and tracks. For routes and tracks lat/lon is
+// the point closest to the search origin, and null when they have no points.
+export type SearchKind = 'waypoint' | 'route' | 'track';
+
+export interface SearchResult {
+  kind: SearchKind;
+  id: number;
+  name: string;
+  description: string | null;
+  lat: number | null;
+  lon: number | null;
+  distance_nm: number | null; // From the search origin, when one was given
+  bearing: number | null;
+}
+
+// min_lon is greater than max_lon for a box across the antimeridian
+export interface BoundingBox {
+  min_lat: number;
+  min_lon: number;
+  max_lat: number;
+  max_lon: number;
 }
 
 // MBTiles metadata
@@ -123,6 +286,92 @@ export interface ChartInfo {
   metadata: MBTilesMetadata;
 }
 
+// User data backup archive
+export interface ArchiveManifest {
+  version: number;
+  app_version: string;
+  exported_at: string;
+  waypoints: number;
+  routes: number;
+  tracks: number;
+}
+
+export type ImportMode = 'merge' | 'replace';
+
+export interface ChangeCounts {
+  added: number;
+  updated: number;
+  unchanged: number;
+  removed: number;
+}
+
+export interface ImportReport {
+  mode: ImportMode;
+  dry_run: boolean;
+  exported_at: string;
+  backup_path: string | null;
+  settings: ChangeCounts;
+  waypoints: ChangeCounts;
+  waypoint_groups: ChangeCounts;
+  route_tags: ChangeCounts;
+  routes: ChangeCounts;
+  tracks: ChangeCounts;
+  gps_sources: ChangeCounts;
+  chart_layers: ChangeCounts;
+  chart_metadata: ChangeCounts;
+  catalogs: ChangeCounts;
+}
+
+// Sync of routes, waypoints, tags and tracks with paired devices on the local network
+export interface SyncSettings {
+  enabled: boolean;
+  device_name: string;
+  // 0.0.0.0 listens on every network the device joins
+  bind_address: string;
+  port: number;
+}
+
+export interface PairingCode {
+  code: string;
+  expires_in_secs: number;
+}
+
+export interface SyncStatus {
+  running: boolean;
+  address: string | null;
+  pairing_code: PairingCode | null;
+}
+
+export interface SyncPeer {
+  device_id: string;
+  name: string;
+  address: string | null;
+  last_sent_seq: number;
+  last_received_seq: number;
+  last_synced_at: string | null;
+  paired_at: string | null;
+}
+
+export type SyncTable = 'route_tags' | 'waypoints' | 'routes' | 'tracks';
+
+// Object edited on both devices since they last synced; the newer edit is kept
+export interface SyncConflict {
+  table: SyncTable;
+  uuid: string;
+  name: string;
+  local_updated_at: string;
+  remote_updated_at: string;
+  kept: 'local' | 'remote';
+}
+
+export interface SyncReport {
+  peer: string;
+  sent: number;
+  received: number;
+  conflicts: SyncConflict[];
+  errors: string[];
+}
+
 // ============ Settings Commands ============
 
 export async function getSettings(): Promise<BackendSettings> {
@@ -408,6 +657,29 @@ export async function getGpsStatus(): Promise<GpsSourceStatus> {
   return result.data;
 }
 
+// Subscribe to one event topic. onDrop is told how many events were missed
+// whenever the sequence skips.
+export async function subscribeGpsEvents<T>(
+  topic: GpsEventTopic,
+  onData: (data: T, event: GpsEvent<T>) => void,
+  onDrop?: (missed: number) => void
+): Promise<UnlistenFn> {
+  let lastSequence: number | null = null;
+  return listen<GpsEvent<T>>(topic, (event) => {
+    const { sequence } = event.payload;
+    if (lastSequence !== null && sequence > lastSequence + 1) {
+      const missed = sequence - lastSequence - 1;
+      if (onDrop) {
+        onDrop(missed);
+      } else {
+        console.warn(`Missed ${missed} ${topic} event(s)`);
+      }
+    }
+    lastSequence = sequence;
+    onData(event.payload.data, event.payload);
+  });
+}
+
 export async function listSerialPorts(): Promise<DetectedPort[]> {
   const result = await invoke<CommandResult<DetectedPort[]>>('list_serial_ports');
   if (!result.success || !result.data) {
@@ -470,6 +742,30 @@ export async function stopGps(): Promise<void> {
   }
 }
 
+export async function discoverSignalKServer(address: string): Promise<SignalKServer> {
+  const result = await invoke<CommandResult<SignalKServer>>('discover_signalk_server', { address });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'No Signal K server found');
+  }
+  return result.data;
+}
+
+export async function getInstrumentData(): Promise<InstrumentData> {
+  const result = await invoke<CommandResult<InstrumentData>>('get_instrument_data');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get instrument data');
+  }
+  return result.data;
+}
+
+export async function getAisTargets(): Promise<AisTarget[]> {
+  const result = await invoke<CommandResult<AisTarget[]>>('get_ais_targets');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get AIS targets');
+  }
+  return result.data;
+}
+
 export async function getNmeaBuffer(): Promise<string[]> {
   const result = await invoke<CommandResult<string[]>>('get_nmea_buffer');
   if (!result.success || !result.data) {
@@ -485,6 +781,40 @@ export async function clearNmeaBuffer(): Promise<void> {
   }
 }
 
+// ============ Data Server Commands ============
+
+export async function getDataServerSettings(): Promise<DataServerSettings> {
+  const result = await invoke<CommandResult<DataServerSettings>>('get_data_server_settings');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get data server settings');
+  }
+  return result.data;
+}
+
+export async function saveDataServerSettings(settings: DataServerSettings): Promise<DataServerStatus> {
+  const result = await invoke<CommandResult<DataServerStatus>>('save_data_server_settings', { settings });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to save data server settings');
+  }
+  return result.data;
+}
+
+export async function getDataServerStatus(): Promise<DataServerStatus> {
+  const result = await invoke<CommandResult<DataServerStatus>>('get_data_server_status');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get data server status');
+  }
+  return result.data;
+}
+
+// Route and next waypoint shared with other devices (null route clears it)
+export async function setNavigationTarget(routeId: number | null, waypointIndex: number): Promise<void> {
+  const result = await invoke<CommandResult<unknown>>('set_navigation_target', { routeId, waypointIndex });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to set navigation target');
+  }
+}
+
 // ============ Waypoint Commands ============
 
 export async function getWaypoints(): Promise<Waypoint[]> {
@@ -537,6 +867,111 @@ export async function toggleWaypointHidden(id: number, hidden: boolean): Promise
   }
 }
 
+/**
+ * Move waypoints into a group, or out of all groups with null
+ */
+export async function moveWaypointsToGroup(ids: number[], groupId: number | null): Promise<void> {
+  const result = await invoke<CommandResult<null>>('move_waypoints_to_group', { ids, groupId });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to move waypoints');
+  }
+}
+
+export async function setWaypointsHidden(ids: number[], hidden: boolean): Promise<void> {
+  const result = await invoke<CommandResult<null>>('set_waypoints_hidden', { ids, hidden });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to change waypoint visibility');
+  }
+}
+
+export async function setWaypointsShowLabel(ids: number[], showLabel: boolean): Promise<void> {
+  const result = await invoke<CommandResult<null>>('set_waypoints_show_label', { ids, showLabel });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to change waypoint labels');
+  }
+}
+
+export async function setWaypointsSymbol(ids: number[], symbol: string | null): Promise<void> {
+  const result = await invoke<CommandResult<null>>('set_waypoints_symbol', { ids, symbol });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to change waypoint symbols');
+  }
+}
+
+/**
+ * Delete several waypoints; like deleteWaypoint, each is removed from its routes
+ */
+export async function deleteWaypoints(ids: number[]): Promise<void> {
+  const result = await invoke<CommandResult<null>>('delete_waypoints', { ids });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to delete waypoints');
+  }
+}
+
+// ============ Waypoint Group Commands ============
+
+export async function getWaypointGroups(): Promise<WaypointGroup[]> {
+  const result = await invoke<CommandResult<WaypointGroup[]>>('get_waypoint_groups');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get waypoint groups');
+  }
+  return result.data;
+}
+
+/**
+ * @returns The new group ID
+ */
+export async function createWaypointGroup(name: string, parentId: number | null): Promise<number> {
+  const result = await invoke<CommandResult<number>>('create_waypoint_group', {
+    group: { id: null, name, parent_id: parentId, hidden: false, created_at: null },
+  });
+  if (!result.success || result.data === null || result.data === undefined) {
+    throw new Error(result.error || 'Failed to create waypoint group');
+  }
+  return result.data;
+}
+
+/**
+ * Rename a group or move it under another group
+ */
+export async function updateWaypointGroup(group: WaypointGroup): Promise<void> {
+  const result = await invoke<CommandResult<null>>('update_waypoint_group', { group });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to update waypoint group');
+  }
+}
+
+export async function toggleWaypointGroupHidden(id: number, hidden: boolean): Promise<void> {
+  const result = await invoke<CommandResult<null>>('toggle_waypoint_group_hidden', { id, hidden });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to toggle waypoint group visibility');
+  }
+}
+
+/**
+ * Count the waypoints deleting a group would remove (those no route uses)
+ */
+export async function getWaypointGroupExclusiveWaypointCount(id: number): Promise<number> {
+  const result = await invoke<CommandResult<number>>('get_waypoint_group_exclusive_waypoint_count', { id });
+  if (!result.success || result.data === null || result.data === undefined) {
+    throw new Error(result.error || 'Failed to get exclusive waypoint count');
+  }
+  return result.data;
+}
+
+/**
+ * Delete a group and its subgroups, optionally with their waypoints. Waypoints
+ * used by routes are kept and move to the parent group.
+ * @returns The IDs of waypoints that were deleted
+ */
+export async function deleteWaypointGroup(id: number, deleteWaypoints: boolean): Promise<number[]> {
+  const result = await invoke<CommandResult<number[]>>('delete_waypoint_group', { id, deleteWaypoints });
+  if (!result.success || result.data === null || result.data === undefined) {
+    throw new Error(result.error || 'Failed to delete waypoint group');
+  }
+  return result.data;
+}
+
 // ============ Route Commands ============
 
 /**
@@ -747,6 +1182,93 @@ export async function calculateRouteStatistics(
   return result.data;
 }
 
+// ============ Search Commands ============
+
+/**
+ * Find the nearest waypoints, routes and tracks to a position
+ * @param kinds - Object types to search; all when omitted
+ */
+export async function searchNearest(
+  lat: number,
+  lon: number,
+  count: number,
+  kinds?: SearchKind[]
+): Promise<SearchResult[]> {
+  const result = await invoke<CommandResult<SearchResult[]>>('search_nearest', {
+    lat,
+    lon,
+    count,
+    kinds: kinds ?? null,
+  });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to search nearby');
+  }
+  return result.data;
+}
+
+/**
+ * Find everything within a radius of a position, nearest first
+ */
+export async function searchWithinRadius(
+  lat: number,
+  lon: number,
+  radiusNm: number,
+  kinds?: SearchKind[]
+): Promise<SearchResult[]> {
+  const result = await invoke<CommandResult<SearchResult[]>>('search_within_radius', {
+    lat,
+    lon,
+    radiusNm,
+    kinds: kinds ?? null,
+  });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to search within radius');
+  }
+  return result.data;
+}
+
+/**
+ * Find everything inside a box, with distance and bearing from origin when given
+ */
+export async function searchWithinBbox(
+  bbox: BoundingBox,
+  origin?: { lat: number; lon: number },
+  kinds?: SearchKind[]
+): Promise<SearchResult[]> {
+  const result = await invoke<CommandResult<SearchResult[]>>('search_within_bbox', {
+    bbox,
+    originLat: origin?.lat ?? null,
+    originLon: origin?.lon ?? null,
+    kinds: kinds ?? null,
+  });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to search area');
+  }
+  return result.data;
+}
+
+/**
+ * Search names and descriptions; each word matches as a prefix
+ */
+export async function searchText(
+  query: string,
+  origin?: { lat: number; lon: number },
+  kinds?: SearchKind[],
+  limit?: number
+): Promise<SearchResult[]> {
+  const result = await invoke<CommandResult<SearchResult[]>>('search_text', {
+    query,
+    originLat: origin?.lat ?? null,
+    originLon: origin?.lon ?? null,
+    kinds: kinds ?? null,
+    limit: limit ?? null,
+  });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to search');
+  }
+  return result.data;
+}
+
 // ============ GPX Import/Export Commands ============
 
 /**
@@ -802,6 +1324,204 @@ export async function getRouteSummaryText(routeId: number): Promise<string> {
   return result.data;
 }
 
+// ============ KML and GeoJSON Commands ============
+
+/**
+ * Import a KML or KMZ file, creating routes and waypoints like a GPX import
+ */
+export async function importKml(filePath: string): Promise<GpxImportResult> {
+  const result = await invoke<CommandResult<GpxImportResult>>('import_kml', { filePath });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to import KML file');
+  }
+  return result.data;
+}
+
+/**
+ * Import a GeoJSON file, creating routes and waypoints like a GPX import
+ */
+export async function importGeojson(filePath: string): Promise<GpxImportResult> {
+  const result = await invoke<CommandResult<GpxImportResult>>('import_geojson', { filePath });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to import GeoJSON file');
+  }
+  return result.data;
+}
+
+/**
+ * Export routes, waypoints and tracks to KML, zipped as KMZ when the path ends in .kmz
+ */
+export async function exportKml(
+  filePath: string,
+  ids: { routeIds?: number[]; waypointIds?: number[]; trackIds?: number[] }
+): Promise<void> {
+  const result = await invoke<CommandResult<null>>('export_kml', {
+    routeIds: ids.routeIds ?? [],
+    waypointIds: ids.waypointIds ?? [],
+    trackIds: ids.trackIds ?? [],
+    filePath,
+  });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to export KML');
+  }
+}
+
+/**
+ * Export routes, waypoints and tracks to a GeoJSON file
+ */
+export async function exportGeojson(
+  filePath: string,
+  ids: { routeIds?: number[]; waypointIds?: number[]; trackIds?: number[] }
+): Promise<void> {
+  const result = await invoke<CommandResult<null>>('export_geojson', {
+    routeIds: ids.routeIds ?? [],
+    waypointIds: ids.waypointIds ?? [],
+    trackIds: ids.trackIds ?? [],
+    filePath,
+  });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to export GeoJSON');
+  }
+}
+
+// ============ OpenCPN Import Commands ============
+
+/**
+ * Import OpenCPN's navobj.xml or a GPX file exported from OpenCPN.
+ * Objects already imported are updated, so this can be run again later.
+ */
+export async function importOpenCpn(filePath: string): Promise<GpxImportResult> {
+  const result = await invoke<CommandResult<GpxImportResult>>('import_opencpn', { filePath });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to import OpenCPN data');
+  }
+  return result.data;
+}
+
+// ============ CSV Import Commands ============
+
+/**
+ * Read a CSV, TSV or spreadsheet waypoint list without importing it.
+ * Shows the detected layout and each row's position or why it can't be read.
+ */
+export async function previewCsvImport(filePath: string, options: CsvImportOptions = {}): Promise<CsvPreview> {
+  const result = await invoke<CommandResult<CsvPreview>>('preview_csv_import', { filePath, options });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to read waypoint list');
+  }
+  return result.data;
+}
+
+/**
+ * Import a CSV, TSV or spreadsheet waypoint list as waypoints, or as a route
+ * through the rows in order
+ */
+export async function importCsv(filePath: string, options: CsvImportOptions = {}): Promise<GpxImportResult> {
+  const result = await invoke<CommandResult<GpxImportResult>>('import_csv', { filePath, options });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to import waypoint list');
+  }
+  return result.data;
+}
+
+// ============ User Data Backup Commands ============
+
+/**
+ * Export all user data to a backup archive
+ */
+export async function exportUserData(filePath: string): Promise<ArchiveManifest> {
+  const result = await invoke<CommandResult<ArchiveManifest>>('export_user_data', { filePath });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to export user data');
+  }
+  return result.data;
+}
+
+/**
+ * Restore user data from a backup archive; with dryRun only report what would change
+ */
+export async function importUserData(filePath: string, mode: ImportMode, dryRun: boolean): Promise<ImportReport> {
+  const result = await invoke<CommandResult<ImportReport>>('import_user_data', { filePath, mode, dryRun });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to import user data');
+  }
+  return result.data;
+}
+
+// ============ Device Sync Commands ============
+
+export async function getSyncSettings(): Promise<SyncSettings> {
+  const result = await invoke<CommandResult<SyncSettings>>('get_sync_settings');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get sync settings');
+  }
+  return result.data;
+}
+
+export async function saveSyncSettings(settings: SyncSettings): Promise<SyncStatus> {
+  const result = await invoke<CommandResult<SyncStatus>>('save_sync_settings', { settings });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to save sync settings');
+  }
+  return result.data;
+}
+
+export async function getSyncStatus(): Promise<SyncStatus> {
+  const result = await invoke<CommandResult<SyncStatus>>('get_sync_status');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get sync status');
+  }
+  return result.data;
+}
+
+/**
+ * Show a pairing code for another device to enter
+ */
+export async function startSyncPairing(): Promise<PairingCode> {
+  const result = await invoke<CommandResult<PairingCode>>('start_sync_pairing');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to start pairing');
+  }
+  return result.data;
+}
+
+/**
+ * Pair with the device at address (host:port) using the code it shows
+ */
+export async function pairSyncDevice(address: string, code: string): Promise<SyncPeer> {
+  const result = await invoke<CommandResult<SyncPeer>>('pair_sync_device', { address, code });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to pair device');
+  }
+  return result.data;
+}
+
+export async function getSyncPeers(): Promise<SyncPeer[]> {
+  const result = await invoke<CommandResult<SyncPeer[]>>('get_sync_peers');
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to get paired devices');
+  }
+  return result.data;
+}
+
+export async function removeSyncPeer(deviceId: string): Promise<void> {
+  const result = await invoke<CommandResult<null>>('remove_sync_peer', { deviceId });
+  if (!result.success) {
+    throw new Error(result.error || 'Failed to remove paired device');
+  }
+}
+
+/**
+ * Exchange changes with a paired device
+ */
+export async function syncWithDevice(deviceId: string): Promise<SyncReport> {
+  const result = await invoke<CommandResult<SyncReport>>('sync_with_device', { deviceId });
+  if (!result.success || !result.data) {
+    throw new Error(result.error || 'Failed to sync');
+  }
+  return result.data;
+}
+
 // ============ Track Recording Commands ============
 
 /**
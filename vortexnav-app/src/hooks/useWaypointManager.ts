@@ -24,16 +24,28 @@ import {
   updateWaypointPosition,
   deleteWaypoint as deleteWaypointApi,
   toggleWaypointHidden as toggleWaypointHiddenApi,
+  moveWaypointsToGroup as moveWaypointsToGroupApi,
+  setWaypointsHidden as setWaypointsHiddenApi,
+  setWaypointsShowLabel as setWaypointsShowLabelApi,
+  setWaypointsSymbol as setWaypointsSymbolApi,
+  deleteWaypoints as deleteWaypointsApi,
+  getWaypointGroups,
+  createWaypointGroup,
+  updateWaypointGroup,
+  toggleWaypointGroupHidden,
+  deleteWaypointGroup,
   isTauri,
   type Waypoint,
+  type WaypointGroup,
 } from './useTauri';
 
-// Re-export Waypoint type for consumers
-export type { Waypoint };
+// Re-export Waypoint types for consumers
+export type { Waypoint, WaypointGroup };
 
 // Define WaypointManagerState locally to avoid circular import
 export interface WaypointManagerState {
   waypoints: Waypoint[];
+  groups: WaypointGroup[];
   activeWaypointId: number | null;
   selectedWaypointId: number | null;
   editState: WaypointEditState;
@@ -48,6 +60,7 @@ export interface WaypointManagerState {
 type WaypointAction =
   | { type: 'LOAD_START' }
   | { type: 'LOAD_SUCCESS'; payload: Waypoint[] }
+  | { type: 'GROUPS_LOADED'; payload: WaypointGroup[] }
   | { type: 'LOAD_ERROR'; payload: string }
   | { type: 'START_CREATE'; payload?: { lat: number; lon: number } }
   | { type: 'START_EDIT'; payload: { waypointId: number; waypoint: Waypoint } }
@@ -65,6 +78,7 @@ type WaypointAction =
   | { type: 'SET_ACTIVE'; payload: number | null }
   | { type: 'SET_SELECTED'; payload: number | null }
   | { type: 'DELETE_SUCCESS'; payload: { deletedId: number; waypoints: Waypoint[] } }
+  | { type: 'BULK_DELETE_SUCCESS'; payload: { deletedIds: number[]; waypoints: Waypoint[] } }
   | { type: 'TOGGLE_ALL_LABELS' }
   | { type: 'TOGGLE_ALL_MARKERS' }
   | { type: 'TOGGLE_HIDDEN_SUCCESS'; payload: Waypoint[] };
@@ -81,6 +95,7 @@ const initialEditState: WaypointEditState = {
 
 const initialState: WaypointManagerState = {
   waypoints: [],
+  groups: [],
   activeWaypointId: null,
   selectedWaypointId: null,
   editState: initialEditState,
@@ -131,6 +146,9 @@ function waypointReducer(
         isLoading: false,
       };
 
+    case 'GROUPS_LOADED':
+      return { ...state, groups: action.payload };
+
     case 'LOAD_ERROR':
       console.error('Failed to load waypoints:', action.payload);
       return { ...state, isLoading: false };
@@ -279,6 +297,22 @@ function waypointReducer(
             : state.activeWaypointId,
       };
 
+    case 'BULK_DELETE_SUCCESS': {
+      const deleted = new Set(action.payload.deletedIds);
+      return {
+        ...state,
+        waypoints: action.payload.waypoints,
+        selectedWaypointId:
+          state.selectedWaypointId !== null && deleted.has(state.selectedWaypointId)
+            ? null
+            : state.selectedWaypointId,
+        activeWaypointId:
+          state.activeWaypointId !== null && deleted.has(state.activeWaypointId)
+            ? null
+            : state.activeWaypointId,
+      };
+    }
+
     case 'TOGGLE_ALL_LABELS':
       return {
         ...state,
@@ -331,6 +365,34 @@ export function useWaypointManager() {
     [state.waypoints, state.editState.waypointId]
   );
 
+  // Groups that are hidden themselves or sit inside a hidden group
+  const hiddenGroupIds = useMemo(() => {
+    const byId = new Map(state.groups.map((g) => [g.id, g]));
+    const hidden = new Set<number>();
+    for (const group of state.groups) {
+      let current: WaypointGroup | undefined = group;
+      for (let depth = 0; current && depth <= state.groups.length; depth++) {
+        if (current.hidden) {
+          hidden.add(group.id!);
+          break;
+        }
+        current = current.parent_id !== null ? byId.get(current.parent_id) : undefined;
+      }
+    }
+    return hidden;
+  }, [state.groups]);
+
+  // Waypoints as drawn on the map: those in hidden groups count as hidden
+  const mapWaypoints = useMemo(
+    () =>
+      hiddenGroupIds.size === 0
+        ? state.waypoints
+        : state.waypoints.map((w) =>
+            w.group_id != null && hiddenGroupIds.has(w.group_id) ? { ...w, hidden: true } : w
+          ),
+    [state.waypoints, hiddenGroupIds]
+  );
+
   const isEditing = state.editState.status === 'editing' || state.editState.status === 'creating';
   const isSaving = state.editState.status === 'saving';
 
@@ -341,7 +403,8 @@ export function useWaypointManager() {
 
     dispatch({ type: 'LOAD_START' });
     try {
-      const waypoints = await getWaypoints();
+      const [waypoints, groups] = await Promise.all([getWaypoints(), getWaypointGroups()]);
+      dispatch({ type: 'GROUPS_LOADED', payload: groups });
       dispatch({ type: 'LOAD_SUCCESS', payload: waypoints });
     } catch (error) {
       dispatch({ type: 'LOAD_ERROR', payload: String(error) });
@@ -412,6 +475,9 @@ export function useWaypointManager() {
           show_label: editState.formData.showLabel,
           hidden: original.hidden, // Preserve hidden state when editing
           created_at: original.created_at,
+          arrival_radius_nm: original.arrival_radius_nm ?? null,
+          depth_m: original.depth_m ?? null,
+          temperature_c: original.temperature_c ?? null,
         });
       }
 
@@ -480,6 +546,87 @@ export function useWaypointManager() {
     }
   }, []);
 
+  // ============ Bulk Actions ============
+
+  const runBulk = useCallback(async (action: () => Promise<unknown>, description: string) => {
+    if (!isTauri()) return;
+
+    try {
+      await action();
+      const [waypoints, groups] = await Promise.all([getWaypoints(), getWaypointGroups()]);
+      dispatch({ type: 'GROUPS_LOADED', payload: groups });
+      dispatch({ type: 'LOAD_SUCCESS', payload: waypoints });
+    } catch (error) {
+      console.error(`Failed to ${description}:`, error);
+      throw error;
+    }
+  }, []);
+
+  const moveWaypointsToGroup = useCallback(
+    (ids: number[], groupId: number | null) => runBulk(() => moveWaypointsToGroupApi(ids, groupId), 'move waypoints'),
+    [runBulk]
+  );
+
+  const setWaypointsHidden = useCallback(
+    (ids: number[], hidden: boolean) => runBulk(() => setWaypointsHiddenApi(ids, hidden), 'change waypoint visibility'),
+    [runBulk]
+  );
+
+  const setWaypointsShowLabel = useCallback(
+    (ids: number[], showLabel: boolean) => runBulk(() => setWaypointsShowLabelApi(ids, showLabel), 'change waypoint labels'),
+    [runBulk]
+  );
+
+  const setWaypointsSymbol = useCallback(
+    (ids: number[], symbol: string) => runBulk(() => setWaypointsSymbolApi(ids, symbol), 'change waypoint symbols'),
+    [runBulk]
+  );
+
+  const deleteWaypoints = useCallback(async (ids: number[]) => {
+    if (!isTauri()) return;
+
+    try {
+      await deleteWaypointsApi(ids);
+      const freshWaypoints = await getWaypoints();
+      dispatch({ type: 'BULK_DELETE_SUCCESS', payload: { deletedIds: ids, waypoints: freshWaypoints } });
+    } catch (error) {
+      console.error('Failed to delete waypoints:', error);
+    }
+  }, []);
+
+  // ============ Group Actions ============
+
+  const createGroup = useCallback(
+    (name: string, parentId: number | null) => runBulk(() => createWaypointGroup(name, parentId), 'create group'),
+    [runBulk]
+  );
+
+  const updateGroup = useCallback(
+    (group: WaypointGroup) => runBulk(() => updateWaypointGroup(group), 'update group'),
+    [runBulk]
+  );
+
+  const toggleGroupHidden = useCallback(
+    (group: WaypointGroup) => runBulk(() => toggleWaypointGroupHidden(group.id!, !group.hidden), 'toggle group visibility'),
+    [runBulk]
+  );
+
+  /**
+   * Delete a group and its subgroups. Waypoints used by routes are always kept.
+   */
+  const deleteGroup = useCallback(async (groupId: number, deleteWaypoints: boolean) => {
+    if (!isTauri()) return;
+
+    try {
+      const deletedIds = await deleteWaypointGroup(groupId, deleteWaypoints);
+      const [waypoints, groups] = await Promise.all([getWaypoints(), getWaypointGroups()]);
+      dispatch({ type: 'GROUPS_LOADED', payload: groups });
+      dispatch({ type: 'BULK_DELETE_SUCCESS', payload: { deletedIds, waypoints } });
+    } catch (error) {
+      console.error('Failed to delete group:', error);
+    }
+  }, []);
+
   // ============ Drag Handlers ============
 
   const startDrag = useCallback((waypointId: number, lat: number, lon: number) => {
@@ -555,6 +702,8 @@ export function useWaypointManager() {
 
     // Derived state
     selectedWaypoint,
+    hiddenGroupIds,
+    mapWaypoints,
     activeWaypoint,
     editingWaypoint,
     editingPreview,
@@ -577,6 +726,17 @@ export function useWaypointManager() {
     toggleAllMarkers,
     toggleWaypointHidden,
 
+    // Bulk and group actions
+    moveWaypointsToGroup,
+    setWaypointsHidden,
+    setWaypointsShowLabel,
+    setWaypointsSymbol,
+    deleteWaypoints,
+    createGroup,
+    updateGroup,
+    toggleGroupHidden,
+    deleteGroup,
+
     // Drag actions
     startDrag,
     moveDrag,
@@ -580,6 +580,7 @@ export interface Route {
   estimated_speed_kn: number;  // For ETA calculations (default 5.0)
   created_at: string | null;
   updated_at: string | null;
+  uuid?: string | null;  // Stable id shared across devices and exports
 }
 
 // Route tag for categorization
@@ -588,6 +589,8 @@ export interface RouteTag {
   name: string;
   color: string | null;  // Tag badge color
   created_at: string | null;
+  uuid?: string | null;
+  updated_at?: string | null;
 }
 
 // Route with associated waypoints and tags
@@ -617,6 +620,8 @@ export interface Waypoint {
   show_label: boolean;
   hidden: boolean;
   created_at: string | null;
+  uuid?: string | null;  // Stable id shared across devices and exports
+  updated_at?: string | null;
 }
 
 // Edit state for route management
@@ -711,9 +716,55 @@ export interface GpxImportResult {
   routes_imported: number;
   waypoints_imported: number;
   tracks_imported: number;
+  // Items whose uuid was already known and were updated in place
+  routes_updated?: number;
+  waypoints_updated?: number;
+  tracks_updated?: number;
   errors: string[];
 }
 
+// Datum of positions in an imported waypoint list; shifted to WGS84 on import
+export type CoordinateDatum = 'wgs84' | 'nzgd49' | 'agd66' | 'ed50' | 'nad27' | 'osgb36' | 'tokyo';
+
+// Zero-based column indexes for a CSV or spreadsheet import
+export interface CsvColumnMapping {
+  name: number | null;
+  lat: number | null;
+  lon: number | null;
+  // Latitude and longitude in one column
+  position: number | null;
+  description: number | null;
+  symbol: number | null;
+}
+
+// Anything left out is detected from the file
+export interface CsvImportOptions {
+  delimiter?: string | null;
+  has_header?: boolean | null;
+  mapping?: CsvColumnMapping | null;
+  datum?: CoordinateDatum;
+  build_route?: boolean;
+  route_name?: string | null;
+}
+
+export interface CsvPreviewRow {
+  row: number;
+  name: string | null;
+  lat: number | null;
+  lon: number | null;
+  description: string | null;
+  symbol: string | null;
+  error: string | null;
+}
+
+export interface CsvPreview {
+  delimiter: string | null;
+  has_header: boolean;
+  columns: string[];
+  mapping: CsvColumnMapping;
+  rows: CsvPreviewRow[];
+}
+
 // Route summary for sharing
 export interface RouteSummary {
   name: string;
@@ -745,6 +796,8 @@ export interface Track {
   point_count: number;
   hidden: boolean;
   created_at: string | null;
+  uuid?: string | null;
+  updated_at?: string | null;
 }
 
 // Track point (position in a recorded track)
@@ -753,11 +806,14 @@ export interface TrackPoint {
   track_id: number;
   lat: number;
   lon: number;
-  timestamp: string;
+  timestamp: string | null; // null for imported points without a time
   sequence: number;
   heading: number | null;
   cog: number | null;
   sog: number | null;
+  segment?: number; // Points in different segments are not joined
+  depth_m?: number | null;
+  temperature_c?: number | null;
 }
 
 // Track with all its points